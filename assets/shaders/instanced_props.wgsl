@@ -0,0 +1,40 @@
+// Draws the instances of a scattered prop, only the mesh positions are
+// used since the meshes of each prop may have any set of attributes
+#import bevy_pbr::mesh_view_bindings::view
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+
+    @location(8) i_position_scale: vec4<f32>,
+    @location(9) i_rotation: vec4<f32>,
+    @location(10) i_color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+// Rotates a vector by a unit quaternion
+fn rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    let t = 2.0 * cross(q.xyz, v);
+    return v + q.w * t + cross(q.xyz, t);
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let local = rotate(vertex.i_rotation, vertex.position * vertex.i_position_scale.w);
+    let world = local + vertex.i_position_scale.xyz;
+
+    var out: VertexOutput;
+    out.clip_position = view.view_proj * vec4<f32>(world, 1.0);
+    // Darken lower vertices so the shape of the props reads without lighting
+    let shade = clamp(0.6 + vertex.position.y * 0.4, 0.4, 1.0);
+    out.color = vec4<f32>(vertex.i_color.rgb * shade, vertex.i_color.a);
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
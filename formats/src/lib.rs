@@ -0,0 +1,9 @@
+//! Format code shared by the viewer and repack. The records of the files
+//! live here in the 32-bit layout they're stored in, the viewer reads them
+//! through binrw and repack reads them through its views. Repack also keeps
+//! host structures for loading files in place on 32-bit hosts
+
+pub mod mesh;
+pub mod platform;
+pub mod texture;
+pub mod types;
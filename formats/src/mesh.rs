@@ -0,0 +1,340 @@
+//! Platform independent portion of the compiled meshes as stored in the
+//! files. Pointers are stored as file offsets and every structure uses the
+//! 32-bit layout of the files, the records are read in the byte order of
+//! the platform the file was compiled for
+//!
+//! The platform specific mesh data pointed to by [FMesh::mesh_is] and
+//! [FMeshMaterial::platform_data] differs between platforms so it's left to
+//! the crate reading it
+
+use binrw::BinRead;
+use bitflags::bitflags;
+
+use crate::types::{
+    FixedString, PtrOffset, RawColorMotif, RawColorRGB, RawMatrix4x3f, RawSphere, RawVec2f,
+    RawVec3f,
+};
+
+pub const FDATA_MESH_NAME_LENGTH: usize = 16;
+pub const FDATA_MAX_LOD_MESH_COUNT: usize = 8;
+pub const FDATA_VW_COUNT_PER_VTX: usize = 4;
+pub const FDATA_BONE_NAME_LENGTH: usize = 32;
+pub const FLIGHT_NAME_LENGTH: usize = 16;
+pub const FLIGHT_TEXTURE_NAME_LENGTH: usize = 16;
+
+///  FMesh_t - This is the base struct that holds the mesh geometry
+#[derive(Debug, BinRead, Clone, Copy)]
+pub struct FMesh {
+    /// ASCIIZ name of this mesh
+    pub name: FixedString<FDATA_MESH_NAME_LENGTH>,
+    // Bounds the mesh in model space (might not be valid for skinned models)
+    pub bound_sphere: RawSphere,
+    pub bound_box_min: RawVec3f,
+    pub bound_box_max: RawVec3f,
+
+    pub flags: u16,
+    /// Or'ing of all MasterCollMasks in the collision trees
+    pub mesh_coll_mask: u16,
+    /// Number of non-void bones which are located at the beginning of pBoneArray
+    pub used_bone_count: u8,
+    /// The index into the FMeshBone_t array of the root bone (-1 if this mesh has no bones)
+    pub root_bone_index: i8,
+    /// Number of bones in this model (0 if none)
+    pub bone_count: u8,
+    /// Number of segments in this object
+    pub segment_count: u8,
+    /// Number of entries in pTexLayerIDArray
+    pub tex_layer_id_count: u8,
+    /// Number of entries in pTexLayerIDArray that have their FMESH_TEXLAYERIDFLAG_USE_ST_INFO flag set
+    pub tex_layer_id_count_st: u8,
+    /// Number of entries in pTexLayerIDArray that have their FMESH_TEXLAYERIDFLAG_USE_FLIP_INFO flag set
+    pub tex_layer_id_count_flip: u8,
+    /// Number of lights attached to this mesh
+    pub light_count: u8,
+    /// Number of materials in the material array
+    pub material_count: u8,
+    /// Number of elements in the collision tree array
+    pub coll_tree_count: u8,
+    /// Number of LOD meshes for this object
+    pub lod_count: u8,
+    /// Bias added to the current LOD for generating shadows
+    pub shadow_lod_bias: u8,
+
+    pub lod_distance: [f32; FDATA_MAX_LOD_MESH_COUNT],
+
+    /// Segment array (number of elements is segment_count)
+    pub segment_array: PtrOffset,
+    /// Bone array (number of elements is bone_count)
+    pub bone_array: PtrOffset,
+    /// Light array (number of elements is light_count)
+    pub light_array: PtrOffset,
+    /// Skeleton index array used by [FMeshSkeleton::child_array_start_index]
+    pub skeleton_index_array: PtrOffset,
+    /// Material array (number of elements is material_count)
+    pub material_array: PtrOffset,
+    /// Mesh collision data structures (1 per segment)
+    pub collision_tree: PtrOffset,
+    /// Texture layer ID array (number of elements is tex_layer_id_count)
+    pub tex_layer_array: PtrOffset,
+    /// Platform specific mesh data
+    pub mesh_is: PtrOffset,
+}
+
+impl FMesh {
+    /// Size of the header in the files
+    pub const SIZE: usize = 136;
+
+    pub fn lod_distances(&self) -> &[f32] {
+        let count = (self.lod_count as usize).min(FDATA_MAX_LOD_MESH_COUNT);
+        &self.lod_distance[..count]
+    }
+}
+
+#[derive(Debug, BinRead, Clone, Copy)]
+pub struct FMeshSegment {
+    /// Bounds the segment in model space
+    pub bound_sphere: RawSphere,
+    /// Number of simultaneous bone matrices used for vertices within this segment (1=segmented, but not skinned)
+    pub bone_mtx_count: u8,
+    /// Index into object instance's bone matrix palette (255=none)
+    #[br(pad_after = 3)]
+    pub bone_mtx_index: [u8; FDATA_VW_COUNT_PER_VTX],
+}
+
+impl FMeshSegment {
+    pub const SIZE: usize = 24;
+
+    /// Bone matrix indices used by the segment
+    pub fn bone_mtx_indices(&self) -> &[u8] {
+        let count = (self.bone_mtx_count as usize).min(FDATA_VW_COUNT_PER_VTX);
+        &self.bone_mtx_index[..count]
+    }
+}
+
+bitflags! {
+    #[derive(Debug, BinRead, Clone, Copy, PartialEq, Eq)]
+    #[br(map = Self::from_bits_retain)]
+    pub struct MeshBoneFlags: u8 {
+        const NONE     = 0x00;
+        const VOIDBONE = 0x01;
+        const SKINNEDBONE = 0x10;
+    }
+}
+
+#[derive(Debug, BinRead, Clone, Copy)]
+pub struct FMeshBone {
+    pub name: FixedString<FDATA_BONE_NAME_LENGTH>,
+    pub at_rest_bone_to_model: RawMatrix4x3f,
+    pub at_rest_model_to_bone: RawMatrix4x3f,
+    pub at_rest_parent_to_bone: RawMatrix4x3f,
+    pub at_rest_bone_to_parent: RawMatrix4x3f,
+    pub segmented_bound_sphere: RawSphere,
+    pub skeleton: FMeshSkeleton,
+    pub flags: MeshBoneFlags,
+    /// The bone is padded to the 16 byte alignment of its matrices
+    #[br(pad_after = 11)]
+    pub part_id: u8,
+}
+
+impl FMeshBone {
+    pub const SIZE: usize = 256;
+
+    /// Index of the parent bone (None for root bones)
+    pub fn parent_index(&self) -> Option<u8> {
+        match self.skeleton.parent_bone_index {
+            255 => None,
+            value => Some(value),
+        }
+    }
+}
+
+#[derive(Debug, BinRead, Clone, Copy)]
+pub struct FMeshSkeleton {
+    /// Bone index of this bone's parent (255 = no parent)
+    pub parent_bone_index: u8,
+    /// Number of children attached to this bone (0 = no children)
+    pub child_bone_count: u8,
+    /// Index into the array of bone indices (FMesh_t::pnSkeletonIndexArray) of where this bone's child index list begins
+    pub child_array_start_index: u8,
+}
+
+bitflags! {
+    #[derive(Debug, BinRead, Clone, Copy, PartialEq, Eq)]
+    #[br(map = Self::from_bits_retain)]
+    pub struct MeshLightFlags: u32 {
+        const NONE     = 0x00000000;
+        const ENABLE = 0x00000001;
+        const HASDIR = 0x00000002;
+        const HASPOS = 0x00000004;
+
+        const LIGHT_ATTACHED = 0x00000008;
+        const NOLIGHT_TERRAIN			= 0x00000010;
+        const DONT_LIGHT_UNATTACHED	= 0x00000020;
+
+        const PER_PIXEL				= 0x00000040;
+
+        const MESH_MUST_BE_PER_PIXEL	= 0x00000080;
+
+        const ENGINE_LIGHT			= 0x00000100;
+        const LIGHTMAP_LIGHT			= 0x00000200;
+        const UNIQUE_LIGHTMAP			= 0x00000400;
+
+        const CORONA					= 0x00000400;
+        const CORONA_PROXFADE			= 0x00000800;
+        const CORONA_ONLY				= 0x00001000;
+
+        const CAST_SHADOWS			= 0x00002000;
+
+        const CORONA_WORLDSPACE		= 0x00004000;
+
+        const GAMEPLAY_LIGHT			= 0x00008000;
+
+        const DYNAMIC_ONLY			= 0x40000000;
+        const INCLUDE					= 0x80000000;
+    }
+}
+
+/// FLightType_e
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightType {
+    Dir = 0,
+    Omni = 1,
+    Spot = 2,
+    Ambient = 3,
+}
+
+impl TryFrom<u8> for LightType {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => LightType::Dir,
+            1 => LightType::Omni,
+            2 => LightType::Spot,
+            3 => LightType::Ambient,
+            _ => return Err(value),
+        })
+    }
+}
+
+#[derive(Debug, BinRead, Clone, Copy)]
+pub struct FMeshLight {
+    /// ASCIIZ name of the light
+    pub name: FixedString<FLIGHT_NAME_LENGTH>,
+    /// Texture that is projected by this light
+    pub per_pixel_tex_name: FixedString<FLIGHT_TEXTURE_NAME_LENGTH>,
+    /// Texture used to create the corona
+    pub corona_tex_name: FixedString<FLIGHT_TEXTURE_NAME_LENGTH>,
+    pub flags: MeshLightFlags,
+
+    pub light_id: u16,
+    /// Light type (see [LightType]), kept as stored so unknown types can be reported
+    pub light_type: u8,
+    /// Index into the parent model's bone (-1 if there is no parent bone)
+    pub parent_bone_index: i8,
+
+    /// Light intensity to be multiplied by each component (0.0f to 1.0f)
+    pub intensity: f32,
+    /// Light color motif (RGBA components range from 0.0f to 1.0f). Alpha is not used.
+    pub motif: RawColorMotif,
+    /// Light position and radius in model space (ignored for directional lights)
+    pub influence: RawSphere,
+    /// Light orientation in model space (or world space if not attached to an object).  Direction (away from source) is in m_vFront (dir and spot)
+    pub orientation: RawMatrix4x3f,
+
+    /// Spotlight inner full-angle in radians
+    pub spot_inner_radians: f32,
+    /// Spotlight outer full-angle in radians
+    pub spot_outer_radians: f32,
+
+    pub corona_scale: f32,
+}
+
+impl FMeshLight {
+    pub const SIZE: usize = 156;
+}
+
+#[derive(Debug, BinRead, Clone, Copy)]
+pub struct FMeshMaterial {
+    /// Shader's lighting register array
+    pub shader_light_registers: PtrOffset,
+    /// Shader's surface register array
+    pub shader_surface_registers: PtrOffset,
+    /// Light Shader index for this material
+    pub light_shader_index: u8,
+    /// Specular Shader index for this material
+    pub specular_shader_index: u8,
+    /// Surface Shader index for this material
+    pub surface_shader_index: u16,
+    /// A mask that has bits set for each mesh part ID that uses it
+    pub part_id_mask: u32,
+    /// Platform specific data for this material
+    pub platform_data: PtrOffset,
+    /// A bit mask that identifies all of the LOD that use this material
+    pub lod_mask: u8,
+    /// 0=normal, 1=appear in front of 0, 2=appear in front of 1, etc. (negative values not allowed)
+    pub depth_bias_level: u8,
+    pub base_st_sets: u8,
+    pub light_map_st_sets: u8,
+    /// Array of texture layer indices used by this material (255=empty slot) (fill lower elements first)
+    pub tex_layer_id_index: [u8; 4],
+    /// cos of angle of affect for angular emissive or angular translucency
+    pub affect_angle: f32,
+    /// Compressed affect normal used for determining material angle to camera (mult by 1/64)
+    pub compressed_affect_normal: [i8; 3],
+    /// Bone ID used to transform the affect angle
+    pub affect_bone_id: i8,
+    /// The radius of the material verts from the average vert pos as a fraction of the mesh bounding sphere radius
+    #[br(pad_after = 1)]
+    pub compressed_radius: u8,
+    /// Material flags (see FMESH_MTLFLAG_* for info)
+    pub mtl_flags: u16,
+    /// Key used by the engine to indicate that this material has already been submitted for drawing during the current viewport render
+    pub draw_key: u32,
+    /// Tint to be applied to the material
+    pub material_tint: RawColorRGB,
+    /// Average of the position of all verts using this material
+    pub average_vert_pos: RawVec3f,
+    /// Hash key used in display list rendering (only valid in game)
+    pub dl_hash_key: u32,
+}
+
+impl FMeshMaterial {
+    pub const SIZE: usize = 72;
+
+    /// Decodes the sphere bounding the verts of this material in model
+    /// space, the radius is stored relative to the mesh bounding sphere
+    pub fn bound_sphere(&self, mesh_radius: f32) -> RawSphere {
+        RawSphere {
+            radius: self.compressed_radius as f32 * (1.0 / 255.0) * mesh_radius,
+            position: self.average_vert_pos,
+        }
+    }
+
+    /// Texture layer indices of the filled slots
+    pub fn tex_layer_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.tex_layer_id_index
+            .iter()
+            // 255 marks an empty slot
+            .filter(|index| **index != 255)
+            .map(|index| *index as usize)
+    }
+}
+
+#[derive(Debug, BinRead, Clone, Copy)]
+pub struct FMeshTexLayerID {
+    pub tex_layer_id: u8,
+    pub flags: u8,
+    pub flip_page_count: u8,
+    pub frames_per_flip: u8,
+    /// Flip palette (array of CFTexInst offsets). Number of palette entries is flip_page_count
+    pub flip_palette: PtrOffset,
+    pub scroll_st_per_second: RawVec2f,
+    pub uv_degree_rotation_per_second: f32,
+    #[br(pad_after = 2)]
+    pub compressed_uv_rot_anchor: [u8; 2],
+}
+
+impl FMeshTexLayerID {
+    pub const SIZE: usize = 24;
+}
@@ -0,0 +1,89 @@
+use std::{fmt::Display, path::Path};
+
+use binrw::Endian;
+
+/// Platform that a compiled game asset was built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Platform {
+    GameCube,
+    Xbox,
+    Pc,
+    Ps2,
+}
+
+impl Platform {
+    /// All the known platforms
+    pub const ALL: [Platform; 4] = [
+        Platform::GameCube,
+        Platform::Xbox,
+        Platform::Pc,
+        Platform::Ps2,
+    ];
+
+    /// File name prefix used by assets compiled for this platform
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Platform::GameCube => "gc",
+            Platform::Xbox => "xb",
+            Platform::Pc => "pc",
+            Platform::Ps2 => "ps",
+        }
+    }
+
+    /// Name of the platform as written in listings and configs
+    pub fn name(&self) -> &'static str {
+        match self {
+            Platform::GameCube => "gamecube",
+            Platform::Xbox => "xbox",
+            Platform::Pc => "pc",
+            Platform::Ps2 => "ps2",
+        }
+    }
+
+    /// Platform with the provided [Platform::name]
+    pub fn from_name(name: &str) -> Option<Platform> {
+        Self::ALL
+            .into_iter()
+            .find(|platform| platform.name() == name)
+    }
+
+    /// Byte order of the data stored in assets for this platform
+    pub fn endian(&self) -> Endian {
+        match self {
+            Platform::GameCube => Endian::Big,
+            Platform::Xbox | Platform::Pc | Platform::Ps2 => Endian::Little,
+        }
+    }
+
+    /// Whether the platform specific mesh data uses the DirectX layout
+    pub fn is_dx(&self) -> bool {
+        matches!(self, Platform::Xbox | Platform::Pc)
+    }
+
+    /// Whether the platform specific mesh data uses the PS2 VIF packet layout
+    pub fn is_ps2(&self) -> bool {
+        matches!(self, Platform::Ps2)
+    }
+
+    /// Detects the platform from the prefix of the provided file name,
+    /// returns the platform and the remaining platform independent name
+    pub fn from_file_name(name: &str) -> Option<(Platform, &str)> {
+        Self::ALL.into_iter().find_map(|platform| {
+            name.strip_prefix(platform.prefix())
+                .map(|remaining| (platform, remaining))
+        })
+    }
+
+    /// Detects the platform from the file name of the provided path
+    pub fn from_path(path: &Path) -> Option<(Platform, String)> {
+        let file_name = path.file_stem()?.to_str()?;
+        let (platform, remaining) = Self::from_file_name(file_name)?;
+        Some((platform, remaining.to_string()))
+    }
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.prefix())
+    }
+}
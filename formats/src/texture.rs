@@ -0,0 +1,67 @@
+//! Texture records referenced by the mesh texture layers, as with the mesh
+//! records pointers are file offsets in the 32-bit layout of the files. The
+//! texel data pointed to by [FTexDef::tex_data] is platform specific
+
+use binrw::BinRead;
+
+use crate::types::{FixedString, PtrOffset};
+
+pub const FDATA_TEXNAME_LENGTH: usize = 16;
+
+#[derive(Debug, BinRead, Clone, Copy)]
+pub struct CFTexInst {
+    /// TexDef to use
+    pub tex_def: PtrOffset,
+    /// Double buffer texture data for RenderTargets
+    pub tex_buffer: [PtrOffset; 2],
+    #[br(pad_after = 3)]
+    pub buffer_index: u8,
+    /// See FTEX_INSTFLAG_* for info
+    pub flags: u32,
+    /// 0.0f=normal, -1=bias by one smaller level, +1=bias by one larger level, etc.
+    pub mipmap_bias: f32,
+}
+
+impl CFTexInst {
+    pub const SIZE: usize = 24;
+}
+
+#[derive(Debug, BinRead, Clone, Copy)]
+pub struct FTexInfo {
+    /// ASCIIZ name of texture
+    pub name: FixedString<FDATA_TEXNAME_LENGTH>,
+    /// User-defined data
+    pub user_data: PtrOffset,
+    /// Texel format (See FTexFmt_e)
+    pub tex_fmt: u8,
+    /// Palette format (See FTexPalFmt_e)
+    pub pal_fmt: u8,
+    /// See FTEX_FLAG_* for info
+    pub flags: u8,
+    /// Number of LODs. 1=not mipmapped. >1 for mipmapped images
+    pub lod_count: u8,
+    /// For render targets, this is the number of bits in the stencil buffer
+    pub render_target_stencil_bit_count: u8,
+    /// For render targets, this is the number of bits in the depth buffer
+    #[br(pad_after = 2)]
+    pub render_target_depth_bit_count: u8,
+    /// Number of texels across of largest LOD image (always a power of 2)
+    pub texels_across: u16,
+    ///  Number of texels down of largest LOD image (always a power of 2)
+    pub texels_down: u16,
+}
+
+impl FTexInfo {
+    pub const SIZE: usize = 32;
+}
+
+#[derive(Debug, BinRead, Clone, Copy)]
+pub struct FTexDef {
+    pub tex_info: FTexInfo,
+    /// Platform specific texture data
+    pub tex_data: PtrOffset,
+}
+
+impl FTexDef {
+    pub const SIZE: usize = 36;
+}
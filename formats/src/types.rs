@@ -0,0 +1,246 @@
+use std::{
+    ffi::CStr,
+    fmt::{Debug, Display},
+};
+
+use binrw::BinRead;
+
+/// Null terminated string created from a fixed length
+/// chunk of bytes
+#[derive(BinRead, Clone, Copy)]
+#[repr(C)]
+pub struct FixedString<const LENGTH: usize> {
+    bytes: [u8; LENGTH],
+}
+
+impl<const LENGTH: usize> Default for FixedString<LENGTH> {
+    fn default() -> Self {
+        Self {
+            bytes: [0u8; LENGTH],
+        }
+    }
+}
+
+impl<const LENGTH: usize> FixedString<LENGTH> {
+    /// Creates a fixed string from the provided value, returns None if
+    /// the value doesn't fit along with its null terminator
+    pub fn new(value: &str) -> Option<Self> {
+        if value.len() >= LENGTH {
+            return None;
+        }
+
+        let mut bytes = [0u8; LENGTH];
+        bytes[..value.len()].copy_from_slice(value.as_bytes());
+        Some(Self { bytes })
+    }
+
+    /// Whether the bytes contain the null terminator, strings written by
+    /// hand may fill the whole length
+    pub fn is_terminated(&self) -> bool {
+        self.bytes.contains(&0)
+    }
+
+    /// The string up to its null terminator, None when the bytes fill the
+    /// whole length without one
+    pub fn as_cstr(&self) -> Option<&CStr> {
+        CStr::from_bytes_until_nul(&self.bytes).ok()
+    }
+
+    /// Bytes up to the null terminator, or all of them when unterminated
+    pub fn as_bytes(&self) -> &[u8] {
+        let end = self
+            .bytes
+            .iter()
+            .position(|value| *value == 0)
+            .unwrap_or(LENGTH);
+        &self.bytes[..end]
+    }
+
+    pub fn as_string(&self) -> String {
+        String::from_utf8_lossy(self.as_bytes()).to_string()
+    }
+}
+
+impl<const LENGTH: usize> Debug for FixedString<LENGTH> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = self.as_string();
+        Debug::fmt(&value, f)
+    }
+}
+
+impl<const LENGTH: usize> Display for FixedString<LENGTH> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = self.as_string();
+        Display::fmt(&value, f)
+    }
+}
+
+// Offset within the file that something can be found at
+#[derive(Debug, BinRead, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PtrOffset(pub u32);
+
+impl PtrOffset {
+    /// Offset within the file, None for null offsets
+    pub fn offset(&self) -> Option<u32> {
+        (self.0 != 0).then_some(self.0)
+    }
+}
+
+// CFMtx43
+#[derive(Debug, BinRead, Default, Clone, Copy, PartialEq)]
+pub struct RawMatrix4x3f {
+    pub matrix: [[f32; 3]; 4],
+}
+
+// CFMtx44
+#[derive(Debug, BinRead, Default, Clone, Copy, PartialEq)]
+pub struct RawMatrix4x4f {
+    pub matrix: [[f32; 4]; 4],
+}
+
+#[derive(Debug, BinRead, Default, Clone, Copy, PartialEq)]
+pub struct RawVec3f {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+#[derive(Debug, BinRead, Default, Clone, Copy, PartialEq)]
+pub struct RawVec2f {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, BinRead, Default, Clone, Copy, PartialEq)]
+pub struct RawSphere {
+    pub radius: f32,
+    pub position: RawVec3f,
+}
+
+#[derive(Debug, BinRead, Default, Clone, Copy, PartialEq)]
+pub struct RawColorRGBA {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+    pub alpha: f32,
+}
+
+#[derive(Debug, BinRead, Default, Clone, Copy, PartialEq)]
+pub struct RawColorRGB {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+}
+
+#[derive(Debug, BinRead, Default, Clone, Copy, PartialEq)]
+pub struct RawColorMotif {
+    pub color: RawColorRGBA,
+    pub modif_index: u32,
+}
+
+/// Conversions to the glam math types used by bevy
+#[cfg(feature = "glam")]
+mod glam_interop {
+    use glam::{Affine3A, Mat4, Vec2, Vec3, Vec3A};
+
+    use super::{RawMatrix4x3f, RawMatrix4x4f, RawVec2f, RawVec3f};
+
+    impl RawMatrix4x3f {
+        /// Right, up, front and position rows of the matrix
+        fn rows(&self) -> [Vec3; 4] {
+            self.matrix.map(Vec3::from_array)
+        }
+    }
+
+    /// Converts to a column major matrix for transforming column vectors.
+    /// The engine stores the right, up, front and position rows and
+    /// transforms row vectors (v * M), so each row becomes a column
+    impl From<&RawMatrix4x3f> for Mat4 {
+        fn from(value: &RawMatrix4x3f) -> Self {
+            let [right, up, front, position] = value.rows();
+            Mat4::from_cols(
+                right.extend(0.0),
+                up.extend(0.0),
+                front.extend(0.0),
+                position.extend(1.0),
+            )
+        }
+    }
+
+    /// Same row to column convention as the [Mat4] conversion
+    impl From<&RawMatrix4x3f> for Affine3A {
+        fn from(value: &RawMatrix4x3f) -> Self {
+            let [right, up, front, position] = value.rows().map(Vec3A::from);
+            Affine3A::from_cols(right, up, front, position)
+        }
+    }
+
+    /// Rows become columns the same as [RawMatrix4x3f]
+    impl From<&RawMatrix4x4f> for Mat4 {
+        fn from(value: &RawMatrix4x4f) -> Self {
+            Mat4::from_cols_array_2d(&value.matrix)
+        }
+    }
+
+    impl From<&RawVec3f> for Vec3 {
+        fn from(value: &RawVec3f) -> Self {
+            Vec3::new(value.x, value.y, value.z)
+        }
+    }
+
+    impl From<Vec3> for RawVec3f {
+        fn from(value: Vec3) -> Self {
+            RawVec3f {
+                x: value.x,
+                y: value.y,
+                z: value.z,
+            }
+        }
+    }
+
+    impl From<&RawVec2f> for Vec2 {
+        fn from(value: &RawVec2f) -> Self {
+            Vec2::new(value.x, value.y)
+        }
+    }
+}
+
+/// Conversions to the interchange types of [mint] for use with math
+/// libraries other than glam
+#[cfg(feature = "mint")]
+mod mint_interop {
+    use glam::Mat4;
+
+    use super::{RawMatrix4x3f, RawMatrix4x4f, RawVec2f, RawVec3f};
+
+    impl From<&RawVec3f> for mint::Vector3<f32> {
+        fn from(value: &RawVec3f) -> Self {
+            mint::Vector3 {
+                x: value.x,
+                y: value.y,
+                z: value.z,
+            }
+        }
+    }
+
+    impl From<&RawVec2f> for mint::Vector2<f32> {
+        fn from(value: &RawVec2f) -> Self {
+            mint::Vector2 {
+                x: value.x,
+                y: value.y,
+            }
+        }
+    }
+
+    impl From<&RawMatrix4x3f> for mint::ColumnMatrix4<f32> {
+        fn from(value: &RawMatrix4x3f) -> Self {
+            Mat4::from(value).to_cols_array_2d().into()
+        }
+    }
+
+    impl From<&RawMatrix4x4f> for mint::ColumnMatrix4<f32> {
+        fn from(value: &RawMatrix4x4f) -> Self {
+            Mat4::from(value).to_cols_array_2d().into()
+        }
+    }
+}
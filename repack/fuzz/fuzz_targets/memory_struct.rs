@@ -0,0 +1,23 @@
+//! Loads arbitrary bytes in place in both byte orders, the offsets are
+//! validated first so files that would be fixed up into pointers outside
+//! the buffer have to be rejected before anything is dereferenced
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use repack::st::{try_load_memory_struct, FMesh, SourceEndian};
+
+fuzz_target!(|data: &[u8]| {
+    for endian in [SourceEndian::Little, SourceEndian::Big] {
+        let Ok(mesh) = (unsafe { try_load_memory_struct::<FMesh>(data.into(), endian) }) else {
+            continue;
+        };
+
+        if let Some(dx_mesh) = mesh.impl_specific_mut() {
+            for buffer in dx_mesh.vertex_buffers_mut().unwrap_or_default() {
+                let _ = buffer.positions();
+            }
+            let _ = dx_mesh.index_buffers();
+        }
+    }
+});
@@ -0,0 +1,33 @@
+//! Walks arbitrary bytes through the offset views in both byte orders and
+//! collects their geometry, every read is checked against the buffer so
+//! nothing here may panic
+
+#![no_main]
+
+use binrw::Endian;
+use libfuzzer_sys::fuzz_target;
+use repack::{
+    export::ExportGeometry, layout::FileLayout, lint::lint_mesh, report::MeshReport, view::MeshView,
+};
+
+fuzz_target!(|data: &[u8]| {
+    for endian in [Endian::Little, Endian::Big] {
+        let Ok(mesh) = MeshView::new(data, endian) else {
+            continue;
+        };
+
+        let _ = (mesh.name(), mesh.bound_sphere(), mesh.flags());
+        let _ = mesh.lod_distances();
+        if let Ok(Some(bones)) = mesh.bones() {
+            for bone in bones.iter() {
+                let _ = (bone.name(), bone.parent_index(), bone.part_id());
+            }
+        }
+
+        let _ = lint_mesh(&mesh);
+        let _ = FileLayout::from_view(&mesh);
+        let _ = MeshReport::from_view(&mesh);
+        let _ = ExportGeometry::from_dx_view(&mesh);
+        let _ = ExportGeometry::from_ps2(&mesh);
+    }
+});
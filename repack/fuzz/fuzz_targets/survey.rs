@@ -0,0 +1,14 @@
+//! Reads arbitrary bytes as a mesh of every platform through the binrw
+//! readers, malformed files must be reported as errors or warnings
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use repack::{diff::MeshSummary, platform::Platform, survey::MeshSurvey};
+
+fuzz_target!(|data: &[u8]| {
+    for platform in Platform::ALL {
+        let _ = MeshSurvey::from_buffer(platform, data);
+        let _ = MeshSummary::from_buffer(platform, data.to_vec());
+    }
+});
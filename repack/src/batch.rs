@@ -0,0 +1,388 @@
+//! Batch edits of material parameters across many assets, materials matching
+//! a predicate are patched and the repacked files are written to a mod
+//! directory along with a log of every change made
+
+use std::{
+    fs::read_dir,
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+use crate::{
+    cancel::{CancelToken, Cancelled, WrittenFiles},
+    model::MeshModel,
+    offsets::ValidationError,
+    patch::{apply_patches, PatchError},
+    platform::Platform,
+    provenance::Provenance,
+    view::{MaterialView, MeshView},
+};
+
+#[derive(Debug, Error)]
+pub enum BatchError {
+    #[error("invalid predicate {0:?}, expected all, texture=NAME or flag=MASK")]
+    InvalidPredicate(String),
+    #[error("invalid edit {0:?}, expected tint=R,G,B, flags+=MASK or flags-=MASK")]
+    InvalidEdit(String),
+    #[error(transparent)]
+    Patch(#[from] PatchError),
+    #[error(transparent)]
+    Cancelled(#[from] Cancelled),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Predicate selecting which materials an edit applies to
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaterialPredicate {
+    All,
+    /// Materials using a texture with the provided name (ignoring case)
+    Texture(String),
+    /// Materials with any of the bits in the mask set in their flags
+    Flag(u16),
+}
+
+/// Parses a mask written in decimal or as hex with a 0x prefix
+fn parse_mask(value: &str) -> Option<u16> {
+    match value.strip_prefix("0x") {
+        Some(value) => u16::from_str_radix(value, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+impl FromStr for MaterialPredicate {
+    type Err = BatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "all" {
+            return Ok(MaterialPredicate::All);
+        }
+
+        match s.split_once('=') {
+            Some(("texture", name)) if !name.is_empty() => {
+                Ok(MaterialPredicate::Texture(name.to_string()))
+            }
+            Some(("flag", mask)) => parse_mask(mask)
+                .map(MaterialPredicate::Flag)
+                .ok_or_else(|| BatchError::InvalidPredicate(s.to_string())),
+            _ => Err(BatchError::InvalidPredicate(s.to_string())),
+        }
+    }
+}
+
+impl MaterialPredicate {
+    pub fn matches(
+        &self,
+        mesh: &MeshView,
+        material: &MaterialView,
+    ) -> Result<bool, ValidationError> {
+        Ok(match self {
+            MaterialPredicate::All => true,
+            MaterialPredicate::Texture(name) => mesh
+                .material_textures(material)?
+                .iter()
+                .any(|value| value.eq_ignore_ascii_case(name)),
+            MaterialPredicate::Flag(mask) => material.flags() & mask != 0,
+        })
+    }
+}
+
+/// Parameter change applied to each matching material
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaterialEdit {
+    SetTint([f32; 3]),
+    /// Sets the bits in the mask
+    SetFlags(u16),
+    /// Clears the bits in the mask
+    ClearFlags(u16),
+}
+
+impl FromStr for MaterialEdit {
+    type Err = BatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BatchError::InvalidEdit(s.to_string());
+
+        if let Some(mask) = s.strip_prefix("flags+=") {
+            return parse_mask(mask)
+                .map(MaterialEdit::SetFlags)
+                .ok_or_else(invalid);
+        }
+
+        if let Some(mask) = s.strip_prefix("flags-=") {
+            return parse_mask(mask)
+                .map(MaterialEdit::ClearFlags)
+                .ok_or_else(invalid);
+        }
+
+        let tint = s.strip_prefix("tint=").ok_or_else(invalid)?;
+        let values: Vec<f32> = tint
+            .split(',')
+            .map(|value| value.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+        let tint: [f32; 3] = values.try_into().map_err(|_| invalid())?;
+
+        Ok(MaterialEdit::SetTint(tint))
+    }
+}
+
+impl MaterialEdit {
+    /// Applies the edit to a material of the model, returns a
+    /// description of the change or None if nothing changed
+    fn apply(&self, model: &mut MeshModel, material: usize) -> Result<Option<String>, PatchError> {
+        let current = model
+            .materials()
+            .get(material)
+            .ok_or(PatchError::MaterialOutOfRange(material))?;
+
+        match self {
+            MaterialEdit::SetTint(tint) => {
+                if current.tint == *tint {
+                    return Ok(None);
+                }
+
+                let change = format!("tint {:?} -> {:?}", current.tint, tint);
+                model.set_material_tint(material, *tint)?;
+                Ok(Some(change))
+            }
+            MaterialEdit::SetFlags(mask) | MaterialEdit::ClearFlags(mask) => {
+                let flags = match self {
+                    MaterialEdit::SetFlags(_) => current.flags | mask,
+                    _ => current.flags & !mask,
+                };
+                if flags == current.flags {
+                    return Ok(None);
+                }
+
+                let change = format!("flags {:#06x} -> {:#06x}", current.flags, flags);
+                model.set_material_flags(material, flags)?;
+                Ok(Some(change))
+            }
+        }
+    }
+}
+
+/// Change made to a single material
+#[derive(Debug, Clone)]
+pub struct ChangeEntry {
+    /// Name of the file the material is in
+    pub file: String,
+    pub material: usize,
+    pub change: String,
+}
+
+/// Result of a batch edit across a directory
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub changes: Vec<ChangeEntry>,
+    /// Files that couldn't be edited along with the reason
+    pub skipped: Vec<(String, String)>,
+    /// Number of files written to the output directory
+    pub written: usize,
+}
+
+/// Applies the edit to the matching materials of a DirectX mesh, the bytes
+/// are patched in place. Returns the index and description of each change
+pub fn edit_buffer(
+    bytes: &mut [u8],
+    platform: Platform,
+    predicate: &MaterialPredicate,
+    edit: &MaterialEdit,
+) -> Result<Vec<(usize, String)>, PatchError> {
+    let mesh = MeshView::new(bytes, platform.endian())?;
+    let mut model = MeshModel::from_view(&mesh)?;
+    let mut changes = Vec::new();
+
+    for (index, material) in mesh
+        .materials()?
+        .iter()
+        .flat_map(|array| array.iter())
+        .enumerate()
+    {
+        if !predicate.matches(&mesh, &material)? {
+            continue;
+        }
+
+        if let Some(change) = edit.apply(&mut model, index)? {
+            changes.push((index, change));
+        }
+    }
+
+    apply_patches(model.patches(), bytes, platform.endian())?;
+    Ok(changes)
+}
+
+/// Applies the edit to every .ape file in the input directory, files with
+/// changes are written to the output directory under the same name. The
+/// token is checked between files, when cancelled the files written so far
+/// are removed
+pub fn batch_edit(
+    input: &Path,
+    output: &Path,
+    predicate: &MaterialPredicate,
+    edit: &MaterialEdit,
+    cancel: &CancelToken,
+) -> Result<BatchReport, BatchError> {
+    let mut report = BatchReport::default();
+    let mut written = WrittenFiles::default();
+    std::fs::create_dir_all(output)?;
+
+    let mut paths = Vec::new();
+    for entry in read_dir(input)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|value| value == "ape") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    for path in paths {
+        if let Err(err) = cancel.check() {
+            written.remove_all()?;
+            return Err(err.into());
+        }
+
+        let Some(file_name) = path.file_name() else {
+            continue;
+        };
+        let file = file_name.to_string_lossy().to_string();
+
+        let platform = match Platform::from_path(&path) {
+            Some((platform, _)) if platform.is_dx() => platform,
+            Some(_) => {
+                report
+                    .skipped
+                    .push((file, "platform not supported by the loader".to_string()));
+                continue;
+            }
+            None => {
+                report.skipped.push((file, "unknown platform".to_string()));
+                continue;
+            }
+        };
+
+        let mut bytes = std::fs::read(&path)?;
+        let provenance = Provenance::default()
+            .with_source(&path, &bytes)
+            .with_setting("predicate", format!("{:?}", predicate))
+            .with_setting("edit", format!("{:?}", edit));
+        let changes = match edit_buffer(&mut bytes, platform, predicate, edit) {
+            Ok(value) => value,
+            Err(err) => {
+                report.skipped.push((file, err.to_string()));
+                continue;
+            }
+        };
+
+        if changes.is_empty() {
+            continue;
+        }
+
+        let out = output.join(file_name);
+        written.push(&out);
+        std::fs::write(&out, &bytes)?;
+        provenance.write_sidecar(&out)?;
+        report.written += 1;
+        report
+            .changes
+            .extend(changes.into_iter().map(|(material, change)| ChangeEntry {
+                file: file.clone(),
+                material,
+                change,
+            }));
+    }
+
+    Ok(report)
+}
+
+/// Writes the change log for a batch edit
+pub fn write_change_log<W: Write>(
+    out: &mut W,
+    predicate: &MaterialPredicate,
+    edit: &MaterialEdit,
+    report: &BatchReport,
+) -> io::Result<()> {
+    writeln!(out, "predicate: {:?}", predicate)?;
+    writeln!(out, "edit: {:?}", edit)?;
+    writeln!(
+        out,
+        "{} changes in {} files",
+        report.changes.len(),
+        report.written
+    )?;
+
+    for change in &report.changes {
+        writeln!(
+            out,
+            "{} material {}: {}",
+            change.file, change.material, change.change
+        )?;
+    }
+
+    for (file, reason) in &report.skipped {
+        writeln!(out, "{} skipped: {}", file, reason)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use binrw::Endian;
+
+    use crate::{
+        fixture::{triangle_mesh, FIXTURE_TINT},
+        platform::Platform,
+        view::MeshView,
+    };
+
+    use super::{edit_buffer, MaterialEdit, MaterialPredicate};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "flag=0x10".parse::<MaterialPredicate>().unwrap(),
+            MaterialPredicate::Flag(0x10)
+        );
+        assert_eq!(
+            "tint=1,0.5,0".parse::<MaterialEdit>().unwrap(),
+            MaterialEdit::SetTint([1.0, 0.5, 0.0])
+        );
+        assert!("tint=1,0.5".parse::<MaterialEdit>().is_err());
+        assert!("colour=red".parse::<MaterialPredicate>().is_err());
+    }
+
+    #[test]
+    fn test_edit_buffer() {
+        let mut bytes = triangle_mesh();
+
+        // Fixture material has no flags so nothing matches
+        let changes = edit_buffer(
+            &mut bytes,
+            Platform::Xbox,
+            &MaterialPredicate::Flag(0x1),
+            &MaterialEdit::SetTint([0.0; 3]),
+        )
+        .unwrap();
+        assert!(changes.is_empty());
+        assert_eq!(bytes, triangle_mesh());
+
+        let changes = edit_buffer(
+            &mut bytes,
+            Platform::Xbox,
+            &MaterialPredicate::All,
+            &MaterialEdit::SetFlags(0x4),
+        )
+        .unwrap();
+        assert_eq!(changes.len(), 1);
+
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        let material = mesh.materials().unwrap().unwrap().get(0).unwrap();
+        assert_eq!(material.flags(), 0x4);
+        assert_eq!(material.tint()[0], FIXTURE_TINT[0]);
+    }
+}
@@ -0,0 +1,841 @@
+//! Building .ape files from scratch. [ApeBuilder] lays out a single DirectX
+//! vertex buffer, index buffer and material along with any bones and a
+//! texture in the 32-bit layout of the files, written through the same field offsets the
+//! views read, in the byte order of the target. Tests and tooling can create
+//! meshes without the game data and read them on any host the same as files
+//! from the game
+
+use binrw::Endian;
+use openglitch_formats::{
+    mesh::{
+        FMesh, FMeshBone, FMeshMaterial, FMeshTexLayerID, FDATA_BONE_NAME_LENGTH,
+        FDATA_MESH_NAME_LENGTH,
+    },
+    texture::{CFTexInst, FDATA_TEXNAME_LENGTH},
+};
+use thiserror::Error;
+
+use crate::{platform::Platform, raw::dx::DxVertexBufferType, st::SourceEndian, view::layout};
+
+/// Bone parent index of bones without a parent
+const NO_PARENT: u8 = 255;
+
+/// Size of the N1C1T1 vertices written (position, normal, color and a
+/// single set of texture coordinates)
+const VERTEX_SIZE: usize = 36;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum BuildError {
+    #[error("{} meshes can't be built, only the DirectX layout is supported", .0.name())]
+    UnsupportedPlatform(Platform),
+    #[error("name {0:?} is too long")]
+    NameTooLong(String),
+    #[error("mesh has no vertices")]
+    NoVertices,
+    #[error("{0} vertices can't be indexed by 16 bit indices")]
+    TooManyVertices(usize),
+    #[error("{0} indices don't fit in a single index buffer")]
+    TooManyIndices(usize),
+    #[error("{0} indices don't form whole triangles")]
+    IncompleteTriangle(usize),
+    #[error("index {index} is out of bounds for {count} vertices")]
+    IndexOutOfBounds { index: u16, count: usize },
+    #[error("{0} bones is more than a mesh can hold")]
+    TooManyBones(usize),
+    #[error("bone {bone} has parent {parent}, parents must come before their children")]
+    InvalidParent { bone: usize, parent: u8 },
+}
+
+/// Vertex of the built mesh
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ApeVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    /// Normalized RGBA color
+    pub color: [f32; 4],
+    pub uv: [f32; 2],
+}
+
+impl ApeVertex {
+    /// White vertex at the position facing along +Z
+    pub fn new(position: [f32; 3]) -> Self {
+        Self {
+            position,
+            normal: [0.0, 0.0, 1.0],
+            color: [1.0; 4],
+            uv: [0.0; 2],
+        }
+    }
+
+    pub fn with_normal(mut self, normal: [f32; 3]) -> Self {
+        self.normal = normal;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_uv(mut self, uv: [f32; 2]) -> Self {
+        self.uv = uv;
+        self
+    }
+
+    /// Color packed as a D3DCOLOR (ARGB)
+    fn packed_color(&self) -> u32 {
+        let [red, green, blue, alpha] = self
+            .color
+            .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8);
+        u32::from_be_bytes([alpha, red, green, blue])
+    }
+}
+
+/// Material drawing every triangle of the built mesh
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApeMaterial {
+    pub tint: [f32; 3],
+    /// Mask of the part IDs using the material
+    pub part_id_mask: u32,
+    /// Mask of the LODs using the material
+    pub lod_mask: u8,
+    pub flags: u16,
+    /// Texture layers used by the material, 255 for empty slots. The first
+    /// slot is replaced by the layer of the texture when the mesh has one
+    pub tex_layer_id_index: [u8; 4],
+}
+
+impl Default for ApeMaterial {
+    fn default() -> Self {
+        Self {
+            tint: [1.0; 3],
+            part_id_mask: 1,
+            lod_mask: 1,
+            flags: 0,
+            tex_layer_id_index: [255; 4],
+        }
+    }
+}
+
+/// Bone of the built mesh, bones are only translated so the at rest
+/// matrices are built from the position of the bone and its parent
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApeBone {
+    pub name: String,
+    /// Index of the parent bone, parents must come before their children
+    pub parent: Option<u8>,
+    pub part_id: u8,
+    /// Position of the bone in model space
+    pub position: [f32; 3],
+}
+
+impl ApeBone {
+    pub fn new(name: &str, parent: Option<u8>) -> Self {
+        Self {
+            name: name.to_string(),
+            parent,
+            part_id: 0,
+            position: [0.0; 3],
+        }
+    }
+
+    pub fn with_part_id(mut self, part_id: u8) -> Self {
+        self.part_id = part_id;
+        self
+    }
+
+    pub fn with_position(mut self, position: [f32; 3]) -> Self {
+        self.position = position;
+        self
+    }
+}
+
+/// Texture sampled by the material of the built mesh, written as DirectX
+/// texture data with the definition embedded at its start
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApeTexture {
+    pub name: String,
+    /// Texel format (See FTexFmt_e)
+    pub tex_fmt: u8,
+    pub lod_count: u8,
+    pub width: u16,
+    pub height: u16,
+    /// Encoded texels of every LOD
+    pub image_data: Vec<u8>,
+}
+
+impl ApeTexture {
+    /// Single LOD texture of the encoded texels
+    pub fn new(name: &str, tex_fmt: u8, width: u16, height: u16, image_data: Vec<u8>) -> Self {
+        Self {
+            name: name.to_string(),
+            tex_fmt,
+            lod_count: 1,
+            width,
+            height,
+            image_data,
+        }
+    }
+
+    pub fn with_lod_count(mut self, lod_count: u8) -> Self {
+        self.lod_count = lod_count;
+        self
+    }
+}
+
+/// Builds a DirectX .ape file with a single LOD, vertex buffer, index buffer
+/// and material. The bounds, material radius and average vertex position are
+/// computed from the vertices
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApeBuilder {
+    name: String,
+    vertices: Vec<ApeVertex>,
+    indices: Vec<u16>,
+    material: ApeMaterial,
+    bones: Vec<ApeBone>,
+    texture: Option<ApeTexture>,
+    lod_distance: f32,
+    /// Radius and center overriding the computed bounding sphere
+    bound_sphere: Option<(f32, [f32; 3])>,
+}
+
+impl ApeBuilder {
+    /// Builder of a mesh drawing the vertices as a list of triangles
+    pub fn new(name: &str, vertices: Vec<ApeVertex>, indices: Vec<u16>) -> Self {
+        Self {
+            name: name.to_string(),
+            vertices,
+            indices,
+            material: ApeMaterial::default(),
+            bones: Vec::new(),
+            texture: None,
+            lod_distance: 0.0,
+            bound_sphere: None,
+        }
+    }
+
+    pub fn with_material(mut self, material: ApeMaterial) -> Self {
+        self.material = material;
+        self
+    }
+
+    pub fn with_bone(mut self, bone: ApeBone) -> Self {
+        self.bones.push(bone);
+        self
+    }
+
+    /// Texture sampled by the first texture layer slot of the material
+    pub fn with_texture(mut self, texture: ApeTexture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Distance the single LOD is used up to
+    pub fn with_lod_distance(mut self, distance: f32) -> Self {
+        self.lod_distance = distance;
+        self
+    }
+
+    /// Uses the provided bounding sphere rather than one computed from
+    /// the vertices
+    pub fn with_bound_sphere(mut self, radius: f32, center: [f32; 3]) -> Self {
+        self.bound_sphere = Some((radius, center));
+        self
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        if self.name.len() >= FDATA_MESH_NAME_LENGTH {
+            return Err(BuildError::NameTooLong(self.name.clone()));
+        }
+
+        let count = self.vertices.len();
+        if count == 0 {
+            return Err(BuildError::NoVertices);
+        }
+        // Kept below the largest index so the index range of the triangle
+        // list fits in 16 bits
+        if count > u16::MAX as usize {
+            return Err(BuildError::TooManyVertices(count));
+        }
+        if self.indices.len() > u16::MAX as usize {
+            return Err(BuildError::TooManyIndices(self.indices.len()));
+        }
+        if self.indices.len() % 3 != 0 {
+            return Err(BuildError::IncompleteTriangle(self.indices.len()));
+        }
+        if let Some(index) = self.indices.iter().find(|index| **index as usize >= count) {
+            return Err(BuildError::IndexOutOfBounds {
+                index: *index,
+                count,
+            });
+        }
+
+        // Bone indices have to fit below the missing parent marker
+        if self.bones.len() > NO_PARENT as usize {
+            return Err(BuildError::TooManyBones(self.bones.len()));
+        }
+        for (index, bone) in self.bones.iter().enumerate() {
+            if bone.name.len() >= FDATA_BONE_NAME_LENGTH {
+                return Err(BuildError::NameTooLong(bone.name.clone()));
+            }
+            if let Some(parent) = bone.parent.filter(|parent| *parent as usize >= index) {
+                return Err(BuildError::InvalidParent {
+                    bone: index,
+                    parent,
+                });
+            }
+        }
+
+        if let Some(texture) = self
+            .texture
+            .as_ref()
+            .filter(|texture| texture.name.len() >= FDATA_TEXNAME_LENGTH)
+        {
+            return Err(BuildError::NameTooLong(texture.name.clone()));
+        }
+
+        Ok(())
+    }
+
+    /// Bounding sphere of the mesh, computed as the sphere around the
+    /// center of the bounding box
+    fn bound_sphere(&self, min: [f32; 3], max: [f32; 3]) -> (f32, [f32; 3]) {
+        if let Some(sphere) = self.bound_sphere {
+            return sphere;
+        }
+
+        let center = [0, 1, 2].map(|axis| (min[axis] + max[axis]) * 0.5);
+        let radius = self
+            .vertices
+            .iter()
+            .map(|vertex| distance(vertex.position, center))
+            .fold(0.0, f32::max);
+        (radius, center)
+    }
+
+    /// Builds the file for the platform in the provided byte order, only
+    /// the DirectX platform data can be built
+    pub fn build(&self, platform: Platform, endian: SourceEndian) -> Result<Vec<u8>, BuildError> {
+        if !platform.is_dx() {
+            return Err(BuildError::UnsupportedPlatform(platform));
+        }
+        self.validate()?;
+
+        let mut file = FileWriter {
+            bytes: Vec::new(),
+            endian: endian.into(),
+        };
+
+        // Header is reserved first so it sits at the start of the file
+        file.reserve(FMesh::SIZE, 4);
+
+        let (bone_offset, skeleton_offset) = self.write_bones(&mut file);
+        let tex_layer_offset = self.write_texture(&mut file);
+
+        // Vertex data
+        let vertex_offset = file.reserve(self.vertices.len() * VERTEX_SIZE, 4);
+        for (index, vertex) in self.vertices.iter().enumerate() {
+            let offset = vertex_offset + index * VERTEX_SIZE;
+            file.put_f32s(offset, &vertex.position);
+            file.put_f32s(offset + 12, &vertex.normal);
+            file.put_u32(offset + 24, vertex.packed_color());
+            file.put_f32s(offset + 28, &vertex.uv);
+        }
+
+        let vertex_buffer = file.reserve(layout::VERTEX_BUFFER_SIZE, 4);
+        file.put_u32(
+            vertex_buffer + layout::VERTEX_BUFFER_VERTEX_COUNT,
+            self.vertices.len() as u32,
+        );
+        file.put_u16(
+            vertex_buffer + layout::VERTEX_BUFFER_BYTES_PER_VERTEX,
+            VERTEX_SIZE as u16,
+        );
+        file.bytes[vertex_buffer + layout::VERTEX_BUFFER_INFO_INDEX] =
+            DxVertexBufferType::N1C1T1 as u8;
+        file.put_u32(
+            vertex_buffer + layout::VERTEX_BUFFER_DATA,
+            vertex_offset as u32,
+        );
+
+        // Collision copy of the positions
+        let collision_offset = file.reserve(self.vertices.len() * 12, 4);
+        for (index, vertex) in self.vertices.iter().enumerate() {
+            file.put_f32s(collision_offset + index * 12, &vertex.position);
+        }
+        let collision_buffers = file.reserve(4, 4);
+        file.put_u32(collision_buffers, collision_offset as u32);
+
+        // Index data
+        let index_offset = file.reserve(self.indices.len() * 2, 4);
+        for (index, value) in self.indices.iter().enumerate() {
+            file.put_u16(index_offset + index * 2, *value);
+        }
+        let index_counts = file.reserve(2, 4);
+        file.put_u16(index_counts, self.indices.len() as u16);
+        let index_buffers = file.reserve(4, 4);
+        file.put_u32(index_buffers, index_offset as u32);
+
+        let dx_mesh = file.reserve(layout::DX_MESH_SIZE, 4);
+        file.bytes[dx_mesh + layout::DX_MESH_VERTEX_BUFFER_COUNT] = 1;
+        file.bytes[dx_mesh + layout::DX_MESH_INDEX_BUFFER_COUNT] = 1;
+        file.put_u32(
+            dx_mesh + layout::DX_MESH_VERTEX_BUFFERS,
+            vertex_buffer as u32,
+        );
+        file.put_u32(
+            dx_mesh + layout::DX_MESH_COLL_VERTEX_BUFFER,
+            collision_buffers as u32,
+        );
+        file.put_u32(
+            dx_mesh + layout::DX_MESH_INDICIES_COUNTS,
+            index_counts as u32,
+        );
+        file.put_u32(dx_mesh + layout::DX_MESH_INDEX_BUFFER, index_buffers as u32);
+
+        // Material drawing the triangles as a single list
+        let cluster = file.reserve(layout::DX_CLUSTER_SIZE, 4);
+        file.put_u16(
+            cluster + layout::DX_CLUSTER_TRI_COUNT,
+            (self.indices.len() / 3) as u16,
+        );
+        if let (Some(min), Some(max)) = (self.indices.iter().min(), self.indices.iter().max()) {
+            file.put_u16(cluster + layout::DX_CLUSTER_TRI_VTX_INDEX_MIN, *min);
+            file.put_u16(
+                cluster + layout::DX_CLUSTER_TRI_VTX_INDEX_RANGE,
+                max - min + 1,
+            );
+        }
+
+        let platform_material = file.reserve(layout::DX_MATERIAL_SIZE, 4);
+        file.put_u32(
+            platform_material + layout::DX_MATERIAL_CLUSTER,
+            cluster as u32,
+        );
+        file.put_u32(platform_material + layout::DX_MATERIAL_CLUSTER_COUNT, 1);
+
+        let (min, max) = self.bounds();
+        let (radius, center) = self.bound_sphere(min, max);
+
+        let material = file.reserve(FMeshMaterial::SIZE, 4);
+        file.put_u32(
+            material + layout::MATERIAL_PLATFORM_DATA,
+            platform_material as u32,
+        );
+        file.put_u32(
+            material + layout::MATERIAL_PART_ID_MASK,
+            self.material.part_id_mask,
+        );
+        file.bytes[material + layout::MATERIAL_LOD_MASK] = self.material.lod_mask;
+        file.put_u16(material + layout::MATERIAL_FLAGS, self.material.flags);
+        let mut tex_layer_id_index = self.material.tex_layer_id_index;
+        if self.texture.is_some() {
+            tex_layer_id_index[0] = 0;
+        }
+        file.put(
+            material + layout::MATERIAL_TEX_LAYER_ID_INDEX,
+            &tex_layer_id_index,
+        );
+        file.put_f32s(material + layout::MATERIAL_TINT, &self.material.tint);
+        // Radius of the verts around their average as a fraction of the
+        // mesh radius, rounded up so the material sphere stays around them
+        let average = self.average_position();
+        let material_radius = self
+            .vertices
+            .iter()
+            .map(|vertex| distance(vertex.position, average))
+            .fold(0.0, f32::max);
+        file.put_f32s(material + layout::MATERIAL_AVERAGE_VERT_POS, &average);
+        file.bytes[material + layout::MATERIAL_COMPRESSED_RADIUS] = match radius > 0.0 {
+            true => (material_radius / radius * 255.0).ceil().min(255.0) as u8,
+            false => 0,
+        };
+
+        // Fill in the header now that everything has an offset
+        file.put(layout::MESH_NAME, self.name.as_bytes());
+        file.put_f32(layout::MESH_BOUND_SPHERE, radius);
+        file.put_f32s(layout::MESH_BOUND_SPHERE + 4, &center);
+        file.put_f32s(layout::MESH_BOUND_BOX_MIN, &min);
+        file.put_f32s(layout::MESH_BOUND_BOX_MAX, &max);
+        file.bytes[layout::MESH_USED_BONE_COUNT] = self.bones.len() as u8;
+        file.bytes[layout::MESH_ROOT_BONE_INDEX] = match self.bones.is_empty() {
+            true => -1i8 as u8,
+            false => 0,
+        };
+        file.bytes[layout::MESH_BONE_COUNT] = self.bones.len() as u8;
+        file.bytes[layout::MESH_TEX_LAYER_ID_COUNT] = self.texture.is_some() as u8;
+        file.bytes[layout::MESH_MATERIAL_COUNT] = 1;
+        file.bytes[layout::MESH_LOD_COUNT] = 1;
+        file.put_f32(layout::MESH_LOD_DISTANCE, self.lod_distance);
+        file.put_u32(layout::MESH_BONE_ARRAY, bone_offset as u32);
+        file.put_u32(layout::MESH_SKELETON_INDEX_ARRAY, skeleton_offset as u32);
+        file.put_u32(layout::MESH_MATERIAL_ARRAY, material as u32);
+        file.put_u32(layout::MESH_TEX_LAYER_ARRAY, tex_layer_offset as u32);
+        file.put_u32(layout::MESH_IS, dx_mesh as u32);
+
+        Ok(file.bytes)
+    }
+
+    /// Writes the bones along with the child lists of the skeleton returning
+    /// the offset of each, zero when there are no bones
+    fn write_bones(&self, file: &mut FileWriter) -> (usize, usize) {
+        if self.bones.is_empty() {
+            return (0, 0);
+        }
+
+        // Bones hold matrices so they keep the 16 byte alignment
+        let bone_offset = file.reserve(self.bones.len() * FMeshBone::SIZE, 16);
+        let mut skeleton_indices: Vec<u8> = Vec::new();
+        for (index, value) in self.bones.iter().enumerate() {
+            let bone = bone_offset + index * FMeshBone::SIZE;
+            let parent_position = value
+                .parent
+                .map(|parent| self.bones[parent as usize].position)
+                .unwrap_or_default();
+            let relative = [0, 1, 2].map(|axis| value.position[axis] - parent_position[axis]);
+
+            file.put(bone + layout::BONE_NAME, value.name.as_bytes());
+            file.put_translation(bone + layout::BONE_AT_REST_BONE_TO_MODEL, value.position);
+            file.put_translation(
+                bone + layout::BONE_AT_REST_MODEL_TO_BONE,
+                value.position.map(|value| -value),
+            );
+            file.put_translation(bone + layout::BONE_AT_REST_BONE_TO_PARENT, relative);
+            file.put_translation(
+                bone + layout::BONE_AT_REST_PARENT_TO_BONE,
+                relative.map(|value| -value),
+            );
+            file.bytes[bone + layout::BONE_PART_ID] = value.part_id;
+
+            let children = self
+                .bones
+                .iter()
+                .enumerate()
+                .filter(|(_, child)| child.parent == Some(index as u8))
+                .map(|(child, _)| child as u8);
+            let child_array_start_index = skeleton_indices.len();
+            skeleton_indices.extend(children);
+            file.bytes[bone + layout::BONE_PARENT_INDEX] = value.parent.unwrap_or(NO_PARENT);
+            file.bytes[bone + layout::BONE_CHILD_ARRAY_START_INDEX] = child_array_start_index as u8;
+            file.bytes[bone + layout::BONE_CHILD_BONE_COUNT] =
+                (skeleton_indices.len() - child_array_start_index) as u8;
+        }
+
+        let skeleton_offset = match skeleton_indices.is_empty() {
+            true => 0,
+            false => {
+                let offset = file.reserve(skeleton_indices.len(), 1);
+                file.put(offset, &skeleton_indices);
+                offset
+            }
+        };
+        (bone_offset, skeleton_offset)
+    }
+
+    /// Writes the texture along with the texture instance and the layer
+    /// sampling it, returning the offset of the layer, zero without a
+    /// texture. The definition embedded at the start of the texture data
+    /// points back to the data the same as the files of the game
+    fn write_texture(&self, file: &mut FileWriter) -> usize {
+        let Some(texture) = &self.texture else {
+            return 0;
+        };
+
+        let image_data = match texture.image_data.is_empty() {
+            true => 0,
+            false => {
+                let offset = file.reserve(texture.image_data.len(), 4);
+                file.put(offset, &texture.image_data);
+                offset
+            }
+        };
+
+        let tex_data = file.reserve(layout::TEX_DATA_SIZE, 4);
+        file.put(tex_data + layout::TEX_INFO_NAME, texture.name.as_bytes());
+        file.bytes[tex_data + layout::TEX_INFO_TEX_FMT] = texture.tex_fmt;
+        file.bytes[tex_data + layout::TEX_INFO_LOD_COUNT] = texture.lod_count;
+        file.put_u16(tex_data + layout::TEX_INFO_TEXELS_ACROSS, texture.width);
+        file.put_u16(tex_data + layout::TEX_INFO_TEXELS_DOWN, texture.height);
+        file.put_u32(tex_data + layout::TEX_DEF_TEX_DATA, tex_data as u32);
+        file.bytes[tex_data + layout::TEX_DATA_LOD_COUNT] = texture.lod_count;
+        file.put_u16(tex_data + layout::TEX_DATA_WIDTH, texture.width);
+        file.put_u16(tex_data + layout::TEX_DATA_HEIGHT, texture.height);
+        file.put_u32(
+            tex_data + layout::TEX_DATA_TEXTURE_BYTES,
+            texture.image_data.len() as u32,
+        );
+        file.put_u32(tex_data + layout::TEX_DATA_IMAGE_DATA, image_data as u32);
+
+        let tex_inst = file.reserve(CFTexInst::SIZE, 4);
+        file.put_u32(tex_inst + layout::TEX_INST_TEX_DEF, tex_data as u32);
+        let flip_palette = file.reserve(4, 4);
+        file.put_u32(flip_palette, tex_inst as u32);
+
+        let tex_layer = file.reserve(FMeshTexLayerID::SIZE, 4);
+        file.bytes[tex_layer + layout::TEX_LAYER_FLIP_PAGE_COUNT] = 1;
+        file.put_u32(
+            tex_layer + layout::TEX_LAYER_FLIP_PALETTE,
+            flip_palette as u32,
+        );
+        tex_layer
+    }
+
+    fn bounds(&self) -> ([f32; 3], [f32; 3]) {
+        self.vertices
+            .iter()
+            .fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), vertex| {
+                (
+                    [0, 1, 2].map(|axis| min[axis].min(vertex.position[axis])),
+                    [0, 1, 2].map(|axis| max[axis].max(vertex.position[axis])),
+                )
+            })
+    }
+
+    fn average_position(&self) -> [f32; 3] {
+        let count = self.vertices.len() as f32;
+        let sum = self.vertices.iter().fold([0.0; 3], |sum, vertex| {
+            [0, 1, 2].map(|axis| sum[axis] + vertex.position[axis])
+        });
+        sum.map(|value| value / count)
+    }
+}
+
+/// Writes values at fixed offsets in the byte order of the file being
+/// built, used for files in the 32-bit layout of the game files
+pub struct FileWriter {
+    pub bytes: Vec<u8>,
+    pub endian: Endian,
+}
+
+impl FileWriter {
+    /// Appends zeroed space for a value at the alignment, returning its offset
+    pub fn reserve(&mut self, size: usize, align: usize) -> usize {
+        let offset = self.bytes.len().next_multiple_of(align);
+        self.bytes.resize(offset + size, 0);
+        offset
+    }
+
+    pub fn put(&mut self, offset: usize, value: &[u8]) {
+        if self.bytes.len() < offset + value.len() {
+            self.bytes.resize(offset + value.len(), 0);
+        }
+        self.bytes[offset..offset + value.len()].copy_from_slice(value);
+    }
+
+    pub fn put_u16(&mut self, offset: usize, value: u16) {
+        let value = match self.endian {
+            Endian::Big => value.to_be_bytes(),
+            Endian::Little => value.to_le_bytes(),
+        };
+        self.put(offset, &value);
+    }
+
+    pub fn put_u32(&mut self, offset: usize, value: u32) {
+        let value = match self.endian {
+            Endian::Big => value.to_be_bytes(),
+            Endian::Little => value.to_le_bytes(),
+        };
+        self.put(offset, &value);
+    }
+
+    pub fn put_f32(&mut self, offset: usize, value: f32) {
+        self.put_u32(offset, value.to_bits());
+    }
+
+    pub fn put_f32s(&mut self, offset: usize, values: &[f32]) {
+        for (index, value) in values.iter().enumerate() {
+            self.put_f32(offset + index * 4, *value);
+        }
+    }
+
+    /// Writes a CFMtx43A that only translates by the position
+    fn put_translation(&mut self, offset: usize, position: [f32; 3]) {
+        let rows = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0], position];
+        for (index, row) in rows.iter().enumerate() {
+            self.put_f32s(offset + index * 12, row);
+        }
+    }
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3)
+        .map(|axis| (a[axis] - b[axis]).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        layout::FileLayout, lint::lint_mesh, platform::Platform, st::SourceEndian, view::MeshView,
+    };
+
+    use super::{ApeBone, ApeBuilder, ApeMaterial, ApeVertex, BuildError};
+
+    fn quad() -> ApeBuilder {
+        let vertices = [
+            [0.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [2.0, 2.0, 0.0],
+            [0.0, 2.0, 0.0],
+        ]
+        .map(|position| ApeVertex::new(position).with_color([1.0, 0.0, 0.0, 0.5]))
+        .to_vec();
+        ApeBuilder::new("quad", vertices, vec![0, 1, 2, 0, 2, 3])
+    }
+
+    #[test]
+    fn test_build_mesh() {
+        for endian in [SourceEndian::Little, SourceEndian::Big] {
+            let bytes = quad()
+                .with_material(ApeMaterial {
+                    tint: [0.5; 3],
+                    ..Default::default()
+                })
+                .with_lod_distance(25.0)
+                .build(Platform::Pc, endian)
+                .unwrap();
+            let mesh = MeshView::new(&bytes, endian.into()).unwrap();
+
+            assert_eq!(mesh.name(), "quad");
+            assert_eq!(mesh.lod_distances(), vec![25.0]);
+            assert!(mesh.bones().unwrap().is_none());
+            assert_eq!(mesh.header().root_bone_index, -1);
+
+            // Sphere around the center of the quad
+            let sphere = mesh.bound_sphere();
+            assert_eq!(sphere.radius, 2f32.sqrt());
+            assert_eq!(sphere.position.x, 1.0);
+            assert_eq!(mesh.header().bound_box_max.y, 2.0);
+
+            let material = mesh.materials().unwrap().unwrap().get(0).unwrap();
+            assert_eq!(material.tint(), [0.5; 3]);
+            assert_eq!(material.record().compressed_radius, 255);
+
+            let dx_mesh = mesh.dx_mesh().unwrap().unwrap();
+            let indices: Vec<Vec<u16>> = dx_mesh
+                .index_buffers()
+                .unwrap()
+                .iter()
+                .map(|buffer| buffer.iter().collect())
+                .collect();
+            assert_eq!(indices, vec![vec![0, 1, 2, 0, 2, 3]]);
+
+            let vertex_buffer = dx_mesh.vertex_buffers().unwrap().unwrap().get(0).unwrap();
+            assert_eq!(vertex_buffer.bytes_per_vertex(), 36);
+            let positions = vertex_buffer.positions().unwrap().unwrap();
+            assert_eq!(positions.get(2), Some([2.0, 2.0, 0.0]));
+
+            let cluster = material
+                .dx_material()
+                .unwrap()
+                .unwrap()
+                .clusters()
+                .unwrap()
+                .unwrap()
+                .get(0)
+                .unwrap();
+            assert_eq!(cluster.tri_list(), (0, 2));
+        }
+    }
+
+    #[test]
+    fn test_build_bones() {
+        let bytes = quad()
+            .with_bone(ApeBone::new("root", None))
+            .with_bone(ApeBone::new("spine", Some(0)).with_position([0.0, 1.0, 0.0]))
+            .with_bone(
+                ApeBone::new("head", Some(1))
+                    .with_position([0.0, 2.0, 0.0])
+                    .with_part_id(1),
+            )
+            .with_bone(ApeBone::new("tail", Some(0)))
+            .build(Platform::Pc, SourceEndian::Big)
+            .unwrap();
+        let mesh = MeshView::new(&bytes, SourceEndian::Big.into()).unwrap();
+
+        let bones = mesh.bones().unwrap().unwrap();
+        assert_eq!(bones.len(), 4);
+        let root = bones.get(0).unwrap();
+        assert_eq!(root.parent_index(), None);
+        assert_eq!(root.record().skeleton.child_bone_count, 2);
+
+        let head = bones.get(2).unwrap();
+        assert_eq!(head.name(), "head");
+        assert_eq!(head.part_id(), 1);
+        assert_eq!(head.parent_index(), Some(1));
+        // Head sits a unit above the spine
+        let head = head.record();
+        assert_eq!(head.at_rest_bone_to_parent.matrix[3], [0.0, 1.0, 0.0]);
+        assert_eq!(head.at_rest_model_to_bone.matrix[3], [0.0, -2.0, 0.0]);
+
+        // Every byte written is reached from the header and nothing is flagged
+        assert!(FileLayout::from_view(&mesh).unwrap().orphaned().is_empty());
+        assert!(lint_mesh(&mesh).unwrap().is_empty());
+    }
+
+    /// Built files load in place on hosts sharing the layout of the files
+    #[test]
+    #[cfg(target_pointer_width = "32")]
+    fn test_build_in_place() {
+        use crate::st::{try_load_memory_struct, FMesh};
+
+        let bytes = quad()
+            .with_bone(ApeBone::new("root", None))
+            .with_bone(ApeBone::new("spine", Some(0)))
+            .build(Platform::Pc, SourceEndian::Big)
+            .unwrap();
+        let mesh =
+            unsafe { try_load_memory_struct::<FMesh>(bytes.into_boxed_slice(), SourceEndian::Big) }
+                .unwrap();
+
+        assert_eq!(mesh.bones().unwrap()[1].skeleton.parent_bone_index, 0);
+
+        let dx_mesh = mesh.impl_specific_mut().unwrap();
+        assert_eq!(dx_mesh.collision_vertices(0).unwrap().len(), 4);
+
+        let vertex_buffers = dx_mesh.vertex_buffers_mut().unwrap();
+        let color = vertex_buffers[0].colors().unwrap().unwrap()[0];
+        assert_eq!(color[0], 1.0);
+        assert_eq!(color[3], 128.0 / 255.0);
+    }
+
+    #[test]
+    fn test_build_errors() {
+        let build = |builder: ApeBuilder| builder.build(Platform::Pc, SourceEndian::Little);
+
+        let vertices = vec![ApeVertex::new([0.0; 3]); 3];
+        assert_eq!(
+            build(ApeBuilder::new("mesh", vertices.clone(), vec![0, 1])),
+            Err(BuildError::IncompleteTriangle(2))
+        );
+        assert_eq!(
+            build(ApeBuilder::new("mesh", vertices.clone(), vec![0, 1, 3])),
+            Err(BuildError::IndexOutOfBounds { index: 3, count: 3 })
+        );
+        assert_eq!(
+            build(ApeBuilder::new("mesh", Vec::new(), Vec::new())),
+            Err(BuildError::NoVertices)
+        );
+        assert!(matches!(
+            build(ApeBuilder::new(
+                "a mesh name too long",
+                vertices.clone(),
+                Vec::new()
+            )),
+            Err(BuildError::NameTooLong(_))
+        ));
+        assert_eq!(
+            build(
+                ApeBuilder::new("mesh", vertices.clone(), Vec::new())
+                    .with_bone(ApeBone::new("root", Some(0)))
+            ),
+            Err(BuildError::InvalidParent { bone: 0, parent: 0 })
+        );
+        assert_eq!(
+            ApeBuilder::new("mesh", vertices, vec![0, 1, 2])
+                .build(Platform::Ps2, SourceEndian::Little),
+            Err(BuildError::UnsupportedPlatform(Platform::Ps2))
+        );
+    }
+}
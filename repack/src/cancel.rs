@@ -0,0 +1,104 @@
+//! Cooperative cancellation of long running commands. Commands check the
+//! token between files so every file they write is complete, when cancelled
+//! the files written so far are removed so no partial output is left behind
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use thiserror::Error;
+
+use crate::provenance::Provenance;
+
+/// Exit code used when a command is cancelled, matches shells for SIGINT
+pub const CANCELLED_EXIT_CODE: u8 = 130;
+
+#[derive(Debug, Error)]
+#[error("cancelled")]
+pub struct Cancelled;
+
+/// Shared flag requesting a command to stop
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Token cancelled by Ctrl+C, pressing Ctrl+C again while the command
+    /// is cleaning up exits straight away
+    pub fn from_ctrl_c() -> Result<Self, ctrlc::Error> {
+        let token = Self::default();
+        let handler = token.clone();
+        ctrlc::set_handler(move || {
+            if handler.is_cancelled() {
+                process::exit(CANCELLED_EXIT_CODE as i32);
+            }
+            eprintln!("Cancelling, press Ctrl+C again to exit immediately");
+            handler.cancel();
+        })?;
+        Ok(token)
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Fails with [Cancelled] once the token has been cancelled
+    pub fn check(&self) -> Result<(), Cancelled> {
+        match self.is_cancelled() {
+            true => Err(Cancelled),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Files written by a command, removed when the command is cancelled
+#[derive(Debug, Default)]
+pub struct WrittenFiles {
+    paths: Vec<PathBuf>,
+}
+
+impl WrittenFiles {
+    /// Records an output along with its provenance sidecar
+    pub fn push(&mut self, path: &Path) {
+        self.paths.push(path.to_path_buf());
+        self.paths.push(Provenance::sidecar_path(path));
+    }
+
+    /// Removes every recorded file, returning how many were removed
+    pub fn remove_all(&mut self) -> io::Result<usize> {
+        let mut removed = 0;
+        for path in self.paths.drain(..) {
+            match std::fs::remove_file(&path) {
+                Ok(()) => removed += 1,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CancelToken;
+
+    #[test]
+    fn test_cancel() {
+        let token = CancelToken::default();
+        let shared = token.clone();
+        assert!(token.check().is_ok());
+
+        shared.cancel();
+        assert!(token.is_cancelled());
+        assert!(token.check().is_err());
+    }
+}
@@ -0,0 +1,304 @@
+//! Command line interface of the repacker, the platform of each file is
+//! detected from its name unless provided with `--platform`
+
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::{export::ExportFormat, platform::Platform};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "repack",
+    version,
+    about = "Inspect, convert and edit game meshes"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+    /// Print a trace of each structure as it's validated and fixed up
+    #[arg(long, global = true)]
+    pub trace_parse: bool,
+}
+
+/// Layout of the mesh data within a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PlatformArg {
+    /// Big endian GameCube layout
+    Gc,
+    /// Little endian DirectX layout used by the Xbox and PC
+    Dx,
+    /// Little endian PS2 layout with VIF packet geometry
+    Ps2,
+}
+
+impl PlatformArg {
+    /// Resolves the platform of a file, the platform detected from the
+    /// file name is used when it matches the provided layout
+    pub fn resolve(arg: Option<PlatformArg>, path: &Path) -> Option<Platform> {
+        let detected = Platform::from_path(path).map(|(platform, _)| platform);
+        match arg {
+            None => detected,
+            Some(PlatformArg::Gc) => Some(Platform::GameCube),
+            Some(PlatformArg::Dx) => detected.filter(Platform::is_dx).or(Some(Platform::Pc)),
+            Some(PlatformArg::Ps2) => Some(Platform::Ps2),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FormatArg {
+    Obj,
+    Ply,
+    Gltf,
+}
+
+impl From<FormatArg> for ExportFormat {
+    fn from(value: FormatArg) -> Self {
+        match value {
+            FormatArg::Obj => ExportFormat::Obj,
+            FormatArg::Ply => ExportFormat::Ply,
+            FormatArg::Gltf => ExportFormat::Gltf,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Summarize meshes without following the platform specific data
+    #[command(alias = "survey")]
+    Inspect {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        #[arg(long, value_enum)]
+        platform: Option<PlatformArg>,
+    },
+    /// Dump the loaded structures and vertex buffers of a DirectX mesh
+    Dump {
+        path: PathBuf,
+        #[arg(long, value_enum)]
+        platform: Option<PlatformArg>,
+        /// Directory the dumps are written to
+        #[arg(long, default_value = ".")]
+        out: PathBuf,
+    },
+    /// Export the geometry of a DirectX or PS2 mesh as a standard mesh file
+    Export {
+        path: PathBuf,
+        #[arg(long, value_enum)]
+        platform: Option<PlatformArg>,
+        #[arg(long, value_enum, default_value = "obj")]
+        format: FormatArg,
+        /// Path of the exported file
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Export the collision buffers of a DirectX mesh as a JSON shape set the
+    /// viewer builds rapier trimesh colliders from
+    Collision {
+        path: PathBuf,
+        #[arg(long, value_enum)]
+        platform: Option<PlatformArg>,
+        /// Path of the exported file
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Export the geometry of many DirectX or PS2 meshes into a directory,
+    /// failures are written to a summary report and don't stop the others
+    Convert {
+        /// Files to convert, or directories to convert the .ape and .wld
+        /// files of
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Also convert the files in subdirectories, the directory structure
+        /// is kept in the output directory
+        #[arg(long)]
+        recursive: bool,
+        /// Number of files converted at once, zero uses a thread per core
+        #[arg(long, default_value_t = 0)]
+        jobs: usize,
+        #[arg(long, value_enum)]
+        platform: Option<PlatformArg>,
+        #[arg(long, value_enum, default_value = "obj")]
+        format: FormatArg,
+        /// Directory the exported files are written to
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Convert the buffer dumps in a directory into a standard mesh file,
+    /// reads dumps from earlier versions as well
+    Import {
+        /// Directory containing buffer_dump.txt and buffer_dump_index.txt
+        dir: PathBuf,
+        /// Decode the indices as triangle strips rather than lists
+        #[arg(long)]
+        strips: bool,
+        #[arg(long, value_enum, default_value = "obj")]
+        format: FormatArg,
+        /// Path of the exported file
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Check meshes for unreadable sections, byte-swapped values and
+    /// orphaned data
+    Validate {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        #[arg(long, value_enum)]
+        platform: Option<PlatformArg>,
+        /// Print the problems and mesh statistics of each mesh as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check meshes against the limits of the engine
+    Lint {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        #[arg(long, value_enum)]
+        platform: Option<PlatformArg>,
+    },
+    /// Write an edited copy of a DirectX mesh, replacing the vertex positions
+    /// with the ones from an OBJ file and applying material edits
+    Pack {
+        path: PathBuf,
+        #[arg(long, value_enum)]
+        platform: Option<PlatformArg>,
+        /// OBJ file with the new vertex positions, usually an edited export
+        #[arg(long)]
+        positions: Option<PathBuf>,
+        /// Predicate selecting the materials the edits apply to
+        #[arg(long, default_value = "all")]
+        materials: String,
+        /// Edit applied to the selected materials, can be repeated
+        #[arg(long = "edit")]
+        edits: Vec<String>,
+        /// Path of the written file
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Compare two platform builds of the same asset
+    Diff { left: PathBuf, right: PathBuf },
+    /// Compatibility matrix of every asset in a directory
+    Matrix { dir: PathBuf },
+    /// Report and strip data not referenced by any structure
+    Strip {
+        path: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Edit the matching materials of every asset in a directory
+    Batch {
+        input: PathBuf,
+        predicate: String,
+        edit: String,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Generate the format specification from the struct definitions
+    Docs {
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Browse the structure of a file in the terminal
+    Tui { path: PathBuf },
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use clap::Parser;
+
+    use crate::platform::Platform;
+
+    use super::{Cli, Command, FormatArg, PlatformArg};
+
+    #[test]
+    fn test_parse_commands() {
+        let cli = Cli::parse_from(["repack", "survey", "a.ape", "b.ape", "--platform", "gc"]);
+        assert!(matches!(
+            cli.command,
+            Command::Inspect { ref paths, platform: Some(PlatformArg::Gc) } if paths.len() == 2
+        ));
+
+        assert!(Cli::try_parse_from(["repack", "export", "a.ape"]).is_err());
+        assert!(Cli::try_parse_from(["repack", "inspect"]).is_err());
+
+        let cli = Cli::parse_from([
+            "repack",
+            "pack",
+            "a.ape",
+            "--edit",
+            "tint=1,1,1",
+            "--edit",
+            "flags+=2",
+            "--out",
+            "b.ape",
+        ]);
+        assert!(matches!(
+            cli.command,
+            Command::Pack { ref edits, ref materials, positions: None, .. }
+                if edits.len() == 2 && materials == "all"
+        ));
+
+        let cli = Cli::parse_from(["repack", "validate", "a.ape", "--json"]);
+        assert!(matches!(
+            cli.command,
+            Command::Validate {
+                json: true,
+                platform: None,
+                ..
+            }
+        ));
+
+        let cli = Cli::parse_from([
+            "repack",
+            "convert",
+            "--recursive",
+            "data/ape",
+            "--out",
+            "exported",
+            "--format",
+            "gltf",
+        ]);
+        assert!(matches!(
+            cli.command,
+            Command::Convert {
+                recursive: true,
+                jobs: 0,
+                format: FormatArg::Gltf,
+                ref paths,
+                ..
+            } if paths.len() == 1
+        ));
+
+        let cli = Cli::parse_from(["repack", "import", "dumps", "--strips", "--out", "a.obj"]);
+        assert!(matches!(
+            cli.command,
+            Command::Import {
+                strips: true,
+                format: FormatArg::Obj,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_resolve_platform() {
+        let path = Path::new("xbdggltch00.ape");
+        assert_eq!(PlatformArg::resolve(None, path), Some(Platform::Xbox));
+        assert_eq!(
+            PlatformArg::resolve(Some(PlatformArg::Dx), path),
+            Some(Platform::Xbox)
+        );
+        assert_eq!(
+            PlatformArg::resolve(Some(PlatformArg::Dx), Path::new("mesh.ape")),
+            Some(Platform::Pc)
+        );
+        assert_eq!(PlatformArg::resolve(None, Path::new("mesh.ape")), None);
+        assert_eq!(
+            PlatformArg::resolve(None, Path::new("psmesh.ape")),
+            Some(Platform::Ps2)
+        );
+    }
+}
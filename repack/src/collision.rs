@@ -0,0 +1,168 @@
+//! Export of the collision copies of the DirectX vertex buffers as the JSON
+//! shape set read by the viewer, each shape holds the inputs of a rapier
+//! trimesh collider. The triangles are the ones the material clusters draw
+//! from the vertex buffer the collision buffer mirrors.
+//!
+//! The files don't store the length of the collision buffers, they are read
+//! with the vertex count of the vertex buffer at the same index. The lint
+//! checks each collision buffer against the positions it mirrors so a run
+//! over the game files shows where that doesn't hold
+
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    export::{cluster_triangles, ExportError},
+    provenance::Provenance,
+    view::MeshView,
+};
+
+/// Triangle mesh collision shape, the same schema as the shapes exported
+/// by the viewer
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CollisionShape {
+    pub name: String,
+    /// World space position of the shape
+    pub translation: [f32; 3],
+    /// Vertices relative to the translation
+    pub vertices: Vec<[f32; 3]>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+/// Set of collision shapes of a mesh
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CollisionSet {
+    pub shapes: Vec<CollisionShape>,
+    /// How the shapes were produced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+impl CollisionSet {
+    /// Builds a shape for each collision buffer of a DirectX mesh from the
+    /// triangles of every cluster drawing its vertex buffer, buffers without
+    /// a collision copy or triangles are skipped
+    pub fn from_dx_view(mesh: &MeshView) -> Result<Self, ExportError> {
+        let dx_mesh = mesh.dx_mesh()?.ok_or(ExportError::MissingMeshData)?;
+        let buffer_count = dx_mesh
+            .vertex_buffers()?
+            .map(|array| array.len())
+            .unwrap_or_default();
+
+        let mut triangles = vec![Vec::new(); buffer_count];
+        for (material, value) in mesh
+            .materials()?
+            .iter()
+            .flat_map(|array| array.iter())
+            .enumerate()
+        {
+            let Some(platform) = value.dx_material()? else {
+                continue;
+            };
+
+            for cluster in platform.clusters()?.iter().flat_map(|array| array.iter()) {
+                let buffer_triangles = triangles
+                    .get_mut(cluster.vertex_buffer_index() as usize)
+                    .ok_or(ExportError::MissingVertexBuffer {
+                        material,
+                        index: cluster.vertex_buffer_index(),
+                    })?;
+                let indices: Vec<u16> = dx_mesh
+                    .index_buffer(cluster.index_buffer_index() as usize)?
+                    .ok_or(ExportError::MissingIndexBuffer {
+                        material,
+                        index: cluster.index_buffer_index(),
+                    })?
+                    .iter()
+                    .collect();
+
+                buffer_triangles.extend(cluster_triangles(&cluster, &indices, material)?);
+            }
+        }
+
+        let name = mesh.header().name.as_string();
+        let mut shapes = Vec::new();
+        for (index, triangles) in triangles.into_iter().enumerate() {
+            let Some(vertices) = dx_mesh.collision_vertices(index)? else {
+                continue;
+            };
+            let vertices: Vec<[f32; 3]> = vertices.iter().collect();
+
+            // Triangles past the collision copy can't be collided with
+            let triangles: Vec<[u32; 3]> = triangles
+                .into_iter()
+                .filter(|triangle| {
+                    triangle
+                        .iter()
+                        .all(|index| (*index as usize) < vertices.len())
+                })
+                .collect();
+            if triangles.is_empty() {
+                continue;
+            }
+
+            shapes.push(CollisionShape {
+                name: format!("{}.coll_vertex_buffer[{}]", name, index),
+                translation: [0.0; 3],
+                vertices,
+                triangles,
+            });
+        }
+
+        Ok(Self {
+            shapes,
+            provenance: None,
+        })
+    }
+
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Writes the shape set as JSON
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        serde_json::to_writer_pretty(out, self)?;
+        Ok(())
+    }
+
+    pub fn read_json(bytes: &[u8]) -> serde_json::Result<Self> {
+        serde_json::from_slice(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use binrw::Endian;
+
+    use crate::{
+        fixture::{triangle_mesh, FIXTURE_MESH_NAME, FIXTURE_POSITIONS},
+        provenance::Provenance,
+        view::MeshView,
+    };
+
+    use super::CollisionSet;
+
+    #[test]
+    fn test_from_dx_view() {
+        let bytes = triangle_mesh();
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        let set = CollisionSet::from_dx_view(&mesh)
+            .unwrap()
+            .with_provenance(Provenance::default());
+
+        assert_eq!(set.shapes.len(), 1);
+        let shape = &set.shapes[0];
+        assert_eq!(
+            shape.name,
+            format!("{}.coll_vertex_buffer[0]", FIXTURE_MESH_NAME)
+        );
+        assert_eq!(shape.vertices, FIXTURE_POSITIONS.to_vec());
+        assert_eq!(shape.triangles, vec![[0, 1, 2]]);
+
+        let mut json = Vec::new();
+        set.write_json(&mut json).unwrap();
+        assert_eq!(CollisionSet::read_json(&json).unwrap(), set);
+    }
+}
@@ -0,0 +1,332 @@
+//! Conversion of many assets at once, the inputs are collected from the listed
+//! files and directories and each failure is recorded in a summary report
+//! rather than stopping the conversion of the remaining files. Inputs are
+//! converted in parallel with the time taken by each recorded
+
+use std::{
+    fs::read_dir,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::{prelude::*, ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::{cancel::CancelToken, export::ExportFormat};
+
+/// Extensions of the files converted from directories
+pub const CONVERT_EXTENSIONS: &[&str] = &["ape", "wld"];
+/// Extension of world files, the worlds aren't decoded yet so they're listed
+/// in the report as skipped
+pub const WORLD_EXTENSION: &str = "wld";
+/// Name of the summary report written to the output directory
+pub const REPORT_FILE: &str = "convert_report.txt";
+
+/// File to convert along with its path relative to the directory it was
+/// found in, the relative path is kept in the output directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertInput {
+    pub path: PathBuf,
+    pub relative: PathBuf,
+}
+
+impl ConvertInput {
+    /// Path of the exported file within the output directory
+    pub fn output(&self, out: &Path, format: ExportFormat) -> PathBuf {
+        out.join(&self.relative).with_extension(format.extension())
+    }
+
+    /// Whether the input is a world file
+    pub fn is_world(&self) -> bool {
+        self.path
+            .extension()
+            .is_some_and(|value| value.eq_ignore_ascii_case(WORLD_EXTENSION))
+    }
+}
+
+/// Outcome of converting a single input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConvertOutcome {
+    Converted,
+    Failed(String),
+    Skipped(String),
+    /// The conversion was cancelled before the input was started
+    Cancelled,
+}
+
+/// Outcome of converting each input
+#[derive(Debug, Default)]
+pub struct ConvertReport {
+    /// Converted files along with the time taken to convert them
+    pub converted: Vec<(PathBuf, Duration)>,
+    /// Files that failed to convert along with the error
+    pub failed: Vec<(PathBuf, String)>,
+    /// Files that weren't converted along with the reason
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+impl ConvertReport {
+    /// Creates the report from the outcome of each input, the outcomes are
+    /// in the same order as the inputs
+    pub fn from_outcomes(
+        inputs: &[ConvertInput],
+        outcomes: Vec<(ConvertOutcome, Duration)>,
+    ) -> Self {
+        let mut report = Self::default();
+        for (input, (outcome, elapsed)) in inputs.iter().zip(outcomes) {
+            let path = input.relative.clone();
+            match outcome {
+                ConvertOutcome::Converted => report.converted.push((path, elapsed)),
+                ConvertOutcome::Failed(error) => report.failed.push((path, error)),
+                ConvertOutcome::Skipped(reason) => report.skipped.push((path, reason)),
+                ConvertOutcome::Cancelled => {}
+            }
+        }
+        report
+    }
+}
+
+/// Whether a file found in a directory should be converted
+fn is_convertible(path: &Path) -> bool {
+    path.extension().is_some_and(|value| {
+        CONVERT_EXTENSIONS
+            .iter()
+            .any(|extension| value.eq_ignore_ascii_case(extension))
+    })
+}
+
+/// Collects the inputs from the provided paths. Files are always converted,
+/// directories only have their .ape and .wld files converted and their
+/// subdirectories are only walked when recursive
+pub fn collect_inputs(paths: &[PathBuf], recursive: bool) -> io::Result<Vec<ConvertInput>> {
+    let mut inputs = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            walk_directory(path, path, recursive, &mut inputs)?;
+        } else if let Some(file_name) = path.file_name() {
+            inputs.push(ConvertInput {
+                path: path.clone(),
+                relative: PathBuf::from(file_name),
+            });
+        }
+    }
+    Ok(inputs)
+}
+
+fn walk_directory(
+    root: &Path,
+    dir: &Path,
+    recursive: bool,
+    inputs: &mut Vec<ConvertInput>,
+) -> io::Result<()> {
+    let mut paths = Vec::new();
+    for entry in read_dir(dir)? {
+        paths.push(entry?.path());
+    }
+    // Sorted so the conversion order and report are stable
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            if recursive {
+                walk_directory(root, &path, recursive, inputs)?;
+            }
+            continue;
+        }
+
+        if !is_convertible(&path) {
+            continue;
+        }
+
+        let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+        inputs.push(ConvertInput { path, relative });
+    }
+
+    Ok(())
+}
+
+/// Progress bar over the inputs of a conversion
+pub fn convert_progress(count: usize) -> ProgressBar {
+    let style =
+        ProgressStyle::with_template("{bar:40} {pos}/{len} [{elapsed_precise}, eta {eta}] {msg}")
+            .expect("progress template is valid");
+    ProgressBar::new(count as u64).with_style(style)
+}
+
+/// Converts the inputs in parallel on `jobs` threads, or a thread per core
+/// when zero. World files are skipped, once cancelled the inputs that
+/// haven't been started are left as [ConvertOutcome::Cancelled]. The
+/// outcomes are returned in the order of the inputs along with the time
+/// taken by each
+pub fn convert_parallel<F>(
+    inputs: &[ConvertInput],
+    jobs: usize,
+    cancel: &CancelToken,
+    progress: &ProgressBar,
+    convert: F,
+) -> Result<Vec<(ConvertOutcome, Duration)>, ThreadPoolBuildError>
+where
+    F: Fn(&ConvertInput) -> Result<(), String> + Sync,
+{
+    let pool = ThreadPoolBuilder::new().num_threads(jobs).build()?;
+
+    let outcomes = pool.install(|| {
+        inputs
+            .par_iter()
+            .map(|input| {
+                if cancel.is_cancelled() {
+                    return (ConvertOutcome::Cancelled, Duration::ZERO);
+                }
+                if input.is_world() {
+                    progress.inc(1);
+                    return (
+                        ConvertOutcome::Skipped("world files can't be decoded yet".to_string()),
+                        Duration::ZERO,
+                    );
+                }
+
+                let start = Instant::now();
+                let outcome = match convert(input) {
+                    Ok(()) => ConvertOutcome::Converted,
+                    Err(error) => {
+                        progress.println(format!("{}: {}", input.path.display(), error));
+                        ConvertOutcome::Failed(error)
+                    }
+                };
+                let elapsed = start.elapsed();
+
+                progress.set_message(input.relative.display().to_string());
+                progress.inc(1);
+                (outcome, elapsed)
+            })
+            .collect()
+    });
+
+    Ok(outcomes)
+}
+
+/// Writes the summary report of a conversion
+pub fn write_convert_report<W: Write>(
+    out: &mut W,
+    format: ExportFormat,
+    report: &ConvertReport,
+) -> io::Result<()> {
+    writeln!(out, "format: {}", format.extension())?;
+    writeln!(
+        out,
+        "{} converted, {} failed, {} skipped",
+        report.converted.len(),
+        report.failed.len(),
+        report.skipped.len()
+    )?;
+
+    for (path, error) in &report.failed {
+        writeln!(out, "{} failed: {}", path.display(), error)?;
+    }
+
+    for (path, reason) in &report.skipped {
+        writeln!(out, "{} skipped: {}", path.display(), reason)?;
+    }
+
+    for (path, elapsed) in &report.converted {
+        writeln!(out, "{} converted in {:.1?}", path.display(), elapsed)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        path::{Path, PathBuf},
+        time::Duration,
+    };
+
+    use indicatif::ProgressBar;
+
+    use crate::{cancel::CancelToken, export::ExportFormat};
+
+    use super::{
+        convert_parallel, is_convertible, write_convert_report, ConvertInput, ConvertOutcome,
+        ConvertReport,
+    };
+
+    fn input(path: &str) -> ConvertInput {
+        ConvertInput {
+            path: PathBuf::from(path),
+            relative: PathBuf::from(path),
+        }
+    }
+
+    #[test]
+    fn test_convert_paths() {
+        let input = ConvertInput {
+            path: PathBuf::from("data/ape/chars/pcglitch.ape"),
+            relative: PathBuf::from("chars/pcglitch.ape"),
+        };
+        assert_eq!(
+            input.output(Path::new("exported"), ExportFormat::Gltf),
+            Path::new("exported/chars/pcglitch.gltf")
+        );
+        assert!(!input.is_world());
+
+        assert!(is_convertible(Path::new("level01.WLD")));
+        assert!(!is_convertible(Path::new("notes.txt")));
+        assert!(!is_convertible(Path::new("ape")));
+    }
+
+    #[test]
+    fn test_write_report() {
+        let report = ConvertReport {
+            converted: vec![(PathBuf::from("a.ape"), Duration::from_millis(12))],
+            failed: vec![(PathBuf::from("b.ape"), "bad magic".to_string())],
+            skipped: Vec::new(),
+        };
+
+        let mut out = Vec::new();
+        write_convert_report(&mut out, ExportFormat::Obj, &report).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("format: obj\n1 converted, 1 failed, 0 skipped\n"));
+        assert!(out.contains("b.ape failed: bad magic\n"));
+        assert!(out.contains("a.ape converted in 12.0ms\n"));
+    }
+
+    #[test]
+    fn test_convert_parallel() {
+        let inputs = [input("a.ape"), input("b.ape"), input("c.wld")];
+        let progress = ProgressBar::hidden();
+
+        let outcomes =
+            convert_parallel(
+                &inputs,
+                2,
+                &CancelToken::default(),
+                &progress,
+                |input| match input.path == Path::new("b.ape") {
+                    true => Err("bad magic".to_string()),
+                    false => Ok(()),
+                },
+            )
+            .unwrap();
+        assert_eq!(progress.position(), 3);
+
+        let report = ConvertReport::from_outcomes(&inputs, outcomes);
+        assert_eq!(report.converted.len(), 1);
+        assert_eq!(report.converted[0].0, Path::new("a.ape"));
+        assert_eq!(
+            report.failed,
+            vec![(PathBuf::from("b.ape"), "bad magic".to_string())]
+        );
+        assert_eq!(report.skipped.len(), 1);
+
+        // Nothing is started once cancelled
+        let cancel = CancelToken::default();
+        cancel.cancel();
+        let outcomes =
+            convert_parallel(&inputs, 1, &cancel, &ProgressBar::hidden(), |_| Ok(())).unwrap();
+        assert!(outcomes
+            .iter()
+            .all(|(outcome, _)| *outcome == ConvertOutcome::Cancelled));
+    }
+}
@@ -0,0 +1,314 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    fs::read_dir,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{offsets::ValidationError, platform::Platform, view::MeshView};
+
+#[derive(Debug, Error)]
+pub enum SummaryError {
+    #[error("unable to detect the platform from the file name")]
+    UnknownPlatform,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+}
+
+/// Structural summary of a mesh used to compare the builds of the
+/// same asset across platforms
+#[derive(Debug, Clone)]
+pub struct MeshSummary {
+    pub platform: Platform,
+    pub name: String,
+    pub bound_radius: f32,
+    pub flags: u16,
+    pub bone_names: Vec<String>,
+    pub segment_count: u8,
+    pub material_count: u8,
+    pub light_count: u8,
+    pub tex_layer_count: u8,
+    pub lod_distances: Vec<f32>,
+    /// Platform specific geometry details, only present for platforms
+    /// using the DirectX layout
+    pub geometry: Option<GeometrySummary>,
+}
+
+/// Summary of the platform specific geometry data
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeometrySummary {
+    pub vertex_buffer_count: usize,
+    pub vertex_count: u32,
+    pub index_buffer_count: usize,
+    pub index_count: usize,
+    /// Distinct texel formats referenced by the texture layers
+    pub texture_formats: Vec<u8>,
+}
+
+impl MeshSummary {
+    /// Loads the summary of the mesh at the provided path, the
+    /// platform is detected from the file name
+    pub fn load(path: &Path) -> Result<MeshSummary, SummaryError> {
+        let (platform, _) = Platform::from_path(path).ok_or(SummaryError::UnknownPlatform)?;
+        let buffer = std::fs::read(path)?;
+        Self::from_buffer(platform, buffer)
+    }
+
+    pub fn from_buffer(platform: Platform, buffer: Vec<u8>) -> Result<MeshSummary, SummaryError> {
+        let mesh = MeshView::new(&buffer, platform.endian())?;
+        let header = mesh.header();
+
+        let bone_names = mesh
+            .bones()?
+            .iter()
+            .flat_map(|array| array.iter())
+            .map(|bone| bone.name())
+            .collect();
+
+        let geometry = if platform.is_dx() {
+            Some(GeometrySummary::from_dx_view(&mesh)?)
+        } else {
+            None
+        };
+
+        Ok(MeshSummary {
+            platform,
+            name: mesh.name(),
+            bound_radius: header.bound_sphere.radius,
+            flags: header.flags,
+            bone_names,
+            segment_count: header.segment_count,
+            material_count: header.material_count,
+            light_count: header.light_count,
+            tex_layer_count: header.tex_layer_id_count,
+            lod_distances: mesh.lod_distances(),
+            geometry,
+        })
+    }
+}
+
+impl GeometrySummary {
+    fn from_dx_view(mesh: &MeshView) -> Result<GeometrySummary, ValidationError> {
+        let mut texture_formats = Vec::new();
+        for layer in mesh.tex_layers()?.iter().flat_map(|array| array.iter()) {
+            for tex_def in mesh.layer_textures(&layer)?.into_iter().flatten() {
+                if !texture_formats.contains(&tex_def.tex_info.tex_fmt) {
+                    texture_formats.push(tex_def.tex_info.tex_fmt);
+                }
+            }
+        }
+        texture_formats.sort_unstable();
+
+        let Some(dx_mesh) = mesh.dx_mesh()? else {
+            return Ok(GeometrySummary {
+                vertex_buffer_count: 0,
+                vertex_count: 0,
+                index_buffer_count: 0,
+                index_count: 0,
+                texture_formats,
+            });
+        };
+
+        let vertex_buffers = dx_mesh.vertex_buffers()?;
+        let index_buffers = dx_mesh.index_buffers()?;
+
+        Ok(GeometrySummary {
+            vertex_buffer_count: vertex_buffers.map(|array| array.len()).unwrap_or_default(),
+            // Counts of vertex buffers without a stride aren't bounded by the
+            // file so they're saturated rather than overflowing
+            vertex_count: vertex_buffers
+                .iter()
+                .flat_map(|array| array.iter())
+                .map(|value| value.vertex_count())
+                .fold(0, u32::saturating_add),
+            index_buffer_count: index_buffers.len(),
+            index_count: index_buffers.iter().map(|value| value.len()).sum(),
+            texture_formats,
+        })
+    }
+}
+
+/// Single structural difference between two summaries
+#[derive(Debug)]
+pub struct Difference {
+    pub field: &'static str,
+    pub left: String,
+    pub right: String,
+}
+
+fn compare<T>(out: &mut Vec<Difference>, field: &'static str, left: &T, right: &T)
+where
+    T: PartialEq + Debug,
+{
+    if left != right {
+        out.push(Difference {
+            field,
+            left: format!("{:?}", left),
+            right: format!("{:?}", right),
+        });
+    }
+}
+
+/// Compares two mesh summaries, geometry is only compared when
+/// both summaries have geometry details
+pub fn diff_summaries(left: &MeshSummary, right: &MeshSummary) -> Vec<Difference> {
+    let mut out = Vec::new();
+
+    compare(&mut out, "name", &left.name, &right.name);
+    compare(&mut out, "flags", &left.flags, &right.flags);
+    compare(&mut out, "bones", &left.bone_names, &right.bone_names);
+    compare(
+        &mut out,
+        "segments",
+        &left.segment_count,
+        &right.segment_count,
+    );
+    compare(
+        &mut out,
+        "materials",
+        &left.material_count,
+        &right.material_count,
+    );
+    compare(&mut out, "lights", &left.light_count, &right.light_count);
+    compare(
+        &mut out,
+        "tex_layers",
+        &left.tex_layer_count,
+        &right.tex_layer_count,
+    );
+    compare(
+        &mut out,
+        "lod_distances",
+        &left.lod_distances,
+        &right.lod_distances,
+    );
+
+    if let (Some(left), Some(right)) = (&left.geometry, &right.geometry) {
+        compare(
+            &mut out,
+            "vertex_count",
+            &left.vertex_count,
+            &right.vertex_count,
+        );
+        compare(
+            &mut out,
+            "index_count",
+            &left.index_count,
+            &right.index_count,
+        );
+        compare(
+            &mut out,
+            "texture_formats",
+            &left.texture_formats,
+            &right.texture_formats,
+        );
+    }
+
+    out
+}
+
+/// Row of the compatibility matrix for a single asset
+pub struct MatrixRow {
+    /// Platform independent name of the asset
+    pub name: String,
+    /// Loaded summaries for each platform variant present
+    pub variants: BTreeMap<Platform, Result<MeshSummary, SummaryError>>,
+    /// Differences for each variant against the first loaded variant
+    pub differences: BTreeMap<Platform, Vec<Difference>>,
+}
+
+/// Groups the platform variants of every .ape file in the provided
+/// directory and compares them against each other
+pub fn compatibility_matrix(dir: &Path) -> io::Result<Vec<MatrixRow>> {
+    let mut assets: BTreeMap<String, BTreeMap<Platform, PathBuf>> = BTreeMap::new();
+
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        if !path.extension().is_some_and(|value| value == "ape") {
+            continue;
+        }
+
+        if let Some((platform, name)) = Platform::from_path(&path) {
+            assets.entry(name).or_default().insert(platform, path);
+        }
+    }
+
+    let rows = assets
+        .into_iter()
+        .map(|(name, paths)| {
+            let variants: BTreeMap<Platform, Result<MeshSummary, SummaryError>> = paths
+                .into_iter()
+                .map(|(platform, path)| (platform, MeshSummary::load(&path)))
+                .collect();
+
+            let mut differences = BTreeMap::new();
+            let reference = variants.values().find_map(|value| value.as_ref().ok());
+
+            if let Some(reference) = reference {
+                for (platform, summary) in &variants {
+                    if let Ok(summary) = summary {
+                        differences.insert(*platform, diff_summaries(reference, summary));
+                    }
+                }
+            }
+
+            MatrixRow {
+                name,
+                variants,
+                differences,
+            }
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+/// Writes the compatibility matrix as a text table followed by
+/// the details of each difference
+pub fn write_matrix<W: Write>(out: &mut W, rows: &[MatrixRow]) -> io::Result<()> {
+    write!(out, "{:<24}", "asset")?;
+    for platform in Platform::ALL {
+        write!(out, "{:>10}", platform)?;
+    }
+    writeln!(out)?;
+
+    for row in rows {
+        write!(out, "{:<24}", row.name)?;
+        for platform in Platform::ALL {
+            let cell = match (row.variants.get(&platform), row.differences.get(&platform)) {
+                (None, _) => "-".to_string(),
+                (Some(Err(_)), _) => "error".to_string(),
+                (Some(Ok(_)), Some(differences)) if differences.is_empty() => "ok".to_string(),
+                (Some(Ok(_)), Some(differences)) => format!("{} diff", differences.len()),
+                (Some(Ok(_)), None) => "?".to_string(),
+            };
+            write!(out, "{:>10}", cell)?;
+        }
+        writeln!(out)?;
+    }
+
+    for row in rows {
+        for (platform, summary) in &row.variants {
+            if let Err(err) = summary {
+                writeln!(out, "{} ({}): {}", row.name, platform, err)?;
+            }
+        }
+
+        for (platform, differences) in &row.differences {
+            for difference in differences {
+                writeln!(
+                    out,
+                    "{} ({}): {} {} != {}",
+                    row.name, platform, difference.field, difference.left, difference.right
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,523 @@
+//! Format specification generator, the raw struct definitions are embedded
+//! and parsed so the field offsets, sizes and descriptions in the generated
+//! document always match what the loader uses
+
+use std::{collections::HashMap, io::Write};
+
+use binrw::Endian;
+use thiserror::Error;
+
+use crate::platform::Platform;
+
+/// Size of pointers within the compiled files, the files are created for
+/// 32-bit targets and pointers are stored as offsets from the file start
+pub const POINTER_SIZE: usize = 4;
+
+/// Source file containing raw struct definitions
+pub struct SourceFile {
+    /// Path of the source relative to the crate source directory
+    pub name: &'static str,
+    /// Platforms that store their data using the structs in this file
+    pub platforms: &'static [Platform],
+    pub source: &'static str,
+}
+
+impl SourceFile {
+    pub fn endian(&self) -> Endian {
+        self.platforms
+            .first()
+            .map(Platform::endian)
+            .unwrap_or(Endian::Little)
+    }
+}
+
+/// Sources that are documented
+pub const SOURCES: &[SourceFile] = &[
+    SourceFile {
+        name: "st.rs",
+        platforms: &[Platform::Xbox, Platform::Pc],
+        source: include_str!("st.rs"),
+    },
+    SourceFile {
+        name: "raw/dx.rs",
+        platforms: &[Platform::Xbox, Platform::Pc],
+        source: include_str!("raw/dx.rs"),
+    },
+];
+
+#[derive(Debug, Error)]
+pub enum DocError {
+    #[error("{structure}.{field} has unknown type {ty}")]
+    UnknownType {
+        structure: String,
+        field: String,
+        ty: String,
+    },
+}
+
+/// Documentation for a single struct field
+#[derive(Debug, Clone)]
+pub struct FieldDoc {
+    pub name: String,
+    pub ty: String,
+    pub offset: usize,
+    pub size: usize,
+    pub description: String,
+}
+
+/// Documentation for a single struct
+#[derive(Debug, Clone)]
+pub struct StructDoc {
+    pub name: String,
+    /// Name of the source file the struct was defined in
+    pub source: &'static str,
+    pub platforms: &'static [Platform],
+    pub endian: Endian,
+    pub size: usize,
+    pub align: usize,
+    pub description: String,
+    pub fields: Vec<FieldDoc>,
+}
+
+/// Struct definition parsed from a source file
+struct RawStruct {
+    name: String,
+    description: String,
+    /// Alignment forced by `#[repr(align(N))]`
+    align: usize,
+    fields: Vec<RawField>,
+}
+
+struct RawField {
+    name: String,
+    ty: String,
+    description: String,
+}
+
+/// Definitions parsed from the sources
+#[derive(Default)]
+struct Definitions {
+    consts: HashMap<String, usize>,
+    /// Types with a known size that aren't structs, such as
+    /// enums with an integer repr and bitflags
+    scalars: HashMap<String, usize>,
+    structs: Vec<(&'static SourceFile, RawStruct)>,
+}
+
+/// Representation of the item following a `#[repr(..)]` attribute
+#[derive(Default)]
+struct Repr {
+    c: bool,
+    align: usize,
+    int: Option<usize>,
+}
+
+impl Repr {
+    fn parse(value: &str) -> Repr {
+        let mut repr = Repr::default();
+        for part in value.split(',').map(str::trim) {
+            if part == "C" {
+                repr.c = true;
+            } else if let Some(align) = part
+                .strip_prefix("align(")
+                .and_then(|value| value.strip_suffix(')'))
+            {
+                repr.align = align.parse().unwrap_or_default();
+            } else if let Some(size) = primitive_size(part) {
+                repr.int = Some(size);
+            }
+        }
+        repr
+    }
+}
+
+fn primitive_size(ty: &str) -> Option<usize> {
+    Some(match ty {
+        "()" => 0,
+        "u8" | "i8" | "bool" => 1,
+        "u16" | "i16" => 2,
+        "u32" | "i32" | "f32" => 4,
+        "u64" | "i64" | "f64" => 8,
+        "usize" | "isize" => POINTER_SIZE,
+        _ => return None,
+    })
+}
+
+fn strip_visibility(value: &str) -> &str {
+    let value = value.strip_prefix("pub(crate) ").unwrap_or(value);
+    value.strip_prefix("pub ").unwrap_or(value)
+}
+
+fn align_up(value: usize, align: usize) -> usize {
+    value.div_ceil(align) * align
+}
+
+impl Definitions {
+    fn parse(&mut self, file: &'static SourceFile) {
+        let mut lines = file.source.lines();
+        let mut docs: Vec<&str> = Vec::new();
+        let mut repr = Repr::default();
+
+        while let Some(line) = lines.next() {
+            let line = line.trim();
+
+            if let Some(doc) = line.strip_prefix("///") {
+                docs.push(doc.trim());
+                continue;
+            }
+
+            if let Some(value) = line
+                .strip_prefix("#[repr(")
+                .and_then(|value| value.strip_suffix(")]"))
+            {
+                repr = Repr::parse(value);
+                continue;
+            }
+
+            if line.starts_with("#[") {
+                continue;
+            }
+
+            let item = strip_visibility(line);
+
+            if let Some((name, value)) = item
+                .strip_prefix("const ")
+                .and_then(|value| value.strip_suffix(';'))
+                .and_then(|value| value.split_once(": usize = "))
+            {
+                if let Ok(value) = value.parse() {
+                    self.consts.insert(name.to_string(), value);
+                }
+            } else if let Some(header) = item
+                .strip_prefix("struct ")
+                .and_then(|value| value.strip_suffix('{'))
+            {
+                match header.trim().split_once(':') {
+                    // Bitflags with an underlying integer type
+                    Some((name, ty)) => {
+                        if let Some(size) = primitive_size(ty.trim()) {
+                            self.scalars.insert(name.trim().to_string(), size);
+                        }
+                        skip_block(&mut lines);
+                    }
+                    // Generic and non C structs have no stable layout
+                    None if header.contains('<') || !repr.c => skip_block(&mut lines),
+                    None => {
+                        let fields = parse_fields(&mut lines);
+                        self.structs.push((
+                            file,
+                            RawStruct {
+                                name: header.trim().to_string(),
+                                description: docs.join(" "),
+                                align: repr.align,
+                                fields,
+                            },
+                        ));
+                    }
+                }
+            } else if let Some(name) = item
+                .strip_prefix("enum ")
+                .and_then(|value| value.strip_suffix('{'))
+            {
+                if let Some(size) = repr.int {
+                    self.scalars.insert(name.trim().to_string(), size);
+                }
+                skip_block(&mut lines);
+            }
+
+            docs.clear();
+            repr = Repr::default();
+        }
+    }
+
+    fn find_struct(&self, name: &str) -> Option<&RawStruct> {
+        self.structs
+            .iter()
+            .map(|(_, value)| value)
+            .find(|value| value.name == name)
+    }
+
+    /// Determines the size and alignment of the provided type
+    fn layout(&self, ty: &str) -> Option<(usize, usize)> {
+        let ty = ty.trim();
+
+        if ty.starts_with("*mut ") || ty.starts_with("*const ") || ty.starts_with("ArrayPtr<") {
+            return Some((POINTER_SIZE, POINTER_SIZE));
+        }
+
+        if let Some((element, length)) = ty
+            .strip_prefix('[')
+            .and_then(|value| value.strip_suffix(']'))
+            .and_then(|value| value.rsplit_once(';'))
+        {
+            let (size, align) = self.layout(element)?;
+            return Some((size * self.const_value(length)?, align));
+        }
+
+        if let Some(length) = ty
+            .strip_prefix("FixedString<")
+            .and_then(|value| value.strip_suffix('>'))
+        {
+            return Some((self.const_value(length)?, 1));
+        }
+
+        if let Some(size) = primitive_size(ty).or_else(|| self.scalars.get(ty).copied()) {
+            return Some((size, size.max(1)));
+        }
+
+        let value = self.find_struct(ty)?;
+        let (layout, _) = self.struct_layout(value).ok()?;
+        Some(layout)
+    }
+
+    fn const_value(&self, value: &str) -> Option<usize> {
+        let value = value.trim();
+        value
+            .parse()
+            .ok()
+            .or_else(|| self.consts.get(value).copied())
+    }
+
+    /// Lays out the fields of a struct using the C representation rules,
+    /// returns the size and alignment along with the field offsets
+    fn struct_layout(
+        &self,
+        value: &RawStruct,
+    ) -> Result<((usize, usize), Vec<FieldDoc>), DocError> {
+        let mut offset = 0;
+        let mut align = value.align.max(1);
+        let mut fields = Vec::with_capacity(value.fields.len());
+
+        for field in &value.fields {
+            let (size, field_align) =
+                self.layout(&field.ty)
+                    .ok_or_else(|| DocError::UnknownType {
+                        structure: value.name.clone(),
+                        field: field.name.clone(),
+                        ty: field.ty.clone(),
+                    })?;
+
+            offset = align_up(offset, field_align);
+            align = align.max(field_align);
+
+            fields.push(FieldDoc {
+                name: field.name.clone(),
+                ty: field.ty.clone(),
+                offset,
+                size,
+                description: field.description.clone(),
+            });
+
+            offset += size;
+        }
+
+        Ok(((align_up(offset, align), align), fields))
+    }
+}
+
+/// Skips the remaining lines of a block whose opening line was consumed
+fn skip_block(lines: &mut std::str::Lines) {
+    let mut depth = 1;
+    for line in lines {
+        depth += line.matches('{').count();
+        depth -= line.matches('}').count().min(depth);
+        if depth == 0 {
+            break;
+        }
+    }
+}
+
+/// Parses struct fields until the closing brace, both doc comments and
+/// regular comments are used as descriptions as the DX structs use the
+/// original C comments
+fn parse_fields(lines: &mut std::str::Lines) -> Vec<RawField> {
+    let mut fields = Vec::new();
+    let mut docs: Vec<&str> = Vec::new();
+
+    for line in lines {
+        let line = line.trim();
+        if line == "}" {
+            break;
+        }
+
+        if let Some(doc) = line.strip_prefix("///").or_else(|| line.strip_prefix("//")) {
+            docs.push(doc.trim());
+            continue;
+        }
+
+        if line.is_empty() || line.starts_with("#[") {
+            continue;
+        }
+
+        // Strip trailing comments noting the original C type
+        let line = match line.split_once("/*") {
+            Some((line, _)) => line.trim(),
+            None => line,
+        };
+        let line = line.strip_suffix(',').unwrap_or(line);
+
+        if let Some((name, ty)) = strip_visibility(line).split_once(": ") {
+            fields.push(RawField {
+                name: name.to_string(),
+                ty: ty.trim().to_string(),
+                description: docs.join(" "),
+            });
+        }
+
+        docs.clear();
+    }
+
+    fields
+}
+
+/// Generates documentation for all the C structs in the sources
+pub fn generate() -> Result<Vec<StructDoc>, DocError> {
+    let mut definitions = Definitions::default();
+    SOURCES.iter().for_each(|file| definitions.parse(file));
+
+    let mut seen = Vec::new();
+    let mut out = Vec::new();
+
+    for (file, value) in &definitions.structs {
+        // The same struct may be declared in multiple sources
+        if seen.contains(&value.name) {
+            continue;
+        }
+        seen.push(value.name.clone());
+
+        let ((size, align), fields) = definitions.struct_layout(value)?;
+        out.push(StructDoc {
+            name: value.name.clone(),
+            source: file.name,
+            platforms: file.platforms,
+            endian: file.endian(),
+            size,
+            align,
+            description: value.description.clone(),
+            fields,
+        });
+    }
+
+    Ok(out)
+}
+
+/// Writes the documentation as a markdown document
+pub fn write_markdown<W: Write>(out: &mut W, docs: &[StructDoc]) -> std::io::Result<()> {
+    writeln!(out, "# Data formats")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "Generated from the repack struct definitions. Pointers are {POINTER_SIZE} byte \
+         offsets from the start of the file."
+    )?;
+
+    for doc in docs {
+        let endian = match doc.endian {
+            Endian::Big => "big endian",
+            Endian::Little => "little endian",
+        };
+        let platforms: Vec<&str> = doc.platforms.iter().map(Platform::prefix).collect();
+
+        writeln!(out)?;
+        writeln!(out, "## {}", doc.name)?;
+        writeln!(out)?;
+        if !doc.description.is_empty() {
+            writeln!(out, "{}", doc.description)?;
+            writeln!(out)?;
+        }
+        writeln!(
+            out,
+            "Defined in `{}`, {} ({}), {} bytes aligned to {}",
+            doc.source,
+            endian,
+            platforms.join(", "),
+            doc.size,
+            doc.align
+        )?;
+        writeln!(out)?;
+        writeln!(out, "| Offset | Size | Field | Type | Description |")?;
+        writeln!(out, "| --- | --- | --- | --- | --- |")?;
+
+        for field in &doc.fields {
+            writeln!(
+                out,
+                "| {:#06x} | {} | {} | `{}` | {} |",
+                field.offset,
+                field.size,
+                field.name,
+                field.ty,
+                field.description.replace('|', "\\|")
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::mem::{align_of, offset_of, size_of};
+
+    use crate::st::{FMeshBone, FMeshLight};
+
+    use super::{generate, StructDoc};
+
+    fn find<'a>(docs: &'a [StructDoc], name: &str) -> &'a StructDoc {
+        docs.iter().find(|doc| doc.name == name).unwrap()
+    }
+
+    fn field_offset(doc: &StructDoc, name: &str) -> usize {
+        doc.fields
+            .iter()
+            .find(|field| field.name == name)
+            .unwrap()
+            .offset
+    }
+
+    #[test]
+    fn test_matches_compiled_layout() {
+        let docs = generate().unwrap();
+
+        let light = find(&docs, "FMeshLight");
+        assert_eq!(light.size, size_of::<FMeshLight>());
+        assert_eq!(
+            field_offset(light, "intensity"),
+            offset_of!(FMeshLight, intensity)
+        );
+        assert_eq!(
+            field_offset(light, "orientation"),
+            offset_of!(FMeshLight, orientation)
+        );
+
+        let bone = find(&docs, "FMeshBone");
+        assert_eq!(bone.size, size_of::<FMeshBone>());
+        assert_eq!(bone.align, align_of::<FMeshBone>());
+        assert_eq!(
+            field_offset(bone, "skeleton"),
+            offset_of!(FMeshBone, skeleton)
+        );
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "32")]
+    fn test_matches_compiled_pointer_layout() {
+        use crate::st::FMesh;
+
+        let docs = generate().unwrap();
+        let mesh = find(&docs, "FMesh");
+        assert_eq!(mesh.size, size_of::<FMesh>());
+        assert_eq!(field_offset(mesh, "mesh_is"), offset_of!(FMesh, mesh_is));
+    }
+
+    #[test]
+    fn test_descriptions() {
+        let docs = generate().unwrap();
+        let mesh = find(&docs, "FMesh");
+        assert!(mesh.description.contains("base struct"));
+
+        let name = &mesh.fields[0];
+        assert_eq!(name.name, "name");
+        assert_eq!(name.size, 16);
+        assert_eq!(name.description, "ASCIIZ name of this mesh");
+    }
+}
@@ -0,0 +1,232 @@
+//! Plain text dumps of the vertex and index buffers of a mesh. The early
+//! dumps wrote every vertex buffer under a "Buffer 1" header to
+//! buffer_dump.txt and only the first index buffer to buffer_dump_index.txt
+//! without any header, both forms are read so those dumps stay usable.
+//! Dumps written now number the headers and include every index buffer
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+};
+
+use thiserror::Error;
+
+use crate::export::{list_triangles, strip_triangles, ExportGeometry, MaterialGroup};
+
+/// Name of the file holding the vertex positions
+pub const POSITIONS_FILE: &str = "buffer_dump.txt";
+/// Name of the file holding the indices
+pub const INDICES_FILE: &str = "buffer_dump_index.txt";
+
+#[derive(Debug, Error)]
+pub enum DumpError {
+    #[error("line {line}: {value:?} is not a valid {expected}")]
+    InvalidLine {
+        line: usize,
+        value: String,
+        expected: &'static str,
+    },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Vertex and index buffers of a mesh dump
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BufferDump {
+    pub vertex_buffers: Vec<Vec<[f32; 3]>>,
+    pub index_buffers: Vec<Vec<u16>>,
+}
+
+impl BufferDump {
+    /// Reads the dump files from the directory, the index file is optional
+    /// since dumping the indices was added after the positions
+    pub fn load(dir: &Path) -> Result<BufferDump, DumpError> {
+        let vertex_buffers = read_buffers(
+            BufReader::new(File::open(dir.join(POSITIONS_FILE))?),
+            parse_position,
+        )?;
+
+        let index_buffers = match File::open(dir.join(INDICES_FILE)) {
+            Ok(file) => read_buffers(BufReader::new(file), parse_index)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(BufferDump {
+            vertex_buffers,
+            index_buffers,
+        })
+    }
+
+    /// Writes the dump files into the directory
+    pub fn write(&self, dir: &Path) -> io::Result<()> {
+        let mut positions = File::create(dir.join(POSITIONS_FILE))?;
+        write_buffers(&mut positions, &self.vertex_buffers, |out, [x, y, z]| {
+            writeln!(out, "{} {} {}", x, y, z)
+        })?;
+
+        let mut indices = File::create(dir.join(INDICES_FILE))?;
+        write_buffers(&mut indices, &self.index_buffers, |out, value| {
+            writeln!(out, "{}", value)
+        })
+    }
+
+    /// Geometry of the dump with the vertex buffers merged, the dumps don't
+    /// record the materials so every index buffer becomes its own group.
+    /// Indices are relative to the first vertex buffer like the clusters
+    /// of single buffer meshes
+    pub fn to_geometry(&self, strips: bool) -> ExportGeometry {
+        let groups = self
+            .index_buffers
+            .iter()
+            .enumerate()
+            .map(|(material, indices)| MaterialGroup {
+                material,
+                triangles: match strips {
+                    true => strip_triangles(indices),
+                    false => list_triangles(indices),
+                },
+            })
+            .collect();
+
+        ExportGeometry {
+            positions: self.vertex_buffers.concat(),
+            groups,
+        }
+    }
+}
+
+/// Whether the line is a buffer header
+fn is_header(line: &str) -> bool {
+    line.starts_with("Buffer")
+}
+
+fn parse_position(line: &str) -> Option<[f32; 3]> {
+    let mut values = line.split_whitespace().map(|value| value.parse().ok());
+    let position = [values.next()??, values.next()??, values.next()??];
+    values.next().is_none().then_some(position)
+}
+
+fn parse_index(line: &str) -> Option<u16> {
+    line.parse().ok()
+}
+
+/// Reads the buffers of a dump file, values before the first header
+/// belong to a single buffer like the legacy index dumps
+fn read_buffers<R, T>(reader: R, parse: fn(&str) -> Option<T>) -> Result<Vec<Vec<T>>, DumpError>
+where
+    R: BufRead,
+{
+    let expected = std::any::type_name::<T>();
+    let mut buffers: Vec<Vec<T>> = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        // Legacy headers are always numbered 1 so the number isn't used
+        if is_header(line) {
+            buffers.push(Vec::new());
+            continue;
+        }
+
+        let value = parse(line).ok_or_else(|| DumpError::InvalidLine {
+            line: index + 1,
+            value: line.to_string(),
+            expected,
+        })?;
+
+        match buffers.last_mut() {
+            Some(buffer) => buffer.push(value),
+            None => buffers.push(vec![value]),
+        }
+    }
+
+    Ok(buffers)
+}
+
+fn write_buffers<W, T>(
+    out: &mut W,
+    buffers: &[Vec<T>],
+    write_value: impl Fn(&mut W, &T) -> io::Result<()>,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    for (index, buffer) in buffers.iter().enumerate() {
+        writeln!(out, "Buffer {}", index + 1)?;
+        for value in buffer {
+            write_value(out, value)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Write};
+
+    use super::{parse_index, parse_position, read_buffers, write_buffers, BufferDump, DumpError};
+
+    #[test]
+    fn test_read_legacy() {
+        let positions = "Buffer 1\n0 0 0\n1 0 0\nBuffer 1\n0 1 0\n";
+        let positions = read_buffers(Cursor::new(positions), parse_position).unwrap();
+        assert_eq!(
+            positions,
+            vec![
+                vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]],
+                vec![[0.0, 1.0, 0.0]]
+            ]
+        );
+
+        // Legacy index dumps have no header
+        let indices = read_buffers(Cursor::new("0\n1\n2\n"), parse_index).unwrap();
+        assert_eq!(indices, vec![vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dump = BufferDump {
+            vertex_buffers: vec![vec![[0.5, -1.0, 2.25]], vec![[1.0, 2.0, 3.0]]],
+            index_buffers: vec![vec![0, 1, 2], vec![2, 1, 0]],
+        };
+
+        let mut positions = Vec::new();
+        write_buffers(&mut positions, &dump.vertex_buffers, |out, [x, y, z]| {
+            writeln!(out, "{} {} {}", x, y, z)
+        })
+        .unwrap();
+        let text = String::from_utf8(positions.clone()).unwrap();
+        assert!(text.starts_with("Buffer 1\n"));
+        assert!(text.contains("Buffer 2\n"));
+
+        let positions = read_buffers(Cursor::new(positions), parse_position).unwrap();
+        assert_eq!(positions, dump.vertex_buffers);
+    }
+
+    #[test]
+    fn test_invalid_line() {
+        let result = read_buffers(Cursor::new("Buffer 1\n0 0\n"), parse_position);
+        assert!(matches!(
+            result,
+            Err(DumpError::InvalidLine { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_geometry() {
+        let dump = BufferDump {
+            vertex_buffers: vec![vec![[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0; 3]]],
+            index_buffers: vec![vec![0, 1, 2, 3]],
+        };
+
+        assert_eq!(dump.to_geometry(true).groups[0].triangles.len(), 2);
+        assert_eq!(dump.to_geometry(false).groups[0].triangles.len(), 1);
+    }
+}
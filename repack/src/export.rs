@@ -0,0 +1,527 @@
+//! Export of the DirectX and PS2 mesh geometry to standard OBJ, PLY and glTF
+//! files. Triangles are grouped by the material that draws them, OBJ files get
+//! a group per material, PLY faces get a material index property and glTF
+//! meshes get a primitive per material
+
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::json;
+use thiserror::Error;
+
+use crate::{
+    offsets::ValidationError,
+    provenance::Provenance,
+    raw::ps2::{decode_vif_packet, VifError},
+    view::{DxClusterView, MeshView},
+};
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("unknown export format {0:?}, expected obj, ply or gltf")]
+    UnknownFormat(String),
+    #[error("mesh has no platform specific mesh data")]
+    MissingMeshData,
+    #[error("cluster of material {material} uses missing vertex buffer {index}")]
+    MissingVertexBuffer { material: usize, index: u8 },
+    #[error("cluster of material {material} uses missing index buffer {index}")]
+    MissingIndexBuffer { material: usize, index: u8 },
+    #[error("cluster of material {material} reads indices {start}..{end} past the index buffer")]
+    IndicesOutOfRange {
+        material: usize,
+        start: usize,
+        end: usize,
+    },
+    #[error("material {material} uses missing packet {index}")]
+    MissingPacket { material: usize, index: usize },
+    #[error("packet {index}: {error}")]
+    Packet { index: usize, error: VifError },
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Obj,
+    Ply,
+    Gltf,
+}
+
+impl ExportFormat {
+    /// File extension of the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Obj => "obj",
+            ExportFormat::Ply => "ply",
+            ExportFormat::Gltf => "gltf",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "obj" => Ok(ExportFormat::Obj),
+            "ply" => Ok(ExportFormat::Ply),
+            "gltf" => Ok(ExportFormat::Gltf),
+            _ => Err(ExportError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+/// glTF accessor component types
+const GLTF_FLOAT: u32 = 5126;
+const GLTF_UNSIGNED_INT: u32 = 5125;
+/// glTF buffer view targets
+const GLTF_ARRAY_BUFFER: u32 = 34962;
+const GLTF_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Triangles drawn by a single material
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaterialGroup {
+    pub material: usize,
+    /// Indices into the positions of the [ExportGeometry]
+    pub triangles: Vec<[u32; 3]>,
+}
+
+/// Geometry of a mesh with the vertex buffers merged together
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportGeometry {
+    pub positions: Vec<[f32; 3]>,
+    pub groups: Vec<MaterialGroup>,
+}
+
+/// Decodes a triangle strip, every other triangle has reversed winding and
+/// triangles with repeated indices are the degenerate joins between strips
+pub fn strip_triangles(indices: &[u16]) -> Vec<[u32; 3]> {
+    indices
+        .windows(3)
+        .enumerate()
+        .map(|(index, value)| {
+            let [a, b, c] = [value[0] as u32, value[1] as u32, value[2] as u32];
+            match index % 2 {
+                0 => [a, b, c],
+                _ => [b, a, c],
+            }
+        })
+        .filter(|[a, b, c]| a != b && b != c && a != c)
+        .collect()
+}
+
+/// Decodes a triangle list
+pub fn list_triangles(indices: &[u16]) -> Vec<[u32; 3]> {
+    indices
+        .chunks_exact(3)
+        .map(|value| [value[0] as u32, value[1] as u32, value[2] as u32])
+        .collect()
+}
+
+/// Slice of `count` indices from `start` in the index buffer
+fn index_range(
+    indices: &[u16],
+    material: usize,
+    start: usize,
+    count: usize,
+) -> Result<&[u16], ExportError> {
+    indices
+        .get(start..start + count)
+        .ok_or(ExportError::IndicesOutOfRange {
+            material,
+            start,
+            end: start + count,
+        })
+}
+
+/// Triangles drawn by the list and strips of a cluster of the material,
+/// indexing the vertex buffer of the cluster
+pub(crate) fn cluster_triangles(
+    cluster: &DxClusterView,
+    indices: &[u16],
+    material: usize,
+) -> Result<Vec<[u32; 3]>, ExportError> {
+    let mut triangles = Vec::new();
+
+    let (start, count) = cluster.tri_list();
+    if count > 0 {
+        let list = index_range(indices, material, start, count * 3)?;
+        triangles.extend(list_triangles(list));
+    }
+
+    for strip in cluster.strips()?.iter().flat_map(|array| array.iter()) {
+        // Strips have two more indices than triangles
+        let strip = index_range(
+            indices,
+            material,
+            strip.start_vindex() as usize,
+            strip.tri_count() as usize + 2,
+        )?;
+        triangles.extend(strip_triangles(strip));
+    }
+
+    Ok(triangles)
+}
+
+impl ExportGeometry {
+    /// Collects the geometry of every material of a DirectX mesh through the
+    /// views, the file is never loaded in place so this works on any host
+    pub fn from_dx_view(mesh: &MeshView) -> Result<Self, ExportError> {
+        let dx_mesh = mesh.dx_mesh()?.ok_or(ExportError::MissingMeshData)?;
+
+        let mut positions = Vec::new();
+        let mut bases = Vec::new();
+        for buffer in dx_mesh
+            .vertex_buffers()?
+            .iter()
+            .flat_map(|array| array.iter())
+        {
+            bases.push(positions.len() as u32);
+            positions.extend(buffer.positions()?.iter().flat_map(|array| array.iter()));
+        }
+
+        let mut groups = Vec::new();
+        for (material, value) in mesh
+            .materials()?
+            .iter()
+            .flat_map(|array| array.iter())
+            .enumerate()
+        {
+            let Some(platform) = value.dx_material()? else {
+                continue;
+            };
+
+            let mut triangles = Vec::new();
+            for cluster in platform.clusters()?.iter().flat_map(|array| array.iter()) {
+                let base = *bases.get(cluster.vertex_buffer_index() as usize).ok_or(
+                    ExportError::MissingVertexBuffer {
+                        material,
+                        index: cluster.vertex_buffer_index(),
+                    },
+                )?;
+                let indices: Vec<u16> = dx_mesh
+                    .index_buffer(cluster.index_buffer_index() as usize)?
+                    .ok_or(ExportError::MissingIndexBuffer {
+                        material,
+                        index: cluster.index_buffer_index(),
+                    })?
+                    .iter()
+                    .collect();
+
+                triangles.extend(
+                    cluster_triangles(&cluster, &indices, material)?
+                        .into_iter()
+                        .map(|triangle| triangle.map(|index| index + base)),
+                );
+            }
+
+            if !triangles.is_empty() {
+                groups.push(MaterialGroup {
+                    material,
+                    triangles,
+                });
+            }
+        }
+
+        Ok(Self { positions, groups })
+    }
+
+    /// Collects the geometry of every material of a PS2 mesh, the vertices
+    /// of each packet are decoded from its VIF data
+    pub fn from_ps2(mesh: &MeshView) -> Result<Self, ExportError> {
+        let ps2_mesh = mesh.ps2_mesh()?.ok_or(ExportError::MissingMeshData)?;
+        let packets = ps2_mesh.packets()?;
+
+        let mut positions = Vec::new();
+        let mut groups = Vec::new();
+        for (material, value) in mesh
+            .materials()?
+            .iter()
+            .flat_map(|array| array.iter())
+            .enumerate()
+        {
+            let Some(platform) = value.ps2_material()? else {
+                continue;
+            };
+
+            let mut triangles = Vec::new();
+            for index in platform.packet_range() {
+                let packet = packets
+                    .and_then(|packets| packets.get(index))
+                    .ok_or(ExportError::MissingPacket { material, index })?;
+                let Some(data) = packet.data()? else {
+                    continue;
+                };
+
+                let batches = decode_vif_packet(data)
+                    .map_err(|error| ExportError::Packet { index, error })?;
+                for batch in batches {
+                    let base = positions.len() as u32;
+                    triangles.extend(
+                        batch
+                            .triangles()
+                            .into_iter()
+                            .map(|triangle| triangle.map(|index| index + base)),
+                    );
+                    positions.extend(batch.positions);
+                }
+            }
+
+            if !triangles.is_empty() {
+                groups.push(MaterialGroup {
+                    material,
+                    triangles,
+                });
+            }
+        }
+
+        Ok(Self { positions, groups })
+    }
+
+    /// Writes the geometry in the format, the provenance is embedded
+    /// in the header comments when provided
+    pub fn write<W: Write>(
+        &self,
+        format: ExportFormat,
+        provenance: Option<&Provenance>,
+        out: &mut W,
+    ) -> io::Result<()> {
+        match format {
+            ExportFormat::Obj => self.write_obj(provenance, out),
+            ExportFormat::Ply => self.write_ply(provenance, out),
+            ExportFormat::Gltf => self.write_gltf(provenance, out),
+        }
+    }
+
+    /// Writes the geometry as an OBJ file with a group per material
+    pub fn write_obj<W: Write>(
+        &self,
+        provenance: Option<&Provenance>,
+        out: &mut W,
+    ) -> io::Result<()> {
+        writeln!(out, "# Exported by repack")?;
+        for line in provenance
+            .map(Provenance::comment_lines)
+            .unwrap_or_default()
+        {
+            writeln!(out, "# {}", line)?;
+        }
+
+        for [x, y, z] in &self.positions {
+            writeln!(out, "v {} {} {}", x, y, z)?;
+        }
+
+        for group in &self.groups {
+            writeln!(out, "g material_{}", group.material)?;
+            // OBJ indices are 1 based
+            for [a, b, c] in &group.triangles {
+                writeln!(out, "f {} {} {}", a + 1, b + 1, c + 1)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the geometry as an ASCII PLY file, PLY has no groups
+    /// so each face stores the index of its material
+    pub fn write_ply<W: Write>(
+        &self,
+        provenance: Option<&Provenance>,
+        out: &mut W,
+    ) -> io::Result<()> {
+        let face_count: usize = self.groups.iter().map(|group| group.triangles.len()).sum();
+
+        writeln!(out, "ply")?;
+        writeln!(out, "format ascii 1.0")?;
+        writeln!(out, "comment Exported by repack")?;
+        for line in provenance
+            .map(Provenance::comment_lines)
+            .unwrap_or_default()
+        {
+            writeln!(out, "comment {}", line)?;
+        }
+        writeln!(out, "element vertex {}", self.positions.len())?;
+        writeln!(out, "property float x")?;
+        writeln!(out, "property float y")?;
+        writeln!(out, "property float z")?;
+        writeln!(out, "element face {}", face_count)?;
+        writeln!(out, "property list uchar uint vertex_indices")?;
+        writeln!(out, "property uint material")?;
+        writeln!(out, "end_header")?;
+
+        for [x, y, z] in &self.positions {
+            writeln!(out, "{} {} {}", x, y, z)?;
+        }
+
+        for group in &self.groups {
+            for [a, b, c] in &group.triangles {
+                writeln!(out, "3 {} {} {} {}", a, b, c, group.material)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the geometry as a glTF file with the buffer embedded as a data
+    /// URI, the provenance is stored in the extras of the asset
+    pub fn write_gltf<W: Write>(
+        &self,
+        provenance: Option<&Provenance>,
+        out: &mut W,
+    ) -> io::Result<()> {
+        let mut asset = json!({
+            "version": "2.0",
+            "generator": "repack",
+        });
+        if let Some(provenance) = provenance {
+            asset["extras"] = serde_json::to_value(provenance)?;
+        }
+
+        // Accessors need at least one element so empty geometry only
+        // gets the asset
+        if self.groups.is_empty() {
+            serde_json::to_writer_pretty(&mut *out, &json!({ "asset": asset }))?;
+            return Ok(());
+        }
+
+        let mut buffer = Vec::new();
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for position in &self.positions {
+            for (axis, value) in position.iter().enumerate() {
+                buffer.extend(value.to_le_bytes());
+                min[axis] = min[axis].min(*value);
+                max[axis] = max[axis].max(*value);
+            }
+        }
+
+        let mut views = vec![json!({
+            "buffer": 0,
+            "byteOffset": 0,
+            "byteLength": buffer.len(),
+            "target": GLTF_ARRAY_BUFFER,
+        })];
+        let mut accessors = vec![json!({
+            "bufferView": 0,
+            "componentType": GLTF_FLOAT,
+            "count": self.positions.len(),
+            "type": "VEC3",
+            "min": min,
+            "max": max,
+        })];
+        let mut materials = Vec::new();
+        let mut primitives = Vec::new();
+
+        // Positions and indices are both 4 bytes wide so every view stays
+        // aligned without padding
+        for group in &self.groups {
+            let offset = buffer.len();
+            for index in group.triangles.iter().flatten() {
+                buffer.extend(index.to_le_bytes());
+            }
+
+            views.push(json!({
+                "buffer": 0,
+                "byteOffset": offset,
+                "byteLength": buffer.len() - offset,
+                "target": GLTF_ELEMENT_ARRAY_BUFFER,
+            }));
+            accessors.push(json!({
+                "bufferView": views.len() - 1,
+                "componentType": GLTF_UNSIGNED_INT,
+                "count": group.triangles.len() * 3,
+                "type": "SCALAR",
+            }));
+            materials.push(json!({ "name": format!("material_{}", group.material) }));
+            primitives.push(json!({
+                "attributes": { "POSITION": 0 },
+                "indices": accessors.len() - 1,
+                "material": materials.len() - 1,
+            }));
+        }
+
+        let document = json!({
+            "asset": asset,
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": primitives }],
+            "materials": materials,
+            "accessors": accessors,
+            "bufferViews": views,
+            "buffers": [{
+                "byteLength": buffer.len(),
+                "uri": format!("data:application/octet-stream;base64,{}", STANDARD.encode(&buffer)),
+            }],
+        });
+        serde_json::to_writer_pretty(&mut *out, &document)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use binrw::Endian;
+
+    use crate::{fixture::triangle_mesh, provenance::Provenance, view::MeshView};
+
+    use super::{strip_triangles, ExportFormat, ExportGeometry, MaterialGroup};
+
+    #[test]
+    fn test_strip_triangles() {
+        // Repeated indices join the two strips
+        let triangles = strip_triangles(&[0, 1, 2, 3, 3, 4, 4, 5, 6]);
+        assert_eq!(triangles, vec![[0, 1, 2], [2, 1, 3], [4, 5, 6]]);
+    }
+
+    #[test]
+    fn test_export_fixture() {
+        let bytes = triangle_mesh();
+        let geometry =
+            ExportGeometry::from_dx_view(&MeshView::new(&bytes, Endian::Little).unwrap()).unwrap();
+
+        assert_eq!(geometry.positions.len(), 3);
+        assert_eq!(
+            geometry.groups,
+            vec![MaterialGroup {
+                material: 0,
+                triangles: vec![[0, 1, 2]],
+            }]
+        );
+
+        let mut obj = Vec::new();
+        geometry.write(ExportFormat::Obj, None, &mut obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        assert!(obj.contains("g material_0\nf 1 2 3\n"));
+
+        let provenance = Provenance::default().with_setting("format", "ply");
+        let mut ply = Vec::new();
+        geometry
+            .write(ExportFormat::Ply, Some(&provenance), &mut ply)
+            .unwrap();
+        let ply = String::from_utf8(ply).unwrap();
+        assert!(ply.contains("element face 1\n"));
+        assert!(ply.contains("comment setting: format=ply\n"));
+        assert!(ply.ends_with("3 0 1 2 0\n"));
+
+        let mut gltf = Vec::new();
+        geometry
+            .write(ExportFormat::Gltf, Some(&provenance), &mut gltf)
+            .unwrap();
+        let gltf: serde_json::Value = serde_json::from_slice(&gltf).unwrap();
+        assert_eq!(gltf["asset"]["version"], "2.0");
+        assert_eq!(gltf["accessors"][0]["count"], 3);
+        assert_eq!(gltf["accessors"][1]["count"], 3);
+        assert_eq!(gltf["materials"][0]["name"], "material_0");
+        // Three positions followed by the three indices
+        assert_eq!(gltf["buffers"][0]["byteLength"], 3 * 12 + 3 * 4);
+
+        assert_eq!("glTF".parse::<ExportFormat>().unwrap(), ExportFormat::Gltf);
+        assert!("fbx".parse::<ExportFormat>().is_err());
+    }
+}
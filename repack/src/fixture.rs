@@ -0,0 +1,263 @@
+//! Synthetic assets built in memory so tests don't depend on the
+//! game data being present
+
+use binrw::Endian;
+
+use crate::{
+    builder::{ApeBone, ApeBuilder, ApeMaterial, ApeTexture, ApeVertex},
+    platform::Platform,
+};
+
+/// Name of the fixture mesh
+pub const FIXTURE_MESH_NAME: &str = "fixture";
+/// Name of the single bone in the fixture mesh
+pub const FIXTURE_BONE_NAME: &str = "root";
+/// Positions of the fixture triangle
+pub const FIXTURE_POSITIONS: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+/// Tint of the single material in the fixture mesh
+pub const FIXTURE_TINT: [f32; 3] = [1.0, 0.5, 0.25];
+/// Name of the texture sampled by the material of the textured fixture
+pub const FIXTURE_TEXTURE_NAME: &str = "fixture_tex";
+/// Encoded texels of the 4x4 texture of the textured fixture, a single
+/// DXT1 block of solid red
+pub const FIXTURE_TEXELS: [u8; 8] = [0x00, 0xF8, 0x00, 0xF8, 0, 0, 0, 0];
+/// Texel format stored in the definition of the fixture texture
+pub const FIXTURE_TEX_FMT: u8 = 12;
+
+/// Builds a PC .ape file containing a single triangle, a single bone and
+/// a single material
+pub fn triangle_mesh() -> Vec<u8> {
+    triangle_file(Endian::Little)
+}
+
+/// Builds [triangle_mesh] in the provided byte order, the file uses the
+/// 32-bit layout of the files so it can be read by the views and the
+/// survey on any host
+pub fn triangle_file(endian: Endian) -> Vec<u8> {
+    triangle_builder()
+        .build(Platform::Pc, endian.into())
+        .expect("Fixture mesh is valid")
+}
+
+/// Builds [triangle_file] with its material sampling a texture stored in the
+/// file, the texture definition is embedded in its texture data which points
+/// back to it the same as the game files
+pub fn textured_triangle_file(endian: Endian) -> Vec<u8> {
+    triangle_builder()
+        .with_texture(ApeTexture::new(
+            FIXTURE_TEXTURE_NAME,
+            FIXTURE_TEX_FMT,
+            4,
+            4,
+            FIXTURE_TEXELS.to_vec(),
+        ))
+        .build(Platform::Pc, endian.into())
+        .expect("Fixture mesh is valid")
+}
+
+fn triangle_builder() -> ApeBuilder {
+    let vertices = FIXTURE_POSITIONS
+        .iter()
+        .map(|position| ApeVertex::new(*position).with_uv([position[0], position[1]]))
+        .collect();
+
+    ApeBuilder::new(FIXTURE_MESH_NAME, vertices, vec![0, 1, 2])
+        .with_material(ApeMaterial {
+            tint: FIXTURE_TINT,
+            ..Default::default()
+        })
+        .with_bone(ApeBone::new(FIXTURE_BONE_NAME, None))
+        .with_lod_distance(100.0)
+        // Unit radius so the material radius of the triangle, ~0.745 from
+        // the centroid, rounds up to 191 / 255
+        .with_bound_sphere(1.0, [0.5, 0.5, 0.0])
+}
+
+#[cfg(test)]
+mod test {
+    use binrw::Endian;
+
+    use crate::{layout::FileLayout, model::MeshModel, patch::apply_patches, view::MeshView};
+
+    use super::{
+        triangle_file, FIXTURE_BONE_NAME, FIXTURE_MESH_NAME, FIXTURE_POSITIONS, FIXTURE_TINT,
+    };
+
+    #[test]
+    fn test_triangle_file() {
+        for endian in [Endian::Little, Endian::Big] {
+            let bytes = triangle_file(endian);
+            let mesh = MeshView::new(&bytes, endian).unwrap();
+
+            assert_eq!(mesh.name(), FIXTURE_MESH_NAME);
+            assert_eq!(mesh.lod_distances(), vec![100.0]);
+
+            let bones = mesh.bones().unwrap().unwrap();
+            assert_eq!(bones.len(), 1);
+            assert_eq!(bones.get(0).unwrap().name(), FIXTURE_BONE_NAME);
+
+            let materials = mesh.materials().unwrap().unwrap();
+            assert_eq!(materials.len(), 1);
+            let material = materials.get(0).unwrap();
+            assert_eq!(material.tint(), FIXTURE_TINT);
+            assert_eq!(material.record().compressed_radius, 191);
+
+            let dx_mesh = mesh.dx_mesh().unwrap().unwrap();
+            let positions: Vec<[f32; 3]> = dx_mesh
+                .vertex_buffers()
+                .unwrap()
+                .unwrap()
+                .get(0)
+                .unwrap()
+                .positions()
+                .unwrap()
+                .unwrap()
+                .iter()
+                .collect();
+            assert_eq!(positions, FIXTURE_POSITIONS.to_vec());
+            assert!(dx_mesh.index_buffer(0).unwrap().is_some());
+            assert!(dx_mesh.index_buffer(1).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn test_triangle_file_layout() {
+        for endian in [Endian::Little, Endian::Big] {
+            let bytes = triangle_file(endian);
+            let mesh = MeshView::new(&bytes, endian).unwrap();
+
+            let layout = FileLayout::from_view(&mesh).unwrap();
+            assert!(layout.orphaned().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_apply_patches() {
+        for endian in [Endian::Little, Endian::Big] {
+            let mut bytes = triangle_file(endian);
+
+            let mut model = MeshModel::from_view(&MeshView::new(&bytes, endian).unwrap()).unwrap();
+            model.rename_bone(0, "spine".to_string()).unwrap();
+            model.set_lod_distance(0, 50.0).unwrap();
+            model.set_material_tint(0, [0.25; 3]).unwrap();
+            assert!(model.rename_bone(1, "missing".to_string()).is_err());
+
+            apply_patches(model.patches(), &mut bytes, endian).unwrap();
+
+            let mesh = MeshView::new(&bytes, endian).unwrap();
+            assert_eq!(
+                mesh.bones().unwrap().unwrap().get(0).unwrap().name(),
+                "spine"
+            );
+            assert_eq!(mesh.lod_distances(), vec![50.0]);
+            let material = mesh.materials().unwrap().unwrap().get(0).unwrap();
+            assert_eq!(material.tint(), [0.25; 3]);
+        }
+    }
+
+    /// Loading the fixture in place, only possible on hosts sharing the
+    /// 32-bit layout of the files
+    #[cfg(target_pointer_width = "32")]
+    mod in_place {
+        use crate::{
+            fixture::{
+                triangle_mesh, FIXTURE_BONE_NAME, FIXTURE_MESH_NAME, FIXTURE_POSITIONS,
+                FIXTURE_TINT,
+            },
+            raw::dx::VertexBufferError,
+            st::{load_memory_struct, FMesh, SourceEndian, FDATA_MAX_LOD_MESH_COUNT},
+        };
+
+        #[test]
+        fn test_load_triangle_mesh() {
+            let buffer = triangle_mesh().into_boxed_slice();
+            let mesh = unsafe { load_memory_struct::<FMesh>(buffer, SourceEndian::Little) };
+            assert_eq!(mesh.base_ptr() as usize % 16, 0);
+
+            assert_eq!(mesh.name.as_string(), FIXTURE_MESH_NAME);
+            assert_eq!(mesh.lod_distances(), &[100.0]);
+
+            let bones = mesh.bones().unwrap();
+            assert_eq!(bones.len(), 1);
+            assert_eq!(bones[0].name.as_string(), FIXTURE_BONE_NAME);
+            // Bone matrices are followed in place so must be aligned
+            assert_eq!(
+                std::ptr::addr_of!(bones[0].at_rest_bone_to_model) as usize % 16,
+                0
+            );
+
+            let materials = mesh.materials().unwrap();
+            assert_eq!(materials.len(), 1);
+            assert_eq!(materials[0].material_tint.green, FIXTURE_TINT[1]);
+
+            let dx_mesh = mesh.impl_specific_mut().unwrap();
+            assert_eq!(dx_mesh.index_buffers(), vec![&[0u16, 1, 2][..]]);
+
+            let collision = dx_mesh.collision_vertices(0).unwrap();
+            assert_eq!(collision.len(), FIXTURE_POSITIONS.len());
+            assert_eq!(
+                [collision[1].x, collision[1].y, collision[1].z],
+                FIXTURE_POSITIONS[1]
+            );
+            assert!(dx_mesh.collision_vertices(1).is_none());
+
+            let vertex_buffers = dx_mesh.vertex_buffers_mut().unwrap();
+            assert_eq!(
+                vertex_buffers[0].positions().unwrap(),
+                FIXTURE_POSITIONS.to_vec()
+            );
+            assert_eq!(
+                vertex_buffers[0].normals().unwrap().unwrap(),
+                vec![[0.0, 0.0, 1.0]; 3]
+            );
+            assert_eq!(
+                vertex_buffers[0].uvs(0).unwrap().unwrap()[1],
+                [FIXTURE_POSITIONS[1][0], FIXTURE_POSITIONS[1][1]]
+            );
+            // The fixture layout has a single set of coordinates
+            assert_eq!(vertex_buffers[0].uvs(1).unwrap(), None);
+            assert_eq!(
+                vertex_buffers[0].colors().unwrap().unwrap()[0],
+                [1.0, 1.0, 1.0, 1.0]
+            );
+        }
+
+        #[test]
+        fn test_stride_mismatch() {
+            let buffer = triangle_mesh().into_boxed_slice();
+            let mesh = unsafe { load_memory_struct::<FMesh>(buffer, SourceEndian::Little) };
+
+            let vertex_buffers = mesh
+                .impl_specific_mut()
+                .unwrap()
+                .vertex_buffers_mut()
+                .unwrap();
+            vertex_buffers[0].bytes_per_vertex += 4;
+            assert!(matches!(
+                vertex_buffers[0].positions(),
+                Err(VertexBufferError::StrideMismatch { .. })
+            ));
+        }
+
+        #[test]
+        fn test_malformed_values() {
+            let buffer = triangle_mesh().into_boxed_slice();
+            let mut mesh = unsafe { load_memory_struct::<FMesh>(buffer, SourceEndian::Little) };
+
+            mesh.lod_count = 255;
+            assert_eq!(mesh.lod_distances().len(), FDATA_MAX_LOD_MESH_COUNT);
+
+            // Index buffers past the count aren't read
+            let dx_mesh = mesh.impl_specific_mut().unwrap();
+            assert_eq!(dx_mesh.index_buffer(1), None);
+            assert_eq!(dx_mesh.index_count(1), 0);
+
+            let vertex_buffers = dx_mesh.vertex_buffers_mut().unwrap();
+            vertex_buffers[0].info_index = 42;
+            assert!(matches!(
+                vertex_buffers[0].positions(),
+                Err(VertexBufferError::UnknownLayout(42))
+            ));
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use std::ops::Range;
+
+use openglitch_formats::{
+    mesh::{FMesh, FMeshTexLayerID},
+    texture::{CFTexInst, FTexDef},
+    types::PtrOffset,
+};
+
+use crate::{
+    offsets::ValidationError,
+    view::{ArrayView, DxMaterialView, DxMeshView, MeshView, TexDataView, View},
+};
+
+/// Gaps smaller than this are treated as alignment padding rather
+/// than orphaned data
+const MIN_ORPHAN_SIZE: usize = 16;
+
+/// Region of the file referenced by a parsed structure
+#[derive(Debug, Clone)]
+pub struct Region {
+    /// Name of the structure or field the region belongs to
+    pub name: String,
+    pub offset: usize,
+    pub size: usize,
+}
+
+impl Region {
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.size
+    }
+}
+
+/// Map of all the regions of a file referenced by the parsed structures
+#[derive(Debug, Default)]
+pub struct FileLayout {
+    /// Length of the file in bytes
+    pub length: usize,
+    /// Referenced regions sorted by offset
+    pub regions: Vec<Region>,
+}
+
+/// Outcome of stripping orphaned data from a file
+#[derive(Debug, Default)]
+pub struct StripReport {
+    /// Bytes removed from the end of the file
+    pub truncated: usize,
+    /// Bytes within the file that were zeroed
+    pub zeroed: usize,
+}
+
+impl FileLayout {
+    /// Walks a DirectX mesh through the views recording every region it
+    /// references, the file is never loaded in place so this works on any host
+    pub fn from_view(mesh: &MeshView) -> Result<FileLayout, ValidationError> {
+        let mut builder = LayoutBuilder {
+            length: mesh.file_len(),
+            known: Vec::new(),
+            opaque: Vec::new(),
+        };
+
+        builder.add_mesh(mesh)?;
+        Ok(builder.finish())
+    }
+
+    /// Ranges of the file that are not referenced by any structure
+    pub fn orphaned(&self) -> Vec<Range<usize>> {
+        let mut out = Vec::new();
+        let mut cursor = 0;
+
+        for region in &self.regions {
+            if region.offset > cursor && region.offset - cursor >= MIN_ORPHAN_SIZE {
+                out.push(cursor..region.offset);
+            }
+            cursor = cursor.max(region.offset + region.size);
+        }
+
+        if self.length > cursor && self.length - cursor >= MIN_ORPHAN_SIZE {
+            out.push(cursor..self.length);
+        }
+
+        out
+    }
+
+    /// Total number of orphaned bytes
+    pub fn orphaned_bytes(&self) -> usize {
+        self.orphaned().iter().map(|range| range.len()).sum()
+    }
+
+    /// Strips the orphaned data from the provided unfixed file bytes. Offsets are
+    /// not relocated so orphans at the end of the file are truncated and orphans
+    /// within the file are zeroed
+    pub fn strip(&self, bytes: &mut Vec<u8>) -> StripReport {
+        let mut report = StripReport::default();
+
+        for range in self.orphaned() {
+            if range.end >= bytes.len() {
+                report.truncated += bytes.len() - range.start;
+                bytes.truncate(range.start);
+            } else {
+                report.zeroed += range.len();
+                bytes[range].fill(0);
+            }
+        }
+
+        report
+    }
+}
+
+struct LayoutBuilder {
+    /// Length of the file
+    length: usize,
+    /// Regions with a known size
+    known: Vec<Region>,
+    /// Regions where only the start is known
+    opaque: Vec<(String, usize)>,
+}
+
+impl LayoutBuilder {
+    /// Adds the region of `size` bytes at the offset
+    fn add(&mut self, name: impl Into<String>, offset: PtrOffset, size: usize) {
+        if let Some(offset) = offset.offset() {
+            self.known.push(Region {
+                name: name.into(),
+                offset: offset as usize,
+                size,
+            });
+        }
+    }
+
+    /// Adds the region covered by an array
+    fn add_array<'a, V: View<'a>>(
+        &mut self,
+        name: impl Into<String>,
+        array: Option<&ArrayView<'a, V>>,
+    ) {
+        if let Some(array) = array {
+            self.known.push(Region {
+                name: name.into(),
+                offset: array.offset(),
+                size: array.byte_len(),
+            });
+        }
+    }
+
+    /// Adds a region where the size is not known, these are extended up
+    /// to the next region so unknown data is never considered orphaned
+    fn add_opaque(&mut self, name: impl Into<String>, offset: PtrOffset) {
+        if let Some(offset) = offset.offset() {
+            if (offset as usize) < self.length {
+                self.opaque.push((name.into(), offset as usize));
+            }
+        }
+    }
+
+    fn add_mesh(&mut self, mesh: &MeshView) -> Result<(), ValidationError> {
+        let header = mesh.header();
+
+        self.add("FMesh", PtrOffset(0), FMesh::SIZE);
+        self.add_array("FMesh.segments", mesh.segments()?.as_ref());
+        let bones = mesh.bones()?;
+        self.add_array("FMesh.bones", bones.as_ref());
+        self.add_array("FMesh.lights", mesh.lights()?.as_ref());
+        self.add_array(
+            "FMesh.skeleton_index_array",
+            mesh.skeleton_indices()?.as_ref(),
+        );
+
+        self.add_opaque("FMesh.collision_tree", header.collision_tree);
+
+        let materials = mesh.materials()?;
+        self.add_array("FMesh.materials", materials.as_ref());
+        for (index, material) in materials.iter().flat_map(|array| array.iter()).enumerate() {
+            let name = format!("FMesh.materials[{}]", index);
+            let record = material.record();
+            self.add_opaque(
+                format!("{}.shader_light_registers", name),
+                record.shader_light_registers,
+            );
+            self.add_opaque(
+                format!("{}.shader_surface_registers", name),
+                record.shader_surface_registers,
+            );
+
+            if let Some(platform_data) = material.dx_material()? {
+                self.add_dx_material(&name, record.platform_data, &platform_data)?;
+            }
+        }
+
+        let layers = mesh.tex_layers()?;
+        self.add_array("FMesh.tex_layers", layers.as_ref());
+        for (index, layer) in layers.iter().flat_map(|array| array.iter()).enumerate() {
+            self.add_tex_layer(mesh, &format!("FMesh.tex_layers[{}]", index), &layer)?;
+        }
+
+        if let Some(dx_mesh) = mesh.dx_mesh()? {
+            self.add_dx_mesh(header.mesh_is, &dx_mesh)?;
+        }
+
+        Ok(())
+    }
+
+    fn add_tex_layer(
+        &mut self,
+        mesh: &MeshView,
+        name: &str,
+        layer: &FMeshTexLayerID,
+    ) -> Result<(), ValidationError> {
+        let name = format!("{}.flip_palette", name);
+        let palette = mesh.flip_palette(layer)?;
+        self.add_array(&name, palette.as_ref());
+
+        for (index, tex_inst_offset) in palette.iter().flat_map(|array| array.iter()).enumerate() {
+            let name = format!("{}[{}]", name, index);
+            self.add(&name, tex_inst_offset, CFTexInst::SIZE);
+
+            let Some(tex_inst) = mesh.tex_inst(tex_inst_offset)? else {
+                continue;
+            };
+
+            self.add(format!("{}.tex_def", name), tex_inst.tex_def, FTexDef::SIZE);
+            if let Some(tex_def) = mesh.tex_def(tex_inst.tex_def)? {
+                self.add_tex_data(
+                    mesh,
+                    &format!("{}.tex_def.tex_data", name),
+                    tex_def.tex_data,
+                )?;
+            }
+
+            for (buffer_index, tex_data) in tex_inst.tex_buffer.iter().enumerate() {
+                self.add_tex_data(
+                    mesh,
+                    &format!("{}.tex_buffer[{}]", name, buffer_index),
+                    *tex_data,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_tex_data(
+        &mut self,
+        mesh: &MeshView,
+        name: &str,
+        offset: PtrOffset,
+    ) -> Result<(), ValidationError> {
+        self.add(name, offset, TexDataView::SIZE);
+
+        if let Some(tex_data) = mesh.dx_tex_data(offset)? {
+            self.add_array(
+                format!("{}.image_data", name),
+                tex_data.image_data()?.as_ref(),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn add_dx_material(
+        &mut self,
+        name: &str,
+        offset: PtrOffset,
+        value: &DxMaterialView,
+    ) -> Result<(), ValidationError> {
+        let name = format!("{}.platform_data", name);
+        self.add(&name, offset, DxMaterialView::SIZE);
+
+        let clusters = value.clusters()?;
+        self.add_array(format!("{}.clusters", name), clusters.as_ref());
+        for (index, cluster) in clusters.iter().flat_map(|array| array.iter()).enumerate() {
+            let name = format!("{}.clusters[{}]", name, index);
+            self.add_array(format!("{}.strips", name), cluster.strips()?.as_ref());
+            self.add_opaque(format!("{}.push_buffer", name), cluster.push_buffer());
+        }
+
+        Ok(())
+    }
+
+    fn add_dx_mesh(
+        &mut self,
+        offset: PtrOffset,
+        dx_mesh: &DxMeshView,
+    ) -> Result<(), ValidationError> {
+        self.add("DxMesh", offset, DxMeshView::SIZE);
+
+        let vertex_buffers = dx_mesh.vertex_buffers()?;
+        self.add_array("DxMesh.vertex_buffers", vertex_buffers.as_ref());
+        for (index, buffer) in vertex_buffers
+            .iter()
+            .flat_map(|array| array.iter())
+            .enumerate()
+        {
+            let name = format!("DxMesh.vertex_buffers[{}]", index);
+
+            self.add_array(
+                format!("{}.vertex_buffer", name),
+                buffer.vertex_data()?.as_ref(),
+            );
+            self.add_array(
+                format!("{}.lmuv_stream", name),
+                buffer.lmuv_stream()?.as_ref(),
+            );
+            self.add_opaque(format!("{}.basis_stream", name), buffer.basis_stream());
+        }
+
+        let collision_buffers = dx_mesh.collision_buffers()?;
+        self.add_array("DxMesh.coll_vertex_buffers", collision_buffers.as_ref());
+        for index in 0..collision_buffers
+            .map(|array| array.len())
+            .unwrap_or_default()
+        {
+            self.add_array(
+                format!("DxMesh.coll_vertex_buffers[{}]", index),
+                dx_mesh.collision_vertices(index)?.as_ref(),
+            );
+        }
+
+        self.add_array("DxMesh.index_counts", dx_mesh.index_counts()?.as_ref());
+        let index_buffers = dx_mesh.index_buffer_offsets()?;
+        self.add_array("DxMesh.index_buffers", index_buffers.as_ref());
+        for index in 0..index_buffers.map(|array| array.len()).unwrap_or_default() {
+            self.add_array(
+                format!("DxMesh.index_buffers[{}]", index),
+                dx_mesh.index_buffer(index)?.as_ref(),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> FileLayout {
+        self.known.sort_by_key(|region| region.offset);
+
+        // Extend the opaque regions up to the next region that starts after them
+        let mut regions = self.known.clone();
+        for (name, offset) in self.opaque {
+            let end = self
+                .known
+                .iter()
+                .map(|region| region.offset)
+                .chain(std::iter::once(self.length))
+                .filter(|value| *value > offset)
+                .min()
+                .unwrap_or(self.length);
+
+            regions.push(Region {
+                name,
+                offset,
+                size: end - offset,
+            });
+        }
+
+        regions.sort_by_key(|region| region.offset);
+
+        FileLayout {
+            length: self.length,
+            regions,
+        }
+    }
+}
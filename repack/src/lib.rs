@@ -0,0 +1,36 @@
+//! Reading, checking and rewriting compiled game meshes. The command line
+//! tool is built over these modules, they're a library so the fuzz targets
+//! can drive the parsers directly
+
+pub mod batch;
+pub mod builder;
+pub mod cancel;
+pub mod cli;
+pub mod collision;
+pub mod convert;
+pub mod diff;
+pub mod docs;
+pub mod dump;
+pub mod export;
+#[cfg(test)]
+pub mod fixture;
+pub mod layout;
+pub mod lint;
+pub mod model;
+pub mod offsets;
+pub mod pack;
+pub mod parts;
+pub mod patch;
+pub mod provenance;
+pub mod raw;
+pub mod registers;
+pub mod report;
+pub mod restrip;
+pub mod sanity;
+#[cfg(test)]
+pub mod snapshot;
+pub mod st;
+pub mod survey;
+pub mod tui;
+pub mod view;
+pub use openglitch_formats::{platform, types};
@@ -0,0 +1,540 @@
+//! Checks of a mesh against the limits of the engine, files produced by
+//! hand or by other tools can load in the repacker but crash the game when
+//! they break one of these limits. Each issue names the offending field and
+//! how the value needs to change
+
+use std::fmt::Display;
+
+use openglitch_formats::mesh::{FDATA_MAX_LOD_MESH_COUNT, FDATA_VW_COUNT_PER_VTX};
+
+use crate::{offsets::ValidationError, view::MeshView};
+
+/// Index used for empty bone, texture layer and parent slots
+const EMPTY_INDEX: u8 = 255;
+
+/// Engine limit broken by an issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintRule {
+    /// Segments can only blend between a few bone matrices
+    SegmentBones,
+    /// Textures must be a power of two in each dimension
+    TextureSize,
+    /// Names must fit in their fixed length field with a null terminator
+    NameLength,
+    /// Meshes need between one and the maximum number of LODs
+    LodCount,
+    /// Indices must reference existing values
+    IndexRange,
+    /// Collision buffers must mirror the positions of the vertex buffer at
+    /// the same index, their length is taken from its vertex count
+    CollisionBuffer,
+}
+
+impl Display for LintRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LintRule::SegmentBones => "segment-bones",
+            LintRule::TextureSize => "texture-size",
+            LintRule::NameLength => "name-length",
+            LintRule::LodCount => "lod-count",
+            LintRule::IndexRange => "index-range",
+            LintRule::CollisionBuffer => "collision-buffer",
+        })
+    }
+}
+
+/// Engine limit broken by a value in the mesh
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    pub rule: LintRule,
+    /// Name of the field holding the value
+    pub field: String,
+    /// What is wrong and how to fix it
+    pub message: String,
+}
+
+impl Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} [{}]", self.field, self.message, self.rule)
+    }
+}
+
+#[derive(Default)]
+struct Linter {
+    issues: Vec<LintIssue>,
+}
+
+impl Linter {
+    fn push(&mut self, rule: LintRule, field: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(LintIssue {
+            rule,
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Checks an index is within `count` values, optionally allowing
+    /// the empty index
+    fn check_index(&mut self, field: String, index: u8, count: usize, allow_empty: bool) {
+        if allow_empty && index == EMPTY_INDEX {
+            return;
+        }
+
+        if index as usize >= count {
+            let empty = if allow_empty { " or 255 for none" } else { "" };
+            self.push(
+                LintRule::IndexRange,
+                field,
+                format!(
+                    "index {} is out of range, must be below {}{}",
+                    index, count, empty
+                ),
+            );
+        }
+    }
+}
+
+/// Checks a DirectX mesh against the limits of the engine, the geometry is
+/// read through [MeshView::dx_mesh] so the caller checks the platform
+pub fn lint_mesh(mesh: &MeshView) -> Result<Vec<LintIssue>, ValidationError> {
+    let mut linter = Linter::default();
+
+    lint_names(&mut linter, mesh)?;
+    lint_lods(&mut linter, mesh)?;
+    lint_bones(&mut linter, mesh)?;
+    lint_materials(&mut linter, mesh)?;
+    lint_geometry(&mut linter, mesh)?;
+    lint_collision(&mut linter, mesh)?;
+
+    Ok(linter.issues)
+}
+
+fn lint_names(linter: &mut Linter, mesh: &MeshView) -> Result<(), ValidationError> {
+    if !mesh.header().name.is_terminated() {
+        linter.push(
+            LintRule::NameLength,
+            "name",
+            "mesh name fills the whole field, shorten it to leave room for the null terminator",
+        );
+    }
+
+    for (index, bone) in mesh
+        .bones()?
+        .iter()
+        .flat_map(|array| array.iter())
+        .enumerate()
+    {
+        if !bone.record().name.is_terminated() {
+            linter.push(
+                LintRule::NameLength,
+                format!("bones[{}].name", index),
+                "bone name fills the whole field, shorten it to leave room for the null terminator",
+            );
+        }
+    }
+
+    for (index, layer) in mesh
+        .tex_layers()?
+        .iter()
+        .flat_map(|array| array.iter())
+        .enumerate()
+    {
+        for (page, tex_def) in mesh.layer_textures(&layer)?.into_iter().enumerate() {
+            let Some(tex_def) = tex_def else {
+                continue;
+            };
+
+            if !tex_def.tex_info.name.is_terminated() {
+                linter.push(
+                    LintRule::NameLength,
+                    format!("tex_layers[{}].flip_palette[{}].name", index, page),
+                    "texture name fills the whole field, shorten it to leave room for the null terminator",
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn lint_lods(linter: &mut Linter, mesh: &MeshView) -> Result<(), ValidationError> {
+    let lod_count = mesh.header().lod_count as usize;
+    if lod_count == 0 || lod_count > FDATA_MAX_LOD_MESH_COUNT {
+        linter.push(
+            LintRule::LodCount,
+            "lod_count",
+            format!(
+                "mesh has {} LODs, must have between 1 and {}",
+                lod_count, FDATA_MAX_LOD_MESH_COUNT
+            ),
+        );
+        return Ok(());
+    }
+
+    // LODs are selected by walking the distances so they must be in order
+    for (index, pair) in mesh.lod_distances().windows(2).enumerate() {
+        if pair[1] < pair[0] {
+            linter.push(
+                LintRule::LodCount,
+                format!("lod_distance[{}]", index + 1),
+                format!(
+                    "distance {} is closer than the previous LOD at {}, distances must increase",
+                    pair[1], pair[0]
+                ),
+            );
+        }
+    }
+
+    for (index, material) in mesh
+        .materials()?
+        .iter()
+        .flat_map(|array| array.iter())
+        .enumerate()
+    {
+        if material.lod_mask() as u32 >> lod_count != 0 {
+            linter.push(
+                LintRule::LodCount,
+                format!("materials[{}].lod_mask", index),
+                format!(
+                    "mask {:#04x} uses LODs the mesh doesn't have, only the lowest {} bits can be set",
+                    material.lod_mask(), lod_count
+                ),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn lint_bones(linter: &mut Linter, mesh: &MeshView) -> Result<(), ValidationError> {
+    let bones = mesh.bones()?;
+    let bone_count = bones.map(|array| array.len()).unwrap_or_default();
+
+    if bone_count > 0 {
+        linter.check_index(
+            "root_bone_index".to_string(),
+            mesh.header().root_bone_index as u8,
+            bone_count,
+            true,
+        );
+    }
+
+    for (index, bone) in bones.iter().flat_map(|array| array.iter()).enumerate() {
+        linter.check_index(
+            format!("bones[{}].skeleton.parent_bone_index", index),
+            bone.record().skeleton.parent_bone_index,
+            bone_count,
+            true,
+        );
+    }
+
+    for (index, segment) in mesh
+        .segments()?
+        .iter()
+        .flat_map(|array| array.iter())
+        .enumerate()
+    {
+        let count = segment.bone_mtx_count as usize;
+        if count > FDATA_VW_COUNT_PER_VTX {
+            linter.push(
+                LintRule::SegmentBones,
+                format!("segments[{}].bone_mtx_count", index),
+                format!(
+                    "segment blends {} bones, split it so no segment uses more than {}",
+                    count, FDATA_VW_COUNT_PER_VTX
+                ),
+            );
+        }
+
+        for (slot, bone) in segment.bone_mtx_indices().iter().enumerate() {
+            linter.check_index(
+                format!("segments[{}].bone_mtx_index[{}]", index, slot),
+                *bone,
+                bone_count,
+                true,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn lint_materials(linter: &mut Linter, mesh: &MeshView) -> Result<(), ValidationError> {
+    let layers = mesh.tex_layers()?;
+    let layer_count = layers.map(|array| array.len()).unwrap_or_default();
+
+    for (index, material) in mesh
+        .materials()?
+        .iter()
+        .flat_map(|array| array.iter())
+        .enumerate()
+    {
+        for (slot, layer) in material.tex_layer_id_index().iter().enumerate() {
+            linter.check_index(
+                format!("materials[{}].tex_layer_id_index[{}]", index, slot),
+                *layer,
+                layer_count,
+                true,
+            );
+        }
+
+        for (slot, layer) in material
+            .tex_layer_id_index()
+            .iter()
+            .filter_map(|layer| layers.and_then(|array| array.get(*layer as usize)))
+            .enumerate()
+        {
+            for tex_def in mesh.layer_textures(&layer)?.into_iter().flatten() {
+                let info = &tex_def.tex_info;
+                let (width, height) = (info.texels_across, info.texels_down);
+                if !width.is_power_of_two() || !height.is_power_of_two() {
+                    linter.push(
+                        LintRule::TextureSize,
+                        format!("materials[{}].textures[{}]", index, slot),
+                        format!(
+                            "texture {} is {}x{}, resize it so both sides are a power of two",
+                            info.name, width, height
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn lint_geometry(linter: &mut Linter, mesh: &MeshView) -> Result<(), ValidationError> {
+    let Some(dx_mesh) = mesh.dx_mesh()? else {
+        return Ok(());
+    };
+
+    let vertex_counts: Vec<usize> = dx_mesh
+        .vertex_buffers()?
+        .iter()
+        .flat_map(|array| array.iter())
+        .map(|buffer| buffer.vertex_count() as usize)
+        .collect();
+    let index_buffers: Vec<Vec<u16>> = dx_mesh
+        .index_buffers()?
+        .iter()
+        .map(|buffer| buffer.iter().collect())
+        .collect();
+
+    for (index, material) in mesh
+        .materials()?
+        .iter()
+        .flat_map(|array| array.iter())
+        .enumerate()
+    {
+        let Some(platform) = material.dx_material()? else {
+            continue;
+        };
+
+        for (cluster_index, cluster) in platform
+            .clusters()?
+            .iter()
+            .flat_map(|array| array.iter())
+            .enumerate()
+        {
+            let field = format!("materials[{}].clusters[{}]", index, cluster_index);
+            linter.check_index(
+                format!("{}.vertex_buffer_index", field),
+                cluster.vertex_buffer_index(),
+                vertex_counts.len(),
+                false,
+            );
+            linter.check_index(
+                format!("{}.index_buffer_index", field),
+                cluster.index_buffer_index(),
+                index_buffers.len(),
+                false,
+            );
+
+            let (Some(vertex_count), Some(indices)) = (
+                vertex_counts.get(cluster.vertex_buffer_index() as usize),
+                index_buffers.get(cluster.index_buffer_index() as usize),
+            ) else {
+                continue;
+            };
+
+            let mut ranges = Vec::new();
+            let (start, count) = cluster.tri_list();
+            if count > 0 {
+                ranges.push((format!("{}.tri_list", field), start..start + count * 3));
+            }
+            for (strip_index, strip) in cluster
+                .strips()?
+                .iter()
+                .flat_map(|array| array.iter())
+                .enumerate()
+            {
+                // Strips have two more indices than triangles
+                let start = strip.start_vindex() as usize;
+                ranges.push((
+                    format!("{}.strips[{}]", field, strip_index),
+                    start..start + strip.tri_count() as usize + 2,
+                ));
+            }
+
+            for (field, range) in ranges {
+                let Some(values) = indices.get(range.clone()) else {
+                    linter.push(
+                        LintRule::IndexRange,
+                        field,
+                        format!(
+                            "indices {}..{} run past the end of the {} index buffer",
+                            range.start,
+                            range.end,
+                            indices.len()
+                        ),
+                    );
+                    continue;
+                };
+
+                if let Some(value) = values
+                    .iter()
+                    .find(|value| **value as usize >= *vertex_count)
+                {
+                    linter.push(
+                        LintRule::IndexRange,
+                        field,
+                        format!(
+                            "index {} references a vertex past the {} in vertex buffer {}",
+                            value,
+                            vertex_count,
+                            cluster.vertex_buffer_index()
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn lint_collision(linter: &mut Linter, mesh: &MeshView) -> Result<(), ValidationError> {
+    let Some(dx_mesh) = mesh.dx_mesh()? else {
+        return Ok(());
+    };
+
+    for (index, buffer) in dx_mesh
+        .vertex_buffers()?
+        .iter()
+        .flat_map(|array| array.iter())
+        .enumerate()
+    {
+        let field = format!("coll_vertex_buffer[{}]", index);
+        let collision = match dx_mesh.collision_vertices(index) {
+            Ok(Some(value)) => value,
+            Ok(None) => continue,
+            Err(_) => {
+                linter.push(
+                    LintRule::CollisionBuffer,
+                    field,
+                    format!(
+                        "{} vertices, the count of vertex buffer {}, run past the end of the file",
+                        buffer.vertex_count(),
+                        index
+                    ),
+                );
+                continue;
+            }
+        };
+        let Some(positions) = buffer.positions()? else {
+            continue;
+        };
+
+        if let Some((vertex, (value, position))) = collision
+            .iter()
+            .zip(positions.iter())
+            .enumerate()
+            .find(|(_, (value, position))| value != position)
+        {
+            linter.push(
+                LintRule::CollisionBuffer,
+                field,
+                format!(
+                    "vertex {} is {:?} but must match the position {:?} of vertex buffer {}",
+                    vertex, value, position, index
+                ),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        fixture::triangle_mesh,
+        view::{layout, MeshView},
+    };
+
+    use binrw::Endian;
+
+    use super::{lint_mesh, LintRule};
+
+    #[test]
+    fn test_fixture_passes() {
+        let bytes = triangle_mesh();
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        assert_eq!(lint_mesh(&mesh).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_lod_count() {
+        let mut bytes = triangle_mesh();
+        bytes[layout::MESH_LOD_COUNT] = 0;
+
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        let issues = lint_mesh(&mesh).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, LintRule::LodCount);
+        assert_eq!(issues[0].field, "lod_count");
+    }
+
+    #[test]
+    fn test_index_range() {
+        let mut bytes = triangle_mesh();
+        let offset = MeshView::new(&bytes, Endian::Little)
+            .unwrap()
+            .dx_mesh()
+            .unwrap()
+            .unwrap()
+            .index_buffer(0)
+            .unwrap()
+            .unwrap()
+            .offset();
+        bytes[offset + 4..offset + 6].copy_from_slice(&7u16.to_le_bytes());
+
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        let issues = lint_mesh(&mesh).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, LintRule::IndexRange);
+        assert_eq!(issues[0].field, "materials[0].clusters[0].tri_list");
+    }
+
+    #[test]
+    fn test_collision_buffer() {
+        let mut bytes = triangle_mesh();
+        let offset = MeshView::new(&bytes, Endian::Little)
+            .unwrap()
+            .dx_mesh()
+            .unwrap()
+            .unwrap()
+            .collision_vertices(0)
+            .unwrap()
+            .unwrap()
+            .offset();
+        bytes[offset + 12..offset + 16].copy_from_slice(&5.0f32.to_le_bytes());
+
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        let issues = lint_mesh(&mesh).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, LintRule::CollisionBuffer);
+        assert_eq!(issues[0].field, "coll_vertex_buffer[0]");
+    }
+}
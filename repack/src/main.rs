@@ -1,83 +1,614 @@
-pub mod raw;
-pub mod st;
-pub mod types;
-use std::fs::{File, OpenOptions};
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+    process::ExitCode,
+};
 
-use st::{load_memory_struct, FMesh, SafeBuffer};
+use clap::Parser;
+use repack::{
+    batch::{
+        batch_edit, edit_buffer, write_change_log, BatchError, MaterialEdit, MaterialPredicate,
+    },
+    cancel::{CancelToken, WrittenFiles, CANCELLED_EXIT_CODE},
+    cli::{Cli, Command, FormatArg, PlatformArg},
+    collision::CollisionSet,
+    convert::{
+        collect_inputs, convert_parallel, convert_progress, write_convert_report, ConvertOutcome,
+        ConvertReport, REPORT_FILE,
+    },
+    diff::{compatibility_matrix, diff_summaries, write_matrix, MeshSummary},
+    docs,
+    dump::{self, BufferDump},
+    export::{ExportFormat, ExportGeometry},
+    layout::FileLayout,
+    lint::lint_mesh,
+    pack::{read_obj_positions, PackWriter},
+    platform::Platform,
+    provenance::Provenance,
+    report::MeshReport,
+    sanity::{check_header, FixupStage},
+    survey::MeshSurvey,
+    tui,
+    view::MeshView,
+};
+use tracing::Level;
+use tracing_subscriber::fmt::format::FmtSpan;
 
-fn main() {
-    use std::io::Write;
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if cli.trace_parse {
+        init_parse_trace();
+    }
 
-    let mut debug_dump = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open("data/dump.txt")
-        .unwrap();
+    match run(cli.command) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    // Read entire file into a buffer
-    let buffer = std::fs::read("data/ape/grdggltch00.ape").unwrap();
-    // Drop extra buffer capacity
-    let buffer: Box<[u8]> = buffer.into_boxed_slice();
+/// Prints the parse spans to stderr as they're entered, each line is
+/// prefixed by the spans of the structures containing it
+fn init_parse_trace() {
+    tracing_subscriber::fmt()
+        .with_max_level(Level::TRACE)
+        .with_span_events(FmtSpan::NEW)
+        .with_writer(std::io::stderr)
+        .without_time()
+        .init();
+}
 
-    println!("Buffer length {}", buffer.len());
+fn run(command: Command) -> Result<ExitCode, Box<dyn Error>> {
+    match command {
+        Command::Inspect { paths, platform } => {
+            for path in paths {
+                let survey = survey(&path, platform)?;
+                println!("{}: {} ({})", path.display(), survey.name, survey.platform);
+                println!(
+                    "{} bones, {} materials, {} lights",
+                    survey.bones.len(),
+                    survey.materials.len(),
+                    survey.lights.len()
+                );
+                for warning in &survey.warnings {
+                    println!("Warning: {}", warning);
+                }
+            }
+        }
+        Command::Dump {
+            path,
+            platform,
+            out,
+        } => dump_mesh(&path, platform, &out)?,
+        Command::Export {
+            path,
+            platform,
+            format,
+            out,
+        } => {
+            let geometry = export_geometry(&path, platform, format, &out)?;
+            println!(
+                "Wrote {} vertices in {} material groups",
+                geometry.positions.len(),
+                geometry.groups.len()
+            );
+        }
+        Command::Collision {
+            path,
+            platform,
+            out,
+        } => {
+            let platform = dx_platform(&path, platform)?;
+            let bytes = std::fs::read(&path)?;
+            let provenance = Provenance::default()
+                .with_source(&path, &bytes)
+                .with_setting("platform", platform)
+                .with_setting("source", "collision buffers");
+
+            let set = CollisionSet::from_dx_view(&MeshView::new(&bytes, platform.endian())?)?
+                .with_provenance(provenance.clone());
+            let mut file = File::create(&out)?;
+            set.write_json(&mut file)?;
+            provenance.write_sidecar(&out)?;
+            println!("Wrote {} collision shapes", set.shapes.len());
+        }
+        Command::Convert {
+            paths,
+            recursive,
+            jobs,
+            platform,
+            format,
+            out,
+        } => {
+            std::fs::create_dir_all(&out)?;
+            let inputs = collect_inputs(&paths, recursive)?;
+            let cancel = CancelToken::from_ctrl_c()?;
+            let progress = convert_progress(inputs.len());
+
+            let outputs: Vec<_> = inputs
+                .iter()
+                .map(|input| input.output(&out, format.into()))
+                .collect();
+            for parent in outputs.iter().filter_map(|output| output.parent()) {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            let outcomes = convert_parallel(&inputs, jobs, &cancel, &progress, |input| {
+                let output = input.output(&out, format.into());
+                export_geometry(&input.path, platform, format, &output)
+                    .map(|_| ())
+                    .map_err(|err| err.to_string())
+            })?;
+            progress.finish_and_clear();
+
+            if cancel.is_cancelled() {
+                // Files being converted when cancelled are finished first so
+                // converted outputs are kept, only outputs left part written
+                // by a failed conversion are removed
+                let mut partial = WrittenFiles::default();
+                let mut converted = 0;
+                for (output, (outcome, _)) in outputs.iter().zip(&outcomes) {
+                    match outcome {
+                        ConvertOutcome::Converted => converted += 1,
+                        ConvertOutcome::Failed(_) => partial.push(output),
+                        _ => {}
+                    }
+                }
+                let removed = partial.remove_all()?;
+                println!(
+                    "Cancelled after converting {} files, removed {} partial files",
+                    converted, removed
+                );
+                return Ok(ExitCode::from(CANCELLED_EXIT_CODE));
+            }
+
+            let report = ConvertReport::from_outcomes(&inputs, outcomes);
+            let mut summary = File::create(out.join(REPORT_FILE))?;
+            write_convert_report(&mut summary, format.into(), &report)?;
+
+            println!(
+                "Converted {} files in {:.1?}, {} failed, {} skipped",
+                report.converted.len(),
+                progress.elapsed(),
+                report.failed.len(),
+                report.skipped.len()
+            );
+            if !report.failed.is_empty() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Import {
+            dir,
+            strips,
+            format,
+            out,
+        } => {
+            let dump = BufferDump::load(&dir)?;
+            let geometry = dump.to_geometry(strips);
+
+            let mut provenance = Provenance::default()
+                .with_setting("strips", strips)
+                .with_setting("format", ExportFormat::from(format).extension());
+            for file in [dump::POSITIONS_FILE, dump::INDICES_FILE] {
+                let path = dir.join(file);
+                if let Ok(bytes) = std::fs::read(&path) {
+                    provenance = provenance.with_source(&path, &bytes);
+                }
+            }
+
+            geometry.write(format.into(), Some(&provenance), &mut File::create(&out)?)?;
+            provenance.write_sidecar(&out)?;
+
+            println!(
+                "Imported {} positions in {} buffers",
+                geometry.positions.len(),
+                dump.vertex_buffers.len()
+            );
+        }
+        Command::Validate {
+            paths,
+            platform,
+            json,
+        } => {
+            let mut invalid = 0;
+            let mut entries = Vec::new();
+            for path in &paths {
+                let (problems, report) = validate(path, platform)?;
+                if !problems.is_empty() {
+                    invalid += 1;
+                }
+
+                if json {
+                    entries.push(serde_json::json!({
+                        "path": path.display().to_string(),
+                        "problems": problems,
+                        "report": report,
+                    }));
+                } else if problems.is_empty() {
+                    println!("{}: ok", path.display());
+                } else {
+                    for problem in problems {
+                        println!("{}: {}", path.display(), problem);
+                    }
+                }
+            }
+
+            if json {
+                let mut out = std::io::stdout().lock();
+                serde_json::to_writer_pretty(&mut out, &entries)?;
+                writeln!(out)?;
+            }
+
+            if invalid > 0 {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Lint { paths, platform } => {
+            let mut failed = 0;
+            for path in &paths {
+                let platform = dx_platform(path, platform)?;
+                let bytes = std::fs::read(path)?;
+                let issues = lint_mesh(&MeshView::new(&bytes, platform.endian())?)?;
+                if issues.is_empty() {
+                    println!("{}: ok", path.display());
+                    continue;
+                }
+
+                failed += 1;
+                for issue in issues {
+                    println!("{}: {}", path.display(), issue);
+                }
+            }
+
+            if failed > 0 {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Pack {
+            path,
+            platform,
+            positions,
+            materials,
+            edits,
+            out,
+        } => {
+            let predicate: MaterialPredicate = materials.parse()?;
+            let mut provenance = Provenance::default()
+                .with_source(&path, &std::fs::read(&path)?)
+                .with_setting(
+                    "platform",
+                    PlatformArg::resolve(platform, &path).unwrap_or(Platform::Pc),
+                )
+                .with_setting("materials", &materials)
+                .with_setting("edits", edits.join(" "));
+            let positions = match positions {
+                Some(positions) => {
+                    provenance = provenance.with_source(&positions, &std::fs::read(&positions)?);
+                    Some(read_obj_positions(BufReader::new(File::open(positions)?))?)
+                }
+                None => None,
+            };
+            let edits = edits
+                .iter()
+                .map(|edit| edit.parse())
+                .collect::<Result<Vec<MaterialEdit>, _>>()?;
+
+            let bytes = pack_mesh(&path, platform, positions, &predicate, &edits)?;
+            std::fs::write(&out, bytes)?;
+            provenance.write_sidecar(&out)?;
+        }
+        Command::Diff { left, right } => {
+            let left = MeshSummary::load(&left)?;
+            let right = MeshSummary::load(&right)?;
+
+            let differences = diff_summaries(&left, &right);
+            if differences.is_empty() {
+                println!("No differences");
+            }
+
+            for difference in differences {
+                println!(
+                    "{}: {} ({}) != {} ({})",
+                    difference.field,
+                    difference.left,
+                    left.platform,
+                    difference.right,
+                    right.platform
+                );
+            }
+        }
+        Command::Matrix { dir } => {
+            let rows = compatibility_matrix(&dir)?;
+            write_matrix(&mut std::io::stdout(), &rows)?;
+        }
+        Command::Strip { path, out } => {
+            let platform = dx_platform(&path, None)?;
+            let mut bytes = std::fs::read(&path)?;
+            let layout = FileLayout::from_view(&MeshView::new(&bytes, platform.endian())?)?;
+            for range in layout.orphaned() {
+                println!(
+                    "Orphaned {:#x}..{:#x} ({} bytes)",
+                    range.start,
+                    range.end,
+                    range.len()
+                );
+            }
+
+            let provenance = Provenance::default()
+                .with_source(&path, &bytes)
+                .with_setting("command", "strip");
+            let report = layout.strip(&mut bytes);
+            println!(
+                "Reclaimed {} bytes, zeroed {} bytes",
+                report.truncated, report.zeroed
+            );
+
+            std::fs::write(&out, bytes)?;
+            provenance.write_sidecar(&out)?;
+        }
+        Command::Batch {
+            input,
+            predicate,
+            edit,
+            out,
+        } => {
+            let predicate: MaterialPredicate = predicate.parse()?;
+            let edit: MaterialEdit = edit.parse()?;
 
-    let mesh: SafeBuffer<FMesh> = unsafe { load_memory_struct::<FMesh>(buffer) };
+            let cancel = CancelToken::from_ctrl_c()?;
+            let report = match batch_edit(&input, &out, &predicate, &edit, &cancel) {
+                Ok(report) => report,
+                Err(BatchError::Cancelled(_)) => {
+                    println!("Cancelled, removed the written files");
+                    return Ok(ExitCode::from(CANCELLED_EXIT_CODE));
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let mut log = File::create(out.join("changes.txt"))?;
+            write_change_log(&mut log, &predicate, &edit, &report)?;
 
-    // let value = unsafe { *mesh.skeleton_index_array };
+            println!(
+                "Wrote {} files with {} changes, skipped {} files",
+                report.written,
+                report.changes.len(),
+                report.skipped.len()
+            );
+        }
+        Command::Docs { out } => {
+            let docs = docs::generate()?;
+            let mut file = File::create(out)?;
+            docs::write_markdown(&mut file, &docs)?;
+        }
+        Command::Tui { path } => tui::run(&path)?,
+    }
 
-    let mesh: &FMesh = &mesh;
+    Ok(ExitCode::SUCCESS)
+}
 
-    writeln!(&mut debug_dump, "{:#?}", &mesh).unwrap();
+/// Surveys the mesh at the provided path
+fn survey(path: &Path, platform: Option<PlatformArg>) -> Result<MeshSurvey, Box<dyn Error>> {
+    let platform = PlatformArg::resolve(platform, path).ok_or_else(|| {
+        format!(
+            "unable to detect the platform of {}, provide --platform",
+            path.display()
+        )
+    })?;
+    let buffer = std::fs::read(path)?;
+    Ok(MeshSurvey::from_buffer(platform, &buffer)?)
+}
 
-    // dbg!(&mesh.materials());
+/// Resolves the platform of a mesh read through the DirectX views, files
+/// without a detectable platform are assumed to use the DirectX layout
+fn dx_platform(path: &Path, platform: Option<PlatformArg>) -> Result<Platform, Box<dyn Error>> {
+    let platform = PlatformArg::resolve(platform, path).unwrap_or(Platform::Pc);
+    if !platform.is_dx() {
+        return Err(format!(
+            "{} uses the {:?} layout which can only be inspected or validated",
+            path.display(),
+            platform
+        )
+        .into());
+    }
 
-    let dx_mesh: &mut raw::dx::DxMesh = mesh.impl_specific_mut().unwrap();
-    // let buffers: &[raw::dx::DxVertexBufferDescriptor] = dx_mesh.vertex_buffers().unwrap();
-    writeln!(&mut debug_dump, "{:#?}", dx_mesh).unwrap();
-    // writeln!(&mut debug_dump, "{:#?}", buffers).unwrap();
+    Ok(platform)
+}
 
-    let mut buffer_dump = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open("data/buffer_dump.txt")
-        .unwrap();
+/// Exports the geometry of the mesh at the provided path
+fn export_geometry(
+    path: &Path,
+    platform: Option<PlatformArg>,
+    format: FormatArg,
+    out: &Path,
+) -> Result<ExportGeometry, Box<dyn Error>> {
+    let bytes = std::fs::read(path)?;
+    let provenance = Provenance::default()
+        .with_source(path, &bytes)
+        .with_setting(
+            "platform",
+            PlatformArg::resolve(platform, path).unwrap_or(Platform::Pc),
+        )
+        .with_setting("format", ExportFormat::from(format).extension());
 
-    let mut buffer_dump_index = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open("data/buffer_dump_index.txt")
-        .unwrap();
+    let geometry = match PlatformArg::resolve(platform, path) {
+        Some(value) if value.is_ps2() => {
+            ExportGeometry::from_ps2(&MeshView::new(&bytes, value.endian())?)?
+        }
+        _ => {
+            let platform = dx_platform(path, platform)?;
+            ExportGeometry::from_dx_view(&MeshView::new(&bytes, platform.endian())?)?
+        }
+    };
+    let mut file = File::create(out)?;
+    geometry.write(format.into(), Some(&provenance), &mut file)?;
+    provenance.write_sidecar(out)?;
+    Ok(geometry)
+}
 
-    dbg!(&dx_mesh);
+/// Applies the material edits and replaces the vertex positions of the mesh at
+/// the provided path, returning the bytes of the edited file
+fn pack_mesh(
+    path: &Path,
+    platform: Option<PlatformArg>,
+    positions: Option<Vec<[f32; 3]>>,
+    predicate: &MaterialPredicate,
+    edits: &[MaterialEdit],
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let platform = dx_platform(path, platform)?;
+    let mut bytes = std::fs::read(path)?;
 
-    let index_buffers = dx_mesh.index_buffers();
+    // Vertex counts are read through a view so the file is never fixed up
+    let mesh = MeshView::new(&bytes, platform.endian())?;
+    let vertex_counts: Option<Vec<usize>> = mesh
+        .dx_mesh()?
+        .map(|dx_mesh| dx_mesh.vertex_buffers())
+        .transpose()?
+        .flatten()
+        .map(|buffers| {
+            buffers
+                .iter()
+                .map(|buffer| buffer.vertex_count() as usize)
+                .collect()
+        });
 
-    let index_buffer = *index_buffers.first().unwrap();
-    for value in index_buffer {
-        writeln!(&mut buffer_dump_index, "{}", value);
+    for edit in edits {
+        for (material, change) in edit_buffer(&mut bytes, platform, predicate, edit)? {
+            println!("Material {}: {}", material, change);
+        }
     }
 
-    let vertex_buffer = dx_mesh.vertex_buffers_mut().unwrap();
-    writeln!(&mut debug_dump, "{:#?}", vertex_buffer).unwrap();
+    let mut writer = PackWriter::new(bytes, platform.endian());
+    if let Some(positions) = positions {
+        let vertex_counts = vertex_counts.ok_or("mesh has no vertex buffers")?;
+
+        // Positions of the merged buffers are split back up in buffer order
+        let expected: usize = vertex_counts.iter().sum();
+        if positions.len() != expected {
+            return Err(format!(
+                "expected {} positions, the OBJ file has {}",
+                expected,
+                positions.len()
+            )
+            .into());
+        }
 
-    // println!("{}", values.len());
+        let mut start = 0;
+        for (index, count) in vertex_counts.iter().enumerate() {
+            let end = start + count;
+            writer.replace_positions(index, &positions[start..end])?;
+            start = end;
+        }
+        println!("Replaced {} positions", positions.len());
+    }
+
+    Ok(writer.finish())
+}
 
-    // for value in indices {
-    //     if value as usize > values.len() {
-    //         // panic!("Index out of range {}", value);
-    //     }
-    //     writeln!(&mut buffer_dump_index, "{}", value);
-    // }
+/// Checks the mesh at the provided path returning the problems found, the
+/// report is only available for meshes using the DirectX layout
+fn validate(
+    path: &Path,
+    platform: Option<PlatformArg>,
+) -> Result<(Vec<String>, Option<MeshReport>), Box<dyn Error>> {
+    let survey = survey(path, platform)?;
+    let mut problems = survey.warnings.clone();
+    let mut report = None;
 
-    for buffer in vertex_buffer {
-        writeln!(&mut buffer_dump, "Buffer 1").unwrap();
-        let positions = buffer.positions();
+    let bytes = std::fs::read(path)?;
+    let mesh = MeshView::new(&bytes, survey.platform.endian())?;
+    if survey.platform.is_dx() {
+        problems.extend(
+            check_header(
+                mesh.header(),
+                FixupStage::for_source(survey.platform.into()),
+            )
+            .iter()
+            .map(ToString::to_string),
+        );
 
-        for [a, b, c] in positions {
-            writeln!(&mut buffer_dump, "{} {} {}", a, b, c).unwrap();
+        // Damaged offsets are reported as problems so the remaining files are still checked
+        match FileLayout::from_view(&mesh) {
+            Ok(layout) if layout.orphaned_bytes() > 0 => problems.push(format!(
+                "{} bytes aren't referenced by any structure",
+                layout.orphaned_bytes()
+            )),
+            Ok(_) => {}
+            Err(err) => problems.push(err.to_string()),
+        }
+
+        match MeshReport::from_view(&mesh) {
+            Ok(mesh_report) => {
+                problems.extend(mesh_report.issues.iter().map(ToString::to_string));
+                report = Some(mesh_report);
+            }
+            Err(err) => problems.push(err.to_string()),
+        }
+    } else if survey.platform.is_ps2() {
+        if let Err(err) = ExportGeometry::from_ps2(&mesh) {
+            problems.push(err.to_string());
         }
     }
+
+    Ok((problems, report))
+}
+
+/// Dumps the structures along with the positions and indices of the
+/// vertex buffers
+fn dump_mesh(path: &Path, platform: Option<PlatformArg>, out: &Path) -> Result<(), Box<dyn Error>> {
+    std::fs::create_dir_all(out)?;
+
+    let buffer = std::fs::read(path)?;
+    println!("Buffer length {}", buffer.len());
+
+    let platform = dx_platform(path, platform)?;
+    let mesh = MeshView::new(&buffer, platform.endian())?;
+
+    let mut debug_dump = File::create(out.join("dump.txt"))?;
+    writeln!(&mut debug_dump, "{:#?}", mesh.header())?;
+    for material in mesh.materials()?.iter().flat_map(|array| array.iter()) {
+        writeln!(&mut debug_dump, "{:#?}", material.record())?;
+    }
+
+    let dx_mesh = mesh
+        .dx_mesh()?
+        .ok_or("mesh has no platform specific data")?;
+    let vertex_buffers = dx_mesh
+        .vertex_buffers()?
+        .ok_or("mesh has no vertex buffers")?;
+    for (index, buffer) in vertex_buffers.iter().enumerate() {
+        writeln!(
+            &mut debug_dump,
+            "vertex buffer {}: {} vertices of {} bytes, info index {}",
+            index,
+            buffer.vertex_count(),
+            buffer.bytes_per_vertex(),
+            buffer.info_index()
+        )?;
+    }
+
+    let mut positions: Vec<Vec<[f32; 3]>> = Vec::new();
+    for buffer in vertex_buffers.iter() {
+        positions.push(
+            buffer
+                .positions()?
+                .iter()
+                .flat_map(|array| array.iter())
+                .collect(),
+        );
+    }
+
+    let dump = BufferDump {
+        vertex_buffers: positions,
+        index_buffers: dx_mesh
+            .index_buffers()?
+            .iter()
+            .map(|buffer| buffer.iter().collect())
+            .collect(),
+    };
+    dump.write(out)?;
+
+    Ok(())
 }
@@ -0,0 +1,188 @@
+use crate::{
+    offsets::ValidationError,
+    patch::{Patch, PatchError},
+    st::{CFSphere, FDATA_BONE_NAME_LENGTH},
+    view::MeshView,
+};
+
+/// Editable details of a bone
+#[derive(Debug, Clone)]
+pub struct BoneModel {
+    pub name: String,
+    /// Index of the parent bone (None for root bones)
+    pub parent_index: Option<u8>,
+    pub part_id: u8,
+}
+
+/// Editable details of a material
+#[derive(Debug, Clone)]
+pub struct MaterialModel {
+    pub tint: [f32; 3],
+    pub part_id_mask: u32,
+    pub lod_mask: u8,
+    /// Material flags (see FMESH_MTLFLAG_* for info)
+    pub flags: u16,
+    /// Sphere bounding the verts of the material in model space, used
+    /// for culling individual materials
+    pub bound_sphere: CFSphere,
+}
+
+/// Platform independent model of a loaded mesh, edits made through
+/// this model are recorded as patches so they can be applied to the
+/// original file when repacking
+#[derive(Debug, Clone)]
+pub struct MeshModel {
+    name: String,
+    lod_distances: Vec<f32>,
+    bones: Vec<BoneModel>,
+    materials: Vec<MaterialModel>,
+    patches: Vec<Patch>,
+}
+
+impl MeshModel {
+    /// Creates the model from the views of a mesh
+    pub fn from_view(mesh: &MeshView) -> Result<MeshModel, ValidationError> {
+        let bones = mesh
+            .bones()?
+            .iter()
+            .flat_map(|array| array.iter())
+            .map(|bone| BoneModel {
+                name: bone.name(),
+                parent_index: bone.parent_index(),
+                part_id: bone.part_id(),
+            })
+            .collect();
+
+        let radius = mesh.bound_sphere().radius;
+        let materials = mesh
+            .materials()?
+            .iter()
+            .flat_map(|array| array.iter())
+            .map(|material| MaterialModel {
+                tint: material.tint(),
+                part_id_mask: material.part_id_mask(),
+                lod_mask: material.lod_mask(),
+                flags: material.flags(),
+                bound_sphere: material.bound_sphere(radius),
+            })
+            .collect();
+
+        Ok(MeshModel {
+            name: mesh.name(),
+            lod_distances: mesh.lod_distances(),
+            bones,
+            materials,
+            patches: Vec::new(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn lod_distances(&self) -> &[f32] {
+        &self.lod_distances
+    }
+
+    pub fn bones(&self) -> &[BoneModel] {
+        &self.bones
+    }
+
+    pub fn materials(&self) -> &[MaterialModel] {
+        &self.materials
+    }
+
+    /// Patches recorded from the edits made to this model
+    pub fn patches(&self) -> &[Patch] {
+        &self.patches
+    }
+
+    /// Takes the recorded patches leaving the list empty
+    pub fn take_patches(&mut self) -> Vec<Patch> {
+        std::mem::take(&mut self.patches)
+    }
+
+    pub fn set_material_tint(&mut self, material: usize, tint: [f32; 3]) -> Result<(), PatchError> {
+        let value = self
+            .materials
+            .get_mut(material)
+            .ok_or(PatchError::MaterialOutOfRange(material))?;
+        value.tint = tint;
+
+        self.patches.push(Patch::SetMaterialTint { material, tint });
+        Ok(())
+    }
+
+    pub fn set_material_flags(&mut self, material: usize, flags: u16) -> Result<(), PatchError> {
+        let value = self
+            .materials
+            .get_mut(material)
+            .ok_or(PatchError::MaterialOutOfRange(material))?;
+        value.flags = flags;
+
+        self.patches
+            .push(Patch::SetMaterialFlags { material, flags });
+        Ok(())
+    }
+
+    pub fn set_lod_distance(&mut self, lod: usize, distance: f32) -> Result<(), PatchError> {
+        let value = self
+            .lod_distances
+            .get_mut(lod)
+            .ok_or(PatchError::LodOutOfRange(lod))?;
+        *value = distance;
+
+        self.patches.push(Patch::SetLodDistance { lod, distance });
+        Ok(())
+    }
+
+    pub fn rename_bone(&mut self, bone: usize, name: String) -> Result<(), PatchError> {
+        // Name must leave room for the null terminator
+        if name.len() >= FDATA_BONE_NAME_LENGTH {
+            return Err(PatchError::NameTooLong(name));
+        }
+
+        let value = self
+            .bones
+            .get_mut(bone)
+            .ok_or(PatchError::BoneOutOfRange(bone))?;
+        value.name = name.clone();
+
+        self.patches.push(Patch::RenameBone { bone, name });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use binrw::Endian;
+
+    use crate::{
+        fixture::{triangle_mesh, FIXTURE_POSITIONS},
+        view::MeshView,
+    };
+
+    use super::MeshModel;
+
+    #[test]
+    fn test_material_bounds() {
+        let bytes = triangle_mesh();
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        let model = MeshModel::from_view(&mesh).unwrap();
+        let sphere = model.materials()[0].bound_sphere;
+
+        assert!(sphere.radius > 0.0 && sphere.radius <= mesh.bound_sphere().radius);
+        for [x, y, z] in FIXTURE_POSITIONS {
+            let distance = ((x - sphere.position.x).powi(2)
+                + (y - sphere.position.y).powi(2)
+                + (z - sphere.position.z).powi(2))
+            .sqrt();
+            assert!(
+                distance <= sphere.radius,
+                "{:?} outside {:?}",
+                [x, y, z],
+                sphere
+            );
+        }
+    }
+}
@@ -0,0 +1,495 @@
+//! Validation of the offsets stored in a file before the pointers are fixed
+//! up. Fixing adds the base pointer to every offset without checking it, so
+//! a corrupt file would produce pointers outside the buffer. Each structure
+//! checks its offsets, along with the length of the arrays they point to, so
+//! the file can be rejected before anything is dereferenced.
+//!
+//! Structures are copied and swapped into the host order using the byte order
+//! of the source before they're checked, values read from the arrays the
+//! structures point to are swapped with [OffsetValidator::read]
+//!
+//! Values the fixup modifies in place are claimed as they're checked, fixing
+//! the same bytes twice would turn an already fixed pointer into a wild one
+//! so files with overlapping or self referencing values are rejected
+
+use std::{cell::RefCell, collections::BTreeMap, fmt::Display, mem::size_of};
+
+use swapbytes::SwapBytes;
+use thiserror::Error;
+use tracing::trace_span;
+
+use crate::st::{Fixable, SourceEndian};
+
+/// Reason an offset stored in a file was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationProblem {
+    /// The value at the offset extends past the end of the buffer
+    OutOfBounds,
+    /// The offset isn't aligned for the value it points to
+    Misaligned,
+    /// The value overlaps another value the fixup modifies in place
+    Overlapping,
+    /// The value is platform data the in-place structures don't describe
+    /// for the byte order of the source
+    UnsupportedPlatform,
+}
+
+impl Display for ValidationProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ValidationProblem::OutOfBounds => "extends past the end of the buffer",
+            ValidationProblem::Misaligned => "is not aligned for the value it points to",
+            ValidationProblem::Overlapping => "overlaps another value fixed in place",
+            ValidationProblem::UnsupportedPlatform => {
+                "is platform data that can't be loaded in place for the source byte order"
+            }
+        })
+    }
+}
+
+/// Offset stored in a file that can't be followed
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{field} at offset {offset:#x} {problem}, the buffer is {buffer_len} bytes")]
+pub struct ValidationError {
+    /// Name of the structure field holding the offset
+    pub field: &'static str,
+    pub offset: usize,
+    pub buffer_len: usize,
+    pub problem: ValidationProblem,
+}
+
+/// Checks offsets against the buffer of an unfixed file
+pub struct OffsetValidator {
+    base: *const u8,
+    length: usize,
+    endian: SourceEndian,
+    /// End of each region claimed by [OffsetValidator::claim] keyed by its
+    /// start, the regions never overlap
+    claimed: RefCell<BTreeMap<usize, usize>>,
+}
+
+impl OffsetValidator {
+    /// Creates a validator for the provided buffer stored in the byte order
+    pub fn new(buffer: &[u8], endian: SourceEndian) -> Self {
+        Self {
+            base: buffer.as_ptr(),
+            length: buffer.len(),
+            endian,
+            claimed: RefCell::default(),
+        }
+    }
+
+    /// Byte order of the source the buffer is stored in
+    pub fn endian(&self) -> SourceEndian {
+        self.endian
+    }
+
+    /// Brings a value read from the buffer into the host order
+    pub fn read<T: SwapBytes>(&self, value: T) -> T {
+        self.endian.read(value)
+    }
+
+    /// Error for the offset stored in the field pointing past the end
+    pub fn error(&self, field: &'static str, offset: usize) -> ValidationError {
+        self.problem(field, offset, ValidationProblem::OutOfBounds)
+    }
+
+    fn problem(
+        &self,
+        field: &'static str,
+        offset: usize,
+        problem: ValidationProblem,
+    ) -> ValidationError {
+        ValidationError {
+            field,
+            offset,
+            buffer_len: self.length,
+            problem,
+        }
+    }
+
+    /// Claims the `start..end` region of the buffer for values the fixup
+    /// modifies in place, fails when any of it was already claimed
+    pub fn claim(
+        &self,
+        field: &'static str,
+        start: usize,
+        end: usize,
+    ) -> Result<(), ValidationError> {
+        if start == end {
+            return Ok(());
+        }
+
+        let mut claimed = self.claimed.borrow_mut();
+        // Claimed regions never overlap so only the last one starting before
+        // the end can reach into the region
+        let overlaps = claimed
+            .range(..end)
+            .next_back()
+            .is_some_and(|(_, claimed_end)| *claimed_end > start);
+        if overlaps {
+            return Err(self.problem(field, start, ValidationProblem::Overlapping));
+        }
+
+        claimed.insert(start, end);
+        Ok(())
+    }
+
+    /// Checks `count` values of `T` at the offset fit within the buffer,
+    /// returns the values when the offset is not null
+    ///
+    /// # Safety
+    ///
+    /// The buffer the validator was created from must still be alive
+    pub unsafe fn slice<T: 'static>(
+        &self,
+        field: &'static str,
+        offset: *const T,
+        count: usize,
+    ) -> Result<Option<&'static [T]>, ValidationError> {
+        if offset.is_null() {
+            return Ok(None);
+        }
+
+        let start = offset as usize;
+        let end = count
+            .checked_mul(size_of::<T>())
+            .and_then(|size| start.checked_add(size))
+            .ok_or_else(|| self.error(field, start))?;
+        if end > self.length {
+            return Err(self.error(field, start));
+        }
+
+        let values = self.base.add(start).cast::<T>();
+        if !values.is_aligned() {
+            return Err(self.problem(field, start, ValidationProblem::Misaligned));
+        }
+
+        Ok(Some(std::slice::from_raw_parts(values, count)))
+    }
+
+    /// Checks `count` values of `T` at the offset fit within the buffer and
+    /// claims them, used for the values the fixup modifies in place
+    ///
+    /// # Safety
+    ///
+    /// The buffer the validator was created from must still be alive
+    pub unsafe fn fixed<T: 'static>(
+        &self,
+        field: &'static str,
+        offset: *const T,
+        count: usize,
+    ) -> Result<Option<&'static [T]>, ValidationError> {
+        let values = self.slice(field, offset, count)?;
+        if values.is_some() {
+            let start = offset as usize;
+            self.claim(field, start, start + count * size_of::<T>())?;
+        }
+        Ok(values)
+    }
+
+    /// Checks and claims the array of `count` values at the offset along
+    /// with the offsets stored in each of the values
+    ///
+    /// # Safety
+    ///
+    /// The buffer the validator was created from must still be alive
+    pub unsafe fn array<T: Fixable + 'static>(
+        &self,
+        field: &'static str,
+        offset: *const T,
+        count: usize,
+    ) -> Result<(), ValidationError> {
+        let _span = trace_span!("validate", field, offset = offset as usize, count).entered();
+
+        if let Some(values) = self.fixed(field, offset, count)? {
+            for value in values {
+                // The structures are plain data so a copy can be swapped
+                // without touching the buffer
+                self.read(std::ptr::read(value)).validate_offsets(self)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the value at the offset along with the offsets stored in it
+    ///
+    /// # Safety
+    ///
+    /// The buffer the validator was created from must still be alive
+    pub unsafe fn value<T: Fixable + 'static>(
+        &self,
+        field: &'static str,
+        offset: *const T,
+    ) -> Result<(), ValidationError> {
+        self.array(field, offset, 1)
+    }
+
+    /// Rejects an offset to platform data that can't be loaded in place,
+    /// null offsets are accepted since there's nothing to follow
+    pub fn unsupported<T>(
+        &self,
+        field: &'static str,
+        offset: *const T,
+    ) -> Result<(), ValidationError> {
+        match offset.is_null() {
+            true => Ok(()),
+            false => Err(self.problem(
+                field,
+                offset as usize,
+                ValidationProblem::UnsupportedPlatform,
+            )),
+        }
+    }
+
+    /// Checks an offset to data of an unknown size starts within the buffer
+    pub fn opaque<T>(&self, field: &'static str, offset: *const T) -> Result<(), ValidationError> {
+        let start = offset as usize;
+        if !offset.is_null() && start >= self.length {
+            return Err(self.error(field, start));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use binrw::Endian;
+
+    use crate::{
+        fixture::{textured_triangle_file, triangle_mesh, FIXTURE_TEXELS, FIXTURE_TEXTURE_NAME},
+        st::SourceEndian,
+        view::{layout, MeshView},
+    };
+
+    use super::{ValidationError, ValidationProblem};
+
+    fn read_u32(bytes: &[u8], offset: usize) -> usize {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+    }
+
+    fn write_u32(bytes: &mut [u8], offset: usize, value: u32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Fixture with the bone array moved to the last word of the file
+    fn bone_array_past_end() -> (Vec<u8>, ValidationError) {
+        let mut bytes = triangle_mesh();
+        let length = bytes.len();
+        write_u32(&mut bytes, layout::MESH_BONE_ARRAY, length as u32 - 4);
+
+        let expected = ValidationError {
+            field: "FMesh.bone_array",
+            offset: length - 4,
+            buffer_len: length,
+            problem: ValidationProblem::OutOfBounds,
+        };
+        (bytes, expected)
+    }
+
+    /// Fixture with the first index count of the DirectX mesh past the end
+    /// of the file
+    fn index_count_past_end() -> Vec<u8> {
+        let mut bytes = triangle_mesh();
+        let mesh_is = read_u32(&bytes, layout::MESH_IS);
+        let counts = read_u32(&bytes, mesh_is + layout::DX_MESH_INDICIES_COUNTS);
+        bytes[counts..counts + 2].copy_from_slice(&u16::MAX.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_offset_past_end() {
+        let (bytes, expected) = bone_array_past_end();
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        assert_eq!(mesh.bones().err(), Some(expected));
+    }
+
+    #[test]
+    fn test_nested_count() {
+        let bytes = index_count_past_end();
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        let dx_mesh = mesh.dx_mesh().unwrap().unwrap();
+        assert_eq!(
+            dx_mesh.index_buffer(0).err().map(|err| err.problem),
+            Some(ValidationProblem::OutOfBounds)
+        );
+    }
+
+    #[test]
+    fn test_embedded_tex_def() {
+        // Texture instance points at the definition embedded in the texture
+        // data, which points back to the data
+        let bytes = textured_triangle_file(Endian::Little);
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        let material = mesh.materials().unwrap().unwrap().get(0).unwrap();
+        assert_eq!(
+            mesh.material_textures(&material).unwrap(),
+            vec![FIXTURE_TEXTURE_NAME.to_string()]
+        );
+
+        let layer = mesh.tex_layers().unwrap().unwrap().get(0).unwrap();
+        let tex_inst = mesh.flip_palette(&layer).unwrap().unwrap().get(0).unwrap();
+        let tex_def_offset = mesh.tex_inst(tex_inst).unwrap().unwrap().tex_def;
+        let tex_def = mesh.tex_def(tex_def_offset).unwrap().unwrap();
+        assert_eq!(tex_def.tex_data, tex_def_offset);
+
+        let tex_data = mesh.dx_tex_data(tex_def.tex_data).unwrap().unwrap();
+        let image_data: Vec<u8> = tex_data.image_data().unwrap().unwrap().iter().collect();
+        assert_eq!(image_data, FIXTURE_TEXELS);
+    }
+
+    #[test]
+    fn test_source_endian() {
+        let foreign = match SourceEndian::NATIVE {
+            SourceEndian::Little => SourceEndian::Big,
+            SourceEndian::Big => SourceEndian::Little,
+        };
+        assert!(foreign.needs_swap());
+        assert_eq!(foreign.read(0x1234u16), 0x3412);
+    }
+
+    #[test]
+    fn test_truncated_header() {
+        let bytes = triangle_mesh()[..16].to_vec();
+        assert_eq!(
+            MeshView::new(&bytes, Endian::Little)
+                .err()
+                .map(|err| err.offset),
+            Some(0)
+        );
+    }
+
+    /// The same files rejected before loading in place, only possible on
+    /// hosts sharing the 32-bit layout of the files
+    #[cfg(target_pointer_width = "32")]
+    mod in_place {
+        use binrw::Endian;
+
+        use crate::{
+            fixture::{
+                textured_triangle_file, triangle_mesh, FIXTURE_TEXELS, FIXTURE_TEXTURE_NAME,
+            },
+            offsets::{ValidationError, ValidationProblem},
+            st::{try_load_memory_struct, FMesh, SourceEndian},
+            survey::FILE_HEADER_SIZE,
+            view::layout,
+        };
+
+        use super::{bone_array_past_end, index_count_past_end, write_u32};
+
+        fn try_load(bytes: Vec<u8>) -> Result<(), ValidationError> {
+            unsafe {
+                try_load_memory_struct::<FMesh>(bytes.into_boxed_slice(), SourceEndian::Little)
+            }
+            .map(|_| ())
+        }
+
+        #[test]
+        fn test_valid_fixture() {
+            assert!(try_load(triangle_mesh()).is_ok());
+        }
+
+        #[test]
+        fn test_offset_past_end() {
+            let (bytes, expected) = bone_array_past_end();
+            assert_eq!(try_load(bytes).err(), Some(expected));
+        }
+
+        #[test]
+        fn test_textured_fixture() {
+            // The definition embedded in the texture data isn't claimed twice
+            // and the data pointing back to it isn't fixed again
+            let bytes = textured_triangle_file(Endian::Little);
+            let mesh = unsafe {
+                try_load_memory_struct::<FMesh>(bytes.into_boxed_slice(), SourceEndian::Little)
+            }
+            .unwrap();
+
+            let layer = &mesh.tex_layers().unwrap()[0];
+            let tex_inst = unsafe { &**layer.flip_palette };
+            let tex_def = unsafe { &*tex_inst.tex_def };
+            assert_eq!(tex_def.tex_info.name.as_string(), FIXTURE_TEXTURE_NAME);
+            assert_eq!(tex_def.tex_info.texels_across, 4);
+            assert_eq!(tex_def.tex_data as usize, tex_inst.tex_def as usize);
+
+            let tex_data = unsafe { &*tex_def.tex_data };
+            assert_eq!(tex_data.texture_bytes as usize, FIXTURE_TEXELS.len());
+            let image_data = unsafe {
+                std::slice::from_raw_parts(
+                    tex_data.image_data.cast::<u8>(),
+                    tex_data.texture_bytes as usize,
+                )
+            };
+            assert_eq!(image_data, FIXTURE_TEXELS);
+        }
+
+        #[test]
+        fn test_misaligned_offset() {
+            let mut bytes = triangle_mesh();
+            write_u32(&mut bytes, layout::MESH_BONE_ARRAY, 2);
+            assert_eq!(
+                try_load(bytes).err().map(|err| (err.field, err.problem)),
+                Some(("FMesh.bone_array", ValidationProblem::Misaligned))
+            );
+        }
+
+        #[test]
+        fn test_overlapping_values() {
+            // Mesh data pointing back into the header would be fixed twice
+            let mut bytes = triangle_mesh();
+            write_u32(&mut bytes, layout::MESH_IS, 4);
+            assert_eq!(
+                try_load(bytes).err().map(|err| (err.field, err.problem)),
+                Some(("FMesh.mesh_is", ValidationProblem::Overlapping))
+            );
+        }
+
+        #[test]
+        fn test_nested_count() {
+            // Index count that runs past the end of the file is only found by
+            // following the DirectX mesh to its index buffers
+            assert_eq!(
+                try_load(index_count_past_end()).err().map(|err| err.field),
+                Some("DxMesh.index_buffer[]")
+            );
+        }
+
+        #[test]
+        fn test_foreign_endian() {
+            // Read in the other byte order the offsets of the fixture run past
+            // the end of the buffer, so the source order is what's used
+            let foreign = match SourceEndian::NATIVE {
+                SourceEndian::Little => SourceEndian::Big,
+                SourceEndian::Big => SourceEndian::Little,
+            };
+            let result = unsafe {
+                try_load_memory_struct::<FMesh>(triangle_mesh().into_boxed_slice(), foreign)
+            };
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn test_big_endian_mesh_data() {
+            // GameCube mesh data isn't a DirectX mesh so it must not be followed
+            let mut bytes = vec![0; FILE_HEADER_SIZE + layout::DX_MESH_SIZE];
+            bytes[layout::MESH_IS..layout::MESH_IS + 4]
+                .copy_from_slice(&(FILE_HEADER_SIZE as u32).to_be_bytes());
+
+            let result = unsafe {
+                try_load_memory_struct::<FMesh>(bytes.into_boxed_slice(), SourceEndian::Big)
+            };
+            assert_eq!(
+                result.err().map(|err| (err.field, err.problem)),
+                Some(("FMesh.mesh_is", ValidationProblem::UnsupportedPlatform))
+            );
+        }
+
+        #[test]
+        fn test_truncated_header() {
+            let bytes = triangle_mesh()[..16].to_vec();
+            assert_eq!(try_load(bytes).err().map(|err| err.offset), Some(0));
+        }
+    }
+}
@@ -0,0 +1,441 @@
+//! Writing of edited meshes back into .ape files. The original file is used
+//! as the base so data the repacker doesn't understand is carried through
+//! untouched. Replaced arrays that fit within the original are written in
+//! place, larger arrays are appended to the end of the file and the pointers
+//! to them are rewritten as offsets from the start of the file.
+//!
+//! Appended data sits past the disposable offset of the DirectX mesh, which
+//! is fine for the vertex and index data since the game only uses those to
+//! create the DirectX resources when the mesh is loaded
+
+use std::{
+    io::{self, BufRead},
+    mem::size_of,
+};
+
+use binrw::Endian;
+use thiserror::Error;
+
+use crate::{
+    patch::{apply_patches, Patch, PatchError},
+    view::layout,
+};
+
+/// Alignment of appended arrays, matches the alignment the game
+/// compiler uses for the arrays within the file
+const APPEND_ALIGNMENT: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum PackError {
+    #[error(transparent)]
+    Patch(#[from] PatchError),
+    #[error("mesh has no platform specific data")]
+    MissingMeshData,
+    #[error("vertex buffer {0} is out of range")]
+    VertexBufferOutOfRange(usize),
+    #[error("index buffer {0} is out of range")]
+    IndexBufferOutOfRange(usize),
+    #[error("vertex data is {actual} bytes, expected a multiple of {stride}")]
+    StrideMismatch { stride: usize, actual: usize },
+    #[error("expected {expected} positions, got {actual}")]
+    PositionCountMismatch { expected: usize, actual: usize },
+    #[error("index buffer has {0} indices, more than the 16 bit count can store")]
+    TooManyIndices(usize),
+    #[error("value at offset {offset} is outside the file")]
+    OutOfBounds { offset: usize },
+}
+
+/// Writer applying edits to the bytes of an unfixed .ape file
+pub struct PackWriter {
+    bytes: Vec<u8>,
+    endian: Endian,
+}
+
+impl PackWriter {
+    /// Creates a writer for the bytes of a file stored using
+    /// the provided byte order
+    pub fn new(bytes: Vec<u8>, endian: Endian) -> Self {
+        Self { bytes, endian }
+    }
+
+    /// Applies the patches recorded by an edited model
+    pub fn apply_patches(&mut self, patches: &[Patch]) -> Result<(), PackError> {
+        apply_patches(patches, &mut self.bytes, self.endian)?;
+        Ok(())
+    }
+
+    /// Replaces the data of a vertex buffer, the vertices must use the same
+    /// layout as the original. Returns the offset the data was written to
+    pub fn replace_vertices(&mut self, buffer: usize, data: &[u8]) -> Result<usize, PackError> {
+        let descriptor = self.vertex_buffer_offset(buffer)?;
+        let stride = self.read_u16(descriptor + layout::VERTEX_BUFFER_BYTES_PER_VERTEX)? as usize;
+        if stride == 0 || data.len() % stride != 0 {
+            return Err(PackError::StrideMismatch {
+                stride,
+                actual: data.len(),
+            });
+        }
+
+        let count_offset = descriptor + layout::VERTEX_BUFFER_VERTEX_COUNT;
+        let ptr_offset = descriptor + layout::VERTEX_BUFFER_DATA;
+        let old_length = self.read_u32(count_offset)? as usize * stride;
+
+        let offset = self.replace_array(ptr_offset, old_length, data)?;
+        self.write_u32(count_offset, (data.len() / stride) as u32)?;
+        Ok(offset)
+    }
+
+    /// Replaces the positions of the vertices in a vertex buffer keeping
+    /// the other attributes, the number of vertices can't change
+    pub fn replace_positions(
+        &mut self,
+        buffer: usize,
+        positions: &[[f32; 3]],
+    ) -> Result<(), PackError> {
+        let descriptor = self.vertex_buffer_offset(buffer)?;
+        let stride = self.read_u16(descriptor + layout::VERTEX_BUFFER_BYTES_PER_VERTEX)? as usize;
+        let count = self.read_u32(descriptor + layout::VERTEX_BUFFER_VERTEX_COUNT)? as usize;
+        if count != positions.len() {
+            return Err(PackError::PositionCountMismatch {
+                expected: count,
+                actual: positions.len(),
+            });
+        }
+
+        let start = self.read_u32(descriptor + layout::VERTEX_BUFFER_DATA)? as usize;
+
+        // Positions are the first field of every vertex layout
+        for (index, position) in positions.iter().enumerate() {
+            let offset = start + index * stride;
+            for (axis, value) in position.iter().enumerate() {
+                self.write_f32(offset + axis * size_of::<f32>(), *value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the indices of an index buffer. Returns the offset the
+    /// indices were written to
+    pub fn replace_indices(&mut self, buffer: usize, indices: &[u16]) -> Result<usize, PackError> {
+        let length =
+            u16::try_from(indices.len()).map_err(|_| PackError::TooManyIndices(indices.len()))?;
+
+        let dx_mesh = self.dx_mesh_offset()?;
+        let buffer_count = self.read_u8(dx_mesh + layout::DX_MESH_INDEX_BUFFER_COUNT)? as usize;
+        if buffer >= buffer_count {
+            return Err(PackError::IndexBufferOutOfRange(buffer));
+        }
+
+        let count_offset = self.read_u32(dx_mesh + layout::DX_MESH_INDICIES_COUNTS)? as usize
+            + buffer * size_of::<u16>();
+        let ptr_offset = self.read_u32(dx_mesh + layout::DX_MESH_INDEX_BUFFER)? as usize
+            + buffer * size_of::<u32>();
+        let old_length = self.read_u16(count_offset)? as usize * size_of::<u16>();
+
+        let data: Vec<u8> = indices
+            .iter()
+            .flat_map(|value| match self.endian {
+                Endian::Big => value.to_be_bytes(),
+                Endian::Little => value.to_le_bytes(),
+            })
+            .collect();
+
+        let offset = self.replace_array(ptr_offset, old_length, &data)?;
+        self.write_u16(count_offset, length)?;
+        Ok(offset)
+    }
+
+    /// Bytes of the written file
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Writes the data over the array the pointer at `ptr_offset` points to
+    /// when it fits, otherwise the data is appended and the pointer updated
+    fn replace_array(
+        &mut self,
+        ptr_offset: usize,
+        old_length: usize,
+        data: &[u8],
+    ) -> Result<usize, PackError> {
+        let start = self.read_u32(ptr_offset)? as usize;
+        if start != 0 && data.len() <= old_length {
+            self.slice_at(start, data.len())?.copy_from_slice(data);
+            return Ok(start);
+        }
+
+        let padding = (APPEND_ALIGNMENT - self.bytes.len() % APPEND_ALIGNMENT) % APPEND_ALIGNMENT;
+        self.bytes.resize(self.bytes.len() + padding, 0);
+
+        let offset = self.bytes.len();
+        self.bytes.extend_from_slice(data);
+        self.write_u32(ptr_offset, offset as u32)?;
+        Ok(offset)
+    }
+
+    /// Offset of the DirectX mesh within the file
+    fn dx_mesh_offset(&mut self) -> Result<usize, PackError> {
+        match self.read_u32(layout::MESH_IS)? {
+            0 => Err(PackError::MissingMeshData),
+            value => Ok(value as usize),
+        }
+    }
+
+    /// Offset of the descriptor of a vertex buffer within the file
+    fn vertex_buffer_offset(&mut self, buffer: usize) -> Result<usize, PackError> {
+        let dx_mesh = self.dx_mesh_offset()?;
+        let count = self.read_u8(dx_mesh + layout::DX_MESH_VERTEX_BUFFER_COUNT)? as usize;
+        if buffer >= count {
+            return Err(PackError::VertexBufferOutOfRange(buffer));
+        }
+
+        let array = self.read_u32(dx_mesh + layout::DX_MESH_VERTEX_BUFFERS)? as usize;
+        Ok(array + buffer * layout::VERTEX_BUFFER_SIZE)
+    }
+
+    fn slice_at(&mut self, offset: usize, length: usize) -> Result<&mut [u8], PackError> {
+        self.bytes
+            .get_mut(offset..offset + length)
+            .ok_or(PackError::OutOfBounds { offset })
+    }
+
+    fn read_u8(&mut self, offset: usize) -> Result<u8, PackError> {
+        Ok(self.slice_at(offset, 1)?[0])
+    }
+
+    fn read_u16(&mut self, offset: usize) -> Result<u16, PackError> {
+        let endian = self.endian;
+        let value: [u8; 2] = self
+            .slice_at(offset, 2)?
+            .try_into()
+            .expect("Slice length checked");
+
+        Ok(match endian {
+            Endian::Big => u16::from_be_bytes(value),
+            Endian::Little => u16::from_le_bytes(value),
+        })
+    }
+
+    fn read_u32(&mut self, offset: usize) -> Result<u32, PackError> {
+        let endian = self.endian;
+        let value: [u8; 4] = self
+            .slice_at(offset, 4)?
+            .try_into()
+            .expect("Slice length checked");
+
+        Ok(match endian {
+            Endian::Big => u32::from_be_bytes(value),
+            Endian::Little => u32::from_le_bytes(value),
+        })
+    }
+
+    fn write_u16(&mut self, offset: usize, value: u16) -> Result<(), PackError> {
+        let value = match self.endian {
+            Endian::Big => value.to_be_bytes(),
+            Endian::Little => value.to_le_bytes(),
+        };
+
+        self.slice_at(offset, 2)?.copy_from_slice(&value);
+        Ok(())
+    }
+
+    fn write_u32(&mut self, offset: usize, value: u32) -> Result<(), PackError> {
+        let value = match self.endian {
+            Endian::Big => value.to_be_bytes(),
+            Endian::Little => value.to_le_bytes(),
+        };
+
+        self.slice_at(offset, 4)?.copy_from_slice(&value);
+        Ok(())
+    }
+
+    fn write_f32(&mut self, offset: usize, value: f32) -> Result<(), PackError> {
+        self.write_u32(offset, value.to_bits())
+    }
+}
+
+/// Reads the vertex positions from an OBJ file, the positions are in the
+/// order of the merged vertex buffers when the file came from an export
+pub fn read_obj_positions<R: BufRead>(input: R) -> io::Result<Vec<[f32; 3]>> {
+    let mut out = Vec::new();
+
+    for line in input.lines() {
+        let line = line?;
+        let Some(values) = line.strip_prefix("v ") else {
+            continue;
+        };
+
+        let values: Vec<f32> = values
+            .split_whitespace()
+            .map(|value| value.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid vertex {:?}", line),
+                )
+            })?;
+
+        match values.as_slice() {
+            [x, y, z, ..] => out.push([*x, *y, *z]),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("vertex {:?} has less than 3 values", line),
+                ))
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use binrw::Endian;
+
+    use crate::{
+        fixture::{triangle_mesh, FIXTURE_POSITIONS},
+        raw::dx::N1C1T1,
+        view::MeshView,
+    };
+
+    use super::{read_obj_positions, PackError, PackWriter};
+
+    fn vertex_bytes(vertices: &[N1C1T1]) -> Vec<u8> {
+        let length = std::mem::size_of_val(vertices);
+        unsafe { std::slice::from_raw_parts(vertices.as_ptr().cast::<u8>(), length) }.to_vec()
+    }
+
+    /// Reads the positions of the first vertex buffer and the indices of
+    /// every index buffer of a packed file
+    fn read_geometry(bytes: &[u8]) -> (Vec<[f32; 3]>, Vec<Vec<u16>>) {
+        let mesh = MeshView::new(bytes, Endian::Little).unwrap();
+        let dx_mesh = mesh.dx_mesh().unwrap().unwrap();
+        let positions = dx_mesh
+            .vertex_buffers()
+            .unwrap()
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .positions()
+            .unwrap()
+            .unwrap()
+            .iter()
+            .collect();
+        let indices = dx_mesh
+            .index_buffers()
+            .unwrap()
+            .iter()
+            .map(|buffer| buffer.iter().collect())
+            .collect();
+        (positions, indices)
+    }
+
+    #[test]
+    fn test_replace_positions() {
+        let mut writer = PackWriter::new(triangle_mesh(), Endian::Little);
+        let moved = FIXTURE_POSITIONS.map(|[x, y, z]| [x, y, z + 2.0]);
+        writer.replace_positions(0, &moved).unwrap();
+        assert!(matches!(
+            writer.replace_positions(0, &moved[..2]),
+            Err(PackError::PositionCountMismatch { .. })
+        ));
+
+        let bytes = writer.finish();
+        assert_eq!(read_geometry(&bytes).0, moved.to_vec());
+
+        // The rest of each vertex is left untouched
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        let vertex_buffer = mesh
+            .dx_mesh()
+            .unwrap()
+            .unwrap()
+            .vertex_buffers()
+            .unwrap()
+            .unwrap()
+            .get(0)
+            .unwrap();
+        let data: Vec<u8> = vertex_buffer
+            .vertex_data()
+            .unwrap()
+            .unwrap()
+            .iter()
+            .collect();
+        let stride = vertex_buffer.bytes_per_vertex() as usize;
+        for vertex in data.chunks_exact(stride) {
+            let normal: Vec<f32> = vertex[12..24]
+                .chunks_exact(4)
+                .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+                .collect();
+            assert_eq!(normal, vec![0.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn test_grow_buffers() {
+        let original = triangle_mesh();
+        let length = original.len();
+        let mut writer = PackWriter::new(original, Endian::Little);
+
+        // A quad doesn't fit in the space of the original triangle
+        let quad = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+        ];
+        let vertices: Vec<N1C1T1> = quad
+            .iter()
+            .map(|position| N1C1T1 {
+                position: *position,
+                normal: [0.0, 0.0, 1.0],
+                diffuse_rgba: u32::MAX,
+                st_0: [0.0, 0.0],
+            })
+            .collect();
+        let vertex_offset = writer
+            .replace_vertices(0, &vertex_bytes(&vertices))
+            .unwrap();
+        let index_offset = writer.replace_indices(0, &[0, 1, 2, 3]).unwrap();
+        assert!(vertex_offset >= length && index_offset > vertex_offset);
+
+        assert!(matches!(
+            writer.replace_vertices(0, &[0; 5]),
+            Err(PackError::StrideMismatch { .. })
+        ));
+        assert!(matches!(
+            writer.replace_indices(1, &[0]),
+            Err(PackError::IndexBufferOutOfRange(1))
+        ));
+
+        assert_eq!(
+            read_geometry(&writer.finish()),
+            (quad.to_vec(), vec![vec![0, 1, 2, 3]])
+        );
+    }
+
+    #[test]
+    fn test_shrink_in_place() {
+        let original = triangle_mesh();
+        let length = original.len();
+        let mut writer = PackWriter::new(original, Endian::Little);
+
+        writer.replace_indices(0, &[2, 1]).unwrap();
+        let bytes = writer.finish();
+        assert_eq!(bytes.len(), length);
+
+        assert_eq!(read_geometry(&bytes).1, vec![vec![2, 1]]);
+    }
+
+    #[test]
+    fn test_read_obj_positions() {
+        let obj = "# Exported by repack\nv 1 2 3\nv 4 5 6 1\ng material_0\nf 1 2 1\n";
+        assert_eq!(
+            read_obj_positions(obj.as_bytes()).unwrap(),
+            vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]
+        );
+        assert!(read_obj_positions("v 1 2".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+//! Part ID visibility groups, bones and clusters belong to a part ID and
+//! materials have a mask of the parts using them. Hiding parts is how the
+//! game shows damage states such as destroyed pieces of a prop
+
+use crate::{
+    offsets::ValidationError,
+    view::{BoneView, DxClusterView, MeshView},
+};
+
+/// Highest part ID that fits in the material part masks
+pub const MAX_PART_ID: u8 = 31;
+
+/// Set of visible part IDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartMask(pub u32);
+
+impl PartMask {
+    pub const ALL: PartMask = PartMask(u32::MAX);
+    pub const NONE: PartMask = PartMask(0);
+
+    pub fn contains(&self, part_id: u8) -> bool {
+        part_id <= MAX_PART_ID && self.0 & (1 << part_id) != 0
+    }
+
+    pub fn set(&mut self, part_id: u8, visible: bool) {
+        if part_id > MAX_PART_ID {
+            return;
+        }
+
+        if visible {
+            self.0 |= 1 << part_id;
+        } else {
+            self.0 &= !(1 << part_id);
+        }
+    }
+
+    pub fn toggle(&mut self, part_id: u8) {
+        self.set(part_id, !self.contains(part_id));
+    }
+
+    /// Checks if any of the parts in the provided mask are visible
+    pub fn intersects(&self, mask: u32) -> bool {
+        self.0 & mask != 0
+    }
+}
+
+impl Default for PartMask {
+    fn default() -> Self {
+        PartMask::ALL
+    }
+}
+
+/// Cluster of a material that is visible with the current parts
+pub struct VisibleCluster<'a> {
+    pub material: usize,
+    pub cluster: DxClusterView<'a>,
+}
+
+/// Collects the clusters of every material that are visible with the
+/// provided parts, materials not used by any visible part are skipped
+pub fn visible_clusters<'a>(
+    mesh: &MeshView<'a>,
+    parts: PartMask,
+) -> Result<Vec<VisibleCluster<'a>>, ValidationError> {
+    let mut visible = Vec::new();
+    for (material, value) in mesh
+        .materials()?
+        .iter()
+        .flat_map(|array| array.iter())
+        .enumerate()
+        .filter(|(_, value)| parts.intersects(value.part_id_mask()))
+    {
+        let Some(dx_material) = value.dx_material()? else {
+            continue;
+        };
+
+        visible.extend(
+            dx_material
+                .clusters()?
+                .iter()
+                .flat_map(|array| array.iter())
+                .filter(|cluster| parts.contains(cluster.part_id()))
+                .map(|cluster| VisibleCluster { material, cluster }),
+        );
+    }
+
+    Ok(visible)
+}
+
+/// Bones belonging to a visible part
+pub fn visible_bones(mesh: &MeshView, parts: PartMask) -> Result<Vec<BoneView>, ValidationError> {
+    Ok(mesh
+        .bones()?
+        .iter()
+        .flat_map(|array| array.iter())
+        .filter(|bone| parts.contains(bone.part_id()))
+        .collect())
+}
+
+/// Mask of every part ID used by the materials of the mesh
+pub fn used_parts(mesh: &MeshView) -> Result<PartMask, ValidationError> {
+    Ok(PartMask(
+        mesh.materials()?
+            .iter()
+            .flat_map(|array| array.iter())
+            .fold(0, |mask, material| mask | material.part_id_mask()),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use binrw::Endian;
+
+    use crate::{fixture::triangle_mesh, view::MeshView};
+
+    use super::{used_parts, visible_bones, visible_clusters, PartMask};
+
+    #[test]
+    fn test_part_mask() {
+        let mut mask = PartMask::NONE;
+        mask.set(3, true);
+        assert!(mask.contains(3));
+        mask.toggle(3);
+        assert!(!mask.contains(3));
+        assert!(!PartMask::ALL.contains(40));
+    }
+
+    #[test]
+    fn test_visible_clusters() {
+        let bytes = triangle_mesh();
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        assert_eq!(used_parts(&mesh).unwrap(), PartMask(1));
+
+        // Fixture geometry and bone all belong to part 0
+        let visible = visible_clusters(&mesh, PartMask::ALL).unwrap();
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].material, 0);
+        assert_eq!(visible_bones(&mesh, PartMask::ALL).unwrap().len(), 1);
+
+        let mut parts = PartMask::ALL;
+        parts.set(0, false);
+        assert!(visible_clusters(&mesh, parts).unwrap().is_empty());
+        assert!(visible_bones(&mesh, parts).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use std::mem::size_of;
+
+use binrw::Endian;
+use thiserror::Error;
+
+use openglitch_formats::mesh::{
+    FMeshBone, FMeshMaterial, FDATA_BONE_NAME_LENGTH, FDATA_MAX_LOD_MESH_COUNT,
+};
+
+use crate::{offsets::ValidationError, view::layout};
+
+#[derive(Debug, Error)]
+pub enum PatchError {
+    #[error("material index {0} is out of range")]
+    MaterialOutOfRange(usize),
+    #[error("lod index {0} is out of range")]
+    LodOutOfRange(usize),
+    #[error("bone index {0} is out of range")]
+    BoneOutOfRange(usize),
+    #[error("bone name {0:?} does not fit in {FDATA_BONE_NAME_LENGTH} bytes")]
+    NameTooLong(String),
+    #[error("patch target at offset {offset} is outside the buffer")]
+    OutOfBounds { offset: usize },
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+}
+
+/// Single edit made to a mesh, patches are recorded by the editable
+/// model and applied to the bytes of the original file when writing
+#[derive(Debug, Clone, PartialEq)]
+pub enum Patch {
+    SetMaterialTint { material: usize, tint: [f32; 3] },
+    SetMaterialFlags { material: usize, flags: u16 },
+    SetLodDistance { lod: usize, distance: f32 },
+    RenameBone { bone: usize, name: String },
+}
+
+impl Patch {
+    /// Applies the patch to the bytes of an unfixed file stored
+    /// using the provided byte order
+    pub fn apply(&self, bytes: &mut [u8], endian: Endian) -> Result<(), PatchError> {
+        match self {
+            Patch::SetMaterialTint { material, tint } => {
+                let offset = material_offset(bytes, *material, endian)? + layout::MATERIAL_TINT;
+
+                for (index, value) in tint.iter().enumerate() {
+                    write_f32(bytes, offset + index * size_of::<f32>(), *value, endian)?;
+                }
+            }
+            Patch::SetMaterialFlags { material, flags } => {
+                let offset = material_offset(bytes, *material, endian)? + layout::MATERIAL_FLAGS;
+                write_u16(bytes, offset, *flags, endian)?;
+            }
+            Patch::SetLodDistance { lod, distance } => {
+                if *lod >= FDATA_MAX_LOD_MESH_COUNT {
+                    return Err(PatchError::LodOutOfRange(*lod));
+                }
+
+                let offset = layout::MESH_LOD_DISTANCE + lod * size_of::<f32>();
+                write_f32(bytes, offset, *distance, endian)?;
+            }
+            Patch::RenameBone { bone, name } => {
+                let count = read_u8(bytes, layout::MESH_BONE_COUNT)? as usize;
+                if *bone >= count {
+                    return Err(PatchError::BoneOutOfRange(*bone));
+                }
+
+                // Name must leave room for the null terminator
+                if name.len() >= FDATA_BONE_NAME_LENGTH {
+                    return Err(PatchError::NameTooLong(name.clone()));
+                }
+
+                let array = read_u32(bytes, layout::MESH_BONE_ARRAY, endian)? as usize;
+                let offset = array + bone * FMeshBone::SIZE + layout::BONE_NAME;
+
+                let target = slice_at(bytes, offset, FDATA_BONE_NAME_LENGTH)?;
+                target.fill(0);
+                target[..name.len()].copy_from_slice(name.as_bytes());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Applies all the provided patches in order
+pub fn apply_patches(
+    patches: &[Patch],
+    bytes: &mut [u8],
+    endian: Endian,
+) -> Result<(), PatchError> {
+    patches
+        .iter()
+        .try_for_each(|patch| patch.apply(bytes, endian))
+}
+
+/// Offset of the material within the file
+fn material_offset(bytes: &mut [u8], material: usize, endian: Endian) -> Result<usize, PatchError> {
+    let count = read_u8(bytes, layout::MESH_MATERIAL_COUNT)? as usize;
+    if material >= count {
+        return Err(PatchError::MaterialOutOfRange(material));
+    }
+
+    let array = read_u32(bytes, layout::MESH_MATERIAL_ARRAY, endian)? as usize;
+    Ok(array + material * FMeshMaterial::SIZE)
+}
+
+fn slice_at(bytes: &mut [u8], offset: usize, length: usize) -> Result<&mut [u8], PatchError> {
+    bytes
+        .get_mut(offset..offset + length)
+        .ok_or(PatchError::OutOfBounds { offset })
+}
+
+fn read_u8(bytes: &mut [u8], offset: usize) -> Result<u8, PatchError> {
+    Ok(slice_at(bytes, offset, 1)?[0])
+}
+
+fn read_u32(bytes: &mut [u8], offset: usize, endian: Endian) -> Result<u32, PatchError> {
+    let value: [u8; 4] = slice_at(bytes, offset, 4)?
+        .try_into()
+        .expect("Slice length checked");
+
+    Ok(match endian {
+        Endian::Big => u32::from_be_bytes(value),
+        Endian::Little => u32::from_le_bytes(value),
+    })
+}
+
+fn write_u16(
+    bytes: &mut [u8],
+    offset: usize,
+    value: u16,
+    endian: Endian,
+) -> Result<(), PatchError> {
+    let value = match endian {
+        Endian::Big => value.to_be_bytes(),
+        Endian::Little => value.to_le_bytes(),
+    };
+
+    slice_at(bytes, offset, 2)?.copy_from_slice(&value);
+    Ok(())
+}
+
+fn write_f32(
+    bytes: &mut [u8],
+    offset: usize,
+    value: f32,
+    endian: Endian,
+) -> Result<(), PatchError> {
+    let value = match endian {
+        Endian::Big => value.to_be_bytes(),
+        Endian::Little => value.to_le_bytes(),
+    };
+
+    slice_at(bytes, offset, 4)?.copy_from_slice(&value);
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! Conversion metadata recorded alongside everything repack writes so a
+//! derived file can be traced back to the exact input and settings that
+//! produced it. Text exports embed the metadata as comments, every output
+//! also gets a `{file}.provenance.json` sidecar since the binary formats
+//! have nowhere to store it
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Input file of a conversion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceFile {
+    pub path: String,
+    /// Hex encoded blake3 hash of the file contents
+    pub blake3: String,
+}
+
+/// Record of how a file was produced
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Name and version of the converter
+    pub converter: String,
+    pub sources: Vec<SourceFile>,
+    /// Settings the conversion was made with
+    pub settings: BTreeMap<String, String>,
+}
+
+impl Default for Provenance {
+    fn default() -> Self {
+        Self {
+            converter: format!("repack {}", env!("CARGO_PKG_VERSION")),
+            sources: Vec::new(),
+            settings: BTreeMap::new(),
+        }
+    }
+}
+
+impl Provenance {
+    /// Adds an input file from its contents
+    pub fn with_source(mut self, path: &Path, bytes: &[u8]) -> Self {
+        self.sources.push(SourceFile {
+            path: path.display().to_string(),
+            blake3: blake3::hash(bytes).to_hex().to_string(),
+        });
+        self
+    }
+
+    pub fn with_setting(mut self, key: &str, value: impl ToString) -> Self {
+        self.settings.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Lines describing the provenance for embedding as comments
+    pub fn comment_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("converter: {}", self.converter)];
+        lines.extend(
+            self.sources
+                .iter()
+                .map(|source| format!("source: {} blake3={}", source.path, source.blake3)),
+        );
+        lines.extend(
+            self.settings
+                .iter()
+                .map(|(key, value)| format!("setting: {}={}", key, value)),
+        );
+        lines
+    }
+
+    /// Path of the sidecar written for the output at `path`
+    pub fn sidecar_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(".provenance.json");
+        PathBuf::from(name)
+    }
+
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        serde_json::to_writer_pretty(out, self)?;
+        Ok(())
+    }
+
+    /// Writes the sidecar of the output at `path`
+    pub fn write_sidecar(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(self)?;
+        std::fs::write(Self::sidecar_path(path), json)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::Provenance;
+
+    #[test]
+    fn test_provenance() {
+        let provenance = Provenance::default()
+            .with_source(Path::new("mesh.ape"), b"abc")
+            .with_setting("format", "obj");
+        assert_eq!(
+            provenance.comment_lines()[1..],
+            [
+                format!("source: mesh.ape blake3={}", blake3::hash(b"abc").to_hex()),
+                "setting: format=obj".to_string(),
+            ]
+        );
+
+        let mut json = Vec::new();
+        provenance.write_json(&mut json).unwrap();
+        let read: Provenance = serde_json::from_slice(&json).unwrap();
+        assert_eq!(read, provenance);
+
+        assert_eq!(
+            Provenance::sidecar_path(Path::new("out/mesh.obj")),
+            Path::new("out/mesh.obj.provenance.json")
+        );
+    }
+}
@@ -1,7 +1,16 @@
-use swapbytes::SwapBytes;
+use std::mem::size_of;
 
-use crate::st::{
-    array_ptr, array_ptr_mut, fix_offset, try_fix, try_fix_array, CFSphere, CFVec3, Fixable,
+use num_enum::TryFromPrimitive;
+use swapbytes::SwapBytes;
+use thiserror::Error;
+use tracing::trace;
+
+use crate::{
+    offsets::{OffsetValidator, ValidationError},
+    st::{
+        array_ptr, array_ptr_mut, fix_offset, try_fix_array, CFSphere, CFVec3, Fixable,
+        SourceEndian,
+    },
 };
 
 /// Directx8 mesh definition
@@ -11,9 +20,9 @@ pub struct DxMesh {
     /// See FDX8MESH_FLAG_* for info
     pub flags: u16,
     /// Number of vertex buffers used by this mesh
-    vertex_buffer_count: u8,
+    pub(crate) vertex_buffer_count: u8,
     /// Number of index buffers used by this mesh
-    index_buffer_count: u8,
+    pub(crate) index_buffer_count: u8,
     /// The address offset for the temporary portion of the file when loaded (this portion is converted to DX resources).
     pub disposable_offset: u32,
     /// Used only when nSegCount is 0
@@ -21,40 +30,120 @@ pub struct DxMesh {
     /// Set at runtime to a pointer of the base object (null and unused for this impl)
     _mesh: *mut (),
     /// Array of vertex buffer descriptors
-    vertex_buffers: *mut DxVertexBufferDescriptor,
-    /// Array of Collision vertex buffers
-    coll_vertex_buffer: *mut *mut CFVec3,
+    pub(crate) vertex_buffers: *mut DxVertexBufferDescriptor,
+    /// Array of Collision vertex buffers, one for each vertex buffer holding
+    /// a CPU side copy of its positions
+    pub(crate) coll_vertex_buffer: ArrayPtr<ArrayPtr<CFVec3>>,
     /// Array for number of indices used by this mesh in each IB
-    indicies_counts: *mut u16,
+    pub(crate) indicies_counts: *mut u16,
     // Pointer to an array of index buffers (arrays of u16s)
-    index_buffer: ArrayPtr<ArrayPtr<u16>>,
+    pub(crate) index_buffer: ArrayPtr<ArrayPtr<u16>>,
 }
 
 /// Type alias that shows a pointer is an array of values rather than
 /// just a normal pointer to a single value
-type ArrayPtr<T> = *mut T;
+pub(crate) type ArrayPtr<T> = *mut T;
 
 impl Fixable for DxMesh {
-    unsafe fn fix_offset(&mut self, ptr: *mut u8) {
-        try_fix_array(&mut self.vertex_buffers, self.vertex_buffer_count, ptr);
-        // todo: fix col vertex buffer
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, endian: SourceEndian) {
+        try_fix_array(
+            &mut self.vertex_buffers,
+            self.vertex_buffer_count,
+            ptr,
+            endian,
+        );
+
+        // Sized from the vertex buffers so they must be fixed first
+        self.coll_vertex_buffer = fix_offset(self.coll_vertex_buffer, ptr);
+        if let Some(buffers) = array_ptr_mut(self.coll_vertex_buffer, self.vertex_buffer_count) {
+            for (index, buffer) in buffers.iter_mut().enumerate() {
+                // The pointer array isn't part of a structure so it's swapped here
+                *buffer = endian.read(*buffer);
+
+                let length = self.collision_vertex_count(index);
+                try_fix_array(buffer, length, ptr, endian);
+            }
+        }
 
-        try_fix_array(&mut self.indicies_counts, self.index_buffer_count, ptr);
+        try_fix_array(
+            &mut self.indicies_counts,
+            self.index_buffer_count,
+            ptr,
+            endian,
+        );
 
         self.index_buffer = fix_offset(self.index_buffer, ptr);
 
         if !self.index_buffer.is_null() {
             for i in 0..self.index_buffer_count {
-                println!("At offset {}", i);
                 let length = self.index_count(i as usize);
+                trace!(index = i, length, "fixing index buffer");
                 let buffer = &mut *self.index_buffer.add(i as usize);
+                // The pointer array isn't part of a structure so it's swapped here
+                *buffer = endian.read(*buffer);
+
+                try_fix_array(buffer, length, ptr, endian);
+            }
+        }
+    }
 
-                try_fix_array(buffer, length, ptr);
+    unsafe fn validate_offsets(&self, validator: &OffsetValidator) -> Result<(), ValidationError> {
+        validator.array(
+            "DxMesh.vertex_buffers",
+            self.vertex_buffers,
+            self.vertex_buffer_count as usize,
+        )?;
+
+        // The collision buffers hold a copy of the positions of each vertex
+        // buffer, the pointers and the vertices are swapped in place
+        let vertex_counts: Vec<usize> = validator
+            .slice(
+                "DxMesh.vertex_buffers",
+                self.vertex_buffers,
+                self.vertex_buffer_count as usize,
+            )?
+            .unwrap_or_default()
+            .iter()
+            .map(|buffer| validator.read(buffer.vertex_count) as usize)
+            .collect();
+        if let Some(buffers) = validator.fixed(
+            "DxMesh.coll_vertex_buffer",
+            self.coll_vertex_buffer,
+            self.vertex_buffer_count as usize,
+        )? {
+            let counts = vertex_counts.iter().copied().chain(std::iter::repeat(0));
+            for (buffer, count) in buffers.iter().zip(counts) {
+                validator.fixed(
+                    "DxMesh.coll_vertex_buffer[]",
+                    validator.read(*buffer),
+                    count,
+                )?;
             }
         }
+
+        // The counts, the pointers to the index buffers and the indices are
+        // all swapped in place
+        let count = self.index_buffer_count as usize;
+        let counts = validator.fixed("DxMesh.indicies_counts", self.indicies_counts, count)?;
+        let Some(buffers) = validator.fixed("DxMesh.index_buffer", self.index_buffer, count)?
+        else {
+            return Ok(());
+        };
+
+        // Fixing the index buffers reads their lengths from the counts
+        let counts = counts.ok_or_else(|| validator.error("DxMesh.indicies_counts", 0))?;
+        for (buffer, length) in buffers.iter().zip(counts) {
+            validator.fixed(
+                "DxMesh.index_buffer[]",
+                validator.read(*buffer),
+                validator.read(*length) as usize,
+            )?;
+        }
+        Ok(())
     }
 }
 impl Fixable for u16 {}
+impl Fixable for CFVec3 {}
 
 impl DxMesh {
     pub fn vertex_buffers(&self) -> Option<&[DxVertexBufferDescriptor]> {
@@ -64,8 +153,35 @@ impl DxMesh {
         unsafe { array_ptr_mut(self.vertex_buffers, self.vertex_buffer_count) }
     }
 
-    /// Gets the number of indexes in the index buffer at the provided index
+    /// Number of vertices in the collision vertex buffer at the provided
+    /// index, the collision buffers mirror the vertex buffers
+    fn collision_vertex_count(&self, index: usize) -> usize {
+        self.vertex_buffers()
+            .and_then(|buffers| buffers.get(index))
+            .map(|buffer| buffer.vertex_count as usize)
+            .unwrap_or_default()
+    }
+
+    /// Collision copy of the positions of the vertex buffer at the provided
+    /// index, none when the mesh has no collision vertices for the buffer
+    pub fn collision_vertices(&self, index: usize) -> Option<&[CFVec3]> {
+        if self.coll_vertex_buffer.is_null() || index >= self.vertex_buffer_count as usize {
+            return None;
+        }
+
+        let length = self.collision_vertex_count(index);
+        let ptr = unsafe { *self.coll_vertex_buffer.add(index) };
+
+        unsafe { array_ptr(ptr, length) }
+    }
+
+    /// Gets the number of indexes in the index buffer at the provided index,
+    /// zero for indices past the index buffer count
     pub fn index_count(&self, index: usize) -> u16 {
+        if self.indicies_counts.is_null() || index >= self.index_buffer_count as usize {
+            return 0;
+        }
+
         unsafe { *self.indicies_counts.add(index) }
     }
 
@@ -82,7 +198,7 @@ impl DxMesh {
     }
 
     pub fn index_buffer(&self, index: usize) -> Option<&[u16]> {
-        if self.index_buffer.is_null() {
+        if self.index_buffer.is_null() || index >= self.index_buffer_count as usize {
             return None;
         }
 
@@ -93,7 +209,7 @@ impl DxMesh {
     }
 
     pub fn index_buffer_mut(&self, index: usize) -> Option<&mut [u16]> {
-        if self.index_buffer.is_null() {
+        if self.index_buffer.is_null() || index >= self.index_buffer_count as usize {
             return None;
         }
 
@@ -105,7 +221,7 @@ impl DxMesh {
 }
 
 /// GameCube attr types, used for its position index types
-#[derive(Debug, Clone, Copy, SwapBytes)]
+#[derive(Debug, Clone, Copy, SwapBytes, TryFromPrimitive)]
 #[repr(i8)]
 pub enum DxVertexBufferType {
     Shader = -1,
@@ -129,6 +245,44 @@ pub enum DxVertexBufferType {
     C1T1 = 6,
 }
 
+impl DxVertexBufferType {
+    /// Size in bytes of a single vertex of this type, shader
+    /// vertex buffers have no fixed layout
+    pub fn vertex_size(&self) -> Option<usize> {
+        Some(match self {
+            DxVertexBufferType::Shader => return None,
+            DxVertexBufferType::N1C1T1 => size_of::<N1C1T1>(),
+            DxVertexBufferType::N1C1T2 => size_of::<N1C1T2>(),
+            DxVertexBufferType::N1W3C1T1 => size_of::<N1W3C1T1>(),
+            DxVertexBufferType::N1W3C1T2 => size_of::<N1W3C1T2>(),
+            DxVertexBufferType::TLC2T2 => size_of::<TLC2T2>(),
+            DxVertexBufferType::C1 => size_of::<C1>(),
+            DxVertexBufferType::C1T1 => size_of::<C1T1>(),
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum VertexBufferError {
+    #[error("vertex buffer uses a shader defined layout")]
+    Shader,
+    #[error("unknown vertex layout index {0}")]
+    UnknownLayout(i8),
+    #[error("{0:?} vertex buffers don't store positions")]
+    MissingPositions(DxVertexBufferType),
+    #[error("vertex buffer has no vertex data")]
+    MissingData,
+    #[error(
+        "unknown vertex layout: {layout:?} vertex buffer declares {declared} bytes per vertex \
+         but the layout is {expected} bytes"
+    )]
+    StrideMismatch {
+        layout: DxVertexBufferType,
+        declared: u16,
+        expected: usize,
+    },
+}
+
 pub enum DxVertexBufferValues<'a> {
     // 1 normal 1 color 1 TC
     N1C1T1(&'a mut [N1C1T1]),
@@ -219,9 +373,11 @@ pub struct FLink {
 }
 
 impl Fixable for FLink {
-    unsafe fn fix_offset(&mut self, ptr: *mut u8) {
-        try_fix(&mut self.prev_link, ptr);
-        try_fix(&mut self.next_link, ptr);
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, _endian: SourceEndian) {
+        // The links chain the vertex buffers at runtime, following them
+        // would fix the neighbouring buffers again so only the offsets are fixed
+        self.prev_link = fix_offset(self.prev_link, ptr);
+        self.next_link = fix_offset(self.next_link, ptr);
     }
 }
 
@@ -231,19 +387,20 @@ pub struct DxVertexBufferDescriptor {
     // Link to other VBs
     _link: FLink,
     // Number of vertices in this DX vertex buffer
-    vertex_count: u32,
+    pub(crate) vertex_count: u32,
     // Number of bytes per vertex
-    bytes_per_vertex: u16,
+    pub(crate) bytes_per_vertex: u16,
     // Number of f32,f32 (S,T) texture coordinate pairs used for lightmaps, per vertex
-    lmtc_count: u16,
+    pub(crate) lmtc_count: u16,
     // Pointer to the stream of lightmap UV's
-    lmuv_stream: *mut (),
+    pub(crate) lmuv_stream: *mut (),
     // Pointer to the stream of basis vectors.
-    basis_stream: *mut (),
-    // Index into FDX8VB_InfoTable[] of the entry that describes this VB format (-1=shader)
-    info_index: DxVertexBufferType,
+    pub(crate) basis_stream: *mut (),
+    // Index into FDX8VB_InfoTable[] of the entry that describes this VB format (-1=shader),
+    // kept as the stored value since damaged files can hold any value
+    pub(crate) info_index: i8,
     // TRUE=this VB is dynamic
-    dynamic: bool,
+    dynamic: u8,
     // TRUE=software vertex processing
     software_vp: u8,
     // TRUE=this VB is locked
@@ -257,14 +414,42 @@ pub struct DxVertexBufferDescriptor {
     // Handle to the vertex shader this VB is currently attached to (or FVF code if nInfoIndex is not -1)
     vertex_shader: u32,
     // Pointer to the actual DX vertex buffers
-    vertex_buffer: *mut (), /* IDirect3DVertexBuffer8 */
+    pub(crate) vertex_buffer: *mut (), /* IDirect3DVertexBuffer8 */
 }
 
 impl DxVertexBufferDescriptor {
-    pub fn positions(&mut self) -> Vec<[f32; 3]> {
+    /// Number of vertices in this vertex buffer
+    pub fn vertex_count(&self) -> u32 {
+        self.vertex_count
+    }
+
+    /// Layout of the vertices in this vertex buffer
+    pub fn vertex_type(&self) -> Result<DxVertexBufferType, VertexBufferError> {
+        DxVertexBufferType::try_from(self.info_index)
+            .map_err(|_| VertexBufferError::UnknownLayout(self.info_index))
+    }
+
+    /// Checks the declared bytes per vertex matches the size of the
+    /// structure the vertices are decoded as
+    pub fn validate_stride(&self) -> Result<(), VertexBufferError> {
+        let layout = self.vertex_type()?;
+        let expected = layout.vertex_size().ok_or(VertexBufferError::Shader)?;
+
+        if self.bytes_per_vertex as usize != expected {
+            return Err(VertexBufferError::StrideMismatch {
+                layout,
+                declared: self.bytes_per_vertex,
+                expected,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn positions(&mut self) -> Result<Vec<[f32; 3]>, VertexBufferError> {
         let mut out = Vec::new();
 
-        match self.buffer_values().unwrap() {
+        match self.buffer_values()? {
             DxVertexBufferValues::N1C1T1(value) => {
                 out.extend(value.iter().map(|value| value.position.clone()))
             }
@@ -280,15 +465,114 @@ impl DxVertexBufferDescriptor {
             DxVertexBufferValues::TLC2T2(value) => {
                 out.extend(value.iter().map(|value| value.position.clone()))
             }
-            DxVertexBufferValues::C1(_) => todo!(),
-            DxVertexBufferValues::C1T1(_) => todo!(),
+            DxVertexBufferValues::C1(_) => {
+                return Err(VertexBufferError::MissingPositions(DxVertexBufferType::C1))
+            }
+            DxVertexBufferValues::C1T1(_) => {
+                return Err(VertexBufferError::MissingPositions(
+                    DxVertexBufferType::C1T1,
+                ))
+            }
         }
 
-        out
+        Ok(out)
     }
 
-    pub fn buffer_values(&mut self) -> Option<DxVertexBufferValues> {
-        match self.info_index {
+    /// Normals of the vertices, post transformed and color only
+    /// layouts have no normals
+    pub fn normals(&mut self) -> Result<Option<Vec<[f32; 3]>>, VertexBufferError> {
+        Ok(match self.buffer_values()? {
+            DxVertexBufferValues::N1C1T1(value) => {
+                Some(value.iter().map(|value| value.normal).collect())
+            }
+            DxVertexBufferValues::N1C1T2(value) => {
+                Some(value.iter().map(|value| value.normal).collect())
+            }
+            DxVertexBufferValues::N1W3C1T1(value) => {
+                Some(value.iter().map(|value| value.normal).collect())
+            }
+            DxVertexBufferValues::N1W3C1T2(value) => {
+                Some(value.iter().map(|value| value.normal).collect())
+            }
+            DxVertexBufferValues::TLC2T2(_)
+            | DxVertexBufferValues::C1(_)
+            | DxVertexBufferValues::C1T1(_) => None,
+        })
+    }
+
+    /// Texture coordinates of the vertices for the layer, none when the
+    /// layout has no coordinates for the layer. Layouts with two sets of
+    /// coordinates provide layers 0 and 1
+    pub fn uvs(&mut self, layer: usize) -> Result<Option<Vec<[f32; 2]>>, VertexBufferError> {
+        Ok(match (self.buffer_values()?, layer) {
+            (DxVertexBufferValues::N1C1T1(value), 0) => {
+                Some(value.iter().map(|value| value.st_0).collect())
+            }
+            (DxVertexBufferValues::N1C1T2(value), 0) => {
+                Some(value.iter().map(|value| value.st_0).collect())
+            }
+            (DxVertexBufferValues::N1C1T2(value), 1) => {
+                Some(value.iter().map(|value| value.st_1).collect())
+            }
+            (DxVertexBufferValues::N1W3C1T1(value), 0) => {
+                Some(value.iter().map(|value| value.st_0).collect())
+            }
+            (DxVertexBufferValues::N1W3C1T2(value), 0) => {
+                Some(value.iter().map(|value| value.st_0).collect())
+            }
+            (DxVertexBufferValues::N1W3C1T2(value), 1) => {
+                Some(value.iter().map(|value| value.st_1).collect())
+            }
+            (DxVertexBufferValues::TLC2T2(value), 0) => {
+                Some(value.iter().map(|value| value.st_0).collect())
+            }
+            (DxVertexBufferValues::TLC2T2(value), 1) => {
+                Some(value.iter().map(|value| value.st_1).collect())
+            }
+            (DxVertexBufferValues::C1T1(value), 0) => {
+                Some(value.iter().map(|value| value.st_0).collect())
+            }
+            _ => None,
+        })
+    }
+
+    /// Diffuse colors of the vertices as normalized RGBA
+    pub fn colors(&mut self) -> Result<Option<Vec<[f32; 4]>>, VertexBufferError> {
+        let colors: Option<Vec<u32>> = match self.buffer_values()? {
+            DxVertexBufferValues::N1C1T1(value) => {
+                Some(value.iter().map(|value| value.diffuse_rgba).collect())
+            }
+            DxVertexBufferValues::N1C1T2(value) => {
+                Some(value.iter().map(|value| value.diffuse_rgba).collect())
+            }
+            DxVertexBufferValues::N1W3C1T1(value) => {
+                Some(value.iter().map(|value| value.diffuse_rgba).collect())
+            }
+            DxVertexBufferValues::N1W3C1T2(value) => {
+                Some(value.iter().map(|value| value.diffuse_rgba).collect())
+            }
+            DxVertexBufferValues::TLC2T2(value) => {
+                Some(value.iter().map(|value| value.diffuse_rgba).collect())
+            }
+            DxVertexBufferValues::C1T1(value) => {
+                Some(value.iter().map(|value| value.diffuse_rgba).collect())
+            }
+            DxVertexBufferValues::C1(_) => None,
+        };
+
+        Ok(colors.map(|colors| colors.into_iter().map(decode_d3d_color).collect()))
+    }
+
+    /// Provides the vertices of the buffer, the declared stride is validated
+    /// against the vertex layout before the data is interpreted
+    pub fn buffer_values(&mut self) -> Result<DxVertexBufferValues, VertexBufferError> {
+        self.validate_stride()?;
+        self.buffer_values_unchecked()
+            .ok_or(VertexBufferError::MissingData)
+    }
+
+    fn buffer_values_unchecked(&mut self) -> Option<DxVertexBufferValues> {
+        match self.vertex_type().ok()? {
             DxVertexBufferType::Shader => None,
             DxVertexBufferType::N1C1T1 => {
                 let values = unsafe {
@@ -336,33 +620,83 @@ impl DxVertexBufferDescriptor {
     }
 }
 
+/// Converts a packed D3DCOLOR (ARGB) into normalized RGBA
+pub fn decode_d3d_color(value: u32) -> [f32; 4] {
+    let [alpha, red, green, blue] = value.to_be_bytes();
+    [red, green, blue, alpha].map(|value| value as f32 / 255.0)
+}
+
 impl Fixable for DxVertexBufferDescriptor {
-    unsafe fn fix_offset(&mut self, ptr: *mut u8) {
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, endian: SourceEndian) {
         self.lmuv_stream = fix_offset(self.lmuv_stream, ptr);
         self.basis_stream = fix_offset(self.basis_stream, ptr);
         self.lock_buf = fix_offset(self.lock_buf, ptr);
         self.vertex_buffer = fix_offset(self.vertex_buffer, ptr);
-        self._link.fix_offset(ptr);
+        self._link.fix_offset(ptr, endian);
+    }
+
+    unsafe fn validate_offsets(&self, validator: &OffsetValidator) -> Result<(), ValidationError> {
+        validator.opaque("DxVertexBufferDescriptor.lmuv_stream", self.lmuv_stream)?;
+        validator.opaque("DxVertexBufferDescriptor.basis_stream", self.basis_stream)?;
+        validator.opaque("DxVertexBufferDescriptor.lock_buf", self.lock_buf)?;
+        validator.opaque(
+            "DxVertexBufferDescriptor.link.prev_link",
+            self._link.prev_link,
+        )?;
+        validator.opaque(
+            "DxVertexBufferDescriptor.link.next_link",
+            self._link.next_link,
+        )?;
+
+        // Saturated so the length is rejected rather than wrapping on 32-bit hosts
+        let length = (self.vertex_count as usize).saturating_mul(self.bytes_per_vertex as usize);
+        validator.slice(
+            "DxVertexBufferDescriptor.vertex_buffer",
+            self.vertex_buffer.cast::<u8>(),
+            length,
+        )?;
+        // Vertices are read as structures of f32 values so the data has to
+        // be aligned for them too
+        validator.slice(
+            "DxVertexBufferDescriptor.vertex_buffer",
+            self.vertex_buffer.cast::<f32>(),
+            0,
+        )?;
+        Ok(())
     }
 }
 
 #[derive(Debug, SwapBytes)]
 #[repr(C)]
 pub struct DxMeshMaterial {
-    cluster: ArrayPtr<DxMeshCluster>,
-    cluster_count: u32,
+    pub(crate) cluster: ArrayPtr<DxMeshCluster>,
+    pub(crate) cluster_count: u32,
 }
 
 impl Fixable for DxMeshMaterial {
-    unsafe fn fix_offset(&mut self, ptr: *mut u8) {
-        try_fix_array(&mut self.cluster, self.cluster_count as usize, ptr);
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, endian: SourceEndian) {
+        try_fix_array(&mut self.cluster, self.cluster_count as usize, ptr, endian);
+    }
+
+    unsafe fn validate_offsets(&self, validator: &OffsetValidator) -> Result<(), ValidationError> {
+        validator.array(
+            "DxMeshMaterial.cluster",
+            self.cluster,
+            self.cluster_count as usize,
+        )
+    }
+}
+
+impl DxMeshMaterial {
+    pub fn clusters(&self) -> Option<&[DxMeshCluster]> {
+        unsafe { array_ptr(self.cluster, self.cluster_count as usize) }
     }
 }
 
 #[derive(Debug, SwapBytes)]
 #[repr(C)]
 pub struct DxMeshCluster {
-    strip_count: u16,
+    pub(crate) strip_count: u16,
     flags: u8,
     segment_index: u8,
     pub vertex_buffer_index: u8,
@@ -370,19 +704,38 @@ pub struct DxMeshCluster {
     part_id: u8,
     lod_id: u8,
 
-    push_buffer: *mut (),
+    pub(crate) push_buffer: *mut (),
     pub tri_list: DxMeshTriList,
-    mesh_strip: ArrayPtr<DxMeshStrip>,
+    pub(crate) mesh_strip: ArrayPtr<DxMeshStrip>,
 }
 
 impl Fixable for DxMeshCluster {
-    unsafe fn fix_offset(&mut self, ptr: *mut u8) {
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, endian: SourceEndian) {
         self.push_buffer = fix_offset(self.push_buffer, ptr);
-        try_fix_array(&mut self.mesh_strip, self.strip_count, ptr);
+        try_fix_array(&mut self.mesh_strip, self.strip_count, ptr, endian);
+    }
+
+    unsafe fn validate_offsets(&self, validator: &OffsetValidator) -> Result<(), ValidationError> {
+        validator.opaque("DxMeshCluster.push_buffer", self.push_buffer)?;
+        validator.array(
+            "DxMeshCluster.mesh_strip",
+            self.mesh_strip,
+            self.strip_count as usize,
+        )
     }
 }
 
 impl DxMeshCluster {
+    /// Part ID the geometry of this cluster belongs to
+    pub fn part_id(&self) -> u8 {
+        self.part_id
+    }
+
+    /// LOD the geometry of this cluster is drawn at
+    pub fn lod_id(&self) -> u8 {
+        self.lod_id
+    }
+
     pub fn mesh_strips(&self) -> Option<&[DxMeshStrip]> {
         unsafe { array_ptr(self.mesh_strip, self.strip_count) }
     }
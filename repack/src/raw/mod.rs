@@ -1 +1,2 @@
 pub mod dx;
+pub mod ps2;
@@ -0,0 +1,424 @@
+//! PlayStation 2 platform specific mesh data. Rather than vertex and index
+//! buffers the geometry is stored as VIF packets, each packet is the data
+//! sent to VU1 for a run of strips so the vertices are decoded from the
+//! UNPACK commands of the packet
+//!
+//! The structures follow the layout of the DirectX ones, the mesh holds
+//! the array of packets and each material references the run of packets
+//! drawing it
+
+use swapbytes::SwapBytes;
+use thiserror::Error;
+
+use crate::{
+    offsets::{OffsetValidator, ValidationError},
+    st::{array_ptr, fix_offset, try_fix_array, CFSphere, Fixable, SourceEndian},
+};
+
+use super::dx::ArrayPtr;
+
+/// PS2 mesh definition
+#[derive(Debug, SwapBytes)]
+#[repr(C)]
+pub struct Ps2Mesh {
+    pub flags: u16,
+    /// Number of VIF packets used by this mesh
+    pub(crate) packet_count: u16,
+    /// Used only when nSegCount is 0
+    pub at_rest_bound_sphere: CFSphere,
+    /// Set at runtime to a pointer of the base object (null and unused for this impl)
+    _mesh: *mut (),
+    /// Array of VIF packets
+    pub(crate) packets: ArrayPtr<Ps2MeshPacket>,
+}
+
+impl Fixable for Ps2Mesh {
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, endian: SourceEndian) {
+        try_fix_array(&mut self.packets, self.packet_count, ptr, endian);
+    }
+
+    unsafe fn validate_offsets(&self, validator: &OffsetValidator) -> Result<(), ValidationError> {
+        validator.array("Ps2Mesh.packets", self.packets, self.packet_count as usize)
+    }
+}
+
+impl Ps2Mesh {
+    pub fn packets(&self) -> Option<&[Ps2MeshPacket]> {
+        unsafe { array_ptr(self.packets, self.packet_count) }
+    }
+}
+
+/// VIF packet drawing strips of a single segment, part and LOD
+#[derive(Debug, SwapBytes)]
+#[repr(C)]
+pub struct Ps2MeshPacket {
+    /// VIF codes and their data
+    pub(crate) data: *mut u8,
+    /// Length of the data in quadwords (16 bytes)
+    pub(crate) qword_count: u16,
+    segment_index: u8,
+    part_id: u8,
+    lod_id: u8,
+    _pad: [u8; 3],
+}
+
+impl Fixable for Ps2MeshPacket {
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, _endian: SourceEndian) {
+        self.data = fix_offset(self.data, ptr);
+    }
+
+    unsafe fn validate_offsets(&self, validator: &OffsetValidator) -> Result<(), ValidationError> {
+        validator.slice("Ps2MeshPacket.data", self.data, self.data_length())?;
+        Ok(())
+    }
+}
+
+impl Ps2MeshPacket {
+    /// Part ID the geometry of this packet belongs to
+    pub fn part_id(&self) -> u8 {
+        self.part_id
+    }
+
+    /// LOD the geometry of this packet is drawn at
+    pub fn lod_id(&self) -> u8 {
+        self.lod_id
+    }
+
+    /// Length of the data in bytes
+    pub fn data_length(&self) -> usize {
+        self.qword_count as usize * 16
+    }
+
+    pub fn data(&self) -> Option<&[u8]> {
+        unsafe { array_ptr(self.data, self.data_length()) }
+    }
+}
+
+/// PS2 platform data of a material, the packets drawing the material
+#[derive(Debug, SwapBytes)]
+#[repr(C)]
+pub struct Ps2MeshMaterial {
+    /// Index of the first packet in the mesh packets
+    pub first_packet: u16,
+    pub packet_count: u16,
+}
+
+impl Fixable for Ps2MeshMaterial {}
+
+impl Ps2MeshMaterial {
+    /// Indices of the packets drawing the material
+    pub fn packet_range(&self) -> std::ops::Range<usize> {
+        let start = self.first_packet as usize;
+        start..start + self.packet_count as usize
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VifError {
+    #[error("VIF code at {offset} runs past the end of the packet")]
+    Truncated { offset: usize },
+    #[error("unknown VIF command {command:#04x} at {offset}")]
+    UnknownCommand { command: u8, offset: usize },
+    #[error("batch ending at {offset} has {positions} positions but {count} {attribute}")]
+    AttributeCount {
+        offset: usize,
+        attribute: &'static str,
+        positions: usize,
+        count: usize,
+    },
+}
+
+/// ADC bit of the position W component, set on vertices that don't
+/// draw a triangle so a new strip starts
+const ADC_FLAG: u32 = 0x8000;
+
+/// Fixed point scale of the 16-bit texture coordinates
+const UV_SCALE: f32 = 1.0 / 4096.0;
+
+/// Color channel value of full intensity, the GS treats 0x80 as 1.0
+const COLOR_SCALE: f32 = 1.0 / 128.0;
+
+/// Vertices sent to VU1 by one microprogram call
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Ps2Batch {
+    pub positions: Vec<[f32; 3]>,
+    /// First texture coordinates, empty when the batch has none
+    pub uvs: Vec<[f32; 2]>,
+    /// Vertex colors as normalized RGBA, empty when the batch has none
+    pub colors: Vec<[f32; 4]>,
+    /// Whether each vertex has the ADC flag set, positions without a W
+    /// component draw every vertex as one strip
+    skip_draw: Vec<bool>,
+}
+
+impl Ps2Batch {
+    /// Decodes the strips of the batch, a strip starts at each run of
+    /// vertices with the ADC flag and every other triangle of a strip has
+    /// reversed winding
+    pub fn triangles(&self) -> Vec<[u32; 3]> {
+        let mut out = Vec::new();
+        let mut strip_start = 0;
+        for index in 0..self.positions.len() {
+            let skip = self.skip_draw.get(index).copied().unwrap_or(false);
+            if skip {
+                if index == 0 || !self.skip_draw[index - 1] {
+                    strip_start = index;
+                }
+                continue;
+            }
+            if index < strip_start + 2 {
+                continue;
+            }
+
+            let [a, b, c] = [index - 2, index - 1, index].map(|value| value as u32);
+            out.push(match (index - strip_start) % 2 {
+                0 => [a, b, c],
+                _ => [b, a, c],
+            });
+        }
+        out
+    }
+}
+
+/// Reads a little endian word, the PS2 is little endian
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, VifError> {
+    data.get(offset..offset + 4)
+        .map(|value| u32::from_le_bytes(value.try_into().expect("Slice length checked")))
+        .ok_or(VifError::Truncated { offset })
+}
+
+/// Checks the attributes read so far match the positions before the
+/// batch is finished
+fn finish_batch(batch: Ps2Batch, offset: usize, out: &mut Vec<Ps2Batch>) -> Result<(), VifError> {
+    let positions = batch.positions.len();
+    for (attribute, count) in [("uvs", batch.uvs.len()), ("colors", batch.colors.len())] {
+        if count != 0 && count != positions {
+            return Err(VifError::AttributeCount {
+                offset,
+                attribute,
+                positions,
+                count,
+            });
+        }
+    }
+
+    if positions > 0 {
+        out.push(batch);
+    }
+    Ok(())
+}
+
+/// Decodes the vertices of a VIF packet into the batch of each
+/// microprogram call. Attributes are identified by their UNPACK format:
+///
+/// * V4-32 positions with the ADC flag in W, or V3-32 positions
+/// * V2-16 texture coordinates in 4.12 fixed point
+/// * V4-8 colors where 0x80 is full intensity
+///
+/// Other formats are skipped. Mesh packets always write with the cycle
+/// length equal to the write length so each UNPACK reads NUM vectors
+pub fn decode_vif_packet(data: &[u8]) -> Result<Vec<Ps2Batch>, VifError> {
+    let mut out = Vec::new();
+    let mut batch = Ps2Batch::default();
+    let mut offset = 0;
+
+    while offset + 4 <= data.len() {
+        let code = read_u32(data, offset)?;
+        let immediate = code & 0xFFFF;
+        let num = (code >> 16) & 0xFF;
+        // Bit 7 requests an interrupt and doesn't change the command
+        let command = ((code >> 24) & 0x7F) as u8;
+        let start = offset;
+        offset += 4;
+
+        let length = match command {
+            // NOP, STCYCL, OFFSET, BASE, ITOP, STMOD, MSKPATH3, MARK and the flushes
+            0x00..=0x07 | 0x10 | 0x11 | 0x13 => 0,
+            // MSCAL, MSCALF and MSCNT run the microprogram on the vertices
+            0x14 | 0x15 | 0x17 => {
+                finish_batch(std::mem::take(&mut batch), start, &mut out)?;
+                0
+            }
+            // STMASK
+            0x20 => 4,
+            // STROW and STCOL
+            0x30 | 0x31 => 16,
+            // MPG, NUM of 0 uploads 256 instructions
+            0x4A => match num {
+                0 => 256 * 8,
+                num => num as usize * 8,
+            },
+            // DIRECT and DIRECTHL, IMMEDIATE of 0 sends 65536 quadwords
+            0x50 | 0x51 => match immediate {
+                0 => 65536 * 16,
+                immediate => immediate as usize * 16,
+            },
+            0x60..=0x7F => {
+                let count = match num {
+                    0 => 256,
+                    num => num as usize,
+                };
+                let components = ((command >> 2) & 0x3) as usize + 1;
+                let bits = match command & 0x3 {
+                    // V4-5 packs a whole vector into 16 bits
+                    0x3 => 16 / components,
+                    value => 32 >> value,
+                };
+                let length = (count * components * bits).div_ceil(32) * 4;
+                let values = data
+                    .get(offset..offset + length)
+                    .ok_or(VifError::Truncated { offset: start })?;
+                let unsigned = immediate & 0x4000 != 0;
+                unpack_values(&mut batch, command & 0xF, unsigned, values);
+                length
+            }
+            command => {
+                return Err(VifError::UnknownCommand {
+                    command,
+                    offset: start,
+                })
+            }
+        };
+
+        if offset + length > data.len() {
+            return Err(VifError::Truncated { offset: start });
+        }
+        offset += length;
+    }
+
+    // Vertices uploaded without a microprogram call are never drawn
+    Ok(out)
+}
+
+/// Adds the values of an UNPACK to the attribute its format holds
+fn unpack_values(batch: &mut Ps2Batch, format: u8, unsigned: bool, values: &[u8]) {
+    let words = || {
+        values
+            .chunks_exact(4)
+            .map(|value| u32::from_le_bytes(value.try_into().expect("Chunk length checked")))
+    };
+
+    match format {
+        // V4-32
+        0xC => {
+            let words: Vec<u32> = words().collect();
+            for vector in words.chunks_exact(4) {
+                batch
+                    .positions
+                    .push([vector[0], vector[1], vector[2]].map(f32::from_bits));
+                batch.skip_draw.push(vector[3] & ADC_FLAG != 0);
+            }
+        }
+        // V3-32
+        0x8 => {
+            let words: Vec<u32> = words().collect();
+            for vector in words.chunks_exact(3) {
+                batch
+                    .positions
+                    .push([vector[0], vector[1], vector[2]].map(f32::from_bits));
+                batch.skip_draw.push(false);
+            }
+        }
+        // V2-16
+        0x5 => {
+            for vector in values.chunks_exact(4) {
+                let [u, v] = [0, 2].map(|index| {
+                    let value = [vector[index], vector[index + 1]];
+                    match unsigned {
+                        true => u16::from_le_bytes(value) as f32,
+                        false => i16::from_le_bytes(value) as f32,
+                    }
+                });
+                batch.uvs.push([u * UV_SCALE, v * UV_SCALE]);
+            }
+        }
+        // V4-8
+        0xE => {
+            for vector in values.chunks_exact(4) {
+                batch
+                    .colors
+                    .push([0, 1, 2, 3].map(|index| vector[index] as f32 * COLOR_SCALE));
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_vif_packet, VifError, ADC_FLAG};
+
+    /// Builds a packet uploading a V4-32 strip with texture coordinates
+    /// followed by an MSCAL
+    fn strip_packet(positions: &[[f32; 3]], skip_draw: &[bool]) -> Vec<u8> {
+        let mut data = Vec::new();
+        // STCYCL 1,1
+        data.extend(0x0100_0101u32.to_le_bytes());
+
+        let count = positions.len() as u32;
+        data.extend((0x6C00_0000 | (count << 16)).to_le_bytes());
+        for (position, skip) in positions.iter().zip(skip_draw) {
+            for value in position {
+                data.extend(value.to_bits().to_le_bytes());
+            }
+            let w = if *skip { ADC_FLAG } else { 0 };
+            data.extend(w.to_le_bytes());
+        }
+
+        data.extend((0x6500_0000 | (count << 16)).to_le_bytes());
+        for _ in positions {
+            data.extend(4096i16.to_le_bytes());
+            data.extend((-2048i16).to_le_bytes());
+        }
+
+        // MSCAL 0
+        data.extend(0x1400_0000u32.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_decode_strip() {
+        let positions = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [5.0, 5.0, 5.0],
+            [6.0, 5.0, 5.0],
+            [5.0, 6.0, 5.0],
+        ];
+        // The last three vertices start a new strip
+        let skip_draw = [true, true, false, false, true, true, false];
+        let batches = decode_vif_packet(&strip_packet(&positions, &skip_draw)).unwrap();
+
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.positions, positions.to_vec());
+        assert_eq!(batch.uvs[0], [1.0, -0.5]);
+        assert!(batch.colors.is_empty());
+        assert_eq!(batch.triangles(), vec![[0, 1, 2], [2, 1, 3], [4, 5, 6]]);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let mut data = strip_packet(&[[0.0; 3]; 3], &[false; 3]);
+        data.truncate(20);
+        assert_eq!(
+            decode_vif_packet(&data),
+            Err(VifError::Truncated { offset: 4 })
+        );
+
+        assert_eq!(
+            decode_vif_packet(&0x0900_0000u32.to_le_bytes()),
+            Err(VifError::UnknownCommand {
+                command: 0x09,
+                offset: 0,
+            })
+        );
+
+        // Vertices without a microprogram call aren't drawn
+        let mut data = strip_packet(&[[0.0; 3]; 3], &[false; 3]);
+        data.truncate(data.len() - 4);
+        assert_eq!(decode_vif_packet(&data), Ok(Vec::new()));
+    }
+}
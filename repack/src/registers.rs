@@ -0,0 +1,163 @@
+//! Shader register arrays of the materials. The length and meaning of the
+//! registers depend on the light and surface shaders of a material and come
+//! from the shader tables of the engine rather than the file, so they're
+//! described by a [RegisterTable] loaded alongside the meshes. Sections in
+//! the table file are named by kind and shader index (e.g. `[surface.3]`)
+//! and list the kind of each register in order:
+//!
+//! ```ini
+//! [surface.3]
+//! registers=constant,constant,tex_layer
+//! ```
+
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use openglitch_formats::types::PtrOffset;
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RegisterTableError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Parse(#[from] serde_ini::de::Error),
+    #[error("invalid register table section {0:?}")]
+    InvalidSection(String),
+    #[error("unknown register kind {0:?}")]
+    UnknownKind(String),
+}
+
+/// How the word stored in a register is used by the shader
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterKind {
+    /// Integer value such as a count or flags
+    Value,
+    /// Floating point shader constant
+    Constant,
+    /// Offset of the texture layer the shader samples
+    TexLayer,
+}
+
+impl FromStr for RegisterKind {
+    type Err = RegisterTableError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "value" => Ok(RegisterKind::Value),
+            "constant" => Ok(RegisterKind::Constant),
+            "tex_layer" => Ok(RegisterKind::TexLayer),
+            value => Err(RegisterTableError::UnknownKind(value.to_string())),
+        }
+    }
+}
+
+/// Register read from a material using the kind described by the table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShaderRegister {
+    Value(u32),
+    Constant(f32),
+    TexLayer(PtrOffset),
+}
+
+impl ShaderRegister {
+    /// Interprets the word stored in a register as the provided kind
+    pub fn from_word(kind: RegisterKind, word: u32) -> Self {
+        match kind {
+            RegisterKind::Value => ShaderRegister::Value(word),
+            RegisterKind::Constant => ShaderRegister::Constant(f32::from_bits(word)),
+            RegisterKind::TexLayer => ShaderRegister::TexLayer(PtrOffset(word)),
+        }
+    }
+}
+
+/// Section of the table file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RegisterSection {
+    registers: String,
+}
+
+/// Table of the registers used by the light and surface shaders
+#[derive(Debug, Default)]
+pub struct RegisterTable {
+    light: HashMap<u8, Vec<RegisterKind>>,
+    surface: HashMap<u16, Vec<RegisterKind>>,
+}
+
+impl RegisterTable {
+    pub fn load(path: &Path) -> Result<RegisterTable, RegisterTableError> {
+        let value = std::fs::read_to_string(path)?;
+        Self::from_ini(&value)
+    }
+
+    pub fn from_ini(value: &str) -> Result<RegisterTable, RegisterTableError> {
+        let sections: HashMap<String, RegisterSection> = serde_ini::from_str(value)?;
+        let mut table = RegisterTable::default();
+
+        for (section, value) in sections {
+            let registers = value
+                .registers
+                .split(',')
+                .filter(|kind| !kind.trim().is_empty())
+                .map(RegisterKind::from_str)
+                .collect::<Result<Vec<_>, _>>()?;
+
+            match section.split_once('.') {
+                Some(("light", index)) => {
+                    let index = index
+                        .parse()
+                        .map_err(|_| RegisterTableError::InvalidSection(section.clone()))?;
+                    table.light.insert(index, registers);
+                }
+                Some(("surface", index)) => {
+                    let index = index
+                        .parse()
+                        .map_err(|_| RegisterTableError::InvalidSection(section.clone()))?;
+                    table.surface.insert(index, registers);
+                }
+                _ => return Err(RegisterTableError::InvalidSection(section)),
+            }
+        }
+
+        Ok(table)
+    }
+
+    /// Registers used by the light shader, None for shaders missing from the table
+    pub fn light(&self, index: u8) -> Option<&[RegisterKind]> {
+        self.light.get(&index).map(Vec::as_slice)
+    }
+
+    /// Registers used by the surface shader, None for shaders missing from the table
+    pub fn surface(&self, index: u16) -> Option<&[RegisterKind]> {
+        self.surface.get(&index).map(Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RegisterKind, RegisterTable, RegisterTableError};
+
+    #[test]
+    fn test_from_ini() {
+        let table = RegisterTable::from_ini(
+            "[light.1]\nregisters=value\n[surface.3]\nregisters=constant, tex_layer\n",
+        )
+        .unwrap();
+        assert_eq!(table.light(1), Some(&[RegisterKind::Value][..]));
+        assert_eq!(
+            table.surface(3),
+            Some(&[RegisterKind::Constant, RegisterKind::TexLayer][..])
+        );
+        assert_eq!(table.light(3), None);
+
+        assert!(matches!(
+            RegisterTable::from_ini("[specular.1]\nregisters=value\n"),
+            Err(RegisterTableError::InvalidSection(_))
+        ));
+        assert!(matches!(
+            RegisterTable::from_ini("[light.1]\nregisters=texture\n"),
+            Err(RegisterTableError::UnknownKind(_))
+        ));
+    }
+}
@@ -0,0 +1,351 @@
+//! Statistics and integrity checks of a loaded mesh. The report walks every
+//! cluster of every material counting what the mesh draws at each LOD and
+//! collects the problems that make a mesh draw incorrectly, the report can
+//! be written as JSON for use by other tools
+
+use std::{
+    collections::BTreeSet,
+    fmt::Display,
+    io::{self, Write},
+};
+
+use serde::Serialize;
+
+use openglitch_formats::mesh::FDATA_MAX_LOD_MESH_COUNT;
+
+use crate::{
+    export::{list_triangles, strip_triangles},
+    offsets::ValidationError,
+    view::MeshView,
+};
+
+/// Problem found while walking the mesh
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReportIssue {
+    /// Material with no clusters drawing it
+    OrphanedMaterial { material: usize },
+    /// Cluster drawing from a vertex or index buffer that doesn't exist
+    MissingBuffer {
+        material: usize,
+        cluster: usize,
+        buffer: &'static str,
+        index: u8,
+    },
+    /// Cluster reading indices past the end of its index buffer
+    IndexRangeOutOfBounds {
+        material: usize,
+        cluster: usize,
+        start: usize,
+        end: usize,
+        length: usize,
+    },
+    /// Triangles referencing vertices past the end of the vertex buffer
+    IndexOutOfRange {
+        material: usize,
+        cluster: usize,
+        count: usize,
+        vertex_count: usize,
+    },
+    /// Triangles with repeated vertices or no area
+    DegenerateTriangles {
+        material: usize,
+        cluster: usize,
+        count: usize,
+    },
+    /// Cluster with an LOD ID past the LODs of the mesh
+    InvalidLod {
+        material: usize,
+        cluster: usize,
+        lod_id: u8,
+    },
+}
+
+impl Display for ReportIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportIssue::OrphanedMaterial { material } => {
+                write!(f, "material {} isn't drawn by any cluster", material)
+            }
+            ReportIssue::MissingBuffer {
+                material,
+                cluster,
+                buffer,
+                index,
+            } => write!(
+                f,
+                "material {} cluster {} uses missing {} buffer {}",
+                material, cluster, buffer, index
+            ),
+            ReportIssue::IndexRangeOutOfBounds {
+                material,
+                cluster,
+                start,
+                end,
+                length,
+            } => write!(
+                f,
+                "material {} cluster {} reads indices {}..{} past the {} index buffer",
+                material, cluster, start, end, length
+            ),
+            ReportIssue::IndexOutOfRange {
+                material,
+                cluster,
+                count,
+                vertex_count,
+            } => write!(
+                f,
+                "material {} cluster {} has {} triangles indexing past {} vertices",
+                material, cluster, count, vertex_count
+            ),
+            ReportIssue::DegenerateTriangles {
+                material,
+                cluster,
+                count,
+            } => write!(
+                f,
+                "material {} cluster {} has {} degenerate triangles",
+                material, cluster, count
+            ),
+            ReportIssue::InvalidLod {
+                material,
+                cluster,
+                lod_id,
+            } => write!(
+                f,
+                "material {} cluster {} uses LOD {} which the mesh doesn't have",
+                material, cluster, lod_id
+            ),
+        }
+    }
+}
+
+/// Statistics of a mesh along with the problems found in it
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MeshReport {
+    pub name: String,
+    pub vertex_buffers: usize,
+    /// Vertices across every vertex buffer
+    pub vertices: usize,
+    /// Triangles drawn at each LOD, degenerate triangles aren't counted
+    pub triangles_per_lod: Vec<usize>,
+    pub materials: usize,
+    pub bones: usize,
+    /// Names of the textures used by the materials
+    pub textures: BTreeSet<String>,
+    pub issues: Vec<ReportIssue>,
+}
+
+/// Whether the triangle has no area
+fn is_zero_area(positions: &[[f32; 3]], [a, b, c]: [u32; 3]) -> bool {
+    let [a, b, c] = [a, b, c].map(|index| positions[index as usize]);
+    let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let cross = [
+        ab[1] * ac[2] - ab[2] * ac[1],
+        ab[2] * ac[0] - ab[0] * ac[2],
+        ab[0] * ac[1] - ab[1] * ac[0],
+    ];
+    cross == [0.0; 3]
+}
+
+impl MeshReport {
+    /// Walks the mesh building the report, only meshes with DirectX data
+    /// have clusters to count so other meshes report no geometry
+    pub fn from_view(mesh: &MeshView) -> Result<Self, ValidationError> {
+        let materials = mesh.materials()?;
+        let lod_count = (mesh.header().lod_count as usize).clamp(1, FDATA_MAX_LOD_MESH_COUNT);
+
+        let mut report = MeshReport {
+            name: mesh.name(),
+            triangles_per_lod: vec![0; lod_count],
+            materials: materials.map(|array| array.len()).unwrap_or_default(),
+            bones: mesh.bones()?.map(|array| array.len()).unwrap_or_default(),
+            ..Default::default()
+        };
+
+        let Some(dx_mesh) = mesh.dx_mesh()? else {
+            return Ok(report);
+        };
+
+        let mut positions: Vec<Vec<[f32; 3]>> = Vec::new();
+        for buffer in dx_mesh
+            .vertex_buffers()?
+            .iter()
+            .flat_map(|array| array.iter())
+        {
+            positions.push(
+                buffer
+                    .positions()?
+                    .iter()
+                    .flat_map(|array| array.iter())
+                    .collect(),
+            );
+        }
+        report.vertex_buffers = positions.len();
+        report.vertices = positions.iter().map(Vec::len).sum();
+
+        for (material, value) in materials.iter().flat_map(|array| array.iter()).enumerate() {
+            report.textures.extend(mesh.material_textures(&value)?);
+
+            let clusters = match value.dx_material()? {
+                Some(platform) => platform.clusters()?,
+                None => None,
+            };
+            let Some(clusters) = clusters.filter(|array| !array.is_empty()) else {
+                report
+                    .issues
+                    .push(ReportIssue::OrphanedMaterial { material });
+                continue;
+            };
+
+            for (index, cluster) in clusters.iter().enumerate() {
+                let Some(positions) = positions.get(cluster.vertex_buffer_index() as usize) else {
+                    report.issues.push(ReportIssue::MissingBuffer {
+                        material,
+                        cluster: index,
+                        buffer: "vertex",
+                        index: cluster.vertex_buffer_index(),
+                    });
+                    continue;
+                };
+                let Some(indices) = dx_mesh.index_buffer(cluster.index_buffer_index() as usize)?
+                else {
+                    report.issues.push(ReportIssue::MissingBuffer {
+                        material,
+                        cluster: index,
+                        buffer: "index",
+                        index: cluster.index_buffer_index(),
+                    });
+                    continue;
+                };
+                let indices: Vec<u16> = indices.iter().collect();
+
+                // Triangle list followed by each strip, strips have two
+                // more indices than triangles
+                let (start, count) = cluster.tri_list();
+                let mut ranges = vec![(start, count * 3, false)];
+                for strip in cluster.strips()?.iter().flat_map(|array| array.iter()) {
+                    ranges.push((
+                        strip.start_vindex() as usize,
+                        strip.tri_count() as usize + 2,
+                        true,
+                    ));
+                }
+
+                let mut drawn = 0;
+                let mut degenerate = 0;
+                let mut out_of_range = 0;
+                for (start, count, strip) in ranges {
+                    if count == 0 {
+                        continue;
+                    }
+
+                    let Some(range) = indices.get(start..start + count) else {
+                        report.issues.push(ReportIssue::IndexRangeOutOfBounds {
+                            material,
+                            cluster: index,
+                            start,
+                            end: start + count,
+                            length: indices.len(),
+                        });
+                        continue;
+                    };
+
+                    let triangles = match strip {
+                        // Repeated indices of strips are joins so aren't counted
+                        true => strip_triangles(range),
+                        false => list_triangles(range),
+                    };
+                    for triangle in triangles {
+                        let [a, b, c] = triangle;
+                        if triangle
+                            .iter()
+                            .any(|value| *value as usize >= positions.len())
+                        {
+                            out_of_range += 1;
+                        } else if a == b || b == c || a == c || is_zero_area(positions, triangle) {
+                            degenerate += 1;
+                        } else {
+                            drawn += 1;
+                        }
+                    }
+                }
+
+                match report.triangles_per_lod.get_mut(cluster.lod_id() as usize) {
+                    Some(count) => *count += drawn,
+                    None => report.issues.push(ReportIssue::InvalidLod {
+                        material,
+                        cluster: index,
+                        lod_id: cluster.lod_id(),
+                    }),
+                }
+                if out_of_range > 0 {
+                    report.issues.push(ReportIssue::IndexOutOfRange {
+                        material,
+                        cluster: index,
+                        count: out_of_range,
+                        vertex_count: positions.len(),
+                    });
+                }
+                if degenerate > 0 {
+                    report.issues.push(ReportIssue::DegenerateTriangles {
+                        material,
+                        cluster: index,
+                        count: degenerate,
+                    });
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        serde_json::to_writer_pretty(out, self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use binrw::Endian;
+
+    use crate::{fixture::triangle_mesh, view::MeshView};
+
+    use super::{MeshReport, ReportIssue};
+
+    #[test]
+    fn test_report_fixture() {
+        let bytes = triangle_mesh();
+        let report =
+            MeshReport::from_view(&MeshView::new(&bytes, Endian::Little).unwrap()).unwrap();
+
+        assert_eq!(report.vertices, 3);
+        assert_eq!(report.materials, 1);
+        assert_eq!(report.triangles_per_lod[0], 1);
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains("\"triangles_per_lod\""));
+    }
+
+    #[test]
+    fn test_issue_json() {
+        let issue = ReportIssue::DegenerateTriangles {
+            material: 0,
+            cluster: 1,
+            count: 2,
+        };
+        assert_eq!(
+            serde_json::to_string(&issue).unwrap(),
+            r#"{"kind":"degenerate_triangles","material":0,"cluster":1,"count":2}"#
+        );
+        assert_eq!(
+            issue.to_string(),
+            "material 0 cluster 1 has 2 degenerate triangles"
+        );
+    }
+}
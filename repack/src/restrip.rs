@@ -0,0 +1,281 @@
+//! Rebuilding of the vertex and index arrays of edited world geometry.
+//! Edited or imported triangles are welded back into a shared vertex
+//! array and re-stripped into a single triangle strip per material, the
+//! strips of each material are stored one after another so every
+//! material covers a contiguous range of the index array.
+//!
+//! The vertex weights and all of the UV sets are carried through the
+//! welding, vertices are only merged when every attribute matches exactly
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RestripError {
+    #[error("segment needs {0} vertices, more than a 16 bit index can address")]
+    TooManyVertices(usize),
+    #[error("vertex has {actual} UV sets, expected {expected}")]
+    UvSetMismatch { expected: usize, actual: usize },
+}
+
+/// Vertex of an edited segment
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentVert {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    /// Bone index and weight of each influence
+    pub weights: Vec<(u8, f32)>,
+    /// Texture coordinates of each UV set
+    pub uvs: Vec<[f32; 2]>,
+}
+
+impl SegmentVert {
+    /// Exact bit pattern of the vertex, used to weld identical vertices
+    fn key(&self) -> Vec<u32> {
+        self.position
+            .iter()
+            .chain(&self.normal)
+            .map(|value| value.to_bits())
+            .chain(
+                self.weights
+                    .iter()
+                    .flat_map(|(bone, weight)| [*bone as u32, weight.to_bits()]),
+            )
+            .chain(self.uvs.iter().flatten().map(|value| value.to_bits()))
+            .collect()
+    }
+}
+
+/// Triangle of an edited segment
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentTriangle {
+    pub material: u16,
+    pub vertices: [SegmentVert; 3],
+}
+
+/// Range of the index array used by a material
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaterialRange {
+    pub material: u16,
+    pub start_index: u32,
+    pub index_count: u32,
+}
+
+/// Rebuilt geometry of a segment
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RestrippedSegment {
+    pub verts: Vec<SegmentVert>,
+    /// Triangle strip indices into `verts`
+    pub indices: Vec<u16>,
+    /// Index ranges of each material in ascending material order
+    pub materials: Vec<MaterialRange>,
+}
+
+impl RestrippedSegment {
+    /// Welds and re-strips the triangles of a segment
+    pub fn build(triangles: &[SegmentTriangle]) -> Result<Self, RestripError> {
+        let mut out = RestrippedSegment::default();
+        let mut welded: HashMap<Vec<u32>, u16> = HashMap::new();
+        let uv_sets = triangles
+            .first()
+            .map(|triangle| triangle.vertices[0].uvs.len())
+            .unwrap_or_default();
+
+        let mut groups: Vec<(u16, Vec<[u16; 3]>)> = Vec::new();
+
+        for triangle in triangles {
+            let mut indices = [0u16; 3];
+            for (index, vert) in indices.iter_mut().zip(&triangle.vertices) {
+                if vert.uvs.len() != uv_sets {
+                    return Err(RestripError::UvSetMismatch {
+                        expected: uv_sets,
+                        actual: vert.uvs.len(),
+                    });
+                }
+
+                *index = match welded.get(&vert.key()) {
+                    Some(index) => *index,
+                    None => {
+                        let index = u16::try_from(out.verts.len())
+                            .map_err(|_| RestripError::TooManyVertices(out.verts.len() + 1))?;
+                        welded.insert(vert.key(), index);
+                        out.verts.push(vert.clone());
+                        index
+                    }
+                };
+            }
+
+            match groups
+                .iter_mut()
+                .find(|(material, _)| *material == triangle.material)
+            {
+                Some((_, group)) => group.push(indices),
+                None => groups.push((triangle.material, vec![indices])),
+            }
+        }
+
+        groups.sort_by_key(|(material, _)| *material);
+
+        for (material, group) in groups {
+            let strip = stripify(&group);
+            out.materials.push(MaterialRange {
+                material,
+                start_index: out.indices.len() as u32,
+                index_count: strip.len() as u32,
+            });
+            out.indices.extend(strip);
+        }
+
+        Ok(out)
+    }
+}
+
+/// Converts a triangle list into a single triangle strip. Triangles are
+/// greedily chained across shared edges and separate chains are joined
+/// with degenerate triangles, every other triangle of a strip has
+/// reversed winding which is accounted for when chaining
+pub fn stripify(triangles: &[[u16; 3]]) -> Vec<u16> {
+    // Triangles each directed edge starts, along with the remaining vertex
+    let mut edges: HashMap<(u16, u16), Vec<(usize, u16)>> = HashMap::new();
+    for (index, [a, b, c]) in triangles.iter().copied().enumerate() {
+        edges.entry((a, b)).or_default().push((index, c));
+        edges.entry((b, c)).or_default().push((index, a));
+        edges.entry((c, a)).or_default().push((index, b));
+    }
+
+    let mut used = vec![false; triangles.len()];
+    let mut out: Vec<u16> = Vec::new();
+
+    for (start, triangle) in triangles.iter().enumerate() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+
+        let mut strip = triangle.to_vec();
+        loop {
+            let last = strip.len() - 1;
+            // Even triangles are wound from the second last vertex, odd
+            // triangles from the last
+            let edge = match (strip.len() - 2) % 2 {
+                0 => (strip[last - 1], strip[last]),
+                _ => (strip[last], strip[last - 1]),
+            };
+
+            let next = edges
+                .get(&edge)
+                .and_then(|candidates| candidates.iter().find(|(index, _)| !used[*index]).copied());
+
+            let Some((index, vertex)) = next else {
+                break;
+            };
+            used[index] = true;
+            strip.push(vertex);
+        }
+
+        if let (Some(last), Some(first)) = (out.last().copied(), strip.first().copied()) {
+            // Joining triangles are degenerate, the new strip has to start
+            // on an even triangle to keep its winding
+            out.push(last);
+            if out.len() % 2 == 0 {
+                out.push(last);
+            }
+            out.push(first);
+        }
+        out.extend(strip);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use crate::export::strip_triangles;
+
+    use super::{stripify, RestripError, RestrippedSegment, SegmentTriangle, SegmentVert};
+
+    fn vert(x: f32, y: f32) -> SegmentVert {
+        SegmentVert {
+            position: [x, y, 0.0],
+            normal: [0.0, 0.0, 1.0],
+            weights: vec![(0, 1.0)],
+            uvs: vec![[x, y], [x * 0.5, y * 0.5]],
+        }
+    }
+
+    /// Rotates a triangle so its smallest index is first, keeping its winding
+    fn canonical([a, b, c]: [u32; 3]) -> [u32; 3] {
+        let min = a.min(b).min(c);
+        if min == a {
+            [a, b, c]
+        } else if min == b {
+            [b, c, a]
+        } else {
+            [c, a, b]
+        }
+    }
+
+    fn round_trip(triangles: &[[u16; 3]]) {
+        let strip = stripify(triangles);
+        let mut decoded: Vec<_> = strip_triangles(&strip).into_iter().map(canonical).collect();
+        let mut expected: Vec<_> = triangles
+            .iter()
+            .map(|[a, b, c]| canonical([*a as u32, *b as u32, *c as u32]))
+            .collect();
+        decoded.sort();
+        expected.sort();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn test_stripify() {
+        // Quad shares an edge so it becomes a single strip
+        assert_eq!(stripify(&[[0, 1, 2], [2, 1, 3]]).len(), 4);
+        round_trip(&[[0, 1, 2], [2, 1, 3]]);
+
+        // Disconnected triangles are joined with degenerates
+        round_trip(&[[0, 1, 2], [3, 4, 5], [6, 7, 8]]);
+        round_trip(&[[0, 1, 2], [2, 1, 3], [2, 3, 4], [5, 6, 7]]);
+    }
+
+    #[test]
+    fn test_restrip_segment() {
+        let triangles = vec![
+            SegmentTriangle {
+                material: 1,
+                vertices: [vert(0.0, 0.0), vert(1.0, 0.0), vert(0.0, 1.0)],
+            },
+            SegmentTriangle {
+                material: 0,
+                vertices: [vert(0.0, 1.0), vert(1.0, 0.0), vert(1.0, 1.0)],
+            },
+            SegmentTriangle {
+                material: 1,
+                vertices: [vert(5.0, 5.0), vert(6.0, 5.0), vert(5.0, 6.0)],
+            },
+        ];
+
+        let segment = RestrippedSegment::build(&triangles).unwrap();
+        // Shared corners are welded
+        assert_eq!(segment.verts.len(), 7);
+        assert_eq!(segment.verts[6].uvs[1], [2.5, 3.0]);
+
+        assert_eq!(segment.materials[0].material, 0);
+        assert_eq!(segment.materials[0].index_count, 3);
+        assert_eq!(segment.materials[1].start_index, 3);
+        let range = segment.materials[1];
+        let indices = &segment.indices[range.start_index as usize..][..range.index_count as usize];
+        assert_eq!(strip_triangles(indices).len(), 2);
+
+        let mut triangles = triangles;
+        triangles[2].vertices[1].uvs.pop();
+        assert!(matches!(
+            RestrippedSegment::build(&triangles),
+            Err(RestripError::UvSetMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+    }
+}
@@ -0,0 +1,202 @@
+//! Heuristics detecting values that were byte-swapped the wrong number of
+//! times, a file passing through two swapping layers ends up with values
+//! that are implausible but become plausible when swapped once more
+
+use std::fmt::Display;
+
+use crate::st::{FMesh, SourceEndian};
+
+/// Largest plausible world space size of a mesh
+const MAX_PLAUSIBLE_SIZE: f32 = 1.0e6;
+/// Largest plausible LOD switch distance
+const MAX_PLAUSIBLE_DISTANCE: f32 = 1.0e7;
+
+/// Stage of loading that produced an implausible value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixupStage {
+    /// Value was already swapped in the source data, before any fixup
+    Source,
+    /// Value was swapped by the byte-swap fixup
+    ByteSwap,
+}
+
+impl FixupStage {
+    /// Stage responsible for the current values of a mesh loaded from the
+    /// source, fixup only swaps bytes when the source order isn't the host's
+    pub fn for_source(endian: SourceEndian) -> FixupStage {
+        match endian.needs_swap() {
+            true => FixupStage::ByteSwap,
+            false => FixupStage::Source,
+        }
+    }
+}
+
+/// Warning about an implausible value that looks byte-swapped
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanityWarning {
+    /// Stage the value most likely became swapped
+    pub stage: FixupStage,
+    /// Name of the field holding the value
+    pub field: String,
+    /// Value after fixup
+    pub value: f32,
+    /// Value when swapped once more
+    pub swapped: f32,
+}
+
+impl Display for SanityWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} has implausible value {:e} ({:?} stage), swapping gives {}",
+            self.field, self.value, self.stage, self.swapped
+        )
+    }
+}
+
+/// Checks if the value is within the plausible range, subnormal values
+/// are never produced by the tools and are the usual sign of a bad swap
+fn is_plausible(value: f32, min: f32, max: f32) -> bool {
+    (value == 0.0 || value.is_normal()) && value >= min && value <= max
+}
+
+fn check_float(
+    out: &mut Vec<SanityWarning>,
+    stage: FixupStage,
+    field: impl Into<String>,
+    value: f32,
+    min: f32,
+    max: f32,
+) {
+    if is_plausible(value, min, max) {
+        return;
+    }
+
+    let swapped = f32::from_bits(value.to_bits().swap_bytes());
+    if is_plausible(swapped, min, max) {
+        out.push(SanityWarning {
+            stage,
+            field: field.into(),
+            value,
+            swapped,
+        });
+    }
+}
+
+/// Checks the values of a mesh after fixup
+pub fn check_mesh(mesh: &FMesh, stage: FixupStage) -> Vec<SanityWarning> {
+    let position = &mesh.bound_sphere.position;
+    check_values(
+        stage,
+        mesh.bound_sphere.radius,
+        [position.x, position.y, position.z],
+        mesh.lod_distances(),
+    )
+}
+
+/// Checks the values of a header read through the views, the values are
+/// swapped from the byte order of the platform the same way fixup swaps them
+pub fn check_header(
+    header: &openglitch_formats::mesh::FMesh,
+    stage: FixupStage,
+) -> Vec<SanityWarning> {
+    let position = &header.bound_sphere.position;
+    check_values(
+        stage,
+        header.bound_sphere.radius,
+        [position.x, position.y, position.z],
+        header.lod_distances(),
+    )
+}
+
+fn check_values(
+    stage: FixupStage,
+    radius: f32,
+    position: [f32; 3],
+    lod_distances: &[f32],
+) -> Vec<SanityWarning> {
+    let mut out = Vec::new();
+
+    check_float(
+        &mut out,
+        stage,
+        "bound_sphere.radius",
+        radius,
+        0.0,
+        MAX_PLAUSIBLE_SIZE,
+    );
+
+    for (axis, value) in ["x", "y", "z"].into_iter().zip(position) {
+        check_float(
+            &mut out,
+            stage,
+            format!("bound_sphere.position.{axis}"),
+            value,
+            -MAX_PLAUSIBLE_SIZE,
+            MAX_PLAUSIBLE_SIZE,
+        );
+    }
+
+    for (index, value) in lod_distances.iter().enumerate() {
+        check_float(
+            &mut out,
+            stage,
+            format!("lod_distance[{index}]"),
+            *value,
+            0.0,
+            MAX_PLAUSIBLE_DISTANCE,
+        );
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use binrw::Endian;
+
+    use crate::{
+        fixture::triangle_mesh,
+        st::SourceEndian,
+        view::{layout, MeshView},
+    };
+
+    use super::{check_header, FixupStage};
+
+    #[test]
+    fn test_fixture_is_plausible() {
+        let bytes = triangle_mesh();
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        assert!(
+            check_header(mesh.header(), FixupStage::for_source(SourceEndian::Little)).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_detect_double_swap() {
+        let mut bytes = triangle_mesh();
+        let offset = layout::MESH_LOD_DISTANCE;
+        bytes[offset..offset + 4].reverse();
+
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        let warnings = check_header(mesh.header(), FixupStage::for_source(SourceEndian::Little));
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].field, "lod_distance[0]");
+        assert_eq!(warnings[0].swapped, 100.0);
+    }
+
+    /// Loading in place runs the same checks on the fixed up mesh
+    #[test]
+    #[cfg(target_pointer_width = "32")]
+    fn test_in_place_is_plausible() {
+        use crate::st::{load_memory_struct, FMesh};
+
+        use super::check_mesh;
+
+        let mesh = unsafe {
+            load_memory_struct::<FMesh>(triangle_mesh().into_boxed_slice(), SourceEndian::Little)
+        };
+        assert!(check_mesh(&mesh, FixupStage::for_source(SourceEndian::Little)).is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+//! Golden file tests of the mesh parsers. The in place structures, the
+//! offset views and the survey each read the synthetic fixture meshes into a
+//! [MeshSnapshot] which is compared against the JSON snapshots checked in
+//! under `snapshots/`, so a change to any one of the parsers that alters what
+//! it reads is caught. Snapshots are rewritten by running the tests with
+//! `UPDATE_SNAPSHOTS=1`
+
+use std::{fs, path::PathBuf};
+
+use serde::Serialize;
+
+use crate::{offsets::ValidationError, st::FMesh, survey::MeshSurvey, view::MeshView};
+
+/// Details of a mesh that every parser reads
+#[derive(Debug, Serialize)]
+pub struct MeshSnapshot {
+    pub name: String,
+    /// Radius and center of the sphere bounding the mesh
+    pub bound_sphere: [f32; 4],
+    pub flags: u16,
+    pub lod_distances: Vec<f32>,
+    pub bones: Vec<BoneSnapshot>,
+    pub materials: Vec<MaterialSnapshot>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BoneSnapshot {
+    pub name: String,
+    pub parent_index: Option<u8>,
+    pub part_id: u8,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MaterialSnapshot {
+    pub part_id_mask: u32,
+    pub lod_mask: u8,
+    pub flags: u16,
+    pub tint: [f32; 3],
+    pub tex_layer_id_index: [u8; 4],
+}
+
+impl MeshSnapshot {
+    /// Snapshot of a mesh loaded in place
+    pub fn from_mesh(mesh: &FMesh) -> Self {
+        let sphere = mesh.bound_sphere;
+        Self {
+            name: mesh.name.as_string(),
+            bound_sphere: [
+                sphere.radius,
+                sphere.position.x,
+                sphere.position.y,
+                sphere.position.z,
+            ],
+            flags: mesh.flags,
+            lod_distances: mesh.lod_distances().to_vec(),
+            bones: mesh
+                .bones()
+                .unwrap_or_default()
+                .iter()
+                .map(|bone| BoneSnapshot {
+                    name: bone.name.as_string(),
+                    parent_index: match bone.skeleton.parent_bone_index {
+                        255 => None,
+                        value => Some(value),
+                    },
+                    part_id: bone.part_id,
+                })
+                .collect(),
+            materials: mesh
+                .materials()
+                .unwrap_or_default()
+                .iter()
+                .map(|material| MaterialSnapshot {
+                    part_id_mask: material.part_id_mask,
+                    lod_mask: material.lod_mask,
+                    flags: material.mtl_flags,
+                    tint: [
+                        material.material_tint.red,
+                        material.material_tint.green,
+                        material.material_tint.blue,
+                    ],
+                    tex_layer_id_index: material.tex_layer_id_index,
+                })
+                .collect(),
+        }
+    }
+
+    /// Snapshot of a mesh read through the offset views
+    pub fn from_view(mesh: &MeshView) -> Result<Self, ValidationError> {
+        let sphere = mesh.bound_sphere();
+        let bones = mesh.bones()?;
+        let materials = mesh.materials()?;
+
+        Ok(Self {
+            name: mesh.name(),
+            bound_sphere: [
+                sphere.radius,
+                sphere.position.x,
+                sphere.position.y,
+                sphere.position.z,
+            ],
+            flags: mesh.flags(),
+            lod_distances: mesh.lod_distances(),
+            bones: bones
+                .iter()
+                .flat_map(|bones| bones.iter())
+                .map(|bone| BoneSnapshot {
+                    name: bone.name(),
+                    parent_index: bone.parent_index(),
+                    part_id: bone.part_id(),
+                })
+                .collect(),
+            materials: materials
+                .iter()
+                .flat_map(|materials| materials.iter())
+                .map(|material| MaterialSnapshot {
+                    part_id_mask: material.part_id_mask(),
+                    lod_mask: material.lod_mask(),
+                    flags: material.flags(),
+                    tint: material.tint(),
+                    tex_layer_id_index: material.tex_layer_id_index(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Snapshot of a mesh read by the survey
+    pub fn from_survey(survey: &MeshSurvey) -> Self {
+        Self {
+            name: survey.name.clone(),
+            bound_sphere: survey.bound_sphere,
+            flags: survey.flags,
+            lod_distances: survey.lod_distances.clone(),
+            bones: survey
+                .bones
+                .iter()
+                .map(|bone| BoneSnapshot {
+                    name: bone.name.clone(),
+                    parent_index: bone.parent_index,
+                    part_id: bone.part_id,
+                })
+                .collect(),
+            materials: survey
+                .materials
+                .iter()
+                .map(|material| MaterialSnapshot {
+                    part_id_mask: material.part_id_mask,
+                    lod_mask: material.lod_mask,
+                    flags: material.flags,
+                    tint: material.tint,
+                    tex_layer_id_index: material.tex_layer_id_index,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn snapshot_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("snapshots")
+        .join(format!("{}.json", name))
+}
+
+/// Compares the value against the named snapshot, the values are compared
+/// as JSON so the formatting of the snapshot doesn't matter
+pub fn assert_snapshot<T: Serialize>(name: &str, value: &T) {
+    let path = snapshot_path(name);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        let mut json = serde_json::to_string_pretty(value).expect("snapshot serializes");
+        json.push('\n');
+        fs::write(&path, json).expect("snapshot written");
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "missing snapshot {}: {}, run with UPDATE_SNAPSHOTS=1 to create it",
+            path.display(),
+            err
+        )
+    });
+    let expected: serde_json::Value =
+        serde_json::from_str(&expected).expect("snapshot is valid JSON");
+    let actual = serde_json::to_value(value).expect("snapshot serializes");
+
+    assert_eq!(
+        actual, expected,
+        "{} doesn't match its snapshot, run with UPDATE_SNAPSHOTS=1 to accept the change",
+        name
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use binrw::Endian;
+
+    use crate::{fixture::triangle_file, platform::Platform, survey::MeshSurvey, view::MeshView};
+
+    use super::{assert_snapshot, MeshSnapshot};
+
+    /// The files can only be loaded in place when the host pointers are 32-bit
+    #[test]
+    #[cfg(target_pointer_width = "32")]
+    fn test_in_place_snapshot() {
+        use crate::st::{load_memory_struct, FMesh, SourceEndian};
+
+        for endian in [SourceEndian::Little, SourceEndian::Big] {
+            let buffer = triangle_file(endian.into()).into_boxed_slice();
+            let mesh = unsafe { load_memory_struct::<FMesh>(buffer, endian) };
+            assert_snapshot("triangle_mesh", &MeshSnapshot::from_mesh(&mesh));
+        }
+    }
+
+    #[test]
+    fn test_view_snapshot() {
+        for endian in [Endian::Little, Endian::Big] {
+            let bytes = triangle_file(endian);
+            let mesh = MeshView::new(&bytes, endian).unwrap();
+            assert_snapshot("triangle_mesh", &MeshSnapshot::from_view(&mesh).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_survey_snapshot() {
+        for platform in Platform::ALL {
+            let bytes = triangle_file(platform.endian());
+            let survey = MeshSurvey::from_buffer(platform, &bytes).unwrap();
+            assert!(survey.warnings.is_empty(), "{:?}", survey.warnings);
+            assert_snapshot("triangle_mesh", &MeshSnapshot::from_survey(&survey));
+        }
+    }
+}
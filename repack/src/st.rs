@@ -1,44 +1,169 @@
+use binrw::Endian;
 use bitflags::bitflags;
-use std::ops::{Deref, DerefMut};
+use std::{
+    alloc::{alloc, dealloc, handle_alloc_error, Layout},
+    mem::{align_of, size_of_val},
+    ops::{Deref, DerefMut},
+};
 use swapbytes::SwapBytes;
+use tracing::{trace, trace_span, warn};
 
 use crate::{
+    offsets::{OffsetValidator, ValidationError},
+    platform::Platform,
     raw::dx::{DxMesh, DxMeshMaterial},
+    sanity::{check_mesh, FixupStage, SanityWarning},
     types::FixedString,
 };
 
-const FDATA_MESH_NAME_LENGTH: usize = 16;
-const FDATA_MAX_LOD_MESH_COUNT: usize = 8;
-const FDATA_VW_COUNT_PER_VTX: usize = 4;
-const FDATA_BONE_NAME_LENGTH: usize = 32;
-const FLIGHT_NAME_LENGTH: usize = 16;
-const FLIGHT_TEXTURE_NAME_LENGTH: usize = 16;
-const FDATA_TEXNAME_LENGTH: usize = 16;
+pub const FDATA_MESH_NAME_LENGTH: usize = 16;
+pub const FDATA_MAX_LOD_MESH_COUNT: usize = 8;
+pub const FDATA_VW_COUNT_PER_VTX: usize = 4;
+pub const FDATA_BONE_NAME_LENGTH: usize = 32;
+pub const FLIGHT_NAME_LENGTH: usize = 16;
+pub const FLIGHT_TEXTURE_NAME_LENGTH: usize = 16;
+pub const FDATA_TEXNAME_LENGTH: usize = 16;
+
+/// Alignment of the buffers structures are loaded into, matches the
+/// alignment of [CFMtx43A]
+const BUFFER_ALIGN: usize = 16;
+
+/// Byte order of the file a structure is loaded from, values are swapped
+/// during fixup when it differs from the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceEndian {
+    Little,
+    Big,
+}
+
+impl SourceEndian {
+    /// Byte order of the host
+    pub const NATIVE: SourceEndian = if cfg!(target_endian = "little") {
+        SourceEndian::Little
+    } else {
+        SourceEndian::Big
+    };
+
+    /// Whether values from the source need swapping to be read on the host
+    pub fn needs_swap(&self) -> bool {
+        *self != Self::NATIVE
+    }
+
+    /// Brings a value stored in the source order into the host order
+    pub fn read<T: SwapBytes>(&self, mut value: T) -> T {
+        if self.needs_swap() {
+            value.swap_bytes_mut();
+        }
+        value
+    }
+}
+
+impl From<Endian> for SourceEndian {
+    fn from(value: Endian) -> Self {
+        match value {
+            Endian::Little => SourceEndian::Little,
+            Endian::Big => SourceEndian::Big,
+        }
+    }
+}
+
+impl From<SourceEndian> for Endian {
+    fn from(value: SourceEndian) -> Self {
+        match value {
+            SourceEndian::Little => Endian::Little,
+            SourceEndian::Big => Endian::Big,
+        }
+    }
+}
+
+impl From<Platform> for SourceEndian {
+    fn from(value: Platform) -> Self {
+        value.endian().into()
+    }
+}
 
 /// Load the structure from the provided buffer pointer
-/// and length of the buffer
+/// and length of the buffer, values are swapped into the host order
+/// when the source uses a different byte order
 ///
 /// # Safety
 ///
 /// Safe as long as the input data is not incorrect (Aka its unsafe)
-pub unsafe fn load_memory_struct<T>(buffer: Box<[u8]>) -> SafeBuffer<T>
+pub unsafe fn load_memory_struct<T>(buffer: Box<[u8]>, endian: SourceEndian) -> SafeBuffer<T>
 where
     T: Sized + SwapBytes + Fixable,
 {
-    let ptr: *mut u8 = Box::into_raw(buffer).cast::<u8>();
+    let length = buffer.len();
+
+    // The boxed bytes are only byte aligned, the structures are copied into
+    // an allocation aligned for the matrices they hold. Nested structures
+    // are aligned relative to the start of the file so the copy is aligned
+    // to at least the alignment of the matrices
+    let layout = Layout::from_size_align(length.max(1), align_of::<T>().max(BUFFER_ALIGN))
+        .expect("Buffer layout is valid");
+    let ptr: *mut u8 = alloc(layout);
+    if ptr.is_null() {
+        handle_alloc_error(layout);
+    }
+    std::ptr::copy_nonoverlapping(buffer.as_ptr(), ptr, length);
+    drop(buffer);
 
     let mut buffer = SafeBuffer {
         // Cast the pointer type to the output type
         ptr: ptr.cast::<T>(),
+        length,
+        layout,
     };
 
+    let _span = trace_span!("load", structure = structure_name::<T>(), length, ?endian).entered();
+
     let value_ref = &mut *buffer;
 
-    value_ref.fix(ptr);
+    value_ref.fix(ptr, endian);
+
+    for warning in value_ref.sanity_check(endian) {
+        warn!("Fixup warning: {}", warning);
+    }
 
     buffer
 }
 
+/// Validates the offsets stored in the buffer before loading the structure,
+/// see [load_memory_struct]
+///
+/// # Safety
+///
+/// Offsets to data the structures don't describe the size of are only
+/// checked to start within the buffer
+pub unsafe fn try_load_memory_struct<T>(
+    buffer: Box<[u8]>,
+    endian: SourceEndian,
+) -> Result<SafeBuffer<T>, ValidationError>
+where
+    T: Sized + SwapBytes + Fixable + 'static,
+{
+    let validator = OffsetValidator::new(&buffer, endian);
+    // Offset of the root structure is the start of the buffer which would be
+    // seen as a null offset, so it's checked by length instead
+    let field = std::any::type_name::<T>();
+    let size = std::mem::size_of::<T>();
+    if buffer.len() < size {
+        return Err(validator.error(field, 0));
+    }
+    validator.claim(field, 0, size)?;
+    validator
+        .read(std::ptr::read_unaligned(buffer.as_ptr().cast::<T>()))
+        .validate_offsets(&validator)?;
+
+    Ok(load_memory_struct(buffer, endian))
+}
+
+/// Name of the structure without its module path, used by the parse trace
+fn structure_name<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
 /// Trait implemented by structures that need to fix their
 /// pointer offsets
 pub trait Fixable: SwapBytes {
@@ -46,13 +171,21 @@ pub trait Fixable: SwapBytes {
     ///
     /// This is not safe, it relies on the values present in the compiled game
     /// assets being correct, that is the only assurance of correctness
-    unsafe fn fix(&mut self, ptr: *mut u8) {
-        #[cfg(not(target_endian = "little"))]
-        {
+    unsafe fn fix(&mut self, ptr: *mut u8, endian: SourceEndian) {
+        let _span = trace_span!(
+            "fix",
+            structure = structure_name::<Self>(),
+            offset = (self as *const Self as *const u8 as usize).wrapping_sub(ptr as usize),
+            size = size_of_val(self)
+        )
+        .entered();
+
+        // Pointers are stored in the source order so they're swapped first
+        if endian.needs_swap() {
             self.swap_bytes_mut();
         }
 
-        self.fix_offset(ptr);
+        self.fix_offset(ptr, endian);
     }
 
     /// Fix up the pointers on the structure and fix any
@@ -62,7 +195,24 @@ pub trait Fixable: SwapBytes {
     ///
     /// This is not safe, it relies on the values present in the compiled game
     /// assets being correct, that is the only assurance of correctness
-    unsafe fn fix_offset(&mut self, _ptr: *mut u8) {}
+    unsafe fn fix_offset(&mut self, _ptr: *mut u8, _endian: SourceEndian) {}
+
+    /// Checks the offsets stored in the unfixed structure, along with
+    /// the structures they point to, are within the buffer. The structure
+    /// is a copy already swapped into the host order
+    ///
+    /// # Safety
+    ///
+    /// The structure must be within the buffer of the validator
+    unsafe fn validate_offsets(&self, _validator: &OffsetValidator) -> Result<(), ValidationError> {
+        Ok(())
+    }
+
+    /// Checks for implausible values left after fixup that suggest
+    /// the data was byte-swapped the wrong number of times
+    fn sanity_check(&self, _endian: SourceEndian) -> Vec<SanityWarning> {
+        Vec::new()
+    }
 }
 
 /// Pointers stored in the memory structure are indexed from
@@ -135,7 +285,7 @@ where
 ///
 /// This is not safe, it relies on the values present in the compiled game
 /// assets being correct, that is the only assurance of correctness
-pub unsafe fn try_fix<T>(value: &mut *mut T, ptr: *mut u8)
+pub unsafe fn try_fix<T>(value: &mut *mut T, ptr: *mut u8, endian: SourceEndian)
 where
     T: Fixable,
 {
@@ -144,7 +294,7 @@ where
 
     // Try fix the value on the other side of the pointer
     if let Some(value) = unsafe { value.as_mut() } {
-        value.fix(ptr)
+        value.fix(ptr, endian)
     }
 }
 
@@ -155,7 +305,7 @@ where
 ///
 /// This is not safe, it relies on the values present in the compiled game
 /// assets being correct, that is the only assurance of correctness
-pub unsafe fn try_fix_array<T, L>(value: &mut *mut T, length: L, ptr: *mut u8)
+pub unsafe fn try_fix_array<T, L>(value: &mut *mut T, length: L, ptr: *mut u8, endian: SourceEndian)
 where
     T: Fixable,
     L: Into<usize> + Copy,
@@ -166,20 +316,29 @@ where
 
     // Try fix the elements
     if let Some(array) = array_ptr_mut(*value, length) {
-        println!("Fixing array {} {}", ptr as usize, *value as usize);
-        array.iter_mut().for_each(|value| value.fix(ptr));
+        trace!(
+            offset = (*value as usize).wrapping_sub(ptr as usize),
+            length = array.len(),
+            "fixing array"
+        );
+        array.iter_mut().for_each(|value| value.fix(ptr, endian));
     }
 }
 
 /// Attempts to fix the offsets of all the values in an array at `value` of
-/// the provided `length` if the `value` pointer is not null
+/// the provided `length` if the `value` pointer is not null, the pointers in
+/// the array are swapped into the host order before they're fixed
 ///
 /// # Safety
 ///
 /// This is not safe, it relies on the values present in the compiled game
 /// assets being correct, that is the only assurance of correctness
-pub unsafe fn try_ptr_fix_array<T, L>(value: &mut *mut *mut T, length: L, ptr: *mut u8)
-where
+pub unsafe fn try_ptr_fix_array<T, L>(
+    value: &mut *mut *mut T,
+    length: L,
+    ptr: *mut u8,
+    endian: SourceEndian,
+) where
     T: Fixable,
     L: Into<usize> + Copy,
     T: 'static,
@@ -190,7 +349,8 @@ where
     // Try fix the elements
     if let Some(array) = array_ptr_mut(*value, length) {
         array.iter_mut().for_each(|value| {
-            try_fix(value, ptr);
+            *value = endian.read(*value);
+            try_fix(value, ptr, endian);
         });
     }
 }
@@ -287,73 +447,137 @@ pub struct FMesh {
     /// The index into the FMeshBone_t array of the root bone (255 if this mesh has no bones)
     pub root_bone_index: i8,
     /// Number of bones in this model (0 if none)
-    bone_count: u8,
+    pub(crate) bone_count: u8,
     /// Number of segments in this object
-    segment_count: u8,
+    pub(crate) segment_count: u8,
     /// Number of entries in pTexLayerIDArray
-    tex_layer_id_count: u8,
+    pub(crate) tex_layer_id_count: u8,
 
     /// Number of entries in pTexLayerIDArray that have their FMESH_TEXLAYERIDFLAG_USE_ST_INFO flag set
-    _tex_layer_id_count_st: u8,
+    pub(crate) _tex_layer_id_count_st: u8,
     /// Number of entries in pTexLayerIDArray that have their FMESH_TEXLAYERIDFLAG_USE_FLIP_INFO flag set
-    _tex_layer_id_count_flip: u8,
+    pub(crate) _tex_layer_id_count_flip: u8,
 
     /// Number of lights attached to this mesh
-    light_count: u8,
+    pub(crate) light_count: u8,
     /// Number of materials in the material array (aMtl)
-    material_count: u8,
+    pub(crate) material_count: u8,
     /// Number of elements in the collision tree array
-    coll_tree_count: u8,
+    pub(crate) coll_tree_count: u8,
     /// Number of LOD meshes for this object
-    lod_count: u8,
+    pub(crate) lod_count: u8,
     /// Bias added to the current LOD for generating shadows
     pub shadow_lod_bias: u8,
 
     pub lod_distance: [f32; FDATA_MAX_LOD_MESH_COUNT],
 
     /// Base of segment array with public information
-    segment_array: *mut FMeshSegment,
+    pub(crate) segment_array: *mut FMeshSegment,
     /// Pointer to bone array (number of elements is nBoneCount) (NULL if nBoneCount is 0)
-    bone_array: *mut FMeshBone,
+    pub(crate) bone_array: *mut FMeshBone,
     /// Pointer to light array (number of elements is nLightCount) (NULL if nLightCount is 0)
-    light_array: *mut FMeshLight,
+    pub(crate) light_array: *mut FMeshLight,
     /// Pointer to the skeleton index array used by FMeshBone_t::Skelton.nChildArrayStartIndex
-    skeleton_index_array: *mut u8,
+    pub(crate) skeleton_index_array: *mut u8,
     /// Pointer to the array of materials
-    material_array: *mut FMeshMaterial,
+    pub(crate) material_array: *mut FMeshMaterial,
 
     /// Pointer to an array of the mesh collision data structures (1 per segment)
-    collision_tree: *mut (), /* FkDOP_Tree_t */
+    pub(crate) collision_tree: *mut (), /* FkDOP_Tree_t */
     /// Texture layer ID array. Each slot matches up with a corresponding slot in each instance of this mesh.
-    tex_layer_array: *mut FMeshTexLayerID,
+    pub(crate) tex_layer_array: *mut FMeshTexLayerID,
 
     /// Pointer to implementation-specific object data
-    mesh_is: *mut DxMesh,
+    pub(crate) mesh_is: *mut DxMesh,
 }
 
 impl Fixable for FMesh {
-    unsafe fn fix_offset(&mut self, ptr: *mut u8) {
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, endian: SourceEndian) {
         self.segment_array = fix_offset(self.segment_array, ptr);
         self.bone_array = fix_offset(self.bone_array, ptr);
         self.light_array = fix_offset(self.light_array, ptr);
         self.skeleton_index_array = fix_offset(self.skeleton_index_array, ptr);
         self.collision_tree = fix_offset(self.collision_tree, ptr);
-        println!("Premat");
 
-        try_fix_array(&mut self.material_array, self.material_count, ptr);
-        println!("Postmat");
+        try_fix_array(&mut self.material_array, self.material_count, ptr, endian);
 
         // TODO: Fixup coll tree
 
-        try_fix_array(&mut self.tex_layer_array, self.tex_layer_id_count, ptr);
+        try_fix_array(
+            &mut self.tex_layer_array,
+            self.tex_layer_id_count,
+            ptr,
+            endian,
+        );
+
+        // Only the DirectX platform data is described by the in-place
+        // structures, big endian files carry GameCube data which is left out
+        match endian {
+            SourceEndian::Little => try_fix(&mut self.mesh_is, ptr, endian),
+            SourceEndian::Big => self.mesh_is = std::ptr::null_mut(),
+        }
+    }
 
-        try_fix(&mut self.mesh_is, ptr);
+    unsafe fn validate_offsets(&self, validator: &OffsetValidator) -> Result<(), ValidationError> {
+        validator.slice(
+            "FMesh.segment_array",
+            self.segment_array,
+            self.segment_count as usize,
+        )?;
+        let bones = validator.slice(
+            "FMesh.bone_array",
+            self.bone_array,
+            self.bone_count as usize,
+        )?;
+        validator.slice(
+            "FMesh.light_array",
+            self.light_array,
+            self.light_count as usize,
+        )?;
+
+        // Skeleton index array is sized by the child lists of the bones
+        let skeleton_count = bones
+            .unwrap_or_default()
+            .iter()
+            .map(|bone| {
+                let bone = validator.read(*bone);
+                bone.skeleton.child_array_start_index as usize
+                    + bone.skeleton.child_bone_count as usize
+            })
+            .max()
+            .unwrap_or_default();
+        validator.slice(
+            "FMesh.skeleton_index_array",
+            self.skeleton_index_array,
+            skeleton_count,
+        )?;
+
+        validator.opaque("FMesh.collision_tree", self.collision_tree)?;
+        validator.array(
+            "FMesh.material_array",
+            self.material_array,
+            self.material_count as usize,
+        )?;
+        validator.array(
+            "FMesh.tex_layer_array",
+            self.tex_layer_array,
+            self.tex_layer_id_count as usize,
+        )?;
+        match validator.endian() {
+            SourceEndian::Little => validator.value("FMesh.mesh_is", self.mesh_is),
+            SourceEndian::Big => validator.unsupported("FMesh.mesh_is", self.mesh_is),
+        }
+    }
+
+    fn sanity_check(&self, endian: SourceEndian) -> Vec<SanityWarning> {
+        check_mesh(self, FixupStage::for_source(endian))
     }
 }
 
 impl FMesh {
     pub fn lod_distances(&self) -> &[f32] {
-        &self.lod_distance[..self.lod_count as usize]
+        let count = (self.lod_count as usize).min(FDATA_MAX_LOD_MESH_COUNT);
+        &self.lod_distance[..count]
     }
 
     pub fn segments(&self) -> Option<&[FMeshSegment]> {
@@ -397,6 +621,7 @@ impl FMesh {
 }
 
 #[derive(Debug, Clone, Copy, SwapBytes)]
+#[repr(C)]
 pub struct FMeshSegment {
     /// Bounds the segment in model space
     pub bound_sphere: CFSphere,
@@ -438,7 +663,7 @@ pub struct FMeshSkeleton {
 pub struct FMeshLight {
     // ASCIIZ name of the light
     #[sb(skip)]
-    pub name: FixedString<FDATA_BONE_NAME_LENGTH>,
+    pub name: FixedString<FLIGHT_NAME_LENGTH>,
 
     // texture that is projected by this light.  MAKE SURE YOU NULL TERMINATE THIS, EVEN IF YOU DON"T WANT A TEXTURE
     #[sb(skip)]
@@ -522,19 +747,52 @@ pub struct FMeshMaterial {
 }
 
 impl Fixable for FMeshMaterial {
-    unsafe fn fix_offset(&mut self, ptr: *mut u8) {
-        println!("Shr");
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, endian: SourceEndian) {
         self.shader_light_registers = fix_offset(self.shader_light_registers, ptr);
         self.shader_surface_reigsters = fix_offset(self.shader_surface_reigsters, ptr);
 
-        println!("Pre");
-        try_fix(&mut self.platform_data, ptr);
-        println!("Post");
+        // Big endian platform data is GameCube data, as with the mesh data
+        match endian {
+            SourceEndian::Little => try_fix(&mut self.platform_data, ptr, endian),
+            SourceEndian::Big => self.platform_data = std::ptr::null_mut(),
+        }
 
-        // TODO: Fixup registers
+        // The register counts come from the shader tables of the engine
+        // which aren't part of the file, so the registers are left as is
+        // and are read through the views with a register table
 
         // TODO: Fix hash key
     }
+
+    unsafe fn validate_offsets(&self, validator: &OffsetValidator) -> Result<(), ValidationError> {
+        validator.opaque(
+            "FMeshMaterial.shader_light_registers",
+            self.shader_light_registers,
+        )?;
+        validator.opaque(
+            "FMeshMaterial.shader_surface_registers",
+            self.shader_surface_reigsters,
+        )?;
+        match validator.endian() {
+            SourceEndian::Little => {
+                validator.value("FMeshMaterial.platform_data", self.platform_data)
+            }
+            SourceEndian::Big => {
+                validator.unsupported("FMeshMaterial.platform_data", self.platform_data)
+            }
+        }
+    }
+}
+
+impl FMeshMaterial {
+    /// Decodes the sphere bounding the verts of this material in model
+    /// space, the radius is stored relative to the mesh bounding sphere
+    pub fn bound_sphere(&self, mesh_radius: f32) -> CFSphere {
+        CFSphere {
+            radius: self.compressed_radius as f32 * (1.0 / 255.0) * mesh_radius,
+            position: self.average_vert_pos,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, SwapBytes)]
@@ -553,13 +811,29 @@ pub struct FMeshTexLayerID {
 }
 
 impl Fixable for FMeshTexLayerID {
-    unsafe fn fix_offset(&mut self, ptr: *mut u8) {
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, endian: SourceEndian) {
         self.flip_palette = fix_offset(self.flip_palette, ptr);
 
         if let Some(array) = array_ptr_mut(self.flip_palette, self.flip_page_count) {
-            array.iter_mut().for_each(|value| try_fix(value, ptr))
+            array.iter_mut().for_each(|value| {
+                *value = endian.read(*value);
+                try_fix(value, ptr, endian)
+            })
         }
     }
+
+    unsafe fn validate_offsets(&self, validator: &OffsetValidator) -> Result<(), ValidationError> {
+        // The pointers in the palette are swapped and fixed in place
+        let palette = validator.fixed(
+            "FMeshTexLayerID.flip_palette",
+            self.flip_palette,
+            self.flip_page_count as usize,
+        )?;
+        for tex_inst in palette.unwrap_or_default() {
+            validator.value("FMeshTexLayerID.flip_palette[]", validator.read(*tex_inst))?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Copy, SwapBytes)]
@@ -576,13 +850,67 @@ pub struct CFTexInst {
     pub mipmap_bias: f32,
 }
 
+impl CFTexInst {
+    /// Texture data the definition is embedded in, the definition usually
+    /// sits at the start of the texture data it points to
+    ///
+    /// # Safety
+    ///
+    /// The buffer the validator was created from must still be alive
+    unsafe fn embedded_tex_data(
+        &self,
+        validator: &OffsetValidator,
+    ) -> Result<Option<*mut FTexData>, ValidationError> {
+        let tex_def = validator.slice("CFTexInst.tex_def", self.tex_def, 1)?;
+        Ok(tex_def
+            .map(|tex_def| validator.read(tex_def[0]).tex_data)
+            .filter(|tex_data| *tex_data as usize == self.tex_def as usize))
+    }
+}
+
 impl Fixable for CFTexInst {
-    unsafe fn fix_offset(&mut self, ptr: *mut u8) {
-        try_fix(&mut self.tex_def, ptr);
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, endian: SourceEndian) {
+        let offset = self.tex_def as usize;
+        self.tex_def = fix_offset(self.tex_def, ptr);
+
+        // A definition embedded in its texture data is fixed along with the
+        // data, so the data pointing back to it isn't fixed a second time
+        let embedded = match self.tex_def.as_mut() {
+            Some(tex_def) if endian.read(tex_def.tex_data) as usize == offset => {
+                (*self.tex_def.cast::<FTexData>()).fix(ptr, endian);
+                true
+            }
+            Some(tex_def) => {
+                tex_def.fix(ptr, endian);
+                false
+            }
+            None => false,
+        };
+
+        for value in self.tex_buffer.iter_mut() {
+            match embedded && *value as usize == offset {
+                true => *value = fix_offset(*value, ptr),
+                false => try_fix(value, ptr, endian),
+            }
+        }
+    }
 
-        self.tex_buffer
-            .iter_mut()
-            .for_each(|value| try_fix(value, ptr));
+    unsafe fn validate_offsets(&self, validator: &OffsetValidator) -> Result<(), ValidationError> {
+        let embedded = self.embedded_tex_data(validator)?;
+        match embedded {
+            Some(tex_data) => validator.value("CFTexInst.tex_def", tex_data)?,
+            None => validator.value("CFTexInst.tex_def", self.tex_def)?,
+        }
+
+        for tex_data in self.tex_buffer {
+            // Buffers of the texture data holding the definition were
+            // checked along with the definition
+            if embedded == Some(tex_data) {
+                continue;
+            }
+            validator.value("CFTexInst.tex_buffer", tex_data)?;
+        }
+        Ok(())
     }
 }
 
@@ -594,9 +922,14 @@ pub struct FTexDef {
 }
 
 impl Fixable for FTexDef {
-    unsafe fn fix_offset(&mut self, ptr: *mut u8) {
-        self.tex_info.fix_offset(ptr);
-        try_fix(&mut self.tex_data, ptr);
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, endian: SourceEndian) {
+        self.tex_info.fix_offset(ptr, endian);
+        try_fix(&mut self.tex_data, ptr, endian);
+    }
+
+    unsafe fn validate_offsets(&self, validator: &OffsetValidator) -> Result<(), ValidationError> {
+        self.tex_info.validate_offsets(validator)?;
+        validator.value("FTexDef.tex_data", self.tex_data)
     }
 }
 
@@ -629,9 +962,13 @@ pub struct FTexInfo {
 }
 
 impl Fixable for FTexInfo {
-    unsafe fn fix_offset(&mut self, ptr: *mut u8) {
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, _endian: SourceEndian) {
         self.user_data = fix_offset(self.user_data, ptr);
     }
+
+    unsafe fn validate_offsets(&self, validator: &OffsetValidator) -> Result<(), ValidationError> {
+        validator.opaque("FTexInfo.user_data", self.user_data)
+    }
 }
 
 #[derive(Debug, Clone, Copy, SwapBytes)]
@@ -642,7 +979,7 @@ pub struct FLink {
 }
 
 impl Fixable for FLink {
-    unsafe fn fix_offset(&mut self, ptr: *mut u8) {
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, _endian: SourceEndian) {
         self.prev_link = fix_offset(self.prev_link, ptr);
         self.next_link = fix_offset(self.next_link, ptr);
         // TODO: should I be fixing the values..?
@@ -701,15 +1038,37 @@ pub struct FTexData {
 }
 
 impl Fixable for FTexData {
-    unsafe fn fix_offset(&mut self, ptr: *mut u8) {
-        self.tex_def.fix_offset(ptr);
-        self.link.fix_offset(ptr);
+    unsafe fn fix_offset(&mut self, ptr: *mut u8, endian: SourceEndian) {
+        // The embedded definition points back to this data, so it's only
+        // rebased rather than followed again, matching the validation
+        self.tex_def.tex_info.fix_offset(ptr, endian);
+        self.tex_def.tex_data = fix_offset(self.tex_def.tex_data, ptr);
+        self.link.fix_offset(ptr, endian);
 
         self.streaming_handle = fix_offset(self.streaming_handle, ptr);
         self.image_data = fix_offset(self.image_data, ptr);
         self.d3d_texture = fix_offset(self.d3d_texture, ptr);
         self.d3d_depth_stencil = fix_offset(self.d3d_depth_stencil, ptr);
     }
+
+    unsafe fn validate_offsets(&self, validator: &OffsetValidator) -> Result<(), ValidationError> {
+        // Texture data usually points back to the definition it's embedded in
+        // so the data it points to isn't followed again
+        self.tex_def.tex_info.validate_offsets(validator)?;
+        validator.opaque("FTexData.tex_def.tex_data", self.tex_def.tex_data)?;
+        validator.opaque("FTexData.link.prev_link", self.link.prev_link)?;
+        validator.opaque("FTexData.link.next_link", self.link.next_link)?;
+
+        validator.opaque("FTexData.streaming_handle", self.streaming_handle)?;
+        validator.slice(
+            "FTexData.image_data",
+            self.image_data.cast::<u8>(),
+            self.texture_bytes as usize,
+        )?;
+        validator.opaque("FTexData.d3d_texture", self.d3d_texture)?;
+        validator.opaque("FTexData.d3d_depth_stencil", self.d3d_depth_stencil)?;
+        Ok(())
+    }
 }
 
 /// Safe wrapper around a type created from a buffer to
@@ -717,15 +1076,32 @@ impl Fixable for FTexData {
 /// to access the inner type
 pub struct SafeBuffer<T> {
     ptr: *mut T,
+    /// Length of the underlying buffer in bytes
+    length: usize,
+    /// Layout the underlying buffer was allocated with
+    layout: Layout,
 }
 
-impl<T> SafeBuffer<T> {}
+impl<T> SafeBuffer<T> {
+    /// Pointer to the start of the underlying buffer
+    pub fn base_ptr(&self) -> *const u8 {
+        self.ptr.cast()
+    }
+
+    /// Length of the underlying buffer in bytes
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
 
 impl<T> Drop for SafeBuffer<T> {
     fn drop(&mut self) {
-        // Recreate and drop the underlying memory
-        let buffer = unsafe { Box::from_raw(self.ptr.cast::<u8>()) };
-        drop(buffer);
+        // Release the aligned copy of the buffer
+        unsafe { dealloc(self.ptr.cast::<u8>(), self.layout) };
     }
 }
 
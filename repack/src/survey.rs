@@ -0,0 +1,327 @@
+//! Safe mode reading of the platform independent portion of a mesh, only
+//! the header, bones, materials and lights are read and the platform
+//! specific data (mesh_is) is skipped entirely. Nothing is fixed up in
+//! memory so damaged or unknown files can be surveyed without the risk
+//! of following a bad pointer, sections that can't be read are recorded
+//! as warnings rather than failing the whole file
+
+use std::{
+    io::{self, Cursor, Seek, SeekFrom},
+    path::Path,
+};
+
+use binrw::{BinRead, Endian};
+use openglitch_formats::mesh::{FMesh, FMeshBone, FMeshLight, FMeshMaterial};
+use thiserror::Error;
+
+use crate::{
+    platform::Platform,
+    types::{RawSphere, RawVec3f},
+};
+
+/// Size of the mesh header within the files
+pub const FILE_HEADER_SIZE: usize = FMesh::SIZE;
+
+/// Size of a material within the files, the in memory structure holds
+/// pointers so its size depends on the host
+pub const FILE_MATERIAL_SIZE: usize = FMeshMaterial::SIZE;
+
+#[derive(Debug, Error)]
+pub enum SurveyError {
+    #[error("unable to detect the platform from the file name")]
+    UnknownPlatform,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("unable to read the mesh header: {0}")]
+    Header(binrw::Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct SurveyBone {
+    pub name: String,
+    /// Index of the parent bone (None for root bones)
+    pub parent_index: Option<u8>,
+    pub part_id: u8,
+    /// Position of the bone in model space when at rest
+    pub position: [f32; 3],
+}
+
+#[derive(Debug, Clone)]
+pub struct SurveyMaterial {
+    pub part_id_mask: u32,
+    pub lod_mask: u8,
+    /// Material flags (see FMESH_MTLFLAG_* for info)
+    pub flags: u16,
+    pub tint: [f32; 3],
+    /// Texture layer indices used by the material (255=empty slot)
+    pub tex_layer_id_index: [u8; 4],
+    /// Radius and center of the sphere bounding the material verts
+    pub bound_sphere: [f32; 4],
+}
+
+#[derive(Debug, Clone)]
+pub struct SurveyLight {
+    pub name: String,
+    /// Light type (see FLightType_e for info)
+    pub light_type: u8,
+    /// Index into the parent model's bone (-1 if there is no parent bone)
+    pub parent_bone_index: i8,
+    pub intensity: f32,
+    pub color: [f32; 4],
+    /// Radius and position of the light in model space
+    pub influence: [f32; 4],
+}
+
+/// Platform independent details of a mesh read in safe mode
+#[derive(Debug, Clone)]
+pub struct MeshSurvey {
+    pub platform: Platform,
+    pub name: String,
+    pub bound_sphere: [f32; 4],
+    pub flags: u16,
+    pub segment_count: u8,
+    pub tex_layer_count: u8,
+    pub lod_distances: Vec<f32>,
+    pub bones: Vec<SurveyBone>,
+    pub materials: Vec<SurveyMaterial>,
+    pub lights: Vec<SurveyLight>,
+    /// Sections that couldn't be read
+    pub warnings: Vec<String>,
+}
+
+/// Reads `count` values of `stride` bytes starting at the offset, stopping
+/// at the first value that can't be read
+fn read_array<T>(
+    cursor: &mut Cursor<&[u8]>,
+    endian: Endian,
+    offset: u32,
+    count: u8,
+    stride: usize,
+) -> Result<Vec<T>, String>
+where
+    T: for<'a> BinRead<Args<'a> = ()>,
+{
+    if offset == 0 {
+        return Ok(Vec::new());
+    }
+
+    let length = cursor.get_ref().len() as u64;
+    let end = offset as u64 + count as u64 * stride as u64;
+    if end > length {
+        return Err(format!(
+            "{} entries at {:#x} end past the {} byte file",
+            count, offset, length
+        ));
+    }
+
+    (0..count as u64)
+        .map(|index| {
+            cursor
+                .seek(SeekFrom::Start(offset as u64 + index * stride as u64))
+                .map_err(|err| err.to_string())?;
+            T::read_options(cursor, endian, ()).map_err(|err| err.to_string())
+        })
+        .collect()
+}
+
+/// Radius followed by the position of the sphere
+fn sphere_values(sphere: &RawSphere) -> [f32; 4] {
+    let RawVec3f { x, y, z } = sphere.position;
+    [sphere.radius, x, y, z]
+}
+
+/// Takes the values of a section, recording the warning if it couldn't be read
+fn section<T>(warnings: &mut Vec<String>, name: &str, result: Result<Vec<T>, String>) -> Vec<T> {
+    match result {
+        Ok(value) => value,
+        Err(err) => {
+            warnings.push(format!("{}: {}", name, err));
+            Vec::new()
+        }
+    }
+}
+
+impl MeshSurvey {
+    /// Surveys the mesh at the provided path, the platform is
+    /// detected from the file name
+    pub fn load(path: &Path) -> Result<MeshSurvey, SurveyError> {
+        let (platform, _) = Platform::from_path(path).ok_or(SurveyError::UnknownPlatform)?;
+        let buffer = std::fs::read(path)?;
+        Self::from_buffer(platform, &buffer)
+    }
+
+    pub fn from_buffer(platform: Platform, buffer: &[u8]) -> Result<MeshSurvey, SurveyError> {
+        let endian = platform.endian();
+        let mut cursor = Cursor::new(buffer);
+        let header = FMesh::read_options(&mut cursor, endian, ()).map_err(SurveyError::Header)?;
+
+        let mut warnings = Vec::new();
+
+        let bones = section(
+            &mut warnings,
+            "bones",
+            read_array::<FMeshBone>(
+                &mut cursor,
+                endian,
+                header.bone_array.0,
+                header.bone_count,
+                FMeshBone::SIZE,
+            ),
+        )
+        .into_iter()
+        .map(|bone| SurveyBone {
+            name: bone.name.as_string(),
+            parent_index: bone.parent_index(),
+            part_id: bone.part_id,
+            position: bone.at_rest_bone_to_model.matrix[3],
+        })
+        .collect();
+
+        let mesh_radius = header.bound_sphere.radius;
+        let materials = section(
+            &mut warnings,
+            "materials",
+            read_array::<FMeshMaterial>(
+                &mut cursor,
+                endian,
+                header.material_array.0,
+                header.material_count,
+                FMeshMaterial::SIZE,
+            ),
+        )
+        .into_iter()
+        .map(|material| {
+            let sphere = material.bound_sphere(mesh_radius);
+            let tint = material.material_tint;
+            SurveyMaterial {
+                part_id_mask: material.part_id_mask,
+                lod_mask: material.lod_mask,
+                flags: material.mtl_flags,
+                tint: [tint.red, tint.green, tint.blue],
+                tex_layer_id_index: material.tex_layer_id_index,
+                bound_sphere: sphere_values(&sphere),
+            }
+        })
+        .collect();
+
+        let lights = section(
+            &mut warnings,
+            "lights",
+            read_array::<FMeshLight>(
+                &mut cursor,
+                endian,
+                header.light_array.0,
+                header.light_count,
+                FMeshLight::SIZE,
+            ),
+        )
+        .into_iter()
+        .map(|light| {
+            let color = light.motif.color;
+            SurveyLight {
+                name: light.name.as_string(),
+                light_type: light.light_type,
+                parent_bone_index: light.parent_bone_index,
+                intensity: light.intensity,
+                color: [color.red, color.green, color.blue, color.alpha],
+                influence: sphere_values(&light.influence),
+            }
+        })
+        .collect();
+
+        Ok(MeshSurvey {
+            platform,
+            name: header.name.as_string(),
+            bound_sphere: sphere_values(&header.bound_sphere),
+            flags: header.flags,
+            segment_count: header.segment_count,
+            tex_layer_count: header.tex_layer_id_count,
+            lod_distances: header.lod_distances().to_vec(),
+            bones,
+            materials,
+            lights,
+            warnings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use openglitch_formats::mesh::FMeshBone;
+
+    use crate::platform::Platform;
+
+    use super::{MeshSurvey, FILE_HEADER_SIZE, FILE_MATERIAL_SIZE};
+
+    fn put_u32(bytes: &mut [u8], offset: usize, value: u32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_f32(bytes: &mut [u8], offset: usize, value: f32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Builds a little endian file with one bone and one material
+    fn survey_file() -> Vec<u8> {
+        let bone_offset = FILE_HEADER_SIZE;
+        let material_offset = bone_offset + FMeshBone::SIZE;
+        let mut bytes = vec![0u8; material_offset + FILE_MATERIAL_SIZE];
+
+        bytes[..4].copy_from_slice(b"mesh");
+        put_f32(&mut bytes, 16, 2.0);
+        bytes[62] = 1; // bone count
+        bytes[68] = 1; // material count
+        bytes[70] = 1; // lod count
+        put_f32(&mut bytes, 72, 50.0);
+        put_u32(&mut bytes, 108, bone_offset as u32);
+        put_u32(&mut bytes, 120, material_offset as u32);
+        // Platform data that would crash the fixups if followed
+        put_u32(&mut bytes, 132, 0xdeadbeef);
+
+        bytes[bone_offset..bone_offset + 4].copy_from_slice(b"root");
+        put_f32(&mut bytes, bone_offset + 32 + 36, 1.5);
+        bytes[bone_offset + 240] = 255;
+        bytes[bone_offset + 244] = 3;
+
+        put_u32(&mut bytes, material_offset + 12, 0b1000);
+        bytes[material_offset + 36] = 255;
+        put_f32(&mut bytes, material_offset + 44, 0.5);
+        put_f32(&mut bytes, material_offset + 56, 1.0);
+
+        bytes
+    }
+
+    #[test]
+    fn test_survey() {
+        let survey = MeshSurvey::from_buffer(Platform::Xbox, &survey_file()).unwrap();
+
+        assert!(survey.warnings.is_empty(), "{:?}", survey.warnings);
+        assert_eq!(survey.name, "mesh");
+        assert_eq!(survey.lod_distances, vec![50.0]);
+
+        assert_eq!(survey.bones.len(), 1);
+        assert_eq!(survey.bones[0].name, "root");
+        assert_eq!(survey.bones[0].parent_index, None);
+        assert_eq!(survey.bones[0].part_id, 3);
+        assert_eq!(survey.bones[0].position, [1.5, 0.0, 0.0]);
+
+        assert_eq!(survey.materials.len(), 1);
+        assert_eq!(survey.materials[0].part_id_mask, 0b1000);
+        assert_eq!(survey.materials[0].tint, [0.5, 0.0, 0.0]);
+        assert_eq!(survey.materials[0].bound_sphere, [2.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_survey_damaged() {
+        let mut bytes = survey_file();
+        // Material count running past the end of the file
+        bytes[68] = 200;
+
+        let survey = MeshSurvey::from_buffer(Platform::Xbox, &bytes).unwrap();
+        assert_eq!(survey.bones.len(), 1);
+        assert!(survey.materials.is_empty());
+        assert_eq!(survey.warnings.len(), 1);
+
+        assert!(MeshSurvey::from_buffer(Platform::Xbox, &bytes[..64]).is_err());
+    }
+}
@@ -0,0 +1,523 @@
+//! Terminal interface for inspecting a mesh without the viewer, the file
+//! structure is shown as a tree next to the values and bytes of the
+//! selected entry. The structure is read in safe mode so any platform can
+//! be browsed, the referenced regions and exports need the DirectX layout
+
+use std::{
+    collections::HashSet,
+    fs::File,
+    io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+use thiserror::Error;
+
+use crate::{
+    export::{ExportFormat, ExportGeometry},
+    layout::FileLayout,
+    platform::Platform,
+    survey::{MeshSurvey, SurveyError, FILE_HEADER_SIZE},
+    view::MeshView,
+};
+
+/// Bytes shown on each line of the hex view
+const HEX_WIDTH: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum TuiError {
+    #[error(transparent)]
+    Survey(#[from] SurveyError),
+    #[error("unknown platform for {0}")]
+    UnknownPlatform(PathBuf),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Entry in the structure tree
+#[derive(Debug, Clone, Default)]
+pub struct TreeNode {
+    pub label: String,
+    pub value: Option<String>,
+    /// Bytes of the file the entry was read from, when known
+    pub range: Option<Range<usize>>,
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    fn field(label: &str, value: impl ToString) -> Self {
+        Self {
+            label: label.to_string(),
+            value: Some(value.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn group(label: String, children: Vec<TreeNode>) -> Self {
+        Self {
+            label,
+            children,
+            ..Default::default()
+        }
+    }
+}
+
+/// Builds the structure tree of the surveyed mesh, the layout adds
+/// the regions referenced by the platform specific data
+pub fn build_tree(survey: &MeshSurvey, layout: Option<&FileLayout>) -> Vec<TreeNode> {
+    let mut nodes = vec![TreeNode {
+        label: "header".to_string(),
+        range: Some(0..FILE_HEADER_SIZE),
+        children: vec![
+            TreeNode::field("name", &survey.name),
+            TreeNode::field("platform", survey.platform),
+            TreeNode::field("bound_sphere", format!("{:?}", survey.bound_sphere)),
+            TreeNode::field("flags", format!("{:#06x}", survey.flags)),
+            TreeNode::field("segment_count", survey.segment_count),
+            TreeNode::field("tex_layer_count", survey.tex_layer_count),
+            TreeNode::field("lod_distances", format!("{:?}", survey.lod_distances)),
+        ],
+        ..Default::default()
+    }];
+
+    nodes.push(TreeNode::group(
+        format!("bones ({})", survey.bones.len()),
+        survey
+            .bones
+            .iter()
+            .enumerate()
+            .map(|(index, bone)| {
+                TreeNode::group(
+                    format!("{}: {}", index, bone.name),
+                    vec![
+                        TreeNode::field("parent_index", format!("{:?}", bone.parent_index)),
+                        TreeNode::field("part_id", bone.part_id),
+                        TreeNode::field("position", format!("{:?}", bone.position)),
+                    ],
+                )
+            })
+            .collect(),
+    ));
+
+    nodes.push(TreeNode::group(
+        format!("materials ({})", survey.materials.len()),
+        survey
+            .materials
+            .iter()
+            .enumerate()
+            .map(|(index, material)| {
+                TreeNode::group(
+                    format!("material {}", index),
+                    vec![
+                        TreeNode::field("part_id_mask", format!("{:#010x}", material.part_id_mask)),
+                        TreeNode::field("lod_mask", format!("{:#04x}", material.lod_mask)),
+                        TreeNode::field("flags", format!("{:#06x}", material.flags)),
+                        TreeNode::field("tint", format!("{:?}", material.tint)),
+                        TreeNode::field(
+                            "tex_layer_id_index",
+                            format!("{:?}", material.tex_layer_id_index),
+                        ),
+                        TreeNode::field("bound_sphere", format!("{:?}", material.bound_sphere)),
+                    ],
+                )
+            })
+            .collect(),
+    ));
+
+    nodes.push(TreeNode::group(
+        format!("lights ({})", survey.lights.len()),
+        survey
+            .lights
+            .iter()
+            .map(|light| {
+                TreeNode::group(
+                    light.name.clone(),
+                    vec![
+                        TreeNode::field("light_type", light.light_type),
+                        TreeNode::field("parent_bone_index", light.parent_bone_index),
+                        TreeNode::field("intensity", light.intensity),
+                        TreeNode::field("color", format!("{:?}", light.color)),
+                        TreeNode::field("influence", format!("{:?}", light.influence)),
+                    ],
+                )
+            })
+            .collect(),
+    ));
+
+    if let Some(layout) = layout {
+        nodes.push(TreeNode::group(
+            format!("regions ({})", layout.regions.len()),
+            layout
+                .regions
+                .iter()
+                .map(|region| TreeNode {
+                    label: region.name.clone(),
+                    value: Some(format!("{:#x} ({} bytes)", region.offset, region.size)),
+                    range: Some(region.range()),
+                    children: Vec::new(),
+                })
+                .collect(),
+        ));
+    }
+
+    if !survey.warnings.is_empty() {
+        nodes.push(TreeNode::group(
+            format!("warnings ({})", survey.warnings.len()),
+            survey
+                .warnings
+                .iter()
+                .map(|warning| TreeNode::field("warning", warning))
+                .collect(),
+        ));
+    }
+
+    nodes
+}
+
+/// Formats the bytes of the range as hex dump lines, the range
+/// is clamped to the bytes that are present
+pub fn hex_lines(bytes: &[u8], range: Range<usize>) -> Vec<String> {
+    let end = range.end.min(bytes.len());
+    let start = range.start.min(end);
+
+    bytes[start..end]
+        .chunks(HEX_WIDTH)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|value| format!("{:02x}", value)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|value| match value.is_ascii_graphic() {
+                    true => *value as char,
+                    false => '.',
+                })
+                .collect();
+
+            format!(
+                "{:08x}  {:<width$}  {}",
+                start + index * HEX_WIDTH,
+                hex.join(" "),
+                ascii,
+                width = HEX_WIDTH * 3 - 1
+            )
+        })
+        .collect()
+}
+
+/// Tree entry visible with the current expanded entries
+struct VisibleNode<'a> {
+    depth: usize,
+    /// Indices of the entry and its parents from the root
+    path: Vec<usize>,
+    node: &'a TreeNode,
+}
+
+struct App {
+    path: PathBuf,
+    platform: Platform,
+    bytes: Vec<u8>,
+    nodes: Vec<TreeNode>,
+    expanded: HashSet<Vec<usize>>,
+    selected: usize,
+    /// First line of the hex view
+    hex_scroll: usize,
+    /// Outcome of the last action
+    status: String,
+}
+
+impl App {
+    fn visible(&self) -> Vec<VisibleNode<'_>> {
+        fn walk<'a>(
+            nodes: &'a [TreeNode],
+            expanded: &HashSet<Vec<usize>>,
+            parent: &[usize],
+            out: &mut Vec<VisibleNode<'a>>,
+        ) {
+            for (index, node) in nodes.iter().enumerate() {
+                let mut path = parent.to_vec();
+                path.push(index);
+
+                let open = expanded.contains(&path);
+                out.push(VisibleNode {
+                    depth: parent.len(),
+                    path: path.clone(),
+                    node,
+                });
+
+                if open {
+                    walk(&node.children, expanded, &path, out);
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        walk(&self.nodes, &self.expanded, &[], &mut out);
+        out
+    }
+
+    fn selected_path(&self) -> Option<Vec<usize>> {
+        self.visible()
+            .into_iter()
+            .nth(self.selected)
+            .map(|visible| visible.path)
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        let count = self.visible().len();
+        if count == 0 {
+            return;
+        }
+
+        self.selected = self.selected.saturating_add_signed(offset).min(count - 1);
+        self.hex_scroll = 0;
+    }
+
+    fn set_expanded(&mut self, expanded: bool) {
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+
+        if expanded {
+            self.expanded.insert(path);
+        } else {
+            self.expanded.remove(&path);
+        }
+    }
+
+    fn export(&mut self, format: ExportFormat) {
+        self.status = match export_file(&self.path, self.platform, &self.bytes, format) {
+            Ok(output) => format!("Exported {}", output.display()),
+            Err(err) => format!("Export failed: {}", err),
+        };
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(1)])
+            .split(frame.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(rows[0]);
+        let details = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(5), Constraint::Min(3)])
+            .split(columns[1]);
+
+        let visible = self.visible();
+        let items: Vec<ListItem> = visible
+            .iter()
+            .map(|visible| {
+                let marker = match (
+                    visible.node.children.is_empty(),
+                    self.expanded.contains(&visible.path),
+                ) {
+                    (true, _) => " ",
+                    (false, true) => "-",
+                    (false, false) => "+",
+                };
+                ListItem::new(format!(
+                    "{}{} {}",
+                    "  ".repeat(visible.depth),
+                    marker,
+                    visible.node.label
+                ))
+            })
+            .collect();
+
+        let title = format!("{} ({})", self.path.display(), self.platform);
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(list, columns[0], &mut state);
+
+        let node = visible.get(self.selected).map(|visible| visible.node);
+        let value = node
+            .map(|node| {
+                let mut lines = vec![node.label.clone()];
+                lines.extend(node.value.clone());
+                if let Some(range) = &node.range {
+                    lines.push(format!("{:#x}..{:#x}", range.start, range.end));
+                }
+                lines.join("\n")
+            })
+            .unwrap_or_default();
+        frame.render_widget(
+            Paragraph::new(value).block(Block::default().borders(Borders::ALL).title("Value")),
+            details[0],
+        );
+
+        let hex = node
+            .and_then(|node| node.range.clone())
+            .map(|range| hex_lines(&self.bytes, range))
+            .unwrap_or_default();
+        let hex: Vec<String> = hex.into_iter().skip(self.hex_scroll).collect();
+        frame.render_widget(
+            Paragraph::new(hex.join("\n"))
+                .block(Block::default().borders(Borders::ALL).title("Hex")),
+            details[1],
+        );
+
+        let status = match self.status.is_empty() {
+            true => "arrows: browse  pgup/pgdn: scroll hex  o: export obj  p: export ply  q: quit",
+            false => &self.status,
+        };
+        frame.render_widget(Paragraph::new(status), rows[1]);
+    }
+}
+
+/// Exports the DirectX geometry of the file next to it
+fn export_file(
+    path: &Path,
+    platform: Platform,
+    bytes: &[u8],
+    format: ExportFormat,
+) -> Result<PathBuf, String> {
+    if !platform.is_dx() {
+        return Err(format!("{} meshes can't be exported", platform));
+    }
+
+    let mesh = MeshView::new(bytes, platform.endian()).map_err(|err| err.to_string())?;
+    let geometry = ExportGeometry::from_dx_view(&mesh).map_err(|err| err.to_string())?;
+
+    let output = path.with_extension(format.extension());
+    let mut file = File::create(&output).map_err(|err| err.to_string())?;
+    geometry
+        .write(format, None, &mut file)
+        .map_err(|err| err.to_string())?;
+
+    Ok(output)
+}
+
+/// Runs the interface for the file at the provided path until it's closed
+pub fn run(path: &Path) -> Result<(), TuiError> {
+    let (platform, _) =
+        Platform::from_path(path).ok_or_else(|| TuiError::UnknownPlatform(path.to_path_buf()))?;
+    let bytes = std::fs::read(path)?;
+    let survey = MeshSurvey::from_buffer(platform, &bytes)?;
+
+    // Files that fail validation are still browsed from the survey, only
+    // the referenced regions are missing
+    let layout = platform
+        .is_dx()
+        .then(|| MeshView::new(&bytes, platform.endian()))
+        .and_then(Result::ok)
+        .and_then(|mesh| FileLayout::from_view(&mesh).ok());
+
+    let mut app = App {
+        path: path.to_path_buf(),
+        platform,
+        nodes: build_tree(&survey, layout.as_ref()),
+        bytes,
+        expanded: HashSet::new(),
+        selected: 0,
+        hex_scroll: 0,
+        status: String::new(),
+    };
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let result = run_app(&mut app);
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+
+    result
+}
+
+fn run_app(app: &mut App) -> Result<(), TuiError> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Up | KeyCode::Char('k') => app.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => app.move_selection(1),
+            KeyCode::Right | KeyCode::Enter => app.set_expanded(true),
+            KeyCode::Left => app.set_expanded(false),
+            KeyCode::PageUp => app.hex_scroll = app.hex_scroll.saturating_sub(8),
+            KeyCode::PageDown => app.hex_scroll += 8,
+            KeyCode::Char('o') => app.export(ExportFormat::Obj),
+            KeyCode::Char('p') => app.export(ExportFormat::Ply),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        platform::Platform,
+        survey::{MeshSurvey, SurveyBone},
+    };
+
+    use super::{build_tree, hex_lines};
+
+    #[test]
+    fn test_hex_lines() {
+        let bytes: Vec<u8> = (0..20).chain(*b"abc").collect();
+        let lines = hex_lines(&bytes, 16..64);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("00000010  10 11 12 13 61 62 63"));
+        assert!(lines[0].ends_with("  ....abc"));
+    }
+
+    #[test]
+    fn test_build_tree() {
+        let survey = MeshSurvey {
+            platform: Platform::Xbox,
+            name: "mesh".to_string(),
+            bound_sphere: [1.0, 0.0, 0.0, 0.0],
+            flags: 0,
+            segment_count: 1,
+            tex_layer_count: 0,
+            lod_distances: vec![50.0],
+            bones: vec![SurveyBone {
+                name: "root".to_string(),
+                parent_index: None,
+                part_id: 0,
+                position: [0.0; 3],
+            }],
+            materials: Vec::new(),
+            lights: Vec::new(),
+            warnings: vec!["lights: damaged".to_string()],
+        };
+        let tree = build_tree(&survey, None);
+
+        let labels: Vec<&str> = tree.iter().map(|node| node.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "header",
+                "bones (1)",
+                "materials (0)",
+                "lights (0)",
+                "warnings (1)"
+            ]
+        );
+        assert_eq!(tree[0].range, Some(0..136));
+        assert_eq!(tree[1].children[0].label, "0: root");
+    }
+}
@@ -0,0 +1,1753 @@
+//! Zero-copy reading of mesh files through views that resolve the stored
+//! offsets on access. Unlike [load_memory_struct](crate::st::load_memory_struct)
+//! the buffer is never modified, values are read in the byte order of the
+//! platform and field offsets use the 32-bit layout of the files rather
+//! than the host structures, so files can be read on any host without
+//! casting the buffer to references
+//!
+//! Every offset is checked against the buffer when followed, so a damaged
+//! file produces a [ValidationError] rather than a bad pointer
+
+use std::{io::Cursor, marker::PhantomData};
+
+use binrw::{BinRead, Endian};
+use openglitch_formats::{
+    mesh::{FMesh, FMeshBone, FMeshLight, FMeshMaterial, FMeshSegment, FMeshTexLayerID},
+    texture::{CFTexInst, FTexDef},
+    types::{PtrOffset, RawSphere},
+};
+
+use crate::{
+    offsets::{ValidationError, ValidationProblem},
+    registers::{RegisterKind, RegisterTable, ShaderRegister},
+    st::{CFSphere, CFVec3},
+    survey::FILE_HEADER_SIZE,
+};
+
+/// Field offsets within the structures as stored in the files
+pub(crate) mod layout {
+    pub const MESH_NAME: usize = 0;
+    pub const MESH_BOUND_SPHERE: usize = 16;
+    pub const MESH_BOUND_BOX_MIN: usize = 32;
+    pub const MESH_BOUND_BOX_MAX: usize = 44;
+    pub const MESH_FLAGS: usize = 56;
+    pub const MESH_USED_BONE_COUNT: usize = 60;
+    pub const MESH_ROOT_BONE_INDEX: usize = 61;
+    pub const MESH_BONE_COUNT: usize = 62;
+    pub const MESH_TEX_LAYER_ID_COUNT: usize = 64;
+    pub const MESH_MATERIAL_COUNT: usize = 68;
+    pub const MESH_LOD_COUNT: usize = 70;
+    pub const MESH_LOD_DISTANCE: usize = 72;
+    pub const MESH_BONE_ARRAY: usize = 108;
+    pub const MESH_SKELETON_INDEX_ARRAY: usize = 116;
+    pub const MESH_MATERIAL_ARRAY: usize = 120;
+    pub const MESH_TEX_LAYER_ARRAY: usize = 128;
+    pub const MESH_IS: usize = 132;
+
+    pub const BONE_SIZE: usize = 256;
+    pub const BONE_NAME: usize = 0;
+    pub const BONE_AT_REST_BONE_TO_MODEL: usize = 32;
+    pub const BONE_AT_REST_MODEL_TO_BONE: usize = 80;
+    pub const BONE_AT_REST_PARENT_TO_BONE: usize = 128;
+    pub const BONE_AT_REST_BONE_TO_PARENT: usize = 176;
+    pub const BONE_PARENT_INDEX: usize = 240;
+    pub const BONE_CHILD_BONE_COUNT: usize = 241;
+    pub const BONE_CHILD_ARRAY_START_INDEX: usize = 242;
+    pub const BONE_PART_ID: usize = 244;
+
+    pub const MATERIAL_SHADER_LIGHT_REGISTERS: usize = 0;
+    pub const MATERIAL_SHADER_SURFACE_REGISTERS: usize = 4;
+    pub const MATERIAL_LIGHT_SHADER_INDEX: usize = 8;
+    pub const MATERIAL_SURFACE_SHADER_INDEX: usize = 10;
+    pub const MATERIAL_PART_ID_MASK: usize = 12;
+    pub const MATERIAL_PLATFORM_DATA: usize = 16;
+    pub const MATERIAL_LOD_MASK: usize = 20;
+    pub const MATERIAL_TEX_LAYER_ID_INDEX: usize = 24;
+    pub const MATERIAL_COMPRESSED_RADIUS: usize = 36;
+    pub const MATERIAL_FLAGS: usize = 38;
+    pub const MATERIAL_TINT: usize = 44;
+    pub const MATERIAL_AVERAGE_VERT_POS: usize = 56;
+
+    pub const TEX_LAYER_FLIP_PAGE_COUNT: usize = 2;
+    pub const TEX_LAYER_FLIP_PALETTE: usize = 4;
+
+    pub const TEX_INST_TEX_DEF: usize = 0;
+
+    pub const TEX_INFO_NAME: usize = 0;
+    pub const TEX_INFO_TEX_FMT: usize = 20;
+    pub const TEX_INFO_LOD_COUNT: usize = 23;
+    pub const TEX_INFO_TEXELS_ACROSS: usize = 28;
+    pub const TEX_INFO_TEXELS_DOWN: usize = 30;
+    pub const TEX_DEF_TEX_DATA: usize = 32;
+
+    pub const DX_MESH_SIZE: usize = 44;
+    pub const DX_MESH_VERTEX_BUFFER_COUNT: usize = 2;
+    pub const DX_MESH_INDEX_BUFFER_COUNT: usize = 3;
+    pub const DX_MESH_VERTEX_BUFFERS: usize = 28;
+    pub const DX_MESH_COLL_VERTEX_BUFFER: usize = 32;
+    pub const DX_MESH_INDICIES_COUNTS: usize = 36;
+    pub const DX_MESH_INDEX_BUFFER: usize = 40;
+
+    pub const DX_MATERIAL_SIZE: usize = 8;
+    pub const DX_MATERIAL_CLUSTER: usize = 0;
+    pub const DX_MATERIAL_CLUSTER_COUNT: usize = 4;
+
+    pub const DX_CLUSTER_SIZE: usize = 24;
+    pub const DX_CLUSTER_STRIP_COUNT: usize = 0;
+    pub const DX_CLUSTER_VERTEX_BUFFER_INDEX: usize = 4;
+    pub const DX_CLUSTER_INDEX_BUFFER_INDEX: usize = 5;
+    pub const DX_CLUSTER_PART_ID: usize = 6;
+    pub const DX_CLUSTER_LOD_ID: usize = 7;
+    pub const DX_CLUSTER_PUSH_BUFFER: usize = 8;
+    pub const DX_CLUSTER_TRI_COUNT: usize = 12;
+    pub const DX_CLUSTER_TRI_START_VINDEX: usize = 14;
+    pub const DX_CLUSTER_TRI_VTX_INDEX_MIN: usize = 16;
+    pub const DX_CLUSTER_TRI_VTX_INDEX_RANGE: usize = 18;
+    pub const DX_CLUSTER_MESH_STRIP: usize = 20;
+
+    pub const DX_STRIP_SIZE: usize = 8;
+    pub const DX_STRIP_TRI_COUNT: usize = 0;
+    pub const DX_STRIP_START_VINDEX: usize = 2;
+
+    pub const VERTEX_BUFFER_SIZE: usize = 48;
+    pub const VERTEX_BUFFER_VERTEX_COUNT: usize = 8;
+    pub const VERTEX_BUFFER_BYTES_PER_VERTEX: usize = 12;
+    pub const VERTEX_BUFFER_LMTC_COUNT: usize = 14;
+    pub const VERTEX_BUFFER_LMUV_STREAM: usize = 16;
+    pub const VERTEX_BUFFER_BASIS_STREAM: usize = 20;
+    pub const VERTEX_BUFFER_INFO_INDEX: usize = 24;
+    pub const VERTEX_BUFFER_DATA: usize = 44;
+
+    pub const TEX_DATA_SIZE: usize = 84;
+    pub const TEX_DATA_LOD_COUNT: usize = 45;
+    pub const TEX_DATA_WIDTH: usize = 46;
+    pub const TEX_DATA_HEIGHT: usize = 48;
+    pub const TEX_DATA_TEXTURE_BYTES: usize = 64;
+    pub const TEX_DATA_IMAGE_DATA: usize = 72;
+
+    pub const PS2_MESH_SIZE: usize = 28;
+    pub const PS2_MESH_PACKET_COUNT: usize = 2;
+    pub const PS2_MESH_PACKETS: usize = 24;
+
+    pub const PS2_PACKET_SIZE: usize = 12;
+    pub const PS2_PACKET_DATA: usize = 0;
+    pub const PS2_PACKET_QWORD_COUNT: usize = 4;
+    pub const PS2_PACKET_PART_ID: usize = 7;
+    pub const PS2_PACKET_LOD_ID: usize = 8;
+
+    pub const PS2_MATERIAL_SIZE: usize = 4;
+    pub const PS2_MATERIAL_FIRST_PACKET: usize = 0;
+    pub const PS2_MATERIAL_PACKET_COUNT: usize = 2;
+}
+
+/// Bounds checked reads from the bytes of a file
+#[derive(Clone, Copy)]
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    endian: Endian,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8], endian: Endian) -> Self {
+        Self { bytes, endian }
+    }
+
+    fn error(&self, field: &'static str, offset: usize) -> ValidationError {
+        ValidationError {
+            field,
+            offset,
+            buffer_len: self.bytes.len(),
+            problem: ValidationProblem::OutOfBounds,
+        }
+    }
+
+    /// Bytes of the region at the offset
+    pub fn bytes(
+        &self,
+        field: &'static str,
+        offset: usize,
+        length: usize,
+    ) -> Result<&'a [u8], ValidationError> {
+        offset
+            .checked_add(length)
+            .and_then(|end| self.bytes.get(offset..end))
+            .ok_or_else(|| self.error(field, offset))
+    }
+
+    fn array<const N: usize>(
+        &self,
+        field: &'static str,
+        offset: usize,
+    ) -> Result<[u8; N], ValidationError> {
+        Ok(self
+            .bytes(field, offset, N)?
+            .try_into()
+            .expect("Slice length checked"))
+    }
+
+    pub fn u8(&self, field: &'static str, offset: usize) -> Result<u8, ValidationError> {
+        Ok(self.array::<1>(field, offset)?[0])
+    }
+
+    pub fn u16(&self, field: &'static str, offset: usize) -> Result<u16, ValidationError> {
+        let value = self.array(field, offset)?;
+        Ok(match self.endian {
+            Endian::Big => u16::from_be_bytes(value),
+            Endian::Little => u16::from_le_bytes(value),
+        })
+    }
+
+    pub fn u32(&self, field: &'static str, offset: usize) -> Result<u32, ValidationError> {
+        let value = self.array(field, offset)?;
+        Ok(match self.endian {
+            Endian::Big => u32::from_be_bytes(value),
+            Endian::Little => u32::from_le_bytes(value),
+        })
+    }
+
+    pub fn f32(&self, field: &'static str, offset: usize) -> Result<f32, ValidationError> {
+        self.u32(field, offset).map(f32::from_bits)
+    }
+
+    /// Parses the record at the offset, the region of the record must
+    /// have already been checked to be within the buffer
+    fn record<T>(&self, offset: usize) -> T
+    where
+        T: for<'b> BinRead<Args<'b> = ()>,
+    {
+        let mut cursor = Cursor::new(&self.bytes[offset..]);
+        T::read_options(&mut cursor, self.endian, ()).expect("View region checked")
+    }
+
+    /// Reads the offset stored at the field, None for null offsets
+    pub fn offset(
+        &self,
+        field: &'static str,
+        offset: usize,
+    ) -> Result<Option<usize>, ValidationError> {
+        Ok(match self.u32(field, offset)? {
+            0 => None,
+            value => Some(value as usize),
+        })
+    }
+}
+
+/// Value that can be read from a region of a file that has already been
+/// checked to be within the buffer
+pub trait View<'a>: Sized {
+    /// Size of the value in the file
+    const SIZE: usize;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self;
+}
+
+/// Reads the field of a view, the region of the view was checked when it
+/// was created so the read can't fail
+fn field<T>(result: Result<T, ValidationError>) -> T {
+    result.expect("View region checked")
+}
+
+impl<'a> View<'a> for u8 {
+    const SIZE: usize = 1;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        field(reader.u8("u8", offset))
+    }
+}
+
+impl<'a> View<'a> for u16 {
+    const SIZE: usize = 2;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        field(reader.u16("u16", offset))
+    }
+}
+
+impl<'a> View<'a> for u32 {
+    const SIZE: usize = 4;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        field(reader.u32("u32", offset))
+    }
+}
+
+impl<'a> View<'a> for [f32; 2] {
+    const SIZE: usize = 8;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        [0, 4].map(|axis| field(reader.f32("[f32; 2]", offset + axis)))
+    }
+}
+
+impl<'a> View<'a> for [f32; 3] {
+    const SIZE: usize = 12;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        [0, 4, 8].map(|axis| field(reader.f32("[f32; 3]", offset + axis)))
+    }
+}
+
+impl<'a> View<'a> for PtrOffset {
+    const SIZE: usize = 4;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        PtrOffset(field(reader.u32("PtrOffset", offset)))
+    }
+}
+
+/// Records shared with the viewer are parsed through binrw
+macro_rules! record_view {
+    ($($ty:ty),*) => {
+        $(
+            impl<'a> View<'a> for $ty {
+                const SIZE: usize = <$ty>::SIZE;
+
+                fn read(reader: Reader<'a>, offset: usize) -> Self {
+                    reader.record(offset)
+                }
+            }
+        )*
+    };
+}
+
+record_view!(
+    FMeshSegment,
+    FMeshLight,
+    FMeshTexLayerID,
+    CFTexInst,
+    FTexDef
+);
+
+/// Array of values within a file, values are read when accessed
+#[derive(Clone, Copy)]
+pub struct ArrayView<'a, V> {
+    reader: Reader<'a>,
+    offset: usize,
+    len: usize,
+    /// Bytes between the start of each value
+    stride: usize,
+    _value: PhantomData<V>,
+}
+
+impl<'a, V: View<'a>> ArrayView<'a, V> {
+    /// Checks the array of `len` values at the offset is within the buffer
+    pub fn new(
+        reader: Reader<'a>,
+        field: &'static str,
+        offset: usize,
+        len: usize,
+    ) -> Result<Self, ValidationError> {
+        Self::with_stride(reader, field, offset, len, V::SIZE)
+    }
+
+    /// Checks the array of `len` values spaced `stride` bytes apart is
+    /// within the buffer, used for values interleaved with other data
+    pub fn with_stride(
+        reader: Reader<'a>,
+        field: &'static str,
+        offset: usize,
+        len: usize,
+        stride: usize,
+    ) -> Result<Self, ValidationError> {
+        if len > 0 {
+            let length = (len - 1)
+                .checked_mul(stride)
+                .and_then(|value| value.checked_add(V::SIZE))
+                .ok_or_else(|| reader.error(field, offset))?;
+            reader.bytes(field, offset, length)?;
+        }
+
+        Ok(Self {
+            reader,
+            offset,
+            len,
+            stride,
+            _value: PhantomData,
+        })
+    }
+
+    /// Offset of the first value within the file
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Number of bytes from the start of the first value to the end of the last
+    pub fn byte_len(&self) -> usize {
+        match self.len {
+            0 => 0,
+            len => (len - 1) * self.stride + V::SIZE,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<V> {
+        (index < self.len).then(|| V::read(self.reader, self.offset + index * self.stride))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = V> + 'a
+    where
+        V: 'a,
+    {
+        let (reader, offset, stride) = (self.reader, self.offset, self.stride);
+        (0..self.len).map(move |index| V::read(reader, offset + index * stride))
+    }
+}
+
+/// Follows the array offset stored at the field, None for null offsets
+fn array_at<'a, V: View<'a>>(
+    reader: Reader<'a>,
+    field: &'static str,
+    offset: usize,
+    len: usize,
+) -> Result<Option<ArrayView<'a, V>>, ValidationError> {
+    reader
+        .offset(field, offset)?
+        .map(|offset| ArrayView::new(reader, field, offset, len))
+        .transpose()
+}
+
+/// Follows the array at the stored offset, None for null offsets
+fn array_in<'a, V: View<'a>>(
+    reader: Reader<'a>,
+    field: &'static str,
+    offset: PtrOffset,
+    len: usize,
+) -> Result<Option<ArrayView<'a, V>>, ValidationError> {
+    offset
+        .offset()
+        .map(|offset| ArrayView::new(reader, field, offset as usize, len))
+        .transpose()
+}
+
+/// Follows the single value at the stored offset, None for null offsets
+fn value_in<'a, V: View<'a>>(
+    reader: Reader<'a>,
+    field: &'static str,
+    offset: PtrOffset,
+) -> Result<Option<V>, ValidationError> {
+    Ok(array_in(reader, field, offset, 1)?.and_then(|array| array.get(0)))
+}
+
+/// Follows the register array at the stored offset reading a register of
+/// each kind, None for null offsets
+fn registers_in(
+    reader: Reader,
+    field: &'static str,
+    offset: PtrOffset,
+    kinds: &[RegisterKind],
+) -> Result<Option<Vec<ShaderRegister>>, ValidationError> {
+    Ok(
+        array_in::<u32>(reader, field, offset, kinds.len())?.map(|array| {
+            kinds
+                .iter()
+                .zip(array.iter())
+                .map(|(kind, word)| ShaderRegister::from_word(*kind, word))
+                .collect()
+        }),
+    )
+}
+
+fn cf_sphere(sphere: &RawSphere) -> CFSphere {
+    let position = sphere.position;
+    CFSphere {
+        radius: sphere.radius,
+        position: CFVec3 {
+            x: position.x,
+            y: position.y,
+            z: position.z,
+        },
+    }
+}
+
+/// View of the FMesh header at the start of a file
+#[derive(Clone, Copy)]
+pub struct MeshView<'a> {
+    reader: Reader<'a>,
+    header: FMesh,
+}
+
+impl<'a> MeshView<'a> {
+    /// Creates a view of the mesh stored in the bytes using the byte order
+    /// of the platform it was compiled for
+    pub fn new(bytes: &'a [u8], endian: Endian) -> Result<Self, ValidationError> {
+        let reader = Reader::new(bytes, endian);
+        reader.bytes("FMesh", 0, FILE_HEADER_SIZE)?;
+        Ok(Self {
+            reader,
+            header: reader.record(0),
+        })
+    }
+
+    /// Header record shared with the viewer
+    pub fn header(&self) -> &FMesh {
+        &self.header
+    }
+
+    /// Length of the file in bytes
+    pub fn file_len(&self) -> usize {
+        self.reader.bytes.len()
+    }
+
+    pub fn name(&self) -> String {
+        self.header.name.as_string()
+    }
+
+    pub fn bound_sphere(&self) -> CFSphere {
+        cf_sphere(&self.header.bound_sphere)
+    }
+
+    pub fn flags(&self) -> u16 {
+        self.header.flags
+    }
+
+    pub fn lod_distances(&self) -> Vec<f32> {
+        self.header.lod_distances().to_vec()
+    }
+
+    pub fn segments(&self) -> Result<Option<ArrayView<'a, FMeshSegment>>, ValidationError> {
+        array_in(
+            self.reader,
+            "FMesh.segment_array",
+            self.header.segment_array,
+            self.header.segment_count as usize,
+        )
+    }
+
+    pub fn bones(&self) -> Result<Option<ArrayView<'a, BoneView>>, ValidationError> {
+        array_in(
+            self.reader,
+            "FMesh.bone_array",
+            self.header.bone_array,
+            self.header.bone_count as usize,
+        )
+    }
+
+    pub fn lights(&self) -> Result<Option<ArrayView<'a, FMeshLight>>, ValidationError> {
+        array_in(
+            self.reader,
+            "FMesh.light_array",
+            self.header.light_array,
+            self.header.light_count as usize,
+        )
+    }
+
+    /// Child bone indices of the skeleton, the array is sized by the child
+    /// lists of the bones as its length isn't stored
+    pub fn skeleton_indices(&self) -> Result<Option<ArrayView<'a, u8>>, ValidationError> {
+        let count = self
+            .bones()?
+            .iter()
+            .flat_map(|array| array.iter())
+            .map(|bone| {
+                let skeleton = &bone.record().skeleton;
+                skeleton.child_array_start_index as usize + skeleton.child_bone_count as usize
+            })
+            .max()
+            .unwrap_or_default();
+        array_in(
+            self.reader,
+            "FMesh.skeleton_index_array",
+            self.header.skeleton_index_array,
+            count,
+        )
+    }
+
+    pub fn materials(&self) -> Result<Option<ArrayView<'a, MaterialView<'a>>>, ValidationError> {
+        array_in(
+            self.reader,
+            "FMesh.material_array",
+            self.header.material_array,
+            self.header.material_count as usize,
+        )
+    }
+
+    pub fn tex_layers(&self) -> Result<Option<ArrayView<'a, FMeshTexLayerID>>, ValidationError> {
+        array_in(
+            self.reader,
+            "FMesh.tex_layer_array",
+            self.header.tex_layer_array,
+            self.header.tex_layer_id_count as usize,
+        )
+    }
+
+    /// Offsets of the texture instances of each flip page of the layer
+    pub fn flip_palette(
+        &self,
+        layer: &FMeshTexLayerID,
+    ) -> Result<Option<ArrayView<'a, PtrOffset>>, ValidationError> {
+        array_in(
+            self.reader,
+            "FMeshTexLayerID.flip_palette",
+            layer.flip_palette,
+            layer.flip_page_count as usize,
+        )
+    }
+
+    pub fn tex_inst(&self, offset: PtrOffset) -> Result<Option<CFTexInst>, ValidationError> {
+        value_in(self.reader, "FMeshTexLayerID.flip_palette", offset)
+    }
+
+    pub fn tex_def(&self, offset: PtrOffset) -> Result<Option<FTexDef>, ValidationError> {
+        value_in(self.reader, "CFTexInst.tex_def", offset)
+    }
+
+    /// DirectX texture data at the offset, only valid for files using the
+    /// DirectX layout
+    pub fn dx_tex_data(
+        &self,
+        offset: PtrOffset,
+    ) -> Result<Option<TexDataView<'a>>, ValidationError> {
+        value_in(self.reader, "FTexDef.tex_data", offset)
+    }
+
+    /// Texture of each flip page of the layer, None for pages without a texture
+    pub fn layer_textures(
+        &self,
+        layer: &FMeshTexLayerID,
+    ) -> Result<Vec<Option<FTexDef>>, ValidationError> {
+        let mut textures = Vec::new();
+        for tex_inst in self
+            .flip_palette(layer)?
+            .iter()
+            .flat_map(|array| array.iter())
+        {
+            let tex_def = match self.tex_inst(tex_inst)? {
+                Some(tex_inst) => self.tex_def(tex_inst.tex_def)?,
+                None => None,
+            };
+            textures.push(tex_def);
+        }
+        Ok(textures)
+    }
+
+    /// Names of the textures used by the texture layers of a material,
+    /// including every flip page of the layers
+    pub fn material_textures(
+        &self,
+        material: &MaterialView,
+    ) -> Result<Vec<String>, ValidationError> {
+        let Some(layers) = self.tex_layers()? else {
+            return Ok(Vec::new());
+        };
+
+        let mut names = Vec::new();
+        for layer in material
+            .record()
+            .tex_layer_indices()
+            .filter_map(|index| layers.get(index))
+        {
+            names.extend(
+                self.layer_textures(&layer)?
+                    .into_iter()
+                    .flatten()
+                    .map(|tex_def| tex_def.tex_info.name.as_string()),
+            );
+        }
+        Ok(names)
+    }
+
+    /// DirectX specific mesh data, only valid for files using the DirectX layout
+    pub fn dx_mesh(&self) -> Result<Option<DxMeshView<'a>>, ValidationError> {
+        value_in(self.reader, "FMesh.mesh_is", self.header.mesh_is)
+    }
+
+    /// PS2 specific mesh data, only valid for files using the PS2 layout
+    pub fn ps2_mesh(&self) -> Result<Option<Ps2MeshView<'a>>, ValidationError> {
+        value_in(self.reader, "FMesh.mesh_is", self.header.mesh_is)
+    }
+}
+
+/// View of an FMeshBone
+#[derive(Clone, Copy)]
+pub struct BoneView {
+    bone: FMeshBone,
+}
+
+impl<'a> View<'a> for BoneView {
+    const SIZE: usize = FMeshBone::SIZE;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        Self {
+            bone: reader.record(offset),
+        }
+    }
+}
+
+impl BoneView {
+    /// Bone record shared with the viewer
+    pub fn record(&self) -> &FMeshBone {
+        &self.bone
+    }
+
+    pub fn name(&self) -> String {
+        self.bone.name.as_string()
+    }
+
+    /// Index of the parent bone (None for root bones)
+    pub fn parent_index(&self) -> Option<u8> {
+        self.bone.parent_index()
+    }
+
+    pub fn part_id(&self) -> u8 {
+        self.bone.part_id
+    }
+}
+
+/// View of an FMeshMaterial
+#[derive(Clone, Copy)]
+pub struct MaterialView<'a> {
+    reader: Reader<'a>,
+    material: FMeshMaterial,
+}
+
+impl<'a> View<'a> for MaterialView<'a> {
+    const SIZE: usize = FMeshMaterial::SIZE;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        Self {
+            reader,
+            material: reader.record(offset),
+        }
+    }
+}
+
+impl MaterialView<'_> {
+    /// Material record shared with the viewer
+    pub fn record(&self) -> &FMeshMaterial {
+        &self.material
+    }
+
+    pub fn part_id_mask(&self) -> u32 {
+        self.material.part_id_mask
+    }
+
+    pub fn lod_mask(&self) -> u8 {
+        self.material.lod_mask
+    }
+
+    /// Texture layer indices used by the material (255=empty slot)
+    pub fn tex_layer_id_index(&self) -> [u8; 4] {
+        self.material.tex_layer_id_index
+    }
+
+    /// Material flags (see FMESH_MTLFLAG_* for info)
+    pub fn flags(&self) -> u16 {
+        self.material.mtl_flags
+    }
+
+    pub fn tint(&self) -> [f32; 3] {
+        let tint = self.material.material_tint;
+        [tint.red, tint.green, tint.blue]
+    }
+
+    /// Decodes the sphere bounding the verts of this material in model
+    /// space, the radius is stored relative to the mesh bounding sphere
+    pub fn bound_sphere(&self, mesh_radius: f32) -> CFSphere {
+        cf_sphere(&self.material.bound_sphere(mesh_radius))
+    }
+
+    /// Registers of the light shader, the length and kind of each comes
+    /// from the table entry of the shader. None when the material has no
+    /// registers or its shader isn't in the table
+    pub fn light_registers(
+        &self,
+        table: &RegisterTable,
+    ) -> Result<Option<Vec<ShaderRegister>>, ValidationError> {
+        let Some(kinds) = table.light(self.material.light_shader_index) else {
+            return Ok(None);
+        };
+        registers_in(
+            self.reader,
+            "FMeshMaterial.shader_light_registers",
+            self.material.shader_light_registers,
+            kinds,
+        )
+    }
+
+    /// Registers of the surface shader, see [MaterialView::light_registers]
+    pub fn surface_registers(
+        &self,
+        table: &RegisterTable,
+    ) -> Result<Option<Vec<ShaderRegister>>, ValidationError> {
+        let Some(kinds) = table.surface(self.material.surface_shader_index) else {
+            return Ok(None);
+        };
+        registers_in(
+            self.reader,
+            "FMeshMaterial.shader_surface_registers",
+            self.material.shader_surface_registers,
+            kinds,
+        )
+    }
+}
+
+impl<'a> MaterialView<'a> {
+    /// PS2 platform data of the material, only valid for files using the PS2 layout
+    pub fn ps2_material(&self) -> Result<Option<Ps2MaterialView<'a>>, ValidationError> {
+        value_in(
+            self.reader,
+            "FMeshMaterial.platform_data",
+            self.material.platform_data,
+        )
+    }
+
+    /// DirectX platform data of the material, only valid for files using
+    /// the DirectX layout
+    pub fn dx_material(&self) -> Result<Option<DxMaterialView<'a>>, ValidationError> {
+        value_in(
+            self.reader,
+            "FMeshMaterial.platform_data",
+            self.material.platform_data,
+        )
+    }
+}
+
+/// View of a DxMeshMaterial
+#[derive(Clone, Copy)]
+pub struct DxMaterialView<'a> {
+    reader: Reader<'a>,
+    offset: usize,
+}
+
+impl<'a> View<'a> for DxMaterialView<'a> {
+    const SIZE: usize = layout::DX_MATERIAL_SIZE;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        Self { reader, offset }
+    }
+}
+
+impl<'a> DxMaterialView<'a> {
+    pub fn clusters(&self) -> Result<Option<ArrayView<'a, DxClusterView<'a>>>, ValidationError> {
+        let count = field(self.reader.u32(
+            "DxMeshMaterial.cluster_count",
+            self.offset + layout::DX_MATERIAL_CLUSTER_COUNT,
+        ));
+        array_at(
+            self.reader,
+            "DxMeshMaterial.cluster",
+            self.offset + layout::DX_MATERIAL_CLUSTER,
+            count as usize,
+        )
+    }
+}
+
+/// View of a DxMeshCluster
+#[derive(Clone, Copy)]
+pub struct DxClusterView<'a> {
+    reader: Reader<'a>,
+    offset: usize,
+}
+
+impl<'a> View<'a> for DxClusterView<'a> {
+    const SIZE: usize = layout::DX_CLUSTER_SIZE;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        Self { reader, offset }
+    }
+}
+
+impl<'a> DxClusterView<'a> {
+    pub fn vertex_buffer_index(&self) -> u8 {
+        field(self.reader.u8(
+            "DxMeshCluster.vertex_buffer_index",
+            self.offset + layout::DX_CLUSTER_VERTEX_BUFFER_INDEX,
+        ))
+    }
+
+    pub fn index_buffer_index(&self) -> u8 {
+        field(self.reader.u8(
+            "DxMeshCluster.index_buffer_index",
+            self.offset + layout::DX_CLUSTER_INDEX_BUFFER_INDEX,
+        ))
+    }
+
+    pub fn part_id(&self) -> u8 {
+        field(self.reader.u8(
+            "DxMeshCluster.part_id",
+            self.offset + layout::DX_CLUSTER_PART_ID,
+        ))
+    }
+
+    pub fn lod_id(&self) -> u8 {
+        field(self.reader.u8(
+            "DxMeshCluster.lod_id",
+            self.offset + layout::DX_CLUSTER_LOD_ID,
+        ))
+    }
+
+    /// Push buffer of the cluster, the size isn't stored so only the offset
+    /// is available
+    pub fn push_buffer(&self) -> PtrOffset {
+        PtrOffset(field(self.reader.u32(
+            "DxMeshCluster.push_buffer",
+            self.offset + layout::DX_CLUSTER_PUSH_BUFFER,
+        )))
+    }
+
+    /// Start index and triangle count of the triangle list
+    pub fn tri_list(&self) -> (usize, usize) {
+        let count = field(self.reader.u16(
+            "DxMeshCluster.tri_list.tri_count",
+            self.offset + layout::DX_CLUSTER_TRI_COUNT,
+        ));
+        let start = field(self.reader.u16(
+            "DxMeshCluster.tri_list.start_vindex",
+            self.offset + layout::DX_CLUSTER_TRI_START_VINDEX,
+        ));
+        (start as usize, count as usize)
+    }
+
+    pub fn strips(&self) -> Result<Option<ArrayView<'a, DxStripView<'a>>>, ValidationError> {
+        let count = field(self.reader.u16(
+            "DxMeshCluster.strip_count",
+            self.offset + layout::DX_CLUSTER_STRIP_COUNT,
+        ));
+        array_at(
+            self.reader,
+            "DxMeshCluster.mesh_strip",
+            self.offset + layout::DX_CLUSTER_MESH_STRIP,
+            count as usize,
+        )
+    }
+}
+
+/// View of a DxMeshStrip
+#[derive(Clone, Copy)]
+pub struct DxStripView<'a> {
+    reader: Reader<'a>,
+    offset: usize,
+}
+
+impl<'a> View<'a> for DxStripView<'a> {
+    const SIZE: usize = layout::DX_STRIP_SIZE;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        Self { reader, offset }
+    }
+}
+
+impl DxStripView<'_> {
+    pub fn tri_count(&self) -> u8 {
+        field(self.reader.u8(
+            "DxMeshStrip.tri_count",
+            self.offset + layout::DX_STRIP_TRI_COUNT,
+        ))
+    }
+
+    pub fn start_vindex(&self) -> u16 {
+        field(self.reader.u16(
+            "DxMeshStrip.start_vindex",
+            self.offset + layout::DX_STRIP_START_VINDEX,
+        ))
+    }
+}
+
+/// View of a DxMesh
+#[derive(Clone, Copy)]
+pub struct DxMeshView<'a> {
+    reader: Reader<'a>,
+    offset: usize,
+}
+
+impl<'a> View<'a> for DxMeshView<'a> {
+    const SIZE: usize = layout::DX_MESH_SIZE;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        Self { reader, offset }
+    }
+}
+
+impl<'a> DxMeshView<'a> {
+    fn index_buffer_count(&self) -> usize {
+        field(self.reader.u8(
+            "DxMesh.index_buffer_count",
+            self.offset + layout::DX_MESH_INDEX_BUFFER_COUNT,
+        )) as usize
+    }
+
+    pub fn vertex_buffers(
+        &self,
+    ) -> Result<Option<ArrayView<'a, VertexBufferView<'a>>>, ValidationError> {
+        array_at(
+            self.reader,
+            "DxMesh.vertex_buffers",
+            self.offset + layout::DX_MESH_VERTEX_BUFFERS,
+            self.vertex_buffer_count(),
+        )
+    }
+
+    fn vertex_buffer_count(&self) -> usize {
+        field(self.reader.u8(
+            "DxMesh.vertex_buffer_count",
+            self.offset + layout::DX_MESH_VERTEX_BUFFER_COUNT,
+        )) as usize
+    }
+
+    /// Offsets of the collision copy of each vertex buffer
+    pub fn collision_buffers(&self) -> Result<Option<ArrayView<'a, PtrOffset>>, ValidationError> {
+        array_at(
+            self.reader,
+            "DxMesh.coll_vertex_buffer",
+            self.offset + layout::DX_MESH_COLL_VERTEX_BUFFER,
+            self.vertex_buffer_count(),
+        )
+    }
+
+    /// Collision copy of the positions of the vertex buffer at the index,
+    /// None when the mesh has no collision vertices for the buffer
+    pub fn collision_vertices(
+        &self,
+        index: usize,
+    ) -> Result<Option<ArrayView<'a, [f32; 3]>>, ValidationError> {
+        let (Some(buffers), Some(vertex_buffers)) =
+            (self.collision_buffers()?, self.vertex_buffers()?)
+        else {
+            return Ok(None);
+        };
+        let (Some(offset), Some(buffer)) = (buffers.get(index), vertex_buffers.get(index)) else {
+            return Ok(None);
+        };
+
+        array_in(
+            self.reader,
+            "DxMesh.coll_vertex_buffer[]",
+            offset,
+            buffer.vertex_count() as usize,
+        )
+    }
+
+    /// Number of indices in each index buffer
+    pub fn index_counts(&self) -> Result<Option<ArrayView<'a, u16>>, ValidationError> {
+        array_at(
+            self.reader,
+            "DxMesh.indicies_counts",
+            self.offset + layout::DX_MESH_INDICIES_COUNTS,
+            self.index_buffer_count(),
+        )
+    }
+
+    /// Offsets of each index buffer
+    pub fn index_buffer_offsets(
+        &self,
+    ) -> Result<Option<ArrayView<'a, PtrOffset>>, ValidationError> {
+        array_at(
+            self.reader,
+            "DxMesh.index_buffer",
+            self.offset + layout::DX_MESH_INDEX_BUFFER,
+            self.index_buffer_count(),
+        )
+    }
+
+    /// Indices of the index buffer at the index, None when the buffer is
+    /// missing or null
+    pub fn index_buffer(
+        &self,
+        index: usize,
+    ) -> Result<Option<ArrayView<'a, u16>>, ValidationError> {
+        if index >= self.index_buffer_count() {
+            return Ok(None);
+        }
+        let (Some(counts), Some(buffers)) = (
+            self.index_counts()?,
+            self.reader.offset(
+                "DxMesh.index_buffer",
+                self.offset + layout::DX_MESH_INDEX_BUFFER,
+            )?,
+        ) else {
+            return Ok(None);
+        };
+
+        array_at(
+            self.reader,
+            "DxMesh.index_buffer[]",
+            buffers + index * 4,
+            counts.get(index).unwrap_or_default() as usize,
+        )
+    }
+
+    /// Indices of every index buffer, the lengths are read from the counts
+    /// array so both arrays are followed
+    pub fn index_buffers(&self) -> Result<Vec<ArrayView<'a, u16>>, ValidationError> {
+        let counts = self.index_counts()?;
+        let Some(buffers) = self.reader.offset(
+            "DxMesh.index_buffer",
+            self.offset + layout::DX_MESH_INDEX_BUFFER,
+        )?
+        else {
+            return Ok(Vec::new());
+        };
+        let counts = counts.ok_or_else(|| self.reader.error("DxMesh.indicies_counts", 0))?;
+
+        counts
+            .iter()
+            .enumerate()
+            .filter_map(|(index, length)| {
+                array_at(
+                    self.reader,
+                    "DxMesh.index_buffer[]",
+                    buffers + index * 4,
+                    length as usize,
+                )
+                .transpose()
+            })
+            .collect()
+    }
+}
+
+/// View of a DxVertexBufferDescriptor
+#[derive(Clone, Copy)]
+pub struct VertexBufferView<'a> {
+    reader: Reader<'a>,
+    offset: usize,
+}
+
+impl<'a> View<'a> for VertexBufferView<'a> {
+    const SIZE: usize = layout::VERTEX_BUFFER_SIZE;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        Self { reader, offset }
+    }
+}
+
+impl<'a> VertexBufferView<'a> {
+    pub fn vertex_count(&self) -> u32 {
+        field(self.reader.u32(
+            "DxVertexBufferDescriptor.vertex_count",
+            self.offset + layout::VERTEX_BUFFER_VERTEX_COUNT,
+        ))
+    }
+
+    pub fn bytes_per_vertex(&self) -> u16 {
+        field(self.reader.u16(
+            "DxVertexBufferDescriptor.bytes_per_vertex",
+            self.offset + layout::VERTEX_BUFFER_BYTES_PER_VERTEX,
+        ))
+    }
+
+    /// Number of lightmap texture coordinate pairs of each vertex
+    pub fn lmtc_count(&self) -> u16 {
+        field(self.reader.u16(
+            "DxVertexBufferDescriptor.lmtc_count",
+            self.offset + layout::VERTEX_BUFFER_LMTC_COUNT,
+        ))
+    }
+
+    /// Index into the vertex buffer info table of the layout of the
+    /// vertices (-1=shader)
+    pub fn info_index(&self) -> i8 {
+        field(self.reader.u8(
+            "DxVertexBufferDescriptor.info_index",
+            self.offset + layout::VERTEX_BUFFER_INFO_INDEX,
+        )) as i8
+    }
+
+    /// Bytes of every vertex in the buffer
+    pub fn vertex_data(&self) -> Result<Option<ArrayView<'a, u8>>, ValidationError> {
+        array_at(
+            self.reader,
+            "DxVertexBufferDescriptor.vertex_buffer",
+            self.offset + layout::VERTEX_BUFFER_DATA,
+            (self.vertex_count() as usize).saturating_mul(self.bytes_per_vertex() as usize),
+        )
+    }
+
+    /// Lightmap texture coordinates, each vertex has [Self::lmtc_count] pairs
+    pub fn lmuv_stream(&self) -> Result<Option<ArrayView<'a, [f32; 2]>>, ValidationError> {
+        array_at(
+            self.reader,
+            "DxVertexBufferDescriptor.lmuv_stream",
+            self.offset + layout::VERTEX_BUFFER_LMUV_STREAM,
+            (self.vertex_count() as usize).saturating_mul(self.lmtc_count() as usize),
+        )
+    }
+
+    /// Stream of basis vectors, the size isn't stored so only the offset
+    /// is available
+    pub fn basis_stream(&self) -> PtrOffset {
+        PtrOffset(field(self.reader.u32(
+            "DxVertexBufferDescriptor.basis_stream",
+            self.offset + layout::VERTEX_BUFFER_BASIS_STREAM,
+        )))
+    }
+
+    /// Positions of the vertices, every fixed vertex layout starts with
+    /// the position so they are read from the start of each vertex
+    pub fn positions(&self) -> Result<Option<ArrayView<'a, [f32; 3]>>, ValidationError> {
+        const FIELD: &str = "DxVertexBufferDescriptor.vertex_buffer";
+
+        let Some(offset) = self
+            .reader
+            .offset(FIELD, self.offset + layout::VERTEX_BUFFER_DATA)?
+        else {
+            return Ok(None);
+        };
+
+        let stride = self.bytes_per_vertex() as usize;
+        if stride < <[f32; 3]>::SIZE {
+            return Err(self.reader.error(FIELD, offset));
+        }
+
+        ArrayView::with_stride(
+            self.reader,
+            FIELD,
+            offset,
+            self.vertex_count() as usize,
+            stride,
+        )
+        .map(Some)
+    }
+}
+
+/// View of the DirectX FTexData
+#[derive(Clone, Copy)]
+pub struct TexDataView<'a> {
+    reader: Reader<'a>,
+    offset: usize,
+}
+
+impl<'a> View<'a> for TexDataView<'a> {
+    const SIZE: usize = layout::TEX_DATA_SIZE;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        Self { reader, offset }
+    }
+}
+
+impl<'a> TexDataView<'a> {
+    /// Image data of the texture, sized by the approximate bytes consumed
+    /// by the texture
+    pub fn image_data(&self) -> Result<Option<ArrayView<'a, u8>>, ValidationError> {
+        let texture_bytes = field(self.reader.u32(
+            "FTexData.texture_bytes",
+            self.offset + layout::TEX_DATA_TEXTURE_BYTES,
+        ));
+        array_at(
+            self.reader,
+            "FTexData.image_data",
+            self.offset + layout::TEX_DATA_IMAGE_DATA,
+            texture_bytes as usize,
+        )
+    }
+}
+
+/// View of a Ps2Mesh
+#[derive(Clone, Copy)]
+pub struct Ps2MeshView<'a> {
+    reader: Reader<'a>,
+    offset: usize,
+}
+
+impl<'a> View<'a> for Ps2MeshView<'a> {
+    const SIZE: usize = layout::PS2_MESH_SIZE;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        Self { reader, offset }
+    }
+}
+
+impl<'a> Ps2MeshView<'a> {
+    pub fn packets(&self) -> Result<Option<ArrayView<'a, Ps2PacketView<'a>>>, ValidationError> {
+        let count = field(self.reader.u16(
+            "Ps2Mesh.packet_count",
+            self.offset + layout::PS2_MESH_PACKET_COUNT,
+        ));
+        array_at(
+            self.reader,
+            "Ps2Mesh.packets",
+            self.offset + layout::PS2_MESH_PACKETS,
+            count as usize,
+        )
+    }
+}
+
+/// View of a Ps2MeshPacket
+#[derive(Clone, Copy)]
+pub struct Ps2PacketView<'a> {
+    reader: Reader<'a>,
+    offset: usize,
+}
+
+impl<'a> View<'a> for Ps2PacketView<'a> {
+    const SIZE: usize = layout::PS2_PACKET_SIZE;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        Self { reader, offset }
+    }
+}
+
+impl<'a> Ps2PacketView<'a> {
+    pub fn part_id(&self) -> u8 {
+        field(self.reader.u8(
+            "Ps2MeshPacket.part_id",
+            self.offset + layout::PS2_PACKET_PART_ID,
+        ))
+    }
+
+    pub fn lod_id(&self) -> u8 {
+        field(self.reader.u8(
+            "Ps2MeshPacket.lod_id",
+            self.offset + layout::PS2_PACKET_LOD_ID,
+        ))
+    }
+
+    /// VIF codes of the packet, the length is stored in quadwords
+    pub fn data(&self) -> Result<Option<&'a [u8]>, ValidationError> {
+        const FIELD: &str = "Ps2MeshPacket.data";
+
+        let qword_count = field(self.reader.u16(
+            "Ps2MeshPacket.qword_count",
+            self.offset + layout::PS2_PACKET_QWORD_COUNT,
+        ));
+        self.reader
+            .offset(FIELD, self.offset + layout::PS2_PACKET_DATA)?
+            .map(|offset| self.reader.bytes(FIELD, offset, qword_count as usize * 16))
+            .transpose()
+    }
+}
+
+/// View of a Ps2MeshMaterial
+#[derive(Clone, Copy)]
+pub struct Ps2MaterialView<'a> {
+    reader: Reader<'a>,
+    offset: usize,
+}
+
+impl<'a> View<'a> for Ps2MaterialView<'a> {
+    const SIZE: usize = layout::PS2_MATERIAL_SIZE;
+
+    fn read(reader: Reader<'a>, offset: usize) -> Self {
+        Self { reader, offset }
+    }
+}
+
+impl Ps2MaterialView<'_> {
+    /// Indices of the packets drawing the material
+    pub fn packet_range(&self) -> std::ops::Range<usize> {
+        let start = field(self.reader.u16(
+            "Ps2MeshMaterial.first_packet",
+            self.offset + layout::PS2_MATERIAL_FIRST_PACKET,
+        )) as usize;
+        let count = field(self.reader.u16(
+            "Ps2MeshMaterial.packet_count",
+            self.offset + layout::PS2_MATERIAL_PACKET_COUNT,
+        )) as usize;
+        start..start + count
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use binrw::Endian;
+
+    use crate::{
+        builder::FileWriter,
+        export::ExportGeometry,
+        offsets::{ValidationError, ValidationProblem},
+        raw::ps2::decode_vif_packet,
+        registers::{RegisterTable, ShaderRegister},
+    };
+
+    use openglitch_formats::{
+        mesh::FMeshTexLayerID,
+        texture::{CFTexInst, FTexDef},
+    };
+
+    use crate::survey::FILE_MATERIAL_SIZE;
+
+    use super::{layout, MeshView, FILE_HEADER_SIZE};
+
+    /// Builds a file with one bone, one material and a DirectX mesh
+    /// holding a single triangle
+    fn view_file(endian: Endian) -> Vec<u8> {
+        let bone = FILE_HEADER_SIZE;
+        let material = bone + layout::BONE_SIZE;
+        let dx_mesh = material + FILE_MATERIAL_SIZE;
+        let vertex_buffer = dx_mesh + layout::DX_MESH_SIZE;
+        let vertices = vertex_buffer + layout::VERTEX_BUFFER_SIZE;
+        let counts = vertices + 3 * 20;
+        let index_buffers = counts + 4;
+        let indices = index_buffers + 4;
+        let dx_material = indices + 8;
+        let cluster = dx_material + layout::DX_MATERIAL_SIZE;
+
+        let mut file = FileWriter {
+            bytes: vec![0; cluster + layout::DX_CLUSTER_SIZE],
+            endian,
+        };
+
+        file.put(layout::MESH_NAME, b"mesh");
+        file.put_f32(layout::MESH_BOUND_SPHERE, 2.0);
+        file.bytes[layout::MESH_BONE_COUNT] = 1;
+        file.bytes[layout::MESH_MATERIAL_COUNT] = 1;
+        file.bytes[layout::MESH_LOD_COUNT] = 1;
+        file.put_f32(layout::MESH_LOD_DISTANCE, 50.0);
+        file.put_u32(layout::MESH_BONE_ARRAY, bone as u32);
+        file.put_u32(layout::MESH_MATERIAL_ARRAY, material as u32);
+        file.put_u32(layout::MESH_IS, dx_mesh as u32);
+
+        file.put(bone, b"root");
+        file.bytes[bone + layout::BONE_PARENT_INDEX] = 255;
+        file.bytes[bone + layout::BONE_PART_ID] = 3;
+
+        file.put_u32(material + layout::MATERIAL_PART_ID_MASK, 0b1000);
+        file.put_u16(material + layout::MATERIAL_FLAGS, 0x40);
+        file.bytes[material + layout::MATERIAL_COMPRESSED_RADIUS] = 255;
+        file.put_f32(material + layout::MATERIAL_TINT, 0.5);
+        file.put_u32(
+            material + layout::MATERIAL_PLATFORM_DATA,
+            dx_material as u32,
+        );
+
+        file.put_u32(dx_material + layout::DX_MATERIAL_CLUSTER, cluster as u32);
+        file.put_u32(dx_material + layout::DX_MATERIAL_CLUSTER_COUNT, 1);
+        file.bytes[cluster + layout::DX_CLUSTER_LOD_ID] = 2;
+        file.put_u16(cluster + layout::DX_CLUSTER_TRI_COUNT, 1);
+
+        file.bytes[dx_mesh + layout::DX_MESH_VERTEX_BUFFER_COUNT] = 1;
+        file.bytes[dx_mesh + layout::DX_MESH_INDEX_BUFFER_COUNT] = 1;
+        file.put_u32(
+            dx_mesh + layout::DX_MESH_VERTEX_BUFFERS,
+            vertex_buffer as u32,
+        );
+        file.put_u32(dx_mesh + layout::DX_MESH_INDICIES_COUNTS, counts as u32);
+        file.put_u32(dx_mesh + layout::DX_MESH_INDEX_BUFFER, index_buffers as u32);
+
+        file.put_u32(vertex_buffer + layout::VERTEX_BUFFER_VERTEX_COUNT, 3);
+        file.put_u16(vertex_buffer + layout::VERTEX_BUFFER_BYTES_PER_VERTEX, 20);
+        file.put_u32(vertex_buffer + layout::VERTEX_BUFFER_DATA, vertices as u32);
+        file.put_f32(vertices + 20, 1.0);
+        file.put_f32(vertices + 40 + 4, 1.0);
+
+        file.put_u16(counts, 3);
+        file.put_u32(index_buffers, indices as u32);
+        for (index, value) in [0, 1, 2].into_iter().enumerate() {
+            file.put_u16(indices + index * 2, value);
+        }
+
+        file.bytes
+    }
+
+    #[test]
+    fn test_view() {
+        for endian in [Endian::Little, Endian::Big] {
+            let bytes = view_file(endian);
+            let mesh = MeshView::new(&bytes, endian).unwrap();
+
+            assert_eq!(mesh.name(), "mesh");
+            assert_eq!(mesh.lod_distances(), vec![50.0]);
+
+            let bone = mesh.bones().unwrap().unwrap().get(0).unwrap();
+            assert_eq!(bone.name(), "root");
+            assert_eq!(bone.parent_index(), None);
+            assert_eq!(bone.part_id(), 3);
+
+            let material = mesh.materials().unwrap().unwrap().get(0).unwrap();
+            assert_eq!(material.part_id_mask(), 0b1000);
+            assert_eq!(material.flags(), 0x40);
+            assert_eq!(material.tint(), [0.5, 0.0, 0.0]);
+            assert_eq!(material.bound_sphere(2.0).radius, 2.0);
+
+            let dx_mesh = mesh.dx_mesh().unwrap().unwrap();
+            let vertex_buffer = dx_mesh.vertex_buffers().unwrap().unwrap().get(0).unwrap();
+            let positions: Vec<[f32; 3]> =
+                vertex_buffer.positions().unwrap().unwrap().iter().collect();
+            assert_eq!(
+                positions,
+                vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+            );
+
+            let indices: Vec<Vec<u16>> = dx_mesh
+                .index_buffers()
+                .unwrap()
+                .iter()
+                .map(|buffer| buffer.iter().collect())
+                .collect();
+            assert_eq!(indices, vec![vec![0, 1, 2]]);
+
+            let cluster = material
+                .dx_material()
+                .unwrap()
+                .unwrap()
+                .clusters()
+                .unwrap()
+                .unwrap()
+                .get(0)
+                .unwrap();
+            assert_eq!(cluster.lod_id(), 2);
+            assert_eq!(cluster.tri_list(), (0, 1));
+            assert!(cluster.strips().unwrap().is_none());
+
+            // Geometry exported through the views matches the stored triangle
+            let geometry = ExportGeometry::from_dx_view(&mesh).unwrap();
+            assert_eq!(geometry.positions, positions);
+            assert_eq!(geometry.groups[0].triangles, vec![[0, 1, 2]]);
+        }
+    }
+
+    #[test]
+    fn test_material_textures() {
+        for endian in [Endian::Little, Endian::Big] {
+            let mut file = FileWriter {
+                bytes: view_file(endian),
+                endian,
+            };
+            let tex_layer = file.bytes.len();
+            let palette = tex_layer + FMeshTexLayerID::SIZE;
+            let tex_insts = palette + 8;
+            let tex_defs = tex_insts + 2 * CFTexInst::SIZE;
+
+            file.bytes[layout::MESH_TEX_LAYER_ID_COUNT] = 1;
+            file.put_u32(layout::MESH_TEX_LAYER_ARRAY, tex_layer as u32);
+            let material = file.bytes[layout::MESH_MATERIAL_ARRAY..][..4].to_vec();
+            let material = match endian {
+                Endian::Big => u32::from_be_bytes(material.try_into().unwrap()),
+                Endian::Little => u32::from_le_bytes(material.try_into().unwrap()),
+            } as usize;
+            file.put(
+                material + layout::MATERIAL_TEX_LAYER_ID_INDEX,
+                &[0, 255, 255, 255],
+            );
+
+            file.bytes[tex_layer + layout::TEX_LAYER_FLIP_PAGE_COUNT] = 2;
+            file.put_u32(tex_layer + layout::TEX_LAYER_FLIP_PALETTE, palette as u32);
+            for (page, name) in [b"page0".as_slice(), b"page1"].into_iter().enumerate() {
+                let tex_inst = tex_insts + page * CFTexInst::SIZE;
+                let tex_def = tex_defs + page * FTexDef::SIZE;
+                file.put_u32(palette + page * 4, tex_inst as u32);
+                file.put_u32(tex_inst, tex_def as u32);
+                // Texture name is padded to the end of the definition
+                file.put(tex_def, name);
+                file.put(tex_def + FTexDef::SIZE - 1, &[0]);
+            }
+
+            let mesh = MeshView::new(&file.bytes, endian).unwrap();
+            let material = mesh.materials().unwrap().unwrap().get(0).unwrap();
+            assert_eq!(
+                mesh.material_textures(&material).unwrap(),
+                vec!["page0".to_string(), "page1".to_string()]
+            );
+        }
+    }
+
+    #[test]
+    fn test_material_registers() {
+        let table = RegisterTable::from_ini(concat!(
+            "[light.2]\nregisters=value,constant\n",
+            "[surface.5]\nregisters=tex_layer,constant,constant\n"
+        ))
+        .unwrap();
+
+        for endian in [Endian::Little, Endian::Big] {
+            let mut file = FileWriter {
+                bytes: view_file(endian),
+                endian,
+            };
+            let material = FILE_HEADER_SIZE + layout::BONE_SIZE;
+            let registers = file.bytes.len();
+            file.bytes.resize(registers + 8, 0);
+
+            file.bytes[material + layout::MATERIAL_LIGHT_SHADER_INDEX] = 2;
+            file.put_u32(
+                material + layout::MATERIAL_SHADER_LIGHT_REGISTERS,
+                registers as u32,
+            );
+            file.put_u32(registers, 7);
+            file.put_f32(registers + 4, 0.5);
+
+            let mesh = MeshView::new(&file.bytes, endian).unwrap();
+            let material = mesh.materials().unwrap().unwrap().get(0).unwrap();
+            assert_eq!(
+                material.light_registers(&table).unwrap(),
+                Some(vec![
+                    ShaderRegister::Value(7),
+                    ShaderRegister::Constant(0.5)
+                ])
+            );
+            // The surface shader isn't in the table
+            assert_eq!(material.surface_registers(&table).unwrap(), None);
+
+            // Registers past the end of the file are rejected rather than read
+            file.put_u16(material + layout::MATERIAL_SURFACE_SHADER_INDEX, 5);
+            file.put_u32(
+                material + layout::MATERIAL_SHADER_SURFACE_REGISTERS,
+                registers as u32,
+            );
+            let mesh = MeshView::new(&file.bytes, endian).unwrap();
+            let material = mesh.materials().unwrap().unwrap().get(0).unwrap();
+            assert_eq!(
+                material
+                    .surface_registers(&table)
+                    .err()
+                    .map(|err| err.field),
+                Some("FMeshMaterial.shader_surface_registers")
+            );
+        }
+    }
+
+    /// Builds a PS2 file with one material drawing a single packet that
+    /// uploads one triangle
+    fn ps2_file() -> Vec<u8> {
+        let material = FILE_HEADER_SIZE;
+        let ps2_material = material + FILE_MATERIAL_SIZE;
+        let ps2_mesh = ps2_material + layout::PS2_MATERIAL_SIZE;
+        let packet = ps2_mesh + layout::PS2_MESH_SIZE;
+        let data = packet + layout::PS2_PACKET_SIZE;
+
+        let mut file = FileWriter {
+            bytes: vec![0; data + 48],
+            endian: Endian::Little,
+        };
+
+        file.bytes[layout::MESH_MATERIAL_COUNT] = 1;
+        file.put_u32(layout::MESH_MATERIAL_ARRAY, material as u32);
+        file.put_u32(layout::MESH_IS, ps2_mesh as u32);
+
+        file.put_u32(
+            material + layout::MATERIAL_PLATFORM_DATA,
+            ps2_material as u32,
+        );
+        file.put_u16(ps2_material + layout::PS2_MATERIAL_PACKET_COUNT, 1);
+
+        file.put_u16(ps2_mesh + layout::PS2_MESH_PACKET_COUNT, 1);
+        file.put_u32(ps2_mesh + layout::PS2_MESH_PACKETS, packet as u32);
+
+        file.put_u32(packet + layout::PS2_PACKET_DATA, data as u32);
+        file.put_u16(packet + layout::PS2_PACKET_QWORD_COUNT, 3);
+        file.bytes[packet + layout::PS2_PACKET_LOD_ID] = 1;
+
+        // UNPACK V3-32 of the three positions followed by MSCAL, the rest
+        // of the last quadword is NOPs
+        file.put_u32(data, 0x6803_0000);
+        for (index, value) in [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+            .into_iter()
+            .enumerate()
+        {
+            file.put_f32(data + 4 + index * 4, value);
+        }
+        file.put_u32(data + 40, 0x1400_0000);
+
+        file.bytes
+    }
+
+    #[test]
+    fn test_ps2_view() {
+        let bytes = ps2_file();
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+
+        let material = mesh.materials().unwrap().unwrap().get(0).unwrap();
+        let range = material.ps2_material().unwrap().unwrap().packet_range();
+        assert_eq!(range, 0..1);
+
+        let packets = mesh
+            .ps2_mesh()
+            .unwrap()
+            .unwrap()
+            .packets()
+            .unwrap()
+            .unwrap();
+        let packet = packets.get(0).unwrap();
+        assert_eq!(packet.lod_id(), 1);
+
+        let data = packet.data().unwrap().unwrap();
+        assert_eq!(data.len(), 48);
+        let batches = decode_vif_packet(data).unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].positions[1], [1.0, 0.0, 0.0]);
+        assert_eq!(batches[0].triangles(), vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn test_offset_past_end() {
+        let mut bytes = view_file(Endian::Little);
+        let length = bytes.len();
+        bytes[layout::MESH_MATERIAL_ARRAY..][..4]
+            .copy_from_slice(&(length as u32 - 8).to_le_bytes());
+
+        let mesh = MeshView::new(&bytes, Endian::Little).unwrap();
+        assert_eq!(
+            mesh.materials().err(),
+            Some(ValidationError {
+                field: "FMesh.material_array",
+                offset: length - 8,
+                buffer_len: length,
+                problem: ValidationProblem::OutOfBounds,
+            })
+        );
+        // Sections not pointing past the end can still be read
+        assert!(mesh.bones().is_ok());
+    }
+
+    #[test]
+    fn test_truncated_header() {
+        let bytes = view_file(Endian::Little);
+        assert!(MeshView::new(&bytes[..64], Endian::Little).is_err());
+    }
+
+    /// File layout matches the host structures when pointers are 32-bit
+    #[test]
+    #[cfg(target_pointer_width = "32")]
+    fn test_host_layout() {
+        use std::mem::{offset_of, size_of};
+
+        use crate::{
+            raw::{
+                dx::{
+                    DxMesh, DxMeshCluster, DxMeshMaterial, DxMeshStrip, DxVertexBufferDescriptor,
+                },
+                ps2::{Ps2Mesh, Ps2MeshMaterial, Ps2MeshPacket},
+            },
+            st::{FMesh, FMeshBone, FMeshMaterial},
+        };
+
+        assert_eq!(size_of::<FMesh>(), FILE_HEADER_SIZE);
+        assert_eq!(offset_of!(FMesh, bone_array), layout::MESH_BONE_ARRAY);
+        assert_eq!(
+            offset_of!(FMesh, material_array),
+            layout::MESH_MATERIAL_ARRAY
+        );
+        assert_eq!(offset_of!(FMesh, mesh_is), layout::MESH_IS);
+        assert_eq!(size_of::<FMeshBone>(), layout::BONE_SIZE);
+        assert_eq!(size_of::<FMeshMaterial>(), FILE_MATERIAL_SIZE);
+        assert_eq!(
+            offset_of!(FMeshMaterial, material_tint),
+            layout::MATERIAL_TINT
+        );
+        assert_eq!(size_of::<DxMesh>(), layout::DX_MESH_SIZE);
+        assert_eq!(
+            offset_of!(DxMesh, index_buffer),
+            layout::DX_MESH_INDEX_BUFFER
+        );
+        assert_eq!(size_of::<DxMeshMaterial>(), layout::DX_MATERIAL_SIZE);
+        assert_eq!(size_of::<DxMeshCluster>(), layout::DX_CLUSTER_SIZE);
+        assert_eq!(
+            offset_of!(DxMeshCluster, mesh_strip),
+            layout::DX_CLUSTER_MESH_STRIP
+        );
+        assert_eq!(size_of::<DxMeshStrip>(), layout::DX_STRIP_SIZE);
+        assert_eq!(
+            size_of::<DxVertexBufferDescriptor>(),
+            layout::VERTEX_BUFFER_SIZE
+        );
+        assert_eq!(
+            offset_of!(DxVertexBufferDescriptor, vertex_buffer),
+            layout::VERTEX_BUFFER_DATA
+        );
+        assert_eq!(
+            offset_of!(FMeshMaterial, platform_data),
+            layout::MATERIAL_PLATFORM_DATA
+        );
+        assert_eq!(size_of::<Ps2Mesh>(), layout::PS2_MESH_SIZE);
+        assert_eq!(offset_of!(Ps2Mesh, packets), layout::PS2_MESH_PACKETS);
+        assert_eq!(size_of::<Ps2MeshPacket>(), layout::PS2_PACKET_SIZE);
+        assert_eq!(offset_of!(Ps2MeshPacket, lod_id), layout::PS2_PACKET_LOD_ID);
+        assert_eq!(size_of::<Ps2MeshMaterial>(), layout::PS2_MATERIAL_SIZE);
+    }
+}
@@ -0,0 +1,35 @@
+//! Cooperative cancellation of long loads and exports. The work checks the
+//! token between steps and stops with [Cancelled] once it has been cancelled
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("cancelled")]
+pub struct Cancelled;
+
+/// Shared flag requesting work to stop, clones share the same flag
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Fails with [Cancelled] once the token has been cancelled
+    pub fn check(&self) -> Result<(), Cancelled> {
+        match self.is_cancelled() {
+            true => Err(Cancelled),
+            false => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use bevy::{
+    audio::{AudioSource, PlaybackMode, Volume},
+    prelude::*,
+};
+
+use crate::{
+    formats::{
+        ambience::{parse_ambience, tracks_for_level, AmbienceKind, AmbienceTrack},
+        report::{LoadReport, LoadReports},
+    },
+    fs::GameFs,
+};
+
+use super::skybox::SkyboxSettings;
+
+/// Plugin looping the music and ambient tracks of the level selected in
+/// the [SkyboxSettings]. Minus and equals change the music volume, the
+/// brackets change the ambient volume and M mutes every track
+pub struct AmbiencePlugin;
+
+impl Plugin for AmbiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadReports>();
+        app.init_resource::<SkyboxSettings>();
+        app.init_resource::<AmbienceSettings>();
+        app.init_resource::<AmbienceListing>();
+        app.add_systems(Startup, load_ambience_listing);
+        app.add_systems(
+            Update,
+            (
+                spawn_level_ambience,
+                update_ambience_settings,
+                update_ambience_volume,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Ambience listing loaded from the game data when present
+const AMBIENCE_FILE: &str = "ambience.txt";
+/// Amount each key press changes a volume by
+const VOLUME_STEP: f32 = 0.1;
+
+/// Volume of each kind of track
+#[derive(Resource)]
+pub struct AmbienceSettings {
+    pub music_volume: f32,
+    pub ambient_volume: f32,
+    pub muted: bool,
+}
+
+impl Default for AmbienceSettings {
+    fn default() -> Self {
+        Self {
+            music_volume: 0.8,
+            ambient_volume: 0.8,
+            muted: false,
+        }
+    }
+}
+
+impl AmbienceSettings {
+    /// Volume a track is played at
+    pub fn track_volume(&self, track: &AmbienceTrack) -> f32 {
+        if self.muted {
+            return 0.0;
+        }
+
+        let volume = match track.kind {
+            AmbienceKind::Music => self.music_volume,
+            AmbienceKind::Ambient => self.ambient_volume,
+        };
+        volume * track.volume
+    }
+}
+
+/// Tracks of each level from the listing
+#[derive(Resource, Default)]
+pub struct AmbienceListing(pub Vec<AmbienceTrack>);
+
+/// Track being played for the current level
+#[derive(Component)]
+pub struct Ambience(pub AmbienceTrack);
+
+fn load_ambience_listing(
+    game_fs: Res<GameFs>,
+    mut listing: ResMut<AmbienceListing>,
+    mut reports: ResMut<LoadReports>,
+) {
+    if !game_fs.contains(AMBIENCE_FILE) {
+        return;
+    }
+
+    let mut report = LoadReport::new(AMBIENCE_FILE);
+    match game_fs
+        .read_to_string(AMBIENCE_FILE)
+        .map_err(|err| err.to_string())
+        .and_then(|value| parse_ambience(&value).map_err(|err| err.to_string()))
+    {
+        Ok(tracks) => {
+            report.info(format!("listed {} ambience tracks", tracks.len()));
+            listing.0 = tracks;
+        }
+        Err(err) => report.error(err),
+    }
+    reports.add(report);
+}
+
+/// System replacing the playing tracks when the level changes
+#[allow(clippy::too_many_arguments)]
+fn spawn_level_ambience(
+    mut commands: Commands,
+    level: Res<SkyboxSettings>,
+    listing: Res<AmbienceListing>,
+    settings: Res<AmbienceSettings>,
+    game_fs: Res<GameFs>,
+    mut sources: ResMut<Assets<AudioSource>>,
+    mut reports: ResMut<LoadReports>,
+    playing: Query<Entity, With<Ambience>>,
+) {
+    if !level.is_changed() && !listing.is_changed() {
+        return;
+    }
+
+    for entity in playing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let Some(level) = level.level.as_deref() else {
+        return;
+    };
+
+    for track in tracks_for_level(&listing.0, level) {
+        let bytes = match game_fs.read(&track.path) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                let mut report = LoadReport::new(&track.path);
+                report.error(err.to_string());
+                reports.add(report);
+                continue;
+            }
+        };
+
+        commands.spawn((
+            AudioBundle {
+                source: sources.add(AudioSource {
+                    bytes: bytes.into(),
+                }),
+                settings: PlaybackSettings {
+                    mode: PlaybackMode::Loop,
+                    volume: Volume::new_relative(settings.track_volume(track)),
+                    ..default()
+                },
+            },
+            Ambience(track.clone()),
+        ));
+    }
+}
+
+/// System changing the volumes from the keyboard
+fn update_ambience_settings(keys: Res<Input<KeyCode>>, mut settings: ResMut<AmbienceSettings>) {
+    let step = |volume: &mut f32, down: KeyCode, up: KeyCode| {
+        if keys.just_pressed(down) {
+            *volume = (*volume - VOLUME_STEP).max(0.0);
+        }
+        if keys.just_pressed(up) {
+            *volume = (*volume + VOLUME_STEP).min(1.0);
+        }
+    };
+
+    let mut music_volume = settings.music_volume;
+    let mut ambient_volume = settings.ambient_volume;
+    step(&mut music_volume, KeyCode::Minus, KeyCode::Equals);
+    step(
+        &mut ambient_volume,
+        KeyCode::BracketLeft,
+        KeyCode::BracketRight,
+    );
+
+    // Only write when changed so the volume isn't reapplied every frame
+    if music_volume != settings.music_volume || ambient_volume != settings.ambient_volume {
+        settings.music_volume = music_volume;
+        settings.ambient_volume = ambient_volume;
+    }
+
+    if keys.just_pressed(KeyCode::M) {
+        settings.muted = !settings.muted;
+    }
+}
+
+/// System applying volume changes to the playing tracks
+fn update_ambience_volume(
+    settings: Res<AmbienceSettings>,
+    playing: Query<(&AudioSink, &Ambience)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (sink, ambience) in playing.iter() {
+        sink.set_volume(settings.track_volume(&ambience.0));
+    }
+}
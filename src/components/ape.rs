@@ -0,0 +1,222 @@
+use bevy::prelude::*;
+
+use crate::formats::{
+    mesh::{
+        asset::{ApeAsset, ApeAssetLoader},
+        loader::LoadedMesh,
+        submesh::{decode_vertex_buffers, material_submeshes},
+    },
+    report::{LoadReport, LoadReports},
+};
+
+use super::{
+    backfaces::ShowBackfaces, lod::LodLevel, lod_rings::LodDistances,
+    material_culling::MaterialBounds, mesh_lights::spawn_mesh_light, parts::PartMask,
+    skeleton::SkeletonSource, texture_animation::AnimatedTexture,
+};
+
+/// Plugin loading .ape meshes through the asset server, entities with an
+/// [ApeInstance] get the meshes and lights of the asset spawned as children
+/// which are respawned whenever the asset is reloaded
+pub struct ApePlugin;
+
+impl Plugin for ApePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadReports>();
+        app.init_asset::<ApeAsset>();
+        app.init_asset_loader::<ApeAssetLoader>();
+        app.add_systems(Update, spawn_ape_instances);
+    }
+}
+
+/// Entity drawing the meshes of an .ape asset
+#[derive(Component, Debug, Clone)]
+pub struct ApeInstance(pub Handle<ApeAsset>);
+
+/// Marker for instances that have had their meshes spawned
+#[derive(Component)]
+struct ApeSpawned;
+
+/// Entity drawing the display lists of a single material
+#[derive(Component, Debug, Clone, Copy)]
+pub struct MaterialCluster {
+    /// Index of the material in the mesh
+    pub material: usize,
+    /// Index of the vertex buffer the display lists index
+    pub vertex_buffer: usize,
+}
+
+/// System spawning the meshes of instances once their asset has loaded.
+/// Meshes with decoded display lists get an entity for each LOD holding the
+/// display lists with their materials, otherwise the whole vertex buffers
+/// are drawn with the default material since they aren't split by material
+fn spawn_ape_instances(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<ApeAsset>>,
+    apes: Res<Assets<ApeAsset>>,
+    mut reports: ResMut<LoadReports>,
+    instances: Query<(Entity, &ApeInstance, Has<ApeSpawned>)>,
+) {
+    let modified: Vec<AssetId<ApeAsset>> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, instance, spawned) in instances.iter() {
+        let id = instance.0.id();
+        if spawned && !modified.contains(&id) {
+            continue;
+        }
+        let Some(ape) = apes.get(id) else {
+            continue;
+        };
+
+        reports.add(ape.report.clone());
+
+        // Bones are children too so the skeleton is rebuilt on reload
+        let mut entity = commands.entity(entity);
+        entity
+            .despawn_descendants()
+            .remove::<SkeletonSource>()
+            .insert((ApeSpawned, LodDistances(ape.lod_distances.clone())))
+            .with_children(|parent| {
+                for light in &ape.lights {
+                    spawn_mesh_light(parent, light);
+                }
+
+                if !ape.lod_meshes.is_empty() {
+                    spawn_lod_levels(parent, ape);
+                    return;
+                }
+
+                for mesh in &ape.meshes {
+                    parent.spawn((
+                        PbrBundle {
+                            mesh: mesh.clone(),
+                            ..default()
+                        },
+                        ShowBackfaces,
+                    ));
+                }
+            });
+
+        if !ape.skeleton.is_empty() {
+            entity.insert(SkeletonSource(ape.skeleton.clone()));
+        }
+    }
+}
+
+/// Spawns an entity for each LOD of the asset with the display lists
+/// drawn at that LOD as its children
+fn spawn_lod_levels(parent: &mut ChildBuilder, ape: &ApeAsset) {
+    let mut lods: Vec<u8> = ape.lod_meshes.iter().map(|value| value.lod_id).collect();
+    lods.sort_unstable();
+    lods.dedup();
+
+    for lod_id in lods {
+        parent
+            .spawn((
+                SpatialBundle::default(),
+                LodLevel(lod_id),
+                Name::new(format!("LOD {}", lod_id)),
+            ))
+            .with_children(|lod| {
+                for value in ape.lod_meshes.iter().filter(|value| value.lod_id == lod_id) {
+                    let mut mesh = lod.spawn((
+                        PbrBundle {
+                            mesh: value.mesh.clone(),
+                            material: ape
+                                .materials
+                                .get(value.material)
+                                .cloned()
+                                .unwrap_or_default(),
+                            ..default()
+                        },
+                        ShowBackfaces,
+                        PartMask::from_part_id(value.part_id),
+                    ));
+                    if let Some(sphere) = ape.material_bounds.get(value.material) {
+                        mesh.insert(MaterialBounds::from_sphere(sphere).bundle());
+                    }
+                    if let Some(animation) = ape
+                        .material_animations
+                        .get(value.material)
+                        .filter(|animation| animation.is_animated())
+                    {
+                        mesh.insert(AnimatedTexture(animation.clone()));
+                    }
+                }
+            });
+    }
+}
+
+/// Spawns a mesh loaded outside the asset server, returning the root entity
+/// named after the mesh. Each display list of a material gets its own child
+/// entity drawn with the handle of its material from `materials`, along with
+/// the part ID and LOD of the display list. When none of the display lists
+/// decode the whole vertex buffers are drawn with the default material like
+/// [ApeInstance]s do
+pub fn spawn_fmesh(
+    commands: &mut Commands,
+    loaded: &LoadedMesh,
+    meshes: &mut Assets<Mesh>,
+    materials: &[Handle<StandardMaterial>],
+    report: &mut LoadReport,
+) -> Entity {
+    let vertex_buffers = decode_vertex_buffers(loaded, report);
+    let submeshes = material_submeshes(loaded, &vertex_buffers, report);
+
+    let mesh = loaded.mesh();
+
+    commands
+        .spawn((
+            SpatialBundle::default(),
+            Name::new(mesh.name.to_string()),
+            LodDistances(mesh.lod_distances().to_vec()),
+        ))
+        .with_children(|parent| {
+            if submeshes.is_empty() {
+                for buffer in vertex_buffers {
+                    parent.spawn((
+                        PbrBundle {
+                            mesh: meshes.add(buffer.mesh),
+                            ..default()
+                        },
+                        ShowBackfaces,
+                    ));
+                }
+                return;
+            }
+
+            // LODs are direct children so the LOD selection doesn't need
+            // an entity for each LOD
+            for submesh in submeshes {
+                let mut entity = parent.spawn((
+                    PbrBundle {
+                        mesh: meshes.add(submesh.mesh),
+                        material: materials.get(submesh.material).cloned().unwrap_or_default(),
+                        ..default()
+                    },
+                    ShowBackfaces,
+                    PartMask::from_part_id(submesh.part_id),
+                    LodLevel(submesh.lod_id),
+                    MaterialCluster {
+                        material: submesh.material,
+                        vertex_buffer: submesh.vertex_buffer,
+                    },
+                    Name::new(format!(
+                        "Material {} LOD {}",
+                        submesh.material, submesh.lod_id
+                    )),
+                ));
+                if let Some(material) = loaded.materials().get(submesh.material) {
+                    let sphere = material.bound_sphere(mesh.bound_sphere.radius);
+                    entity.insert(MaterialBounds::from_sphere(&sphere).bundle());
+                }
+            }
+        })
+        .id()
+}
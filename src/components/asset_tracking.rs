@@ -0,0 +1,128 @@
+use bevy::{prelude::*, utils::HashMap};
+
+/// Plugin tracking the instances of each loaded asset, once the last
+/// instance of an asset is despawned its meshes and images are released
+pub struct AssetTrackingPlugin;
+
+impl Plugin for AssetTrackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadedAssets>();
+        app.add_systems(
+            PostUpdate,
+            (track_asset_instances, release_unused_assets).chain(),
+        );
+    }
+}
+
+/// Component marking an entity as an instance of a loaded asset
+#[derive(Component, Clone)]
+pub struct AssetInstance {
+    /// Path of the asset the entity is an instance of
+    pub asset: String,
+}
+
+/// Resources created when loading a single asset
+#[derive(Default)]
+pub struct LoadedAsset {
+    pub meshes: Vec<Handle<Mesh>>,
+    pub images: Vec<Handle<Image>>,
+    /// Number of spawned instances of the asset
+    instances: usize,
+}
+
+impl LoadedAsset {
+    pub fn new(meshes: Vec<Handle<Mesh>>, images: Vec<Handle<Image>>) -> Self {
+        Self {
+            meshes,
+            images,
+            instances: 0,
+        }
+    }
+}
+
+/// Cache of the loaded assets and their instances
+#[derive(Resource, Default)]
+pub struct LoadedAssets {
+    assets: HashMap<String, LoadedAsset>,
+    /// Asset each tracked instance entity belongs to
+    instances: HashMap<Entity, String>,
+}
+
+impl LoadedAssets {
+    /// Stores the resources created for an asset so later instances
+    /// can reuse them instead of loading the asset again
+    pub fn insert(&mut self, path: impl Into<String>, asset: LoadedAsset) {
+        self.assets.insert(path.into(), asset);
+    }
+
+    pub fn get(&self, path: &str) -> Option<&LoadedAsset> {
+        self.assets.get(path)
+    }
+
+    /// Number of spawned instances of the asset
+    pub fn instance_count(&self, path: &str) -> usize {
+        self.assets
+            .get(path)
+            .map(|asset| asset.instances)
+            .unwrap_or_default()
+    }
+}
+
+/// System counting newly spawned instances
+fn track_asset_instances(
+    mut loaded: ResMut<LoadedAssets>,
+    added: Query<(Entity, &AssetInstance), Added<AssetInstance>>,
+) {
+    for (entity, instance) in added.iter() {
+        let Some(asset) = loaded.assets.get_mut(&instance.asset) else {
+            warn!("Instance of untracked asset {}", instance.asset);
+            continue;
+        };
+
+        asset.instances += 1;
+        loaded.instances.insert(entity, instance.asset.clone());
+    }
+}
+
+/// System releasing the resources of assets that have no instances left
+fn release_unused_assets(
+    mut loaded: ResMut<LoadedAssets>,
+    mut removed: RemovedComponents<AssetInstance>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for entity in removed.read() {
+        let Some(path) = loaded.instances.remove(&entity) else {
+            continue;
+        };
+
+        let Some(asset) = loaded.assets.get_mut(&path) else {
+            continue;
+        };
+
+        asset.instances = asset.instances.saturating_sub(1);
+        if asset.instances > 0 {
+            continue;
+        }
+
+        let Some(asset) = loaded.assets.remove(&path) else {
+            continue;
+        };
+
+        debug!(
+            "Releasing {} ({} meshes, {} images)",
+            path,
+            asset.meshes.len(),
+            asset.images.len()
+        );
+
+        // Removing explicitly frees the GPU resources even if a stray
+        // strong handle is still held elsewhere
+        for mesh in &asset.meshes {
+            meshes.remove(mesh);
+        }
+        for image in &asset.images {
+            images.remove(image);
+        }
+    }
+}
@@ -1,91 +1,302 @@
-// /// FFmpeg audio source from video playback
-// pub struct FAudio {
-//     pub path: PathBuf,
-// }
-
-// impl Decodable for FAudio {
-//     type DecoderItem = f32;
-//     type Decoder = FAudioDecoder;
-
-//     fn decoder(&self) -> Self::Decoder {
-//         let input_context = input(&self.path).unwrap();
-//         let audio_stream = input_context
-//             .streams()
-//             .best(ffmpeg_next::media::Type::Audio)
-//             .ok_or(ffmpeg_next::Error::StreamNotFound)
-//             .unwrap();
-
-//         let context_decoder =
-//             ffmpeg_next::codec::context::Context::from_parameters(audio_stream.parameters())
-//                 .unwrap();
-//         let audio_decoder = context_decoder.decoder().audio().unwrap();
-//         let audio_stream_index = audio_stream.index();
-
-//         FAudioDecoder {
-//             decoder: audio_decoder,
-//             input_context,
-//             audio_stream_index,
-//             frame: None,
-//             frame_index: 0,
-//             frame_planes: 0,
-//         }
-//     }
-// }
-
-// pub struct FAudioDecoder {
-//     /// ffmpeg video decoder
-//     decoder: ffmpeg_next::decoder::Audio,
-//     /// ffmpeg input context
-//     input_context: ffmpeg_next::format::context::Input,
-//     /// index of the audio stream
-//     audio_stream_index: usize,
-
-//     /// The current audio frame
-//     frame: ffmpeg_next::frame::Audio,
-
-//     frame_index: usize,
-//     frame_planes: usize,
-// }
-
-// impl FAudioDecoder {
-//     pub fn new() -> Result<Self, ffmpeg_next::Error> {
-//         let input_context = input(&self.path).unwrap();
-//         let audio_stream = input_context
-//             .streams()
-//             .best(ffmpeg_next::media::Type::Audio)
-//             .ok_or(ffmpeg_next::Error::StreamNotFound)
-//             .unwrap();
-
-//         let context_decoder =
-//             ffmpeg_next::codec::context::Context::from_parameters(audio_stream.parameters())
-//                 .unwrap();
-//         let audio_decoder = context_decoder.decoder().audio().unwrap();
-//         let audio_stream_index = audio_stream.index();
-//     }
-// }
-
-// impl Iterator for FAudioDecoder {
-//     type Item = f32;
-
-//     fn next(&mut self) -> Option<Self::Item> {
-//         None
-//     }
-// }
-
-// impl Source for FAudioDecoder {
-//     fn current_frame_len(&self) -> Option<usize> {
-//         None
-//     }
-
-//     fn channels(&self) -> u16 {
-//         self.decoder.channels()
-//     }
-
-//     fn sample_rate(&self) -> u32 {
-//         self.decoder.rate()
-//     }
-
-//     fn total_duration(&self) -> Option<std::time::Duration> {
-//         None
-//     }
-// }
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{
+    audio::{AddAudioSource, Decodable, Source},
+    prelude::*,
+};
+use ffmpeg_next::{
+    format::{input, sample::Type as SampleType, Sample},
+    frame::Audio,
+};
+
+use super::video::{VideoDecodeSet, VideoPlayer};
+
+/// Plugin playing the audio streams of videos through ffmpeg, so movies
+/// don't need their soundtrack extracted to a separate .wav
+pub struct FAudioPlugin;
+
+impl Plugin for FAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_source::<FAudioSource>();
+        app.add_systems(Update, sync_soundtracks.after(VideoDecodeSet));
+    }
+}
+
+/// Audio stream of a video file decoded with ffmpeg
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct FAudioSource {
+    pub path: PathBuf,
+    /// Position within the stream to start playing from
+    pub start: Duration,
+}
+
+impl FAudioSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            start: Duration::ZERO,
+        }
+    }
+}
+
+impl Decodable for FAudioSource {
+    type DecoderItem = f32;
+    type Decoder = FAudioDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        match FAudioDecoder::open(&self.path, self.start) {
+            Ok(decoder) => decoder,
+            Err(err) => {
+                warn!(
+                    "Failed to open the audio of {}: {}",
+                    self.path.display(),
+                    err
+                );
+                FAudioDecoder::empty()
+            }
+        }
+    }
+}
+
+/// Open ffmpeg audio stream
+struct FAudioStream {
+    input_context: ffmpeg_next::format::context::Input,
+    decoder: ffmpeg_next::decoder::Audio,
+    stream_index: usize,
+    /// Whether the end of the file has been sent to the decoder
+    eof: bool,
+}
+
+/// Decoder providing the samples of an [FAudioSource] as interleaved f32
+pub struct FAudioDecoder {
+    /// Stream being decoded, None once the stream has ended
+    stream: Option<FAudioStream>,
+    channels: u16,
+    sample_rate: u32,
+    /// Decoded samples not yet played
+    samples: VecDeque<f32>,
+}
+
+impl FAudioDecoder {
+    fn open(path: &Path, start: Duration) -> Result<Self, ffmpeg_next::Error> {
+        let mut input_context = input(path)?;
+        if start > Duration::ZERO {
+            // Seeking without a stream uses microsecond timestamps
+            let timestamp = start.as_micros() as i64;
+            input_context.seek(timestamp, ..timestamp)?;
+        }
+
+        let audio_stream = input_context
+            .streams()
+            .best(ffmpeg_next::media::Type::Audio)
+            .ok_or(ffmpeg_next::Error::StreamNotFound)?;
+        let stream_index = audio_stream.index();
+
+        let context_decoder =
+            ffmpeg_next::codec::context::Context::from_parameters(audio_stream.parameters())?;
+        let decoder = context_decoder.decoder().audio()?;
+
+        Ok(Self {
+            channels: decoder.channels(),
+            sample_rate: decoder.rate(),
+            stream: Some(FAudioStream {
+                input_context,
+                decoder,
+                stream_index,
+                eof: false,
+            }),
+            samples: VecDeque::new(),
+        })
+    }
+
+    /// Decoder that plays nothing, used when the file can't be opened
+    fn empty() -> Self {
+        Self {
+            stream: None,
+            channels: 1,
+            sample_rate: 44100,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Decodes packets until a frame is received, returns false once the
+    /// stream has no frames left
+    fn decode_frame(&mut self) -> bool {
+        let Some(stream) = &mut self.stream else {
+            return false;
+        };
+
+        let mut frame = Audio::empty();
+        loop {
+            if stream.decoder.receive_frame(&mut frame).is_ok() {
+                push_samples(&frame, &mut self.samples);
+                return true;
+            }
+
+            if stream.eof {
+                self.stream = None;
+                return false;
+            }
+
+            match stream.input_context.packets().next() {
+                Some((packet_stream, packet)) => {
+                    if packet_stream.index() != stream.stream_index {
+                        continue;
+                    }
+                    if let Err(err) = stream.decoder.send_packet(&packet) {
+                        warn!("Failed to decode audio packet: {}", err);
+                    }
+                }
+                // Flush the frames still buffered in the decoder
+                None => {
+                    stream.eof = true;
+                    let _ = stream.decoder.send_eof();
+                }
+            }
+        }
+    }
+}
+
+/// Appends the samples of the frame interleaved by channel, planar
+/// frames store each channel separately
+fn push_samples(frame: &Audio, out: &mut VecDeque<f32>) {
+    let samples = frame.samples();
+    let channels = frame.channels() as usize;
+
+    let read: fn(&[u8]) -> f32 = match frame.format() {
+        Sample::F32(_) => |bytes| f32::from_ne_bytes(bytes.try_into().expect("Sample length")),
+        Sample::I16(_) => |bytes| {
+            i16::from_ne_bytes(bytes.try_into().expect("Sample length")) as f32 / i16::MAX as f32
+        },
+        format => {
+            warn!("Unsupported audio sample format {:?}", format);
+            return;
+        }
+    };
+    let size = frame.format().bytes();
+
+    match frame.format() {
+        Sample::F32(SampleType::Planar) | Sample::I16(SampleType::Planar) => {
+            let planes: Vec<&[u8]> = (0..channels).map(|channel| frame.data(channel)).collect();
+            for index in 0..samples {
+                for plane in &planes {
+                    out.push_back(read(&plane[index * size..(index + 1) * size]));
+                }
+            }
+        }
+        _ => {
+            let data = &frame.data(0)[..samples * channels * size];
+            out.extend(data.chunks_exact(size).map(read));
+        }
+    }
+}
+
+impl Iterator for FAudioDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.samples.is_empty() {
+            if !self.decode_frame() {
+                return None;
+            }
+        }
+        self.samples.pop_front()
+    }
+}
+
+impl Source for FAudioDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        // The format is the same for the whole stream
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Soundtrack of a [VideoPlayer], the audio pauses along with the video,
+/// restarts from the video position when the video loops or seeks and is
+/// removed when the video finishes
+#[derive(Component, Debug)]
+pub struct VideoSoundtrack {
+    /// Entity of the video player
+    pub player: Entity,
+    /// Loop of the video the audio is playing for
+    played_loop: u32,
+    /// Seek of the video the audio is playing from
+    played_seek: u32,
+}
+
+impl VideoSoundtrack {
+    pub fn new(player: Entity) -> Self {
+        Self {
+            player,
+            played_loop: 0,
+            played_seek: 0,
+        }
+    }
+}
+
+/// System keeping the soundtracks in step with their video players,
+/// removing the sink stops the audio and has bevy play the source again
+/// so restarting swaps in a source starting at the video position
+fn sync_soundtracks(
+    mut commands: Commands,
+    players: Query<&VideoPlayer>,
+    mut sources: ResMut<Assets<FAudioSource>>,
+    mut soundtracks: Query<(
+        Entity,
+        &mut VideoSoundtrack,
+        &mut Handle<FAudioSource>,
+        Option<&AudioSink>,
+    )>,
+) {
+    for (entity, mut soundtrack, mut handle, sink) in soundtracks.iter_mut() {
+        let Ok(player) = players.get(soundtrack.player) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        if player.finished {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        if player.loop_count != soundtrack.played_loop
+            || player.seek_count != soundtrack.played_seek
+        {
+            soundtrack.played_loop = player.loop_count;
+            soundtrack.played_seek = player.seek_count;
+
+            if let Some(source) = sources.get(handle.as_ref()) {
+                let source = FAudioSource {
+                    path: source.path.clone(),
+                    start: player.position(),
+                };
+                *handle = sources.add(source);
+            }
+            commands.entity(entity).remove::<AudioSink>();
+            continue;
+        }
+
+        if let Some(sink) = sink {
+            match player.is_paused() {
+                true => sink.pause(),
+                false => sink.play(),
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use crate::formats::mesh::winding::flip_winding;
+
+/// Plugin for debugging the winding of loaded meshes, pressing B
+/// toggles rendering the back faces of meshes in red
+pub struct BackfacePlugin;
+
+impl Plugin for BackfacePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BackfaceSettings>();
+        app.init_resource::<TwoSidedOverrides>();
+        app.add_systems(Update, (toggle_backfaces, update_backface_overlays).chain());
+    }
+}
+
+/// Settings for the back face visualization
+#[derive(Resource, Default)]
+pub struct BackfaceSettings {
+    /// Whether back faces are highlighted
+    pub enabled: bool,
+}
+
+/// Marker for meshes that should have their back faces highlighted
+#[derive(Component)]
+pub struct ShowBackfaces;
+
+/// Marker for the overlay entities rendering the back faces
+#[derive(Component)]
+struct BackfaceOverlay;
+
+/// Materials that should be rendered from both sides, keyed by
+/// the mesh name and the material index
+#[derive(Resource, Default)]
+pub struct TwoSidedOverrides {
+    materials: HashSet<(String, usize)>,
+}
+
+impl TwoSidedOverrides {
+    pub fn insert(&mut self, mesh: impl Into<String>, material: usize) {
+        self.materials.insert((mesh.into(), material));
+    }
+
+    pub fn remove(&mut self, mesh: &str, material: usize) {
+        self.materials.remove(&(mesh.to_string(), material));
+    }
+
+    pub fn is_two_sided(&self, mesh: &str, material: usize) -> bool {
+        self.materials.contains(&(mesh.to_string(), material))
+    }
+}
+
+/// System that toggles the back face visualization
+fn toggle_backfaces(keys: Res<Input<KeyCode>>, mut settings: ResMut<BackfaceSettings>) {
+    if keys.just_pressed(KeyCode::B) {
+        settings.enabled = !settings.enabled;
+    }
+}
+
+/// System that spawns a copy of each mesh with its winding flipped and an unlit
+/// red material, the copy is only visible where the back of the original is
+fn update_backface_overlays(
+    mut commands: Commands,
+    settings: Res<BackfaceSettings>,
+    targets: Query<(Entity, &Handle<Mesh>), With<ShowBackfaces>>,
+    overlays: Query<Entity, With<BackfaceOverlay>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for overlay in overlays.iter() {
+        commands.entity(overlay).despawn_recursive();
+    }
+
+    if !settings.enabled {
+        return;
+    }
+
+    let material = materials.add(StandardMaterial {
+        base_color: Color::RED,
+        unlit: true,
+        ..default()
+    });
+
+    for (entity, mesh) in targets.iter() {
+        let Some(mut flipped) = meshes.get(mesh).cloned() else {
+            continue;
+        };
+        flip_winding(&mut flipped);
+        let mesh = meshes.add(flipped);
+
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                PbrBundle {
+                    mesh,
+                    material: material.clone(),
+                    ..default()
+                },
+                BackfaceOverlay,
+            ));
+        });
+    }
+}
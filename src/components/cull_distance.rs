@@ -0,0 +1,85 @@
+use bevy::{prelude::*, render::view::VisibilitySystems};
+
+/// Plugin hiding objects that are further from the camera than their
+/// [CullDistance] like the game does, Comma and Period halve and double
+/// the distances through the [CullDistanceSettings] scale
+pub struct CullDistancePlugin;
+
+impl Plugin for CullDistancePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CullDistanceSettings>();
+        app.add_systems(
+            PostUpdate,
+            (update_cull_input, apply_cull_distances)
+                .chain()
+                .before(VisibilitySystems::VisibilityPropagate),
+        );
+    }
+}
+
+/// Range the scale can be adjusted within
+const SCALE_RANGE: (f32, f32) = (0.125, 16.0);
+
+/// Distance from the camera beyond which the entity is hidden
+#[derive(Component, Debug, Clone, Copy)]
+pub struct CullDistance(pub f32);
+
+/// Global settings for the cull distances
+#[derive(Resource, Debug, Clone)]
+pub struct CullDistanceSettings {
+    /// Whether objects are culled at all
+    pub enabled: bool,
+    /// Factor every cull distance is multiplied by
+    pub scale: f32,
+}
+
+impl Default for CullDistanceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            scale: 1.0,
+        }
+    }
+}
+
+fn update_cull_input(keys: Res<Input<KeyCode>>, mut settings: ResMut<CullDistanceSettings>) {
+    let factor = if keys.just_pressed(KeyCode::Comma) {
+        0.5
+    } else if keys.just_pressed(KeyCode::Period) {
+        2.0
+    } else {
+        return;
+    };
+
+    settings.scale = (settings.scale * factor).clamp(SCALE_RANGE.0, SCALE_RANGE.1);
+    info!("Cull distance scale: {}", settings.scale);
+}
+
+/// System hiding the entities outside their cull distance, entities back
+/// within range inherit the visibility of their parent again
+fn apply_cull_distances(
+    settings: Res<CullDistanceSettings>,
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+    mut objects: Query<(&GlobalTransform, &CullDistance, &mut Visibility)>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let camera = camera.translation();
+
+    for (transform, distance, mut visibility) in objects.iter_mut() {
+        let limit = distance.0 * settings.scale;
+        let culled =
+            settings.enabled && transform.translation().distance_squared(camera) > limit * limit;
+        let value = if culled {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+
+        // Only write on change so change detection isn't triggered every frame
+        if *visibility != value {
+            *visibility = value;
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    utils::HashMap,
+};
+
+use crate::{
+    formats::{
+        decals::{parse_decals, DecalKind, DecalPlacement},
+        report::{LoadReport, LoadReports},
+    },
+    fs::GameFs,
+};
+
+use super::perf_hud::MeshSpawnSet;
+
+/// Plugin rendering decals as quads projected straight down onto the
+/// ground plane, objects with a [BlobShadow] get a shadow that follows them
+pub struct DecalPlugin;
+
+impl Plugin for DecalPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadReports>();
+        app.init_resource::<DecalSettings>();
+        app.init_resource::<MarkingTextures>();
+        app.add_systems(Startup, (init_decal_assets, load_decal_placements).chain());
+        app.add_systems(
+            Update,
+            (
+                (spawn_decals, spawn_blob_shadows).in_set(MeshSpawnSet),
+                update_blob_shadows,
+            ),
+        );
+    }
+}
+
+/// Decal listing loaded from the game data when present
+const DECALS_FILE: &str = "decals.txt";
+/// Size of the generated blob texture
+const BLOB_TEXTURE_SIZE: u32 = 64;
+/// Offset above the ground to prevent the decals fighting with the ground
+const GROUND_OFFSET: f32 = 0.01;
+
+/// Settings for projecting decals
+#[derive(Resource)]
+pub struct DecalSettings {
+    /// Height of the ground plane decals are projected onto
+    pub ground_height: f32,
+    /// Height above the ground at which blob shadows shrink to half size
+    pub fade_height: f32,
+}
+
+impl Default for DecalSettings {
+    fn default() -> Self {
+        Self {
+            ground_height: 0.0,
+            fade_height: 10.0,
+        }
+    }
+}
+
+/// Textures for ground markings keyed by texture name, markings without
+/// a texture use the blob texture until their texture is provided
+#[derive(Resource, Default)]
+pub struct MarkingTextures(pub HashMap<String, Handle<Image>>);
+
+/// Shared assets used to render decals
+#[derive(Resource)]
+struct DecalAssets {
+    quad: Handle<Mesh>,
+    blob: Handle<Image>,
+    shadow: Handle<StandardMaterial>,
+}
+
+/// Decal placed in the world, rendered once spawned
+#[derive(Component)]
+pub struct Decal(pub DecalPlacement);
+
+/// Component giving an object a blob shadow on the ground below it
+#[derive(Component)]
+pub struct BlobShadow {
+    pub radius: f32,
+}
+
+/// Entity rendering the blob shadow of the owner entity
+#[derive(Component)]
+struct BlobShadowOf(Entity);
+
+/// Creates a white texture with an alpha that fades out from the center
+fn blob_image() -> Image {
+    let size = BLOB_TEXTURE_SIZE;
+    let center = (size as f32 - 1.0) / 2.0;
+
+    let data = (0..size * size)
+        .flat_map(|index| {
+            let x = (index % size) as f32 - center;
+            let y = (index / size) as f32 - center;
+            let distance = (x * x + y * y).sqrt() / center;
+            let alpha = (1.0 - distance).clamp(0.0, 1.0);
+            // Smooth the falloff so the edge isn't visible
+            let alpha = alpha * alpha * (3.0 - 2.0 * alpha);
+            [255, 255, 255, (alpha * 255.0) as u8]
+        })
+        .collect();
+
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+fn init_decal_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let blob = images.add(blob_image());
+    let shadow = materials.add(StandardMaterial {
+        base_color: Color::rgba(0.0, 0.0, 0.0, 0.6),
+        base_color_texture: Some(blob.clone()),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+
+    commands.insert_resource(DecalAssets {
+        quad: meshes.add(Mesh::from(shape::Plane::from_size(1.0))),
+        blob,
+        shadow,
+    });
+}
+
+/// System spawning the decals listed in the game data
+fn load_decal_placements(
+    mut commands: Commands,
+    game_fs: Res<GameFs>,
+    mut reports: ResMut<LoadReports>,
+) {
+    if !game_fs.contains(DECALS_FILE) {
+        return;
+    }
+
+    let mut report = LoadReport::new(DECALS_FILE);
+    match game_fs
+        .read_to_string(DECALS_FILE)
+        .map_err(|err| err.to_string())
+        .and_then(|value| parse_decals(&value).map_err(|err| err.to_string()))
+    {
+        Ok(decals) => {
+            report.info(format!("placed {} decals", decals.len()));
+            for decal in decals {
+                commands.spawn(Decal(decal));
+            }
+        }
+        Err(err) => report.error(err),
+    }
+    reports.add(report);
+}
+
+/// Transform of a decal projected onto the ground below the position
+fn ground_transform(
+    settings: &DecalSettings,
+    position: Vec3,
+    size: f32,
+    rotation: f32,
+) -> Transform {
+    Transform::from_xyz(
+        position.x,
+        settings.ground_height + GROUND_OFFSET,
+        position.z,
+    )
+    .with_rotation(Quat::from_rotation_y(rotation.to_radians()))
+    .with_scale(Vec3::new(size, 1.0, size))
+}
+
+fn spawn_decals(
+    mut commands: Commands,
+    assets: Res<DecalAssets>,
+    settings: Res<DecalSettings>,
+    textures: Res<MarkingTextures>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    decals: Query<(Entity, &Decal), Added<Decal>>,
+) {
+    for (entity, Decal(decal)) in decals.iter() {
+        let material = match &decal.kind {
+            DecalKind::Shadow => assets.shadow.clone(),
+            DecalKind::Marking { texture } => {
+                let image = textures.0.get(texture);
+                if image.is_none() {
+                    debug!("No texture for decal marking {}", texture);
+                }
+
+                materials.add(StandardMaterial {
+                    base_color_texture: Some(image.unwrap_or(&assets.blob).clone()),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..default()
+                })
+            }
+        };
+
+        commands.entity(entity).insert(PbrBundle {
+            mesh: assets.quad.clone(),
+            material,
+            transform: ground_transform(
+                &settings,
+                Vec3::from_array(decal.position),
+                decal.radius * 2.0,
+                decal.rotation,
+            ),
+            ..default()
+        });
+    }
+}
+
+fn spawn_blob_shadows(
+    mut commands: Commands,
+    assets: Res<DecalAssets>,
+    owners: Query<Entity, Added<BlobShadow>>,
+) {
+    for owner in owners.iter() {
+        // Shadows are separate entities so they don't inherit the
+        // rotation and scale of their owner
+        commands.spawn((
+            PbrBundle {
+                mesh: assets.quad.clone(),
+                material: assets.shadow.clone(),
+                ..default()
+            },
+            BlobShadowOf(owner),
+        ));
+    }
+}
+
+/// System keeping blob shadows under their owners, shadows shrink as
+/// their owner moves further above the ground
+fn update_blob_shadows(
+    mut commands: Commands,
+    settings: Res<DecalSettings>,
+    owners: Query<(&GlobalTransform, &BlobShadow)>,
+    mut shadows: Query<(Entity, &BlobShadowOf, &mut Transform)>,
+) {
+    for (entity, BlobShadowOf(owner), mut transform) in shadows.iter_mut() {
+        let Ok((owner, shadow)) = owners.get(*owner) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        let position = owner.translation();
+        let height = (position.y - settings.ground_height).max(0.0);
+        let falloff = settings.fade_height / (settings.fade_height + height);
+
+        *transform = ground_transform(&settings, position, shadow.radius * 2.0 * falloff, 0.0);
+    }
+}
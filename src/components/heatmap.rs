@@ -0,0 +1,332 @@
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{PrimitiveTopology, VertexAttributeValues},
+        render_resource::Face,
+    },
+};
+
+use super::backfaces::ShowBackfaces;
+
+/// Plugin coloring meshes by how much texture detail they show or by how
+/// expensive their material is to draw, F6 cycles through the modes. Used
+/// to check the decoded UV scales and to find the textures most worth
+/// replacing. The original materials are restored when turned off
+pub struct HeatmapPlugin;
+
+impl Plugin for HeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HeatmapSettings>();
+        app.add_systems(Startup, init_heatmap_panel);
+        app.add_systems(
+            Update,
+            (
+                update_heatmap_input,
+                restore_materials,
+                apply_heatmap,
+                update_heatmap_panel,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Number of colors the heatmap gradient is split into
+const GRADIENT_STEPS: usize = 9;
+
+/// Octaves of density either side of the target before the gradient
+/// reaches its ends
+const DENSITY_OCTAVES: f32 = 2.0;
+
+/// Cost at which the material cost gradient reaches its end
+const MAX_MATERIAL_COST: u32 = 7;
+
+/// Value the meshes are colored by
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HeatmapMode {
+    #[default]
+    Off,
+    /// Texels of the base texture per world unit
+    TexelDensity,
+    /// Estimated cost of drawing the material
+    MaterialCost,
+}
+
+impl HeatmapMode {
+    fn next(self) -> Self {
+        match self {
+            HeatmapMode::Off => HeatmapMode::TexelDensity,
+            HeatmapMode::TexelDensity => HeatmapMode::MaterialCost,
+            HeatmapMode::MaterialCost => HeatmapMode::Off,
+        }
+    }
+}
+
+/// Settings for the heatmap overlay
+#[derive(Resource, Debug)]
+pub struct HeatmapSettings {
+    pub mode: HeatmapMode,
+    /// Texels per world unit shown in green, lower densities go towards
+    /// blue and higher densities towards red
+    pub target_density: f32,
+}
+
+impl Default for HeatmapSettings {
+    fn default() -> Self {
+        Self {
+            mode: HeatmapMode::Off,
+            target_density: 64.0,
+        }
+    }
+}
+
+/// Material the mesh had before the heatmap replaced it
+#[derive(Component)]
+struct HeatmapOriginal(Handle<StandardMaterial>);
+
+/// Marker for the text of the heatmap legend
+#[derive(Component)]
+struct HeatmapPanelText;
+
+fn init_heatmap_panel(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::default().with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..default()
+        }),
+        HeatmapPanelText,
+    ));
+}
+
+fn update_heatmap_input(keys: Res<Input<KeyCode>>, mut settings: ResMut<HeatmapSettings>) {
+    if keys.just_pressed(KeyCode::F6) {
+        settings.mode = settings.mode.next();
+    }
+}
+
+/// System putting back the original materials whenever the mode changes,
+/// [apply_heatmap] then recolors the meshes for the new mode
+fn restore_materials(
+    mut commands: Commands,
+    settings: Res<HeatmapSettings>,
+    mut targets: Query<(Entity, &HeatmapOriginal, &mut Handle<StandardMaterial>)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (entity, original, mut material) in targets.iter_mut() {
+        *material = original.0.clone();
+        commands.entity(entity).remove::<HeatmapOriginal>();
+    }
+}
+
+/// Color of the gradient at `value` between 0 and 1, going from blue
+/// through green to red
+fn gradient_color(value: f32) -> Color {
+    let value = value.clamp(0.0, 1.0);
+    if value < 0.5 {
+        Color::rgb(0.0, value * 2.0, 1.0 - value * 2.0)
+    } else {
+        Color::rgb(value * 2.0 - 1.0, 2.0 - value * 2.0, 0.0)
+    }
+}
+
+/// Area of the triangle between the three points
+fn triangle_area(a: Vec3, b: Vec3, c: Vec3) -> f32 {
+    (b - a).cross(c - a).length() * 0.5
+}
+
+/// Texels of a `size` texture per world unit across the mesh, weighted by
+/// the area of each triangle. None for meshes without positions, UVs or
+/// any area
+fn texel_density(mesh: &Mesh, transform: &GlobalTransform, size: Vec2) -> Option<f32> {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return None;
+    };
+    let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+        return None;
+    };
+
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..positions.len()).collect(),
+    };
+    let triangles: Vec<&[usize]> = match mesh.primitive_topology() {
+        PrimitiveTopology::TriangleList => indices.chunks_exact(3).collect(),
+        // Winding doesn't change the area so strips aren't reordered
+        PrimitiveTopology::TriangleStrip => indices.windows(3).collect(),
+        _ => return None,
+    };
+
+    let mut world_area = 0.0;
+    let mut uv_area = 0.0;
+    for triangle in triangles {
+        if triangle
+            .iter()
+            .any(|index| *index >= positions.len() || *index >= uvs.len())
+        {
+            continue;
+        }
+
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]]
+            .map(|index| transform.transform_point(Vec3::from(positions[index])));
+        world_area += triangle_area(a, b, c);
+
+        let [a, b, c] =
+            [triangle[0], triangle[1], triangle[2]].map(|index| Vec2::from(uvs[index]) * size);
+        uv_area += triangle_area(a.extend(0.0), b.extend(0.0), c.extend(0.0));
+    }
+
+    if world_area <= 0.0 || uv_area <= 0.0 {
+        return None;
+    }
+
+    Some((uv_area / world_area).sqrt())
+}
+
+/// Rough cost of drawing the material, each texture and each feature that
+/// stops the material being drawn as a plain opaque surface adds to it
+fn material_cost(material: &StandardMaterial) -> u32 {
+    let textures = [
+        material.base_color_texture.is_some(),
+        material.normal_map_texture.is_some(),
+        material.emissive_texture.is_some(),
+        material.metallic_roughness_texture.is_some(),
+        material.occlusion_texture.is_some(),
+    ]
+    .into_iter()
+    .filter(|value| *value)
+    .count() as u32;
+
+    let blending = match material.alpha_mode {
+        AlphaMode::Opaque => 0,
+        AlphaMode::Mask(_) => 1,
+        _ => 2,
+    };
+    let culling = match material.cull_mode {
+        Some(Face::Back) | Some(Face::Front) => 0,
+        None => 1,
+    };
+    let lighting = match material.unlit {
+        true => 0,
+        false => 1,
+    };
+
+    textures + blending + culling + lighting
+}
+
+/// System replacing the material of each mesh with the heatmap color for
+/// the active mode. Meshes that can't be measured yet, such as ones whose
+/// textures are still loading, are retried each frame
+fn apply_heatmap(
+    mut commands: Commands,
+    settings: Res<HeatmapSettings>,
+    mut gradient: Local<Vec<Handle<StandardMaterial>>>,
+    meshes: Res<Assets<Mesh>>,
+    images: Res<Assets<Image>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut targets: Query<
+        (
+            Entity,
+            &Handle<Mesh>,
+            &GlobalTransform,
+            &mut Handle<StandardMaterial>,
+        ),
+        (With<ShowBackfaces>, Without<HeatmapOriginal>),
+    >,
+) {
+    if settings.mode == HeatmapMode::Off {
+        return;
+    }
+
+    if gradient.is_empty() {
+        *gradient = (0..GRADIENT_STEPS)
+            .map(|step| {
+                materials.add(StandardMaterial {
+                    base_color: gradient_color(step as f32 / (GRADIENT_STEPS - 1) as f32),
+                    unlit: true,
+                    ..default()
+                })
+            })
+            .collect();
+    }
+
+    for (entity, mesh, transform, mut handle) in targets.iter_mut() {
+        let Some(material) = materials.get(handle.as_ref()) else {
+            continue;
+        };
+
+        let value = match settings.mode {
+            HeatmapMode::Off => continue,
+            HeatmapMode::TexelDensity => {
+                let Some(image) = material
+                    .base_color_texture
+                    .as_ref()
+                    .and_then(|image| images.get(image))
+                else {
+                    continue;
+                };
+                let Some(density) = meshes
+                    .get(mesh)
+                    .and_then(|mesh| texel_density(mesh, transform, image.size_f32()))
+                else {
+                    continue;
+                };
+
+                let octaves = (density / settings.target_density).log2();
+                octaves / (DENSITY_OCTAVES * 2.0) + 0.5
+            }
+            HeatmapMode::MaterialCost => material_cost(material) as f32 / MAX_MATERIAL_COST as f32,
+        };
+
+        let step = (value.clamp(0.0, 1.0) * (GRADIENT_STEPS - 1) as f32).round() as usize;
+        commands
+            .entity(entity)
+            .insert(HeatmapOriginal(handle.clone()));
+        *handle = gradient[step].clone();
+    }
+}
+
+fn update_heatmap_panel(
+    settings: Res<HeatmapSettings>,
+    mut text: Query<&mut Text, With<HeatmapPanelText>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+
+    let legend = match settings.mode {
+        HeatmapMode::Off => None,
+        HeatmapMode::TexelDensity => Some(format!(
+            "Texel density: green {} texels per unit, blue {}x lower, red {}x higher",
+            settings.target_density,
+            2f32.powf(DENSITY_OCTAVES),
+            2f32.powf(DENSITY_OCTAVES)
+        )),
+        HeatmapMode::MaterialCost => Some(format!(
+            "Material cost: blue cheapest, red {} or more",
+            MAX_MATERIAL_COST
+        )),
+    };
+
+    text.sections = match legend {
+        Some(legend) => vec![TextSection::new(
+            legend,
+            TextStyle {
+                font_size: 14.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )],
+        None => Vec::new(),
+    };
+}
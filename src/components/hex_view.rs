@@ -0,0 +1,379 @@
+use bevy::prelude::*;
+
+use crate::{
+    formats::{
+        handler::FormatRegistry,
+        hex::{field_at, interpret, FieldSpan},
+    },
+    fs::{FsError, GameFs},
+};
+
+/// Plugin showing the raw bytes of a file, hovering a byte highlights the
+/// parsed field it belongs to and clicking a byte lists candidate
+/// interpretations of it. Files dropped onto the window are opened,
+/// H toggles the panel and Up / Down scroll it
+pub struct HexViewPlugin;
+
+impl Plugin for HexViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<OpenHexView>();
+        app.add_systems(Startup, init_hex_view);
+        app.add_systems(
+            Update,
+            (
+                (open_hex_view, open_dropped_file),
+                (update_hex_input, update_hex_interaction, update_hex_view)
+                    .chain()
+                    .run_if(resource_exists::<HexView>()),
+            )
+                .chain(),
+        );
+    }
+}
+
+const BYTES_PER_ROW: usize = 16;
+const VISIBLE_ROWS: usize = 16;
+
+/// Colors for bytes of the hovered field, the selected byte and
+/// bytes belonging to any parsed field
+const HOVERED_COLOR: Color = Color::rgba(0.9, 0.7, 0.1, 0.6);
+const SELECTED_COLOR: Color = Color::rgba(0.8, 0.2, 0.2, 0.8);
+const FIELD_COLOR: Color = Color::rgba(0.2, 0.4, 0.8, 0.3);
+
+/// Event requesting a file be opened in the hex view
+#[derive(Event)]
+pub struct OpenHexView {
+    pub path: String,
+}
+
+/// File currently shown in the hex view
+#[derive(Resource)]
+pub struct HexView {
+    pub path: String,
+    pub bytes: Vec<u8>,
+    /// Fields parsed from the file
+    pub fields: Vec<FieldSpan>,
+    /// Row shown at the top of the panel
+    pub first_row: usize,
+    /// Offset of the byte under the cursor
+    pub hovered: Option<usize>,
+    /// Offset of the last clicked byte
+    pub selected: Option<usize>,
+    pub visible: bool,
+}
+
+impl HexView {
+    pub fn new(path: impl Into<String>, bytes: Vec<u8>, fields: Vec<FieldSpan>) -> Self {
+        Self {
+            path: path.into(),
+            bytes,
+            fields,
+            first_row: 0,
+            hovered: None,
+            selected: None,
+            visible: true,
+        }
+    }
+
+    /// Opens a file from the game filesystem
+    pub fn open(fs: &GameFs, registry: &FormatRegistry, path: &str) -> Result<Self, FsError> {
+        let bytes = fs.read(path)?;
+        Ok(Self::from_bytes(registry, path, bytes))
+    }
+
+    /// Creates a view of the provided file contents, the fields come from the
+    /// matching format handler. Files that fail to parse are still shown
+    /// as they're usually the ones worth looking at
+    pub fn from_bytes(registry: &FormatRegistry, path: &str, bytes: Vec<u8>) -> Self {
+        let fields = match registry
+            .find(path, &bytes)
+            .map(|handler| handler.parse(&bytes))
+        {
+            Some(Ok(parsed)) => parsed.fields(),
+            Some(Err(err)) => {
+                warn!("Failed to parse {} for hex view: {}", path, err);
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+
+        Self::new(path, bytes, fields)
+    }
+
+    fn row_count(&self) -> usize {
+        self.bytes.len().div_ceil(BYTES_PER_ROW)
+    }
+
+    /// Offset of the byte shown in the cell at the provided index
+    fn cell_offset(&self, index: usize) -> usize {
+        self.first_row * BYTES_PER_ROW + index
+    }
+}
+
+/// Marker for the hex view panel
+#[derive(Component)]
+struct HexPanel;
+
+/// Label showing the offset of a row
+#[derive(Component)]
+struct HexRowLabel(usize);
+
+/// Cell showing a single byte, the index is relative to the first visible byte
+#[derive(Component)]
+struct HexCell(usize);
+
+#[derive(Component)]
+struct HexCellText(usize);
+
+/// Marker for the text describing the hovered and selected bytes
+#[derive(Component)]
+struct HexInfoText;
+
+fn init_hex_view(mut commands: Commands) {
+    let style = TextStyle {
+        font_size: 12.0,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(32.0),
+                left: Val::Px(8.0),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .insert(HexPanel)
+        .with_children(|parent| {
+            for row in 0..VISIBLE_ROWS {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section("", style.clone()).with_style(Style {
+                                width: Val::Px(64.0),
+                                ..default()
+                            }),
+                            HexRowLabel(row),
+                        ));
+
+                        for column in 0..BYTES_PER_ROW {
+                            let index = row * BYTES_PER_ROW + column;
+                            parent
+                                .spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            width: Val::Px(20.0),
+                                            justify_content: JustifyContent::Center,
+                                            ..default()
+                                        },
+                                        background_color: Color::NONE.into(),
+                                        ..default()
+                                    },
+                                    HexCell(index),
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn((
+                                        TextBundle::from_section("", style.clone()),
+                                        HexCellText(index),
+                                    ));
+                                });
+                        }
+                    });
+            }
+
+            parent.spawn((
+                TextBundle::from_section("", style.clone()).with_style(Style {
+                    margin: UiRect::top(Val::Px(4.0)),
+                    ..default()
+                }),
+                HexInfoText,
+            ));
+        });
+}
+
+fn open_hex_view(
+    mut commands: Commands,
+    mut events: EventReader<OpenHexView>,
+    game_fs: Res<GameFs>,
+    registry: Res<FormatRegistry>,
+) {
+    // Only the most recent request matters
+    let Some(event) = events.read().last() else {
+        return;
+    };
+
+    match HexView::open(&game_fs, &registry, &event.path) {
+        Ok(view) => commands.insert_resource(view),
+        Err(err) => error!("Failed to open {} in hex view: {}", event.path, err),
+    }
+}
+
+/// System opening files dropped onto the window from outside the game data
+fn open_dropped_file(
+    mut commands: Commands,
+    mut events: EventReader<FileDragAndDrop>,
+    registry: Res<FormatRegistry>,
+) {
+    for event in events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+
+        match std::fs::read(path_buf) {
+            Ok(bytes) => {
+                let path = path_buf.to_string_lossy();
+                commands.insert_resource(HexView::from_bytes(&registry, &path, bytes));
+            }
+            Err(err) => error!("Failed to open {} in hex view: {}", path_buf.display(), err),
+        }
+    }
+}
+
+fn update_hex_input(keys: Res<Input<KeyCode>>, mut view: ResMut<HexView>) {
+    if keys.just_pressed(KeyCode::H) {
+        view.visible = !view.visible;
+    }
+
+    let max_row = view.row_count().saturating_sub(VISIBLE_ROWS);
+    if keys.just_pressed(KeyCode::Down) && view.first_row < max_row {
+        view.first_row += 1;
+    } else if keys.just_pressed(KeyCode::Up) && view.first_row > 0 {
+        view.first_row -= 1;
+    }
+}
+
+/// System tracking which byte is hovered and clicked
+fn update_hex_interaction(
+    mut view: ResMut<HexView>,
+    cells: Query<(&Interaction, &HexCell), Changed<Interaction>>,
+) {
+    for (interaction, cell) in cells.iter() {
+        let offset = view.cell_offset(cell.0);
+        if offset >= view.bytes.len() {
+            continue;
+        }
+
+        match interaction {
+            Interaction::Pressed => {
+                view.hovered = Some(offset);
+                view.selected = Some(offset);
+            }
+            Interaction::Hovered => view.hovered = Some(offset),
+            Interaction::None if view.hovered == Some(offset) => view.hovered = None,
+            Interaction::None => {}
+        }
+    }
+}
+
+/// Describes the hovered field and the selected byte
+fn describe(view: &HexView) -> String {
+    let mut out = String::new();
+
+    if let Some(field) = view
+        .hovered
+        .and_then(|offset| field_at(&view.fields, offset))
+    {
+        out.push_str(&format!(
+            "{}: {:#x}..{:#x} ({} bytes)\n",
+            field.name,
+            field.range.start,
+            field.range.end,
+            field.range.len()
+        ));
+    }
+
+    let Some(offset) = view.selected else {
+        return out;
+    };
+
+    match field_at(&view.fields, offset) {
+        Some(field) => out.push_str(&format!("Selected {:#x} in {}\n", offset, field.name)),
+        None => {
+            out.push_str(&format!("Unknown bytes at {:#x}\n", offset));
+            for value in interpret(&view.bytes, offset) {
+                out.push_str(&format!("  {:<10} {}\n", value.label, value.value));
+            }
+        }
+    }
+
+    out
+}
+
+/// System redrawing the panel when the view changes
+fn update_hex_view(
+    view: Res<HexView>,
+    mut panel: Query<&mut Visibility, With<HexPanel>>,
+    mut cells: Query<(&HexCell, &mut BackgroundColor)>,
+    mut texts: ParamSet<(
+        Query<(&HexCellText, &mut Text)>,
+        Query<(&HexRowLabel, &mut Text)>,
+        Query<&mut Text, With<HexInfoText>>,
+    )>,
+) {
+    if !view.is_changed() {
+        return;
+    }
+
+    if let Ok(mut visibility) = panel.get_single_mut() {
+        *visibility = if view.visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    let hovered_field = view
+        .hovered
+        .and_then(|offset| field_at(&view.fields, offset));
+
+    for (cell, mut color) in cells.iter_mut() {
+        let offset = view.cell_offset(cell.0);
+
+        *color = if view.selected == Some(offset) {
+            SELECTED_COLOR
+        } else if hovered_field.is_some_and(|field| field.range.contains(&offset)) {
+            HOVERED_COLOR
+        } else if field_at(&view.fields, offset).is_some() {
+            FIELD_COLOR
+        } else {
+            Color::NONE
+        }
+        .into();
+    }
+
+    for (cell, mut text) in texts.p0().iter_mut() {
+        let offset = view.cell_offset(cell.0);
+        text.sections[0].value = view
+            .bytes
+            .get(offset)
+            .map(|value| format!("{:02X}", value))
+            .unwrap_or_default();
+    }
+
+    for (label, mut text) in texts.p1().iter_mut() {
+        let row = view.first_row + label.0;
+        text.sections[0].value = if row < view.row_count() {
+            format!("{:08X}", row * BYTES_PER_ROW)
+        } else {
+            String::new()
+        };
+    }
+
+    if let Ok(mut text) = texts.p2().get_single_mut() {
+        text.sections[0].value = describe(&view);
+    }
+}
@@ -0,0 +1,547 @@
+use bevy::{input::mouse::MouseWheel, prelude::*, render::primitives::Aabb};
+
+use crate::formats::mesh::{asset::ApeAsset, lights::MeshLightKind};
+
+use super::{ape::ApeInstance, lod::LodLevel, selection::Selected, skeleton::Skeleton};
+
+/// Plugin showing the structure of the selected mesh as a tree of its
+/// segments, bones, materials with their texture layers, LODs, vertex
+/// buffers and lights. Clicking a row outlines the geometry it belongs to,
+/// I toggles the panel and the mouse wheel scrolls it
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inspector>();
+        app.add_systems(Startup, init_inspector_panel);
+        app.add_systems(
+            Update,
+            (
+                update_inspector_input,
+                rebuild_inspector_tree,
+                update_inspector_interaction,
+                update_inspector_panel,
+                draw_inspector_highlight,
+            )
+                .chain(),
+        );
+    }
+}
+
+const VISIBLE_ROWS: usize = 32;
+/// Indent of each level of the tree
+const INDENT: &str = "  ";
+
+const ROW_SELECTED_COLOR: Color = Color::rgba(0.8, 0.6, 0.1, 0.6);
+const ROW_HOVERED_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.1);
+/// Color of the outlines drawn around the highlighted geometry
+const HIGHLIGHT_COLOR: Color = Color::ORANGE;
+/// Size of the axes drawn at a highlighted bone
+const BONE_AXIS_LENGTH: f32 = 0.1;
+
+/// Part of the mesh a row of the tree belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InspectorTarget {
+    None,
+    Segment(usize),
+    Bone(usize),
+    Material(usize),
+    Lod(u8),
+    DisplayList(usize),
+    VertexBuffer(usize),
+}
+
+/// Row of the tree
+#[derive(Debug, Clone)]
+pub struct InspectorNode {
+    pub depth: usize,
+    pub label: String,
+    pub target: InspectorTarget,
+}
+
+#[derive(Resource, Default)]
+pub struct Inspector {
+    pub visible: bool,
+    /// Instance entity and the asset the tree was built from
+    pub source: Option<(Entity, AssetId<ApeAsset>)>,
+    pub nodes: Vec<InspectorNode>,
+    /// Row shown at the top of the panel
+    pub first_row: usize,
+    /// Index of the clicked node
+    pub selected: Option<usize>,
+    hovered: Option<usize>,
+}
+
+impl Inspector {
+    /// Part of the mesh the clicked row belongs to
+    pub fn target(&self) -> InspectorTarget {
+        self.selected
+            .and_then(|index| self.nodes.get(index))
+            .map(|node| node.target)
+            .unwrap_or(InspectorTarget::None)
+    }
+}
+
+#[derive(Component)]
+struct InspectorPanel;
+
+/// Button of a visible row
+#[derive(Component)]
+struct InspectorRow(usize);
+
+#[derive(Component)]
+struct InspectorRowText(usize);
+
+fn init_inspector_panel(mut commands: Commands) {
+    let style = TextStyle {
+        font_size: 14.0,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(8.0),
+                    right: Val::Px(8.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(4.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            // Tracks hovering so the wheel only scrolls over the panel
+            Interaction::default(),
+            InspectorPanel,
+        ))
+        .with_children(|parent| {
+            for row in 0..VISIBLE_ROWS {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            background_color: Color::NONE.into(),
+                            ..default()
+                        },
+                        InspectorRow(row),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section("", style.clone()),
+                            InspectorRowText(row),
+                        ));
+                    });
+            }
+        });
+}
+
+fn update_inspector_input(
+    keys: Res<Input<KeyCode>>,
+    mut wheel: EventReader<MouseWheel>,
+    panel: Query<&Interaction, With<InspectorPanel>>,
+    mut inspector: ResMut<Inspector>,
+) {
+    if keys.just_pressed(KeyCode::I) {
+        inspector.visible = !inspector.visible;
+    }
+
+    let hovered = panel
+        .get_single()
+        .is_ok_and(|interaction| *interaction != Interaction::None);
+    let scroll: f32 = wheel.read().map(|event| event.y).sum();
+    if !inspector.visible || !hovered || scroll == 0.0 {
+        return;
+    }
+
+    let max_row = inspector.nodes.len().saturating_sub(VISIBLE_ROWS);
+    inspector.first_row = if scroll > 0.0 {
+        inspector.first_row.saturating_sub(1)
+    } else {
+        (inspector.first_row + 1).min(max_row)
+    };
+}
+
+/// System rebuilding the tree when the selection changes or the mesh of
+/// the selection finishes loading or is reloaded
+fn rebuild_inspector_tree(
+    mut inspector: ResMut<Inspector>,
+    mut events: EventReader<AssetEvent<ApeAsset>>,
+    apes: Res<Assets<ApeAsset>>,
+    meshes: Res<Assets<Mesh>>,
+    selected: Query<(Entity, &ApeInstance), With<Selected>>,
+) {
+    let source = selected
+        .get_single()
+        .ok()
+        .map(|(entity, instance)| (entity, instance.0.id()));
+    let reloaded = events.read().any(|event| match (event, source) {
+        (
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id },
+            Some((_, asset)),
+        ) => *id == asset,
+        _ => false,
+    });
+    if source == inspector.source && !reloaded {
+        return;
+    }
+
+    inspector.source = source;
+    inspector.first_row = 0;
+    inspector.selected = None;
+    inspector.hovered = None;
+    inspector.nodes = source
+        .and_then(|(_, asset)| apes.get(asset))
+        .map(|ape| inspector_tree(ape, &meshes))
+        .unwrap_or_default();
+}
+
+/// Builds the rows of the tree for the mesh
+fn inspector_tree(ape: &ApeAsset, meshes: &Assets<Mesh>) -> Vec<InspectorNode> {
+    let mut nodes = Vec::new();
+    let mut push = |depth: usize, label: String, target: InspectorTarget| {
+        nodes.push(InspectorNode {
+            depth,
+            label,
+            target,
+        })
+    };
+
+    push(0, ape.name.clone(), InspectorTarget::None);
+
+    push(
+        1,
+        format!("Segments ({})", ape.segments.len()),
+        InspectorTarget::None,
+    );
+    for (index, segment) in ape.segments.iter().enumerate() {
+        push(
+            2,
+            format!(
+                "Segment {} radius {:.2} bones {:?}",
+                index, segment.radius, segment.bones
+            ),
+            InspectorTarget::Segment(index),
+        );
+    }
+
+    push(
+        1,
+        format!("Bones ({})", ape.skeleton.len()),
+        InspectorTarget::None,
+    );
+    for (index, bone) in ape.skeleton.iter().enumerate() {
+        let parent = match bone.parent {
+            Some(parent) => format!(" -> {}", ape.skeleton[parent].name),
+            None => String::new(),
+        };
+        let skinned = if bone.skinned { " (skinned)" } else { "" };
+        push(
+            2,
+            format!("{}: {}{}{}", index, bone.name, parent, skinned),
+            InspectorTarget::Bone(index),
+        );
+    }
+
+    push(
+        1,
+        format!("Materials ({})", ape.material_sources.len()),
+        InspectorTarget::None,
+    );
+    for (index, source) in ape.material_sources.iter().enumerate() {
+        push(
+            2,
+            format!(
+                "Material {} shaders {}/{}/{} flags {:#06x}",
+                index,
+                source.light_shader_index,
+                source.specular_shader_index,
+                source.surface_shader_index,
+                source.flags
+            ),
+            InspectorTarget::Material(index),
+        );
+        for layer in &source.layers {
+            let mut label = format!("Layer {}", layer.tex_layer_id);
+            if !layer.pages.is_empty() {
+                label.push_str(&format!(" {}", layer.pages.join(", ")));
+            }
+            if layer.pages.len() > 1 {
+                label.push_str(&format!(" flip every {}", layer.frames_per_flip));
+            }
+            if layer.scroll_per_second != Vec2::ZERO {
+                label.push_str(&format!(" scroll {}", layer.scroll_per_second));
+            }
+            if layer.rotation_per_second != 0.0 {
+                label.push_str(&format!(" rotate {}", layer.rotation_per_second));
+            }
+            push(3, label, InspectorTarget::Material(index));
+        }
+    }
+
+    push(
+        1,
+        format!("LODs ({})", ape.lod_distances.len()),
+        InspectorTarget::None,
+    );
+    for (lod, distance) in ape.lod_distances.iter().enumerate() {
+        let lod = lod as u8;
+        push(
+            2,
+            format!("LOD {} from {:.1}", lod, distance),
+            InspectorTarget::Lod(lod),
+        );
+        for (index, display_list) in ape
+            .display_lists
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| value.lod_id == lod)
+        {
+            let decoded = ape.lod_meshes.iter().position(|value| {
+                value.lod_id == lod
+                    && value.material == display_list.material
+                    && value.part_id == display_list.part_id
+            });
+            let streamed = if display_list.streamed {
+                " streamed"
+            } else {
+                ""
+            };
+            let undecoded = if decoded.is_none() {
+                " (not decoded)"
+            } else {
+                ""
+            };
+            push(
+                3,
+                format!(
+                    "Display list {} material {} part {} {} bytes{}{}",
+                    index,
+                    display_list.material,
+                    display_list.part_id,
+                    display_list.bytes.len(),
+                    streamed,
+                    undecoded
+                ),
+                decoded
+                    .map(InspectorTarget::DisplayList)
+                    .unwrap_or(InspectorTarget::None),
+            );
+        }
+    }
+
+    push(
+        1,
+        format!("Vertex buffers ({})", ape.meshes.len()),
+        InspectorTarget::None,
+    );
+    for (index, handle) in ape.meshes.iter().enumerate() {
+        let label = match meshes.get(handle) {
+            Some(mesh) => format!(
+                "Vertex buffer {} {} vertices {} indices",
+                index,
+                mesh.count_vertices(),
+                mesh.indices().map_or(0, |indices| indices.len())
+            ),
+            None => format!("Vertex buffer {}", index),
+        };
+        push(2, label, InspectorTarget::VertexBuffer(index));
+    }
+
+    push(
+        1,
+        format!("Lights ({})", ape.lights.len()),
+        InspectorTarget::None,
+    );
+    for light in &ape.lights {
+        let kind = match light.kind {
+            MeshLightKind::Point { .. } => "point",
+            MeshLightKind::Spot { .. } => "spot",
+            MeshLightKind::Directional => "directional",
+            MeshLightKind::Ambient => "ambient",
+        };
+        push(2, format!("{} {}", light.name, kind), InspectorTarget::None);
+    }
+
+    nodes
+}
+
+/// System tracking which row is hovered and clicked
+fn update_inspector_interaction(
+    mut inspector: ResMut<Inspector>,
+    rows: Query<(&Interaction, &InspectorRow), Changed<Interaction>>,
+) {
+    for (interaction, row) in rows.iter() {
+        let index = inspector.first_row + row.0;
+        if index >= inspector.nodes.len() {
+            continue;
+        }
+
+        match interaction {
+            Interaction::Pressed => {
+                inspector.selected = (inspector.selected != Some(index)).then_some(index);
+            }
+            Interaction::Hovered => inspector.hovered = Some(index),
+            Interaction::None if inspector.hovered == Some(index) => inspector.hovered = None,
+            Interaction::None => {}
+        }
+    }
+}
+
+fn update_inspector_panel(
+    inspector: Res<Inspector>,
+    mut panel: Query<&mut Visibility, With<InspectorPanel>>,
+    mut rows: Query<(&InspectorRow, &mut BackgroundColor)>,
+    mut texts: Query<(&InspectorRowText, &mut Text)>,
+) {
+    if !inspector.is_changed() {
+        return;
+    }
+
+    let visible = inspector.visible && !inspector.nodes.is_empty();
+    for mut visibility in panel.iter_mut() {
+        *visibility = if visible {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    for (row, mut color) in rows.iter_mut() {
+        let index = inspector.first_row + row.0;
+        *color = if inspector.selected == Some(index) {
+            ROW_SELECTED_COLOR
+        } else if inspector.hovered == Some(index) {
+            ROW_HOVERED_COLOR
+        } else {
+            Color::NONE
+        }
+        .into();
+    }
+
+    for (row, mut text) in texts.iter_mut() {
+        text.sections[0].value = inspector
+            .nodes
+            .get(inspector.first_row + row.0)
+            .map(|node| format!("{}{}", INDENT.repeat(node.depth), node.label))
+            .unwrap_or_default();
+    }
+}
+
+/// Whether the entity is the ancestor or one of its descendants
+fn is_within(entity: Entity, ancestor: Entity, parents: &Query<&Parent>) -> bool {
+    let mut current = entity;
+    loop {
+        if current == ancestor {
+            return true;
+        }
+        match parents.get(current) {
+            Ok(parent) => current = parent.get(),
+            Err(_) => return false,
+        }
+    }
+}
+
+/// System outlining the geometry of the clicked row
+#[allow(clippy::too_many_arguments)]
+fn draw_inspector_highlight(
+    mut gizmos: Gizmos,
+    inspector: Res<Inspector>,
+    apes: Res<Assets<ApeAsset>>,
+    instances: Query<(&GlobalTransform, Option<&Skeleton>)>,
+    bones: Query<&GlobalTransform>,
+    parents: Query<&Parent>,
+    lods: Query<&LodLevel>,
+    geometry: Query<(
+        Entity,
+        &Handle<Mesh>,
+        &Handle<StandardMaterial>,
+        &GlobalTransform,
+        &Aabb,
+    )>,
+) {
+    let target = inspector.target();
+    if !inspector.visible || target == InspectorTarget::None {
+        return;
+    }
+    let Some((instance, asset)) = inspector.source else {
+        return;
+    };
+    let (Some(ape), Ok((instance_transform, skeleton))) =
+        (apes.get(asset), instances.get(instance))
+    else {
+        return;
+    };
+
+    match target {
+        InspectorTarget::Segment(index) => {
+            if let Some(segment) = ape.segments.get(index) {
+                let (scale, rotation, _) = instance_transform.to_scale_rotation_translation();
+                gizmos.sphere(
+                    instance_transform.transform_point(segment.center),
+                    rotation,
+                    segment.radius * scale.max_element(),
+                    HIGHLIGHT_COLOR,
+                );
+            }
+            return;
+        }
+        InspectorTarget::Bone(index) => {
+            let transform = skeleton
+                .and_then(|skeleton| skeleton.joints.get(index))
+                .and_then(|joint| bones.get(*joint).ok());
+            if let Some(transform) = transform {
+                let position = transform.translation();
+                gizmos.line(
+                    position,
+                    position + transform.right() * BONE_AXIS_LENGTH,
+                    Color::RED,
+                );
+                gizmos.line(
+                    position,
+                    position + transform.up() * BONE_AXIS_LENGTH,
+                    Color::GREEN,
+                );
+                gizmos.line(
+                    position,
+                    position + transform.back() * BONE_AXIS_LENGTH,
+                    Color::BLUE,
+                );
+                gizmos.sphere(
+                    position,
+                    Quat::IDENTITY,
+                    BONE_AXIS_LENGTH * 0.5,
+                    HIGHLIGHT_COLOR,
+                );
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    // Hidden LODs are outlined too so every LOD can be inspected
+    for (entity, mesh, material, transform, aabb) in geometry.iter() {
+        let matches = match target {
+            InspectorTarget::Material(index) => ape.materials.get(index) == Some(material),
+            InspectorTarget::Lod(lod) => parents
+                .get(entity)
+                .and_then(|parent| lods.get(parent.get()))
+                .is_ok_and(|level| level.0 == lod),
+            InspectorTarget::DisplayList(index) => {
+                ape.lod_meshes.get(index).map(|value| &value.mesh) == Some(mesh)
+            }
+            InspectorTarget::VertexBuffer(index) => ape.meshes.get(index) == Some(mesh),
+            _ => false,
+        };
+        if !matches || !is_within(entity, instance, &parents) {
+            continue;
+        }
+
+        let transform = transform.compute_transform()
+            * Transform::from_translation(aabb.center.into())
+                .with_scale((aabb.half_extents * 2.0).into());
+        gizmos.cuboid(transform, HIGHLIGHT_COLOR);
+    }
+}
@@ -0,0 +1,339 @@
+use bevy::{
+    core_pipeline::core_3d::Transparent3d,
+    ecs::{
+        query::QueryItem,
+        system::{lifetimeless::*, SystemParamItem},
+    },
+    pbr::{
+        MeshPipeline, MeshPipelineKey, RenderMeshInstances, SetMeshBindGroup, SetMeshViewBindGroup,
+    },
+    prelude::*,
+    render::{
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        mesh::{GpuBufferInfo, MeshVertexBufferLayout},
+        render_asset::RenderAssets,
+        render_phase::{
+            AddRenderCommand, DrawFunctions, PhaseItem, RenderCommand, RenderCommandResult,
+            RenderPhase, SetItemPipeline, TrackedRenderPass,
+        },
+        render_resource::*,
+        renderer::RenderDevice,
+        view::{ExtractedView, NoFrustumCulling},
+        Render, RenderApp, RenderSet,
+    },
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::formats::mesh::asset::ApeAsset;
+
+/// Plugin drawing every instance of a scattered prop in a single draw
+/// call, entities with a [PropScatter] get a child for each mesh of the
+/// prop which draws all of its instances
+pub struct InstancingPlugin;
+
+impl Plugin for InstancingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<PropInstances>::default());
+        app.add_systems(Update, spawn_prop_scatters);
+        app.sub_app_mut(RenderApp)
+            .add_render_command::<Transparent3d, DrawPropInstances>()
+            .init_resource::<SpecializedMeshPipelines<PropInstancePipeline>>()
+            .add_systems(
+                Render,
+                (
+                    queue_prop_instances.in_set(RenderSet::QueueMeshes),
+                    prepare_prop_instance_buffers.in_set(RenderSet::PrepareResources),
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp)
+            .init_resource::<PropInstancePipeline>();
+    }
+}
+
+/// Shader drawing the instances
+const INSTANCING_SHADER: &str = "shaders/instanced_props.wgsl";
+/// First shader location of the instance attributes, placed after the
+/// locations the mesh pipeline uses for mesh attributes
+const INSTANCE_LOCATION: u32 = 8;
+/// Color the props are drawn with
+const PROP_COLOR: Color = Color::rgb(0.6, 0.65, 0.55);
+
+/// Per instance data uploaded to the instance buffer
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct PropInstance {
+    /// World position in xyz and uniform scale in w
+    pub position_scale: [f32; 4],
+    /// Rotation quaternion
+    pub rotation: [f32; 4],
+    pub color: [f32; 4],
+}
+
+impl PropInstance {
+    pub fn from_transform(transform: &Transform, color: Color) -> Self {
+        Self {
+            position_scale: transform.translation.extend(transform.scale.x).to_array(),
+            rotation: transform.rotation.to_array(),
+            color: color.as_rgba_f32(),
+        }
+    }
+}
+
+/// Instances of a prop to spawn once the prop mesh has loaded
+#[derive(Component, Debug, Clone)]
+pub struct PropScatter {
+    pub prop: Handle<ApeAsset>,
+    pub instances: Vec<PropInstance>,
+}
+
+impl PropScatter {
+    /// Creates a scatter of the prop at the provided transforms, only the
+    /// x scale of each transform is used
+    pub fn new(prop: Handle<ApeAsset>, transforms: &[Transform]) -> Self {
+        Self {
+            prop,
+            instances: transforms
+                .iter()
+                .map(|transform| PropInstance::from_transform(transform, PROP_COLOR))
+                .collect(),
+        }
+    }
+}
+
+/// Instances drawn by the mesh of the entity
+#[derive(Component, Debug, Clone, Deref)]
+pub struct PropInstances(pub Vec<PropInstance>);
+
+impl ExtractComponent for PropInstances {
+    type Query = &'static PropInstances;
+    type Filter = ();
+    type Out = Self;
+
+    fn extract_component(item: QueryItem<'_, Self::Query>) -> Option<Self> {
+        Some(item.clone())
+    }
+}
+
+/// Marker for scatters that have had their meshes spawned
+#[derive(Component)]
+struct PropScatterSpawned;
+
+/// System spawning the instanced meshes of scatters once their prop has
+/// loaded, the scatters are respawned when the prop is reloaded
+fn spawn_prop_scatters(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<ApeAsset>>,
+    apes: Res<Assets<ApeAsset>>,
+    scatters: Query<(Entity, &PropScatter, Has<PropScatterSpawned>)>,
+) {
+    let modified: Vec<AssetId<ApeAsset>> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, scatter, spawned) in scatters.iter() {
+        let id = scatter.prop.id();
+        if spawned && !modified.contains(&id) {
+            continue;
+        }
+        let Some(ape) = apes.get(id) else {
+            continue;
+        };
+
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .insert(PropScatterSpawned)
+            .with_children(|parent| {
+                for mesh in &ape.meshes {
+                    // Instances are spread across the level so the mesh bounds
+                    // can't be used for culling
+                    parent.spawn((
+                        mesh.clone(),
+                        SpatialBundle::default(),
+                        PropInstances(scatter.instances.clone()),
+                        NoFrustumCulling,
+                    ));
+                }
+            });
+    }
+}
+
+/// Instance buffer of an entity in the render world
+#[derive(Component)]
+struct PropInstanceBuffer {
+    buffer: Buffer,
+    length: usize,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn queue_prop_instances(
+    draw_functions: Res<DrawFunctions<Transparent3d>>,
+    pipeline: Res<PropInstancePipeline>,
+    msaa: Res<Msaa>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<PropInstancePipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    meshes: Res<RenderAssets<Mesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    instanced: Query<Entity, With<PropInstances>>,
+    mut views: Query<(&ExtractedView, &mut RenderPhase<Transparent3d>)>,
+) {
+    let draw_function = draw_functions.read().id::<DrawPropInstances>();
+    let msaa_key = MeshPipelineKey::from_msaa_samples(msaa.samples());
+
+    for (view, mut phase) in &mut views {
+        let view_key = msaa_key | MeshPipelineKey::from_hdr(view.hdr);
+        let rangefinder = view.rangefinder3d();
+
+        for entity in &instanced {
+            let Some(mesh_instance) = render_mesh_instances.get(&entity) else {
+                continue;
+            };
+            let Some(mesh) = meshes.get(mesh_instance.mesh_asset_id) else {
+                continue;
+            };
+
+            let key = view_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology);
+            let pipeline = match pipelines.specialize(&pipeline_cache, &pipeline, key, &mesh.layout)
+            {
+                Ok(value) => value,
+                Err(err) => {
+                    error!("Failed to specialize prop pipeline: {}", err);
+                    continue;
+                }
+            };
+
+            phase.add(Transparent3d {
+                entity,
+                pipeline,
+                draw_function,
+                distance: rangefinder
+                    .distance_translation(&mesh_instance.transforms.transform.translation),
+                batch_range: 0..1,
+                dynamic_offset: None,
+            });
+        }
+    }
+}
+
+fn prepare_prop_instance_buffers(
+    mut commands: Commands,
+    instanced: Query<(Entity, &PropInstances)>,
+    render_device: Res<RenderDevice>,
+) {
+    for (entity, instances) in &instanced {
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("prop instance buffer"),
+            contents: bytemuck::cast_slice(instances.as_slice()),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+        commands.entity(entity).insert(PropInstanceBuffer {
+            buffer,
+            length: instances.len(),
+        });
+    }
+}
+
+/// Mesh pipeline with the instance buffer added and the shaders replaced
+#[derive(Resource)]
+struct PropInstancePipeline {
+    shader: Handle<Shader>,
+    mesh_pipeline: MeshPipeline,
+}
+
+impl FromWorld for PropInstancePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let shader = world.resource::<AssetServer>().load(INSTANCING_SHADER);
+        let mesh_pipeline = world.resource::<MeshPipeline>().clone();
+        Self {
+            shader,
+            mesh_pipeline,
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for PropInstancePipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+
+        let attribute = |index: u32| VertexAttribute {
+            format: VertexFormat::Float32x4,
+            offset: VertexFormat::Float32x4.size() * index as u64,
+            shader_location: INSTANCE_LOCATION + index,
+        };
+
+        descriptor.vertex.shader = self.shader.clone();
+        descriptor.vertex.buffers.push(VertexBufferLayout {
+            array_stride: std::mem::size_of::<PropInstance>() as u64,
+            step_mode: VertexStepMode::Instance,
+            attributes: vec![attribute(0), attribute(1), attribute(2)],
+        });
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader = self.shader.clone();
+        }
+        Ok(descriptor)
+    }
+}
+
+type DrawPropInstances = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    DrawMeshInstanced,
+);
+
+/// Draws the mesh of an entity once for each of its instances
+struct DrawMeshInstanced;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
+    type Param = (SRes<RenderAssets<Mesh>>, SRes<RenderMeshInstances>);
+    type ViewWorldQuery = ();
+    type ItemWorldQuery = Read<PropInstanceBuffer>;
+
+    #[inline]
+    fn render<'w>(
+        item: &P,
+        _view: (),
+        instance_buffer: &'w PropInstanceBuffer,
+        (meshes, render_mesh_instances): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(mesh_instance) = render_mesh_instances.get(&item.entity()) else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(gpu_mesh) = meshes.into_inner().get(mesh_instance.mesh_asset_id) else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+
+        let instances = 0..instance_buffer.length as u32;
+        match &gpu_mesh.buffer_info {
+            GpuBufferInfo::Indexed {
+                buffer,
+                index_format,
+                count,
+            } => {
+                pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(0..*count, 0, instances);
+            }
+            GpuBufferInfo::NonIndexed => {
+                pass.draw(0..gpu_mesh.vertex_count, instances);
+            }
+        }
+        RenderCommandResult::Success
+    }
+}
@@ -0,0 +1,390 @@
+use bevy::{
+    asset::RecursiveDependencyLoadState,
+    audio::{PlaybackMode, Volume},
+    prelude::*,
+};
+
+use crate::{
+    cancel::CancelToken,
+    formats::{
+        gamedata::GameData,
+        level::{LevelLight, LevelLightKind, WldAsset, WldAssetLoader, WldLoadSettings},
+    },
+    fs::{vfs_path, GameFs},
+};
+
+use super::{
+    ambience::AmbienceSettings, ape::ApeInstance, cull_distance::CullDistance,
+    instancing::PropScatter, motif::MotifColor, perf_hud::MeshSpawnSet, skybox::SkyboxSettings,
+    splines::LevelSpline,
+};
+
+/// Plugin spawning the level selected in the [SkyboxSettings] from its
+/// `Levels/{level}.level` description when one exists in the game data.
+/// While a level is loading a panel with a Cancel button is shown, which
+/// stops the level and its meshes from loading
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SkyboxSettings>();
+        app.init_asset::<WldAsset>();
+        app.init_asset_loader::<WldAssetLoader>();
+        app.add_systems(Startup, init_loading_panel);
+        app.add_systems(Update, (update_loading_panel, cancel_level_loads).chain());
+        app.add_systems(
+            Update,
+            (
+                load_level_scene,
+                spawn_wld_scenes.in_set(WldSpawner).in_set(MeshSpawnSet),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Set for the system instantiating the contents of [WldScene] entities
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WldSpawner;
+
+/// Entity the contents of a level are spawned as children of, the
+/// children are respawned whenever the level is reloaded
+#[derive(Component, Debug, Clone)]
+pub struct WldScene(pub Handle<WldAsset>);
+
+/// Token cancelling the load of the level of a [WldScene]
+#[derive(Component, Debug, Clone)]
+pub struct LevelLoad(pub CancelToken);
+
+/// Marker for the level objects and world geometry the player collides with
+#[derive(Component, Debug, Clone, Copy)]
+pub struct LevelCollision;
+
+/// Game data of a spawned level object or entity
+#[derive(Component, Debug, Clone)]
+pub struct LevelGameData(pub GameData);
+
+/// Marker for scenes that have had their contents spawned
+#[derive(Component)]
+struct WldSpawned;
+
+/// Path of the description of a level
+fn level_path(level: &str) -> String {
+    format!("Levels/{}.level", level)
+}
+
+/// System replacing the level scene when the level changes
+fn load_level_scene(
+    mut commands: Commands,
+    settings: Res<SkyboxSettings>,
+    asset_server: Res<AssetServer>,
+    game_fs: Res<GameFs>,
+    scenes: Query<Entity, With<WldScene>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for entity in scenes.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let Some(path) = settings.level.as_deref().map(level_path) else {
+        return;
+    };
+    if !game_fs.contains(&path) {
+        return;
+    }
+
+    let cancel = CancelToken::default();
+    let token = cancel.clone();
+    let handle = asset_server
+        .load_with_settings(vfs_path(&path), move |settings: &mut WldLoadSettings| {
+            settings.cancel = token.clone()
+        });
+
+    commands.spawn((
+        SpatialBundle::default(),
+        WldScene(handle),
+        LevelLoad(cancel),
+        Name::new(path),
+    ));
+}
+
+/// Marker for the panel shown while a level is loading
+#[derive(Component)]
+struct LoadingPanel;
+
+/// Marker for the text of the loading panel
+#[derive(Component)]
+struct LoadingText;
+
+/// Marker for the button cancelling the level loads
+#[derive(Component)]
+struct CancelLoadButton;
+
+fn init_loading_panel(mut commands: Commands) {
+    let style = TextStyle {
+        font_size: 14.0,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(8.0),
+                    right: Val::Px(8.0),
+                    column_gap: Val::Px(8.0),
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            LoadingPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::from_section("", style.clone()), LoadingText));
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                            ..default()
+                        },
+                        background_color: Color::DARK_GRAY.into(),
+                        ..default()
+                    },
+                    CancelLoadButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Cancel", style));
+                });
+        });
+}
+
+/// Whether the level or any of the meshes it places are still loading
+fn is_loading(asset_server: &AssetServer, scene: &WldScene) -> bool {
+    !matches!(
+        asset_server.get_recursive_dependency_load_state(scene.0.id()),
+        Some(RecursiveDependencyLoadState::Loaded | RecursiveDependencyLoadState::Failed) | None
+    )
+}
+
+/// System showing the loading panel while any level is loading
+fn update_loading_panel(
+    asset_server: Res<AssetServer>,
+    scenes: Query<(&WldScene, &Name), With<LevelLoad>>,
+    mut panel: Query<&mut Visibility, With<LoadingPanel>>,
+    mut text: Query<&mut Text, With<LoadingText>>,
+) {
+    let loading: Vec<&str> = scenes
+        .iter()
+        .filter(|(scene, _)| is_loading(&asset_server, scene))
+        .map(|(_, name)| name.as_str())
+        .collect();
+
+    let value = match loading.is_empty() {
+        true => Visibility::Hidden,
+        false => Visibility::Inherited,
+    };
+    for mut visibility in panel.iter_mut() {
+        if *visibility != value {
+            *visibility = value;
+        }
+    }
+
+    if let (Ok(mut text), false) = (text.get_single_mut(), loading.is_empty()) {
+        text.sections[0].value = format!("Loading {}", loading.join(", "));
+    }
+}
+
+/// System cancelling the levels that are still loading when the cancel
+/// button is pressed, their scenes are removed along with anything spawned
+fn cancel_level_loads(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<CancelLoadButton>)>,
+    scenes: Query<(Entity, &WldScene, &LevelLoad)>,
+) {
+    if !buttons
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    for (entity, scene, load) in scenes.iter() {
+        if !is_loading(&asset_server, scene) {
+            continue;
+        }
+
+        load.0.cancel();
+        commands.entity(entity).despawn_recursive();
+        info!("Cancelled loading level {:?}", scene.0.path());
+    }
+}
+
+/// System spawning the objects, scattered props, lights and world geometry
+/// of scenes once their level has loaded
+fn spawn_wld_scenes(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<WldAsset>>,
+    levels: Res<Assets<WldAsset>>,
+    asset_server: Res<AssetServer>,
+    ambience: Option<Res<AmbienceSettings>>,
+    scenes: Query<(Entity, &WldScene, Has<WldSpawned>)>,
+) {
+    let ambient_volume = ambience.map_or(1.0, |settings| {
+        if settings.muted {
+            0.0
+        } else {
+            settings.ambient_volume
+        }
+    });
+
+    let modified: Vec<AssetId<WldAsset>> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, scene, spawned) in scenes.iter() {
+        let id = scene.0.id();
+        if spawned && !modified.contains(&id) {
+            continue;
+        }
+        let Some(wld) = levels.get(id) else {
+            continue;
+        };
+
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .insert(WldSpawned)
+            .with_children(|parent| {
+                for segment in &wld.segments {
+                    parent.spawn((
+                        SpatialBundle::default(),
+                        ApeInstance(segment.clone()),
+                        LevelCollision,
+                    ));
+                }
+
+                for (object, handle) in wld.level.objects.iter().zip(&wld.objects) {
+                    let mut object_entity = parent.spawn((
+                        SpatialBundle::from_transform(object.transform),
+                        ApeInstance(handle.clone()),
+                        Name::new(object.mesh.clone()),
+                        LevelCollision,
+                    ));
+                    if let Some(distance) = object.cull_distance {
+                        object_entity.insert(CullDistance(distance));
+                    }
+                    if !object.data.entries.is_empty() {
+                        object_entity.insert(LevelGameData(object.data.clone()));
+                    }
+                }
+
+                for entity in &wld.level.entities {
+                    let name = entity.data.entity_class().unwrap_or("Entity").to_string();
+                    parent
+                        .spawn((
+                            SpatialBundle::from_transform(entity.transform),
+                            LevelGameData(entity.data.clone()),
+                            Name::new(name),
+                        ))
+                        .with_children(|parent| {
+                            for sound in entity.data.ambient_sounds() {
+                                parent.spawn((
+                                    AudioBundle {
+                                        source: asset_server.load(vfs_path(&sound.path)),
+                                        settings: PlaybackSettings {
+                                            mode: PlaybackMode::Loop,
+                                            volume: Volume::new_relative(
+                                                ambient_volume * sound.volume,
+                                            ),
+                                            ..default()
+                                        },
+                                    },
+                                    Name::new(sound.name),
+                                ));
+                            }
+                        });
+                }
+
+                for (group, handle) in wld.level.scatter.iter().zip(&wld.scatter) {
+                    parent.spawn((
+                        SpatialBundle::default(),
+                        PropScatter::new(handle.clone(), &group.instances),
+                        Name::new(group.mesh.clone()),
+                    ));
+                }
+
+                for spline in &wld.level.splines {
+                    parent.spawn((
+                        SpatialBundle::default(),
+                        LevelSpline(spline.clone()),
+                        Name::new("Spline"),
+                    ));
+                }
+
+                for light in &wld.level.lights {
+                    spawn_light(parent, light);
+                }
+            });
+    }
+}
+
+/// Spawns the bevy light matching a level light
+fn spawn_light(parent: &mut ChildBuilder, light: &LevelLight) {
+    let direction = light.direction.try_normalize().unwrap_or(Vec3::NEG_Y);
+    let transform = Transform::from_translation(light.position)
+        .looking_to(direction, direction.any_orthonormal_vector());
+
+    let mut entity = match light.kind {
+        LevelLightKind::Point => parent.spawn(PointLightBundle {
+            point_light: PointLight {
+                color: light.color,
+                intensity: light.intensity,
+                range: light.range,
+                ..default()
+            },
+            transform,
+            ..default()
+        }),
+        LevelLightKind::Spot { angle } => parent.spawn(SpotLightBundle {
+            spot_light: SpotLight {
+                color: light.color,
+                intensity: light.intensity,
+                range: light.range,
+                outer_angle: angle,
+                inner_angle: angle * 0.8,
+                ..default()
+            },
+            transform,
+            ..default()
+        }),
+        LevelLightKind::Directional => parent.spawn(DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                color: light.color,
+                illuminance: light.intensity,
+                ..default()
+            },
+            transform,
+            ..default()
+        }),
+    };
+
+    if light.motif != 0 {
+        entity.insert(MotifColor {
+            index: light.motif,
+            base: light.color,
+        });
+    }
+}
@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+
+use crate::formats::report::{LoadReports, Severity};
+
+/// Plugin showing the findings from loading assets in a log panel,
+/// F1-F3 filter the panel by severity and L toggles it
+pub struct LoadLogPlugin;
+
+impl Plugin for LoadLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadReports>();
+        app.init_resource::<LoadLogSettings>();
+        app.add_systems(Startup, init_load_log);
+        app.add_systems(Update, (update_load_log_settings, update_load_log).chain());
+    }
+}
+
+/// Settings for the load log panel
+#[derive(Resource)]
+pub struct LoadLogSettings {
+    pub visible: bool,
+    /// Least severe findings shown in the panel
+    pub min_severity: Severity,
+}
+
+impl Default for LoadLogSettings {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            min_severity: Severity::Warning,
+        }
+    }
+}
+
+/// Marker for the log panel
+#[derive(Component)]
+struct LoadLogPanel;
+
+/// Marker for the text of the log panel
+#[derive(Component)]
+struct LoadLogText;
+
+fn severity_color(severity: Severity) -> Color {
+    match severity {
+        Severity::Info => Color::GRAY,
+        Severity::Warning => Color::YELLOW,
+        Severity::Error => Color::RED,
+    }
+}
+
+fn init_load_log(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(8.0),
+                left: Val::Px(8.0),
+                max_width: Val::Percent(60.0),
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .insert(LoadLogPanel)
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), LoadLogText));
+        });
+}
+
+fn update_load_log_settings(keys: Res<Input<KeyCode>>, mut settings: ResMut<LoadLogSettings>) {
+    if keys.just_pressed(KeyCode::L) {
+        settings.visible = !settings.visible;
+    }
+
+    if keys.just_pressed(KeyCode::F1) {
+        settings.min_severity = Severity::Info;
+    } else if keys.just_pressed(KeyCode::F2) {
+        settings.min_severity = Severity::Warning;
+    } else if keys.just_pressed(KeyCode::F3) {
+        settings.min_severity = Severity::Error;
+    }
+}
+
+/// System rebuilding the panel text when the reports or filters change
+fn update_load_log(
+    reports: Res<LoadReports>,
+    settings: Res<LoadLogSettings>,
+    mut text: Query<&mut Text, With<LoadLogText>>,
+    mut panel: Query<&mut Visibility, With<LoadLogPanel>>,
+) {
+    if !reports.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let (Ok(mut text), Ok(mut visibility)) = (text.get_single_mut(), panel.get_single_mut()) else {
+        return;
+    };
+
+    let style = |color| TextStyle {
+        font_size: 14.0,
+        color,
+        ..default()
+    };
+
+    let sections: Vec<TextSection> = reports
+        .reports()
+        .iter()
+        .flat_map(|report| {
+            report.filtered(settings.min_severity).map(|finding| {
+                TextSection::new(
+                    format!(
+                        "[{}] {}{}: {}\n",
+                        finding.severity,
+                        report.asset,
+                        if report.partial { " (partial)" } else { "" },
+                        finding.message
+                    ),
+                    style(severity_color(finding.severity)),
+                )
+            })
+        })
+        .collect();
+
+    *visibility = if settings.visible && !sections.is_empty() {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    text.sections = sections;
+}
@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+
+use super::lod_rings::LodDistances;
+
+/// Plugin switching the visible LOD of meshes by their distance from the
+/// camera. Each LOD of a mesh is a [LodLevel] child of the mesh entity, O
+/// cycles through forcing each LOD for every mesh and back to automatic
+pub struct LodPlugin;
+
+impl Plugin for LodPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LodSettings>();
+        app.add_systems(Startup, init_lod_panel);
+        app.add_systems(
+            Update,
+            (update_lod_input, select_lods, update_lod_panel).chain(),
+        );
+    }
+}
+
+/// Most LODs a mesh can have, matches FDATA_MAX_LOD_MESH_COUNT
+const MAX_LOD_COUNT: u8 = 8;
+
+/// Settings for the LOD selection
+#[derive(Resource, Debug, Default)]
+pub struct LodSettings {
+    /// LOD shown for every mesh instead of picking by distance
+    pub forced: Option<u8>,
+}
+
+/// Entity holding the geometry of a single LOD of its parent mesh
+#[derive(Component, Debug, Clone, Copy)]
+pub struct LodLevel(pub u8);
+
+/// Marker for the text of the LOD override panel
+#[derive(Component)]
+struct LodPanelText;
+
+fn init_lod_panel(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::default().with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+        LodPanelText,
+    ));
+}
+
+fn update_lod_input(keys: Res<Input<KeyCode>>, mut settings: ResMut<LodSettings>) {
+    if !keys.just_pressed(KeyCode::O) {
+        return;
+    }
+
+    settings.forced = match settings.forced {
+        None => Some(0),
+        Some(lod) if lod + 1 < MAX_LOD_COUNT => Some(lod + 1),
+        Some(_) => None,
+    };
+}
+
+/// System showing the active LOD of each mesh and hiding the rest, meshes
+/// without the wanted LOD show the closest more detailed LOD they have
+fn select_lods(
+    settings: Res<LodSettings>,
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+    meshes: Query<(&GlobalTransform, &LodDistances, &Children)>,
+    mut levels: Query<(&LodLevel, &mut Visibility)>,
+) {
+    let camera = camera.get_single().ok().map(GlobalTransform::translation);
+
+    for (transform, distances, children) in meshes.iter() {
+        let wanted = match (settings.forced, camera) {
+            (Some(lod), _) => lod,
+            (None, Some(camera)) => {
+                distances.active_lod(camera.distance(transform.translation())) as u8
+            }
+            (None, None) => continue,
+        };
+
+        let available: Vec<u8> = children
+            .iter()
+            .filter_map(|child| levels.get(*child).ok())
+            .map(|(level, _)| level.0)
+            .collect();
+        let active = available
+            .iter()
+            .copied()
+            .filter(|lod| *lod <= wanted)
+            .max()
+            .or_else(|| available.iter().copied().min());
+
+        for child in children.iter() {
+            let Ok((level, mut visibility)) = levels.get_mut(*child) else {
+                continue;
+            };
+
+            let value = if Some(level.0) == active {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+
+            // Only write on change so change detection isn't triggered every frame
+            if *visibility != value {
+                *visibility = value;
+            }
+        }
+    }
+}
+
+fn update_lod_panel(settings: Res<LodSettings>, mut text: Query<&mut Text, With<LodPanelText>>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+
+    text.sections = match settings.forced {
+        Some(lod) => vec![TextSection::new(
+            format!("Forced LOD {}", lod),
+            TextStyle {
+                font_size: 14.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )],
+        None => Vec::new(),
+    };
+}
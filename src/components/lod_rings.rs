@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+
+use crate::locale::Locale;
+
+use super::selection::Selected;
+
+/// Plugin drawing the LOD switch distances of the selected mesh as
+/// rings around it, along with a label of the active LOD
+pub struct LodRingsPlugin;
+
+impl Plugin for LodRingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, init_lod_label);
+        app.add_systems(Update, (draw_lod_rings, update_lod_label));
+    }
+}
+
+/// Colors used for each successive LOD ring
+const RING_COLORS: [Color; 4] = [Color::GREEN, Color::YELLOW, Color::ORANGE, Color::RED];
+
+/// Distances at which each LOD of a mesh switches in
+#[derive(Component, Debug, Clone)]
+pub struct LodDistances(pub Vec<f32>);
+
+impl LodDistances {
+    /// Index of the LOD that's active at the provided distance
+    pub fn active_lod(&self, distance: f32) -> usize {
+        self.0
+            .iter()
+            .rposition(|value| distance >= *value)
+            .unwrap_or_default()
+    }
+}
+
+/// Marker for the text showing the active LOD
+#[derive(Component)]
+struct LodLabel;
+
+fn init_lod_label(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+        LodLabel,
+    ));
+}
+
+/// System drawing a ring on the ground plane for each LOD distance
+fn draw_lod_rings(
+    mut gizmos: Gizmos,
+    selected: Query<(&GlobalTransform, &LodDistances), With<Selected>>,
+) {
+    for (transform, distances) in selected.iter() {
+        let position = transform.translation();
+
+        for (index, distance) in distances.0.iter().enumerate() {
+            // LOD 0 usually switches in at zero which has nothing to draw
+            if *distance <= 0.0 {
+                continue;
+            }
+
+            let color = RING_COLORS[index.min(RING_COLORS.len() - 1)];
+            gizmos.circle(position, Vec3::Y, *distance, color);
+        }
+    }
+}
+
+/// System updating the label with the LOD active at the camera distance
+fn update_lod_label(
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+    selected: Query<(&GlobalTransform, &LodDistances, Option<&Name>), With<Selected>>,
+    mut label: Query<&mut Text, With<LodLabel>>,
+    locale: Res<Locale>,
+) {
+    let Ok(mut text) = label.get_single_mut() else {
+        return;
+    };
+
+    let (Ok(camera), Ok((transform, distances, name))) =
+        (camera.get_single(), selected.get_single())
+    else {
+        text.sections[0].value.clear();
+        return;
+    };
+
+    let distance = camera.translation().distance(transform.translation());
+    let lod = distances.active_lod(distance);
+
+    let name = name
+        .map(|value| value.as_str())
+        .unwrap_or_else(|| locale.get("lod.unnamed"));
+
+    text.sections[0].value = locale.format(
+        "lod.label",
+        &[&name, &lod, &distances.0.len(), &format!("{:.1}", distance)],
+    );
+}
@@ -0,0 +1,101 @@
+use bevy::{prelude::*, render::primitives::Aabb};
+
+use crate::formats::types::RawSphere;
+
+/// Plugin culling individual materials of merged level meshes using the
+/// bound spheres stored with each material, rather than the bounds of the
+/// whole mesh
+pub struct MaterialCullingPlugin;
+
+impl Plugin for MaterialCullingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, apply_material_bounds);
+    }
+}
+
+/// Sphere bounding the verts of a material in model space, the sphere is
+/// moved into world space by the transform of the entity when culled
+#[derive(Component, Debug, Clone, Copy)]
+pub struct MaterialBounds {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl MaterialBounds {
+    pub fn from_sphere(sphere: &RawSphere) -> Self {
+        Self {
+            center: Vec3::from(&sphere.position),
+            radius: sphere.radius,
+        }
+    }
+
+    /// Box enclosing the sphere, used by the frustum culling
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_min_max(
+            self.center - Vec3::splat(self.radius),
+            self.center + Vec3::splat(self.radius),
+        )
+    }
+
+    /// Bounds along with their box, spawning the box with the bounds
+    /// stops the bounds of the mesh being computed on the first frame
+    pub fn bundle(self) -> (MaterialBounds, Aabb) {
+        (self, self.aabb())
+    }
+}
+
+/// System replacing the bounds computed from the mesh with the material
+/// bounds, the frustum culling then skips materials outside the view
+fn apply_material_bounds(
+    mut commands: Commands,
+    bounds: Query<(Entity, &MaterialBounds), Changed<MaterialBounds>>,
+) {
+    for (entity, bounds) in bounds.iter() {
+        commands.entity(entity).insert(bounds.aabb());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{
+        math::Affine3A,
+        prelude::*,
+        render::primitives::{Aabb, Frustum},
+    };
+
+    use crate::formats::types::{RawSphere, RawVec3f};
+
+    use super::{apply_material_bounds, MaterialBounds};
+
+    fn sphere(z: f32) -> RawSphere {
+        RawSphere {
+            radius: 1.0,
+            position: RawVec3f { x: 0.0, y: 0.0, z },
+        }
+    }
+
+    #[test]
+    fn test_material_outside_frustum() {
+        // Both materials belong to one merged mesh spanning the frustum
+        let mut world = World::new();
+        let inside = world
+            .spawn(MaterialBounds::from_sphere(&sphere(-10.0)))
+            .id();
+        let outside = world.spawn(MaterialBounds::from_sphere(&sphere(10.0))).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(apply_material_bounds);
+        schedule.run(&mut world);
+
+        // Camera at the origin looking down -Z, culled the same way as the
+        // visibility checks of the renderer
+        let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 100.0);
+        let frustum = Frustum::from_view_projection(&projection);
+        let visible = |entity: Entity| {
+            let aabb = world.get::<Aabb>(entity).expect("bounds applied");
+            frustum.intersects_obb(aabb, &Affine3A::IDENTITY, true, true)
+        };
+        assert!(visible(inside));
+        assert!(!visible(outside));
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+
+use crate::formats::mesh::lights::{MeshLight, MeshLightKind};
+
+use super::{motif::MotifColor, skeleton::Skeleton};
+
+/// Plugin moving the lights of meshes onto the bones they're attached to
+/// once the skeleton of the mesh has been spawned
+pub struct MeshLightPlugin;
+
+impl Plugin for MeshLightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, attach_lights_to_bones);
+    }
+}
+
+/// Luminous power of a full intensity point or spot light
+const LIGHT_LUMENS: f32 = 800.0;
+/// Illuminance of a full intensity directional light
+const DIRECTIONAL_LUX: f32 = 10000.0;
+
+/// Light waiting for the bone of its skeleton to be spawned, the light is
+/// spawned as a child of the skeleton entity
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BoneAttachment(pub usize);
+
+/// Spawns the bevy light matching a mesh light, ambient lights are skipped
+/// since the renderer only has a global ambient light
+pub fn spawn_mesh_light(parent: &mut ChildBuilder, light: &MeshLight) {
+    let transform = light.transform;
+    let mut entity = match light.kind {
+        MeshLightKind::Point { range } => parent.spawn(PointLightBundle {
+            point_light: PointLight {
+                color: light.color,
+                intensity: LIGHT_LUMENS,
+                range,
+                shadows_enabled: light.cast_shadows,
+                ..default()
+            },
+            transform,
+            ..default()
+        }),
+        MeshLightKind::Spot {
+            range,
+            inner_angle,
+            outer_angle,
+        } => parent.spawn(SpotLightBundle {
+            spot_light: SpotLight {
+                color: light.color,
+                intensity: LIGHT_LUMENS,
+                range,
+                inner_angle,
+                outer_angle,
+                shadows_enabled: light.cast_shadows,
+                ..default()
+            },
+            transform,
+            ..default()
+        }),
+        MeshLightKind::Directional => parent.spawn(DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                color: light.color,
+                illuminance: DIRECTIONAL_LUX,
+                shadows_enabled: light.cast_shadows,
+                ..default()
+            },
+            transform,
+            ..default()
+        }),
+        MeshLightKind::Ambient => return,
+    };
+
+    entity.insert(Name::new(light.name.clone()));
+    if light.motif != 0 {
+        entity.insert(MotifColor {
+            index: light.motif,
+            base: light.color,
+        });
+    }
+    if let Some(bone) = light.parent_bone {
+        entity.insert(BoneAttachment(bone));
+    }
+}
+
+/// System moving attached lights onto their bone once it exists
+fn attach_lights_to_bones(
+    mut commands: Commands,
+    lights: Query<(Entity, &BoneAttachment, &Parent)>,
+    skeletons: Query<&Skeleton>,
+) {
+    for (entity, attachment, parent) in lights.iter() {
+        let Ok(skeleton) = skeletons.get(parent.get()) else {
+            continue;
+        };
+
+        let mut entity = commands.entity(entity);
+        entity.remove::<BoneAttachment>();
+        if let Some(bone) = skeleton.joints.get(attachment.0) {
+            entity.set_parent(*bone);
+        }
+    }
+}
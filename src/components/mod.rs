@@ -1,2 +1,34 @@
+pub mod ambience;
+pub mod ape;
+pub mod asset_tracking;
+#[cfg(feature = "ffmpeg")]
 pub mod audio;
+pub mod backfaces;
+pub mod cull_distance;
+pub mod decals;
+pub mod heatmap;
+pub mod hex_view;
+pub mod inspector;
+pub mod instancing;
+pub mod level;
+pub mod load_log;
+pub mod lod;
+pub mod lod_rings;
+pub mod material_culling;
+pub mod mesh_lights;
+pub mod motif;
+pub mod parts;
+pub mod perf_hud;
+pub mod play_mode;
+pub mod replay;
+pub mod selection;
+pub mod skeleton;
+pub mod skybox;
+pub mod sound_events;
+pub mod splines;
+pub mod texture_animation;
+pub mod texture_filtering;
+pub mod texture_streaming;
+pub mod timeline;
+pub mod validation;
 pub mod video;
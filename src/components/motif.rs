@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+
+use crate::{
+    formats::{
+        motif::{parse_motifs, MotifTable},
+        report::{LoadReport, LoadReports},
+    },
+    fs::GameFs,
+};
+
+/// Plugin animating the colors of lights and materials that reference a
+/// color motif, the motifs are read from the motif listing in the game data
+pub struct MotifPlugin;
+
+impl Plugin for MotifPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadReports>();
+        app.init_resource::<Motifs>();
+        app.add_systems(Startup, load_motifs);
+        app.add_systems(Update, (animate_light_motifs, animate_material_motifs));
+    }
+}
+
+/// Motif listing loaded from the game data when present
+const MOTIF_FILE: &str = "motifs.txt";
+
+/// Motifs from the listing
+#[derive(Resource, Default)]
+pub struct Motifs(pub MotifTable);
+
+/// Color of a light or material along with the motif tinting it. Added to
+/// entities with a light to animate the light color, or to entities with a
+/// [StandardMaterial] to animate the base color of the material
+#[derive(Component, Debug, Clone, Copy)]
+pub struct MotifColor {
+    pub index: u32,
+    /// Color before it's tinted by the motif
+    pub base: Color,
+}
+
+fn load_motifs(game_fs: Res<GameFs>, mut motifs: ResMut<Motifs>, mut reports: ResMut<LoadReports>) {
+    if !game_fs.contains(MOTIF_FILE) {
+        return;
+    }
+
+    let mut report = LoadReport::new(MOTIF_FILE);
+    match game_fs
+        .read_to_string(MOTIF_FILE)
+        .map_err(|err| err.to_string())
+        .and_then(|value| parse_motifs(&value).map_err(|err| err.to_string()))
+    {
+        Ok(table) => {
+            report.info(format!("listed {} color motifs", table.motifs.len()));
+            motifs.0 = table;
+        }
+        Err(err) => report.error(err),
+    }
+    reports.add(report);
+}
+
+fn animate_light_motifs(
+    time: Res<Time>,
+    motifs: Res<Motifs>,
+    mut lights: Query<(
+        &MotifColor,
+        Option<&mut PointLight>,
+        Option<&mut SpotLight>,
+        Option<&mut DirectionalLight>,
+    )>,
+) {
+    let seconds = time.elapsed_seconds();
+
+    for (motif, point, spot, directional) in lights.iter_mut() {
+        let color = motifs.0.apply(motif.base, motif.index, seconds);
+        if let Some(mut light) = point {
+            light.color = color;
+        }
+        if let Some(mut light) = spot {
+            light.color = color;
+        }
+        if let Some(mut light) = directional {
+            light.color = color;
+        }
+    }
+}
+
+fn animate_material_motifs(
+    time: Res<Time>,
+    motifs: Res<Motifs>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    targets: Query<(&MotifColor, &Handle<StandardMaterial>)>,
+) {
+    let seconds = time.elapsed_seconds();
+
+    for (motif, handle) in targets.iter() {
+        let color = motifs.0.apply(motif.base, motif.index, seconds);
+        // Only write changed colors so unchanged materials aren't reuploaded
+        if materials
+            .get(handle)
+            .is_some_and(|material| material.base_color != color)
+        {
+            if let Some(material) = materials.get_mut(handle) {
+                material.base_color = color;
+            }
+        }
+    }
+}
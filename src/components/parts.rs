@@ -0,0 +1,166 @@
+use bevy::prelude::*;
+
+/// Plugin for previewing damage states, parts of a mesh can be hidden
+/// by their part ID. Keys 0-9 toggle the matching part IDs
+pub struct PartVisibilityPlugin;
+
+impl Plugin for PartVisibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PartVisibility>();
+        app.add_systems(Startup, init_parts_panel);
+        app.add_systems(
+            Update,
+            (update_part_input, apply_part_visibility, update_parts_panel).chain(),
+        );
+    }
+}
+
+/// Highest part ID that fits in a part mask
+pub const MAX_PART_ID: u8 = 31;
+
+/// Keys toggling the part IDs matching their index
+const PART_KEYS: [KeyCode; 10] = [
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+/// Mask of the part IDs the entity belongs to, for materials this is
+/// their part_id_mask and for clusters and bones the bit of their part_id
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PartMask(pub u32);
+
+impl PartMask {
+    pub fn from_part_id(part_id: u8) -> Self {
+        if part_id > MAX_PART_ID {
+            return PartMask(0);
+        }
+        PartMask(1 << part_id)
+    }
+}
+
+/// Part IDs that are currently shown
+#[derive(Resource)]
+pub struct PartVisibility {
+    enabled: u32,
+}
+
+impl Default for PartVisibility {
+    fn default() -> Self {
+        Self { enabled: u32::MAX }
+    }
+}
+
+impl PartVisibility {
+    pub fn is_enabled(&self, part_id: u8) -> bool {
+        part_id <= MAX_PART_ID && self.enabled & (1 << part_id) != 0
+    }
+
+    pub fn set(&mut self, part_id: u8, enabled: bool) {
+        if part_id > MAX_PART_ID {
+            return;
+        }
+
+        if enabled {
+            self.enabled |= 1 << part_id;
+        } else {
+            self.enabled &= !(1 << part_id);
+        }
+    }
+
+    pub fn toggle(&mut self, part_id: u8) {
+        self.set(part_id, !self.is_enabled(part_id));
+    }
+
+    pub fn show_all(&mut self) {
+        self.enabled = u32::MAX;
+    }
+
+    /// Checks if anything using the provided parts should be shown
+    pub fn is_visible(&self, mask: PartMask) -> bool {
+        self.enabled & mask.0 != 0
+    }
+}
+
+/// Marker for the text of the parts panel
+#[derive(Component)]
+struct PartsText;
+
+fn init_parts_panel(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::default().with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..default()
+        }),
+        PartsText,
+    ));
+}
+
+fn update_part_input(keys: Res<Input<KeyCode>>, mut parts: ResMut<PartVisibility>) {
+    for (part_id, key) in PART_KEYS.iter().enumerate() {
+        if keys.just_pressed(*key) {
+            parts.toggle(part_id as u8);
+        }
+    }
+}
+
+/// System hiding entities whose parts are all disabled
+fn apply_part_visibility(
+    parts: Res<PartVisibility>,
+    mut entities: Query<(Ref<PartMask>, &mut Visibility)>,
+) {
+    for (mask, mut visibility) in entities.iter_mut() {
+        if !parts.is_changed() && !mask.is_changed() {
+            continue;
+        }
+
+        *visibility = if parts.is_visible(*mask) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// System listing the part IDs in use along with their state
+fn update_parts_panel(
+    parts: Res<PartVisibility>,
+    masks: Query<Ref<PartMask>>,
+    mut text: Query<&mut Text, With<PartsText>>,
+) {
+    if !parts.is_changed() && !masks.iter().any(|mask| mask.is_changed()) {
+        return;
+    }
+
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+
+    let used = masks.iter().fold(0, |used, mask| used | mask.0);
+    let style = |color| TextStyle {
+        font_size: 14.0,
+        color,
+        ..default()
+    };
+
+    text.sections = (0..=MAX_PART_ID)
+        .filter(|part_id| used & (1 << part_id) != 0)
+        .map(|part_id| {
+            let (state, color) = if parts.is_enabled(part_id) {
+                ("shown", Color::WHITE)
+            } else {
+                ("hidden", Color::GRAY)
+            };
+            TextSection::new(format!("Part {}: {}\n", part_id, state), style(color))
+        })
+        .collect();
+}
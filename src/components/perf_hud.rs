@@ -0,0 +1,193 @@
+use bevy::{
+    diagnostic::{
+        Diagnostic, DiagnosticId, Diagnostics, DiagnosticsStore, FrameTimeDiagnosticsPlugin,
+        RegisterDiagnostic,
+    },
+    prelude::*,
+    render::view::VisibilitySystems,
+    ui::UiSystem,
+    utils::{HashMap, Instant},
+};
+
+use super::video::VideoDecodeSet;
+
+/// Plugin showing a HUD with the frame time broken down by subsystem,
+/// F4 toggles it. Timings are the CPU time spent in each subsystem's
+/// systems, Bevy doesn't expose the timings of individual render passes
+/// so GPU time is only visible as part of the overall frame time
+pub struct PerfHudPlugin;
+
+impl Plugin for PerfHudPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+
+        for subsystem in Subsystem::ALL {
+            app.register_diagnostic(
+                Diagnostic::new(subsystem.diagnostic_id(), subsystem.name(), HISTORY_LENGTH)
+                    .with_suffix("ms"),
+            );
+        }
+
+        app.init_resource::<PerfHudSettings>();
+        app.init_resource::<SubsystemTimers>();
+        app.add_systems(Startup, init_perf_hud);
+        app.add_systems(
+            Update,
+            (
+                begin_timing(Subsystem::VideoUpload).before(VideoDecodeSet),
+                end_timing(Subsystem::VideoUpload).after(VideoDecodeSet),
+                begin_timing(Subsystem::MeshSpawn).before(MeshSpawnSet),
+                end_timing(Subsystem::MeshSpawn).after(MeshSpawnSet),
+                (update_perf_hud_settings, update_perf_hud).chain(),
+            ),
+        );
+        app.add_systems(
+            PostUpdate,
+            (
+                begin_timing(Subsystem::Culling).before(VisibilitySystems::CheckVisibility),
+                end_timing(Subsystem::Culling).after(VisibilitySystems::CheckVisibility),
+                begin_timing(Subsystem::Ui).before(UiSystem::Layout),
+                end_timing(Subsystem::Ui).after(UiSystem::Layout),
+            ),
+        );
+    }
+}
+
+/// Number of measurements kept for smoothing the timings
+const HISTORY_LENGTH: usize = 60;
+/// Random base for the subsystem diagnostic IDs, each subsystem
+/// is offset from this by its index
+const DIAGNOSTIC_ID_BASE: u128 = 0x6f5a1c2e8b3d4e719a0621c4d0b7e300;
+
+/// Set for systems spawning meshes into the world, these are timed
+/// as mesh spawning in the HUD
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MeshSpawnSet;
+
+/// Subsystems with their own timing in the HUD
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Subsystem {
+    /// Decoding video frames and copying them into their images
+    VideoUpload,
+    MeshSpawn,
+    /// Frustum culling and visibility checks
+    Culling,
+    /// UI layout
+    Ui,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 4] = [
+        Subsystem::VideoUpload,
+        Subsystem::MeshSpawn,
+        Subsystem::Culling,
+        Subsystem::Ui,
+    ];
+
+    pub fn diagnostic_id(&self) -> DiagnosticId {
+        DiagnosticId::from_u128(DIAGNOSTIC_ID_BASE + *self as u128)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Subsystem::VideoUpload => "video decode/upload",
+            Subsystem::MeshSpawn => "mesh spawn",
+            Subsystem::Culling => "culling",
+            Subsystem::Ui => "ui",
+        }
+    }
+}
+
+/// Settings for the performance HUD
+#[derive(Resource, Default)]
+pub struct PerfHudSettings {
+    pub visible: bool,
+}
+
+/// Start times of the subsystems currently being timed
+#[derive(Resource, Default)]
+struct SubsystemTimers(HashMap<Subsystem, Instant>);
+
+/// Marker for the text of the HUD
+#[derive(Component)]
+struct PerfHudText;
+
+/// Creates a system marking the start of the subsystem
+fn begin_timing(subsystem: Subsystem) -> impl FnMut(ResMut<SubsystemTimers>) {
+    move |mut timers: ResMut<SubsystemTimers>| {
+        timers.0.insert(subsystem, Instant::now());
+    }
+}
+
+/// Creates a system recording the time since the start of the subsystem
+fn end_timing(subsystem: Subsystem) -> impl FnMut(ResMut<SubsystemTimers>, Diagnostics) {
+    move |mut timers: ResMut<SubsystemTimers>, mut diagnostics: Diagnostics| {
+        if let Some(start) = timers.0.remove(&subsystem) {
+            diagnostics.add_measurement(subsystem.diagnostic_id(), || {
+                start.elapsed().as_secs_f64() * 1000.0
+            });
+        }
+    }
+}
+
+fn init_perf_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::default().with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            // Corners are taken by the other panels
+            left: Val::Percent(40.0),
+            ..default()
+        }),
+        PerfHudText,
+    ));
+}
+
+fn update_perf_hud_settings(keys: Res<Input<KeyCode>>, mut settings: ResMut<PerfHudSettings>) {
+    if keys.just_pressed(KeyCode::F4) {
+        settings.visible = !settings.visible;
+    }
+}
+
+fn update_perf_hud(
+    settings: Res<PerfHudSettings>,
+    diagnostics: Res<DiagnosticsStore>,
+    mut text: Query<(&mut Text, &mut Visibility), With<PerfHudText>>,
+) {
+    let Ok((mut text, mut visibility)) = text.get_single_mut() else {
+        return;
+    };
+
+    if !settings.visible {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Inherited;
+
+    let smoothed = |id: DiagnosticId| {
+        diagnostics
+            .get(id)
+            .and_then(|diagnostic| diagnostic.smoothed())
+    };
+    let style = TextStyle {
+        font_size: 14.0,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    let mut lines = vec![format!(
+        "{:.0} fps, {:.2} ms frame",
+        smoothed(FrameTimeDiagnosticsPlugin::FPS).unwrap_or_default(),
+        smoothed(FrameTimeDiagnosticsPlugin::FRAME_TIME).unwrap_or_default()
+    )];
+    lines.extend(Subsystem::ALL.iter().map(|subsystem| {
+        match smoothed(subsystem.diagnostic_id()) {
+            Some(value) => format!("{}: {:.2} ms", subsystem.name(), value),
+            None => format!("{}: -", subsystem.name()),
+        }
+    }));
+
+    text.sections = vec![TextSection::new(lines.join("\n"), style)];
+}
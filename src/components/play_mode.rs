@@ -0,0 +1,299 @@
+use bevy::{input::mouse::MouseMotion, prelude::*};
+use bevy_flycam::prelude::FlyCam;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    export::collision::CollisionShape,
+    formats::level::WldAsset,
+    fs::{vfs_path, GameFs},
+};
+
+use super::{
+    ape::ApeInstance,
+    level::{LevelCollision, WldScene},
+};
+
+/// Plugin for walking around the loaded level, F5 swaps the free camera for
+/// a character placed at the first StartPoint of the level that walks on the
+/// level collision with WASD and is followed by a third person camera. There
+/// is no gameplay, it only proves the meshes, levels and collision fit
+/// together end to end
+pub struct PlayModePlugin;
+
+impl Plugin for PlayModePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<RapierPhysicsPlugin<NoUserData>>() {
+            app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default());
+        }
+        app.init_resource::<PlayModeSettings>();
+        app.add_systems(
+            Update,
+            (
+                toggle_play_mode,
+                update_play_mode,
+                add_level_colliders,
+                move_player,
+                follow_player,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Mesh drawn for the player
+const PLAYER_MESH_FILE: &str = "ape/gcdggltch00.ape";
+/// Half the height of the cylinder of the player capsule
+const PLAYER_HALF_HEIGHT: f32 = 0.5;
+const PLAYER_RADIUS: f32 = 0.4;
+/// Range the camera pitch is clamped to in radians
+const PITCH_RANGE: (f32, f32) = (-1.2, 0.6);
+
+/// Settings for the play mode
+#[derive(Resource, Debug, Clone)]
+pub struct PlayModeSettings {
+    /// Whether the player is spawned and controlled
+    pub enabled: bool,
+    /// Walking speed in units per second
+    pub walk_speed: f32,
+    /// Downwards acceleration while the player isn't grounded
+    pub gravity: f32,
+    /// Radians the camera turns per pixel of mouse movement
+    pub look_sensitivity: f32,
+    /// Distance of the camera behind the player
+    pub camera_distance: f32,
+    /// Height above the center of the player the camera looks at
+    pub camera_height: f32,
+}
+
+impl Default for PlayModeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            walk_speed: 6.0,
+            gravity: 20.0,
+            look_sensitivity: 0.003,
+            camera_distance: 6.0,
+            camera_height: 1.0,
+        }
+    }
+}
+
+/// Character controlled in play mode
+#[derive(Component, Debug, Clone, Default)]
+pub struct PlayModePlayer {
+    /// Yaw of the camera around the player
+    pub yaw: f32,
+    /// Pitch of the camera looking at the player
+    pub pitch: f32,
+    /// Current vertical speed from falling
+    pub vertical_speed: f32,
+}
+
+/// Marker for the camera following the player, the [FlyCam] marker is
+/// taken off the camera while playing so the free camera doesn't move it
+#[derive(Component)]
+struct PlayCamera;
+
+/// Marker for meshes that have had their collider built, meshes without
+/// any triangles get the marker without a collider
+#[derive(Component)]
+struct CollisionBuilt;
+
+fn toggle_play_mode(keys: Res<Input<KeyCode>>, mut settings: ResMut<PlayModeSettings>) {
+    if keys.just_pressed(KeyCode::F5) {
+        settings.enabled = !settings.enabled;
+        info!("Play mode: {}", settings.enabled);
+    }
+}
+
+/// Finds the first StartPoint of the loaded levels in world space
+fn find_start_point(
+    scenes: &Query<(&WldScene, &GlobalTransform)>,
+    levels: &Assets<WldAsset>,
+) -> Option<Transform> {
+    scenes.iter().find_map(|(scene, transform)| {
+        let start = levels.get(&scene.0)?.level.start_points.first()?;
+        Some(transform.mul_transform(*start).compute_transform())
+    })
+}
+
+/// System spawning the player and taking over the camera when play mode
+/// is entered and handing the camera back when it's left
+#[allow(clippy::too_many_arguments)]
+fn update_play_mode(
+    mut commands: Commands,
+    settings: Res<PlayModeSettings>,
+    asset_server: Res<AssetServer>,
+    game_fs: Res<GameFs>,
+    levels: Res<Assets<WldAsset>>,
+    scenes: Query<(&WldScene, &GlobalTransform)>,
+    fly_cameras: Query<(Entity, &Transform), With<FlyCam>>,
+    play_cameras: Query<Entity, With<PlayCamera>>,
+    players: Query<Entity, With<PlayModePlayer>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    if !settings.enabled {
+        for entity in players.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        for entity in play_cameras.iter() {
+            commands
+                .entity(entity)
+                .remove::<PlayCamera>()
+                .insert(FlyCam);
+        }
+        return;
+    }
+
+    if !players.is_empty() {
+        return;
+    }
+    let Ok((camera, camera_transform)) = fly_cameras.get_single() else {
+        return;
+    };
+
+    // Levels without a StartPoint start the player where the camera is
+    let start = find_start_point(&scenes, &levels).unwrap_or_else(|| {
+        warn!("Level has no StartPoint, starting the player at the camera");
+        Transform::from_translation(camera_transform.translation)
+    });
+    let (yaw, _, _) = start.rotation.to_euler(EulerRot::YXZ);
+    let feet = PLAYER_HALF_HEIGHT + PLAYER_RADIUS;
+
+    commands
+        .entity(camera)
+        .remove::<FlyCam>()
+        .insert(PlayCamera);
+    commands
+        .spawn((
+            SpatialBundle::from_transform(Transform::from_translation(
+                start.translation + Vec3::Y * feet,
+            )),
+            RigidBody::KinematicPositionBased,
+            Collider::capsule_y(PLAYER_HALF_HEIGHT, PLAYER_RADIUS),
+            KinematicCharacterController {
+                snap_to_ground: Some(CharacterLength::Absolute(0.5)),
+                ..default()
+            },
+            PlayModePlayer {
+                yaw,
+                pitch: -0.3,
+                vertical_speed: 0.0,
+            },
+            Name::new("Player"),
+        ))
+        .with_children(|parent| {
+            if !game_fs.contains(PLAYER_MESH_FILE) {
+                return;
+            }
+            parent.spawn((
+                SpatialBundle::from_transform(Transform::from_xyz(0.0, -feet, 0.0)),
+                ApeInstance(asset_server.load(vfs_path(PLAYER_MESH_FILE))),
+            ));
+        });
+}
+
+/// System building trimesh colliders for the meshes of the level from their
+/// render geometry, the kDOP collision trees haven't been decoded yet
+fn add_level_colliders(
+    mut commands: Commands,
+    settings: Res<PlayModeSettings>,
+    meshes: Res<Assets<Mesh>>,
+    targets: Query<(Entity, &Handle<Mesh>, &Parent), Without<CollisionBuilt>>,
+    level_objects: Query<Option<&Name>, With<LevelCollision>>,
+) {
+    if !settings.enabled {
+        return;
+    }
+
+    for (entity, handle, parent) in targets.iter() {
+        let Ok(name) = level_objects.get(parent.get()) else {
+            continue;
+        };
+        let Some(mesh) = meshes.get(handle) else {
+            continue;
+        };
+
+        let name = name.map(|name| name.to_string()).unwrap_or_default();
+        let mut entity = commands.entity(entity);
+        entity.insert(CollisionBuilt);
+        if let Some(shape) = CollisionShape::from_mesh(name, [0.0; 3], mesh) {
+            entity.insert(shape.to_collider());
+        }
+    }
+}
+
+/// System walking the player relative to the camera and turning the
+/// camera with the mouse
+fn move_player(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mut motion: EventReader<MouseMotion>,
+    settings: Res<PlayModeSettings>,
+    mut players: Query<(
+        &mut PlayModePlayer,
+        &mut Transform,
+        &mut KinematicCharacterController,
+        Option<&KinematicCharacterControllerOutput>,
+    )>,
+) {
+    let delta: Vec2 = motion.read().map(|event| event.delta).sum();
+    let Ok((mut player, mut transform, mut controller, output)) = players.get_single_mut() else {
+        return;
+    };
+
+    player.yaw -= delta.x * settings.look_sensitivity;
+    player.pitch =
+        (player.pitch - delta.y * settings.look_sensitivity).clamp(PITCH_RANGE.0, PITCH_RANGE.1);
+
+    let rotation = Quat::from_rotation_y(player.yaw);
+    let mut direction = Vec3::ZERO;
+    if keys.pressed(KeyCode::W) {
+        direction += rotation * Vec3::NEG_Z;
+    }
+    if keys.pressed(KeyCode::S) {
+        direction += rotation * Vec3::Z;
+    }
+    if keys.pressed(KeyCode::A) {
+        direction += rotation * Vec3::NEG_X;
+    }
+    if keys.pressed(KeyCode::D) {
+        direction += rotation * Vec3::X;
+    }
+    let direction = direction.normalize_or_zero();
+
+    // Face the way the player walks
+    if direction != Vec3::ZERO {
+        transform.look_to(direction, Vec3::Y);
+    }
+
+    let dt = time.delta_seconds();
+    if output.is_some_and(|output| output.grounded) {
+        player.vertical_speed = 0.0;
+    }
+    player.vertical_speed -= settings.gravity * dt;
+
+    controller.translation =
+        Some((direction * settings.walk_speed + Vec3::Y * player.vertical_speed) * dt);
+}
+
+/// System placing the camera behind the player looking at it
+fn follow_player(
+    settings: Res<PlayModeSettings>,
+    players: Query<(&Transform, &PlayModePlayer)>,
+    mut cameras: Query<&mut Transform, (With<PlayCamera>, Without<PlayModePlayer>)>,
+) {
+    let Ok((player_transform, player)) = players.get_single() else {
+        return;
+    };
+
+    let focus = player_transform.translation + Vec3::Y * settings.camera_height;
+    let rotation = Quat::from_euler(EulerRot::YXZ, player.yaw, player.pitch, 0.0);
+    for mut transform in cameras.iter_mut() {
+        transform.translation = focus + rotation * Vec3::new(0.0, 0.0, settings.camera_distance);
+        transform.look_at(focus, Vec3::Y);
+    }
+}
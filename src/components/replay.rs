@@ -0,0 +1,297 @@
+use std::{
+    fs::File,
+    hash::Hash,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use bevy::{
+    input::{mouse::MouseMotion, InputSystem},
+    prelude::*,
+    time::TimeUpdateStrategy,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{constants::VERSION, fs::version::GameVersion};
+
+/// Plugin recording the viewer input to a replay file or playing a replay
+/// file back, selected through the environment. Replays feed the recorded
+/// input and frame times back in place of the real input so the viewer
+/// runs the same frames as the recording, which lets bugs reported with a
+/// replay be reproduced against the same game data
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let fingerprint = app
+            .world
+            .get_resource::<GameVersion>()
+            .and_then(|version| version.fingerprint.clone());
+
+        if let Some(path) = std::env::var_os(REPLAY_ENV) {
+            let player = match ReplayPlayer::load(Path::new(&path)) {
+                Ok(value) => value,
+                Err(err) => {
+                    warn!("Failed to load replay {:?}: {}", path, err);
+                    return;
+                }
+            };
+
+            if player.header.data_fingerprint != fingerprint {
+                warn!("Replay was recorded against different game data, playback may not match");
+            }
+
+            if let Some(frame) = player.frames.first() {
+                app.insert_resource(TimeUpdateStrategy::ManualDuration(frame.delta));
+            }
+            app.insert_resource(player);
+            app.add_systems(
+                PreUpdate,
+                play_replay_input
+                    .after(InputSystem)
+                    .run_if(resource_exists::<ReplayPlayer>()),
+            );
+            app.add_systems(
+                Last,
+                advance_replay.run_if(resource_exists::<ReplayPlayer>()),
+            );
+        } else if let Some(path) = std::env::var_os(RECORD_ENV) {
+            let header = ReplayHeader {
+                format: REPLAY_FORMAT,
+                viewer_version: VERSION.to_string(),
+                data_fingerprint: fingerprint,
+            };
+            let recorder = match ReplayRecorder::create(Path::new(&path), &header) {
+                Ok(value) => value,
+                Err(err) => {
+                    warn!("Failed to create replay {:?}: {}", path, err);
+                    return;
+                }
+            };
+
+            app.insert_resource(recorder);
+            app.add_systems(
+                PreUpdate,
+                record_replay_input
+                    .after(InputSystem)
+                    .run_if(resource_exists::<ReplayRecorder>()),
+            );
+        }
+    }
+}
+
+/// Environment variable with the path to record a replay to
+const RECORD_ENV: &str = "OPENMA_RECORD";
+/// Environment variable with the path of a replay to play back
+const REPLAY_ENV: &str = "OPENMA_REPLAY";
+/// Version of the replay file layout
+const REPLAY_FORMAT: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("replay is missing its header")]
+    MissingHeader,
+    #[error("unsupported replay format {0}")]
+    UnsupportedFormat(u32),
+}
+
+/// First line of a replay file, describing what the replay was recorded
+/// against
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    pub format: u32,
+    pub viewer_version: String,
+    /// Fingerprint of the game data the replay was recorded against
+    pub data_fingerprint: Option<String>,
+}
+
+/// State of a set of buttons during a frame
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ButtonFrame<T> {
+    /// Buttons held down, including the ones just pressed
+    pub held: Vec<T>,
+    pub pressed: Vec<T>,
+    pub released: Vec<T>,
+}
+
+impl<T> ButtonFrame<T>
+where
+    T: Copy + Eq + Hash + Send + Sync + 'static,
+{
+    fn capture(input: &Input<T>) -> Self {
+        Self {
+            held: input.get_pressed().copied().collect(),
+            pressed: input.get_just_pressed().copied().collect(),
+            released: input.get_just_released().copied().collect(),
+        }
+    }
+
+    /// Replaces the state of the input with the recorded state
+    fn apply(&self, input: &mut Input<T>) {
+        input.reset_all();
+        for button in &self.held {
+            input.press(*button);
+            if !self.pressed.contains(button) {
+                input.clear_just_pressed(*button);
+            }
+        }
+        // Buttons are only released when they are held
+        for button in &self.released {
+            input.press(*button);
+            input.release(*button);
+            input.clear_just_pressed(*button);
+        }
+    }
+}
+
+/// Input of a single recorded frame, one is stored per line of the replay
+/// after the header
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    /// Real time since the previous frame
+    pub delta: Duration,
+    pub keys: ButtonFrame<KeyCode>,
+    pub mouse_buttons: ButtonFrame<MouseButton>,
+    /// Mouse movement summed across the events of the frame
+    pub mouse_motion: [f32; 2],
+}
+
+/// Replay being recorded, each frame is flushed as it's written so the
+/// replay is kept when the viewer crashes
+#[derive(Resource)]
+struct ReplayRecorder {
+    writer: BufWriter<File>,
+}
+
+impl ReplayRecorder {
+    fn create(path: &Path, header: &ReplayHeader) -> Result<Self, ReplayError> {
+        let mut recorder = Self {
+            writer: BufWriter::new(File::create(path)?),
+        };
+        recorder.write_line(header)?;
+        Ok(recorder)
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) -> Result<(), ReplayError> {
+        serde_json::to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Replay being played back
+#[derive(Resource)]
+struct ReplayPlayer {
+    header: ReplayHeader,
+    frames: Vec<ReplayFrame>,
+    /// Index of the frame being played
+    current: usize,
+}
+
+impl ReplayPlayer {
+    fn load(path: &Path) -> Result<Self, ReplayError> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+
+        let header: ReplayHeader = match lines.next() {
+            Some(line) => serde_json::from_str(&line?)?,
+            None => return Err(ReplayError::MissingHeader),
+        };
+        if header.format != REPLAY_FORMAT {
+            return Err(ReplayError::UnsupportedFormat(header.format));
+        }
+
+        let mut frames = Vec::new();
+        for line in lines {
+            let line = line?;
+            // A crash while recording can leave the last frame incomplete
+            match serde_json::from_str(&line) {
+                Ok(frame) => frames.push(frame),
+                Err(err) if err.is_eof() => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        info!(
+            "Playing replay of {} frames recorded with viewer {}",
+            frames.len(),
+            header.viewer_version
+        );
+
+        Ok(Self {
+            header,
+            frames,
+            current: 0,
+        })
+    }
+}
+
+fn record_replay_input(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    let mouse_motion = motion.read().map(|event| event.delta).sum::<Vec2>();
+    let frame = ReplayFrame {
+        delta: time.delta(),
+        keys: ButtonFrame::capture(&keys),
+        mouse_buttons: ButtonFrame::capture(&mouse_buttons),
+        mouse_motion: mouse_motion.to_array(),
+    };
+
+    if let Err(err) = recorder.write_line(&frame) {
+        warn!("Failed to write replay, recording stopped: {}", err);
+        commands.remove_resource::<ReplayRecorder>();
+    }
+}
+
+/// System replacing the real input with the input of the current frame,
+/// runs after bevy has processed the real input events
+fn play_replay_input(
+    player: Res<ReplayPlayer>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut mouse_buttons: ResMut<Input<MouseButton>>,
+    mut motion: ResMut<Events<MouseMotion>>,
+) {
+    let Some(frame) = player.frames.get(player.current) else {
+        return;
+    };
+
+    frame.keys.apply(&mut keys);
+    frame.mouse_buttons.apply(&mut mouse_buttons);
+
+    motion.clear();
+    let delta = Vec2::from_array(frame.mouse_motion);
+    if delta != Vec2::ZERO {
+        motion.send(MouseMotion { delta });
+    }
+}
+
+/// System moving to the next frame of the replay, the frame time of the
+/// next frame is set here so bevy advances time by the recorded amount.
+/// Real input and time are restored once the replay ends
+fn advance_replay(
+    mut commands: Commands,
+    mut player: ResMut<ReplayPlayer>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+) {
+    player.current += 1;
+
+    match player.frames.get(player.current) {
+        Some(frame) => *strategy = TimeUpdateStrategy::ManualDuration(frame.delta),
+        None => {
+            info!("Replay finished after {} frames", player.frames.len());
+            *strategy = TimeUpdateStrategy::Automatic;
+            commands.remove_resource::<ReplayPlayer>();
+        }
+    }
+}
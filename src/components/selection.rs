@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+
+/// Plugin for selecting spawned entities, pressing Tab cycles the
+/// selection through all the selectable entities
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, cycle_selection);
+    }
+}
+
+/// Marker for entities that can be selected
+#[derive(Component)]
+pub struct Selectable;
+
+/// Marker for the currently selected entity
+#[derive(Component)]
+pub struct Selected;
+
+/// Replaces the current selection with the provided entity
+pub fn select(commands: &mut Commands, selected: &Query<Entity, With<Selected>>, entity: Entity) {
+    for entity in selected.iter() {
+        commands.entity(entity).remove::<Selected>();
+    }
+
+    commands.entity(entity).insert(Selected);
+}
+
+/// System that moves the selection onto the next selectable entity
+fn cycle_selection(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    selectable: Query<Entity, With<Selectable>>,
+    selected: Query<Entity, With<Selected>>,
+) {
+    if !keys.just_pressed(KeyCode::Tab) {
+        return;
+    }
+
+    let mut entities: Vec<Entity> = selectable.iter().collect();
+    if entities.is_empty() {
+        return;
+    }
+    entities.sort();
+
+    let next = selected
+        .iter()
+        .next()
+        .and_then(|current| entities.iter().position(|entity| *entity == current))
+        .map(|index| (index + 1) % entities.len())
+        .unwrap_or_default();
+
+    select(&mut commands, &selected, entities[next]);
+}
@@ -0,0 +1,197 @@
+use bevy::{
+    prelude::*,
+    render::mesh::skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
+};
+
+use crate::formats::mesh::{skeleton::SkeletonBone, skinning::SkinPartition};
+
+/// Plugin building bone hierarchies for entities with a [SkeletonSource]
+/// and binding any [SkinnedParts] of the entity to the spawned bones,
+/// pressing K toggles drawing the bones of every skeleton
+pub struct SkeletonPlugin;
+
+impl Plugin for SkeletonPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SkeletonGizmoSettings>();
+        app.add_systems(
+            Update,
+            (
+                spawn_skeletons,
+                apply_deferred,
+                bind_skinned_parts,
+                toggle_bone_gizmos,
+                draw_bone_gizmos,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Length of the axes drawn at each bone
+const BONE_AXIS_LENGTH: f32 = 0.05;
+
+/// Settings for the bone gizmos
+#[derive(Resource, Default)]
+pub struct SkeletonGizmoSettings {
+    pub enabled: bool,
+}
+
+/// Bones of a mesh, the bone entities are spawned as children of the
+/// entity this is added to
+#[derive(Component, Debug, Clone)]
+pub struct SkeletonSource(pub Vec<SkeletonBone>);
+
+/// Bone entities of a spawned skeleton in bone index order, usable as the
+/// joints of a skinned mesh
+#[derive(Component, Debug, Clone)]
+pub struct Skeleton {
+    pub joints: Vec<Entity>,
+}
+
+/// Bone within a skeleton
+#[derive(Component, Debug, Clone)]
+pub struct Bone {
+    pub index: usize,
+    /// Entity of the parent bone
+    pub parent: Option<Entity>,
+}
+
+/// Skinned mesh deformed by the skeleton of its entity
+#[derive(Debug, Clone)]
+pub struct SkinnedPart {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+    /// Skeleton bone of each joint the mesh references
+    pub palette: Vec<u16>,
+    pub inverse_bindposes: Handle<SkinnedMeshInverseBindposes>,
+}
+
+/// Skinned meshes spawned once the skeleton of the entity exists
+#[derive(Component, Debug, Clone, Default)]
+pub struct SkinnedParts(pub Vec<SkinnedPart>);
+
+impl SkinnedParts {
+    /// Creates the parts for the partitions of a skinned mesh, the inverse
+    /// bind poses are indexed by skeleton bone
+    pub fn from_partitions(
+        partitions: &[SkinPartition],
+        inverse_bindposes: &[Mat4],
+        material: Handle<StandardMaterial>,
+        meshes: &mut Assets<Mesh>,
+        bindposes: &mut Assets<SkinnedMeshInverseBindposes>,
+    ) -> Self {
+        Self(
+            partitions
+                .iter()
+                .map(|partition| SkinnedPart {
+                    mesh: meshes.add(partition.to_mesh()),
+                    material: material.clone(),
+                    palette: partition.palette.clone(),
+                    inverse_bindposes: bindposes.add(SkinnedMeshInverseBindposes::from(
+                        partition.palette_bindposes(inverse_bindposes),
+                    )),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// System spawning the bones of newly added skeletons
+fn spawn_skeletons(
+    mut commands: Commands,
+    sources: Query<(Entity, &SkeletonSource), Added<SkeletonSource>>,
+) {
+    for (entity, source) in sources.iter() {
+        let joints: Vec<Entity> = source
+            .0
+            .iter()
+            .map(|bone| {
+                commands
+                    .spawn((
+                        SpatialBundle::from_transform(Transform::from_matrix(bone.local)),
+                        Name::new(bone.name.clone()),
+                    ))
+                    .id()
+            })
+            .collect();
+
+        // Parents are set once every bone exists since a parent
+        // can come after its children
+        for (index, (bone, joint)) in source.0.iter().zip(&joints).enumerate() {
+            let parent = bone.parent.map(|parent| joints[parent]);
+            commands
+                .entity(*joint)
+                .insert(Bone { index, parent })
+                .set_parent(parent.unwrap_or(entity));
+        }
+
+        commands.entity(entity).insert(Skeleton { joints });
+    }
+}
+
+/// System spawning the skinned meshes of newly spawned skeletons with
+/// their joints mapped onto the bone entities
+fn bind_skinned_parts(
+    mut commands: Commands,
+    skeletons: Query<(Entity, &Skeleton, &SkinnedParts), Added<Skeleton>>,
+) {
+    for (entity, skeleton, parts) in skeletons.iter() {
+        commands.entity(entity).with_children(|parent| {
+            for part in &parts.0 {
+                let joints = part
+                    .palette
+                    .iter()
+                    .filter_map(|joint| skeleton.joints.get(*joint as usize).copied())
+                    .collect();
+
+                parent.spawn((
+                    PbrBundle {
+                        mesh: part.mesh.clone(),
+                        material: part.material.clone(),
+                        ..default()
+                    },
+                    SkinnedMesh {
+                        inverse_bindposes: part.inverse_bindposes.clone(),
+                        joints,
+                    },
+                ));
+            }
+        });
+    }
+}
+
+/// System that toggles the bone gizmos
+fn toggle_bone_gizmos(keys: Res<Input<KeyCode>>, mut settings: ResMut<SkeletonGizmoSettings>) {
+    if keys.just_pressed(KeyCode::K) {
+        settings.enabled = !settings.enabled;
+    }
+}
+
+/// System drawing a line from each bone to its parent along with the
+/// axes of the bone
+fn draw_bone_gizmos(
+    mut gizmos: Gizmos,
+    settings: Res<SkeletonGizmoSettings>,
+    bones: Query<(&GlobalTransform, &Bone)>,
+    transforms: Query<&GlobalTransform>,
+) {
+    if !settings.enabled {
+        return;
+    }
+
+    for (transform, bone) in bones.iter() {
+        let position = transform.translation();
+
+        if let Some(parent) = bone.parent.and_then(|parent| transforms.get(parent).ok()) {
+            gizmos.line(parent.translation(), position, Color::YELLOW);
+        }
+
+        for (axis, color) in [
+            (transform.right(), Color::RED),
+            (transform.up(), Color::GREEN),
+            (transform.back(), Color::BLUE),
+        ] {
+            gizmos.ray(position, axis * BONE_AXIS_LENGTH, color);
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig, prelude::*, render::view::RenderLayers,
+    transform::TransformSystem,
+};
+
+use crate::{
+    formats::{
+        mesh::{loader::MeshLoader, mesh_raw_old::create_bevy_mesh},
+        report::{LoadReport, LoadReports},
+        skybox::{parse_skyboxes, skybox_for_level, SkyboxEntry},
+    },
+    fs::GameFs,
+};
+
+use super::{
+    asset_tracking::{AssetInstance, LoadedAsset, LoadedAssets},
+    perf_hud::MeshSpawnSet,
+};
+
+/// Plugin drawing the skybox of the current level behind the world. The
+/// skybox is drawn by its own camera before the world cameras, which draw
+/// over it without clearing, so the skybox never writes to the depth of
+/// the world and always stays centered on the viewer
+pub struct SkyboxPlugin;
+
+impl Plugin for SkyboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadReports>();
+        app.init_resource::<LoadedAssets>();
+        app.init_resource::<SkyboxSettings>();
+        app.init_resource::<SkyboxListing>();
+        app.add_systems(Startup, (load_skybox_listing, init_skybox_camera));
+        app.add_systems(
+            Update,
+            (
+                spawn_skybox.in_set(MeshSpawnSet),
+                update_skybox_visibility,
+                update_world_clear_color,
+            )
+                .chain(),
+        );
+        app.add_systems(
+            PostUpdate,
+            follow_camera.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Skybox listing loaded from the game data when present
+const SKYBOX_FILE: &str = "skyboxes.txt";
+/// Render layer only drawn by the skybox camera
+pub const SKYBOX_LAYER: u8 = 31;
+
+/// Settings for the skybox
+#[derive(Resource)]
+pub struct SkyboxSettings {
+    /// Level whose skybox is shown
+    pub level: Option<String>,
+    pub visible: bool,
+}
+
+impl Default for SkyboxSettings {
+    fn default() -> Self {
+        Self {
+            level: None,
+            visible: true,
+        }
+    }
+}
+
+/// Skyboxes of each level from the listing
+#[derive(Resource, Default)]
+pub struct SkyboxListing(pub Vec<SkyboxEntry>);
+
+/// Root entity of the spawned skybox meshes
+#[derive(Component)]
+pub struct Skybox(pub SkyboxEntry);
+
+/// Camera drawing the skybox
+#[derive(Component)]
+struct SkyboxCamera;
+
+fn load_skybox_listing(
+    mut commands: Commands,
+    game_fs: Res<GameFs>,
+    mut reports: ResMut<LoadReports>,
+) {
+    if !game_fs.contains(SKYBOX_FILE) {
+        return;
+    }
+
+    let mut report = LoadReport::new(SKYBOX_FILE);
+    match game_fs
+        .read_to_string(SKYBOX_FILE)
+        .map_err(|err| err.to_string())
+        .and_then(|value| parse_skyboxes(&value).map_err(|err| err.to_string()))
+    {
+        Ok(entries) => {
+            report.info(format!("listed skyboxes for {} levels", entries.len()));
+            commands.insert_resource(SkyboxListing(entries));
+        }
+        Err(err) => report.error(err),
+    }
+    reports.add(report);
+}
+
+fn init_skybox_camera(mut commands: Commands) {
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                // Drawn before the world cameras
+                order: -1,
+                ..default()
+            },
+            ..default()
+        },
+        RenderLayers::layer(SKYBOX_LAYER),
+        SkyboxCamera,
+    ));
+}
+
+/// Loads the meshes of every vertex buffer in a mesh file
+fn load_mesh_file(
+    game_fs: &GameFs,
+    path: &str,
+    report: &mut LoadReport,
+) -> Result<Vec<Mesh>, String> {
+    let bytes = game_fs.read(path).map_err(|err| err.to_string())?;
+    let loaded = MeshLoader::from_bytes(bytes)
+        .recover_truncated()
+        .load()
+        .map_err(|err| err.to_string())?;
+    loaded.report_truncations(report);
+
+    loaded
+        .vertex_buffers()
+        .iter()
+        .map(|buffer| create_bevy_mesh(buffer, report).map_err(|err| err.to_string()))
+        .collect()
+}
+
+/// System replacing the skybox when the level changes
+#[allow(clippy::too_many_arguments)]
+fn spawn_skybox(
+    mut commands: Commands,
+    settings: Res<SkyboxSettings>,
+    listing: Res<SkyboxListing>,
+    game_fs: Res<GameFs>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut loaded: ResMut<LoadedAssets>,
+    mut reports: ResMut<LoadReports>,
+    skyboxes: Query<Entity, With<Skybox>>,
+) {
+    if !settings.is_changed() && !listing.is_changed() {
+        return;
+    }
+
+    for entity in skyboxes.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let Some(entry) = settings
+        .level
+        .as_deref()
+        .and_then(|level| skybox_for_level(&listing.0, level))
+    else {
+        return;
+    };
+
+    if loaded.get(&entry.mesh).is_none() {
+        let mut report = LoadReport::new(&entry.mesh);
+        match load_mesh_file(&game_fs, &entry.mesh, &mut report) {
+            Ok(values) => {
+                let handles = values.into_iter().map(|mesh| meshes.add(mesh)).collect();
+                loaded.insert(entry.mesh.clone(), LoadedAsset::new(handles, Vec::new()));
+            }
+            Err(err) => report.error(err),
+        }
+        reports.add(report);
+    }
+
+    let Some(asset) = loaded.get(&entry.mesh) else {
+        return;
+    };
+
+    // The skybox isn't lit by the level lights
+    let material = materials.add(StandardMaterial {
+        unlit: true,
+        cull_mode: None,
+        ..default()
+    });
+
+    commands
+        .spawn((
+            SpatialBundle::from_transform(Transform::from_scale(Vec3::splat(entry.scale))),
+            RenderLayers::layer(SKYBOX_LAYER),
+            Skybox(entry.clone()),
+        ))
+        .with_children(|parent| {
+            for mesh in &asset.meshes {
+                parent.spawn((
+                    PbrBundle {
+                        mesh: mesh.clone(),
+                        material: material.clone(),
+                        ..default()
+                    },
+                    RenderLayers::layer(SKYBOX_LAYER),
+                    AssetInstance {
+                        asset: entry.mesh.clone(),
+                    },
+                ));
+            }
+        });
+}
+
+fn update_skybox_visibility(
+    settings: Res<SkyboxSettings>,
+    mut skyboxes: Query<&mut Visibility, With<Skybox>>,
+) {
+    for mut visibility in skyboxes.iter_mut() {
+        *visibility = match settings.visible {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        };
+    }
+}
+
+/// World cameras keep the skybox drawn before them while a skybox is shown
+fn update_world_clear_color(
+    settings: Res<SkyboxSettings>,
+    skyboxes: Query<(), With<Skybox>>,
+    mut cameras: Query<&mut Camera3d, Without<SkyboxCamera>>,
+) {
+    let show_skybox = settings.visible && !skyboxes.is_empty();
+
+    for mut camera in cameras.iter_mut() {
+        let cleared = !matches!(camera.clear_color, ClearColorConfig::None);
+        if show_skybox && cleared {
+            camera.clear_color = ClearColorConfig::None;
+        } else if !show_skybox && !cleared {
+            camera.clear_color = ClearColorConfig::Default;
+        }
+    }
+}
+
+/// Keeps the skybox camera looking the same direction as the world camera
+/// while staying at the center of the skybox
+fn follow_camera(
+    world_cameras: Query<
+        (&Camera, &Transform, &Projection),
+        (With<Camera3d>, Without<SkyboxCamera>),
+    >,
+    mut skybox_camera: Query<(&mut Transform, &mut Projection), With<SkyboxCamera>>,
+) {
+    let Some((_, world_transform, world_projection)) = world_cameras
+        .iter()
+        .filter(|(camera, _, _)| camera.is_active)
+        .max_by_key(|(camera, _, _)| camera.order)
+    else {
+        return;
+    };
+
+    for (mut transform, mut projection) in skybox_camera.iter_mut() {
+        *transform = Transform::from_rotation(world_transform.rotation);
+        *projection = world_projection.clone();
+    }
+}
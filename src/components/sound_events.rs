@@ -0,0 +1,132 @@
+use bevy::{audio::AudioSource, prelude::*, utils::HashMap};
+
+use crate::{
+    formats::{
+        report::{LoadReport, LoadReports},
+        sound_events::SoundEventTable,
+    },
+    fs::GameFs,
+};
+
+use super::selection::Selected;
+
+/// Plugin playing the sounds of the events on the selected entity,
+/// E plays the next event of the entity each time it's pressed
+pub struct SoundEventPlugin;
+
+impl Plugin for SoundEventPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadReports>();
+        app.init_resource::<SoundEvents>();
+        app.init_resource::<SoundSamples>();
+        app.add_systems(Startup, load_sound_events);
+        app.add_systems(Update, play_selected_sounds);
+    }
+}
+
+/// Sound event listing loaded from the game data when present
+const SOUND_EVENTS_FILE: &str = "sound_events.txt";
+
+/// Sound events known to the viewer
+#[derive(Resource, Default)]
+pub struct SoundEvents(pub SoundEventTable);
+
+/// Samples already loaded keyed by event ID
+#[derive(Resource, Default)]
+struct SoundSamples(HashMap<u32, Handle<AudioSource>>);
+
+/// Names of the sound events an entity triggers
+#[derive(Component, Default)]
+pub struct EntitySounds {
+    pub events: Vec<String>,
+    /// Index of the next event played
+    next: usize,
+}
+
+impl EntitySounds {
+    pub fn new(events: Vec<String>) -> Self {
+        Self { events, next: 0 }
+    }
+}
+
+fn load_sound_events(
+    game_fs: Res<GameFs>,
+    mut events: ResMut<SoundEvents>,
+    mut reports: ResMut<LoadReports>,
+) {
+    if !game_fs.contains(SOUND_EVENTS_FILE) {
+        return;
+    }
+
+    let mut report = LoadReport::new(SOUND_EVENTS_FILE);
+    match game_fs
+        .read_to_string(SOUND_EVENTS_FILE)
+        .map_err(|err| err.to_string())
+        .and_then(|value| SoundEventTable::parse(&value).map_err(|err| err.to_string()))
+    {
+        Ok(table) => {
+            report.info(format!("mapped {} sound events", table.events().len()));
+            events.0 = table;
+        }
+        Err(err) => report.error(err),
+    }
+    reports.add(report);
+}
+
+fn play_selected_sounds(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    game_fs: Res<GameFs>,
+    events: Res<SoundEvents>,
+    mut samples: ResMut<SoundSamples>,
+    mut sources: ResMut<Assets<AudioSource>>,
+    mut reports: ResMut<LoadReports>,
+    mut selected: Query<&mut EntitySounds, With<Selected>>,
+) {
+    if !keys.just_pressed(KeyCode::E) {
+        return;
+    }
+
+    for mut sounds in selected.iter_mut() {
+        if sounds.events.is_empty() {
+            continue;
+        }
+
+        let index = sounds.next % sounds.events.len();
+        sounds.next = index + 1;
+
+        let name = &sounds.events[index];
+        let Some(event) = events.0.by_name(name) else {
+            debug!("No sound event named {}", name);
+            continue;
+        };
+
+        let source = match samples.0.get(&event.id) {
+            Some(source) => source.clone(),
+            None => {
+                let path = event.sample_path();
+                let bytes = match game_fs.read(&path) {
+                    Ok(bytes) => bytes,
+                    Err(err) => {
+                        let mut report = LoadReport::new(path);
+                        report.error(err.to_string());
+                        reports.add(report);
+                        continue;
+                    }
+                };
+
+                let source = sources.add(AudioSource {
+                    bytes: bytes.into(),
+                });
+                samples.0.insert(event.id, source.clone());
+                source
+            }
+        };
+
+        debug!("Playing sound event {} ({})", event.name, event.id);
+        commands.spawn(AudioBundle {
+            source,
+            settings: PlaybackSettings::DESPAWN,
+        });
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+
+use crate::formats::spline::Spline;
+
+/// Plugin drawing the spline shapes of the level as polylines so the paths
+/// cameras and entities follow can be inspected, F7 toggles the splines
+pub struct SplineDebugPlugin;
+
+impl Plugin for SplineDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SplineDebugSettings>();
+        app.add_systems(Update, (update_spline_input, draw_splines).chain());
+    }
+}
+
+/// Color of the line between points
+const SPLINE_COLOR: Color = Color::CYAN;
+/// Color of the crosses marking each point
+const POINT_COLOR: Color = Color::YELLOW;
+/// Size of the crosses marking each point
+const POINT_SIZE: f32 = 0.25;
+
+#[derive(Resource, Debug)]
+pub struct SplineDebugSettings {
+    pub visible: bool,
+    /// Number of line steps drawn along each segment
+    pub steps: usize,
+}
+
+impl Default for SplineDebugSettings {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            steps: 16,
+        }
+    }
+}
+
+/// Spline shape placed in the level, the points are relative to the
+/// transform of the entity
+#[derive(Component, Debug, Clone)]
+pub struct LevelSpline(pub Spline);
+
+fn update_spline_input(keys: Res<Input<KeyCode>>, mut settings: ResMut<SplineDebugSettings>) {
+    if keys.just_pressed(KeyCode::F7) {
+        settings.visible = !settings.visible;
+    }
+}
+
+fn draw_splines(
+    mut gizmos: Gizmos,
+    settings: Res<SplineDebugSettings>,
+    splines: Query<(&GlobalTransform, &LevelSpline)>,
+) {
+    if !settings.visible {
+        return;
+    }
+
+    for (transform, spline) in splines.iter() {
+        let polyline = spline.0.polyline(settings.steps);
+        gizmos.linestrip(
+            polyline
+                .into_iter()
+                .map(|point| transform.transform_point(point)),
+            SPLINE_COLOR,
+        );
+
+        for point in &spline.0.points {
+            let point = transform.transform_point(*point);
+            for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+                let offset = axis * POINT_SIZE;
+                gizmos.line(point - offset, point + offset, POINT_COLOR);
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::{
+    prelude::*,
+    render::mesh::VertexAttributeValues,
+    utils::{HashMap, HashSet},
+};
+
+use crate::formats::mesh::material::MaterialAnimation;
+
+/// Plugin animating the texture layers of mesh materials, flipping layers
+/// cycle through their pages and scrolling or rotating layers move the
+/// texture coordinates of the meshes drawn with them
+pub struct TextureAnimationPlugin;
+
+impl Plugin for TextureAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BaseUvs>();
+        app.add_systems(Update, (flip_texture_pages, animate_uvs));
+    }
+}
+
+/// Texture animation of the material drawing the mesh of the entity
+#[derive(Component, Debug, Clone)]
+pub struct AnimatedTexture(pub MaterialAnimation);
+
+/// Texture coordinates of the animated meshes before animating, meshes are
+/// shared between instances so the animation is applied over these
+#[derive(Resource, Default)]
+struct BaseUvs(HashMap<AssetId<Mesh>, Vec<[f32; 2]>>);
+
+/// System swapping the base texture of flipping materials to the current
+/// page, materials shared between meshes are only updated once
+fn flip_texture_pages(
+    time: Res<Time>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    animated: Query<(&AnimatedTexture, &Handle<StandardMaterial>)>,
+) {
+    let seconds = time.elapsed_seconds();
+    let mut updated = HashSet::new();
+
+    for (animation, handle) in animated.iter() {
+        let Some(flip) = &animation.0.flip else {
+            continue;
+        };
+        if !updated.insert(handle.id()) {
+            continue;
+        }
+        let Some(page) = flip.page(seconds) else {
+            continue;
+        };
+
+        // Only write on a page change so the material isn't prepared again
+        // every frame
+        let current = materials
+            .get(handle)
+            .and_then(|material| material.base_color_texture.as_ref());
+        if current != Some(page) {
+            if let Some(material) = materials.get_mut(handle) {
+                material.base_color_texture = Some(page.clone());
+            }
+        }
+    }
+}
+
+/// System moving the texture coordinates of the meshes drawn with
+/// scrolling or rotating materials
+fn animate_uvs(
+    time: Res<Time>,
+    mut base_uvs: ResMut<BaseUvs>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    animated: Query<(&AnimatedTexture, &Handle<Mesh>)>,
+) {
+    let seconds = time.elapsed_seconds();
+    let mut updated = HashSet::new();
+
+    for (animation, handle) in animated.iter() {
+        let Some(uv) = &animation.0.uv else {
+            continue;
+        };
+        if !updated.insert(handle.id()) {
+            continue;
+        }
+        let Some(mesh) = meshes.get_mut(handle) else {
+            continue;
+        };
+        let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0)
+        else {
+            continue;
+        };
+
+        let base = base_uvs.0.entry(handle.id()).or_insert_with(|| uvs.clone());
+        if base.len() != uvs.len() {
+            // The mesh was replaced by a reload
+            *base = uvs.clone();
+        }
+
+        let transform = uv.transform(seconds);
+        for (out, value) in uvs.iter_mut().zip(base.iter()) {
+            *out = transform.transform_point2(Vec2::from(*value)).to_array();
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use bevy::{asset::AssetId, prelude::*, utils::HashMap};
+
+use crate::formats::texture::sampling::{SamplerSettings, TextureFilter, TextureSampling};
+
+use super::decals::MarkingTextures;
+
+/// Plugin applying the [TextureSampling] settings to the loaded textures,
+/// N switches between nearest neighbor (retro) and linear filtering
+pub struct TextureFilteringPlugin;
+
+impl Plugin for TextureFilteringPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TextureSampling>();
+        app.add_systems(
+            Update,
+            (update_filtering_input, apply_texture_sampling).chain(),
+        );
+    }
+}
+
+fn update_filtering_input(keys: Res<Input<KeyCode>>, mut sampling: ResMut<TextureSampling>) {
+    if keys.just_pressed(KeyCode::N) {
+        sampling.global = match sampling.global.filter {
+            TextureFilter::Linear => SamplerSettings::RETRO,
+            TextureFilter::Nearest => SamplerSettings::SMOOTH,
+        };
+    }
+}
+
+/// System updating the samplers of every image when the settings change,
+/// along with the sampler of newly added images
+fn apply_texture_sampling(
+    sampling: Res<TextureSampling>,
+    textures: Option<Res<MarkingTextures>>,
+    mut events: EventReader<AssetEvent<Image>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let added: Vec<AssetId<Image>> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Added { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    let changed =
+        sampling.is_changed() || textures.as_ref().is_some_and(|value| value.is_changed());
+    if !changed && added.is_empty() {
+        return;
+    }
+
+    // Names of the textures that can be overridden
+    let names: HashMap<AssetId<Image>, &str> = textures
+        .iter()
+        .flat_map(|textures| textures.0.iter())
+        .map(|(name, handle)| (handle.id(), name.as_str()))
+        .collect();
+    let settings_for = |id: &AssetId<Image>| match names.get(id) {
+        Some(name) => sampling.for_texture(name),
+        None => sampling.global,
+    };
+
+    let targets: Vec<AssetId<Image>> = if changed {
+        images.ids().collect()
+    } else {
+        added
+    };
+
+    for id in targets {
+        if let Some(image) = images.get_mut(id) {
+            image.sampler = settings_for(&id).image_sampler();
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::{asset::AssetId, prelude::*, utils::HashMap};
+
+use crate::formats::texture::{generate_mipmaps, mipmapped_image, DecodedTexture};
+
+/// Plugin streaming the detail of large textures, textures start out with
+/// only their smallest mip levels uploaded so levels load quickly and the
+/// larger levels are uploaded once a mesh using the texture is near the
+/// camera
+pub struct TextureStreamingPlugin;
+
+impl Plugin for TextureStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TextureStreamingSettings>();
+        app.init_resource::<StreamedTextures>();
+        app.add_systems(
+            Update,
+            (track_streamed_textures, refine_streamed_textures).chain(),
+        );
+    }
+}
+
+/// Settings controlling which textures are streamed and how quickly
+#[derive(Resource, Debug, Clone)]
+pub struct TextureStreamingSettings {
+    pub enabled: bool,
+    /// Textures this size or smaller in both dimensions are uploaded
+    /// in full straight away
+    pub min_size: u32,
+    /// Largest size of the level textures start out with
+    pub initial_size: u32,
+    /// Distance within which textures are shown at full detail, each
+    /// doubling of the distance drops a level
+    pub full_detail_distance: f32,
+    /// Number of textures that can be uploaded each frame
+    pub uploads_per_frame: usize,
+}
+
+impl Default for TextureStreamingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size: 128,
+            initial_size: 32,
+            full_detail_distance: 16.0,
+            uploads_per_frame: 4,
+        }
+    }
+}
+
+/// Texture with only part of its mip chain uploaded
+struct StreamedTexture {
+    /// Every level of the mip chain starting at the full size level
+    levels: Vec<DecodedTexture>,
+    /// Index of the largest level currently uploaded
+    resident: usize,
+}
+
+impl StreamedTexture {
+    /// Index of the level used for the first upload
+    fn initial_level(&self, initial_size: u32) -> usize {
+        self.levels
+            .iter()
+            .position(|level| level.width <= initial_size && level.height <= initial_size)
+            .unwrap_or(self.levels.len() - 1)
+    }
+}
+
+/// Textures being streamed by their image asset
+#[derive(Resource, Default)]
+struct StreamedTextures(HashMap<AssetId<Image>, StreamedTexture>);
+
+/// Whether the image is a single level RGBA8 texture like the
+/// ones decoded from the game data
+fn is_streamable(image: &Image) -> bool {
+    let size = image.texture_descriptor.size;
+    image.texture_descriptor.mip_level_count == 1
+        && image.data.len() == (size.width * size.height * 4) as usize
+}
+
+/// Replaces the texels of the image with the mip chain from `first` down
+fn upload_levels(image: &mut Image, levels: &[DecodedTexture], first: usize) {
+    let Ok(resident) = mipmapped_image(&levels[first..]) else {
+        return;
+    };
+
+    // Sampler is kept since it's managed by the texture filtering
+    image.data = resident.data;
+    image.texture_descriptor.size = resident.texture_descriptor.size;
+    image.texture_descriptor.mip_level_count = resident.texture_descriptor.mip_level_count;
+}
+
+/// System taking over newly added large textures, the texture is swapped
+/// for its smallest levels and the full chain is kept for refining
+fn track_streamed_textures(
+    settings: Res<TextureStreamingSettings>,
+    mut streamed: ResMut<StreamedTextures>,
+    mut events: EventReader<AssetEvent<Image>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let mut added = Vec::new();
+    for event in events.read() {
+        match event {
+            AssetEvent::Added { id } => added.push(*id),
+            AssetEvent::Removed { id } => {
+                streamed.0.remove(id);
+            }
+            _ => {}
+        }
+    }
+
+    if !settings.enabled {
+        return;
+    }
+
+    for id in added {
+        let Some(image) = images.get_mut(id) else {
+            continue;
+        };
+        let size = image.texture_descriptor.size;
+        if !is_streamable(image)
+            || (size.width <= settings.min_size && size.height <= settings.min_size)
+        {
+            continue;
+        }
+
+        let levels = generate_mipmaps(&DecodedTexture {
+            width: size.width,
+            height: size.height,
+            data: std::mem::take(&mut image.data),
+        });
+        let mut texture = StreamedTexture {
+            levels,
+            resident: 0,
+        };
+        texture.resident = texture.initial_level(settings.initial_size);
+        upload_levels(image, &texture.levels, texture.resident);
+
+        streamed.0.insert(id, texture);
+    }
+}
+
+/// System uploading larger levels of the textures used by meshes near the
+/// camera, the textures closest to the camera are refined first
+fn refine_streamed_textures(
+    settings: Res<TextureStreamingSettings>,
+    mut streamed: ResMut<StreamedTextures>,
+    mut images: ResMut<Assets<Image>>,
+    materials: Res<Assets<StandardMaterial>>,
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+    meshes: Query<(&GlobalTransform, &Handle<StandardMaterial>, &ViewVisibility)>,
+) {
+    if streamed.0.is_empty() {
+        return;
+    }
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let camera = camera.translation();
+
+    // Distance to the closest visible mesh using each streamed texture
+    let mut distances: HashMap<AssetId<Image>, f32> = HashMap::new();
+    for (transform, material, visibility) in meshes.iter() {
+        if !visibility.get() {
+            continue;
+        }
+        let Some(material) = materials.get(material) else {
+            continue;
+        };
+
+        let distance = transform.translation().distance(camera);
+        for texture in [&material.base_color_texture, &material.emissive_texture]
+            .into_iter()
+            .flatten()
+        {
+            let id = texture.id();
+            if streamed.0.contains_key(&id) {
+                let value = distances.entry(id).or_insert(f32::MAX);
+                *value = value.min(distance);
+            }
+        }
+    }
+
+    let mut pending: Vec<(AssetId<Image>, f32, usize)> = distances
+        .into_iter()
+        .filter_map(|(id, distance)| {
+            let texture = streamed.0.get(&id)?;
+            let doublings = (distance / settings.full_detail_distance).max(1.0).log2();
+            let wanted = (doublings as usize).min(texture.levels.len() - 1);
+            // Levels are only ever added so textures don't pop back and forth
+            (wanted < texture.resident).then_some((id, distance, wanted))
+        })
+        .collect();
+    pending.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    for (id, _, wanted) in pending.into_iter().take(settings.uploads_per_frame) {
+        let (Some(texture), Some(image)) = (streamed.0.get_mut(&id), images.get_mut(id)) else {
+            continue;
+        };
+
+        upload_levels(image, &texture.levels, wanted);
+        texture.resident = wanted;
+    }
+
+    // Fully refined textures no longer need their chain kept around
+    streamed.0.retain(|_, texture| texture.resident > 0);
+}
@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+use thiserror::Error;
+
+use crate::{
+    formats::timeline::{Timeline, TimelineError},
+    fs::{FsError, GameFs},
+};
+
+/// Plugin showing the events of the active cutscene timeline, P plays and
+/// pauses the timeline while PageUp / PageDown jump between events
+pub struct TimelinePlugin;
+
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TimelineSeek>();
+        app.add_systems(Startup, init_timeline_panel);
+        app.add_systems(
+            Update,
+            (
+                update_timeline_input,
+                advance_timeline,
+                update_timeline_panel,
+            )
+                .chain()
+                .run_if(resource_exists::<ActiveTimeline>()),
+        );
+    }
+}
+
+/// Timeline currently being viewed
+#[derive(Resource)]
+pub struct ActiveTimeline {
+    pub timeline: Timeline,
+    /// Current time within the timeline in seconds
+    pub time: f32,
+    pub playing: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum LoadTimelineError {
+    #[error(transparent)]
+    Fs(#[from] FsError),
+    #[error(transparent)]
+    Timeline(#[from] TimelineError),
+}
+
+impl ActiveTimeline {
+    pub fn new(timeline: Timeline) -> Self {
+        Self {
+            timeline,
+            time: 0.0,
+            playing: false,
+        }
+    }
+
+    pub fn load(fs: &GameFs, path: &str) -> Result<Self, LoadTimelineError> {
+        let value = fs.read_to_string(path)?;
+        Ok(Self::new(Timeline::parse(&value)?))
+    }
+}
+
+/// Event sent when the timeline jumps to a new time, systems driving the
+/// scene (animations, cameras, audio) can use this to scrub to the time
+#[derive(Event)]
+pub struct TimelineSeek {
+    pub time: f32,
+}
+
+/// Marker for the text of the timeline panel
+#[derive(Component)]
+struct TimelineText;
+
+fn init_timeline_panel(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::default().with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..default()
+        }),
+        TimelineText,
+    ));
+}
+
+fn update_timeline_input(
+    keys: Res<Input<KeyCode>>,
+    mut active: ResMut<ActiveTimeline>,
+    mut seek: EventWriter<TimelineSeek>,
+) {
+    if keys.just_pressed(KeyCode::P) {
+        active.playing = !active.playing;
+    }
+
+    let current = active.timeline.event_at(active.time);
+    let target = if keys.just_pressed(KeyCode::PageDown) {
+        match current {
+            Some(index) => active.timeline.events.get(index + 1),
+            None => active.timeline.events.first(),
+        }
+    } else if keys.just_pressed(KeyCode::PageUp) {
+        current
+            .and_then(|index| index.checked_sub(1))
+            .and_then(|index| active.timeline.events.get(index))
+    } else {
+        None
+    };
+
+    if let Some(time) = target.map(|event| event.time) {
+        active.time = time;
+        seek.send(TimelineSeek { time });
+    }
+}
+
+fn advance_timeline(time: Res<Time>, mut active: ResMut<ActiveTimeline>) {
+    if !active.playing {
+        return;
+    }
+
+    active.time += time.delta_seconds();
+    if active.time >= active.timeline.duration() {
+        active.time = active.timeline.duration();
+        active.playing = false;
+    }
+}
+
+fn update_timeline_panel(
+    active: Res<ActiveTimeline>,
+    mut text: Query<&mut Text, With<TimelineText>>,
+) {
+    if !active.is_changed() {
+        return;
+    }
+
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+
+    let current = active.timeline.event_at(active.time);
+    let mut sections = vec![TextSection::new(
+        format!("{:.2} / {:.2}\n", active.time, active.timeline.duration()),
+        TextStyle {
+            font_size: 14.0,
+            color: Color::WHITE,
+            ..default()
+        },
+    )];
+
+    sections.extend(
+        active
+            .timeline
+            .events
+            .iter()
+            .enumerate()
+            .map(|(index, event)| {
+                let color = if Some(index) == current {
+                    Color::YELLOW
+                } else {
+                    Color::GRAY
+                };
+
+                TextSection::new(
+                    format!("{:>7.2} {:<9} {}\n", event.time, event.kind, event.target),
+                    TextStyle {
+                        font_size: 14.0,
+                        color,
+                        ..default()
+                    },
+                )
+            }),
+    );
+
+    text.sections = sections;
+}
@@ -0,0 +1,352 @@
+use std::{path::Path, sync::Arc};
+
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    render::view::{screenshot::ScreenshotManager, RenderLayers},
+    window::PrimaryWindow,
+};
+use parking_lot::Mutex;
+
+use crate::{
+    formats::{
+        mesh::{
+            loader::MeshLoader,
+            material::{GameTextures, MaterialConverter, MaterialSource, MeshTextures},
+        },
+        report::{LoadReport, LoadReports},
+        shader_table::{ShaderEffectTable, SHADER_TABLE_PATH},
+        texture::{
+            compare::{compare_with_reference, save_png},
+            DecodedTexture,
+        },
+        validation::{
+            parse_cases, SpaceTransforms, ValidationCase, ValidationOutcome, VALIDATION_DIR,
+            VALIDATION_LISTING,
+        },
+    },
+    fs::GameFs,
+};
+
+use super::ape::spawn_fmesh;
+
+/// Plugin running the screenshot validation harness when the validation
+/// environment variable is set. Each case is rendered by its own camera on
+/// a dedicated render layer, captured and compared against its reference
+/// screenshot, the viewer exits once every case has run. Materials are
+/// converted the same as meshes loaded through the asset server
+pub struct ValidationPlugin;
+
+impl Plugin for ValidationPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<SpaceTransforms>() {
+            app.insert_resource(SpaceTransforms::with_builtin());
+        }
+
+        if std::env::var_os(VALIDATE_ENV).is_none() {
+            return;
+        }
+
+        app.init_resource::<LoadReports>();
+        app.init_resource::<ValidationRun>();
+        app.add_systems(Startup, load_validation_cases);
+        app.add_systems(Update, run_validation);
+    }
+}
+
+/// Environment variable enabling the validation harness
+const VALIDATE_ENV: &str = "OPENMA_VALIDATE";
+/// Render layer only drawn by the validation camera
+const VALIDATION_LAYER: u8 = 30;
+/// Frames rendered before capturing so pipelines and assets are ready
+const SETTLE_FRAMES: u32 = 10;
+
+#[derive(Default)]
+enum ValidationStage {
+    /// Next case needs to be spawned
+    #[default]
+    Spawn,
+    /// Waiting for the case to render
+    Settle(u32),
+    /// Waiting for the capture of the case
+    Capture(Arc<Mutex<Option<Image>>>),
+    /// Every case has run
+    Done,
+}
+
+/// Progress of the validation run
+#[derive(Resource, Default)]
+struct ValidationRun {
+    cases: Vec<ValidationCase>,
+    shaders: ShaderEffectTable,
+    current: usize,
+    stage: ValidationStage,
+    failures: usize,
+}
+
+/// Entities spawned for the current case
+#[derive(Component)]
+struct ValidationSubject;
+
+fn load_validation_cases(mut run: ResMut<ValidationRun>, mut reports: ResMut<LoadReports>) {
+    let path = Path::new(VALIDATION_DIR).join(VALIDATION_LISTING);
+    let mut report = LoadReport::new(path.to_string_lossy());
+
+    match std::fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|value| parse_cases(&value).map_err(|err| err.to_string()))
+    {
+        Ok(cases) => {
+            report.info(format!("running {} validation cases", cases.len()));
+            run.cases = cases;
+        }
+        Err(err) => report.error(err),
+    }
+
+    run.shaders = ShaderEffectTable::load(SHADER_TABLE_PATH.as_ref()).unwrap_or_else(|err| {
+        report.warn(format!("shader effect table: {}", err));
+        ShaderEffectTable::default()
+    });
+    reports.add(report);
+}
+
+/// Reads the asset of a case, assets stored with the cases are used
+/// before the game data
+fn read_case_asset(case: &ValidationCase, game_fs: &GameFs) -> Result<Vec<u8>, String> {
+    match case.local_asset_path() {
+        Some(path) => std::fs::read(path).map_err(|err| err.to_string()),
+        None => game_fs.read(&case.asset).map_err(|err| err.to_string()),
+    }
+}
+
+/// Adds the render layer to the entity and all of its descendants
+fn insert_render_layers(world: &mut World, entity: Entity, layers: RenderLayers) {
+    let Some(mut value) = world.get_entity_mut(entity) else {
+        return;
+    };
+    value.insert(layers);
+
+    let children = value
+        .get::<Children>()
+        .map(|children| children.to_vec())
+        .unwrap_or_default();
+    for child in children {
+        insert_render_layers(world, child, layers);
+    }
+}
+
+/// Assets the cases are spawned with
+struct CaseAssets<'a> {
+    game_fs: &'a GameFs,
+    shaders: &'a ShaderEffectTable,
+    meshes: &'a mut Assets<Mesh>,
+    materials: &'a mut Assets<StandardMaterial>,
+    images: &'a mut Assets<Image>,
+}
+
+/// Spawns the asset and camera of a case
+fn spawn_case(
+    commands: &mut Commands,
+    case: &ValidationCase,
+    transforms: &SpaceTransforms,
+    assets: CaseAssets,
+    report: &mut LoadReport,
+) -> Result<(), String> {
+    let space = transforms
+        .get(&case.transform)
+        .ok_or_else(|| format!("unknown coordinate space transform {:?}", case.transform))?;
+    let loaded = MeshLoader::from_bytes(read_case_asset(case, assets.game_fs)?)
+        .recover_truncated()
+        .load()
+        .map_err(|err| err.to_string())?;
+    loaded.report_truncations(report);
+
+    let sources: Vec<MaterialSource> = loaded
+        .materials()
+        .iter()
+        .map(|material| MaterialSource::from_fmesh(&loaded, material, report))
+        .collect();
+    let textures = MeshTextures::new(&loaded, GameTextures::new(assets.game_fs, assets.images));
+    let mut converter = MaterialConverter::new(assets.shaders, textures);
+    let materials: Vec<Handle<StandardMaterial>> = sources
+        .iter()
+        .map(|source| {
+            let converted = converter.convert(source);
+            for name in &converted.missing_textures {
+                report.warn(format!("missing texture {}", name));
+            }
+            assets.materials.add(converted.material)
+        })
+        .collect();
+    converter.into_textures().report_errors(report);
+
+    let root = spawn_fmesh(commands, &loaded, assets.meshes, &materials, report);
+    commands
+        .entity(root)
+        .insert((Transform::from_matrix(space.to_world()), ValidationSubject));
+    commands.add(move |world: &mut World| {
+        insert_render_layers(world, root, RenderLayers::layer(VALIDATION_LAYER))
+    });
+
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                // Drawn over the world cameras
+                order: 10,
+                ..default()
+            },
+            transform: Transform::from_translation(case.eye).looking_at(case.target, Vec3::Y),
+            ..default()
+        },
+        RenderLayers::layer(VALIDATION_LAYER),
+        ValidationSubject,
+    ));
+    commands.spawn((
+        DirectionalLightBundle {
+            transform: Transform::from_translation(case.eye).looking_at(case.target, Vec3::Y),
+            ..default()
+        },
+        RenderLayers::layer(VALIDATION_LAYER),
+        ValidationSubject,
+    ));
+
+    Ok(())
+}
+
+/// Compares the capture of a case against its reference, the capture is
+/// stored as the reference when there isn't one yet
+fn compare_capture(case: &ValidationCase, capture: Image) -> ValidationOutcome {
+    let capture = match capture.try_into_dynamic() {
+        Ok(value) => value.to_rgba8(),
+        Err(err) => return ValidationOutcome::Error(err.to_string()),
+    };
+    let capture = DecodedTexture {
+        width: capture.width(),
+        height: capture.height(),
+        data: capture.into_raw(),
+    };
+
+    let reference = case.reference_path();
+    if !Path::new(&reference).exists() {
+        return match save_png(&capture, Path::new(&reference)) {
+            Ok(()) => ValidationOutcome::Recorded,
+            Err(err) => ValidationOutcome::Error(err.to_string()),
+        };
+    }
+
+    match compare_with_reference(
+        &capture,
+        Path::new(&reference),
+        Path::new(&case.highlight_path()),
+        case.tolerance,
+    ) {
+        Ok(comparison) => ValidationOutcome::from_comparison(comparison),
+        Err(err) => ValidationOutcome::Error(err.to_string()),
+    }
+}
+
+fn report_outcome(case: &ValidationCase, outcome: &ValidationOutcome, reports: &mut LoadReports) {
+    let mut report = LoadReport::new(format!("validation {}", case.name));
+    match outcome {
+        ValidationOutcome::Passed(comparison) => {
+            report.info(format!("passed ({:.1} dB)", comparison.psnr))
+        }
+        ValidationOutcome::Failed(comparison) => report.error(format!(
+            "{} blocks differ from the reference ({:.1} dB), see {}",
+            comparison.mismatched_blocks.len(),
+            comparison.psnr,
+            case.highlight_path()
+        )),
+        ValidationOutcome::Recorded => report.warn(format!(
+            "recorded missing reference {}",
+            case.reference_path()
+        )),
+        ValidationOutcome::Error(err) => report.error(err.clone()),
+    }
+    reports.add(report);
+}
+
+/// System stepping through the validation cases
+#[allow(clippy::too_many_arguments)]
+fn run_validation(
+    mut commands: Commands,
+    mut run: ResMut<ValidationRun>,
+    game_fs: Res<GameFs>,
+    transforms: Res<SpaceTransforms>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut screenshots: ResMut<ScreenshotManager>,
+    mut reports: ResMut<LoadReports>,
+    mut exit: EventWriter<AppExit>,
+    window: Query<Entity, With<PrimaryWindow>>,
+    subjects: Query<Entity, With<ValidationSubject>>,
+) {
+    let run = &mut *run;
+    match &mut run.stage {
+        ValidationStage::Spawn => {
+            let Some(case) = run.cases.get(run.current) else {
+                info!(
+                    "Validation finished, {} of {} cases failed",
+                    run.failures,
+                    run.cases.len()
+                );
+                run.stage = ValidationStage::Done;
+                exit.send(AppExit);
+                return;
+            };
+
+            let mut report = LoadReport::new(&case.asset);
+            let assets = CaseAssets {
+                game_fs: &game_fs,
+                shaders: &run.shaders,
+                meshes: &mut meshes,
+                materials: &mut materials,
+                images: &mut images,
+            };
+            match spawn_case(&mut commands, case, &transforms, assets, &mut report) {
+                Ok(()) => run.stage = ValidationStage::Settle(SETTLE_FRAMES),
+                Err(err) => {
+                    report_outcome(case, &ValidationOutcome::Error(err), &mut reports);
+                    run.failures += 1;
+                    run.current += 1;
+                }
+            }
+            reports.add(report);
+        }
+        ValidationStage::Settle(frames) if *frames > 0 => *frames -= 1,
+        ValidationStage::Settle(_) => {
+            let Ok(window) = window.get_single() else {
+                return;
+            };
+
+            let capture = Arc::new(Mutex::new(None));
+            let target = capture.clone();
+            if screenshots
+                .take_screenshot(window, move |image| *target.lock() = Some(image))
+                .is_ok()
+            {
+                run.stage = ValidationStage::Capture(capture);
+            }
+        }
+        ValidationStage::Capture(capture) => {
+            let Some(image) = capture.lock().take() else {
+                return;
+            };
+
+            let case = &run.cases[run.current];
+            let outcome = compare_capture(case, image);
+            if outcome.is_failure() {
+                run.failures += 1;
+            }
+            report_outcome(case, &outcome, &mut reports);
+
+            for entity in subjects.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            run.current += 1;
+            run.stage = ValidationStage::Spawn;
+        }
+        ValidationStage::Done => {}
+    }
+}
@@ -0,0 +1,82 @@
+//! Video backend decoding Bink movies without ffmpeg. The movie container
+//! and video codec are decoded natively, timing, looping and seeking behave
+//! the same as the ffmpeg backend. Frames after a seek build on the
+//! keyframe the seek lands on
+
+use std::{fs::File, io::BufReader, path::Path, time::Duration};
+
+use bevy::log::warn;
+
+use crate::formats::bink::{
+    video::{BinkPicture, BinkVideoDecoder},
+    BinkError, BinkMovie,
+};
+
+pub type VideoError = BinkError;
+
+pub type VideoFrame = BinkPicture;
+
+pub fn init() {}
+
+pub struct VideoDecoder {
+    reader: BufReader<File>,
+    movie: BinkMovie,
+    video: BinkVideoDecoder,
+    /// Index of the next frame to read
+    next: usize,
+}
+
+impl VideoDecoder {
+    pub fn open(path: &Path) -> Result<Self, VideoError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let movie = BinkMovie::read(&mut reader)?;
+        let video = BinkVideoDecoder::new(&movie.header)?;
+
+        Ok(Self {
+            reader,
+            movie,
+            video,
+            next: 0,
+        })
+    }
+
+    /// Width and height of the frames
+    pub fn size(&self) -> (u32, u32) {
+        (self.movie.header.width, self.movie.header.height)
+    }
+
+    /// Decodes the next frame of the movie, None once every frame has been
+    /// decoded or a frame can't be decoded
+    pub fn decode_frame(&mut self) -> Option<(Duration, VideoFrame)> {
+        if self.next >= self.movie.frames.len() {
+            return None;
+        }
+
+        let index = self.next;
+        self.next += 1;
+
+        match self.read_frame(index) {
+            Ok(picture) => Some((self.movie.frame_time(index), picture)),
+            Err(err) => {
+                warn!("Failed to decode Bink frame {}: {}", index, err);
+                None
+            }
+        }
+    }
+
+    fn read_frame(&mut self, index: usize) -> Result<BinkPicture, BinkError> {
+        let data = self.movie.read_video(&mut self.reader, index)?;
+        Ok(self.video.decode(&data)?.clone())
+    }
+
+    /// Seeks to the keyframe at or before the position
+    pub fn seek(&mut self, position: Duration) -> Result<(), VideoError> {
+        self.next = self.movie.keyframe_before(position);
+        Ok(())
+    }
+
+    pub fn write_rgba(&mut self, frame: &VideoFrame, out: &mut [u8]) -> Result<(), VideoError> {
+        frame.write_rgba(out);
+        Ok(())
+    }
+}
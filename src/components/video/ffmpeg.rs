@@ -0,0 +1,155 @@
+//! Video backend decoding every format ffmpeg supports
+
+use std::{path::Path, time::Duration};
+
+use bevy::log::warn;
+use ffmpeg_next::{
+    format::{input, Pixel},
+    frame::Video,
+    software::scaling::{context::Context as ScalingContext, Flags},
+    Rational,
+};
+
+pub type VideoError = ffmpeg_next::Error;
+
+/// Decoded frame before conversion to RGBA
+pub type VideoFrame = Video;
+
+/// Frame duration used for streams that don't provide a frame rate
+const DEFAULT_FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 30);
+
+pub fn init() {
+    ffmpeg_next::init().expect("Failed to initialize FFmpeg");
+}
+
+pub struct VideoDecoder {
+    input_context: ffmpeg_next::format::context::Input,
+    decoder: ffmpeg_next::decoder::Video,
+    scaler: ScalingContext,
+    stream_index: usize,
+    /// Units of the stream timestamps in seconds
+    time_base: Rational,
+    /// Timestamp of the first frame of the stream
+    start_time: i64,
+    /// Time between frames, used for frames without a timestamp
+    frame_duration: Duration,
+    /// Presentation time of the last frame decoded
+    last_frame_time: Duration,
+    /// Whether the end of the file has been sent to the decoder
+    eof: bool,
+}
+
+impl VideoDecoder {
+    pub fn open(path: &Path) -> Result<Self, VideoError> {
+        let input = input(&path)?;
+
+        let video_stream = input
+            .streams()
+            .best(ffmpeg_next::media::Type::Video)
+            .ok_or(ffmpeg_next::Error::StreamNotFound)?;
+        let stream_index = video_stream.index();
+        let time_base = video_stream.time_base();
+        // Streams without a start time use the no timestamp value
+        let start_time = video_stream.start_time().max(0);
+        let frame_rate = f64::from(video_stream.avg_frame_rate());
+        let frame_duration = match frame_rate > 0.0 {
+            true => Duration::from_secs_f64(1.0 / frame_rate),
+            false => DEFAULT_FRAME_DURATION,
+        };
+
+        let context_decoder =
+            ffmpeg_next::codec::context::Context::from_parameters(video_stream.parameters())?;
+        let decoder = context_decoder.decoder().video()?;
+
+        let scaler = ScalingContext::get(
+            decoder.format(),
+            decoder.width(),
+            decoder.height(),
+            Pixel::RGBA,
+            decoder.width(),
+            decoder.height(),
+            Flags::BILINEAR,
+        )?;
+
+        Ok(Self {
+            input_context: input,
+            decoder,
+            scaler,
+            stream_index,
+            time_base,
+            start_time,
+            frame_duration,
+            last_frame_time: Duration::ZERO,
+            eof: false,
+        })
+    }
+
+    /// Width and height of the frames
+    pub fn size(&self) -> (u32, u32) {
+        (self.decoder.width(), self.decoder.height())
+    }
+
+    /// Presentation time of the frame relative to the start of the stream
+    fn frame_time(&self, frame: &Video) -> Duration {
+        match frame.timestamp() {
+            Some(timestamp) => {
+                let ticks = (timestamp - self.start_time).max(0);
+                Duration::from_secs_f64(ticks as f64 * f64::from(self.time_base))
+            }
+            None => self.last_frame_time + self.frame_duration,
+        }
+    }
+
+    /// Decodes packets until a frame is received, None once the stream has
+    /// no frames left
+    pub fn decode_frame(&mut self) -> Option<(Duration, VideoFrame)> {
+        let mut decoded = Video::empty();
+        loop {
+            if self.decoder.receive_frame(&mut decoded).is_ok() {
+                let time = self.frame_time(&decoded);
+                self.last_frame_time = time;
+                return Some((time, decoded));
+            }
+
+            if self.eof {
+                return None;
+            }
+
+            match self.input_context.packets().next() {
+                Some((stream, packet)) => {
+                    if stream.index() != self.stream_index {
+                        continue;
+                    }
+                    if let Err(err) = self.decoder.send_packet(&packet) {
+                        warn!("Failed to decode video packet: {}", err);
+                    }
+                }
+                // Flush the frames still buffered in the decoder
+                None => {
+                    self.eof = true;
+                    let _ = self.decoder.send_eof();
+                }
+            }
+        }
+    }
+
+    /// Seeks to the keyframe at or before the position
+    pub fn seek(&mut self, position: Duration) -> Result<(), VideoError> {
+        // Seeking without a stream uses microsecond timestamps
+        let timestamp = position.as_micros() as i64;
+        self.input_context.seek(timestamp, ..timestamp)?;
+        self.decoder.flush();
+        self.eof = false;
+        self.last_frame_time = position;
+        Ok(())
+    }
+
+    /// Converts the frame to RGBA pixels
+    pub fn write_rgba(&mut self, frame: &VideoFrame, out: &mut [u8]) -> Result<(), VideoError> {
+        let mut rgb_frame = Video::empty();
+        // run frame through scaler for color space conversion
+        self.scaler.run(frame, &mut rgb_frame)?;
+        out.copy_from_slice(rgb_frame.data(0));
+        Ok(())
+    }
+}
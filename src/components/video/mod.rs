@@ -0,0 +1,290 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+use bevy::utils::hashbrown::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+
+#[cfg(feature = "bink")]
+mod bink;
+#[cfg(all(feature = "ffmpeg", not(feature = "bink")))]
+mod ffmpeg;
+
+#[cfg(feature = "bink")]
+use bink as backend;
+#[cfg(all(feature = "ffmpeg", not(feature = "bink")))]
+use ffmpeg as backend;
+
+#[cfg(not(any(feature = "ffmpeg", feature = "bink")))]
+compile_error!("Video playback needs either the ffmpeg or the bink feature");
+
+pub use backend::VideoError;
+
+/// Resource for storing internal video player data which is !Send, each
+/// player decodes independently and its data is dropped when the player
+/// entity is despawned
+#[derive(Default)]
+pub struct VideoResource {
+    /// Mapping between spawned video player entities and their data
+    pub data: HashMap<Entity, VideoPlayerInternal>,
+}
+
+pub struct VideoPlugin;
+
+impl Plugin for VideoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_non_send_resource::<VideoResource>();
+        app.add_event::<VideoFinished>();
+        app.add_systems(Startup, init_backend);
+        app.add_systems(
+            Update,
+            (
+                play_video.in_set(VideoDecodeSet),
+                update_video_targets.after(VideoDecodeSet),
+                cleanup_video_players,
+            ),
+        );
+    }
+}
+
+/// Set for the systems decoding video frames into their images
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VideoDecodeSet;
+
+/// Where the frames of a [VideoPlayer] are shown besides its image, players
+/// without a target only update their image
+#[derive(Component, Debug, Clone, Default)]
+pub enum VideoTarget {
+    /// Frames are only written to the image, such as for UI images
+    #[default]
+    Image,
+    /// Frames are shown as the base color texture of the material, used
+    /// for in-world surfaces such as TV screens
+    Material(Handle<StandardMaterial>),
+}
+
+/// Event sent when a video that isn't looping plays its last frame
+#[derive(Event, Debug, Clone, Copy)]
+pub struct VideoFinished {
+    /// Entity of the video player
+    pub entity: Entity,
+}
+
+/// Video player data
+pub struct VideoPlayerInternal {
+    decoder: backend::VideoDecoder,
+    /// Decoded frame waiting for its presentation time along with the time
+    next_frame: Option<(Duration, backend::VideoFrame)>,
+}
+
+impl VideoPlayerInternal {
+    /// Seeks to the keyframe at or before the position, the frames up to
+    /// the position are then decoded and skipped by [play_video]
+    fn seek(&mut self, position: Duration) -> Result<(), VideoError> {
+        self.next_frame = None;
+        self.decoder.seek(position)
+    }
+}
+
+#[derive(Component)]
+pub struct VideoPlayer {
+    pub image_handle: Handle<Image>,
+    /// Whether to loop the video
+    pub looping: bool,
+    /// Whether the video has finished playing
+    pub finished: bool,
+    /// Number of times the video has looped back to the start
+    pub loop_count: u32,
+    /// Number of seeks applied to the video
+    pub seek_count: u32,
+    /// Playback position within the current loop
+    position: Duration,
+    paused: bool,
+    /// Position requested by [VideoPlayer::seek] not yet applied
+    pending_seek: Option<Duration>,
+}
+
+impl VideoPlayer {
+    pub fn new<P>(
+        path: P,
+        looping: bool,
+        mut images: ResMut<Assets<Image>>,
+    ) -> Result<(VideoPlayer, VideoPlayerInternal), VideoError>
+    where
+        P: AsRef<Path>,
+    {
+        let decoder = backend::VideoDecoder::open(path.as_ref())?;
+        let (width, height) = decoder.size();
+
+        let mut image = Image::new_fill(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &Color::BLACK.as_rgba_u8(),
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        image.texture_descriptor.usage = TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING;
+
+        let image_handle: Handle<Image> = images.add(image);
+
+        Ok((
+            VideoPlayer {
+                image_handle,
+                looping,
+                finished: false,
+                loop_count: 0,
+                seek_count: 0,
+                position: Duration::ZERO,
+                paused: false,
+                pending_seek: None,
+            },
+            VideoPlayerInternal {
+                decoder,
+                next_frame: None,
+            },
+        ))
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Moves playback to the position, seeking a finished video plays it
+    /// again from the position
+    pub fn seek(&mut self, position: Duration) {
+        self.pending_seek = Some(position);
+        self.finished = false;
+    }
+
+    /// Playback position within the current loop of the video
+    pub fn position(&self) -> Duration {
+        self.pending_seek.unwrap_or(self.position)
+    }
+}
+
+/// System that initialized the video backend
+fn init_backend() {
+    backend::init();
+}
+
+/// System that advances the playback position of each video and displays
+/// the latest frame whose presentation time has been reached, frames that
+/// fall behind are decoded and dropped so videos play at their own rate
+fn play_video(
+    time: Res<Time>,
+    mut video_player_query: Query<(&mut VideoPlayer, Entity)>,
+    mut video_resource: NonSendMut<VideoResource>,
+    mut images: ResMut<Assets<Image>>,
+    mut finished_events: EventWriter<VideoFinished>,
+) {
+    for (mut video_player, entity) in video_player_query.iter_mut() {
+        let Some(data) = video_resource.data.get_mut(&entity) else {
+            continue;
+        };
+
+        if let Some(position) = video_player.pending_seek.take() {
+            if let Err(err) = data.seek(position) {
+                warn!("Failed to seek video: {}", err);
+            }
+            video_player.position = position;
+            video_player.seek_count += 1;
+        }
+
+        // Skip finished and paused players
+        if video_player.finished || video_player.paused {
+            continue;
+        }
+
+        video_player.position += time.delta();
+
+        let mut latest = None;
+        loop {
+            let next = match data.next_frame.take() {
+                Some(value) => Some(value),
+                None => data.decoder.decode_frame(),
+            };
+            let Some((frame_time, frame)) = next else {
+                // Handle looping the video player
+                if video_player.looping {
+                    if let Err(err) = data.seek(Duration::ZERO) {
+                        warn!("Failed to loop video: {}", err);
+                    }
+                    video_player.position = Duration::ZERO;
+                    video_player.loop_count += 1;
+                } else {
+                    video_player.finished = true;
+                    finished_events.send(VideoFinished { entity });
+                }
+                break;
+            };
+
+            if frame_time > video_player.position {
+                data.next_frame = Some((frame_time, frame));
+                break;
+            }
+            latest = Some(frame);
+        }
+
+        let Some(decoded) = latest else {
+            continue;
+        };
+
+        // update data of image texture
+        let Some(image) = images.get_mut(&video_player.image_handle) else {
+            continue;
+        };
+        if let Err(err) = data.decoder.write_rgba(&decoded, &mut image.data) {
+            warn!("Failed to convert video frame: {}", err);
+        }
+    }
+}
+
+/// System pointing the target materials at the images of their players.
+/// Materials don't pick up changes to their textures by themselves so
+/// each material is touched whenever its player writes a new frame
+fn update_video_targets(
+    mut image_events: EventReader<AssetEvent<Image>>,
+    players: Query<(&VideoPlayer, Ref<VideoTarget>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let modified: HashSet<AssetId<Image>> = image_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (video_player, target) in players.iter() {
+        let VideoTarget::Material(material) = target.as_ref() else {
+            continue;
+        };
+        if !target.is_changed() && !modified.contains(&video_player.image_handle.id()) {
+            continue;
+        }
+
+        if let Some(material) = materials.get_mut(material) {
+            material.base_color_texture = Some(video_player.image_handle.clone());
+        }
+    }
+}
+
+/// System dropping the decoder of each despawned video player
+fn cleanup_video_players(
+    mut removed: RemovedComponents<VideoPlayer>,
+    mut video_resource: NonSendMut<VideoResource>,
+) {
+    for entity in removed.read() {
+        video_resource.data.remove(&entity);
+    }
+}
@@ -0,0 +1,76 @@
+//! Persistent viewer settings stored as `key=value` entries, values the
+//! viewer detects are written back so they can be overridden by hand
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Path of the config file, relative to the working directory
+pub const CONFIG_PATH: &str = "openma.ini";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Parse(#[from] serde_ini::de::Error),
+    #[error(transparent)]
+    Write(#[from] serde_ini::ser::Error),
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Fingerprint of the game data the version was detected for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_fingerprint: Option<String>,
+    /// Platform of the game data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_platform: Option<String>,
+    /// Region of the game data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_region: Option<String>,
+    /// Name of the known release the game data matched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_release: Option<String>,
+}
+
+impl Config {
+    /// Loads the config, a missing file is an empty config
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(value) => Ok(serde_ini::from_str(&value)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let value = serde_ini::to_string(self)?;
+        std::fs::write(path, value)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Config;
+
+    #[test]
+    fn test_config_round_trip() {
+        let config = Config {
+            data_fingerprint: Some("abc".to_string()),
+            data_platform: Some("gamecube".to_string()),
+            ..Default::default()
+        };
+
+        let value = serde_ini::to_string(&config).unwrap();
+        assert!(!value.contains("data_region"));
+        assert_eq!(serde_ini::from_str::<Config>(&value).unwrap(), config);
+        assert_eq!(
+            serde_ini::from_str::<Config>("").unwrap(),
+            Config::default()
+        );
+    }
+}
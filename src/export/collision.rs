@@ -0,0 +1,254 @@
+//! Export of level collision for use in other engines, shapes are written
+//! as OBJ or glTF meshes or as a JSON shape set that maps directly onto
+//! rapier trimesh colliders. The kDOP collision trees haven't been decoded
+//! yet so the shapes are built from the render geometry of the level
+//! until they are
+
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use bevy::{
+    math::Vec3,
+    render::{
+        mesh::{Indices, Mesh, VertexAttributeValues},
+        render_resource::PrimitiveTopology,
+    },
+};
+use bevy_rapier3d::geometry::Collider;
+use serde::{Deserialize, Serialize};
+
+use super::{gltf::build_gltf, provenance::Provenance, ExportMesh};
+
+/// Triangle mesh collision shape
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CollisionShape {
+    pub name: String,
+    /// World space position of the shape
+    pub translation: [f32; 3],
+    /// Vertices relative to the translation
+    pub vertices: Vec<[f32; 3]>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+/// Set of collision shapes making up a level
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CollisionSet {
+    pub shapes: Vec<CollisionShape>,
+    /// How the shapes were produced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+/// Reads the indices of the mesh, meshes without indices are
+/// treated as having sequential indices
+fn mesh_indices(mesh: &Mesh, count: usize) -> Vec<u32> {
+    match mesh.indices() {
+        Some(Indices::U16(values)) => values.iter().map(|value| *value as u32).collect(),
+        Some(Indices::U32(values)) => values.clone(),
+        None => (0..count as u32).collect(),
+    }
+}
+
+impl CollisionShape {
+    /// Creates a shape from the triangles of a bevy mesh, returns None
+    /// for meshes without positions or triangles
+    pub fn from_mesh(name: impl Into<String>, translation: [f32; 3], mesh: &Mesh) -> Option<Self> {
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            return None;
+        };
+
+        let indices = mesh_indices(mesh, positions.len());
+        let triangles: Vec<[u32; 3]> = match mesh.primitive_topology() {
+            PrimitiveTopology::TriangleList => indices
+                .chunks_exact(3)
+                .map(|value| [value[0], value[1], value[2]])
+                .collect(),
+            PrimitiveTopology::TriangleStrip => indices
+                .windows(3)
+                .enumerate()
+                // Every other triangle of a strip has reversed winding
+                .map(|(index, value)| match index % 2 {
+                    0 => [value[0], value[1], value[2]],
+                    _ => [value[1], value[0], value[2]],
+                })
+                .collect(),
+            _ => return None,
+        };
+
+        let triangles: Vec<[u32; 3]> = triangles
+            .into_iter()
+            // Strips use repeated indices to join, these triangles have no area
+            .filter(|[a, b, c]| a != b && b != c && a != c)
+            .filter(|triangle| {
+                triangle
+                    .iter()
+                    .all(|index| (*index as usize) < positions.len())
+            })
+            .collect();
+
+        if triangles.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            name: name.into(),
+            translation,
+            vertices: positions.clone(),
+            triangles,
+        })
+    }
+
+    /// Creates the rapier collider for the shape, the translation
+    /// isn't included and should be applied to the collider entity
+    pub fn to_collider(&self) -> Collider {
+        Collider::trimesh(
+            self.vertices
+                .iter()
+                .copied()
+                .map(Vec3::from_array)
+                .collect(),
+            self.triangles.clone(),
+        )
+    }
+
+    fn to_export_mesh(&self) -> ExportMesh {
+        ExportMesh {
+            name: self.name.clone(),
+            translation: self.translation,
+            positions: self.vertices.clone(),
+            indices: self.triangles.iter().flatten().copied().collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl CollisionSet {
+    /// Writes the shapes as OBJ objects, vertices are written
+    /// in world space
+    pub fn write_obj<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "# Collision exported by OpenMA")?;
+        if let Some(provenance) = &self.provenance {
+            for source in &provenance.sources {
+                writeln!(out, "# source: {} blake3={}", source.path, source.blake3)?;
+            }
+            for (key, value) in &provenance.settings {
+                writeln!(out, "# setting: {}={}", key, value)?;
+            }
+        }
+
+        // OBJ indices are 1 based and shared across all objects
+        let mut base = 1;
+        for shape in &self.shapes {
+            writeln!(out, "o {}", shape.name)?;
+
+            let [tx, ty, tz] = shape.translation;
+            for [x, y, z] in &shape.vertices {
+                writeln!(out, "v {} {} {}", x + tx, y + ty, z + tz)?;
+            }
+
+            for [a, b, c] in &shape.triangles {
+                writeln!(out, "f {} {} {}", base + a, base + b, base + c)?;
+            }
+
+            base += shape.vertices.len() as u32;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the shapes as an untextured glTF to `path` with the
+    /// buffer alongside it
+    pub fn write_gltf(&self, path: &Path) -> io::Result<()> {
+        let meshes: Vec<ExportMesh> = self
+            .shapes
+            .iter()
+            .map(CollisionShape::to_export_mesh)
+            .collect();
+        let meshes: Vec<&ExportMesh> = meshes.iter().collect();
+
+        let buffer_uri = path
+            .with_extension("bin")
+            .file_name()
+            .map(|value| value.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        build_gltf(&meshes, &buffer_uri, "")
+            .with_provenance(self.provenance.as_ref())
+            .write(path)
+    }
+
+    /// Writes the shape set as JSON, each shape holds the inputs
+    /// of a rapier trimesh collider
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        serde_json::to_writer_pretty(out, self)?;
+        Ok(())
+    }
+
+    pub fn read_json(bytes: &[u8]) -> serde_json::Result<Self> {
+        serde_json::from_slice(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::render::{
+        mesh::{Indices, Mesh},
+        render_resource::PrimitiveTopology,
+    };
+
+    use crate::export::provenance::Provenance;
+
+    use super::{CollisionSet, CollisionShape};
+
+    fn quad_strip() -> Mesh {
+        Mesh::new(PrimitiveTopology::TriangleStrip)
+            .with_inserted_attribute(
+                Mesh::ATTRIBUTE_POSITION,
+                vec![
+                    [0.0, 0.0, 0.0],
+                    [1.0, 0.0, 0.0],
+                    [0.0, 0.0, 1.0],
+                    [1.0, 0.0, 1.0],
+                ],
+            )
+            // Trailing repeated index joins the next strip
+            .with_indices(Some(Indices::U16(vec![0, 1, 2, 3, 3])))
+    }
+
+    #[test]
+    fn test_strip_triangles() {
+        let shape = CollisionShape::from_mesh("floor", [0.0; 3], &quad_strip()).unwrap();
+        assert_eq!(shape.triangles, vec![[0, 1, 2], [2, 1, 3]]);
+    }
+
+    #[test]
+    fn test_write_obj_and_json() {
+        let set = CollisionSet {
+            shapes: vec![
+                CollisionShape::from_mesh("a", [0.0; 3], &quad_strip()).unwrap(),
+                CollisionShape::from_mesh("b", [0.0, 2.0, 0.0], &quad_strip()).unwrap(),
+            ],
+            provenance: Some(
+                Provenance::default()
+                    .with_source("wld/gcwdglitch01.ape", blake3::hash(b"level"))
+                    .with_setting("source", "render geometry"),
+            ),
+        };
+
+        let mut obj = Vec::new();
+        set.write_obj(&mut obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        assert!(obj.contains("o b\nv 0 2 0\n"));
+        // Second object indices follow on from the first
+        assert!(obj.contains("f 5 6 7\n"));
+        assert!(obj.contains("# setting: source=render geometry\n"));
+
+        let mut json = Vec::new();
+        set.write_json(&mut json).unwrap();
+        assert_eq!(CollisionSet::read_json(&json).unwrap(), set);
+    }
+}
@@ -0,0 +1,333 @@
+//! Minimal glTF 2.0 writer, geometry is stored in an external .bin buffer
+//! and textures are referenced by URI so tiles can share them
+
+use std::{collections::HashMap, path::Path};
+
+use serde::Serialize;
+
+use crate::formats::texture::sampling::SamplerSettings;
+
+use super::{provenance::Provenance, ExportMesh};
+
+const COMPONENT_FLOAT: u32 = 5126;
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Gltf {
+    asset: Asset,
+    scene: usize,
+    scenes: Vec<Scene>,
+    nodes: Vec<Node>,
+    meshes: Vec<Mesh>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    materials: Vec<Material>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    textures: Vec<Texture>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    images: Vec<Image>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    samplers: Vec<Sampler>,
+    buffers: Vec<Buffer>,
+    buffer_views: Vec<BufferView>,
+    accessors: Vec<Accessor>,
+}
+
+#[derive(Serialize)]
+struct Asset {
+    version: &'static str,
+    generator: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extras: Option<AssetExtras>,
+}
+
+#[derive(Serialize)]
+struct AssetExtras {
+    provenance: Provenance,
+}
+
+#[derive(Serialize)]
+struct Scene {
+    nodes: Vec<usize>,
+}
+
+#[derive(Serialize)]
+struct Node {
+    name: String,
+    mesh: usize,
+    translation: [f32; 3],
+}
+
+#[derive(Serialize)]
+struct Mesh {
+    name: String,
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Serialize)]
+struct Primitive {
+    attributes: HashMap<&'static str, usize>,
+    indices: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    material: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Material {
+    pbr_metallic_roughness: PbrMetallicRoughness,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PbrMetallicRoughness {
+    base_color_texture: TextureInfo,
+    metallic_factor: f32,
+}
+
+#[derive(Serialize)]
+struct TextureInfo {
+    index: usize,
+}
+
+#[derive(Serialize)]
+struct Texture {
+    source: usize,
+    sampler: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Sampler {
+    mag_filter: u32,
+    min_filter: u32,
+}
+
+#[derive(Serialize)]
+struct Image {
+    uri: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Buffer {
+    uri: String,
+    byte_length: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    byte_offset: usize,
+    byte_length: usize,
+    target: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: usize,
+    component_type: u32,
+    count: usize,
+    #[serde(rename = "type")]
+    ty: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<Vec<f32>>,
+}
+
+/// glTF document along with its binary buffer
+pub struct GltfFile {
+    pub document: Gltf,
+    pub buffer: Vec<u8>,
+}
+
+impl GltfFile {
+    /// Stores the provenance in the extras of the document asset
+    pub fn with_provenance(mut self, provenance: Option<&Provenance>) -> Self {
+        self.document.asset.extras = provenance.map(|provenance| AssetExtras {
+            provenance: provenance.clone(),
+        });
+        self
+    }
+
+    /// Writes the document to `path` and the buffer alongside it
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path.with_extension("bin"), &self.buffer)?;
+        let json = serde_json::to_vec_pretty(&self.document)?;
+        std::fs::write(path, json)
+    }
+}
+
+/// Builds a glTF document from the meshes, `buffer_uri` is the file name the
+/// binary buffer will be written to and `texture_dir` is the URI prefix of
+/// the shared texture directory
+pub fn build_gltf(meshes: &[&ExportMesh], buffer_uri: &str, texture_dir: &str) -> GltfFile {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut nodes = Vec::new();
+    let mut out_meshes = Vec::new();
+    let mut images: Vec<Image> = Vec::new();
+    let mut image_lookup: HashMap<&str, usize> = HashMap::new();
+    let mut samplers: Vec<Sampler> = Vec::new();
+    let mut sampler_lookup: HashMap<SamplerSettings, usize> = HashMap::new();
+    let mut textures: Vec<Texture> = Vec::new();
+    let mut materials = Vec::new();
+    // Textures sampled differently by different meshes need separate materials
+    let mut material_lookup: HashMap<(&str, SamplerSettings), usize> = HashMap::new();
+
+    let mut push_view = |buffer: &mut Vec<u8>, bytes: &[u8], target: u32| {
+        // Accessors require 4 byte alignment
+        while buffer.len() % 4 != 0 {
+            buffer.push(0);
+        }
+        views.push(BufferView {
+            buffer: 0,
+            byte_offset: buffer.len(),
+            byte_length: bytes.len(),
+            target,
+        });
+        buffer.extend_from_slice(bytes);
+        views.len() - 1
+    };
+
+    for mesh in meshes {
+        let Some((min, max)) = mesh.local_bounds() else {
+            continue;
+        };
+
+        let mut attributes = HashMap::new();
+
+        let positions: Vec<u8> = mesh
+            .positions
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let view = push_view(&mut buffer, &positions, TARGET_ARRAY_BUFFER);
+        accessors.push(Accessor {
+            buffer_view: view,
+            component_type: COMPONENT_FLOAT,
+            count: mesh.positions.len(),
+            ty: "VEC3",
+            min: Some(min.to_vec()),
+            max: Some(max.to_vec()),
+        });
+        attributes.insert("POSITION", accessors.len() - 1);
+
+        if let Some(uvs) = mesh
+            .uvs
+            .as_ref()
+            .filter(|uvs| uvs.len() == mesh.positions.len())
+        {
+            let bytes: Vec<u8> = uvs
+                .iter()
+                .flatten()
+                .flat_map(|value| value.to_le_bytes())
+                .collect();
+            let view = push_view(&mut buffer, &bytes, TARGET_ARRAY_BUFFER);
+            accessors.push(Accessor {
+                buffer_view: view,
+                component_type: COMPONENT_FLOAT,
+                count: uvs.len(),
+                ty: "VEC2",
+                min: None,
+                max: None,
+            });
+            attributes.insert("TEXCOORD_0", accessors.len() - 1);
+        }
+
+        let indices: Vec<u8> = mesh
+            .indices
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let view = push_view(&mut buffer, &indices, TARGET_ELEMENT_ARRAY_BUFFER);
+        accessors.push(Accessor {
+            buffer_view: view,
+            component_type: COMPONENT_UNSIGNED_INT,
+            count: mesh.indices.len(),
+            ty: "SCALAR",
+            min: None,
+            max: None,
+        });
+        let indices = accessors.len() - 1;
+
+        let material = mesh.texture.as_deref().map(|texture| {
+            *material_lookup
+                .entry((texture, mesh.sampler))
+                .or_insert_with(|| {
+                    let source = *image_lookup.entry(texture).or_insert_with(|| {
+                        images.push(Image {
+                            uri: format!("{}/{}", texture_dir.trim_end_matches('/'), texture),
+                        });
+                        images.len() - 1
+                    });
+                    let sampler = *sampler_lookup.entry(mesh.sampler).or_insert_with(|| {
+                        let (mag_filter, min_filter) = mesh.sampler.gltf_filters();
+                        samplers.push(Sampler {
+                            mag_filter,
+                            min_filter,
+                        });
+                        samplers.len() - 1
+                    });
+
+                    textures.push(Texture { source, sampler });
+                    materials.push(Material {
+                        pbr_metallic_roughness: PbrMetallicRoughness {
+                            base_color_texture: TextureInfo {
+                                index: textures.len() - 1,
+                            },
+                            metallic_factor: 0.0,
+                        },
+                    });
+                    materials.len() - 1
+                })
+        });
+
+        out_meshes.push(Mesh {
+            name: mesh.name.clone(),
+            primitives: vec![Primitive {
+                attributes,
+                indices,
+                material,
+            }],
+        });
+        nodes.push(Node {
+            name: mesh.name.clone(),
+            mesh: out_meshes.len() - 1,
+            translation: mesh.translation,
+        });
+    }
+
+    let document = Gltf {
+        asset: Asset {
+            version: "2.0",
+            generator: "OpenMA",
+            extras: None,
+        },
+        scene: 0,
+        scenes: vec![Scene {
+            nodes: (0..nodes.len()).collect(),
+        }],
+        nodes,
+        meshes: out_meshes,
+        materials,
+        textures,
+        images,
+        samplers,
+        buffers: vec![Buffer {
+            uri: buffer_uri.to_string(),
+            byte_length: buffer.len(),
+        }],
+        buffer_views: views,
+        accessors,
+    };
+
+    GltfFile { document, buffer }
+}
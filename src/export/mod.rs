@@ -0,0 +1,71 @@
+//! Exporting of loaded geometry into formats usable by other tools
+
+use crate::formats::texture::sampling::SamplerSettings;
+
+pub mod collision;
+pub mod gltf;
+pub mod provenance;
+pub mod tiles;
+
+/// Mesh geometry prepared for export
+#[derive(Debug, Clone, Default)]
+pub struct ExportMesh {
+    pub name: String,
+    /// World space position of the mesh
+    pub translation: [f32; 3],
+    /// Positions relative to the translation
+    pub positions: Vec<[f32; 3]>,
+    pub uvs: Option<Vec<[f32; 2]>>,
+    /// Triangle list indices
+    pub indices: Vec<u32>,
+    /// Path of the texture image relative to the shared texture directory
+    pub texture: Option<String>,
+    /// How the texture is sampled
+    pub sampler: SamplerSettings,
+    /// Level cell the mesh belongs to, when known
+    pub cell: Option<u32>,
+    /// Part of the level skybox rather than the level itself, skybox
+    /// meshes are centered on the viewer so have no place in the level
+    pub skybox: bool,
+}
+
+impl ExportMesh {
+    /// Local space bounds of the mesh positions
+    pub fn local_bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let first = *self.positions.first()?;
+        Some(
+            self.positions
+                .iter()
+                .fold((first, first), |bounds, position| {
+                    expand_bounds(bounds, *position)
+                }),
+        )
+    }
+
+    /// World space bounds of the mesh
+    pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let (min, max) = self.local_bounds()?;
+        let offset = |value: [f32; 3]| {
+            [
+                value[0] + self.translation[0],
+                value[1] + self.translation[1],
+                value[2] + self.translation[2],
+            ]
+        };
+        Some((offset(min), offset(max)))
+    }
+}
+
+/// Splits the meshes into the level meshes and the skybox meshes
+pub fn split_skybox(meshes: &[ExportMesh]) -> (Vec<&ExportMesh>, Vec<&ExportMesh>) {
+    meshes.iter().partition(|mesh| !mesh.skybox)
+}
+
+/// Expands the (min, max) bounds to contain the provided point
+pub(crate) fn expand_bounds(bounds: ([f32; 3], [f32; 3]), point: [f32; 3]) -> ([f32; 3], [f32; 3]) {
+    let (min, max) = bounds;
+    (
+        std::array::from_fn(|axis| min[axis].min(point[axis])),
+        std::array::from_fn(|axis| max[axis].max(point[axis])),
+    )
+}
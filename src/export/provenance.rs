@@ -0,0 +1,55 @@
+//! Conversion metadata stored with exported files so they can be traced back
+//! to the game files and settings they were produced from. glTF exports store
+//! it in the asset extras and the JSON exports include it directly
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::VERSION;
+
+/// Game file an export was produced from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceFile {
+    /// Path of the file in the game data
+    pub path: String,
+    /// Hex encoded blake3 hash of the file contents
+    pub blake3: String,
+}
+
+/// Record of how an exported file was produced
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Name and version of the converter
+    pub converter: String,
+    pub sources: Vec<SourceFile>,
+    /// Settings the export was made with
+    pub settings: BTreeMap<String, String>,
+}
+
+impl Default for Provenance {
+    fn default() -> Self {
+        Self {
+            converter: format!("OpenMA {}", VERSION),
+            sources: Vec::new(),
+            settings: BTreeMap::new(),
+        }
+    }
+}
+
+impl Provenance {
+    /// Adds a source file with its hash, [GameFs::hash](crate::fs::GameFs::hash)
+    /// provides the hash of files in the game data
+    pub fn with_source(mut self, path: impl Into<String>, hash: blake3::Hash) -> Self {
+        self.sources.push(SourceFile {
+            path: path.into(),
+            blake3: hash.to_hex().to_string(),
+        });
+        self
+    }
+
+    pub fn with_setting(mut self, key: &str, value: impl ToString) -> Self {
+        self.settings.insert(key.to_string(), value.to_string());
+        self
+    }
+}
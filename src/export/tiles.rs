@@ -0,0 +1,244 @@
+//! Tiled export of large levels, the level is split into spatial chunks
+//! that are each written as a self-contained glTF file. Textures are shared
+//! between the tiles and a manifest describes where each tile is placed
+
+use std::{collections::BTreeMap, io, path::Path};
+
+use serde::Serialize;
+
+use crate::cancel::CancelToken;
+
+use super::{expand_bounds, gltf::build_gltf, provenance::Provenance, split_skybox, ExportMesh};
+
+/// How meshes are grouped into tiles
+#[derive(Debug, Clone, Copy)]
+pub enum TileMode {
+    /// Square grid on the ground (XZ) plane with the provided tile size
+    Grid { size: f32 },
+    /// One tile per level cell, meshes without a cell are grouped into cell 0
+    Cells,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TileKey {
+    Grid(i32, i32),
+    Cell(u32),
+}
+
+impl TileKey {
+    fn file_name(&self, name: &str) -> String {
+        match self {
+            TileKey::Grid(x, z) => format!("{name}_{x}_{z}.gltf"),
+            TileKey::Cell(cell) => format!("{name}_cell{cell}.gltf"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TileManifest {
+    /// Directory of the shared textures, relative to the manifest
+    pub texture_dir: String,
+    /// Size of each grid tile when exported as a grid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tile_size: Option<f32>,
+    pub tiles: Vec<TileEntry>,
+    /// glTF file of the skybox, relative to the manifest
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skybox: Option<String>,
+    /// How the tiles were produced, also stored in each tile
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TileEntry {
+    /// glTF file of the tile, relative to the manifest
+    pub file: String,
+    /// Grid position of the tile when exported as a grid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid: Option<[i32; 2]>,
+    /// Level cell of the tile when exported by cell
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell: Option<u32>,
+    /// World space bounds of the tile contents
+    pub bounds_min: [f32; 3],
+    pub bounds_max: [f32; 3],
+    pub mesh_count: usize,
+}
+
+/// Groups the meshes into tiles by the center of their bounds, skybox
+/// meshes aren't placed in the level so don't belong to any tile
+pub fn group_tiles(meshes: &[ExportMesh], mode: TileMode) -> BTreeMap<TileKey, Vec<&ExportMesh>> {
+    let mut out: BTreeMap<TileKey, Vec<&ExportMesh>> = BTreeMap::new();
+
+    for mesh in meshes.iter().filter(|mesh| !mesh.skybox) {
+        let Some((min, max)) = mesh.bounds() else {
+            continue;
+        };
+
+        let key = match mode {
+            TileMode::Grid { size } => {
+                let center_x = (min[0] + max[0]) * 0.5;
+                let center_z = (min[2] + max[2]) * 0.5;
+                TileKey::Grid(
+                    (center_x / size).floor() as i32,
+                    (center_z / size).floor() as i32,
+                )
+            }
+            TileMode::Cells => TileKey::Cell(mesh.cell.unwrap_or_default()),
+        };
+
+        out.entry(key).or_default().push(mesh);
+    }
+
+    out
+}
+
+/// Removes a written tile along with its buffer
+fn remove_tile(path: &Path) -> io::Result<()> {
+    for path in [path.to_path_buf(), path.with_extension("bin")] {
+        match std::fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn combined_bounds(meshes: &[&ExportMesh]) -> ([f32; 3], [f32; 3]) {
+    meshes.iter().filter_map(|mesh| mesh.bounds()).fold(
+        ([f32::MAX; 3], [f32::MIN; 3]),
+        |bounds, (mesh_min, mesh_max)| expand_bounds(expand_bounds(bounds, mesh_min), mesh_max),
+    )
+}
+
+/// Exports the meshes as tiles into `out_dir`, writing a glTF and buffer
+/// per tile along with a `{name}.tiles.json` manifest. Skybox meshes are
+/// written separately to `{name}_skybox.gltf`. `texture_dir` is the
+/// directory of the shared textures relative to `out_dir`. The provenance
+/// is stored in the manifest and the asset extras of every glTF file.
+/// The token is checked between tiles, when cancelled the tiles written so
+/// far are removed and the export fails with [io::ErrorKind::Interrupted]
+pub fn export_tiles(
+    meshes: &[ExportMesh],
+    mode: TileMode,
+    out_dir: &Path,
+    name: &str,
+    texture_dir: &str,
+    provenance: Option<&Provenance>,
+    cancel: &CancelToken,
+) -> io::Result<TileManifest> {
+    std::fs::create_dir_all(out_dir)?;
+
+    let mut tiles: Vec<TileEntry> = Vec::new();
+    for (key, meshes) in group_tiles(meshes, mode) {
+        if let Err(err) = cancel.check() {
+            for tile in &tiles {
+                remove_tile(&out_dir.join(&tile.file))?;
+            }
+            return Err(io::Error::new(io::ErrorKind::Interrupted, err));
+        }
+
+        let file = key.file_name(name);
+        let path = out_dir.join(&file);
+        let buffer_uri = Path::new(&file)
+            .with_extension("bin")
+            .to_string_lossy()
+            .into_owned();
+
+        build_gltf(&meshes, &buffer_uri, texture_dir)
+            .with_provenance(provenance)
+            .write(&path)?;
+
+        let (bounds_min, bounds_max) = combined_bounds(&meshes);
+        tiles.push(TileEntry {
+            file,
+            grid: match key {
+                TileKey::Grid(x, z) => Some([x, z]),
+                TileKey::Cell(_) => None,
+            },
+            cell: match key {
+                TileKey::Cell(cell) => Some(cell),
+                TileKey::Grid(..) => None,
+            },
+            bounds_min,
+            bounds_max,
+            mesh_count: meshes.len(),
+        });
+    }
+
+    let (_, skybox_meshes) = split_skybox(meshes);
+    let skybox = match skybox_meshes.is_empty() {
+        true => None,
+        false => {
+            let file = format!("{name}_skybox.gltf");
+            let buffer_uri = format!("{name}_skybox.bin");
+            build_gltf(&skybox_meshes, &buffer_uri, texture_dir)
+                .with_provenance(provenance)
+                .write(&out_dir.join(&file))?;
+            Some(file)
+        }
+    };
+
+    let manifest = TileManifest {
+        texture_dir: texture_dir.to_string(),
+        tile_size: match mode {
+            TileMode::Grid { size } => Some(size),
+            TileMode::Cells => None,
+        },
+        tiles,
+        skybox,
+        provenance: provenance.cloned(),
+    };
+
+    let json = serde_json::to_vec_pretty(&manifest)?;
+    std::fs::write(out_dir.join(format!("{name}.tiles.json")), json)?;
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::export::ExportMesh;
+
+    use super::{group_tiles, TileKey, TileMode};
+
+    fn triangle(name: &str, translation: [f32; 3]) -> ExportMesh {
+        ExportMesh {
+            name: name.to_string(),
+            translation,
+            positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_group_grid_tiles() {
+        let meshes = [
+            triangle("a", [0.0, 0.0, 0.0]),
+            triangle("b", [10.0, 5.0, 0.0]),
+            triangle("c", [-30.0, 0.0, 70.0]),
+        ];
+
+        let tiles = group_tiles(&meshes, TileMode::Grid { size: 64.0 });
+        assert_eq!(tiles.len(), 2);
+        assert_eq!(tiles[&TileKey::Grid(0, 0)].len(), 2);
+        assert_eq!(tiles[&TileKey::Grid(-1, 1)][0].name, "c");
+    }
+
+    #[test]
+    fn test_skybox_not_tiled() {
+        let meshes = [
+            triangle("level", [0.0, 0.0, 0.0]),
+            ExportMesh {
+                skybox: true,
+                ..triangle("sky", [0.0, 0.0, 0.0])
+            },
+        ];
+
+        let tiles = group_tiles(&meshes, TileMode::Cells);
+        assert_eq!(tiles[&TileKey::Cell(0)].len(), 1);
+        assert_eq!(tiles[&TileKey::Cell(0)][0].name, "level");
+    }
+}
@@ -0,0 +1,126 @@
+//! Music and ambient loops played in each level. The level data referencing
+//! the tracks hasn't been decoded yet, so the tracks are read from a plain
+//! text listing with one track per line as `level kind path [volume]` where
+//! kind is either `music` or `ambient`
+
+use std::str::FromStr;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AmbienceError {
+    #[error("line {0}: expected level kind path [volume]")]
+    MalformedLine(usize),
+    #[error("line {0}: unknown track kind {1:?}")]
+    UnknownKind(usize, String),
+    #[error("line {0}: invalid volume {1:?}")]
+    InvalidVolume(usize, String),
+}
+
+/// Kind of track, each kind has its own volume control
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AmbienceKind {
+    Music,
+    Ambient,
+}
+
+impl FromStr for AmbienceKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "music" => Ok(AmbienceKind::Music),
+            "ambient" => Ok(AmbienceKind::Ambient),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Track looped while a level is loaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmbienceTrack {
+    /// Name of the level, matched ignoring case
+    pub level: String,
+    pub kind: AmbienceKind,
+    /// Path of the audio file in the game data
+    pub path: String,
+    /// Volume of the track relative to the volume of its kind
+    pub volume: f32,
+}
+
+/// Parses an ambience listing, blank lines and lines starting
+/// with `#` are ignored
+pub fn parse_ambience(value: &str) -> Result<Vec<AmbienceTrack>, AmbienceError> {
+    let mut out = Vec::new();
+
+    for (index, line) in value.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let (level, kind, path, volume) = match parts.as_slice() {
+            &[level, kind, path] => (level, kind, path, 1.0),
+            &[level, kind, path, volume] => (
+                level,
+                kind,
+                path,
+                volume
+                    .parse()
+                    .map_err(|_| AmbienceError::InvalidVolume(line_number, volume.to_string()))?,
+            ),
+            _ => return Err(AmbienceError::MalformedLine(line_number)),
+        };
+
+        let kind = kind
+            .parse()
+            .map_err(|_| AmbienceError::UnknownKind(line_number, kind.to_string()))?;
+
+        out.push(AmbienceTrack {
+            level: level.to_string(),
+            kind,
+            path: path.to_string(),
+            volume,
+        });
+    }
+
+    Ok(out)
+}
+
+/// Tracks played in the provided level
+pub fn tracks_for_level<'a>(
+    tracks: &'a [AmbienceTrack],
+    level: &'a str,
+) -> impl Iterator<Item = &'a AmbienceTrack> {
+    tracks
+        .iter()
+        .filter(move |track| track.level.eq_ignore_ascii_case(level))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_ambience, tracks_for_level, AmbienceError, AmbienceKind};
+
+    #[test]
+    fn test_parse_ambience() {
+        let tracks = parse_ambience(
+            "# tracks\nWDGlitch01 music Music/glitch01.wav\nwdglitch01 ambient Sounds/wind.wav 0.4\n",
+        )
+        .unwrap();
+
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[1].kind, AmbienceKind::Ambient);
+        assert_eq!(tracks[1].volume, 0.4);
+        assert_eq!(tracks_for_level(&tracks, "WDGLITCH01").count(), 2);
+        assert_eq!(tracks_for_level(&tracks, "missing").count(), 0);
+
+        assert!(matches!(
+            parse_ambience("level radio track.wav"),
+            Err(AmbienceError::UnknownKind(1, _))
+        ));
+        assert!(parse_ambience("level music").is_err());
+        assert!(parse_ambience("level music track.wav loud").is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Container of Bink (.bik) movies. The header describes the video and
+//! its audio tracks and is followed by the offset of each frame, every
+//! frame holds a packet for each audio track followed by the video data
+//! which is decoded by [video]
+
+use std::{
+    io::{Read, Seek, SeekFrom},
+    time::Duration,
+};
+
+use binrw::BinRead;
+use thiserror::Error;
+
+use self::video::BinkVideoError;
+
+pub mod video;
+
+#[derive(Debug, Error)]
+pub enum BinkError {
+    #[error(transparent)]
+    Parse(#[from] binrw::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("movie has no frames")]
+    NoFrames,
+    #[error("movie has an invalid frame rate {0}/{1}")]
+    InvalidFrameRate(u32, u32),
+    #[error("frame {index} at {offset} runs past the end of the file")]
+    FrameOutOfBounds { index: usize, offset: u32 },
+    #[error(transparent)]
+    Video(#[from] BinkVideoError),
+}
+
+#[derive(Debug, BinRead)]
+#[br(little, magic = b"BIK")]
+pub struct BinkHeader {
+    /// Revision of the video codec, a letter from 'b' onwards
+    pub revision: u8,
+    /// Size of the file after the first 8 bytes
+    pub file_size: u32,
+    pub frame_count: u32,
+    /// Size of the largest frame in bytes
+    pub largest_frame: u32,
+    #[br(pad_before = 4)]
+    pub width: u32,
+    pub height: u32,
+    pub fps_numerator: u32,
+    pub fps_denominator: u32,
+    pub video_flags: u32,
+    pub audio_track_count: u32,
+    /// Largest decoded size of each audio track
+    #[br(count = audio_track_count)]
+    pub max_audio_sizes: Vec<u32>,
+    #[br(count = audio_track_count)]
+    pub audio_tracks: Vec<BinkAudioTrack>,
+    #[br(count = audio_track_count)]
+    pub audio_track_ids: Vec<u32>,
+}
+
+#[derive(Debug, BinRead)]
+#[br(little)]
+pub struct BinkAudioTrack {
+    pub sample_rate: u16,
+    pub flags: u16,
+}
+
+impl BinkAudioTrack {
+    pub const FLAG_USE_DCT: u16 = 0x1000;
+    pub const FLAG_STEREO: u16 = 0x2000;
+    pub const FLAG_16_BITS: u16 = 0x4000;
+}
+
+/// Location of a frame within the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinkFrame {
+    pub offset: u32,
+    pub size: u32,
+    pub keyframe: bool,
+}
+
+/// Header and frame index of a movie
+#[derive(Debug)]
+pub struct BinkMovie {
+    pub header: BinkHeader,
+    pub frames: Vec<BinkFrame>,
+}
+
+impl BinkMovie {
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<BinkMovie, BinkError> {
+        let header = BinkHeader::read(reader)?;
+        if header.frame_count == 0 {
+            return Err(BinkError::NoFrames);
+        }
+        if header.fps_numerator == 0 || header.fps_denominator == 0 {
+            return Err(BinkError::InvalidFrameRate(
+                header.fps_numerator,
+                header.fps_denominator,
+            ));
+        }
+
+        let file_size = header.file_size.saturating_add(8);
+        let offsets: Vec<u32> = (0..header.frame_count)
+            .map(|_| u32::read_le(reader))
+            .collect::<Result<_, _>>()?;
+
+        // The last frame runs to the end of the file, the lowest bit of
+        // each offset marks keyframes
+        let frames = offsets
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let offset = value & !1;
+                let end = offsets
+                    .get(index + 1)
+                    .map(|next| next & !1)
+                    .unwrap_or(file_size);
+                if end < offset || end > file_size {
+                    return Err(BinkError::FrameOutOfBounds { index, offset });
+                }
+                Ok(BinkFrame {
+                    offset,
+                    size: end - offset,
+                    keyframe: value & 1 != 0,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(BinkMovie { header, frames })
+    }
+
+    /// Time each frame is shown for
+    pub fn frame_duration(&self) -> Duration {
+        Duration::from_secs_f64(
+            self.header.fps_denominator as f64 / self.header.fps_numerator as f64,
+        )
+    }
+
+    /// Presentation time of the frame
+    pub fn frame_time(&self, index: usize) -> Duration {
+        self.frame_duration() * index as u32
+    }
+
+    /// Index of the keyframe at or before the position
+    pub fn keyframe_before(&self, position: Duration) -> usize {
+        let target = (position.as_secs_f64() / self.frame_duration().as_secs_f64()) as usize;
+        let target = target.min(self.frames.len() - 1);
+        (0..=target)
+            .rev()
+            .find(|index| self.frames[*index].keyframe)
+            .unwrap_or(0)
+    }
+
+    /// Reads the video data of the frame, skipping the audio packets
+    /// stored before it
+    pub fn read_video<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        index: usize,
+    ) -> Result<Vec<u8>, BinkError> {
+        let frame = self.frames[index];
+        reader.seek(SeekFrom::Start(frame.offset as u64))?;
+
+        let mut remaining = frame.size;
+        for _ in 0..self.header.audio_track_count {
+            let size = u32::read_le(reader)?;
+            let skipped = size
+                .checked_add(4)
+                .filter(|value| *value <= remaining)
+                .ok_or(BinkError::FrameOutOfBounds {
+                    index,
+                    offset: frame.offset,
+                })?;
+            reader.seek(SeekFrom::Current(size as i64))?;
+            remaining -= skipped;
+        }
+
+        let mut data = vec![0; remaining as usize];
+        reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{io::Cursor, time::Duration};
+
+    use super::{BinkFrame, BinkMovie};
+
+    /// Movie with one audio track and three frames, the first and last of
+    /// which are keyframes
+    fn test_movie() -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(b"BIKi");
+        let header_size = 44 + 12 + 12;
+        let frames: [&[u8]; 3] = [&[1, 2, 3], &[4], &[5, 6]];
+        let frame_sizes: Vec<u32> = frames
+            .iter()
+            .map(|frame| 4 + 1 + frame.len() as u32)
+            .collect();
+        let file_size = header_size + frame_sizes.iter().sum::<u32>();
+
+        for value in [file_size - 8, 3, 8, 3, 320, 240, 30, 1, 0, 1] {
+            out.extend_from_slice(&u32::to_le_bytes(value));
+        }
+        out.extend_from_slice(&u32::to_le_bytes(4096));
+        out.extend_from_slice(&u16::to_le_bytes(22050));
+        out.extend_from_slice(&u16::to_le_bytes(0x2000));
+        out.extend_from_slice(&u32::to_le_bytes(0));
+
+        let mut offset = header_size;
+        for (index, size) in frame_sizes.iter().enumerate() {
+            let keyframe = (index != 1) as u32;
+            out.extend_from_slice(&u32::to_le_bytes(offset | keyframe));
+            offset += size;
+        }
+
+        for frame in frames {
+            out.extend_from_slice(&u32::to_le_bytes(1));
+            out.push(0xFF);
+            out.extend_from_slice(frame);
+        }
+        out
+    }
+
+    #[test]
+    fn test_read_movie() {
+        let mut reader = Cursor::new(test_movie());
+        let movie = BinkMovie::read(&mut reader).unwrap();
+
+        assert_eq!(movie.header.revision, b'i');
+        assert_eq!((movie.header.width, movie.header.height), (320, 240));
+        assert_eq!(movie.header.audio_tracks[0].sample_rate, 22050);
+        assert_eq!(
+            movie.frames[0],
+            BinkFrame {
+                offset: 68,
+                size: 8,
+                keyframe: true
+            }
+        );
+        assert!(!movie.frames[1].keyframe);
+
+        assert_eq!(movie.read_video(&mut reader, 0).unwrap(), vec![1, 2, 3]);
+        assert_eq!(movie.read_video(&mut reader, 2).unwrap(), vec![5, 6]);
+    }
+
+    #[test]
+    fn test_keyframe_seek() {
+        let movie = BinkMovie::read(&mut Cursor::new(test_movie())).unwrap();
+        assert_eq!(movie.keyframe_before(Duration::from_millis(40)), 0);
+        assert_eq!(movie.keyframe_before(Duration::from_millis(70)), 2);
+        assert_eq!(movie.keyframe_before(Duration::from_secs(10)), 2);
+    }
+}
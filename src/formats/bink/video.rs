@@ -0,0 +1,1686 @@
+//! Decoder of the Bink video codec from revision 'c' onwards. Each frame
+//! codes the Y, U and V planes, and the alpha plane of movies that have
+//! one, as rows of 8x8 blocks with the chroma planes at half the size.
+//!
+//! The values the blocks use are read into a bundle for each kind of value,
+//! bundles are refilled from the bitstream at the start of each block row
+//! once their values have been used. Blocks repeat or move the block of the
+//! previous frame, fill themselves from runs, patterns or raw colours, or
+//! code DCT coefficients or residues. Scaled blocks are coded at 8x8 and
+//! cover the 16x16 pixels of four blocks
+
+use std::{
+    array,
+    f64::consts::{PI, SQRT_2},
+    ops::Range,
+};
+
+use thiserror::Error;
+
+use super::BinkHeader;
+
+/// Video flag of movies with an alpha plane
+const FLAG_ALPHA: u32 = 0x0010_0000;
+
+/// Bits of the first value of a DC bundle
+const DC_START_BITS: u32 = 11;
+
+/// Plane indices of the decoded picture
+const PLANE_Y: usize = 0;
+const PLANE_U: usize = 1;
+const PLANE_V: usize = 2;
+const PLANE_ALPHA: usize = 3;
+
+#[derive(Debug, Error)]
+pub enum BinkVideoError {
+    #[error("Bink video revision '{}' isn't supported", char::from(*.0))]
+    UnsupportedRevision(u8),
+    #[error("invalid block type {0}")]
+    InvalidBlockType(i32),
+    #[error("{0} bundle runs past the end of its values")]
+    BundleOverflow(&'static str),
+    #[error("block at ({x}, {y}) copies from outside the previous frame")]
+    MotionOutOfBounds { x: isize, y: isize },
+    #[error("run block covers more than 64 pixels")]
+    RunOverflow,
+    #[error("DC value runs out of range")]
+    DcOutOfRange,
+    #[error("coefficients of a block run past the end of the list")]
+    CoefficientOverflow,
+}
+
+/// Kind of value held by a bundle, in the order the bundles are read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    BlockTypes,
+    SubBlockTypes,
+    Colors,
+    Pattern,
+    XOffset,
+    YOffset,
+    IntraDc,
+    InterDc,
+    Run,
+}
+
+const SOURCES: [Source; 9] = [
+    Source::BlockTypes,
+    Source::SubBlockTypes,
+    Source::Colors,
+    Source::Pattern,
+    Source::XOffset,
+    Source::YOffset,
+    Source::IntraDc,
+    Source::InterDc,
+    Source::Run,
+];
+
+impl Source {
+    fn name(&self) -> &'static str {
+        match self {
+            Source::BlockTypes => "block type",
+            Source::SubBlockTypes => "sub block type",
+            Source::Colors => "color",
+            Source::Pattern => "pattern",
+            Source::XOffset => "x offset",
+            Source::YOffset => "y offset",
+            Source::IntraDc => "intra DC",
+            Source::InterDc => "inter DC",
+            Source::Run => "run",
+        }
+    }
+}
+
+/// How a block is coded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockType {
+    /// Copy of the block of the previous frame
+    Skip,
+    /// 8x8 block of one of the other types scaled up to 16x16
+    Scaled,
+    /// Copy of a block of the previous frame at an offset
+    Motion,
+    /// Runs of colours along one of the [PATTERNS]
+    Run,
+    /// Motion block with a residue added
+    Residue,
+    /// DCT coded block
+    Intra,
+    /// Single colour
+    Fill,
+    /// Motion block with a DCT coded difference added
+    Inter,
+    /// Two colours chosen by a bit mask for each row
+    Pattern,
+    /// Colour of each pixel
+    Raw,
+}
+
+impl TryFrom<i32> for BlockType {
+    type Error = BinkVideoError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => BlockType::Skip,
+            1 => BlockType::Scaled,
+            2 => BlockType::Motion,
+            3 => BlockType::Run,
+            4 => BlockType::Residue,
+            5 => BlockType::Intra,
+            6 => BlockType::Fill,
+            7 => BlockType::Inter,
+            8 => BlockType::Pattern,
+            9 => BlockType::Raw,
+            _ => return Err(BinkVideoError::InvalidBlockType(value)),
+        })
+    }
+}
+
+/// Reader of the bitstream, bits are read from the lowest bit of each
+/// byte. Bits past the end of the data read as zero
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn bits(&mut self, count: u32) -> u32 {
+        let mut window = 0u64;
+        for (index, value) in self
+            .data
+            .iter()
+            .skip(self.position >> 3)
+            .take(8)
+            .enumerate()
+        {
+            window |= (*value as u64) << (index * 8);
+        }
+
+        let value = (window >> (self.position & 7)) & ((1u64 << count) - 1);
+        self.position += count as usize;
+        value as u32
+    }
+
+    fn bit(&mut self) -> u32 {
+        self.bits(1)
+    }
+
+    /// Negates a non zero value when the following sign bit is set
+    fn signed(&mut self, value: i32) -> i32 {
+        match value != 0 && self.bit() == 1 {
+            true => -value,
+            false => value,
+        }
+    }
+
+    fn skip(&mut self, count: usize) {
+        self.position += count;
+    }
+
+    /// Skips to the next 32 bit boundary
+    fn align(&mut self) {
+        self.position = self.position.next_multiple_of(32);
+    }
+
+    fn is_finished(&self) -> bool {
+        self.position >= self.data.len() * 8
+    }
+}
+
+/// Code lengths of each symbol of the 16 Huffman trees, the codes are
+/// assigned canonically in symbol order with the first bit read as the
+/// most significant bit
+const TREE_LENGTHS: [[u8; 16]; 16] = [
+    [4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4],
+    [1, 4, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5],
+    [2, 2, 4, 4, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5],
+    [2, 3, 3, 4, 4, 4, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5],
+    [3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 5, 5, 5, 5],
+    [3, 3, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 5, 5, 5, 5],
+    [2, 4, 4, 4, 4, 4, 4, 4, 4, 4, 5, 5, 5, 5, 5, 5],
+    [1, 3, 3, 5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6],
+    [1, 2, 5, 5, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6],
+    [1, 3, 4, 4, 5, 5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 6],
+    [2, 2, 3, 4, 4, 5, 5, 5, 5, 5, 6, 6, 6, 6, 6, 6],
+    [1, 4, 4, 4, 4, 5, 5, 5, 5, 5, 6, 6, 6, 6, 6, 6],
+    [2, 2, 2, 5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6],
+    [1, 3, 3, 3, 6, 6, 6, 6, 7, 7, 7, 7, 7, 7, 7, 7],
+    [1, 3, 3, 3, 5, 6, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7],
+    [2, 2, 3, 3, 3, 6, 6, 6, 6, 6, 7, 7, 7, 7, 7, 7],
+];
+
+/// Number of codes of each length in each tree
+const TREE_COUNTS: [[u8; 8]; 16] = tree_counts();
+
+const fn tree_counts() -> [[u8; 8]; 16] {
+    let mut counts = [[0; 8]; 16];
+    let mut tree = 0;
+    while tree < 16 {
+        let mut symbol = 0;
+        while symbol < 16 {
+            counts[tree][TREE_LENGTHS[tree][symbol] as usize] += 1;
+            symbol += 1;
+        }
+        tree += 1;
+    }
+    counts
+}
+
+/// Order of the coefficients of DCT blocks and residues
+const SCAN: [u8; 64] = [
+    0, 1, 8, 9, 2, 3, 10, 11, 4, 5, 12, 13, 6, 7, 14, 15, 20, 21, 28, 29, 22, 23, 30, 31, 16, 17,
+    24, 25, 32, 33, 40, 41, 34, 35, 42, 43, 48, 49, 56, 57, 50, 51, 58, 59, 18, 19, 26, 27, 36, 37,
+    44, 45, 38, 39, 46, 47, 52, 53, 60, 61, 54, 55, 62, 63,
+];
+
+/// Quantizer weights of each coefficient of intra blocks
+const INTRA_WEIGHTS: [u8; 64] = [
+    16, 16, 16, 19, 16, 19, 22, 22, 22, 22, 26, 24, 26, 22, 22, 27, 27, 27, 26, 26, 26, 29, 29, 29,
+    27, 27, 27, 26, 34, 34, 34, 29, 29, 29, 27, 27, 37, 34, 34, 32, 32, 29, 29, 38, 37, 35, 35, 34,
+    35, 40, 40, 40, 38, 38, 48, 48, 46, 46, 58, 56, 56, 69, 69, 83,
+];
+
+/// Quantizer weights of each coefficient of inter blocks
+const INTER_WEIGHTS: [u8; 64] = [
+    16, 17, 17, 18, 18, 18, 19, 19, 19, 19, 20, 20, 20, 20, 20, 21, 21, 21, 21, 21, 21, 22, 22, 22,
+    22, 22, 22, 22, 23, 23, 23, 23, 23, 23, 23, 23, 24, 24, 24, 25, 24, 24, 24, 25, 26, 26, 26, 26,
+    25, 27, 27, 27, 27, 27, 28, 28, 28, 28, 30, 30, 30, 31, 31, 33,
+];
+
+/// Scale of the weights for each of the 16 quantizers
+const QUANT_SCALES: [(u32, u32); 16] = [
+    (1, 1),
+    (4, 3),
+    (5, 3),
+    (2, 1),
+    (7, 3),
+    (8, 3),
+    (3, 1),
+    (7, 2),
+    (4, 1),
+    (9, 2),
+    (5, 1),
+    (6, 1),
+    (7, 1),
+    (8, 1),
+    (9, 1),
+    (10, 1),
+];
+
+/// Order run blocks are filled in, chosen by each block
+const PATTERNS: [[u8; 64]; 16] = [
+    [
+        0x00, 0x08, 0x10, 0x18, 0x20, 0x28, 0x30, 0x38, 0x39, 0x31, 0x29, 0x21, 0x19, 0x11, 0x09,
+        0x01, 0x02, 0x0A, 0x12, 0x1A, 0x22, 0x2A, 0x32, 0x3A, 0x3B, 0x33, 0x2B, 0x23, 0x1B, 0x13,
+        0x0B, 0x03, 0x04, 0x0C, 0x14, 0x1C, 0x24, 0x2C, 0x34, 0x3C, 0x3D, 0x35, 0x2D, 0x25, 0x1D,
+        0x15, 0x0D, 0x05, 0x06, 0x0E, 0x16, 0x1E, 0x26, 0x2E, 0x36, 0x3E, 0x3F, 0x37, 0x2F, 0x27,
+        0x1F, 0x17, 0x0F, 0x07,
+    ],
+    [
+        0x3B, 0x3A, 0x39, 0x38, 0x30, 0x31, 0x32, 0x33, 0x2B, 0x2A, 0x29, 0x28, 0x20, 0x21, 0x22,
+        0x23, 0x18, 0x19, 0x1A, 0x1B, 0x13, 0x12, 0x11, 0x10, 0x08, 0x09, 0x0A, 0x0B, 0x03, 0x02,
+        0x01, 0x00, 0x04, 0x05, 0x06, 0x07, 0x0F, 0x0E, 0x0D, 0x0C, 0x14, 0x15, 0x16, 0x17, 0x1F,
+        0x1E, 0x1D, 0x1C, 0x27, 0x26, 0x25, 0x24, 0x2C, 0x2D, 0x2E, 0x2F, 0x37, 0x36, 0x35, 0x34,
+        0x3C, 0x3D, 0x3E, 0x3F,
+    ],
+    [
+        0x19, 0x11, 0x12, 0x1A, 0x1B, 0x13, 0x0B, 0x03, 0x02, 0x0A, 0x09, 0x01, 0x00, 0x08, 0x10,
+        0x18, 0x20, 0x28, 0x30, 0x38, 0x39, 0x31, 0x29, 0x2A, 0x32, 0x3A, 0x3B, 0x33, 0x2B, 0x23,
+        0x22, 0x21, 0x1D, 0x15, 0x16, 0x1E, 0x1F, 0x17, 0x0F, 0x07, 0x06, 0x0E, 0x0D, 0x05, 0x04,
+        0x0C, 0x14, 0x1C, 0x24, 0x2C, 0x34, 0x3C, 0x3D, 0x35, 0x2D, 0x2E, 0x36, 0x3E, 0x3F, 0x37,
+        0x2F, 0x27, 0x26, 0x25,
+    ],
+    [
+        0x03, 0x0B, 0x02, 0x0A, 0x01, 0x09, 0x00, 0x08, 0x10, 0x18, 0x11, 0x19, 0x12, 0x1A, 0x13,
+        0x1B, 0x23, 0x2B, 0x22, 0x2A, 0x21, 0x29, 0x20, 0x28, 0x30, 0x38, 0x31, 0x39, 0x32, 0x3A,
+        0x33, 0x3B, 0x3C, 0x34, 0x3D, 0x35, 0x3E, 0x36, 0x3F, 0x37, 0x2F, 0x27, 0x2E, 0x26, 0x2D,
+        0x25, 0x2C, 0x24, 0x1C, 0x14, 0x1D, 0x15, 0x1E, 0x16, 0x1F, 0x17, 0x0F, 0x07, 0x0E, 0x06,
+        0x0D, 0x05, 0x0C, 0x04,
+    ],
+    [
+        0x18, 0x19, 0x10, 0x11, 0x08, 0x09, 0x00, 0x01, 0x02, 0x03, 0x0A, 0x0B, 0x12, 0x13, 0x1A,
+        0x1B, 0x1C, 0x1D, 0x14, 0x15, 0x0C, 0x0D, 0x04, 0x05, 0x06, 0x07, 0x0E, 0x0F, 0x16, 0x17,
+        0x1E, 0x1F, 0x26, 0x27, 0x2E, 0x2F, 0x36, 0x37, 0x3E, 0x3F, 0x3C, 0x3D, 0x34, 0x35, 0x2C,
+        0x2D, 0x24, 0x25, 0x22, 0x23, 0x2A, 0x2B, 0x32, 0x33, 0x3A, 0x3B, 0x38, 0x39, 0x30, 0x31,
+        0x28, 0x29, 0x20, 0x21,
+    ],
+    [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x0F, 0x0E, 0x0D, 0x0C, 0x0B, 0x0A, 0x09,
+        0x08, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x1F, 0x1E, 0x1D, 0x1C, 0x1B, 0x1A,
+        0x19, 0x18, 0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x2F, 0x2E, 0x2D, 0x2C, 0x2B,
+        0x2A, 0x29, 0x28, 0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x3F, 0x3E, 0x3D, 0x3C,
+        0x3B, 0x3A, 0x39, 0x38,
+    ],
+    [
+        0x00, 0x08, 0x09, 0x01, 0x02, 0x03, 0x0B, 0x0A, 0x12, 0x13, 0x1B, 0x1A, 0x19, 0x11, 0x10,
+        0x18, 0x20, 0x28, 0x29, 0x21, 0x22, 0x23, 0x2B, 0x2A, 0x32, 0x31, 0x30, 0x38, 0x39, 0x3A,
+        0x3B, 0x33, 0x34, 0x3C, 0x3D, 0x3E, 0x3F, 0x37, 0x36, 0x35, 0x2D, 0x2C, 0x24, 0x25, 0x26,
+        0x2E, 0x2F, 0x27, 0x1F, 0x17, 0x16, 0x1E, 0x1D, 0x1C, 0x14, 0x15, 0x0D, 0x0C, 0x04, 0x05,
+        0x06, 0x0E, 0x0F, 0x07,
+    ],
+    [
+        0x18, 0x10, 0x08, 0x00, 0x01, 0x02, 0x03, 0x0B, 0x13, 0x1B, 0x1A, 0x19, 0x11, 0x0A, 0x09,
+        0x12, 0x1C, 0x14, 0x0C, 0x04, 0x05, 0x06, 0x07, 0x0F, 0x17, 0x1F, 0x1E, 0x1D, 0x15, 0x0E,
+        0x0D, 0x16, 0x3C, 0x34, 0x2C, 0x24, 0x25, 0x26, 0x27, 0x2F, 0x37, 0x3F, 0x3E, 0x3D, 0x35,
+        0x2E, 0x2D, 0x36, 0x38, 0x30, 0x28, 0x20, 0x21, 0x22, 0x23, 0x2B, 0x33, 0x3B, 0x3A, 0x39,
+        0x31, 0x2A, 0x29, 0x32,
+    ],
+    [
+        0x00, 0x08, 0x09, 0x01, 0x02, 0x0A, 0x12, 0x11, 0x10, 0x18, 0x19, 0x1A, 0x1B, 0x13, 0x0B,
+        0x03, 0x04, 0x0C, 0x14, 0x15, 0x0D, 0x05, 0x06, 0x0E, 0x16, 0x1E, 0x1D, 0x1C, 0x1F, 0x17,
+        0x0F, 0x07, 0x3F, 0x37, 0x2F, 0x27, 0x26, 0x2E, 0x36, 0x3E, 0x3D, 0x35, 0x2D, 0x25, 0x24,
+        0x2C, 0x34, 0x3C, 0x3B, 0x33, 0x2B, 0x23, 0x22, 0x2A, 0x32, 0x3A, 0x39, 0x31, 0x29, 0x21,
+        0x20, 0x28, 0x30, 0x38,
+    ],
+    [
+        0x00, 0x08, 0x10, 0x18, 0x19, 0x1A, 0x1B, 0x13, 0x0B, 0x03, 0x02, 0x01, 0x09, 0x11, 0x12,
+        0x0A, 0x04, 0x0C, 0x14, 0x1C, 0x1D, 0x1E, 0x1F, 0x17, 0x0F, 0x07, 0x06, 0x05, 0x0D, 0x15,
+        0x16, 0x0E, 0x24, 0x2C, 0x34, 0x3C, 0x3D, 0x3E, 0x3F, 0x37, 0x2F, 0x27, 0x26, 0x25, 0x2D,
+        0x35, 0x36, 0x2E, 0x20, 0x28, 0x30, 0x38, 0x39, 0x3A, 0x3B, 0x33, 0x2B, 0x23, 0x22, 0x21,
+        0x29, 0x31, 0x32, 0x2A,
+    ],
+    [
+        0x00, 0x08, 0x10, 0x18, 0x19, 0x11, 0x09, 0x01, 0x02, 0x0A, 0x12, 0x1A, 0x1B, 0x13, 0x0B,
+        0x03, 0x04, 0x0C, 0x14, 0x1C, 0x1D, 0x15, 0x0D, 0x05, 0x06, 0x0E, 0x16, 0x1E, 0x1F, 0x17,
+        0x0F, 0x07, 0x3F, 0x37, 0x2F, 0x27, 0x26, 0x2E, 0x36, 0x3E, 0x3D, 0x35, 0x2D, 0x25, 0x24,
+        0x2C, 0x34, 0x3C, 0x3B, 0x33, 0x2B, 0x23, 0x22, 0x2A, 0x32, 0x3A, 0x39, 0x31, 0x29, 0x21,
+        0x20, 0x28, 0x30, 0x38,
+    ],
+    [
+        0x00, 0x08, 0x01, 0x09, 0x02, 0x0A, 0x03, 0x0B, 0x04, 0x0C, 0x05, 0x0D, 0x06, 0x0E, 0x07,
+        0x0F, 0x17, 0x1F, 0x16, 0x1E, 0x15, 0x1D, 0x14, 0x1C, 0x13, 0x1B, 0x12, 0x1A, 0x11, 0x19,
+        0x10, 0x18, 0x20, 0x28, 0x21, 0x29, 0x22, 0x2A, 0x23, 0x2B, 0x24, 0x2C, 0x25, 0x2D, 0x26,
+        0x2E, 0x27, 0x2F, 0x37, 0x3F, 0x36, 0x3E, 0x35, 0x3D, 0x34, 0x3C, 0x33, 0x3B, 0x32, 0x3A,
+        0x31, 0x39, 0x30, 0x38,
+    ],
+    [
+        0x00, 0x01, 0x09, 0x08, 0x10, 0x11, 0x19, 0x18, 0x20, 0x21, 0x29, 0x28, 0x30, 0x31, 0x39,
+        0x38, 0x3A, 0x3B, 0x33, 0x32, 0x2A, 0x2B, 0x23, 0x22, 0x1A, 0x1B, 0x13, 0x12, 0x0A, 0x0B,
+        0x03, 0x02, 0x04, 0x05, 0x0D, 0x0C, 0x14, 0x15, 0x1D, 0x1C, 0x24, 0x25, 0x2D, 0x2C, 0x34,
+        0x35, 0x3D, 0x3C, 0x3E, 0x3F, 0x37, 0x36, 0x2E, 0x2F, 0x27, 0x26, 0x1E, 0x1F, 0x17, 0x16,
+        0x0E, 0x0F, 0x07, 0x06,
+    ],
+    [
+        0x00, 0x08, 0x09, 0x01, 0x02, 0x0A, 0x0B, 0x03, 0x04, 0x0C, 0x0D, 0x05, 0x06, 0x0E, 0x0F,
+        0x07, 0x17, 0x1F, 0x1E, 0x16, 0x15, 0x1D, 0x1C, 0x14, 0x13, 0x1B, 0x1A, 0x12, 0x11, 0x19,
+        0x18, 0x10, 0x20, 0x28, 0x29, 0x21, 0x22, 0x2A, 0x2B, 0x23, 0x24, 0x2C, 0x2D, 0x25, 0x26,
+        0x2E, 0x2F, 0x27, 0x37, 0x3F, 0x3E, 0x36, 0x35, 0x3D, 0x3C, 0x34, 0x33, 0x3B, 0x3A, 0x32,
+        0x31, 0x39, 0x38, 0x30,
+    ],
+    [
+        0x00, 0x01, 0x02, 0x03, 0x0B, 0x0A, 0x09, 0x08, 0x10, 0x11, 0x12, 0x13, 0x1B, 0x1A, 0x19,
+        0x18, 0x20, 0x21, 0x22, 0x23, 0x2B, 0x2A, 0x29, 0x28, 0x30, 0x31, 0x32, 0x33, 0x3B, 0x3A,
+        0x39, 0x38, 0x3C, 0x3D, 0x3E, 0x3F, 0x37, 0x36, 0x35, 0x34, 0x2C, 0x2D, 0x2E, 0x2F, 0x27,
+        0x26, 0x25, 0x24, 0x1C, 0x1D, 0x1E, 0x1F, 0x17, 0x16, 0x15, 0x14, 0x0C, 0x0D, 0x0E, 0x0F,
+        0x07, 0x06, 0x05, 0x04,
+    ],
+    [
+        0x00, 0x01, 0x08, 0x09, 0x02, 0x03, 0x0A, 0x0B, 0x04, 0x05, 0x0C, 0x0D, 0x06, 0x07, 0x0E,
+        0x0F, 0x10, 0x11, 0x18, 0x19, 0x12, 0x13, 0x1A, 0x1B, 0x14, 0x15, 0x1C, 0x1D, 0x16, 0x17,
+        0x1E, 0x1F, 0x20, 0x21, 0x28, 0x29, 0x22, 0x23, 0x2A, 0x2B, 0x24, 0x25, 0x2C, 0x2D, 0x26,
+        0x27, 0x2E, 0x2F, 0x30, 0x31, 0x38, 0x39, 0x32, 0x33, 0x3A, 0x3B, 0x34, 0x35, 0x3C, 0x3D,
+        0x36, 0x37, 0x3E, 0x3F,
+    ],
+];
+
+/// Run lengths of the block types past 11, repeating the last block type
+const BLOCK_TYPE_RUNS: [usize; 4] = [4, 8, 12, 32];
+
+/// Quantizers of each coefficient in [SCAN] order for each of the 16
+/// quantizers, the coefficients are scaled for [idct] and have 11
+/// fractional bits
+fn quant_matrices(weights: &[u8; 64]) -> [[i64; 64]; 16] {
+    let scale = |index: usize| match index {
+        0 => 1.0,
+        _ => (index as f64 * PI / 16.0).cos() * SQRT_2,
+    };
+
+    array::from_fn(|quant| {
+        let (numerator, denominator) = QUANT_SCALES[quant];
+        array::from_fn(|index| {
+            let position = SCAN[index] as usize;
+            let value = weights[position] as f64
+                * scale(position >> 3)
+                * scale(position & 7)
+                * numerator as f64
+                / denominator as f64;
+            (value * 4096.0).round() as i64
+        })
+    })
+}
+
+/// Huffman tree along with the values of its symbols
+#[derive(Debug, Clone, Copy)]
+struct Tree {
+    index: usize,
+    symbols: [u8; 16],
+}
+
+impl Default for Tree {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            symbols: array::from_fn(|index| index as u8),
+        }
+    }
+}
+
+impl Tree {
+    /// Reads the tree used and the order of its symbols, the symbols are
+    /// either listed or ordered by merging runs of increasing length
+    fn read(reader: &mut BitReader) -> Self {
+        let mut tree = Tree {
+            index: reader.bits(4) as usize,
+            ..Default::default()
+        };
+        if tree.index == 0 {
+            return tree;
+        }
+
+        if reader.bit() == 1 {
+            let mut used = [false; 16];
+            let mut last = reader.bits(3) as usize;
+            for symbol in tree.symbols.iter_mut().take(last + 1) {
+                *symbol = reader.bits(4) as u8;
+                used[*symbol as usize] = true;
+            }
+
+            // Symbols that weren't listed follow in order
+            for (value, used) in used.iter().enumerate() {
+                if last >= 15 {
+                    break;
+                }
+                if !used {
+                    last += 1;
+                    tree.symbols[last] = value as u8;
+                }
+            }
+        } else {
+            let depth = reader.bits(2) as usize;
+            for level in 0..=depth {
+                let size = 1 << level;
+                let mut merged = [0; 16];
+                for start in (0..16).step_by(size * 2) {
+                    let (first, second) = tree.symbols[start..start + size * 2].split_at(size);
+                    merge(reader, &mut merged[start..start + size * 2], first, second);
+                }
+                tree.symbols = merged;
+            }
+        }
+
+        tree
+    }
+
+    /// Reads a symbol through the tree
+    fn value(&self, reader: &mut BitReader) -> u8 {
+        let (mut code, mut first, mut index) = (0u32, 0u32, 0usize);
+        for count in TREE_COUNTS[self.index].iter().skip(1) {
+            code |= reader.bit();
+            let count = *count as u32;
+            if code < first + count {
+                return self.symbols[index + (code - first) as usize];
+            }
+
+            index += count as usize;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        self.symbols[15]
+    }
+}
+
+/// Merges two runs of symbols, each bit picks the run the next symbol is
+/// taken from until one of them runs out
+fn merge(reader: &mut BitReader, out: &mut [u8], first: &[u8], second: &[u8]) {
+    let (mut first, mut second) = (first.iter().peekable(), second.iter().peekable());
+    for value in out.iter_mut() {
+        let next = match (first.peek(), second.peek()) {
+            (Some(_), Some(_)) => match reader.bit() {
+                0 => first.next(),
+                _ => second.next(),
+            },
+            (Some(_), None) => first.next(),
+            _ => second.next(),
+        };
+        *value = next.copied().unwrap_or_default();
+    }
+}
+
+/// Values of one kind read ahead of the blocks using them
+#[derive(Debug, Default)]
+struct Bundle {
+    /// Bits of the number of values read at once
+    count_bits: u32,
+    tree: Tree,
+    values: Vec<i32>,
+    /// Index the next values read are stored at, None once every value of
+    /// the plane has been read
+    decoded: Option<usize>,
+    /// Index of the next value used by a block
+    next: usize,
+}
+
+impl Bundle {
+    /// Reads the number of values read next, None while the values read
+    /// before haven't been used or once the bundle has ended
+    fn start(
+        &mut self,
+        reader: &mut BitReader,
+        source: Source,
+    ) -> Result<Option<Range<usize>>, BinkVideoError> {
+        let Some(start) = self.decoded.filter(|decoded| *decoded <= self.next) else {
+            return Ok(None);
+        };
+
+        let count = reader.bits(self.count_bits) as usize;
+        if count == 0 {
+            self.decoded = None;
+            return Ok(None);
+        }
+
+        let end = start + count;
+        if end > self.values.len() {
+            return Err(BinkVideoError::BundleOverflow(source.name()));
+        }
+        self.decoded = Some(end);
+        Ok(Some(start..end))
+    }
+}
+
+/// Bundles of the plane being decoded
+struct Bundles {
+    revision: u8,
+    bundles: [Bundle; 9],
+    /// Trees of the high nibble of colours, chosen by the previous high
+    /// nibble
+    color_high: [Tree; 16],
+    last_color_high: u8,
+}
+
+impl Bundles {
+    fn new(revision: u8, blocks: usize) -> Self {
+        Self {
+            revision,
+            bundles: array::from_fn(|_| Bundle {
+                values: vec![0; blocks * 64],
+                ..Default::default()
+            }),
+            color_high: [Tree::default(); 16],
+            last_color_high: 0,
+        }
+    }
+
+    fn bundle(&mut self, source: Source) -> &mut Bundle {
+        &mut self.bundles[source as usize]
+    }
+
+    /// Reads the trees of every bundle and resets them for a plane `width`
+    /// pixels across covered by `blocks_across` blocks
+    fn start_plane(&mut self, reader: &mut BitReader, width: u32, blocks_across: u32) {
+        let width = width.max(8).next_multiple_of(8);
+        let bits = |value: u32| (value + 511).ilog2() + 1;
+
+        for source in SOURCES {
+            let count_bits = match source {
+                Source::BlockTypes
+                | Source::XOffset
+                | Source::YOffset
+                | Source::IntraDc
+                | Source::InterDc => bits(width >> 3),
+                Source::SubBlockTypes => bits(width >> 4),
+                Source::Colors => bits(blocks_across * 64),
+                Source::Pattern => bits(blocks_across << 3),
+                Source::Run => bits(blocks_across * 48),
+            };
+
+            if source == Source::Colors {
+                for tree in self.color_high.iter_mut() {
+                    *tree = Tree::read(reader);
+                }
+                self.last_color_high = 0;
+            }
+
+            let bundle = self.bundle(source);
+            bundle.count_bits = count_bits;
+            if !matches!(source, Source::IntraDc | Source::InterDc) {
+                bundle.tree = Tree::read(reader);
+            }
+            bundle.decoded = Some(0);
+            bundle.next = 0;
+        }
+    }
+
+    /// Reads the values each bundle needs for the next row of blocks
+    fn read_row(&mut self, reader: &mut BitReader) -> Result<(), BinkVideoError> {
+        self.read_block_types(reader, Source::BlockTypes)?;
+        self.read_block_types(reader, Source::SubBlockTypes)?;
+        self.read_colors(reader)?;
+        self.read_patterns(reader)?;
+        self.read_offsets(reader, Source::XOffset)?;
+        self.read_offsets(reader, Source::YOffset)?;
+        self.read_dcs(reader, Source::IntraDc, false)?;
+        self.read_dcs(reader, Source::InterDc, true)?;
+        self.read_runs(reader)
+    }
+
+    /// Takes the next value of the bundle
+    fn value(&mut self, source: Source) -> Result<i32, BinkVideoError> {
+        let bundle = self.bundle(source);
+        let value = *bundle
+            .values
+            .get(bundle.next)
+            .ok_or(BinkVideoError::BundleOverflow(source.name()))?;
+        bundle.next += 1;
+        Ok(value)
+    }
+
+    /// Block types are either all the same or read through the tree, types
+    /// past 11 repeat the last type read
+    fn read_block_types(
+        &mut self,
+        reader: &mut BitReader,
+        source: Source,
+    ) -> Result<(), BinkVideoError> {
+        let bundle = self.bundle(source);
+        let Some(range) = bundle.start(reader, source)? else {
+            return Ok(());
+        };
+
+        if reader.bit() == 1 {
+            let value = reader.bits(4) as i32;
+            bundle.values[range].fill(value);
+            return Ok(());
+        }
+
+        let mut last = 0;
+        let mut index = range.start;
+        while index < range.end {
+            let value = bundle.tree.value(reader) as usize;
+            match BLOCK_TYPE_RUNS.get(value.wrapping_sub(12)) {
+                Some(run) => {
+                    let run = bundle
+                        .values
+                        .get_mut(index..index + run)
+                        .filter(|_| index + run <= range.end)
+                        .ok_or(BinkVideoError::BundleOverflow(source.name()))?;
+                    run.fill(last);
+                    index += run.len();
+                }
+                None => {
+                    last = value as i32;
+                    bundle.values[index] = last;
+                    index += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Colours are read as a high nibble through the tree chosen by the
+    /// previous high nibble and a low nibble through the bundle tree
+    fn read_colors(&mut self, reader: &mut BitReader) -> Result<(), BinkVideoError> {
+        let source = Source::Colors;
+        let Some(range) = self.bundle(source).start(reader, source)? else {
+            return Ok(());
+        };
+
+        let color = |bundles: &mut Self, reader: &mut BitReader| {
+            bundles.last_color_high =
+                bundles.color_high[bundles.last_color_high as usize].value(reader);
+            let value = (bundles.last_color_high << 4) | bundles.bundle(source).tree.value(reader);
+
+            // Earlier revisions store colours as sign and magnitude
+            match bundles.revision < b'i' {
+                true => {
+                    let magnitude = (value & 0x7F) as i32;
+                    let value = if value & 0x80 != 0 {
+                        -magnitude
+                    } else {
+                        magnitude
+                    };
+                    (value + 0x80) as u8 as i32
+                }
+                false => value as i32,
+            }
+        };
+
+        if reader.bit() == 1 {
+            let value = color(self, reader);
+            self.bundle(source).values[range].fill(value);
+            return Ok(());
+        }
+
+        for index in range {
+            let value = color(self, reader);
+            self.bundle(source).values[index] = value;
+        }
+        Ok(())
+    }
+
+    /// Each pattern is a byte read as two nibbles, lowest first
+    fn read_patterns(&mut self, reader: &mut BitReader) -> Result<(), BinkVideoError> {
+        let source = Source::Pattern;
+        let bundle = self.bundle(source);
+        let Some(range) = bundle.start(reader, source)? else {
+            return Ok(());
+        };
+
+        for index in range {
+            let low = bundle.tree.value(reader) as i32;
+            let high = bundle.tree.value(reader) as i32;
+            bundle.values[index] = low | (high << 4);
+        }
+        Ok(())
+    }
+
+    /// Motion offsets are signed nibbles
+    fn read_offsets(
+        &mut self,
+        reader: &mut BitReader,
+        source: Source,
+    ) -> Result<(), BinkVideoError> {
+        let bundle = self.bundle(source);
+        let Some(range) = bundle.start(reader, source)? else {
+            return Ok(());
+        };
+
+        if reader.bit() == 1 {
+            let value = reader.bits(4) as i32;
+            let value = reader.signed(value);
+            bundle.values[range].fill(value);
+            return Ok(());
+        }
+
+        for index in range {
+            let value = bundle.tree.value(reader) as i32;
+            bundle.values[index] = reader.signed(value);
+        }
+        Ok(())
+    }
+
+    /// DC values start with an absolute value followed by groups of up to
+    /// 8 differences sharing a bit count
+    fn read_dcs(
+        &mut self,
+        reader: &mut BitReader,
+        source: Source,
+        signed: bool,
+    ) -> Result<(), BinkVideoError> {
+        let bundle = self.bundle(source);
+        let Some(range) = bundle.start(reader, source)? else {
+            return Ok(());
+        };
+
+        let mut value = reader.bits(DC_START_BITS - signed as u32) as i32;
+        if signed {
+            value = reader.signed(value);
+        }
+        bundle.values[range.start] = value;
+
+        let mut index = range.start + 1;
+        while index < range.end {
+            let count = (range.end - index).min(8);
+            let bits = reader.bits(4);
+            for slot in bundle.values[index..index + count].iter_mut() {
+                if bits != 0 {
+                    let difference = reader.bits(bits) as i32;
+                    value += reader.signed(difference);
+                    if !(i16::MIN as i32..=i16::MAX as i32).contains(&value) {
+                        return Err(BinkVideoError::DcOutOfRange);
+                    }
+                }
+                *slot = value;
+            }
+            index += count;
+        }
+
+        Ok(())
+    }
+
+    /// Runs are either all the same or read through the tree
+    fn read_runs(&mut self, reader: &mut BitReader) -> Result<(), BinkVideoError> {
+        let source = Source::Run;
+        let bundle = self.bundle(source);
+        let Some(range) = bundle.start(reader, source)? else {
+            return Ok(());
+        };
+
+        if reader.bit() == 1 {
+            let value = reader.bits(4) as i32;
+            bundle.values[range].fill(value);
+            return Ok(());
+        }
+
+        for index in range {
+            bundle.values[index] = bundle.tree.value(reader) as i32;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the DCT coefficients of a block into `block` by their position,
+/// returning the quantizer and the [SCAN] index of each coefficient read.
+/// Coefficients are read a bit at a time from the highest bit down, groups
+/// of coefficients are split once they have a coefficient at the bit
+fn read_dct_coefficients(
+    reader: &mut BitReader,
+    block: &mut [i32; 64],
+) -> Result<(usize, Vec<usize>), BinkVideoError> {
+    let mut indices = Vec::new();
+    let mut list = CoefficientList::new(&[(4, 0), (24, 0), (44, 0), (1, 3), (2, 3), (3, 3)]);
+
+    let mut value = |reader: &mut BitReader, bits: u32, index: usize| {
+        let value = match bits {
+            0 => 1,
+            _ => (reader.bits(bits) | (1 << bits)) as i32,
+        };
+        // Single bit values always have a sign bit
+        let value = match reader.bit() {
+            1 => -value,
+            _ => value,
+        };
+        block[SCAN[index] as usize] = value;
+        indices.push(index);
+    };
+
+    for bits in (0..reader.bits(4)).rev() {
+        let mut position = list.start;
+        while position < list.end {
+            let (coefficient, mode) = list.entries[position];
+            if (coefficient == 0 && mode == 0) || reader.bit() == 0 {
+                position += 1;
+                continue;
+            }
+
+            match mode {
+                0 | 2 => {
+                    if mode == 0 {
+                        list.entries[position] = (coefficient + 4, 1);
+                    } else {
+                        list.entries[position] = (0, 0);
+                        position += 1;
+                    }
+                    for coefficient in coefficient..coefficient + 4 {
+                        if reader.bit() == 1 {
+                            list.push_front(coefficient)?;
+                        } else {
+                            value(reader, bits, coefficient);
+                        }
+                    }
+                }
+                1 => {
+                    list.entries[position].1 = 2;
+                    for group in 1..=3 {
+                        list.push_back(coefficient + group * 4)?;
+                    }
+                }
+                _ => {
+                    value(reader, bits, coefficient);
+                    list.entries[position] = (0, 0);
+                    position += 1;
+                }
+            }
+        }
+    }
+
+    Ok((reader.bits(4) as usize, indices))
+}
+
+/// Reads the residue of a block into `block`. Each bit of the residues is
+/// read from the highest bit down, the bit of every residue already read
+/// followed by the residues that start at that bit. No more than `count`
+/// bits are set
+fn read_residue(
+    reader: &mut BitReader,
+    block: &mut [i32; 64],
+    mut count: i32,
+) -> Result<(), BinkVideoError> {
+    let mut positions = Vec::new();
+    let mut list = CoefficientList::new(&[(4, 0), (24, 0), (44, 0), (0, 2)]);
+
+    let mut mask = 1 << reader.bits(3);
+    while mask != 0 {
+        for position in positions.iter() {
+            if reader.bit() == 0 {
+                continue;
+            }
+            let value: &mut i32 = &mut block[*position];
+            *value += if *value < 0 { -mask } else { mask };
+            count -= 1;
+            if count < 0 {
+                return Ok(());
+            }
+        }
+
+        let mut start = |reader: &mut BitReader, coefficient: usize| {
+            let position = SCAN[coefficient] as usize;
+            positions.push(position);
+            block[position] = match reader.bit() {
+                1 => -mask,
+                _ => mask,
+            };
+            count -= 1;
+            count < 0
+        };
+
+        let mut position = list.start;
+        while position < list.end {
+            let (coefficient, mode) = list.entries[position];
+            if (coefficient == 0 && mode == 0) || reader.bit() == 0 {
+                position += 1;
+                continue;
+            }
+
+            match mode {
+                0 | 2 => {
+                    if mode == 0 {
+                        list.entries[position] = (coefficient + 4, 1);
+                    } else {
+                        list.entries[position] = (0, 0);
+                        position += 1;
+                    }
+                    for coefficient in coefficient..coefficient + 4 {
+                        if reader.bit() == 1 {
+                            list.push_front(coefficient)?;
+                        } else if start(reader, coefficient) {
+                            return Ok(());
+                        }
+                    }
+                }
+                1 => {
+                    list.entries[position].1 = 2;
+                    for group in 1..=3 {
+                        list.push_back(coefficient + group * 4)?;
+                    }
+                }
+                _ => {
+                    list.entries[position] = (0, 0);
+                    position += 1;
+                    if start(reader, coefficient) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        mask >>= 1;
+    }
+
+    Ok(())
+}
+
+/// Groups of coefficients still to be read along with how each is split,
+/// single coefficients are added to the front and groups to the back
+struct CoefficientList {
+    entries: [(usize, u8); 128],
+    start: usize,
+    end: usize,
+}
+
+impl CoefficientList {
+    fn new(initial: &[(usize, u8)]) -> Self {
+        let mut list = Self {
+            entries: [(0, 0); 128],
+            start: 64,
+            end: 64,
+        };
+        for entry in initial {
+            list.entries[list.end] = *entry;
+            list.end += 1;
+        }
+        list
+    }
+
+    fn push_front(&mut self, coefficient: usize) -> Result<(), BinkVideoError> {
+        self.start = self
+            .start
+            .checked_sub(1)
+            .ok_or(BinkVideoError::CoefficientOverflow)?;
+        self.entries[self.start] = (coefficient, 3);
+        Ok(())
+    }
+
+    fn push_back(&mut self, coefficient: usize) -> Result<(), BinkVideoError> {
+        let entry = self
+            .entries
+            .get_mut(self.end)
+            .ok_or(BinkVideoError::CoefficientOverflow)?;
+        *entry = (coefficient, 2);
+        self.end += 1;
+        Ok(())
+    }
+}
+
+/// Scales the coefficients read by the quantizer
+fn dequantize(block: &mut [i32; 64], quant: &[i64; 64], indices: &[usize]) {
+    block[0] = ((block[0] as i64 * quant[0]) >> 11) as i32;
+    for index in indices {
+        let position = SCAN[*index] as usize;
+        block[position] = ((block[position] as i64 * quant[*index]) >> 11) as i32;
+    }
+}
+
+/// Fixed point constants of [idct_1d] with 11 fractional bits
+const IDCT_A1: i32 = 2896;
+const IDCT_A2: i32 = 2217;
+const IDCT_A3: i32 = 3784;
+const IDCT_A4: i32 = -5352;
+
+fn idct_1d(s: [i32; 8]) -> [i32; 8] {
+    let a0 = s[0] + s[4];
+    let a1 = s[0] - s[4];
+    let a2 = s[2] + s[6];
+    let a3 = (IDCT_A1 * (s[2] - s[6])) >> 11;
+    let a4 = s[5] + s[3];
+    let a5 = s[5] - s[3];
+    let a6 = s[1] + s[7];
+    let a7 = s[1] - s[7];
+    let b0 = a4 + a6;
+    let b1 = (IDCT_A3 * (a5 + a7)) >> 11;
+    let b2 = ((IDCT_A4 * a5) >> 11) - b0 + b1;
+    let b3 = ((IDCT_A1 * (a6 - a4)) >> 11) - b2;
+    let b4 = ((IDCT_A2 * a7) >> 11) + b3 - b1;
+
+    [
+        a0 + a2 + b0,
+        a1 + a3 - a2 + b2,
+        a1 - a3 + a2 + b3,
+        a0 - a2 - b4,
+        a0 - a2 + b4,
+        a1 - a3 + a2 - b3,
+        a1 + a3 - a2 - b2,
+        a0 + a2 - b0,
+    ]
+}
+
+/// Inverse DCT of the dequantized coefficients, columns are transformed
+/// before rows and the result drops the 8 fractional bits
+fn idct(block: &[i32; 64]) -> [i32; 64] {
+    let mut columns = [0; 64];
+    for column in 0..8 {
+        let values = idct_1d(array::from_fn(|row| block[row * 8 + column]));
+        for (row, value) in values.into_iter().enumerate() {
+            columns[row * 8 + column] = value;
+        }
+    }
+
+    let mut out = [0; 64];
+    for row in 0..8 {
+        let values = idct_1d(array::from_fn(|column| columns[row * 8 + column]));
+        for (column, value) in values.into_iter().enumerate() {
+            out[row * 8 + column] = (value + 0x7F) >> 8;
+        }
+    }
+    out
+}
+
+fn clip(value: i32) -> u8 {
+    value.clamp(0, 255) as u8
+}
+
+/// Pixels of a plane, padded by a block on the right and bottom so the
+/// scaled blocks at the edges fit
+#[derive(Debug, Clone)]
+struct Plane {
+    blocks_across: usize,
+    blocks_down: usize,
+    stride: usize,
+    data: Vec<u8>,
+}
+
+impl Plane {
+    fn new(blocks_across: usize, blocks_down: usize, value: u8) -> Self {
+        let stride = (blocks_across + 1) * 8;
+        Self {
+            blocks_across,
+            blocks_down,
+            stride,
+            data: vec![value; stride * (blocks_down + 1) * 8],
+        }
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> u8 {
+        self.data[y * self.stride + x]
+    }
+
+    /// Offset of a pixel within a block
+    fn block_offset(&self, index: u8) -> usize {
+        (index & 7) as usize + (index >> 3) as usize * self.stride
+    }
+
+    fn fill(&mut self, offset: usize, size: usize, value: u8) {
+        for row in 0..size {
+            let start = offset + row * self.stride;
+            self.data[start..start + size].fill(value);
+        }
+    }
+
+    fn put(&mut self, offset: usize, block: &[u8; 64]) {
+        for (row, values) in block.chunks_exact(8).enumerate() {
+            let start = offset + row * self.stride;
+            self.data[start..start + 8].copy_from_slice(values);
+        }
+    }
+
+    /// Writes the block doubled in each dimension
+    fn put_scaled(&mut self, offset: usize, block: &[u8; 64]) {
+        for (index, value) in block.iter().enumerate() {
+            let start = offset + (index >> 3) * 2 * self.stride + (index & 7) * 2;
+            self.data[start..start + 2].fill(*value);
+            self.data[start + self.stride..start + self.stride + 2].fill(*value);
+        }
+    }
+
+    /// Adds the values to the pixels of the block
+    fn add(&mut self, offset: usize, block: &[i32; 64]) {
+        for (index, value) in block.iter().enumerate() {
+            let position = offset + self.block_offset(index as u8);
+            let pixel = &mut self.data[position];
+            *pixel = clip(*pixel as i32 + value);
+        }
+    }
+
+    fn copy_from(&mut self, offset: usize, source: &Plane, source_offset: usize) {
+        for row in 0..8 {
+            let start = offset + row * self.stride;
+            let source_start = source_offset + row * source.stride;
+            self.data[start..start + 8]
+                .copy_from_slice(&source.data[source_start..source_start + 8]);
+        }
+    }
+}
+
+/// Planes of a decoded frame
+#[derive(Debug, Clone)]
+pub struct BinkPicture {
+    pub width: u32,
+    pub height: u32,
+    pub has_alpha: bool,
+    /// Y, U, V and alpha planes
+    planes: [Plane; 4],
+}
+
+impl BinkPicture {
+    fn new(width: u32, height: u32, has_alpha: bool) -> Self {
+        let (across, down) = (width.div_ceil(8) as usize, height.div_ceil(8) as usize);
+        let chroma = Plane::new(across.div_ceil(2), down.div_ceil(2), 128);
+
+        Self {
+            width,
+            height,
+            has_alpha,
+            planes: [
+                Plane::new(across, down, 0),
+                chroma.clone(),
+                chroma,
+                Plane::new(across, down, 255),
+            ],
+        }
+    }
+
+    /// Converts the planes from BT.601 YUV to RGBA pixels, movies without
+    /// an alpha plane are opaque
+    pub fn write_rgba(&self, out: &mut [u8]) {
+        let width = self.width as usize;
+        for (index, pixel) in out.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (index % width, index / width);
+            let luma = self.planes[PLANE_Y].pixel(x, y) as i32 - 16;
+            let u = self.planes[PLANE_U].pixel(x / 2, y / 2) as i32 - 128;
+            let v = self.planes[PLANE_V].pixel(x / 2, y / 2) as i32 - 128;
+
+            let luma = luma * 298 + 128;
+            pixel[0] = clip((luma + 409 * v) >> 8);
+            pixel[1] = clip((luma - 100 * u - 208 * v) >> 8);
+            pixel[2] = clip((luma + 516 * u) >> 8);
+            pixel[3] = match self.has_alpha {
+                true => self.planes[PLANE_ALPHA].pixel(x, y),
+                false => 255,
+            };
+        }
+    }
+}
+
+/// Decoder of the video data of the frames of a movie, frames other than
+/// keyframes build on the frame decoded before them
+pub struct BinkVideoDecoder {
+    revision: u8,
+    bundles: Bundles,
+    intra_quant: [[i64; 64]; 16],
+    inter_quant: [[i64; 64]; 16],
+    current: BinkPicture,
+    previous: BinkPicture,
+}
+
+impl BinkVideoDecoder {
+    pub fn new(header: &BinkHeader) -> Result<Self, BinkVideoError> {
+        // Revision 'b' uses an earlier codec
+        if header.revision < b'c' {
+            return Err(BinkVideoError::UnsupportedRevision(header.revision));
+        }
+
+        let picture = BinkPicture::new(
+            header.width,
+            header.height,
+            header.video_flags & FLAG_ALPHA != 0,
+        );
+        let blocks = picture.planes[PLANE_Y].blocks_across * picture.planes[PLANE_Y].blocks_down;
+
+        Ok(Self {
+            revision: header.revision,
+            bundles: Bundles::new(header.revision, blocks),
+            intra_quant: quant_matrices(&INTRA_WEIGHTS),
+            inter_quant: quant_matrices(&INTER_WEIGHTS),
+            current: picture.clone(),
+            previous: picture,
+        })
+    }
+
+    /// Decodes the video data of the next frame
+    pub fn decode(&mut self, data: &[u8]) -> Result<&BinkPicture, BinkVideoError> {
+        std::mem::swap(&mut self.current, &mut self.previous);
+
+        let mut reader = BitReader::new(data);
+        if self.current.has_alpha {
+            if self.revision >= b'i' {
+                reader.skip(32);
+            }
+            self.decode_plane(&mut reader, PLANE_ALPHA)?;
+        }
+        if self.revision >= b'i' {
+            reader.skip(32);
+        }
+
+        // Later revisions store the V plane before the U plane
+        let planes = match self.revision >= b'h' {
+            true => [PLANE_Y, PLANE_V, PLANE_U],
+            false => [PLANE_Y, PLANE_U, PLANE_V],
+        };
+        for plane in planes {
+            self.decode_plane(&mut reader, plane)?;
+            if reader.is_finished() {
+                break;
+            }
+        }
+
+        Ok(&self.current)
+    }
+
+    fn decode_plane(&mut self, reader: &mut BitReader, plane: usize) -> Result<(), BinkVideoError> {
+        let chroma = matches!(plane, PLANE_U | PLANE_V) as u32;
+        let width = self.current.width >> chroma;
+
+        let bundles = &mut self.bundles;
+        let previous = &self.previous.planes[plane];
+        let current = &mut self.current.planes[plane];
+        let quant = (&self.intra_quant, &self.inter_quant);
+
+        // Revision 'k' can fill a whole plane with a single value
+        if self.revision == b'k' && reader.bit() == 1 {
+            let value = reader.bits(8) as u8;
+            current.data.fill(value);
+        } else {
+            bundles.start_plane(reader, width, current.blocks_across as u32);
+            for y in 0..current.blocks_down {
+                bundles.read_row(reader)?;
+                decode_row(reader, bundles, quant, current, previous, y)?;
+            }
+        }
+
+        // Each plane starts on a 32 bit boundary
+        reader.align();
+        Ok(())
+    }
+}
+
+/// Decodes a row of blocks of the plane
+fn decode_row(
+    reader: &mut BitReader,
+    bundles: &mut Bundles,
+    (intra_quant, inter_quant): (&[[i64; 64]; 16], &[[i64; 64]; 16]),
+    plane: &mut Plane,
+    previous: &Plane,
+    y: usize,
+) -> Result<(), BinkVideoError> {
+    let mut x = 0;
+    while x < plane.blocks_across {
+        let offset = y * 8 * plane.stride + x * 8;
+        let block = BlockType::try_from(bundles.value(Source::BlockTypes)?)?;
+
+        // Scaled blocks on odd rows are covered by the row above
+        if block == BlockType::Scaled && y & 1 == 1 {
+            x += 2;
+            continue;
+        }
+
+        match block {
+            BlockType::Skip => plane.copy_from(offset, previous, offset),
+            BlockType::Scaled => {
+                let sub_block = BlockType::try_from(bundles.value(Source::SubBlockTypes)?)?;
+                let mut pixels = [0; 64];
+                match sub_block {
+                    BlockType::Run => read_run(reader, bundles, |index, value| {
+                        pixels[index as usize] = value
+                    })?,
+                    BlockType::Intra => {
+                        let mut block = [0; 64];
+                        block[0] = bundles.value(Source::IntraDc)?;
+                        let (quant, indices) = read_dct_coefficients(reader, &mut block)?;
+                        dequantize(&mut block, &intra_quant[quant & 15], &indices);
+                        pixels = idct(&block).map(clip);
+                    }
+                    BlockType::Fill => {
+                        let value = bundles.value(Source::Colors)? as u8;
+                        plane.fill(offset, 16, value);
+                        x += 2;
+                        continue;
+                    }
+                    BlockType::Pattern => pixels = read_pattern(bundles)?,
+                    BlockType::Raw => {
+                        for pixel in pixels.iter_mut() {
+                            *pixel = bundles.value(Source::Colors)? as u8;
+                        }
+                    }
+                    _ => return Err(BinkVideoError::InvalidBlockType(sub_block as i32)),
+                }
+                plane.put_scaled(offset, &pixels);
+                x += 1;
+            }
+            BlockType::Motion => {
+                let source = motion_offset(bundles, plane, x, y)?;
+                plane.copy_from(offset, previous, source);
+            }
+            BlockType::Run => {
+                let mut pixels = [0; 64];
+                read_run(reader, bundles, |index, value| {
+                    pixels[index as usize] = value
+                })?;
+                for (index, value) in pixels.iter().enumerate() {
+                    let position = offset + plane.block_offset(index as u8);
+                    plane.data[position] = *value;
+                }
+            }
+            BlockType::Residue => {
+                let source = motion_offset(bundles, plane, x, y)?;
+                plane.copy_from(offset, previous, source);
+
+                let mut block = [0; 64];
+                let count = reader.bits(7) as i32;
+                read_residue(reader, &mut block, count)?;
+                plane.add(offset, &block);
+            }
+            BlockType::Intra => {
+                let mut block = [0; 64];
+                block[0] = bundles.value(Source::IntraDc)?;
+                let (quant, indices) = read_dct_coefficients(reader, &mut block)?;
+                dequantize(&mut block, &intra_quant[quant & 15], &indices);
+                plane.put(offset, &idct(&block).map(clip));
+            }
+            BlockType::Fill => {
+                let value = bundles.value(Source::Colors)? as u8;
+                plane.fill(offset, 8, value);
+            }
+            BlockType::Inter => {
+                let source = motion_offset(bundles, plane, x, y)?;
+                plane.copy_from(offset, previous, source);
+
+                let mut block = [0; 64];
+                block[0] = bundles.value(Source::InterDc)?;
+                let (quant, indices) = read_dct_coefficients(reader, &mut block)?;
+                dequantize(&mut block, &inter_quant[quant & 15], &indices);
+                plane.add(offset, &idct(&block));
+            }
+            BlockType::Pattern => plane.put(offset, &read_pattern(bundles)?),
+            BlockType::Raw => {
+                let mut pixels = [0; 64];
+                for pixel in pixels.iter_mut() {
+                    *pixel = bundles.value(Source::Colors)? as u8;
+                }
+                plane.put(offset, &pixels);
+            }
+        }
+        x += 1;
+    }
+
+    Ok(())
+}
+
+/// Offset of the block of the previous frame a block copies from
+fn motion_offset(
+    bundles: &mut Bundles,
+    plane: &Plane,
+    x: usize,
+    y: usize,
+) -> Result<usize, BinkVideoError> {
+    let source_x = (x * 8) as isize + bundles.value(Source::XOffset)? as isize;
+    let source_y = (y * 8) as isize + bundles.value(Source::YOffset)? as isize;
+    let offset = source_y * plane.stride as isize + source_x;
+
+    // The offset can be anywhere up to the start of the last block
+    let last = ((plane.blocks_down - 1) * plane.stride + (plane.blocks_across - 1)) * 8;
+    if offset < 0 || offset as usize > last {
+        return Err(BinkVideoError::MotionOutOfBounds {
+            x: source_x,
+            y: source_y,
+        });
+    }
+    Ok(offset as usize)
+}
+
+/// Reads the runs of a run block, writing each pixel by its index within
+/// the block. Runs either repeat one colour or have a colour per pixel
+fn read_run(
+    reader: &mut BitReader,
+    bundles: &mut Bundles,
+    mut write: impl FnMut(u8, u8),
+) -> Result<(), BinkVideoError> {
+    let mut scan = PATTERNS[reader.bits(4) as usize].iter();
+    let mut filled = 0;
+    while filled < 63 {
+        let run = bundles.value(Source::Run)? as usize + 1;
+        filled += run;
+        if filled > 64 {
+            return Err(BinkVideoError::RunOverflow);
+        }
+
+        match reader.bit() {
+            1 => {
+                let value = bundles.value(Source::Colors)? as u8;
+                for index in scan.by_ref().take(run) {
+                    write(*index, value);
+                }
+            }
+            _ => {
+                for index in scan.by_ref().take(run) {
+                    write(*index, bundles.value(Source::Colors)? as u8);
+                }
+            }
+        }
+    }
+
+    // The last pixel is left when the runs cover 63 pixels
+    if filled == 63 {
+        if let Some(index) = scan.next() {
+            write(*index, bundles.value(Source::Colors)? as u8);
+        }
+    }
+    Ok(())
+}
+
+/// Reads a block of two colours, each row picks the colour of its pixels
+/// from the bits of a pattern
+fn read_pattern(bundles: &mut Bundles) -> Result<[u8; 64], BinkVideoError> {
+    let colors = [
+        bundles.value(Source::Colors)? as u8,
+        bundles.value(Source::Colors)? as u8,
+    ];
+
+    let mut pixels = [0; 64];
+    for row in pixels.chunks_exact_mut(8) {
+        let pattern = bundles.value(Source::Pattern)?;
+        for (column, pixel) in row.iter_mut().enumerate() {
+            *pixel = colors[(pattern >> column) as usize & 1];
+        }
+    }
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::formats::bink::BinkHeader;
+
+    use super::{BinkVideoDecoder, PATTERNS, PLANE_U, PLANE_V, PLANE_Y, TREE_LENGTHS};
+
+    /// Writes bits from the lowest bit of each byte like the movies
+    #[derive(Default)]
+    struct BitWriter {
+        data: Vec<u8>,
+        position: usize,
+    }
+
+    impl BitWriter {
+        fn put(&mut self, value: u32, count: u32) {
+            for bit in 0..count {
+                if self.position & 7 == 0 {
+                    self.data.push(0);
+                }
+                let byte = self.data.last_mut().unwrap();
+                *byte |= (((value >> bit) & 1) as u8) << (self.position & 7);
+                self.position += 1;
+            }
+        }
+
+        /// Writes a symbol of the identity tree, the code is the symbol
+        /// read from its highest bit
+        fn symbol(&mut self, value: u32) {
+            for bit in (0..4).rev() {
+                self.put(value >> bit, 1);
+            }
+        }
+
+        fn align(&mut self) {
+            while self.position & 31 != 0 {
+                self.put(0, 1);
+            }
+        }
+    }
+
+    fn header(width: u32, height: u32) -> BinkHeader {
+        BinkHeader {
+            revision: b'i',
+            file_size: 0,
+            frame_count: 1,
+            largest_frame: 0,
+            width,
+            height,
+            fps_numerator: 30,
+            fps_denominator: 1,
+            video_flags: 0,
+            audio_track_count: 0,
+            max_audio_sizes: Vec::new(),
+            audio_tracks: Vec::new(),
+            audio_track_ids: Vec::new(),
+        }
+    }
+
+    /// Writes the identity trees of each bundle of a plane
+    fn write_trees(writer: &mut BitWriter) {
+        // Colors have a tree for each high nibble, DCs don't have a tree
+        for _ in 0..23 {
+            writer.put(0, 4);
+        }
+    }
+
+    /// Writes a 16x16 frame where every luma block is of the block type,
+    /// `bundles` writes the bundles from the colours to the inter DCs and
+    /// `blocks` the data of the blocks. Each chroma plane is a single fill
+    /// block of 128
+    fn write_frame(
+        block: u32,
+        bundles: impl Fn(&mut BitWriter),
+        blocks: impl Fn(&mut BitWriter),
+    ) -> Vec<u8> {
+        let mut writer = BitWriter::default();
+        writer.put(0, 32);
+
+        write_trees(&mut writer);
+        writer.put(4, 10);
+        writer.put(1, 1);
+        writer.put(block, 4);
+        writer.put(0, 10);
+        bundles(&mut writer);
+        writer.put(0, 10);
+        blocks(&mut writer);
+        writer.align();
+
+        for _ in 0..2 {
+            write_trees(&mut writer);
+            writer.put(1, 10);
+            writer.put(1, 1);
+            writer.put(6, 4);
+            writer.put(0, 9);
+            writer.put(1, 10);
+            writer.put(1, 1);
+            writer.symbol(8);
+            writer.symbol(0);
+            for _ in 0..6 {
+                writer.put(0, 10);
+            }
+            writer.align();
+        }
+
+        writer.data
+    }
+
+    #[test]
+    fn test_tree_lengths() {
+        for lengths in TREE_LENGTHS {
+            let sum: u32 = lengths.iter().map(|length| 128 >> length).sum();
+            assert_eq!(sum, 128);
+        }
+    }
+
+    #[test]
+    fn test_patterns() {
+        for pattern in PATTERNS {
+            let mut sorted = pattern;
+            sorted.sort();
+            assert!(sorted.iter().enumerate().all(|(i, v)| *v as usize == i));
+        }
+    }
+
+    #[test]
+    fn test_decode_fill() {
+        let data = write_frame(
+            6,
+            |writer| {
+                writer.put(4, 10);
+                writer.put(0, 1);
+                for color in [0x10, 0xEB, 0xEB, 0x10] {
+                    writer.symbol(color >> 4);
+                    writer.symbol(color & 15);
+                }
+                for _ in 0..5 {
+                    writer.put(0, 10);
+                }
+            },
+            |_| {},
+        );
+
+        let mut decoder = BinkVideoDecoder::new(&header(16, 16)).unwrap();
+        let picture = decoder.decode(&data).unwrap();
+        assert_eq!(picture.planes[PLANE_Y].pixel(0, 0), 0x10);
+        assert_eq!(picture.planes[PLANE_Y].pixel(8, 7), 0xEB);
+        assert_eq!(picture.planes[PLANE_Y].pixel(7, 8), 0xEB);
+        assert_eq!(picture.planes[PLANE_U].pixel(7, 7), 128);
+        assert_eq!(picture.planes[PLANE_V].pixel(0, 0), 128);
+
+        let mut rgba = vec![0; 16 * 16 * 4];
+        picture.write_rgba(&mut rgba);
+        assert_eq!(rgba[..4], [0, 0, 0, 255]);
+        assert_eq!(rgba[8 * 4..9 * 4], [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_decode_intra_dc() {
+        let data = write_frame(
+            5,
+            |writer| {
+                for _ in 0..4 {
+                    writer.put(0, 10);
+                }
+                // DC of 1024 for every block
+                writer.put(4, 10);
+                writer.put(1024, 11);
+                writer.put(0, 4);
+                writer.put(0, 10);
+            },
+            |writer| {
+                // No coefficient bits and the first quantizer
+                for _ in 0..4 {
+                    writer.put(0, 4);
+                    writer.put(0, 4);
+                }
+            },
+        );
+
+        let mut decoder = BinkVideoDecoder::new(&header(16, 16)).unwrap();
+        let picture = decoder.decode(&data).unwrap();
+        assert!(picture.planes[PLANE_Y].data[..16].iter().all(|v| *v == 128));
+        assert_eq!(picture.planes[PLANE_Y].pixel(15, 15), 128);
+    }
+
+    #[test]
+    fn test_unsupported_revision() {
+        let header = BinkHeader {
+            revision: b'b',
+            ..header(16, 16)
+        };
+        assert!(BinkVideoDecoder::new(&header).is_err());
+    }
+}
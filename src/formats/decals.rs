@@ -0,0 +1,115 @@
+//! Decal placements for blob shadows and ground markings. The engine's own
+//! placement records haven't been identified in the world data yet, so
+//! placements are read from a plain text listing with one decal per line,
+//! either `shadow x y z radius` or `marking texture x y z radius [rotation]`
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DecalError {
+    #[error("line {0}: expected shadow x y z radius or marking texture x y z radius [rotation]")]
+    MalformedLine(usize),
+    #[error("line {0}: invalid number {1:?}")]
+    InvalidNumber(usize, String),
+    #[error("line {0}: unknown decal kind {1:?}")]
+    UnknownKind(usize, String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecalKind {
+    /// Soft dark blob under an object
+    Shadow,
+    /// Textured marking on the ground
+    Marking { texture: String },
+}
+
+/// Single decal projected onto the ground
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecalPlacement {
+    pub kind: DecalKind,
+    /// Position the decal is projected down from
+    pub position: [f32; 3],
+    pub radius: f32,
+    /// Rotation around the vertical axis in degrees
+    pub rotation: f32,
+}
+
+fn parse_number(line: usize, value: &str) -> Result<f32, DecalError> {
+    value
+        .parse()
+        .map_err(|_| DecalError::InvalidNumber(line, value.to_string()))
+}
+
+/// Parses a decal listing, blank lines and lines starting
+/// with `#` are ignored
+pub fn parse_decals(value: &str) -> Result<Vec<DecalPlacement>, DecalError> {
+    let mut out = Vec::new();
+
+    for (index, line) in value.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let kind = match parts.next() {
+            Some("shadow") => DecalKind::Shadow,
+            Some("marking") => DecalKind::Marking {
+                texture: parts
+                    .next()
+                    .ok_or(DecalError::MalformedLine(line_number))?
+                    .to_string(),
+            },
+            Some(kind) => return Err(DecalError::UnknownKind(line_number, kind.to_string())),
+            None => continue,
+        };
+
+        let numbers = parts
+            .map(|value| parse_number(line_number, value))
+            .collect::<Result<Vec<f32>, DecalError>>()?;
+
+        let (position, radius, rotation) = match (&kind, numbers.as_slice()) {
+            (DecalKind::Shadow, &[x, y, z, radius]) => ([x, y, z], radius, 0.0),
+            (DecalKind::Marking { .. }, &[x, y, z, radius]) => ([x, y, z], radius, 0.0),
+            (DecalKind::Marking { .. }, &[x, y, z, radius, rotation]) => {
+                ([x, y, z], radius, rotation)
+            }
+            _ => return Err(DecalError::MalformedLine(line_number)),
+        };
+
+        out.push(DecalPlacement {
+            kind,
+            position,
+            radius,
+            rotation,
+        });
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_decals, DecalKind};
+
+    #[test]
+    fn test_parse_decals() {
+        let decals =
+            parse_decals("# level 1\nshadow 0 1 2 0.5\nmarking arrow_01 4 0 4 2 90\n").unwrap();
+
+        assert_eq!(decals.len(), 2);
+        assert_eq!(decals[0].kind, DecalKind::Shadow);
+        assert_eq!(decals[0].position, [0.0, 1.0, 2.0]);
+        assert_eq!(
+            decals[1].kind,
+            DecalKind::Marking {
+                texture: "arrow_01".to_string()
+            }
+        );
+        assert_eq!(decals[1].rotation, 90.0);
+
+        assert!(parse_decals("shadow 0 1 2 0.5 45").is_err());
+        assert!(parse_decals("puddle 0 0 0 1").is_err());
+    }
+}
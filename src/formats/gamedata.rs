@@ -0,0 +1,333 @@
+//! Game data attached to placed objects and shapes as user data. The data
+//! is text of `key=value` assignments separated by whitespace, newlines or
+//! `;`, a key can be given a list of values separated by commas and values
+//! containing spaces are quoted. `//` starts a comment running to the end
+//! of the line. Keys are matched ignoring case
+//!
+//! ```text
+//! class=Spawner spawn_count=3 spawn_delay=1.5
+//! sound_ambient_wind="Sounds/wind.wav", 0.5
+//! ```
+
+use std::str::FromStr;
+
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum GameDataError {
+    #[error("game data is not valid UTF-8")]
+    InvalidText,
+    #[error("expected a key at {0:?}")]
+    ExpectedKey(String),
+    #[error("expected = after key {0:?}")]
+    ExpectedAssign(String),
+    #[error("key {0:?} has no value")]
+    MissingValue(String),
+    #[error("unterminated string in the value of {0:?}")]
+    UnterminatedString(String),
+}
+
+/// Key of the class of entity an object or shape instantiates
+pub const CLASS_KEY: &str = "class";
+/// Prefix of the keys naming the ambient sounds played around a shape
+pub const AMBIENT_SOUND_PREFIX: &str = "sound_ambient_";
+
+/// Key along with its values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameDataEntry {
+    pub key: String,
+    pub values: Vec<String>,
+}
+
+/// Parameters of an entity spawner, missing keys use the defaults
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpawnParams {
+    /// Number of entities spawned, `spawn_count`
+    pub count: u32,
+    /// Seconds between each spawn, `spawn_delay`
+    pub delay: f32,
+    /// Distance from the spawner entities are spread across, `spawn_radius`
+    pub radius: f32,
+}
+
+impl Default for SpawnParams {
+    fn default() -> Self {
+        Self {
+            count: 1,
+            delay: 0.0,
+            radius: 0.0,
+        }
+    }
+}
+
+/// Ambient sound named by a `sound_ambient_*` key
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmbientSound {
+    /// Name following the key prefix
+    pub name: String,
+    /// Path of the sound in the game data
+    pub path: String,
+    /// Volume of the sound, 1.0 when not provided
+    pub volume: f32,
+}
+
+/// Parsed game data of an object or shape
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GameData {
+    pub entries: Vec<GameDataEntry>,
+}
+
+/// Whether the character ends a bare key or value
+fn is_separator(value: char) -> bool {
+    value.is_whitespace() || matches!(value, '=' | ',' | ';' | '"')
+}
+
+impl GameData {
+    /// Parses the user data blob of an object or shape, the blob is a null
+    /// terminated string
+    pub fn from_bytes(bytes: &[u8]) -> Result<GameData, GameDataError> {
+        let bytes = bytes.split(|value| *value == 0).next().unwrap_or_default();
+        let value = std::str::from_utf8(bytes).map_err(|_| GameDataError::InvalidText)?;
+        value.parse()
+    }
+
+    /// Values of the key
+    pub fn get(&self, key: &str) -> Option<&[String]> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key.eq_ignore_ascii_case(key))
+            .map(|entry| entry.values.as_slice())
+    }
+
+    /// First value of the key
+    pub fn string(&self, key: &str) -> Option<&str> {
+        self.get(key)?.first().map(String::as_str)
+    }
+
+    /// First value of the key parsed as a number, None when the key is
+    /// missing or isn't a number
+    pub fn number<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.string(key)?.parse().ok()
+    }
+
+    /// Whether the key is set to a true value
+    pub fn flag(&self, key: &str) -> bool {
+        self.string(key).is_some_and(|value| {
+            value == "1" || value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("yes")
+        })
+    }
+
+    /// Class of entity the object or shape instantiates
+    pub fn entity_class(&self) -> Option<&str> {
+        self.string(CLASS_KEY)
+    }
+
+    pub fn spawn_params(&self) -> SpawnParams {
+        let default = SpawnParams::default();
+        SpawnParams {
+            count: self.number("spawn_count").unwrap_or(default.count),
+            delay: self.number("spawn_delay").unwrap_or(default.delay),
+            radius: self.number("spawn_radius").unwrap_or(default.radius),
+        }
+    }
+
+    /// Ambient sounds in the order listed, keys without a path are skipped
+    pub fn ambient_sounds(&self) -> Vec<AmbientSound> {
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                let prefix = entry.key.get(..AMBIENT_SOUND_PREFIX.len())?;
+                if !prefix.eq_ignore_ascii_case(AMBIENT_SOUND_PREFIX) {
+                    return None;
+                }
+                Some(AmbientSound {
+                    name: entry.key[AMBIENT_SOUND_PREFIX.len()..].to_string(),
+                    path: entry.values.first()?.clone(),
+                    volume: entry
+                        .values
+                        .get(1)
+                        .and_then(|value| value.parse().ok())
+                        .unwrap_or(1.0),
+                })
+            })
+            .collect()
+    }
+}
+
+impl FromStr for GameData {
+    type Err = GameDataError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { rest: s };
+        let mut entries = Vec::new();
+
+        while let Some(key) = parser.key()? {
+            parser.skip_space();
+            if !parser.eat('=') {
+                return Err(GameDataError::ExpectedAssign(key));
+            }
+
+            let mut values = Vec::new();
+            loop {
+                parser.skip_space();
+                match parser.value(&key)? {
+                    Some(value) => values.push(value),
+                    None => return Err(GameDataError::MissingValue(key)),
+                }
+                parser.skip_space();
+                if !parser.eat(',') {
+                    break;
+                }
+            }
+
+            entries.push(GameDataEntry { key, values });
+        }
+
+        Ok(GameData { entries })
+    }
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl Parser<'_> {
+    /// Skips whitespace on the current line along with any comment
+    fn skip_space(&mut self) {
+        self.rest = self.rest.trim_start_matches([' ', '\t']);
+        if self.rest.starts_with("//") {
+            self.rest = self.rest.find('\n').map_or("", |end| &self.rest[end..]);
+        }
+    }
+
+    /// Skips whitespace, comments and separators between assignments
+    fn skip_separators(&mut self) {
+        loop {
+            self.skip_space();
+            let trimmed = self
+                .rest
+                .trim_start_matches(|value: char| value.is_whitespace() || value == ';');
+            if trimmed.len() == self.rest.len() {
+                break;
+            }
+            self.rest = trimmed;
+        }
+    }
+
+    fn eat(&mut self, value: char) -> bool {
+        match self.rest.strip_prefix(value) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Takes a bare token
+    fn token(&mut self) -> &str {
+        let end = self.rest.find(is_separator).unwrap_or(self.rest.len());
+        let (token, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        token
+    }
+
+    /// Next key, None once there are no assignments left
+    fn key(&mut self) -> Result<Option<String>, GameDataError> {
+        self.skip_separators();
+        if self.rest.is_empty() {
+            return Ok(None);
+        }
+
+        let key = self.token();
+        if key.is_empty() {
+            let context: String = self.rest.chars().take(16).collect();
+            return Err(GameDataError::ExpectedKey(context));
+        }
+        Ok(Some(key.to_string()))
+    }
+
+    /// Next value, None when the assignment has no value
+    fn value(&mut self, key: &str) -> Result<Option<String>, GameDataError> {
+        if self.eat('"') {
+            let end = self
+                .rest
+                .find(['"', '\n'])
+                .filter(|end| self.rest[*end..].starts_with('"'))
+                .ok_or_else(|| GameDataError::UnterminatedString(key.to_string()))?;
+            let value = self.rest[..end].to_string();
+            self.rest = &self.rest[end + 1..];
+            return Ok(Some(value));
+        }
+
+        let value = self.token();
+        Ok((!value.is_empty()).then(|| value.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AmbientSound, GameData, GameDataError, SpawnParams};
+
+    #[test]
+    fn test_parse_gamedata() {
+        let data: GameData = r#"
+            // Spawner placed by the level
+            Class=Spawner spawn_count=3; spawn_delay = 1.5
+            sound_ambient_wind="Sounds/wind loop.wav", 0.5
+            sound_ambient_birds=Sounds/birds.wav
+            hidden=true
+        "#
+        .parse()
+        .unwrap();
+
+        assert_eq!(data.entity_class(), Some("Spawner"));
+        assert_eq!(
+            data.spawn_params(),
+            SpawnParams {
+                count: 3,
+                delay: 1.5,
+                radius: 0.0,
+            }
+        );
+        assert!(data.flag("HIDDEN"));
+        assert_eq!(
+            data.ambient_sounds(),
+            vec![
+                AmbientSound {
+                    name: "wind".to_string(),
+                    path: "Sounds/wind loop.wav".to_string(),
+                    volume: 0.5,
+                },
+                AmbientSound {
+                    name: "birds".to_string(),
+                    path: "Sounds/birds.wav".to_string(),
+                    volume: 1.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_blob() {
+        let data = GameData::from_bytes(b"class=Door\0\xFF\xFF").unwrap();
+        assert_eq!(data.entity_class(), Some("Door"));
+        assert_eq!(GameData::from_bytes(b"\0").unwrap(), GameData::default());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "class".parse::<GameData>(),
+            Err(GameDataError::ExpectedAssign("class".to_string()))
+        );
+        assert_eq!(
+            "class=".parse::<GameData>(),
+            Err(GameDataError::MissingValue("class".to_string()))
+        );
+        assert_eq!(
+            "path=\"Sounds/wind.wav".parse::<GameData>(),
+            Err(GameDataError::UnterminatedString("path".to_string()))
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Extension point for file formats, handlers are registered with the
+//! [FormatRegistry] and picked up by anything that loads or inspects files
+
+use std::{io::Write, path::Path};
+
+use bevy::{
+    app::{App, Plugin},
+    ecs::system::Resource,
+};
+use openglitch_formats::mesh::FMesh;
+use thiserror::Error;
+
+use crate::fs::{FsError, GameFs};
+
+use super::{
+    hex::{sequential_fields, FieldSpan},
+    mesh::loader::{MeshLoadError, MeshLoader},
+};
+
+/// Number of bytes from the start of a file provided for detection
+pub const DETECT_HEADER_SIZE: usize = 64;
+
+#[derive(Debug, Error)]
+pub enum HandlerError {
+    #[error("no handler for file: {0}")]
+    Unsupported(String),
+    #[error("export format {0:?} is not supported")]
+    UnsupportedExport(String),
+    #[error(transparent)]
+    Fs(#[from] FsError),
+    #[error(transparent)]
+    Parse(#[from] binrw::Error),
+    #[error(transparent)]
+    MeshLoad(#[from] MeshLoadError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Handler adding support for a file format
+pub trait FormatHandler: Send + Sync {
+    /// Name of the format for display
+    fn name(&self) -> &str;
+
+    /// Checks if the handler supports the file at the provided path, `header`
+    /// contains up to [DETECT_HEADER_SIZE] bytes from the start of the file
+    fn detect(&self, path: &str, header: &[u8]) -> bool;
+
+    /// Parses the contents of the file
+    fn parse(&self, bytes: &[u8]) -> Result<Box<dyn ParsedFile>, HandlerError>;
+}
+
+/// File parsed by a [FormatHandler]
+pub trait ParsedFile: Send + Sync {
+    /// Describes the file as a list of labelled values
+    fn inspect(&self) -> Vec<(String, String)>;
+
+    /// Byte ranges within the file that the parsed fields were read from
+    fn fields(&self) -> Vec<FieldSpan> {
+        Vec::new()
+    }
+
+    /// Names of the formats the file can be exported to
+    fn export_formats(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Exports the file in the provided format
+    fn export(&self, format: &str, _out: &mut dyn Write) -> Result<(), HandlerError> {
+        Err(HandlerError::UnsupportedExport(format.to_string()))
+    }
+}
+
+/// Registry of the available format handlers, handlers registered later
+/// take priority so they can override the built-in handlers
+#[derive(Default, Resource)]
+pub struct FormatRegistry {
+    handlers: Vec<Box<dyn FormatHandler>>,
+}
+
+impl FormatRegistry {
+    /// Creates a registry containing the built-in handlers
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register(ApeHandler);
+        registry
+    }
+
+    pub fn register<H: FormatHandler + 'static>(&mut self, handler: H) {
+        self.handlers.push(Box::new(handler));
+    }
+
+    pub fn handlers(&self) -> impl Iterator<Item = &dyn FormatHandler> {
+        self.handlers.iter().rev().map(|handler| handler.as_ref())
+    }
+
+    /// Finds the handler for the provided file
+    pub fn find(&self, path: &str, header: &[u8]) -> Option<&dyn FormatHandler> {
+        let header = &header[..header.len().min(DETECT_HEADER_SIZE)];
+        self.handlers().find(|handler| handler.detect(path, header))
+    }
+
+    /// Parses a file from the game filesystem using the matching handler
+    pub fn parse(&self, fs: &GameFs, path: &str) -> Result<Box<dyn ParsedFile>, HandlerError> {
+        let bytes = fs.read(path)?;
+        let handler = self
+            .find(path, &bytes)
+            .ok_or_else(|| HandlerError::Unsupported(path.to_string()))?;
+        handler.parse(&bytes)
+    }
+}
+
+/// Extension for registering format handlers from other plugins
+pub trait RegisterFormatHandler {
+    fn register_format_handler<H: FormatHandler + 'static>(&mut self, handler: H) -> &mut Self;
+}
+
+impl RegisterFormatHandler for App {
+    fn register_format_handler<H: FormatHandler + 'static>(&mut self, handler: H) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(FormatRegistry::with_builtin)
+            .register(handler);
+        self
+    }
+}
+
+/// Plugin providing the [FormatRegistry] resource
+pub struct FormatsPlugin;
+
+impl Plugin for FormatsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<FormatRegistry>() {
+            app.insert_resource(FormatRegistry::with_builtin());
+        }
+    }
+}
+
+/// Checks the extension of the provided path ignoring case
+pub fn has_extension(path: &str, extension: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|value| value.eq_ignore_ascii_case(extension))
+}
+
+/// Built-in handler for GameCube meshes
+struct ApeHandler;
+
+impl FormatHandler for ApeHandler {
+    fn name(&self) -> &str {
+        "GameCube mesh"
+    }
+
+    fn detect(&self, path: &str, _header: &[u8]) -> bool {
+        has_extension(path, "ape")
+            && Path::new(path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.to_ascii_lowercase().starts_with("gc"))
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<Box<dyn ParsedFile>, HandlerError> {
+        let mesh = MeshLoader::from_bytes(bytes.to_vec()).load()?;
+        Ok(Box::new(mesh.into_mesh()))
+    }
+}
+
+impl ParsedFile for FMesh {
+    fn inspect(&self) -> Vec<(String, String)> {
+        vec![
+            ("Name".to_string(), self.name.to_string()),
+            (
+                "Bound radius".to_string(),
+                self.bound_sphere.radius.to_string(),
+            ),
+            ("Bones".to_string(), self.bone_count.to_string()),
+            ("Segments".to_string(), self.segment_count.to_string()),
+            ("Materials".to_string(), self.material_count.to_string()),
+            ("Lights".to_string(), self.light_count.to_string()),
+            ("LODs".to_string(), self.lod_count.to_string()),
+        ]
+    }
+
+    fn fields(&self) -> Vec<FieldSpan> {
+        // Header is read without padding from the start of the file
+        sequential_fields(
+            0,
+            &[
+                ("name", 16),
+                ("bound_sphere", 16),
+                ("bound_box_min", 12),
+                ("bound_box_max", 12),
+                ("flags", 2),
+                ("mesh_coll_mask", 2),
+                ("used_bone_count", 1),
+                ("root_bone_index", 1),
+                ("bone_count", 1),
+                ("segment_count", 1),
+                ("tex_layer_id_count", 1),
+                ("tex_layer_id_count_st", 1),
+                ("tex_layer_id_count_flip", 1),
+                ("light_count", 1),
+                ("material_count", 1),
+                ("coll_tree_count", 1),
+                ("lod_count", 1),
+                ("shadow_lod_bias", 1),
+                ("lod_distance", 32),
+                ("segment_array", 4),
+                ("bone_array", 4),
+                ("light_array", 4),
+                ("skeleton_index_array", 4),
+                ("material_array", 4),
+                ("collision_tree", 4),
+                ("tex_layer_array", 4),
+                ("mesh_is", 4),
+            ],
+        )
+    }
+}
@@ -0,0 +1,118 @@
+//! Helpers for viewing the raw bytes of a file, mapping bytes to the parsed
+//! fields they belong to and guessing what unknown bytes could be
+
+use std::ops::Range;
+
+/// Named range of bytes within a file that a parsed field was read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSpan {
+    pub name: String,
+    pub range: Range<usize>,
+}
+
+/// Creates spans for fields that are read one after another starting
+/// at the provided offset
+pub fn sequential_fields(start: usize, fields: &[(&str, usize)]) -> Vec<FieldSpan> {
+    let mut offset = start;
+    fields
+        .iter()
+        .map(|(name, size)| {
+            let range = offset..offset + size;
+            offset += size;
+            FieldSpan {
+                name: name.to_string(),
+                range,
+            }
+        })
+        .collect()
+}
+
+/// Finds the field containing the byte at the provided offset
+pub fn field_at(fields: &[FieldSpan], offset: usize) -> Option<&FieldSpan> {
+    fields.iter().find(|field| field.range.contains(&offset))
+}
+
+/// Possible interpretation of the bytes at an offset
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interpretation {
+    pub label: &'static str,
+    pub value: String,
+}
+
+/// Lists the candidate interpretations of the bytes starting at the offset,
+/// values that are implausible for their type are left out
+pub fn interpret(bytes: &[u8], offset: usize) -> Vec<Interpretation> {
+    let mut out = Vec::new();
+    let mut push = |label, value: String| out.push(Interpretation { label, value });
+
+    if let Some(&[a, b]) = bytes.get(offset..offset + 2) {
+        push("u16 BE", u16::from_be_bytes([a, b]).to_string());
+        push("u16 LE", u16::from_le_bytes([a, b]).to_string());
+    }
+
+    let Some(&[a, b, c, d]) = bytes.get(offset..offset + 4) else {
+        return out;
+    };
+
+    for (label, value) in [
+        ("f32 BE", f32::from_be_bytes([a, b, c, d])),
+        ("f32 LE", f32::from_le_bytes([a, b, c, d])),
+    ] {
+        // Subnormal and non finite values are almost never real floats
+        if value == 0.0 || value.is_normal() {
+            push(label, value.to_string());
+        }
+    }
+
+    for (label, value) in [
+        ("u32 BE", u32::from_be_bytes([a, b, c, d])),
+        ("u32 LE", u32::from_le_bytes([a, b, c, d])),
+    ] {
+        push(label, value.to_string());
+    }
+
+    for (label, value) in [
+        ("offset BE", u32::from_be_bytes([a, b, c, d])),
+        ("offset LE", u32::from_le_bytes([a, b, c, d])),
+    ] {
+        if value != 0 && (value as usize) < bytes.len() {
+            push(label, format!("{:#x}", value));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{field_at, interpret, sequential_fields};
+
+    #[test]
+    fn test_sequential_fields() {
+        let fields = sequential_fields(4, &[("a", 2), ("b", 4)]);
+        assert_eq!(fields[1].range, 6..10);
+        assert_eq!(field_at(&fields, 7).unwrap().name, "b");
+        assert!(field_at(&fields, 3).is_none());
+    }
+
+    #[test]
+    fn test_interpret() {
+        let mut bytes = 1.5f32.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&[0; 12]);
+
+        let values = interpret(&bytes, 0);
+        let value = |label| {
+            values
+                .iter()
+                .find(|value| value.label == label)
+                .map(|value| value.value.clone())
+        };
+
+        assert_eq!(value("f32 BE").as_deref(), Some("1.5"));
+        // Little endian reading is subnormal
+        assert_eq!(value("f32 LE"), None);
+        assert_eq!(value("u16 BE").as_deref(), Some("16320"));
+        // Too large to be an offset within the file
+        assert_eq!(value("offset BE"), None);
+    }
+}
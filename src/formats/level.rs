@@ -0,0 +1,554 @@
+//! Level scenes made up of placed objects, lights and world geometry. The
+//! PASM data in the .wld files hasn't been decoded yet, so levels are read
+//! from a plain text `.level` description with one entry per line:
+//!
+//! - `object mesh x y z [yaw] [cull] [data]` places a mesh rotated by yaw
+//!   degrees, the mesh is hidden beyond the cull distance when one is
+//!   provided
+//! - `segment mesh` adds world geometry which is already in world space
+//! - `scatter mesh x y z [yaw] [scale]` places an instance of a small prop,
+//!   props are drawn instanced so dense areas don't need an entity each
+//! - `light point x y z r g b intensity range [motif]`
+//! - `light spot x y z dx dy dz r g b intensity range angle [motif]`
+//! - `light directional dx dy dz r g b intensity [motif]`
+//! - `start x y z [yaw]` marks a StartPoint shape the player can be placed
+//!   at, facing along the yaw in degrees
+//! - `entity x y z [yaw] [data]` marks a shape instantiating an entity
+//! - `spline open|closed x y z x y z ...` places a spline shape passing
+//!   through each point
+//!
+//! The data of objects and entities is the [GameData] of their user data,
+//! it begins at the first `key=value` assignment and runs to the end of
+//! the line
+
+use std::str::FromStr;
+
+use bevy::{
+    asset::{io::Reader, Asset, AssetLoader, AsyncReadExt, Handle, LoadContext},
+    math::{Quat, Vec3},
+    reflect::TypePath,
+    render::color::Color,
+    transform::components::Transform,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    cancel::{CancelToken, Cancelled},
+    fs::source_asset_path,
+};
+
+use super::{
+    gamedata::{GameData, GameDataError},
+    mesh::asset::{ApeAsset, ApeLoadSettings},
+    spline::{Spline, SplineError},
+};
+
+#[derive(Debug, Error)]
+pub enum LevelError {
+    #[error("line {0}: expected {1}")]
+    MalformedLine(usize, &'static str),
+    #[error("line {0}: unknown entry {1:?}")]
+    UnknownEntry(usize, String),
+    #[error("line {0}: unknown light kind {1:?}")]
+    UnknownLightKind(usize, String),
+    #[error("line {0}: invalid number {1:?}")]
+    InvalidNumber(usize, String),
+    #[error("line {0}: {1}")]
+    GameData(usize, GameDataError),
+    #[error("line {0}: {1}")]
+    Spline(usize, SplineError),
+    #[error("line {0}: {1} entries don't take game data")]
+    UnexpectedGameData(usize, String),
+}
+
+/// Mesh placed in the level
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelObject {
+    /// Path of the mesh in the game data
+    pub mesh: String,
+    pub transform: Transform,
+    /// Distance from the camera beyond which the object is hidden, none
+    /// when the object is always drawn
+    pub cull_distance: Option<f32>,
+    /// Game data of the object, empty when the object has none
+    pub data: GameData,
+}
+
+/// Shape instantiating an entity described by its game data
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelEntity {
+    pub transform: Transform,
+    pub data: GameData,
+}
+
+/// Instances of a single scattered prop
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScatterGroup {
+    /// Path of the prop mesh in the game data
+    pub mesh: String,
+    pub instances: Vec<Transform>,
+}
+
+/// Kind of light along with the values specific to the kind
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LevelLightKind {
+    Point,
+    /// Spot light with the outer cone angle in radians
+    Spot {
+        angle: f32,
+    },
+    Directional,
+}
+
+/// Light placed in the level
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelLight {
+    pub kind: LevelLightKind,
+    /// Position of the light, unused by directional lights
+    pub position: Vec3,
+    /// Direction the light points in, unused by point lights
+    pub direction: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+    /// Distance the light reaches, unused by directional lights
+    pub range: f32,
+    /// Index of the color motif tinting the light, zero for none
+    pub motif: u32,
+}
+
+/// Contents of a level description
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Level {
+    pub objects: Vec<LevelObject>,
+    pub lights: Vec<LevelLight>,
+    /// Paths of the world geometry meshes
+    pub segments: Vec<String>,
+    /// Scattered props grouped by mesh in the order first used
+    pub scatter: Vec<ScatterGroup>,
+    /// Transforms of the StartPoint shapes in the order listed
+    pub start_points: Vec<Transform>,
+    pub entities: Vec<LevelEntity>,
+    pub splines: Vec<Spline>,
+}
+
+impl Level {
+    /// Adds an instance of a scattered prop
+    fn add_scatter(&mut self, mesh: &str, instance: Transform) {
+        match self.scatter.iter_mut().find(|group| group.mesh == mesh) {
+            Some(group) => group.instances.push(instance),
+            None => self.scatter.push(ScatterGroup {
+                mesh: mesh.to_string(),
+                instances: vec![instance],
+            }),
+        }
+    }
+}
+
+/// Parses a number from a line
+fn number<T: FromStr>(line_number: usize, value: &str) -> Result<T, LevelError> {
+    value
+        .parse()
+        .map_err(|_| LevelError::InvalidNumber(line_number, value.to_string()))
+}
+
+/// Parses the first three values into a vector
+fn vec3(line_number: usize, values: &[&str]) -> Result<Vec3, LevelError> {
+    Ok(Vec3::new(
+        number(line_number, values[0])?,
+        number(line_number, values[1])?,
+        number(line_number, values[2])?,
+    ))
+}
+
+/// Splits the line before the first `key=value` assignment, returning the
+/// fields of the entry and its game data
+fn split_gamedata(line: &str) -> (&str, &str) {
+    let Some(assign) = line.find('=') else {
+        return (line, "");
+    };
+    let key_end = line[..assign].trim_end().len();
+    let start = line[..key_end]
+        .rfind(char::is_whitespace)
+        .map_or(0, |index| index + 1);
+    (&line[..start], &line[start..])
+}
+
+/// Parses a level description, blank lines and lines starting
+/// with `#` are ignored
+pub fn parse_level(value: &str) -> Result<Level, LevelError> {
+    let mut out = Level::default();
+
+    for (index, line) in value.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (fields, data) = split_gamedata(line);
+        let parts: Vec<&str> = fields.split_whitespace().collect();
+        let data: GameData = data
+            .parse()
+            .map_err(|err| LevelError::GameData(line_number, err))?;
+        if !data.entries.is_empty() && !matches!(parts.first(), Some(&"object" | &"entity")) {
+            let entry = parts.first().copied().unwrap_or_default();
+            return Err(LevelError::UnexpectedGameData(
+                line_number,
+                entry.to_string(),
+            ));
+        }
+
+        match parts.as_slice() {
+            &["object", mesh, ref rest @ ..] if (3..=5).contains(&rest.len()) => {
+                let yaw: f32 = match rest.get(3) {
+                    Some(value) => number(line_number, value)?,
+                    None => 0.0,
+                };
+                let cull_distance: Option<f32> = match rest.get(4) {
+                    Some(value) => Some(number(line_number, value)?),
+                    None => None,
+                };
+                out.objects.push(LevelObject {
+                    mesh: mesh.to_string(),
+                    transform: Transform::from_translation(vec3(line_number, rest)?)
+                        .with_rotation(Quat::from_rotation_y(yaw.to_radians())),
+                    // A distance of zero is used by objects that are never culled
+                    cull_distance: cull_distance.filter(|value| *value > 0.0),
+                    data,
+                });
+            }
+            &["object", ..] => {
+                return Err(LevelError::MalformedLine(
+                    line_number,
+                    "object mesh x y z [yaw] [cull] [data]",
+                ))
+            }
+            &["scatter", mesh, ref rest @ ..] if (3..=5).contains(&rest.len()) => {
+                let yaw: f32 = match rest.get(3) {
+                    Some(value) => number(line_number, value)?,
+                    None => 0.0,
+                };
+                let scale: f32 = match rest.get(4) {
+                    Some(value) => number(line_number, value)?,
+                    None => 1.0,
+                };
+                out.add_scatter(
+                    mesh,
+                    Transform::from_translation(vec3(line_number, rest)?)
+                        .with_rotation(Quat::from_rotation_y(yaw.to_radians()))
+                        .with_scale(Vec3::splat(scale)),
+                );
+            }
+            &["scatter", ..] => {
+                return Err(LevelError::MalformedLine(
+                    line_number,
+                    "scatter mesh x y z [yaw] [scale]",
+                ))
+            }
+            &["start", ref rest @ ..] if (3..=4).contains(&rest.len()) => {
+                let yaw: f32 = match rest.get(3) {
+                    Some(value) => number(line_number, value)?,
+                    None => 0.0,
+                };
+                out.start_points.push(
+                    Transform::from_translation(vec3(line_number, rest)?)
+                        .with_rotation(Quat::from_rotation_y(yaw.to_radians())),
+                );
+            }
+            &["start", ..] => {
+                return Err(LevelError::MalformedLine(line_number, "start x y z [yaw]"))
+            }
+            &["entity", ref rest @ ..] if (3..=4).contains(&rest.len()) => {
+                let yaw: f32 = match rest.get(3) {
+                    Some(value) => number(line_number, value)?,
+                    None => 0.0,
+                };
+                out.entities.push(LevelEntity {
+                    transform: Transform::from_translation(vec3(line_number, rest)?)
+                        .with_rotation(Quat::from_rotation_y(yaw.to_radians())),
+                    data,
+                });
+            }
+            &["entity", ..] => {
+                return Err(LevelError::MalformedLine(
+                    line_number,
+                    "entity x y z [yaw] [data]",
+                ))
+            }
+            &["spline", kind @ ("open" | "closed"), ref rest @ ..] if rest.len() % 3 == 0 => {
+                let points = rest
+                    .chunks_exact(3)
+                    .map(|values| vec3(line_number, values))
+                    .collect::<Result<Vec<Vec3>, LevelError>>()?;
+                let spline = Spline::new(points, kind == "closed")
+                    .map_err(|err| LevelError::Spline(line_number, err))?;
+                out.splines.push(spline);
+            }
+            &["spline", ..] => {
+                return Err(LevelError::MalformedLine(
+                    line_number,
+                    "spline open|closed x y z x y z ...",
+                ))
+            }
+            &["segment", mesh] => out.segments.push(mesh.to_string()),
+            &["segment", ..] => return Err(LevelError::MalformedLine(line_number, "segment mesh")),
+            &["light", kind, ref rest @ ..] => {
+                out.lights.push(parse_light(line_number, kind, rest)?)
+            }
+            &[entry, ..] => return Err(LevelError::UnknownEntry(line_number, entry.to_string())),
+            &[] => {}
+        }
+    }
+
+    Ok(out)
+}
+
+/// Parses the values of a light entry
+fn parse_light(line_number: usize, kind: &str, values: &[&str]) -> Result<LevelLight, LevelError> {
+    let color = |values: &[&str]| -> Result<Color, LevelError> {
+        let [r, g, b] = vec3(line_number, values)?.to_array();
+        Ok(Color::rgb(r, g, b))
+    };
+    // The motif index optionally follows the values of each kind
+    let motif = |index: usize| -> Result<u32, LevelError> {
+        match values.get(index) {
+            Some(value) => number(line_number, value),
+            None => Ok(0),
+        }
+    };
+
+    match (kind, values.len()) {
+        ("point", 8 | 9) => Ok(LevelLight {
+            kind: LevelLightKind::Point,
+            position: vec3(line_number, &values[0..3])?,
+            direction: Vec3::NEG_Y,
+            color: color(&values[3..6])?,
+            intensity: number(line_number, values[6])?,
+            range: number(line_number, values[7])?,
+            motif: motif(8)?,
+        }),
+        ("point", _) => Err(LevelError::MalformedLine(
+            line_number,
+            "light point x y z r g b intensity range [motif]",
+        )),
+        ("spot", 12 | 13) => Ok(LevelLight {
+            kind: LevelLightKind::Spot {
+                angle: number::<f32>(line_number, values[11])?.to_radians(),
+            },
+            position: vec3(line_number, &values[0..3])?,
+            direction: vec3(line_number, &values[3..6])?,
+            color: color(&values[6..9])?,
+            intensity: number(line_number, values[9])?,
+            range: number(line_number, values[10])?,
+            motif: motif(12)?,
+        }),
+        ("spot", _) => Err(LevelError::MalformedLine(
+            line_number,
+            "light spot x y z dx dy dz r g b intensity range angle [motif]",
+        )),
+        ("directional", 7 | 8) => Ok(LevelLight {
+            kind: LevelLightKind::Directional,
+            position: Vec3::ZERO,
+            direction: vec3(line_number, &values[0..3])?,
+            color: color(&values[3..6])?,
+            intensity: number(line_number, values[6])?,
+            range: 0.0,
+            motif: motif(7)?,
+        }),
+        ("directional", _) => Err(LevelError::MalformedLine(
+            line_number,
+            "light directional dx dy dz r g b intensity [motif]",
+        )),
+        (kind, _) => Err(LevelError::UnknownLightKind(line_number, kind.to_string())),
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WldAssetError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("level is not valid UTF-8")]
+    InvalidText,
+    #[error(transparent)]
+    Level(#[from] LevelError),
+    #[error(transparent)]
+    Cancelled(#[from] Cancelled),
+}
+
+/// Settings for loading a level
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WldLoadSettings {
+    /// Token cancelling the load of the level and every mesh it places
+    #[serde(skip)]
+    pub cancel: CancelToken,
+}
+
+/// Level loaded from a `.level` description along with the meshes
+/// it places
+#[derive(Asset, TypePath, Debug)]
+pub struct WldAsset {
+    pub level: Level,
+    /// Mesh of each [LevelObject]
+    #[dependency]
+    pub objects: Vec<Handle<ApeAsset>>,
+    /// Mesh of each world geometry segment
+    #[dependency]
+    pub segments: Vec<Handle<ApeAsset>>,
+    /// Mesh of each [ScatterGroup]
+    #[dependency]
+    pub scatter: Vec<Handle<ApeAsset>>,
+}
+
+/// Loader for `.level` assets, the meshes are loaded from the game data
+#[derive(Default)]
+pub struct WldAssetLoader;
+
+impl AssetLoader for WldAssetLoader {
+    type Asset = WldAsset;
+    type Settings = WldLoadSettings;
+    type Error = WldAssetError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        settings: &'a WldLoadSettings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<WldAsset, WldAssetError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            settings.cancel.check()?;
+            let value = String::from_utf8(bytes).map_err(|_| WldAssetError::InvalidText)?;
+            let level = parse_level(&value)?;
+
+            // Meshes share the token of the level so they stop loading with
+            // it and load through the same source as the level
+            let source = load_context.asset_path().source().clone_owned();
+            let mut load_mesh = |path: &str| -> Handle<ApeAsset> {
+                let cancel = settings.cancel.clone();
+                load_context.load_with_settings(
+                    source_asset_path(&source, path),
+                    move |settings: &mut ApeLoadSettings| settings.cancel = cancel.clone(),
+                )
+            };
+
+            let objects = level
+                .objects
+                .iter()
+                .map(|object| load_mesh(&object.mesh))
+                .collect();
+            let segments = level
+                .segments
+                .iter()
+                .map(|segment| load_mesh(segment))
+                .collect();
+            let scatter = level
+                .scatter
+                .iter()
+                .map(|group| load_mesh(&group.mesh))
+                .collect();
+
+            Ok(WldAsset {
+                level,
+                objects,
+                segments,
+                scatter,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level"]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::Vec3;
+
+    use super::{parse_level, LevelError, LevelLightKind};
+
+    #[test]
+    fn test_parse_level() {
+        let level = parse_level(
+            "# level\n\
+             segment ape/gcwdglitch01.ape\n\
+             object ape/gcdggltch00.ape 1 2 3 90\n\
+             object ape/gcdgbarrel00.ape 0 0 0 0 150\n\
+             scatter ape/gcgrass00.ape 0 0 0\n\
+             scatter ape/gcrock00.ape 5 0 5 45 2\n\
+             scatter ape/gcgrass00.ape 1 0 0 90\n\
+             light point 0 4 0 1 0.5 0 800 10\n\
+             light spot 0 4 0 0 -1 0 1 1 1 1000 20 45\n\
+             light directional 0 -1 0 1 1 1 10000 4\n\
+             start 4 0 -2 180\n\
+             entity 0 0 8 class=Spawner spawn_count=2\n\
+             spline closed 0 0 0 4 0 0 4 0 4\n",
+        )
+        .unwrap();
+
+        assert_eq!(level.segments, ["ape/gcwdglitch01.ape"]);
+        assert_eq!(
+            level.objects[0].transform.translation,
+            Vec3::new(1.0, 2.0, 3.0)
+        );
+        assert_eq!(level.objects[0].cull_distance, None);
+        assert_eq!(level.objects[1].cull_distance, Some(150.0));
+        assert_eq!(level.scatter.len(), 2);
+        assert_eq!(level.scatter[0].instances.len(), 2);
+        assert_eq!(level.scatter[1].instances[0].scale, Vec3::splat(2.0));
+        assert_eq!(level.lights[0].kind, LevelLightKind::Point);
+        assert_eq!(level.lights[0].range, 10.0);
+        assert!(matches!(level.lights[1].kind, LevelLightKind::Spot { .. }));
+        assert_eq!(level.lights[2].direction, Vec3::NEG_Y);
+        assert_eq!(level.lights[0].motif, 0);
+        assert_eq!(level.lights[2].motif, 4);
+        assert_eq!(level.start_points.len(), 1);
+        assert_eq!(level.start_points[0].translation, Vec3::new(4.0, 0.0, -2.0));
+        assert_eq!(level.entities[0].data.entity_class(), Some("Spawner"));
+        assert_eq!(level.entities[0].data.spawn_params().count, 2);
+        assert!(level.objects[0].data.entries.is_empty());
+        assert!(level.splines[0].closed);
+        assert_eq!(level.splines[0].points[1], Vec3::new(4.0, 0.0, 0.0));
+
+        assert!(matches!(
+            parse_level("light area 0 0 0"),
+            Err(LevelError::UnknownLightKind(1, _))
+        ));
+        assert!(matches!(
+            parse_level("object mesh.ape 0 0"),
+            Err(LevelError::MalformedLine(1, _))
+        ));
+        assert!(matches!(
+            parse_level("start 0 0"),
+            Err(LevelError::MalformedLine(1, _))
+        ));
+        assert!(matches!(
+            parse_level("portal a b"),
+            Err(LevelError::UnknownEntry(1, _))
+        ));
+        assert!(parse_level("light point 0 0 0 1 1 1 bright 10").is_err());
+        assert!(matches!(
+            parse_level("spline open 0 0 0 1 1"),
+            Err(LevelError::MalformedLine(1, _))
+        ));
+        assert!(matches!(
+            parse_level("spline open 0 0 0"),
+            Err(LevelError::Spline(1, _))
+        ));
+        assert!(matches!(
+            parse_level("start 0 0 0 class=Spawner"),
+            Err(LevelError::UnexpectedGameData(1, _))
+        ));
+        assert!(matches!(
+            parse_level("object mesh.ape 0 0 0 0 0 sound=\"a.wav"),
+            Err(LevelError::GameData(1, _))
+        ));
+
+        let level = parse_level("object door.ape 0 0 0 90 class = Door locked=1").unwrap();
+        assert_eq!(level.objects[0].mesh, "door.ape");
+        assert!(level.objects[0].data.flag("locked"));
+    }
+}
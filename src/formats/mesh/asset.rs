@@ -0,0 +1,351 @@
+//! Asset loader for GameCube .ape meshes, loading an .ape through the
+//! asset server creates an [ApeAsset] with a labeled mesh for each vertex
+//! buffer (`Mesh0`, `Mesh1`, ...) and a labeled material for each mesh
+//! material (`Material0`, ...). Display lists that decode are also added as
+//! labeled meshes (`DisplayList0`, ...) so each LOD can be drawn on its own,
+//! textures stored in the mesh are added as labeled images by their
+//! lowercase name (`Texture{name}`)
+
+use bevy::{
+    asset::{io::Reader, Asset, AssetLoader, AssetPath, AsyncReadExt, Handle, LoadContext},
+    ecs::world::{FromWorld, World},
+    log::warn,
+    math::Vec3,
+    pbr::StandardMaterial,
+    reflect::TypePath,
+    render::{mesh::Mesh, texture::Image},
+    utils::BoxedFuture,
+};
+use openglitch_formats::{platform::Platform, types::RawSphere};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    cancel::{CancelToken, Cancelled},
+    formats::{
+        report::LoadReport,
+        shader_table::{ShaderEffectTable, SHADER_TABLE_PATH},
+    },
+    fs::{
+        source_asset_path,
+        version::{GameVersion, ParseQuirks},
+    },
+};
+
+use super::{
+    display_list::VertexDescriptor,
+    dl_container::{
+        read_containers, resolve_display_lists, DisplayListSource, MaterialDisplayList,
+        STREAM_FILE_EXTENSION,
+    },
+    lights::{mesh_lights, MeshLight},
+    loader::{MeshLoadError, MeshLoader},
+    material::{
+        GameTextures, ImageStore, MaterialAnimation, MaterialConverter, MaterialSource,
+        MaterialTexture, MeshTextures, TextureSource,
+    },
+    mesh_raw_old::create_bevy_mesh,
+    skeleton::{skeleton_bones, SkeletonBone},
+    submesh::{decode_material_mesh, DecodedVertexBuffer},
+};
+
+#[derive(Debug, Error)]
+pub enum ApeAssetError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Load(#[from] MeshLoadError),
+    #[error("meshes from {} releases aren't supported", .0.name())]
+    UnsupportedPlatform(Platform),
+    #[error(transparent)]
+    Cancelled(#[from] Cancelled),
+}
+
+/// Settings for loading an .ape asset
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApeLoadSettings {
+    /// Token checked between the loading steps, set by levels so their
+    /// meshes stop loading when the level load is cancelled
+    #[serde(skip)]
+    pub cancel: CancelToken,
+}
+
+/// Geometry of a single display list, drawn when its LOD is active
+#[derive(Debug, Clone)]
+pub struct ApeLodMesh {
+    pub lod_id: u8,
+    pub part_id: u8,
+    /// Index of the material drawing the display list
+    pub material: usize,
+    pub mesh: Handle<Mesh>,
+}
+
+/// Segment of the mesh, each segment is placed by the bones it lists
+#[derive(Debug, Clone)]
+pub struct ApeSegment {
+    /// Bounding sphere of the segment in model space
+    pub center: Vec3,
+    pub radius: f32,
+    /// Bones the vertices of the segment are weighted to
+    pub bones: Vec<usize>,
+}
+
+/// Mesh loaded from an .ape file
+#[derive(Asset, TypePath, Debug)]
+pub struct ApeAsset {
+    /// Name stored in the mesh header
+    pub name: String,
+    /// Mesh of each vertex buffer
+    #[dependency]
+    pub meshes: Vec<Handle<Mesh>>,
+    /// Converted mesh materials
+    #[dependency]
+    pub materials: Vec<Handle<StandardMaterial>>,
+    /// Fields each material was converted from, including its texture layers
+    pub material_sources: Vec<MaterialSource>,
+    /// Sphere bounding the verts of each material in model space
+    pub material_bounds: Vec<RawSphere>,
+    /// Texture animations of each material
+    pub material_animations: Vec<MaterialAnimation>,
+    pub segments: Vec<ApeSegment>,
+    /// Bone hierarchy, empty when the skeleton is invalid or missing
+    pub skeleton: Vec<SkeletonBone>,
+    /// Enabled lights of the mesh
+    pub lights: Vec<MeshLight>,
+    /// Display lists drawing the materials, including the streamed ones
+    pub display_lists: Vec<MaterialDisplayList>,
+    /// Distance each LOD switches in at, one for each LOD of the mesh
+    pub lod_distances: Vec<f32>,
+    /// Decoded display lists, empty when none of them could be decoded
+    pub lod_meshes: Vec<ApeLodMesh>,
+    /// Findings from loading the mesh
+    pub report: LoadReport,
+}
+
+/// Loader for .ape assets
+pub struct ApeAssetLoader {
+    shaders: ShaderEffectTable,
+    /// Version of the game data meshes are loaded from
+    version: GameVersion,
+    quirks: ParseQuirks,
+}
+
+impl FromWorld for ApeAssetLoader {
+    fn from_world(world: &mut World) -> Self {
+        let shaders = ShaderEffectTable::load(SHADER_TABLE_PATH.as_ref()).unwrap_or_else(|err| {
+            warn!("Failed to load shader table: {}", err);
+            ShaderEffectTable::default()
+        });
+        let version = world
+            .get_resource::<GameVersion>()
+            .cloned()
+            .unwrap_or_default();
+        Self {
+            shaders,
+            quirks: version.quirks(),
+            version,
+        }
+    }
+}
+
+/// Textures loaded from the game data through the asset server, texture
+/// translucency isn't known until the texture is loaded so is left unset.
+/// Decoded textures are added as labeled assets
+struct LoadContextTextures<'a, 'b> {
+    load_context: &'a mut LoadContext<'b>,
+}
+
+impl TextureSource for LoadContextTextures<'_, '_> {
+    fn texture(&mut self, name: &str) -> Option<MaterialTexture> {
+        let path = source_asset_path(
+            self.load_context.asset_path().source(),
+            &GameTextures::texture_path(name),
+        );
+        Some(MaterialTexture {
+            image: self.load_context.load::<Image>(path),
+            translucent: false,
+        })
+    }
+}
+
+impl ImageStore for LoadContextTextures<'_, '_> {
+    fn add_image(&mut self, name: &str, image: Image) -> Handle<Image> {
+        self.load_context
+            .add_labeled_asset(format!("Texture{}", name), image)
+    }
+}
+
+impl AssetLoader for ApeAssetLoader {
+    type Asset = ApeAsset;
+    type Settings = ApeLoadSettings;
+    type Error = ApeAssetError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        settings: &'a ApeLoadSettings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<ApeAsset, ApeAssetError>> {
+        Box::pin(async move {
+            match self.version.platform {
+                Some(platform) if !self.quirks.big_endian_meshes => {
+                    return Err(ApeAssetError::UnsupportedPlatform(platform))
+                }
+                _ => {}
+            }
+
+            let cancel = &settings.cancel;
+            cancel.check()?;
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            cancel.check()?;
+
+            let loaded = MeshLoader::from_bytes(bytes).recover_truncated().load()?;
+            let mut report = LoadReport::new(load_context.path().to_string_lossy());
+            loaded.report_truncations(&mut report);
+
+            let containers = read_containers(&loaded).unwrap_or_else(|err| {
+                report.warn(format!("display list containers: {}", err));
+                Vec::new()
+            });
+
+            // Stream file is only read when a display list is streamed from it
+            let stream = match containers.iter().any(|(_, value)| value.is_streaming()) {
+                true => {
+                    let path =
+                        AssetPath::from(load_context.path().with_extension(STREAM_FILE_EXTENSION))
+                            .with_source(load_context.asset_path().source().clone_owned());
+                    load_context.read_asset_bytes(path).await.ok()
+                }
+                false => None,
+            };
+
+            let (display_lists, failed) = resolve_display_lists(
+                &containers,
+                &DisplayListSource {
+                    file: loaded.bytes(),
+                    stream: stream.as_deref(),
+                },
+            );
+            for (material, err) in failed {
+                report.warn(format!("material {}: {}", material, err));
+            }
+
+            cancel.check()?;
+            let mesh = loaded.mesh();
+
+            let skeleton = skeleton_bones(loaded.bones()).unwrap_or_else(|err| {
+                report.warn(err.to_string());
+                Vec::new()
+            });
+
+            let segments = loaded
+                .segments()
+                .iter()
+                .map(|segment| ApeSegment {
+                    center: Vec3::from(&segment.bound_sphere.position),
+                    radius: segment.bound_sphere.radius,
+                    bones: segment
+                        .bone_mtx_indices()
+                        .iter()
+                        .map(|bone| *bone as usize)
+                        .collect(),
+                })
+                .collect();
+            let lights = mesh_lights(loaded.lights(), &skeleton);
+
+            let material_bounds = loaded
+                .materials()
+                .iter()
+                .map(|material| material.bound_sphere(mesh.bound_sphere.radius))
+                .collect();
+            let sources: Vec<MaterialSource> = loaded
+                .materials()
+                .iter()
+                .map(|material| MaterialSource::from_fmesh(&loaded, material, &mut report))
+                .collect();
+            let converted: Vec<_> = {
+                let textures = MeshTextures::new(&loaded, LoadContextTextures { load_context });
+                let mut converter = MaterialConverter::new(&self.shaders, textures);
+                let converted = sources
+                    .iter()
+                    .map(|source| converter.convert(source))
+                    .collect();
+                converter.into_textures().report_errors(&mut report);
+                converted
+            };
+            let mut material_animations = Vec::with_capacity(converted.len());
+            let materials = converted
+                .into_iter()
+                .enumerate()
+                .map(|(index, converted)| {
+                    material_animations.push(MaterialAnimation {
+                        flip: converted.flip,
+                        uv: converted.uv_animation,
+                    });
+                    load_context.add_labeled_asset(format!("Material{}", index), converted.material)
+                })
+                .collect();
+
+            let vertex_buffers = loaded.vertex_buffers();
+
+            let mut meshes = Vec::with_capacity(vertex_buffers.len());
+            let mut decodable = Vec::with_capacity(vertex_buffers.len());
+            for (index, buffer) in vertex_buffers.iter().enumerate() {
+                match create_bevy_mesh(buffer, &mut report) {
+                    Ok(value) => {
+                        decodable.push(DecodedVertexBuffer {
+                            index,
+                            descriptor: VertexDescriptor::from_vertex_buffer(buffer),
+                            vertex_format: buffer.vertex_format,
+                            mesh: value.clone(),
+                        });
+                        meshes.push(load_context.add_labeled_asset(format!("Mesh{}", index), value))
+                    }
+                    Err(err) => report.error(format!("vertex buffer {}: {}", index, err)),
+                }
+            }
+
+            cancel.check()?;
+            let mut lod_meshes = Vec::with_capacity(display_lists.len());
+            for (index, display_list) in display_lists.iter().enumerate() {
+                let Some(decoded) = decode_material_mesh(display_list, &decodable) else {
+                    report.warn(format!(
+                        "display list {} of material {} doesn't decode against any vertex buffer",
+                        index, display_list.material
+                    ));
+                    continue;
+                };
+                decoded.report_ambiguous(&mut report, index, display_list.material);
+
+                lod_meshes.push(ApeLodMesh {
+                    lod_id: display_list.lod_id,
+                    part_id: display_list.part_id,
+                    material: display_list.material,
+                    mesh: load_context
+                        .add_labeled_asset(format!("DisplayList{}", index), decoded.mesh),
+                });
+            }
+
+            Ok(ApeAsset {
+                name: mesh.name.to_string(),
+                meshes,
+                materials,
+                material_sources: sources,
+                material_bounds,
+                material_animations,
+                segments,
+                skeleton,
+                lights,
+                display_lists,
+                lod_distances: mesh.lod_distances().to_vec(),
+                lod_meshes,
+                report,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ape"]
+    }
+}
@@ -0,0 +1,103 @@
+//! Decoding of GameCube vertex colors referenced by index from display lists
+
+use super::mesh_raw_old::{GCColor, GXAttrType};
+
+impl GCColor {
+    /// Converts the color to normalized RGBA
+    pub fn to_rgba(&self) -> [f32; 4] {
+        [self.red, self.green, self.blue, self.alpha].map(|value| value as f32 / 255.0)
+    }
+}
+
+/// Color used for vertices referencing colors outside the palette
+const MISSING_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// Reads the color index of each vertex within a display list vertex stream
+/// where each vertex is `stride` bytes with its color index at `color_offset`
+pub fn read_color_indices(
+    stream: &[u8],
+    vertex_count: usize,
+    stride: usize,
+    color_offset: usize,
+    index_type: GXAttrType,
+) -> Option<Vec<u16>> {
+    (0..vertex_count)
+        .map(|vertex| {
+            let start = vertex * stride + color_offset;
+            index_type.read_index(stream.get(start..)?)
+        })
+        .collect()
+}
+
+/// Resolves color indices into per-vertex colors using the diffuse palette
+/// of the vertex buffer
+pub fn decode_indexed_colors(indices: &[u16], palette: &[GCColor]) -> Vec<[f32; 4]> {
+    indices
+        .iter()
+        .map(|index| {
+            palette
+                .get(*index as usize)
+                .map(GCColor::to_rgba)
+                .unwrap_or(MISSING_COLOR)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use binrw::BinRead;
+
+    use crate::formats::mesh::mesh_raw_old::{GCColor, GCVertexBuffer, GXAttrType};
+
+    use super::{decode_indexed_colors, read_color_indices};
+
+    #[test]
+    fn test_decode_index_widths() {
+        let palette = [
+            GCColor {
+                red: 255,
+                green: 0,
+                blue: 0,
+                alpha: 255,
+            },
+            GCColor {
+                red: 0,
+                green: 0,
+                blue: 255,
+                alpha: 255,
+            },
+        ];
+
+        // Vertices of a position index followed by the color index
+        let stream8 = [0x00, 0x01, 0x01, 0x00];
+        let indices = read_color_indices(&stream8, 2, 2, 1, GXAttrType::Index8).unwrap();
+        assert_eq!(indices, vec![1, 0]);
+
+        let stream16 = [0x00, 0x00, 0x00, 0x01, 0x00, 0x05];
+        let indices = read_color_indices(&stream16, 2, 3, 1, GXAttrType::Index16).unwrap();
+        assert_eq!(indices, vec![0, 5]);
+
+        let colors = decode_indexed_colors(&indices, &palette);
+        assert_eq!(colors[0], [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(colors[1], [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_attr_type_parse() {
+        assert_eq!(GXAttrType::try_from(2).unwrap(), GXAttrType::Index8);
+        assert_eq!(GXAttrType::try_from(9).unwrap_err().0, 9);
+
+        // Vertex buffer header with null pointers
+        let mut bytes = vec![0, 0, 0, 0, 4, 3, 12, 0, 0, 0, 2, 0];
+        bytes.extend_from_slice(&[0; 16]);
+        let buffer = GCVertexBuffer::read(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(buffer.pos_idx_type, GXAttrType::Index16);
+        assert_eq!(buffer.color_idx_type, GXAttrType::Index8);
+
+        // Unknown color index type is rejected while parsing
+        bytes[10] = 7;
+        assert!(GCVertexBuffer::read(&mut Cursor::new(&bytes)).is_err());
+    }
+}
@@ -0,0 +1,385 @@
+//! Interpreter for GX display lists, the command buffers the GameCube
+//! meshes draw their geometry with. Draw commands are decoded into a flat
+//! triangle list of position indices that can be used as the indices of a
+//! mesh built from the vertex buffer, register loads are skipped
+
+use bevy::render::{
+    mesh::{Indices, Mesh},
+    render_resource::PrimitiveTopology,
+};
+use thiserror::Error;
+
+use super::mesh_raw_old::{GCVertexBuffer, GXAttrType};
+
+/// Display list opcodes that aren't draws
+const GX_NOP: u8 = 0x00;
+const GX_LOAD_CP_REG: u8 = 0x08;
+const GX_LOAD_XF_REG: u8 = 0x10;
+const GX_LOAD_INDX_A: u8 = 0x20;
+const GX_LOAD_INDX_B: u8 = 0x28;
+const GX_LOAD_INDX_C: u8 = 0x30;
+const GX_LOAD_INDX_D: u8 = 0x38;
+const GX_CALL_DL: u8 = 0x40;
+const GX_INVAL_VTX_CACHE: u8 = 0x48;
+const GX_LOAD_BP_REG: u8 = 0x61;
+
+/// Draw opcodes, stored in the upper 5 bits with the vertex
+/// format index in the lower 3 bits
+const GX_DRAW_QUADS: u8 = 0x80;
+const GX_DRAW_TRIANGLES: u8 = 0x90;
+const GX_DRAW_TRIANGLE_STRIP: u8 = 0x98;
+const GX_DRAW_TRIANGLE_FAN: u8 = 0xA0;
+const GX_DRAW_LINES: u8 = 0xA8;
+const GX_DRAW_LINE_STRIP: u8 = 0xB0;
+const GX_DRAW_POINTS: u8 = 0xB8;
+
+#[derive(Debug, Error)]
+pub enum DisplayListError {
+    #[error("unknown display list opcode {opcode:#04x} at {offset:#x}")]
+    UnknownOpcode { opcode: u8, offset: usize },
+    #[error("display list command at {offset:#x} runs past the end of the list")]
+    Truncated { offset: usize },
+    #[error("vertex descriptor has no indexed position attribute")]
+    MissingPosition,
+}
+
+/// Vertex attributes in the order GX stores them within each vertex
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GXAttr {
+    /// Position and normal matrix index, always direct
+    PosMatrixIndex,
+    Position,
+    Normal,
+    Color0,
+    Color1,
+    TexCoord(u8),
+}
+
+/// Attribute present in each vertex of a draw command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub attr: GXAttr,
+    pub ty: GXAttrType,
+    /// Size in bytes of the value when stored directly in the display list
+    pub direct_size: usize,
+}
+
+/// Layout of the vertices within the draw commands
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VertexDescriptor {
+    pub attributes: Vec<VertexAttribute>,
+}
+
+impl VertexDescriptor {
+    /// Descriptor using the index types of the vertex buffer, only the
+    /// position and color index types are stored in the vertex buffer so
+    /// any further attributes need to be added to the descriptor
+    pub fn from_vertex_buffer(buffer: &GCVertexBuffer) -> Self {
+        let mut descriptor = Self::default();
+        descriptor.push(
+            GXAttr::Position,
+            buffer.pos_idx_type,
+            buffer.pos_type.size(),
+        );
+        descriptor.push(GXAttr::Color0, buffer.color_idx_type, 4);
+        descriptor
+    }
+
+    /// Adds an attribute, attributes that aren't present are skipped
+    pub fn push(&mut self, attr: GXAttr, ty: GXAttrType, direct_size: usize) {
+        if ty == GXAttrType::None {
+            return;
+        }
+
+        self.attributes.push(VertexAttribute {
+            attr,
+            ty,
+            direct_size,
+        });
+    }
+
+    /// Size in bytes of a single vertex in a draw command
+    pub fn vertex_size(&self) -> usize {
+        self.attributes
+            .iter()
+            .map(|attribute| match attribute.ty {
+                GXAttrType::Direct => attribute.direct_size,
+                ty => ty.index_size(),
+            })
+            .sum()
+    }
+
+    /// Offset of the position index within a vertex
+    fn position_offset(&self) -> Result<(usize, GXAttrType), DisplayListError> {
+        let mut offset = 0;
+        for attribute in &self.attributes {
+            if attribute.attr == GXAttr::Position {
+                return match attribute.ty {
+                    GXAttrType::Index8 | GXAttrType::Index16 => Ok((offset, attribute.ty)),
+                    _ => Err(DisplayListError::MissingPosition),
+                };
+            }
+
+            offset += match attribute.ty {
+                GXAttrType::Direct => attribute.direct_size,
+                ty => ty.index_size(),
+            };
+        }
+
+        Err(DisplayListError::MissingPosition)
+    }
+}
+
+/// Converts the vertices of a draw into triangle list indices, lines
+/// and points have no triangles and are dropped
+fn push_triangles(out: &mut Vec<u16>, primitive: u8, vertices: &[u16]) {
+    match primitive {
+        GX_DRAW_TRIANGLES => {
+            out.extend(vertices.chunks_exact(3).flatten());
+        }
+        GX_DRAW_TRIANGLE_STRIP => {
+            for (index, value) in vertices.windows(3).enumerate() {
+                let [a, b, c] = [value[0], value[1], value[2]];
+                // Repeated indices are the degenerate joins between strips
+                if a == b || b == c || a == c {
+                    continue;
+                }
+
+                // Every other triangle of a strip has reversed winding
+                match index % 2 {
+                    0 => out.extend([a, b, c]),
+                    _ => out.extend([b, a, c]),
+                }
+            }
+        }
+        GX_DRAW_TRIANGLE_FAN => {
+            if let Some((first, rest)) = vertices.split_first() {
+                for value in rest.windows(2) {
+                    out.extend([*first, value[0], value[1]]);
+                }
+            }
+        }
+        GX_DRAW_QUADS => {
+            for quad in vertices.chunks_exact(4) {
+                out.extend([quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Walks the display list decoding every draw into a triangle list of
+/// position indices. The triangles keep the winding of the GameCube assets
+pub fn decode_display_list(
+    bytes: &[u8],
+    descriptor: &VertexDescriptor,
+) -> Result<Vec<u16>, DisplayListError> {
+    let mut out = Vec::new();
+    walk_draws(bytes, descriptor, |opcode, vertices| {
+        push_triangles(&mut out, opcode & 0xF8, vertices)
+    })?;
+    Ok(out)
+}
+
+/// Vertex format indices used by the draws of the display list, sorted
+/// and without duplicates
+pub fn draw_vertex_formats(
+    bytes: &[u8],
+    descriptor: &VertexDescriptor,
+) -> Result<Vec<u8>, DisplayListError> {
+    let mut formats = Vec::new();
+    walk_draws(bytes, descriptor, |opcode, _| formats.push(opcode & 0x07))?;
+    formats.sort_unstable();
+    formats.dedup();
+    Ok(formats)
+}
+
+/// Walks the commands of the display list calling `draw` with the opcode
+/// and position indices of each draw
+fn walk_draws(
+    bytes: &[u8],
+    descriptor: &VertexDescriptor,
+    mut draw: impl FnMut(u8, &[u16]),
+) -> Result<(), DisplayListError> {
+    let (position_offset, position_type) = descriptor.position_offset()?;
+    let vertex_size = descriptor.vertex_size();
+
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let command = offset;
+        let opcode = bytes[offset];
+        offset += 1;
+
+        // Reads `length` bytes of the command starting at `start`
+        let take = |start: usize, length: usize| {
+            bytes
+                .get(start..start + length)
+                .ok_or(DisplayListError::Truncated { offset: command })
+        };
+
+        // Length of the arguments of non draw commands
+        let arguments = match opcode {
+            GX_NOP | GX_INVAL_VTX_CACHE => Some(0),
+            GX_LOAD_CP_REG => Some(5),
+            GX_LOAD_XF_REG => {
+                let header = take(offset, 4)?;
+                // Count is stored as the number of values minus one
+                let count = u16::from_be_bytes([header[0], header[1]]) as usize + 1;
+                Some(4 + count * 4)
+            }
+            GX_LOAD_INDX_A | GX_LOAD_INDX_B | GX_LOAD_INDX_C | GX_LOAD_INDX_D => Some(4),
+            GX_CALL_DL => Some(8),
+            GX_LOAD_BP_REG => Some(4),
+            _ => None,
+        };
+
+        match arguments {
+            Some(length) => {
+                take(offset, length)?;
+                offset += length;
+            }
+            None => {
+                let primitive = opcode & 0xF8;
+                if !matches!(
+                    primitive,
+                    GX_DRAW_QUADS
+                        | GX_DRAW_TRIANGLES
+                        | GX_DRAW_TRIANGLE_STRIP
+                        | GX_DRAW_TRIANGLE_FAN
+                        | GX_DRAW_LINES
+                        | GX_DRAW_LINE_STRIP
+                        | GX_DRAW_POINTS
+                ) {
+                    return Err(DisplayListError::UnknownOpcode {
+                        opcode,
+                        offset: command,
+                    });
+                }
+
+                let count = take(offset, 2)?;
+                let count = u16::from_be_bytes([count[0], count[1]]) as usize;
+                offset += 2;
+
+                let data = take(offset, count * vertex_size)?;
+                offset += data.len();
+
+                let vertices: Vec<u16> = data
+                    .chunks_exact(vertex_size.max(1))
+                    .filter_map(|vertex| position_type.read_index(&vertex[position_offset..]))
+                    .collect();
+
+                draw(opcode, &vertices);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes the display list into indices for a triangle list mesh
+pub fn display_list_indices(
+    bytes: &[u8],
+    descriptor: &VertexDescriptor,
+) -> Result<Indices, DisplayListError> {
+    decode_display_list(bytes, descriptor).map(Indices::U16)
+}
+
+/// Builds a triangle list mesh drawing the decoded indices with the vertex
+/// attributes of the mesh built from the vertex buffer
+pub fn indexed_mesh(vertex_buffer: &Mesh, indices: Vec<u16>) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    for attribute in [
+        Mesh::ATTRIBUTE_POSITION,
+        Mesh::ATTRIBUTE_NORMAL,
+        Mesh::ATTRIBUTE_UV_0,
+        Mesh::ATTRIBUTE_COLOR,
+    ] {
+        if let Some(values) = vertex_buffer.attribute(attribute.id) {
+            mesh.insert_attribute(attribute, values.clone());
+        }
+    }
+    mesh.set_indices(Some(Indices::U16(indices)));
+    mesh
+}
+
+#[cfg(test)]
+mod test {
+    use crate::formats::mesh::mesh_raw_old::GXAttrType;
+
+    use bevy::render::{
+        mesh::{Indices, Mesh},
+        render_resource::PrimitiveTopology,
+    };
+
+    use super::{
+        decode_display_list, draw_vertex_formats, indexed_mesh, DisplayListError, GXAttr,
+        VertexDescriptor,
+    };
+
+    /// Positions as 16 bit indices followed by direct colors
+    fn descriptor() -> VertexDescriptor {
+        let mut descriptor = VertexDescriptor::default();
+        descriptor.push(GXAttr::PosMatrixIndex, GXAttrType::Direct, 1);
+        descriptor.push(GXAttr::Position, GXAttrType::Index16, 6);
+        descriptor.push(GXAttr::Color0, GXAttrType::Direct, 4);
+        descriptor
+    }
+
+    fn draw(opcode: u8, positions: &[u16]) -> Vec<u8> {
+        let mut out = vec![opcode];
+        out.extend((positions.len() as u16).to_be_bytes());
+        for position in positions {
+            out.push(0);
+            out.extend(position.to_be_bytes());
+            out.extend([0xFF; 4]);
+        }
+        out
+    }
+
+    #[test]
+    fn test_decode_strip_and_list() {
+        let mut bytes = Vec::new();
+        // Register load before the draws is skipped
+        bytes.extend([0x08, 0x50, 0, 0, 0, 0]);
+        bytes.extend(draw(0x98, &[0, 1, 2, 3]));
+        bytes.extend(draw(0x90, &[4, 5, 6]));
+        // Padding at the end of the list
+        bytes.extend([0x00, 0x00]);
+
+        let indices = decode_display_list(&bytes, &descriptor()).unwrap();
+        assert_eq!(indices, vec![0, 1, 2, 2, 1, 3, 4, 5, 6]);
+
+        // Vertex format index is in the low bits of each draw
+        bytes.extend(draw(0x92, &[7, 8, 9]));
+        assert_eq!(draw_vertex_formats(&bytes, &descriptor()).unwrap(), [0, 2]);
+    }
+
+    #[test]
+    fn test_reject_invalid_lists() {
+        let mut bytes = draw(0x98, &[0, 1, 2]);
+        bytes.truncate(bytes.len() - 1);
+        assert!(matches!(
+            decode_display_list(&bytes, &descriptor()),
+            Err(DisplayListError::Truncated { offset: 0 })
+        ));
+
+        assert!(matches!(
+            decode_display_list(&[0x70], &descriptor()),
+            Err(DisplayListError::UnknownOpcode { opcode: 0x70, .. })
+        ));
+    }
+
+    #[test]
+    fn test_indexed_mesh() {
+        let vertex_buffer = Mesh::new(PrimitiveTopology::TriangleStrip)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0f32; 3]; 4])
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0f32; 2]; 4]);
+
+        let mesh = indexed_mesh(&vertex_buffer, vec![0, 1, 2, 2, 1, 3]);
+        assert_eq!(mesh.primitive_topology(), PrimitiveTopology::TriangleList);
+        assert!(mesh.attribute(Mesh::ATTRIBUTE_UV_0).is_some());
+        assert!(mesh.attribute(Mesh::ATTRIBUTE_NORMAL).is_none());
+        assert!(matches!(mesh.indices(), Some(Indices::U16(values)) if values.len() == 6));
+    }
+}
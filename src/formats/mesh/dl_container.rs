@@ -0,0 +1,212 @@
+//! Display list containers of the GameCube mesh materials. Each material
+//! points to an array of containers holding the display lists that draw it,
+//! one for each part and LOD. Containers flagged as streaming don't have
+//! their display list in the .ape, the buffer offset is into the companion
+//! stream file that shares the name of the mesh so the geometry of distant
+//! LODs can be loaded later by the game
+//!
+//! Streamed display lists are read from the stream file when it's provided,
+//! containers that can't be resolved are reported rather than dropped
+
+use std::io::{Cursor, Seek, SeekFrom};
+
+use binrw::{BinRead, BinResult};
+use bitflags::bitflags;
+use thiserror::Error;
+
+use super::loader::LoadedMesh;
+
+/// Extension of the companion file holding the streamed display lists
+pub const STREAM_FILE_EXTENSION: &str = "str";
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct DLContFlags: u8 {
+        /// Display list is stored in the stream file
+        const STREAMING = 0x01;
+    }
+}
+
+/// FGCDLCont_t - Display list drawing part of a material
+#[derive(Debug, Clone, BinRead)]
+#[br(big)]
+pub struct FGCDLCont {
+    #[br(map = DLContFlags::from_bits_retain)]
+    pub flags: DLContFlags,
+    pub part_id: u8,
+    pub lod_id: u8,
+    pub matrix_index: u8,
+    /// Size of the display list in bytes
+    pub size: u32,
+    /// Offset of the display list, into the stream file for streaming containers
+    pub buffer: u32,
+}
+
+impl FGCDLCont {
+    pub fn is_streaming(&self) -> bool {
+        self.flags.contains(DLContFlags::STREAMING)
+    }
+}
+
+/// Header of the GameCube platform data of a material
+#[derive(Debug, BinRead)]
+#[br(big)]
+struct GCMeshMaterial {
+    containers: u32,
+    #[br(pad_after = 2)]
+    container_count: u16,
+}
+
+#[derive(Debug, Error)]
+pub enum DLContError {
+    #[error("streamed display list at {offset:#x} needs the stream file which wasn't found")]
+    MissingStream { offset: u32 },
+    #[error("display list at {offset:#x} of {size} bytes is outside the {length} byte {file}")]
+    OutOfBounds {
+        offset: u32,
+        size: u32,
+        length: usize,
+        file: &'static str,
+    },
+}
+
+/// Display list resolved from its container
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialDisplayList {
+    pub material: usize,
+    pub part_id: u8,
+    pub lod_id: u8,
+    /// Whether the display list was read from the stream file
+    pub streamed: bool,
+    pub bytes: Vec<u8>,
+}
+
+/// Files the display lists are read from
+pub struct DisplayListSource<'a> {
+    /// Bytes of the .ape file
+    pub file: &'a [u8],
+    /// Bytes of the companion stream file if there is one
+    pub stream: Option<&'a [u8]>,
+}
+
+impl<'a> DisplayListSource<'a> {
+    /// Bytes of the display list held by the container
+    pub fn resolve(&self, container: &FGCDLCont) -> Result<&'a [u8], DLContError> {
+        let (bytes, file) = if container.is_streaming() {
+            let stream = self.stream.ok_or(DLContError::MissingStream {
+                offset: container.buffer,
+            })?;
+            (stream, "stream file")
+        } else {
+            (self.file, "mesh file")
+        };
+
+        let start = container.buffer as usize;
+        start
+            .checked_add(container.size as usize)
+            .and_then(|end| bytes.get(start..end))
+            .ok_or(DLContError::OutOfBounds {
+                offset: container.buffer,
+                size: container.size,
+                length: bytes.len(),
+                file,
+            })
+    }
+}
+
+/// Reads the display list containers of every material in the mesh, the
+/// containers are returned by the index of their material
+pub fn read_containers(mesh: &LoadedMesh) -> BinResult<Vec<(usize, FGCDLCont)>> {
+    let mut cursor = Cursor::new(mesh.bytes());
+    let mut out = Vec::new();
+
+    for (index, material) in mesh.materials().iter().enumerate() {
+        let Some(offset) = material.platform_data.offset() else {
+            continue;
+        };
+
+        cursor.seek(SeekFrom::Start(offset as u64))?;
+        let header = GCMeshMaterial::read(&mut cursor)?;
+        if header.containers == 0 {
+            continue;
+        }
+
+        cursor.seek(SeekFrom::Start(header.containers as u64))?;
+        for _ in 0..header.container_count {
+            out.push((index, FGCDLCont::read(&mut cursor)?));
+        }
+    }
+
+    Ok(out)
+}
+
+/// Resolves the display lists of the containers, containers that can't be
+/// resolved are returned along with the reason
+pub fn resolve_display_lists(
+    containers: &[(usize, FGCDLCont)],
+    source: &DisplayListSource,
+) -> (Vec<MaterialDisplayList>, Vec<(usize, DLContError)>) {
+    let mut resolved = Vec::new();
+    let mut failed = Vec::new();
+
+    for (material, container) in containers {
+        match source.resolve(container) {
+            Ok(bytes) => resolved.push(MaterialDisplayList {
+                material: *material,
+                part_id: container.part_id,
+                lod_id: container.lod_id,
+                streamed: container.is_streaming(),
+                bytes: bytes.to_vec(),
+            }),
+            Err(err) => failed.push((*material, err)),
+        }
+    }
+
+    (resolved, failed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DLContError, DLContFlags, DisplayListSource, FGCDLCont};
+
+    fn container(flags: DLContFlags, buffer: u32, size: u32) -> FGCDLCont {
+        FGCDLCont {
+            flags,
+            part_id: 0,
+            lod_id: 1,
+            matrix_index: 0,
+            size,
+            buffer,
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let file = [0u8, 1, 2, 3];
+        let stream = [9u8, 8, 7, 6, 5];
+        let inline = container(DLContFlags::empty(), 1, 2);
+        let streamed = container(DLContFlags::STREAMING, 2, 3);
+
+        let source = DisplayListSource {
+            file: &file,
+            stream: Some(&stream),
+        };
+        assert_eq!(source.resolve(&inline).unwrap(), &[1, 2]);
+        assert_eq!(source.resolve(&streamed).unwrap(), &[7, 6, 5]);
+
+        // Stream file isn't needed for display lists within the mesh
+        let source = DisplayListSource {
+            file: &file,
+            stream: None,
+        };
+        assert!(source.resolve(&inline).is_ok());
+        assert!(matches!(
+            source.resolve(&streamed),
+            Err(DLContError::MissingStream { offset: 2 })
+        ));
+        assert!(matches!(
+            source.resolve(&container(DLContFlags::empty(), 3, 4)),
+            Err(DLContError::OutOfBounds { length: 4, .. })
+        ));
+    }
+}
@@ -0,0 +1,224 @@
+//! Builds GameCube mesh files for the tests. Each mesh has a single
+//! material sampling a texture layer whose textures are stored in the file,
+//! [textured_quad] also has the geometry of a quad and is the mesh stored
+//! with the validation cases ([VALIDATION_QUAD])
+
+use openglitch_formats::{
+    mesh::{FMeshMaterial, FMeshTexLayerID},
+    texture::CFTexInst,
+};
+
+use crate::formats::texture::gx::GXTexFmt;
+
+use super::{loader::MESH_HEADER_SIZE, textures::GCTexData};
+
+/// Path of the [textured_quad] stored with the validation cases
+pub const VALIDATION_QUAD: &str = "assets/validation/quad.ape";
+
+const MESH_BOUND_SPHERE_RADIUS: usize = 16;
+const MESH_TEX_LAYER_ID_COUNT: usize = 64;
+const MESH_MATERIAL_COUNT: usize = 68;
+const MESH_MATERIAL_ARRAY: usize = 120;
+const MESH_TEX_LAYER_ARRAY: usize = 128;
+const MESH_MESH_IS: usize = 132;
+const MATERIAL_PART_ID_MASK: usize = 12;
+const MATERIAL_PLATFORM_DATA: usize = 16;
+const MATERIAL_LOD_MASK: usize = 20;
+const MATERIAL_TEX_LAYER_ID_INDEX: usize = 24;
+const MATERIAL_COMPRESSED_RADIUS: usize = 36;
+const MATERIAL_TINT: usize = 44;
+const TEX_LAYER_FLIP_PAGE_COUNT: usize = 2;
+const TEX_LAYER_FLIP_PALETTE: usize = 4;
+const TEX_INFO_LOD_COUNT: usize = 23;
+const TEX_INFO_TEXELS_ACROSS: usize = 28;
+const TEX_INFO_TEXELS_DOWN: usize = 30;
+const TEX_DEF_TEX_DATA: usize = 32;
+const TEX_DATA_LOD_COUNT: usize = 45;
+const TEX_DATA_GX_FMT: usize = 52;
+const TEX_DATA_TEXTURE_BYTES: usize = 56;
+const TEX_DATA_IMAGE_DATA: usize = 60;
+const GC_MATERIAL_SIZE: usize = 8;
+const DL_CONTAINER_SIZE: usize = 12;
+const GC_MESH_SIZE: usize = 32;
+const GC_MESH_VERTEX_BUFFER_COUNT: usize = 21;
+const GC_MESH_VERTEX_BUFFERS: usize = 24;
+const GC_VERTEX_BUFFER_SIZE: usize = 28;
+
+/// GX attribute type of 16 bit indices (GX_INDEX16)
+const GX_INDEX16: u8 = 3;
+/// Position type of 32 bit float positions
+const POS_TYPE_F32: u8 = 4;
+/// Triangle strip draw using vertex format 0
+const GX_DRAW_TRIANGLE_STRIP: u8 = 0x98;
+
+/// Opaque white RGB5A3 texels of a 4x4 texture, a single tile
+pub const FIXTURE_TEXELS: [u8; 32] = [0xFF; 32];
+
+/// Name of the texture of the [textured_quad]
+pub const QUAD_TEXTURE_NAME: &str = "validation_check";
+
+fn put_u16(bytes: &mut [u8], offset: usize, value: u16) {
+    bytes[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+fn put_u32(bytes: &mut [u8], offset: usize, value: usize) {
+    bytes[offset..offset + 4].copy_from_slice(&(value as u32).to_be_bytes());
+}
+
+fn put_f32s(bytes: &mut [u8], offset: usize, values: &[f32]) {
+    for (index, value) in values.iter().enumerate() {
+        bytes[offset + index * 4..][..4].copy_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Appends zeroed space 4 byte aligned like the structures of the files,
+/// returning its offset
+fn reserve(bytes: &mut Vec<u8>, size: usize) -> usize {
+    let offset = bytes.len().next_multiple_of(4);
+    bytes.resize(offset + size, 0);
+    offset
+}
+
+/// Header of a mesh with a single material, returning the header and the
+/// offset of the material
+fn mesh_header(name: &str) -> (Vec<u8>, usize) {
+    let mut bytes = vec![0; MESH_HEADER_SIZE];
+    bytes[..name.len()].copy_from_slice(name.as_bytes());
+
+    let material = reserve(&mut bytes, FMeshMaterial::SIZE);
+    bytes[material + MATERIAL_TEX_LAYER_ID_INDEX..][..4].copy_from_slice(&[0, 255, 255, 255]);
+
+    bytes[MESH_MATERIAL_COUNT] = 1;
+    put_u32(&mut bytes, MESH_MATERIAL_ARRAY, material);
+    (bytes, material)
+}
+
+/// Writes a texture layer flipping between the named 4x4 textures which
+/// all store the texels, the layer is the only one of the mesh
+fn write_tex_layer(bytes: &mut Vec<u8>, pages: &[&str], texels: &[u8]) {
+    let flip_palette = reserve(bytes, pages.len() * 4);
+    for (index, name) in pages.iter().enumerate() {
+        let image_data = reserve(bytes, texels.len());
+        bytes[image_data..].copy_from_slice(texels);
+
+        // Definition is embedded at the start of the texture data
+        let tex_def = reserve(bytes, GCTexData::SIZE);
+        bytes[tex_def..tex_def + name.len()].copy_from_slice(name.as_bytes());
+        bytes[tex_def + TEX_INFO_LOD_COUNT] = 1;
+        put_u16(bytes, tex_def + TEX_INFO_TEXELS_ACROSS, 4);
+        put_u16(bytes, tex_def + TEX_INFO_TEXELS_DOWN, 4);
+        put_u32(bytes, tex_def + TEX_DEF_TEX_DATA, tex_def);
+        bytes[tex_def + TEX_DATA_LOD_COUNT] = 1;
+        put_u32(bytes, tex_def + TEX_DATA_GX_FMT, GXTexFmt::RGB5A3 as usize);
+        put_u32(bytes, tex_def + TEX_DATA_TEXTURE_BYTES, texels.len());
+        put_u32(bytes, tex_def + TEX_DATA_IMAGE_DATA, image_data);
+
+        let tex_inst = reserve(bytes, CFTexInst::SIZE);
+        put_u32(bytes, tex_inst, tex_def);
+        put_u32(bytes, flip_palette + index * 4, tex_inst);
+    }
+
+    let tex_layer = reserve(bytes, FMeshTexLayerID::SIZE);
+    bytes[tex_layer + TEX_LAYER_FLIP_PAGE_COUNT] = pages.len() as u8;
+    put_u32(bytes, tex_layer + TEX_LAYER_FLIP_PALETTE, flip_palette);
+
+    bytes[MESH_TEX_LAYER_ID_COUNT] = 1;
+    put_u32(bytes, MESH_TEX_LAYER_ARRAY, tex_layer);
+}
+
+/// Mesh with one material using a texture layer flipping between the
+/// named 4x4 textures, each texture stores [FIXTURE_TEXELS] in the file
+pub fn textured_mesh(pages: &[&str]) -> Vec<u8> {
+    let (mut bytes, _) = mesh_header("test");
+    write_tex_layer(&mut bytes, pages, &FIXTURE_TEXELS);
+    bytes
+}
+
+/// RGB5A3 texels of a 4x4 checker of 2x2 white and grey squares
+pub fn checker_texels() -> Vec<u8> {
+    // Opaque RGB555 texels have the top bit set
+    const WHITE: u16 = 0xFFFF;
+    const GREY: u16 = 0x8000 | (8 << 10) | (8 << 5) | 8;
+
+    (0..16)
+        .flat_map(|index| {
+            let (x, y) = (index % 4, index / 4);
+            match (x / 2 + y / 2) % 2 {
+                0 => WHITE.to_be_bytes(),
+                _ => GREY.to_be_bytes(),
+            }
+        })
+        .collect()
+}
+
+/// Quad two units across facing +Z drawn by a single triangle strip, textured
+/// with the [checker_texels] named [QUAD_TEXTURE_NAME]
+pub fn textured_quad() -> Vec<u8> {
+    let (mut bytes, material) = mesh_header("validation_quad");
+    put_f32s(&mut bytes, MESH_BOUND_SPHERE_RADIUS, &[2.0f32.sqrt()]);
+
+    put_u32(&mut bytes, material + MATERIAL_PART_ID_MASK, 1);
+    bytes[material + MATERIAL_LOD_MASK] = 1;
+    bytes[material + MATERIAL_COMPRESSED_RADIUS] = 255;
+    put_f32s(&mut bytes, material + MATERIAL_TINT, &[1.0; 3]);
+
+    // Display list indexing the positions of the vertex buffer
+    let strip = [0u16, 1, 2, 3];
+    let mut display_list = vec![GX_DRAW_TRIANGLE_STRIP];
+    display_list.extend((strip.len() as u16).to_be_bytes());
+    display_list.extend(strip.iter().flat_map(|index| index.to_be_bytes()));
+
+    let platform = reserve(&mut bytes, GC_MATERIAL_SIZE);
+    let container = reserve(&mut bytes, DL_CONTAINER_SIZE);
+    let buffer = reserve(&mut bytes, display_list.len());
+    bytes[buffer..].copy_from_slice(&display_list);
+    put_u32(&mut bytes, material + MATERIAL_PLATFORM_DATA, platform);
+    put_u32(&mut bytes, platform, container);
+    put_u16(&mut bytes, platform + 4, 1);
+    put_u32(&mut bytes, container + 4, display_list.len());
+    put_u32(&mut bytes, container + 8, buffer);
+
+    write_tex_layer(&mut bytes, &[QUAD_TEXTURE_NAME], &checker_texels());
+
+    let mesh_is = reserve(&mut bytes, GC_MESH_SIZE);
+    let vertex_buffer = reserve(&mut bytes, GC_VERTEX_BUFFER_SIZE);
+    bytes[mesh_is + GC_MESH_VERTEX_BUFFER_COUNT] = 1;
+    put_u32(&mut bytes, mesh_is + GC_MESH_VERTEX_BUFFERS, vertex_buffer);
+    put_u32(&mut bytes, MESH_MESH_IS, mesh_is);
+
+    let corners = [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0], [1.0, 1.0]];
+    let position = reserve(&mut bytes, corners.len() * 12);
+    for (index, [x, y]) in corners.iter().enumerate() {
+        put_f32s(&mut bytes, position + index * 12, &[*x, *y, 0.0]);
+    }
+
+    // Texture coordinates have 12 fractional bits with T pointing down
+    let st = reserve(&mut bytes, corners.len() * 4);
+    for (index, [x, y]) in corners.iter().enumerate() {
+        put_u16(
+            &mut bytes,
+            st + index * 4,
+            if *x > 0.0 { 0x1000 } else { 0 },
+        );
+        put_u16(
+            &mut bytes,
+            st + index * 4 + 2,
+            if *y > 0.0 { 0 } else { 0x1000 },
+        );
+    }
+
+    // Normals have 14 fractional bits
+    let normals = reserve(&mut bytes, corners.len() * 6);
+    for index in 0..corners.len() {
+        put_u16(&mut bytes, normals + index * 6 + 4, 0x4000);
+    }
+
+    put_u16(&mut bytes, vertex_buffer + 2, corners.len() as u16);
+    bytes[vertex_buffer + 4] = POS_TYPE_F32;
+    bytes[vertex_buffer + 5] = GX_INDEX16;
+    bytes[vertex_buffer + 6] = 12;
+    put_u32(&mut bytes, vertex_buffer + 12, position);
+    put_u32(&mut bytes, vertex_buffer + 20, st);
+    put_u32(&mut bytes, vertex_buffer + 24, normals);
+    bytes
+}
@@ -0,0 +1,231 @@
+//! Conversion of the lights stored in a mesh. Lights are placed in model
+//! space, lights attached to a bone are converted into the space of the
+//! bone so they follow it once spawned as a child of the bone. Disabled
+//! lights are skipped
+
+use bevy::{
+    math::{Mat4, Vec3},
+    render::color::Color,
+    transform::components::Transform,
+};
+use openglitch_formats::mesh::{FMeshLight, LightType, MeshLightFlags};
+
+use super::skeleton::SkeletonBone;
+
+/// Kind of mesh light along with the values specific to the kind
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MeshLightKind {
+    Point {
+        range: f32,
+    },
+    /// Spot light with the half angles of its cones in radians
+    Spot {
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+    Directional,
+    /// Light added to everything the mesh lights, the renderer only has a
+    /// single global ambient light so these aren't spawned
+    Ambient,
+}
+
+/// Light converted from a mesh
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshLight {
+    pub name: String,
+    pub kind: MeshLightKind,
+    /// Color of the light scaled by its intensity
+    pub color: Color,
+    /// Index of the color motif tinting the light, zero for none
+    pub motif: u32,
+    /// Placement in model space, or in the space of the parent bone
+    pub transform: Transform,
+    /// Index of the bone the light follows
+    pub parent_bone: Option<usize>,
+    pub cast_shadows: bool,
+}
+
+impl MeshLight {
+    /// Converts the light, none when the light is disabled or of an
+    /// unknown type
+    pub fn from_fmesh(light: &FMeshLight, skeleton: &[SkeletonBone]) -> Option<MeshLight> {
+        if !light.flags.contains(MeshLightFlags::ENABLE) {
+            return None;
+        }
+
+        let range = light.influence.radius;
+        let kind = match LightType::try_from(light.light_type).ok()? {
+            LightType::Omni => MeshLightKind::Point { range },
+            // The spot angles are stored as the full angle of each cone
+            LightType::Spot => MeshLightKind::Spot {
+                range,
+                inner_angle: light.spot_inner_radians * 0.5,
+                outer_angle: light.spot_outer_radians * 0.5,
+            },
+            LightType::Dir => MeshLightKind::Directional,
+            LightType::Ambient => MeshLightKind::Ambient,
+        };
+
+        // Lights shine along the front of their orientation while bevy
+        // lights shine along their forward direction
+        let orientation = Mat4::from(&light.orientation);
+        let front = orientation.z_axis.truncate().try_normalize();
+        let up = orientation
+            .y_axis
+            .truncate()
+            .try_normalize()
+            .unwrap_or(Vec3::Y);
+        let position = Vec3::from(&light.influence.position);
+        let mut transform = Transform::from_translation(position);
+        if let Some(front) = front {
+            transform.look_to(front, up);
+        }
+
+        let parent_bone = (light.flags.contains(MeshLightFlags::LIGHT_ATTACHED)
+            && light.parent_bone_index >= 0)
+            .then_some(light.parent_bone_index as usize)
+            .filter(|index| *index < skeleton.len());
+        if let Some(bone) = parent_bone {
+            let model_to_bone = skeleton[bone].bone_to_model.inverse();
+            transform = Transform::from_matrix(model_to_bone * transform.compute_matrix());
+        }
+
+        let color = &light.motif.color;
+        let intensity = light.intensity;
+
+        Some(MeshLight {
+            name: light.name.as_string(),
+            kind,
+            color: Color::rgb(
+                color.red * intensity,
+                color.green * intensity,
+                color.blue * intensity,
+            ),
+            motif: light.motif.modif_index,
+            transform,
+            parent_bone,
+            cast_shadows: light.flags.contains(MeshLightFlags::CAST_SHADOWS),
+        })
+    }
+}
+
+/// Converts the enabled lights of a mesh
+pub fn mesh_lights(lights: &[FMeshLight], skeleton: &[SkeletonBone]) -> Vec<MeshLight> {
+    lights
+        .iter()
+        .filter_map(|light| MeshLight::from_fmesh(light, skeleton))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{
+        math::{Mat4, Vec3},
+        render::color::Color,
+    };
+
+    use openglitch_formats::mesh::{FMeshLight, LightType, MeshLightFlags};
+
+    use crate::formats::{
+        mesh::skeleton::SkeletonBone,
+        types::{RawColorMotif, RawColorRGBA, RawMatrix4x3f, RawSphere, RawVec3f},
+    };
+
+    use super::{mesh_lights, MeshLightKind};
+
+    fn light(light_type: LightType, flags: MeshLightFlags) -> FMeshLight {
+        // Right, up and front rows pointing the light down
+        let orientation = RawMatrix4x3f {
+            matrix: [
+                [1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0],
+                [0.0, -1.0, 0.0],
+                [0.0, 0.0, 0.0],
+            ],
+        };
+
+        FMeshLight {
+            name: Default::default(),
+            per_pixel_tex_name: Default::default(),
+            corona_tex_name: Default::default(),
+            flags,
+            light_id: 0,
+            light_type: light_type as u8,
+            parent_bone_index: 0,
+            intensity: 0.5,
+            motif: RawColorMotif {
+                color: RawColorRGBA {
+                    red: 1.0,
+                    green: 1.0,
+                    blue: 0.0,
+                    alpha: 1.0,
+                },
+                modif_index: 2,
+            },
+            influence: RawSphere {
+                radius: 10.0,
+                position: RawVec3f {
+                    x: 0.0,
+                    y: 4.0,
+                    z: 0.0,
+                },
+            },
+            orientation,
+            spot_inner_radians: 0.5,
+            spot_outer_radians: 1.0,
+            corona_scale: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_convert_lights() {
+        let lights = [
+            light(LightType::Spot, MeshLightFlags::ENABLE),
+            light(LightType::Omni, MeshLightFlags::NONE),
+        ];
+        let converted = mesh_lights(&lights, &[]);
+
+        assert_eq!(converted.len(), 1);
+        let spot = &converted[0];
+        assert_eq!(
+            spot.kind,
+            MeshLightKind::Spot {
+                range: 10.0,
+                inner_angle: 0.25,
+                outer_angle: 0.5
+            }
+        );
+        assert_eq!(spot.color, Color::rgb(0.5, 0.5, 0.0));
+        assert_eq!(spot.motif, 2);
+        assert_eq!(spot.parent_bone, None);
+        assert!(spot.transform.forward().abs_diff_eq(Vec3::NEG_Y, 1e-5));
+    }
+
+    #[test]
+    fn test_attached_light() {
+        let bone = SkeletonBone {
+            name: "head".to_string(),
+            parent: None,
+            local: Mat4::IDENTITY,
+            bone_to_model: Mat4::from_translation(Vec3::new(0.0, 3.0, 0.0)),
+            skinned: false,
+        };
+        let lights = [light(
+            LightType::Omni,
+            MeshLightFlags::ENABLE | MeshLightFlags::LIGHT_ATTACHED,
+        )];
+
+        let converted = mesh_lights(&lights, &[bone]);
+        assert_eq!(converted[0].parent_bone, Some(0));
+        assert!(converted[0]
+            .transform
+            .translation
+            .abs_diff_eq(Vec3::Y, 1e-5));
+
+        // Lights attached to missing bones stay in model space
+        let converted = mesh_lights(&lights, &[]);
+        assert_eq!(converted[0].parent_bone, None);
+        assert_eq!(converted[0].transform.translation, Vec3::new(0.0, 4.0, 0.0));
+    }
+}
@@ -0,0 +1,696 @@
+//! Safe entry point for loading GameCube .ape meshes from a path or a
+//! byte buffer. The offsets in the header are checked against the file
+//! before any of them are followed so corrupt files are reported by the
+//! field that's wrong. The offsets nested in the materials, display list
+//! containers and vertex buffers are checked in the same way, along with
+//! the size of what they point to, before the nested structures are read
+//!
+//! Truncated files can be loaded with [MeshLoader::recover_truncated] which
+//! reads any offset past the end of the file as null, the loaded mesh lists
+//! what was dropped so it can be reported as partial
+
+use std::{
+    io::{self, Cursor},
+    path::{Path, PathBuf},
+};
+
+use binrw::{BinRead, BinResult};
+use openglitch_formats::{
+    mesh::{FMesh, FMeshBone, FMeshLight, FMeshMaterial, FMeshSegment, FMeshTexLayerID},
+    types::PtrOffset,
+};
+use thiserror::Error;
+
+use crate::formats::{
+    report::LoadReport,
+    types::{recover_truncated, Truncation},
+};
+
+use super::{
+    dl_container::DLContFlags,
+    mesh_raw_old::{GCMesh, GCPosType, GCVertexBuffer, GCVertexBufferFlags},
+};
+
+/// Size of the [FMesh] header at the start of the file
+pub const MESH_HEADER_SIZE: usize = FMesh::SIZE;
+
+/// Sizes and field positions of the nested GameCube structures
+const GC_MATERIAL_SIZE: usize = 8;
+const DL_CONTAINER_SIZE: usize = 12;
+const GC_MESH_SIZE: usize = 32;
+const GC_MESH_VERTEX_BUFFER_COUNT: usize = 21;
+const GC_MESH_VERTEX_BUFFERS: usize = 24;
+const GC_MESH_SKIN: usize = 28;
+const GC_VERTEX_BUFFER_SIZE: usize = 28;
+const GC_MESH_SKIN_SIZE: usize = 24;
+
+/// Offsets in the header along with their position in the header
+const HEADER_OFFSETS: [(&str, usize); 8] = [
+    ("segment_array", 104),
+    ("bone_array", 108),
+    ("light_array", 112),
+    ("skeleton_index_array", 116),
+    ("material_array", 120),
+    ("collision_tree", 124),
+    ("tex_layer_array", 128),
+    ("mesh_is", 132),
+];
+
+#[derive(Debug, Error)]
+pub enum MeshLoadError {
+    #[error("file is {0} bytes, smaller than the {MESH_HEADER_SIZE} byte mesh header")]
+    TooSmall(usize),
+    #[error("{field} offset {offset:#x} is outside the {length} byte file")]
+    OffsetOutOfBounds {
+        field: &'static str,
+        offset: u32,
+        length: usize,
+    },
+    #[error("{field} offset {offset:#x} is not 4 byte aligned")]
+    MisalignedOffset { field: &'static str, offset: u32 },
+    #[error("{field} of {size} bytes at {offset:#x} runs past the end of the {length} byte file")]
+    RegionOutOfBounds {
+        field: &'static str,
+        offset: u32,
+        size: usize,
+        length: usize,
+    },
+    #[error(transparent)]
+    Parse(#[from] binrw::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Loader for GameCube meshes
+pub struct MeshLoader {
+    bytes: Vec<u8>,
+    path: Option<PathBuf>,
+    /// Offsets past the end of the file are read as null
+    recover: bool,
+}
+
+impl MeshLoader {
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            path: None,
+            recover: false,
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, MeshLoadError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        Ok(Self {
+            bytes,
+            path: Some(path.to_path_buf()),
+            recover: false,
+        })
+    }
+
+    /// Recovers what can be read from truncated files instead of failing,
+    /// offsets past the end of the file are read as null
+    pub fn recover_truncated(mut self) -> Self {
+        self.recover = true;
+        self
+    }
+
+    /// Offset stored at a position in the header
+    fn header_offset(&self, position: usize) -> u32 {
+        u32::from_be_bytes(
+            self.bytes[position..position + 4]
+                .try_into()
+                .expect("Offset slice is 4 bytes"),
+        )
+    }
+
+    /// Value at a position that has already been checked to be in the file
+    fn read_u16(&self, position: usize) -> usize {
+        u16::from_be_bytes([self.bytes[position], self.bytes[position + 1]]) as usize
+    }
+
+    /// Checks the `size` bytes at a nested offset are within the file,
+    /// returning whether the value can be followed. Structures must be 4
+    /// byte aligned like the header offsets. When recovering, values past
+    /// the end of the file are left for the reads to record as truncated
+    fn check_nested(
+        &self,
+        field: &'static str,
+        offset: u32,
+        size: usize,
+        aligned: bool,
+    ) -> Result<bool, MeshLoadError> {
+        if offset == 0 {
+            return Ok(false);
+        }
+
+        if aligned && offset % 4 != 0 {
+            return Err(MeshLoadError::MisalignedOffset { field, offset });
+        }
+
+        let length = self.bytes.len();
+        let in_bounds = (offset as usize)
+            .checked_add(size)
+            .is_some_and(|end| end <= length);
+        match in_bounds {
+            true => Ok(true),
+            false if self.recover => Ok(false),
+            false => Err(MeshLoadError::RegionOutOfBounds {
+                field,
+                offset,
+                size,
+                length,
+            }),
+        }
+    }
+
+    /// Checks the platform data of the materials along with their display
+    /// list containers and the display lists stored in the file
+    fn validate_materials(&self, materials: &[FMeshMaterial]) -> Result<(), MeshLoadError> {
+        for material in materials {
+            // Register counts come from the shader tables rather than the
+            // file so only the start of the registers is checked
+            self.check_nested(
+                "shader_light_registers",
+                material.shader_light_registers.0,
+                4,
+                true,
+            )?;
+            self.check_nested(
+                "shader_surface_registers",
+                material.shader_surface_registers.0,
+                4,
+                true,
+            )?;
+
+            let platform = material.platform_data.0;
+            if !self.check_nested("platform_data", platform, GC_MATERIAL_SIZE, true)? {
+                continue;
+            }
+
+            let containers = self.header_offset(platform as usize);
+            let count = self.read_u16(platform as usize + 4);
+            if !self.check_nested(
+                "display_list_containers",
+                containers,
+                count * DL_CONTAINER_SIZE,
+                true,
+            )? {
+                continue;
+            }
+
+            for index in 0..count {
+                let container = containers as usize + index * DL_CONTAINER_SIZE;
+
+                // Streamed display lists are offsets into the stream file
+                let flags = DLContFlags::from_bits_retain(self.bytes[container]);
+                if flags.contains(DLContFlags::STREAMING) {
+                    continue;
+                }
+
+                let size = self.header_offset(container + 4) as usize;
+                let buffer = self.header_offset(container + 8);
+                self.check_nested("display_list", buffer, size, false)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the vertex buffers and skin of the GameCube mesh data along
+    /// with the arrays they point to
+    fn validate_mesh_data(&self, mesh_is: PtrOffset) -> Result<(), MeshLoadError> {
+        if !self.check_nested("mesh_is", mesh_is.0, GC_MESH_SIZE, true)? {
+            return Ok(());
+        }
+        let mesh_is = mesh_is.0 as usize;
+
+        let count = self.bytes[mesh_is + GC_MESH_VERTEX_BUFFER_COUNT] as usize;
+        let buffers = self.header_offset(mesh_is + GC_MESH_VERTEX_BUFFERS);
+        if self.check_nested(
+            "vertex_buffers",
+            buffers,
+            count * GC_VERTEX_BUFFER_SIZE,
+            true,
+        )? {
+            for index in 0..count {
+                self.validate_vertex_buffer(buffers as usize + index * GC_VERTEX_BUFFER_SIZE)?;
+            }
+        }
+
+        let skin = self.header_offset(mesh_is + GC_MESH_SKIN);
+        if self.check_nested("mesh_skin", skin, GC_MESH_SKIN_SIZE, true)? {
+            let skin = skin as usize;
+            let trans_desc_count = self.read_u16(skin);
+            let vertex_count = self.header_offset(skin + 12) as usize;
+            self.check_nested(
+                "trans_desc",
+                self.header_offset(skin + 8),
+                trans_desc_count * 8,
+                false,
+            )?;
+            self.check_nested(
+                "skinned_verts",
+                self.header_offset(skin + 16),
+                vertex_count.saturating_mul(12),
+                false,
+            )?;
+            self.check_nested(
+                "weights",
+                self.header_offset(skin + 20),
+                vertex_count.saturating_mul(4),
+                false,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the attribute arrays of the vertex buffer at the position
+    fn validate_vertex_buffer(&self, buffer: usize) -> Result<(), MeshLoadError> {
+        let flags = GCVertexBufferFlags::from_bits_retain(self.read_u16(buffer) as u16);
+        let pos_count = self.read_u16(buffer + 2);
+        let diffuse_count = self.read_u16(buffer + 8);
+
+        // Unknown position types fail when the buffer is read
+        if let Ok(pos_type) = GCPosType::read(&mut Cursor::new(&self.bytes[buffer + 4..])) {
+            self.check_nested(
+                "position",
+                self.header_offset(buffer + 12),
+                pos_count * pos_type.size(),
+                false,
+            )?;
+        }
+        self.check_nested(
+            "diffuse",
+            self.header_offset(buffer + 16),
+            diffuse_count * 4,
+            false,
+        )?;
+        self.check_nested("st", self.header_offset(buffer + 20), pos_count * 4, false)?;
+
+        let normal_size = match flags.contains(GCVertexBufferFlags::NORM_NBT) {
+            true => 9,
+            false => 6,
+        };
+        self.check_nested(
+            "normals",
+            self.header_offset(buffer + 24),
+            pos_count * normal_size,
+            false,
+        )?;
+
+        Ok(())
+    }
+
+    /// Checks the offsets in the header all point within the file
+    pub fn validate(&self) -> Result<(), MeshLoadError> {
+        let length = self.bytes.len();
+        if length < MESH_HEADER_SIZE {
+            return Err(MeshLoadError::TooSmall(length));
+        }
+
+        for (field, position) in HEADER_OFFSETS {
+            let offset = self.header_offset(position);
+
+            // Null offsets are absent values
+            if offset == 0 {
+                continue;
+            }
+
+            if offset as usize >= length {
+                return Err(MeshLoadError::OffsetOutOfBounds {
+                    field,
+                    offset,
+                    length,
+                });
+            }
+
+            if offset % 4 != 0 {
+                return Err(MeshLoadError::MisalignedOffset { field, offset });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates and parses the mesh
+    pub fn load(mut self) -> Result<LoadedMesh, MeshLoadError> {
+        let mut truncations = if self.recover {
+            self.null_truncated_offsets()?
+        } else {
+            Vec::new()
+        };
+        self.validate()?;
+
+        let mesh = FMesh::read_be(&mut Cursor::new(&self.bytes))?;
+        let segments = self.read_array(
+            "segment_array",
+            mesh.segment_array,
+            mesh.segment_count,
+            &mut truncations,
+        )?;
+        let bones = self.read_array(
+            "bone_array",
+            mesh.bone_array,
+            mesh.bone_count,
+            &mut truncations,
+        )?;
+        let lights = self.read_array(
+            "light_array",
+            mesh.light_array,
+            mesh.light_count,
+            &mut truncations,
+        )?;
+        let materials = self.read_array(
+            "material_array",
+            mesh.material_array,
+            mesh.material_count,
+            &mut truncations,
+        )?;
+        let tex_layers = self.read_array(
+            "tex_layer_array",
+            mesh.tex_layer_array,
+            mesh.tex_layer_id_count,
+            &mut truncations,
+        )?;
+        self.validate_materials(&materials)?;
+        self.validate_mesh_data(mesh.mesh_is)?;
+
+        let mesh_data = self.read_at("mesh_is", mesh.mesh_is, &mut truncations, |cursor| {
+            GCMesh::read(cursor)
+        })?;
+
+        Ok(LoadedMesh {
+            mesh,
+            segments,
+            bones,
+            lights,
+            materials,
+            tex_layers,
+            mesh_data,
+            path: self.path,
+            bytes: self.bytes,
+            truncations,
+        })
+    }
+
+    /// Reads the value at the offset, when recovering a value that runs
+    /// past the end of the file is recorded as truncated and read as None
+    fn read_at<T>(
+        &self,
+        target: &'static str,
+        offset: PtrOffset,
+        truncations: &mut Vec<Truncation>,
+        read: impl FnOnce(&mut Cursor<&[u8]>) -> BinResult<T>,
+    ) -> Result<Option<T>, MeshLoadError> {
+        let Some(ptr) = offset.offset() else {
+            return Ok(None);
+        };
+
+        let mut cursor = Cursor::new(self.bytes.as_slice());
+        cursor.set_position(ptr as u64);
+        let value = if self.recover {
+            let (value, truncated) = recover_truncated(|| read(&mut cursor));
+            truncations.extend(truncated);
+            value
+        } else {
+            read(&mut cursor)
+        };
+
+        match value {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.is_eof() && self.recover => {
+                truncations.push(Truncation { ptr, target });
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Reads the array of `count` records at the offset
+    fn read_array<T>(
+        &self,
+        target: &'static str,
+        offset: PtrOffset,
+        count: u8,
+        truncations: &mut Vec<Truncation>,
+    ) -> Result<Vec<T>, MeshLoadError>
+    where
+        T: for<'a> BinRead<Args<'a> = ()>,
+    {
+        let values = self.read_at(target, offset, truncations, |cursor| {
+            (0..count).map(|_| T::read_be(cursor)).collect()
+        })?;
+        Ok(values.unwrap_or_default())
+    }
+
+    /// Nulls the header offsets that are past the end of the file
+    fn null_truncated_offsets(&mut self) -> Result<Vec<Truncation>, MeshLoadError> {
+        let length = self.bytes.len();
+        if length < MESH_HEADER_SIZE {
+            return Err(MeshLoadError::TooSmall(length));
+        }
+
+        let mut truncations = Vec::new();
+        for (field, position) in HEADER_OFFSETS {
+            let offset = self.header_offset(position);
+            if offset as usize >= length {
+                self.bytes[position..position + 4].fill(0);
+                truncations.push(Truncation {
+                    ptr: offset,
+                    target: field,
+                });
+            }
+        }
+        Ok(truncations)
+    }
+}
+
+/// Mesh loaded by a [MeshLoader]
+#[derive(Debug)]
+pub struct LoadedMesh {
+    mesh: FMesh,
+    segments: Vec<FMeshSegment>,
+    bones: Vec<FMeshBone>,
+    lights: Vec<FMeshLight>,
+    materials: Vec<FMeshMaterial>,
+    tex_layers: Vec<FMeshTexLayerID>,
+    /// GameCube specific mesh data
+    mesh_data: Option<GCMesh>,
+    /// Path the mesh was loaded from if loaded from a path
+    path: Option<PathBuf>,
+    /// Bytes of the file, kept for the data the mesh only stores offsets to
+    bytes: Vec<u8>,
+    /// Values that were dropped for being past the end of the file
+    truncations: Vec<Truncation>,
+}
+
+impl LoadedMesh {
+    pub fn mesh(&self) -> &FMesh {
+        &self.mesh
+    }
+
+    pub fn segments(&self) -> &[FMeshSegment] {
+        &self.segments
+    }
+
+    pub fn bones(&self) -> &[FMeshBone] {
+        &self.bones
+    }
+
+    pub fn lights(&self) -> &[FMeshLight] {
+        &self.lights
+    }
+
+    pub fn materials(&self) -> &[FMeshMaterial] {
+        &self.materials
+    }
+
+    pub fn tex_layers(&self) -> &[FMeshTexLayerID] {
+        &self.tex_layers
+    }
+
+    pub fn mesh_data(&self) -> Option<&GCMesh> {
+        self.mesh_data.as_ref()
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn length(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn truncations(&self) -> &[Truncation] {
+        &self.truncations
+    }
+
+    /// Only part of the mesh was recovered from a truncated file
+    pub fn is_partial(&self) -> bool {
+        !self.truncations.is_empty()
+    }
+
+    /// Marks the report as partial when the mesh was truncated, listing
+    /// each of the dropped values
+    pub fn report_truncations(&self, report: &mut LoadReport) {
+        for truncation in &self.truncations {
+            report.partial(format!(
+                "{} at {:#x} is past the end of the {} byte file",
+                truncation.target,
+                truncation.ptr,
+                self.length()
+            ));
+        }
+    }
+
+    /// Vertex buffers of the GameCube mesh data
+    pub fn vertex_buffers(&self) -> &[GCVertexBuffer] {
+        self.mesh_data
+            .as_ref()
+            .and_then(|mesh_data| mesh_data.vertex_buffers.value.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Takes the header of the mesh
+    pub fn into_mesh(self) -> FMesh {
+        self.mesh
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use openglitch_formats::mesh::FMeshMaterial;
+
+    use super::{
+        MeshLoadError, MeshLoader, GC_MESH_SIZE, GC_MESH_VERTEX_BUFFERS,
+        GC_MESH_VERTEX_BUFFER_COUNT, GC_VERTEX_BUFFER_SIZE, MESH_HEADER_SIZE,
+    };
+    use crate::formats::report::LoadReport;
+
+    fn header() -> Vec<u8> {
+        let mut bytes = vec![0; MESH_HEADER_SIZE];
+        bytes[..4].copy_from_slice(b"test");
+        bytes
+    }
+
+    #[test]
+    fn test_load_empty_mesh() {
+        let loaded = MeshLoader::from_bytes(header()).load().unwrap();
+        assert_eq!(loaded.mesh().name.as_string(), "test");
+        assert!(loaded.vertex_buffers().is_empty());
+    }
+
+    #[test]
+    fn test_reject_offsets() {
+        let mut bytes = header();
+        bytes[108..112].copy_from_slice(&0x1000u32.to_be_bytes());
+        assert!(matches!(
+            MeshLoader::from_bytes(bytes).load(),
+            Err(MeshLoadError::OffsetOutOfBounds {
+                field: "bone_array",
+                ..
+            })
+        ));
+
+        let mut bytes = header();
+        bytes[132..136].copy_from_slice(&0x6u32.to_be_bytes());
+        assert!(matches!(
+            MeshLoader::from_bytes(bytes).load(),
+            Err(MeshLoadError::MisalignedOffset {
+                field: "mesh_is",
+                ..
+            })
+        ));
+
+        assert!(matches!(
+            MeshLoader::from_bytes(vec![0; 16]).load(),
+            Err(MeshLoadError::TooSmall(16))
+        ));
+    }
+
+    #[test]
+    fn test_reject_nested_offsets() {
+        // Material whose platform data starts past the end of the file
+        let mut bytes = header();
+        bytes[68] = 1;
+        bytes[120..124].copy_from_slice(&(MESH_HEADER_SIZE as u32).to_be_bytes());
+        let mut material = vec![0; FMeshMaterial::SIZE];
+        material[16..20].copy_from_slice(&0x1000u32.to_be_bytes());
+        bytes.extend_from_slice(&material);
+        assert!(matches!(
+            MeshLoader::from_bytes(bytes).load(),
+            Err(MeshLoadError::RegionOutOfBounds {
+                field: "platform_data",
+                offset: 0x1000,
+                ..
+            })
+        ));
+
+        // Vertex buffer of two 16 bit positions with room for one
+        let mut bytes = header();
+        bytes[132..136].copy_from_slice(&(MESH_HEADER_SIZE as u32).to_be_bytes());
+        let buffers = MESH_HEADER_SIZE + GC_MESH_SIZE;
+        let mut mesh = vec![0; GC_MESH_SIZE];
+        mesh[GC_MESH_VERTEX_BUFFER_COUNT] = 1;
+        mesh[GC_MESH_VERTEX_BUFFERS..GC_MESH_VERTEX_BUFFERS + 4]
+            .copy_from_slice(&(buffers as u32).to_be_bytes());
+        bytes.extend_from_slice(&mesh);
+
+        let position = buffers + GC_VERTEX_BUFFER_SIZE;
+        let mut buffer = vec![0; GC_VERTEX_BUFFER_SIZE];
+        buffer[2..4].copy_from_slice(&2u16.to_be_bytes());
+        buffer[4] = 3;
+        buffer[12..16].copy_from_slice(&(position as u32).to_be_bytes());
+        bytes.extend_from_slice(&buffer);
+        bytes.extend_from_slice(&[0; 6]);
+
+        assert!(matches!(
+            MeshLoader::from_bytes(bytes.clone()).load(),
+            Err(MeshLoadError::RegionOutOfBounds {
+                field: "position",
+                size: 12,
+                ..
+            })
+        ));
+
+        // Recovering leaves the short array to be recorded as truncated
+        let loaded = MeshLoader::from_bytes(bytes)
+            .recover_truncated()
+            .load()
+            .unwrap();
+        assert!(loaded.is_partial());
+    }
+
+    #[test]
+    fn test_recover_truncated() {
+        let mut bytes = header();
+        bytes[108..112].copy_from_slice(&0x1000u32.to_be_bytes());
+        // Materials fit the offset but not the material itself
+        bytes[68] = 1;
+        bytes[120..124].copy_from_slice(&(MESH_HEADER_SIZE as u32).to_be_bytes());
+        bytes.extend_from_slice(&[0; 8]);
+
+        assert!(MeshLoader::from_bytes(bytes.clone()).load().is_err());
+
+        let loaded = MeshLoader::from_bytes(bytes)
+            .recover_truncated()
+            .load()
+            .unwrap();
+        assert!(loaded.is_partial());
+        assert_eq!(loaded.truncations().len(), 2);
+        assert_eq!(loaded.truncations()[0].target, "bone_array");
+        assert_eq!(loaded.truncations()[1].target, "material_array");
+        assert!(loaded.bones().is_empty());
+        assert!(loaded.materials().is_empty());
+
+        let mut report = LoadReport::new("test");
+        loaded.report_truncations(&mut report);
+        assert!(report.partial);
+    }
+}
@@ -0,0 +1,580 @@
+//! Conversion of mesh materials into renderer materials. The material
+//! tint, texture layers and shader effects are mapped onto a
+//! [StandardMaterial], flipping texture layers are converted with every
+//! page loaded so they can be animated by swapping the base texture, and
+//! the scroll and rotation speeds of the base layer are kept so its
+//! texture coordinates can be animated.
+//!
+//! The page names of each layer are resolved through the texture instances
+//! of its flip palette to the texture definitions that name them, textures
+//! stored in the mesh are decoded by [MeshTextures] while the others are
+//! loaded from the extracted textures
+
+use bevy::{
+    asset::{Assets, Handle},
+    math::{Affine2, Vec2},
+    pbr::{AlphaMode, StandardMaterial},
+    render::{color::Color, texture::Image},
+    utils::HashMap,
+};
+
+use openglitch_formats::{mesh::FMeshMaterial, texture::FTexDef};
+
+use crate::{
+    formats::{report::LoadReport, shader_table::ShaderEffectTable, texture::DecodedTexture},
+    fs::GameFs,
+};
+
+use super::{
+    loader::LoadedMesh,
+    textures::{
+        decode_tex_data, layer_texture_names, mesh_texture_defs, texture_image, MeshTextureError,
+    },
+};
+
+/// Directory the extracted textures are stored in
+pub const TEXTURE_DIR: &str = "Textures";
+/// Extension of the extracted textures
+const TEXTURE_EXTENSION: &str = "png";
+/// Value of an unused texture layer slot
+const EMPTY_LAYER_SLOT: u8 = 255;
+/// Rate of the game frames the flip pages are counted in
+pub const FLIP_FRAMES_PER_SECOND: f32 = 30.0;
+
+/// Texture layer used by a material
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MaterialLayer {
+    /// Texture layer ID the shader reads the layer through
+    pub tex_layer_id: u8,
+    /// Names of the textures of each flip page, layers that don't flip
+    /// have a single page
+    pub pages: Vec<String>,
+    /// Number of frames each page is shown for
+    pub frames_per_flip: u8,
+    /// Texture coordinate scroll speed in texture space units per second
+    pub scroll_per_second: Vec2,
+    /// Texture coordinate rotation speed in degrees per second
+    pub rotation_per_second: f32,
+}
+
+/// Fields of a mesh material used for rendering
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialSource {
+    pub tint: [f32; 3],
+    pub light_shader_index: u8,
+    pub specular_shader_index: u8,
+    pub surface_shader_index: u16,
+    /// Engine material flags, passed through to the converted material
+    pub flags: u16,
+    pub layers: Vec<MaterialLayer>,
+}
+
+impl MaterialSource {
+    /// Collects the rendering fields of a GameCube mesh material along
+    /// with the texture layers it uses, layers with textures that can't be
+    /// read are reported and kept without pages
+    pub fn from_fmesh(
+        mesh: &LoadedMesh,
+        material: &FMeshMaterial,
+        report: &mut LoadReport,
+    ) -> Self {
+        let tex_layers = mesh.tex_layers();
+
+        let layers = material
+            .tex_layer_id_index
+            .iter()
+            .filter(|index| **index != EMPTY_LAYER_SLOT)
+            .filter_map(|index| tex_layers.get(*index as usize).map(|layer| (index, layer)))
+            .map(|(index, layer)| MaterialLayer {
+                tex_layer_id: layer.tex_layer_id,
+                pages: layer_texture_names(mesh, layer).unwrap_or_else(|err| {
+                    report.warn(format!("textures of texture layer {}: {}", index, err));
+                    Vec::new()
+                }),
+                frames_per_flip: layer.frames_per_flip,
+                scroll_per_second: Vec2::from(&layer.scroll_st_per_second),
+                rotation_per_second: layer.uv_degree_rotation_per_second,
+            })
+            .collect();
+
+        Self {
+            tint: [
+                material.material_tint.red,
+                material.material_tint.green,
+                material.material_tint.blue,
+            ],
+            light_shader_index: material.light_shader_index,
+            specular_shader_index: material.specular_shader_index,
+            surface_shader_index: material.surface_shader_index,
+            flags: material.mtl_flags,
+            layers,
+        }
+    }
+
+    /// Sets the texture names of the flip pages of a layer
+    pub fn with_pages(mut self, layer: usize, pages: Vec<String>) -> Self {
+        if let Some(layer) = self.layers.get_mut(layer) {
+            layer.pages = pages;
+        }
+        self
+    }
+}
+
+/// Texture loaded for a material
+#[derive(Debug, Clone)]
+pub struct MaterialTexture {
+    pub image: Handle<Image>,
+    /// Texture has texels that aren't fully opaque
+    pub translucent: bool,
+}
+
+/// Provides the textures referenced by materials
+pub trait TextureSource {
+    fn texture(&mut self, name: &str) -> Option<MaterialTexture>;
+}
+
+/// Stores the images decoded by a texture source
+pub trait ImageStore {
+    fn add_image(&mut self, name: &str, image: Image) -> Handle<Image>;
+}
+
+/// Texture source decoding the textures stored in a mesh, textures the mesh
+/// only names are provided by the fallback source which also stores the
+/// decoded images. Textures that fail to decode are kept in the errors and
+/// left to the fallback
+pub struct MeshTextures<'a, T> {
+    mesh: &'a LoadedMesh,
+    defs: HashMap<String, FTexDef>,
+    fallback: T,
+    cache: HashMap<String, Option<MaterialTexture>>,
+    errors: Vec<(String, MeshTextureError)>,
+}
+
+impl<'a, T: TextureSource + ImageStore> MeshTextures<'a, T> {
+    pub fn new(mesh: &'a LoadedMesh, fallback: T) -> Self {
+        Self {
+            mesh,
+            defs: mesh_texture_defs(mesh),
+            fallback,
+            cache: HashMap::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Names of the stored textures that failed to decode and why
+    pub fn errors(&self) -> &[(String, MeshTextureError)] {
+        &self.errors
+    }
+
+    /// Reports the textures that failed to decode
+    pub fn report_errors(&self, report: &mut LoadReport) {
+        for (name, err) in &self.errors {
+            report.warn(format!("texture {}: {}", name, err));
+        }
+    }
+
+    fn decode(&mut self, key: &str) -> Option<MaterialTexture> {
+        let tex_def = self.defs.get(key)?;
+        let decoded = decode_tex_data(self.mesh, tex_def).and_then(|levels| {
+            levels
+                .map(|levels| texture_image(&levels).map_err(MeshTextureError::from))
+                .transpose()
+        });
+
+        match decoded {
+            Ok(Some((image, translucent))) => Some(MaterialTexture {
+                image: self.fallback.add_image(key, image),
+                translucent,
+            }),
+            Ok(None) => None,
+            Err(err) => {
+                self.errors.push((key.to_string(), err));
+                None
+            }
+        }
+    }
+}
+
+impl<T: TextureSource + ImageStore> TextureSource for MeshTextures<'_, T> {
+    fn texture(&mut self, name: &str) -> Option<MaterialTexture> {
+        let key = name.to_ascii_lowercase();
+        if let Some(texture) = self.cache.get(&key) {
+            return texture.clone();
+        }
+
+        let texture = self.decode(&key).or_else(|| self.fallback.texture(name));
+        self.cache.insert(key, texture.clone());
+        texture
+    }
+}
+
+/// Texture source loading the extracted textures from the game data,
+/// textures are cached by name so shared textures are only loaded once
+pub struct GameTextures<'a> {
+    game_fs: &'a GameFs,
+    images: &'a mut Assets<Image>,
+    cache: HashMap<String, Option<MaterialTexture>>,
+}
+
+impl<'a> GameTextures<'a> {
+    pub fn new(game_fs: &'a GameFs, images: &'a mut Assets<Image>) -> Self {
+        Self {
+            game_fs,
+            images,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Path of an extracted texture in the game data
+    pub fn texture_path(name: &str) -> String {
+        format!("{}/{}.{}", TEXTURE_DIR, name, TEXTURE_EXTENSION)
+    }
+
+    fn load(&mut self, name: &str) -> Option<MaterialTexture> {
+        let bytes = self.game_fs.read(&Self::texture_path(name)).ok()?;
+        let image = image::load_from_memory(&bytes).ok()?.to_rgba8();
+        let texture = DecodedTexture {
+            width: image.width(),
+            height: image.height(),
+            data: image.into_raw(),
+        };
+
+        let translucent = texture.data.chunks_exact(4).any(|texel| texel[3] != 255);
+        Some(MaterialTexture {
+            image: self.images.add(texture.to_image()),
+            translucent,
+        })
+    }
+}
+
+impl ImageStore for GameTextures<'_> {
+    fn add_image(&mut self, _name: &str, image: Image) -> Handle<Image> {
+        self.images.add(image)
+    }
+}
+
+impl TextureSource for GameTextures<'_> {
+    fn texture(&mut self, name: &str) -> Option<MaterialTexture> {
+        let key = name.to_ascii_lowercase();
+        if let Some(texture) = self.cache.get(&key) {
+            return texture.clone();
+        }
+
+        let texture = self.load(name);
+        self.cache.insert(key, texture.clone());
+        texture
+    }
+}
+
+/// Flipping texture layer of a converted material
+#[derive(Debug, Clone)]
+pub struct FlipLayer {
+    /// Texture of each page, pages that failed to load are skipped
+    pub pages: Vec<Handle<Image>>,
+    pub frames_per_flip: u8,
+}
+
+impl FlipLayer {
+    /// Index of the page shown the provided number of seconds in, layers
+    /// with zero frames per flip advance every frame
+    pub fn page_index(&self, seconds: f32) -> usize {
+        let frame = (seconds.max(0.0) * FLIP_FRAMES_PER_SECOND) as usize;
+        (frame / self.frames_per_flip.max(1) as usize) % self.pages.len().max(1)
+    }
+
+    pub fn page(&self, seconds: f32) -> Option<&Handle<Image>> {
+        self.pages.get(self.page_index(seconds))
+    }
+}
+
+/// Scrolling and rotating texture coordinates of a converted material
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvAnimation {
+    /// Texture space units per second
+    pub scroll_per_second: Vec2,
+    /// Degrees per second, rotating around the middle of the texture
+    pub rotation_per_second: f32,
+}
+
+impl UvAnimation {
+    /// Animation of the layer, none when the layer is static
+    pub fn from_layer(layer: &MaterialLayer) -> Option<UvAnimation> {
+        (layer.scroll_per_second != Vec2::ZERO || layer.rotation_per_second != 0.0).then_some(
+            UvAnimation {
+                scroll_per_second: layer.scroll_per_second,
+                rotation_per_second: layer.rotation_per_second,
+            },
+        )
+    }
+
+    /// Transform applied to the texture coordinates the provided number
+    /// of seconds in
+    pub fn transform(&self, seconds: f32) -> Affine2 {
+        let center = Vec2::splat(0.5);
+        // The scroll wraps so the coordinates keep their precision, the
+        // textures repeat so the wrap isn't visible
+        let offset = (self.scroll_per_second * seconds).fract();
+        let angle = (self.rotation_per_second * seconds).rem_euclid(360.0);
+        Affine2::from_translation(center + offset)
+            * Affine2::from_angle(angle.to_radians())
+            * Affine2::from_translation(-center)
+    }
+}
+
+/// Texture animations of a converted material
+#[derive(Debug, Clone, Default)]
+pub struct MaterialAnimation {
+    pub flip: Option<FlipLayer>,
+    pub uv: Option<UvAnimation>,
+}
+
+impl MaterialAnimation {
+    pub fn is_animated(&self) -> bool {
+        self.flip.is_some() || self.uv.is_some()
+    }
+}
+
+/// Material created by a [MaterialConverter]
+#[derive(Debug, Clone)]
+pub struct ConvertedMaterial {
+    pub material: StandardMaterial,
+    /// Engine material flags
+    pub flags: u16,
+    /// Base texture layer when it flips between pages
+    pub flip: Option<FlipLayer>,
+    /// Base texture layer when its coordinates scroll or rotate
+    pub uv_animation: Option<UvAnimation>,
+    /// Names of the textures that couldn't be loaded
+    pub missing_textures: Vec<String>,
+}
+
+/// Converts mesh materials into [StandardMaterial]s
+pub struct MaterialConverter<'a, T> {
+    shaders: &'a ShaderEffectTable,
+    textures: T,
+}
+
+impl<'a, T: TextureSource> MaterialConverter<'a, T> {
+    pub fn new(shaders: &'a ShaderEffectTable, textures: T) -> Self {
+        Self { shaders, textures }
+    }
+
+    /// Texture source the converter loaded the textures through
+    pub fn into_textures(self) -> T {
+        self.textures
+    }
+
+    pub fn convert(&mut self, source: &MaterialSource) -> ConvertedMaterial {
+        let [red, green, blue] = source.tint;
+        let mut material = StandardMaterial {
+            base_color: Color::rgb(red, green, blue),
+            ..Default::default()
+        };
+        let mut missing_textures = Vec::new();
+        let mut flip = None;
+        let uv_animation = source.layers.first().and_then(UvAnimation::from_layer);
+
+        // The first layer is the base texture, the remaining layers are
+        // blended by shaders the renderer has no equivalent for
+        if let Some(layer) = source.layers.first() {
+            let pages: Vec<MaterialTexture> = layer
+                .pages
+                .iter()
+                .filter_map(|name| {
+                    let texture = self.textures.texture(name);
+                    if texture.is_none() {
+                        missing_textures.push(name.clone());
+                    }
+                    texture
+                })
+                .collect();
+
+            if let Some(first) = pages.first() {
+                material.base_color_texture = Some(first.image.clone());
+                if pages.iter().any(|page| page.translucent) {
+                    material.alpha_mode = AlphaMode::Blend;
+                }
+            }
+
+            if pages.len() > 1 {
+                flip = Some(FlipLayer {
+                    pages: pages.into_iter().map(|page| page.image).collect(),
+                    frames_per_flip: layer.frames_per_flip,
+                });
+            }
+        }
+
+        self.shaders
+            .material_effects(
+                source.light_shader_index,
+                source.specular_shader_index,
+                source.surface_shader_index,
+            )
+            .apply(&mut material);
+
+        ConvertedMaterial {
+            material,
+            flags: source.flags,
+            flip,
+            uv_animation,
+            missing_textures,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{
+        asset::Handle,
+        math::Vec2,
+        pbr::AlphaMode,
+        render::{color::Color, texture::Image},
+    };
+
+    use crate::formats::{
+        mesh::{fixture::textured_mesh, loader::MeshLoader},
+        report::LoadReport,
+        shader_table::ShaderEffectTable,
+    };
+
+    use super::{
+        FlipLayer, ImageStore, MaterialConverter, MaterialLayer, MaterialSource, MaterialTexture,
+        MeshTextures, TextureSource, UvAnimation,
+    };
+
+    /// Texture source where textures named with a `_a` suffix are translucent
+    struct TestTextures;
+
+    impl TextureSource for TestTextures {
+        fn texture(&mut self, name: &str) -> Option<MaterialTexture> {
+            if name == "missing" {
+                return None;
+            }
+
+            Some(MaterialTexture {
+                image: Handle::default(),
+                translucent: name.ends_with("_a"),
+            })
+        }
+    }
+
+    /// Texture source without any textures keeping the images stored in it
+    #[derive(Default)]
+    struct StoredImages {
+        images: Vec<(String, Image)>,
+    }
+
+    impl TextureSource for StoredImages {
+        fn texture(&mut self, _name: &str) -> Option<MaterialTexture> {
+            None
+        }
+    }
+
+    impl ImageStore for StoredImages {
+        fn add_image(&mut self, name: &str, image: Image) -> Handle<Image> {
+            self.images.push((name.to_string(), image));
+            Handle::default()
+        }
+    }
+
+    #[test]
+    fn test_convert_material() {
+        let table = ShaderEffectTable::from_ini("[surface.2]\nunlit=true\n").unwrap();
+        let mut converter = MaterialConverter::new(&table, TestTextures);
+
+        let source = MaterialSource {
+            tint: [1.0, 0.5, 0.0],
+            light_shader_index: 0,
+            specular_shader_index: 0,
+            surface_shader_index: 2,
+            flags: 0x4,
+            layers: vec![MaterialLayer {
+                pages: vec!["water_00".to_string(), "water_01_a".to_string()],
+                frames_per_flip: 4,
+                ..Default::default()
+            }],
+        };
+
+        let converted = converter.convert(&source);
+        assert_eq!(converted.material.base_color, Color::rgb(1.0, 0.5, 0.0));
+        assert!(converted.material.base_color_texture.is_some());
+        assert!(matches!(converted.material.alpha_mode, AlphaMode::Blend));
+        assert!(converted.material.unlit);
+        assert_eq!(converted.flags, 0x4);
+        assert_eq!(converted.flip.unwrap().pages.len(), 2);
+        assert!(converted.uv_animation.is_none());
+
+        let source = source.with_pages(0, vec!["missing".to_string()]);
+        let converted = converter.convert(&source);
+        assert!(converted.material.base_color_texture.is_none());
+        assert!(converted.flip.is_none());
+        assert_eq!(converted.missing_textures, ["missing"]);
+    }
+
+    #[test]
+    fn test_convert_mesh_material() {
+        // Pages are named by the texture definitions stored in the mesh
+        let loaded = MeshLoader::from_bytes(textured_mesh(&["rock_00"]))
+            .load()
+            .unwrap();
+        let mut report = LoadReport::new("test");
+        let source = MaterialSource::from_fmesh(&loaded, &loaded.materials()[0], &mut report);
+        assert!(report.findings.is_empty());
+        assert_eq!(source.layers[0].pages, ["rock_00"]);
+
+        // Texture is decoded from the texels stored in the mesh
+        let table = ShaderEffectTable::default();
+        let mut converter =
+            MaterialConverter::new(&table, MeshTextures::new(&loaded, StoredImages::default()));
+        let converted = converter.convert(&source);
+        assert!(converted.material.base_color_texture.is_some());
+        assert!(matches!(converted.material.alpha_mode, AlphaMode::Opaque));
+        assert!(converted.missing_textures.is_empty());
+
+        // Shared textures are only decoded once
+        converter.convert(&source);
+        let textures = converter.into_textures();
+        assert!(textures.errors().is_empty());
+        let images = textures.fallback.images;
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].0, "rock_00");
+        assert_eq!(images[0].1.data, vec![255; 4 * 4 * 4]);
+    }
+
+    #[test]
+    fn test_flip_pages() {
+        let flip = FlipLayer {
+            pages: vec![Handle::default(); 3],
+            frames_per_flip: 15,
+        };
+        assert_eq!(flip.page_index(0.0), 0);
+        assert_eq!(flip.page_index(0.5), 1);
+        assert_eq!(flip.page_index(1.49), 2);
+        assert_eq!(flip.page_index(1.5), 0);
+    }
+
+    #[test]
+    fn test_uv_animation() {
+        let layer = MaterialLayer {
+            scroll_per_second: Vec2::new(0.25, 0.0),
+            ..Default::default()
+        };
+        let scroll = UvAnimation::from_layer(&layer).unwrap();
+        let uv = scroll.transform(1.0).transform_point2(Vec2::ZERO);
+        assert!(uv.abs_diff_eq(Vec2::new(0.25, 0.0), 1e-5));
+
+        let rotate = UvAnimation {
+            scroll_per_second: Vec2::ZERO,
+            rotation_per_second: 90.0,
+        };
+        let transform = rotate.transform(1.0);
+        assert!(transform
+            .transform_point2(Vec2::splat(0.5))
+            .abs_diff_eq(Vec2::splat(0.5), 1e-5));
+        assert!(transform
+            .transform_point2(Vec2::new(1.0, 0.5))
+            .abs_diff_eq(Vec2::new(0.5, 1.0), 1e-5));
+
+        assert!(UvAnimation::from_layer(&MaterialLayer::default()).is_none());
+    }
+}
@@ -1,80 +1,19 @@
-use bevy::render::{
-    mesh::{Indices, Mesh},
-    render_resource::PrimitiveTopology,
-};
-use binrw::{BinRead, FilePtr};
+use bevy::render::{mesh::Mesh, render_resource::PrimitiveTopology};
+use binrw::BinRead;
 use bitflags::bitflags;
-
-use crate::formats::types::{
-    FixedString, NullableFilePtr, PtrOffset, RawColorMotif, RawColorRGB, RawColorRGBA,
-    RawMatrix4x3f, RawSphere, RawVec2f, RawVec3f,
+use thiserror::Error;
+
+use crate::formats::{
+    mesh::{
+        normals::{decode_normals, decode_tangents},
+        uvs::decode_uvs,
+        winding::{normalize_winding, Winding},
+    },
+    report::LoadReport,
+    types::{NullableFilePtr, PtrOffset, RawSphere},
 };
 
-const FDATA_MESH_NAME_LENGTH: usize = 16;
-const FDATA_MAX_LOD_MESH_COUNT: usize = 8;
-const FDATA_BONE_NAME_LENGTH: usize = 32;
-const FDATA_VW_COUNT_PER_VTX: usize = 4;
-const FLIGHT_NAME_LENGTH: usize = 16;
-const FLIGHT_TEXTURE_NAME_LENGTH: usize = 16;
-
-bitflags! {
-    #[derive(Debug, BinRead, Clone, Copy)]
-    #[br(map = Self::from_bits_retain)]
-    pub struct FMeshFlags: u16 {
-
-    }
-}
-
-#[derive(Debug, BinRead)]
-#[br(big)]
-pub struct FMesh {
-    pub name: FixedString<FDATA_MESH_NAME_LENGTH>,
-
-    pub bound_sphere: RawSphere,
-    pub bound_box_min: RawVec3f,
-    pub bound_box_max: RawVec3f,
-
-    pub flags: u16,
-    pub mesh_coll_mask: u16,
-
-    pub used_bone_count: u8,
-    pub root_bone_index: u8,
-    pub bone_count: u8,
-    pub seg_count: u8,
-    pub tex_layer_id_count: u8,
-    pub tex_layer_id_count_st: u8,
-    pub tex_layer_id_count_flip: u8,
-    pub light_count: u8,
-    pub material_count: u8,
-    pub coll_tree_count: u8,
-    pub lod_count: u8,
-    pub shadow_lod_bias: u8,
-
-    pub lod_distance: [f32; FDATA_MAX_LOD_MESH_COUNT],
-
-    #[br(args { count: seg_count as usize })]
-    pub segments: NullableFilePtr<Vec<FMeshSeg>>,
-
-    #[br(args { count: bone_count as usize })]
-    pub bones: NullableFilePtr<Vec<FMeshBone>>,
-
-    #[br(args { count: light_count as usize })]
-    pub lights: NullableFilePtr<Vec<FMeshLight>>,
-
-    pub skeleton_index_array: PtrOffset,
-
-    #[br(args { count: material_count as usize })]
-    pub materials: NullableFilePtr<Vec<FMeshMaterial>>,
-
-    pub coll_tree: PtrOffset,
-
-    #[br(args { count: tex_layer_id_count as usize })]
-    pub tex_layer_id_array: NullableFilePtr<Vec<FMeshTexLayerID>>,
-
-    /// Pointer to gamecube specific mesh data
-    pub mesh_data: NullableFilePtr<GCMesh>,
-}
-
+/// GameCube mesh data pointed to by [FMesh::mesh_is](openglitch_formats::mesh::FMesh::mesh_is)
 #[derive(Debug, BinRead)]
 #[br(big)]
 pub struct GCMesh {
@@ -150,6 +89,24 @@ pub struct GCNBT8 {
     pub tangents: [i8; 3],
 }
 
+/// Fixed point normal used when the vertex buffer has no NBT data
+#[derive(Debug, BinRead)]
+#[br(big)]
+pub struct GCNorm16 {
+    pub normal: [i16; 3],
+}
+
+/// Normal of a vertex buffer, the format depends on the
+/// [GCVertexBufferFlags::NORM_NBT] flag
+#[derive(Debug, BinRead)]
+#[br(big, import(nbt: bool))]
+pub enum GCNormal {
+    #[br(pre_assert(nbt))]
+    Nbt(GCNBT8),
+    #[br(pre_assert(!nbt))]
+    Norm16(GCNorm16),
+}
+
 /// 8 bit UV's do not have enough resolution.  16 bit seems to be fine
 #[derive(Debug, BinRead)]
 #[br(big)]
@@ -176,6 +133,89 @@ pub enum GCPosType {
     F32 = 4,
 }
 
+impl GCPosType {
+    /// Size in bytes of a position of this type
+    pub fn size(&self) -> usize {
+        match self {
+            GCPosType::S8 => 3,
+            GCPosType::S16 => 6,
+            GCPosType::F32 => 12,
+        }
+    }
+}
+
+/// How an attribute is referenced by the vertices of a display list
+/// (matches the GX attribute types)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum GXAttrType {
+    /// Attribute isn't present (GX_NONE)
+    None = 0,
+    /// Attribute value is stored directly in the display list (GX_DIRECT)
+    Direct = 1,
+    /// Attribute is an 8 bit index into the attribute array (GX_INDEX8)
+    Index8 = 2,
+    /// Attribute is a 16 bit index into the attribute array (GX_INDEX16)
+    Index16 = 3,
+}
+
+/// Attribute type value that isn't one of the GX attribute types
+#[derive(Debug, Error)]
+#[error("unknown GX attribute type {0}")]
+pub struct UnknownAttrType(pub u8);
+
+impl TryFrom<u8> for GXAttrType {
+    type Error = UnknownAttrType;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => GXAttrType::None,
+            1 => GXAttrType::Direct,
+            2 => GXAttrType::Index8,
+            3 => GXAttrType::Index16,
+            _ => return Err(UnknownAttrType(value)),
+        })
+    }
+}
+
+impl GXAttrType {
+    /// Size in bytes of an index of this type, direct and missing
+    /// attributes have no index
+    pub fn index_size(&self) -> usize {
+        match self {
+            GXAttrType::Index8 => 1,
+            GXAttrType::Index16 => 2,
+            GXAttrType::None | GXAttrType::Direct => 0,
+        }
+    }
+
+    /// Reads an index of this type from the start of the provided bytes
+    pub fn read_index(&self, bytes: &[u8]) -> Option<u16> {
+        match self {
+            GXAttrType::Index8 => bytes.first().map(|value| *value as u16),
+            GXAttrType::Index16 => Some(u16::from_be_bytes([*bytes.first()?, *bytes.get(1)?])),
+            GXAttrType::None | GXAttrType::Direct => None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("unknown vertex layout: {pos_type:?} positions declare a stride of {declared} bytes but are {expected} bytes")]
+pub struct StrideError {
+    pub pos_type: GCPosType,
+    pub declared: u8,
+    pub expected: usize,
+}
+
+/// Vertex buffer that can't be decoded into a mesh
+#[derive(Debug, Error)]
+pub enum VertexBufferError {
+    #[error(transparent)]
+    Stride(#[from] StrideError),
+    #[error("vertex buffer has no positions")]
+    MissingPositions,
+}
+
 // GameCube "vertex buffer" format
 #[derive(Debug, BinRead)]
 #[br(big)]
@@ -184,12 +224,14 @@ pub struct GCVertexBuffer {
 
     pub pos_count: u16,
     pub pos_type: GCPosType,
-    pub pos_idx_type: u8,
+    #[br(try_map = |value: u8| GXAttrType::try_from(value))]
+    pub pos_idx_type: GXAttrType,
     pub pos_stride: u8,
     pub pos_frac: u8,
 
     pub diffuse_count: u16,
-    pub color_idx_type: u8,
+    #[br(try_map = |value: u8| GXAttrType::try_from(value))]
+    pub color_idx_type: GXAttrType,
 
     pub vertex_format: u8,
 
@@ -198,8 +240,14 @@ pub struct GCVertexBuffer {
     // pub position: PtrOffset,
     #[br(args { count: diffuse_count as usize })]
     pub diffuse: NullableFilePtr<Vec<GCColor>>,
-    pub st: NullableFilePtr<GCST16>,
-    pub nbt: NullableFilePtr<GCNBT8>,
+    /// Texture coordinates of each position
+    #[br(args { count: pos_count as usize })]
+    pub st: NullableFilePtr<Vec<GCST16>>,
+    #[br(args {
+        count: pos_count as usize,
+        inner: (flags.contains(GCVertexBufferFlags::NORM_NBT),)
+    })]
+    pub normals: NullableFilePtr<Vec<GCNormal>>,
 }
 
 #[derive(Debug, BinRead, Clone)]
@@ -213,211 +261,117 @@ pub enum GCVertBufferPos {
     F32 { x: f32, y: f32, z: f32 },
 }
 
-pub fn create_bevy_mesh(mut buffer: GCVertexBuffer) -> Mesh {
-    let values: Vec<[f32; 3]> = buffer
-        .position
-        .value
-        .take()
-        .unwrap()
-        .into_iter()
-        .map(|value| match value {
-            GCVertBufferPos::S8 { x, y, z } => [x as f32, y as f32, z as f32],
-            GCVertBufferPos::S16 { x, y, z } => [x as f32, y as f32, z as f32],
-            GCVertBufferPos::F32 { x, y, z } => [x, y, z],
-        })
-        .collect::<Vec<_>>();
-
-    let mesh = Mesh::new(PrimitiveTopology::TriangleStrip)
-        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, values);
-
-    mesh
-}
-
-#[derive(Debug, BinRead)]
-#[br(big)]
-pub struct FMeshSeg {
-    pub bound_sphere: RawSphere,
-    pub bone_mtx_count: u8,
-    pub bone_mtx_index: [u8; FDATA_VW_COUNT_PER_VTX],
-}
-
-bitflags! {
-    #[derive(Debug, BinRead, Clone, Copy)]
-    #[br(map = Self::from_bits_retain)]
-    pub struct MeshBoneFlags: u8 {
-        const NONE     = 0x00;
-        const VOIDBONE = 0x01;
-        const SKINNEDBONE = 0x10;
+impl GCVertexBuffer {
+    /// Checks the declared position stride matches the size of the decoded
+    /// position type, positions are read tightly packed
+    pub fn validate_stride(&self) -> Result<(), StrideError> {
+        let expected = self.pos_type.size();
+        if self.pos_stride as usize != expected {
+            return Err(StrideError {
+                pos_type: self.pos_type,
+                declared: self.pos_stride,
+                expected,
+            });
+        }
+        Ok(())
     }
 }
 
-#[derive(Debug, BinRead)]
-#[br(big)]
-pub struct FMeshBone {
-    pub name: FixedString<FDATA_BONE_NAME_LENGTH>,
-    pub at_rest_bone_to_model: RawMatrix4x3f,
-    pub at_rest_model_to_bone: RawMatrix4x3f,
-    pub at_rest_parent_to_bone: RawMatrix4x3f,
-    pub at_rest_bone_to_parent: RawMatrix4x3f,
-    pub segment_bounded_sphere: RawSphere,
-    pub skeleton: FMeshSkeleton,
-    pub flags: MeshBoneFlags,
-    #[br(pad_after = 3)]
-    pub part_id: u8,
-}
-
-#[derive(Debug, BinRead)]
-#[br(big)]
-pub struct FMeshSkeleton {
-    /// Bone index of this bone's parent (255 = no parent)
-    pub parent_bone_index: u8,
-    /// Number of children attached to this bone (0 = no children)
-    pub child_bone_count: u8,
-    /// Index into the array of bone indices (FMesh_t::pnSkeletonIndexArray) of where this bone's child index list begins
-    pub child_array_start_index: u8,
-}
-
-bitflags! {
-    #[derive(Debug, BinRead, Clone, Copy)]
-    #[br(map = Self::from_bits_retain)]
-    pub struct MeshLightFlags: u32 {
-        const NONE     = 0x00000000;
-        const ENABLE = 0x00000001;
-        const HASDIR = 0x00000002;
-        const HASPOS = 0x00000004;
-
-        const LIGHT_ATTACHED = 0x00000008;
-        const NOLIGHT_TERRAIN			= 0x00000010;
-        const DONT_LIGHT_UNATTACHED	= 0x00000020;
-
-        const PER_PIXEL				= 0x00000040;
-
-        const MESH_MUST_BE_PER_PIXEL	= 0x00000080;
+pub fn create_bevy_mesh(
+    buffer: &GCVertexBuffer,
+    report: &mut LoadReport,
+) -> Result<Mesh, VertexBufferError> {
+    buffer.validate_stride()?;
 
-        const ENGINE_LIGHT			= 0x00000100;
-        const LIGHTMAP_LIGHT			= 0x00000200;
-        const UNIQUE_LIGHTMAP			= 0x00000400;
-
-        const CORONA					= 0x00000400;
-        const CORONA_PROXFADE			= 0x00000800;
-        const CORONA_ONLY				= 0x00001000;
-
-        const CAST_SHADOWS			= 0x00002000;
-
-        const CORONA_WORLDSPACE		= 0x00004000;
-
-        const GAMEPLAY_LIGHT			= 0x00008000;
-
-        const DYNAMIC_ONLY			= 0x40000000;
-        const INCLUDE					= 0x80000000;
+    let unknown_flags = buffer.flags.bits() & !GCVertexBufferFlags::all().bits();
+    if unknown_flags != 0 {
+        report.warn(format!("unknown vertex buffer flags {:#x}", unknown_flags));
     }
-}
 
-#[derive(Debug, BinRead)]
-#[br(big, repr = u8)]
-pub enum LightType {
-    Dir = 0,
-    Omni = 1,
-    Spot = 2,
-    Ambient = 3,
-}
+    // Fixed point positions are scaled by the fractional bits of the buffer
+    let values: Vec<[f32; 3]> = buffer
+        .positions()
+        .ok_or(VertexBufferError::MissingPositions)?;
+
+    let normals = buffer.normals.value.as_deref();
+    let tangents = normals
+        .and_then(decode_tangents)
+        .filter(|tangents| tangents.len() == values.len());
+    let normals = normals.map(decode_normals).filter(|normals| {
+        let matches = normals.len() == values.len();
+        if !matches {
+            report.warn(format!(
+                "skipped {} normals for {} positions",
+                normals.len(),
+                values.len()
+            ));
+        }
+        matches
+    });
+
+    let uvs = buffer.st.value.as_deref().map(decode_uvs).filter(|uvs| {
+        let matches = uvs.len() == values.len();
+        if !matches {
+            report.warn(format!(
+                "skipped {} texture coordinates for {} positions",
+                uvs.len(),
+                values.len()
+            ));
+        }
+        matches
+    });
+
+    // Colors are indexed separately by the display lists, they can only
+    // be used directly when there is a color for every position
+    let colors = buffer
+        .diffuse
+        .value
+        .as_deref()
+        .filter(|colors| colors.len() == values.len())
+        .map(|colors| colors.iter().map(GCColor::to_rgba).collect::<Vec<_>>());
 
-#[derive(Debug, BinRead)]
-#[br(big)]
-pub struct FMeshLight {
-    pub name: FixedString<FLIGHT_NAME_LENGTH>,
-    pub per_pixel_tex_name: FixedString<FLIGHT_TEXTURE_NAME_LENGTH>,
-    pub corona_tex_name: FixedString<FLIGHT_TEXTURE_NAME_LENGTH>,
-    pub flags: MeshLightFlags,
-
-    pub light_id: u16,
-    pub light_type: LightType,
-    /// Index into the parent model's bone (-1 if there is no parent bone)
-    pub parent_bone_index: i8,
-
-    /// Light intensity to be multiplied by each component (0.0f to 1.0f)
-    pub intensity: f32,
-    /// Light color motif (RGBA components range from 0.0f to 1.0f). Alpha is not used.
-    pub motif: RawColorMotif,
-    /// Light position and radius in model space (ignored for directional lights)
-    pub influence: RawSphere,
-    /// Light orientation in model space (or world space if not attached to an object).  Direction (away from source) is in m_vFront (dir and spot)
-    pub orientation: RawMatrix4x3f,
-
-    /// Spotlight inner full-angle in radians
-    pub spot_inner_radians: f32,
-    /// Spotlight outer full-angle in radians
-    pub spot_outer_radians: f32,
-
-    pub corona_scale: f32,
-}
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleStrip)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, values);
 
-#[derive(Debug, BinRead)]
-#[br(big)]
-pub struct FMeshMaterial {
-    pub shader_light_registers: PtrOffset,
-    pub shader_surface_registers: PtrOffset,
-
-    pub light_shader_index: u8,
-    pub specular_shader_index: u8,
-    pub surface_shader_index: u16,
-
-    pub part_id_mask: u32,
-    pub platform_data: PtrOffset,
-
-    pub lod_mask: u8,
-    pub depth_bias_level: u8,
-    pub base_st_sets: u8,
-    pub light_map_st_sets: u8,
-    pub tex_layer_id_index: [u8; 4],
-    pub affect_angle: f32,
-    pub comp_affect_normal: [i8; 3],
-    pub affect_bone_id: i8,
+    if let Some(normals) = normals {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    }
 
-    #[br(pad_after = 1)]
-    pub compressed_radius: u8,
+    if let Some(tangents) = tangents {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_TANGENT, tangents);
+    }
 
-    pub material_flags: u16,
-    pub draw_key: u32,
-    pub material_tint: RawColorRGB,
-    pub average_vert_pos: RawVec3f,
-    pub dl_hash_key: u32,
-}
+    if let Some(uvs) = uvs {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    }
 
-#[derive(Debug, BinRead)]
-#[br(big)]
-pub struct FMeshTexLayerID {
-    pub tex_layer_id: u8,
-    pub flags: u8,
+    if let Some(colors) = colors {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    }
 
-    pub flip_page_count: u8,
-    pub frames_per_flip: u8,
-    pub flip_palette: PtrOffset,
+    normalize_winding(&mut mesh, Winding::GAMECUBE);
 
-    pub scroll_st_per_second: RawVec2f,
-    pub uv_degree_rotation_per_second: f32,
-    #[br(pad_after = 2)]
-    pub compresed_uv_rot_anchor: [u8; 2],
+    Ok(mesh)
 }
 
 #[cfg(test)]
 mod test {
-    use std::{fs::File, io::Seek, os::windows::fs::MetadataExt};
+    use std::{fs::File, io::Seek};
 
     use bevy::log::debug;
     use binrw::BinRead;
-
-    use crate::formats::mesh::mesh_raw_old::FMesh;
+    use openglitch_formats::mesh::FMesh;
 
     #[test]
     fn test_load_mesh() {
         let mut file = File::open("data/ape/gcdggltch00.ape").unwrap();
-        let mut header: FMesh = FMesh::read(&mut file).unwrap();
-        println!("Length: {}", file.metadata().unwrap().file_size());
-        dbg!(
-            &header.bound_sphere,
-            &header.bound_box_min,
-            &header.bound_box_max
+        let header: FMesh = FMesh::read_be(&mut file).unwrap();
+        debug!(
+            length = file.metadata().unwrap().len(),
+            bound_sphere = ?header.bound_sphere,
+            bound_box_min = ?header.bound_box_min,
+            bound_box_max = ?header.bound_box_max,
+            "loaded mesh header"
         );
     }
 }
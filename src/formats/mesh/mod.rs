@@ -1,2 +1,19 @@
+pub mod asset;
+pub mod colors;
+pub mod display_list;
+pub mod dl_container;
 pub mod fixed;
+#[cfg(test)]
+pub(crate) mod fixture;
+pub mod lights;
+pub mod loader;
+pub mod material;
 pub mod mesh_raw_old;
+pub mod normals;
+pub mod positions;
+pub mod skeleton;
+pub mod skinning;
+pub mod submesh;
+pub mod textures;
+pub mod uvs;
+pub mod winding;
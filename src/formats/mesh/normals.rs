@@ -0,0 +1,207 @@
+//! Decoding of the fixed point GameCube vertex normals, along with the
+//! binormals and tangents of the NBT normals used for bump mapping
+
+use super::mesh_raw_old::{GCMeshSkin, GCNormal, GCVertexBuffer, GCNBT8};
+
+/// Fractional bits of 16 bit normals, fixed by the GX hardware
+pub const NORMAL_S16_FRAC: u32 = 14;
+/// Fractional bits of 8 bit normals, fixed by the GX hardware
+pub const NORMAL_S8_FRAC: u32 = 6;
+
+/// Decodes a 16 bit fixed point normal
+pub fn decode_normal_s16(value: [i16; 3]) -> [f32; 3] {
+    let scale = 1.0 / (1u32 << NORMAL_S16_FRAC) as f32;
+    normalize(value.map(|value| value as f32 * scale))
+}
+
+/// Decodes an 8 bit fixed point normal
+pub fn decode_normal_s8(value: [i8; 3]) -> [f32; 3] {
+    let scale = 1.0 / (1u32 << NORMAL_S8_FRAC) as f32;
+    normalize(value.map(|value| value as f32 * scale))
+}
+
+/// Decodes the normals of a vertex buffer, NBT normals only
+/// contribute their normal component
+pub fn decode_normals(normals: &[GCNormal]) -> Vec<[f32; 3]> {
+    normals
+        .iter()
+        .map(|value| match value {
+            GCNormal::Nbt(nbt) => decode_normal_s8(nbt.normal),
+            GCNormal::Norm16(norm) => decode_normal_s16(norm.normal),
+        })
+        .collect()
+}
+
+/// Decodes the tangent of an NBT normal, the handedness is stored in the
+/// last component so the binormal is the cross product of the normal and
+/// tangent scaled by it
+pub fn decode_tangent(nbt: &GCNBT8) -> [f32; 4] {
+    let normal = decode_normal_s8(nbt.normal);
+    let binormal = decode_normal_s8(nbt.binormal);
+    let [x, y, z] = decode_normal_s8(nbt.tangents);
+
+    let cross = [
+        normal[1] * z - normal[2] * y,
+        normal[2] * x - normal[0] * z,
+        normal[0] * y - normal[1] * x,
+    ];
+    let handedness = cross
+        .iter()
+        .zip(binormal.iter())
+        .map(|(a, b)| a * b)
+        .sum::<f32>();
+    let w = if handedness < 0.0 { -1.0 } else { 1.0 };
+
+    [x, y, z, w]
+}
+
+/// Decodes the tangents of a vertex buffer, none unless every normal is
+/// an NBT normal
+pub fn decode_tangents(normals: &[GCNormal]) -> Option<Vec<[f32; 4]>> {
+    normals
+        .iter()
+        .map(|value| match value {
+            GCNormal::Nbt(nbt) => Some(decode_tangent(nbt)),
+            GCNormal::Norm16(_) => None,
+        })
+        .collect()
+}
+
+impl GCVertexBuffer {
+    /// Unit length normal of each position, none when the buffer has
+    /// no normals
+    pub fn normals(&self) -> Option<Vec<[f32; 3]>> {
+        self.normals.value.as_deref().map(decode_normals)
+    }
+
+    /// Tangent of each position, none when the buffer has no NBT normals
+    pub fn tangents(&self) -> Option<Vec<[f32; 4]>> {
+        self.normals.value.as_deref().and_then(decode_tangents)
+    }
+}
+
+impl GCMeshSkin {
+    /// Unit length normal of each skinned vertex, the normals share the
+    /// `frac` fractional bits of the positions
+    pub fn normals(&self, frac: u8) -> Option<Vec<[f32; 3]>> {
+        let vertices = self.skinned_verts.value.as_ref()?;
+        Some(
+            vertices
+                .iter()
+                .map(|vertex| normalize(vertex.decode(frac).1))
+                .collect(),
+        )
+    }
+}
+
+/// Normalizes away the quantization error, zero length normals are left as is
+pub(crate) fn normalize(value: [f32; 3]) -> [f32; 3] {
+    let length = value.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if length == 0.0 {
+        return value;
+    }
+    value.map(|value| value / length)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::formats::{
+        mesh::mesh_raw_old::{GCMeshSkin, GCNorm16, GCNormal, GCSkinPosNorm, GCNBT8},
+        types::NullableFilePtr,
+    };
+
+    use super::{decode_normal_s16, decode_normal_s8, decode_tangent, decode_tangents};
+
+    fn assert_close(left: [f32; 3], right: [f32; 3]) {
+        for (a, b) in left.iter().zip(right.iter()) {
+            assert!((a - b).abs() < 0.001, "{left:?} != {right:?}");
+        }
+    }
+
+    #[test]
+    fn test_flat_surface_normals() {
+        assert_close(decode_normal_s16([0, 16384, 0]), [0.0, 1.0, 0.0]);
+        assert_close(decode_normal_s16([0, 0, -16384]), [0.0, 0.0, -1.0]);
+        assert_close(decode_normal_s8([64, 0, 0]), [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_round_surface_normals() {
+        // Normals sampled around a sphere remain unit length
+        for step in 0..16 {
+            let angle = step as f32 * std::f32::consts::TAU / 16.0;
+            let raw = [
+                (angle.cos() * 16384.0) as i16,
+                (angle.sin() * 16384.0) as i16,
+                0,
+            ];
+            assert_close(decode_normal_s16(raw), [angle.cos(), angle.sin(), 0.0]);
+        }
+    }
+
+    #[test]
+    fn test_nbt_tangents() {
+        let nbt = GCNBT8 {
+            normal: [0, 64, 0],
+            binormal: [0, 0, 64],
+            tangents: [64, 0, 0],
+        };
+        // Normal cross tangent is -Z, opposite to the binormal
+        assert_close_tangent(decode_tangent(&nbt), [1.0, 0.0, 0.0, -1.0]);
+
+        let flipped = GCNBT8 {
+            binormal: [0, 0, -64],
+            ..nbt
+        };
+        assert_close_tangent(decode_tangent(&flipped), [1.0, 0.0, 0.0, 1.0]);
+
+        // Tangents are only known when every normal has them
+        let normals = [
+            GCNormal::Nbt(flipped),
+            GCNormal::Norm16(GCNorm16 {
+                normal: [0, 16384, 0],
+            }),
+        ];
+        assert!(decode_tangents(&normals).is_none());
+        assert_eq!(
+            decode_tangents(&normals[..1]).map(|value| value.len()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_skinned_normals() {
+        let skin = GCMeshSkin {
+            trans_desc_count: 0,
+            td1_mtx_count: 0,
+            td2_mtx_count: 0,
+            td3_or_4mtx_count: 0,
+            trans_desc: NullableFilePtr {
+                ptr: 0,
+                value: None,
+            },
+            skinned_verts_count: 1,
+            skinned_verts: NullableFilePtr {
+                ptr: 1,
+                value: Some(vec![GCSkinPosNorm {
+                    position: [0, 0, 0],
+                    // Quantized normals aren't exactly unit length
+                    normal: [0, 30, 40],
+                }]),
+            },
+            weights: NullableFilePtr {
+                ptr: 0,
+                value: None,
+            },
+        };
+
+        let normals = skin.normals(4).unwrap();
+        assert_close(normals[0], [0.0, 0.6, 0.8]);
+    }
+
+    fn assert_close_tangent(left: [f32; 4], right: [f32; 4]) {
+        for (a, b) in left.iter().zip(right.iter()) {
+            assert!((a - b).abs() < 0.001, "{left:?} != {right:?}");
+        }
+    }
+}
@@ -0,0 +1,366 @@
+//! Decoding and re-encoding of the fixed point GameCube vertex positions.
+//! Positions are scaled by the fractional bits of their vertex buffer, a
+//! decoded position encodes back to the same fixed point value so edited
+//! geometry can be written back without drifting
+
+use thiserror::Error;
+
+use super::mesh_raw_old::{GCMeshSkin, GCPosType, GCSkinPosNorm, GCVertBufferPos, GCVertexBuffer};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PositionError {
+    #[error("vertex buffer has no positions")]
+    MissingPositions,
+    #[error("position {index} is out of bounds for {count} positions")]
+    OutOfBounds { index: usize, count: usize },
+    #[error("position {value:?} can't be stored as {pos_type:?}")]
+    OutOfRange {
+        value: [f32; 3],
+        pos_type: GCPosType,
+    },
+}
+
+/// Scale of a single step of a fixed point value with `frac` fractional bits
+fn fixed_scale(frac: u8) -> f32 {
+    2f32.powi(-(frac as i32))
+}
+
+/// Quantizes the value to the nearest step, none when the value is outside
+/// the range of the component type
+fn quantize(value: f32, frac: u8, min: f32, max: f32) -> Option<f32> {
+    let value = (value / fixed_scale(frac)).round();
+    (min..=max).contains(&value).then_some(value)
+}
+
+fn quantize_s8(value: [f32; 3], frac: u8) -> Option<[i8; 3]> {
+    let [x, y, z] = value.map(|value| quantize(value, frac, i8::MIN as f32, i8::MAX as f32));
+    Some([x? as i8, y? as i8, z? as i8])
+}
+
+fn quantize_s16(value: [f32; 3], frac: u8) -> Option<[i16; 3]> {
+    let [x, y, z] = value.map(|value| quantize(value, frac, i16::MIN as f32, i16::MAX as f32));
+    Some([x? as i16, y? as i16, z? as i16])
+}
+
+/// Decodes a position with `frac` fractional bits, float positions aren't
+/// scaled
+pub fn decode_position(value: &GCVertBufferPos, frac: u8) -> [f32; 3] {
+    let scale = fixed_scale(frac);
+    match *value {
+        GCVertBufferPos::S8 { x, y, z } => [x, y, z].map(|value| value as f32 * scale),
+        GCVertBufferPos::S16 { x, y, z } => [x, y, z].map(|value| value as f32 * scale),
+        GCVertBufferPos::F32 { x, y, z } => [x, y, z],
+    }
+}
+
+/// Encodes a position as the provided type with `frac` fractional bits,
+/// rounding to the nearest fixed point step
+pub fn encode_position(
+    value: [f32; 3],
+    pos_type: GCPosType,
+    frac: u8,
+) -> Result<GCVertBufferPos, PositionError> {
+    let out_of_range = PositionError::OutOfRange { value, pos_type };
+    Ok(match pos_type {
+        GCPosType::S8 => {
+            let [x, y, z] = quantize_s8(value, frac).ok_or(out_of_range)?;
+            GCVertBufferPos::S8 { x, y, z }
+        }
+        GCPosType::S16 => {
+            let [x, y, z] = quantize_s16(value, frac).ok_or(out_of_range)?;
+            GCVertBufferPos::S16 { x, y, z }
+        }
+        GCPosType::F32 => {
+            let [x, y, z] = value;
+            GCVertBufferPos::F32 { x, y, z }
+        }
+    })
+}
+
+impl GCVertexBuffer {
+    /// Decoded positions of the buffer, none when the buffer has no positions
+    pub fn positions(&self) -> Option<Vec<[f32; 3]>> {
+        let positions = self.position.value.as_ref()?;
+        Some(
+            positions
+                .iter()
+                .map(|value| decode_position(value, self.pos_frac))
+                .collect(),
+        )
+    }
+
+    /// Decoded position at the index, none when the buffer has no position
+    /// at the index
+    pub fn get_position(&self, index: usize) -> Option<[f32; 3]> {
+        let position = self.position.value.as_ref()?.get(index)?;
+        Some(decode_position(position, self.pos_frac))
+    }
+
+    /// Replaces the position at the index, the position is quantized to the
+    /// position type and fractional bits of the buffer
+    pub fn set_position(&mut self, index: usize, value: [f32; 3]) -> Result<(), PositionError> {
+        let encoded = encode_position(value, self.pos_type, self.pos_frac)?;
+        let positions = self
+            .position
+            .value
+            .as_mut()
+            .ok_or(PositionError::MissingPositions)?;
+        let count = positions.len();
+        let position = positions
+            .get_mut(index)
+            .ok_or(PositionError::OutOfBounds { index, count })?;
+        *position = encoded;
+        Ok(())
+    }
+}
+
+impl GCSkinPosNorm {
+    /// Decodes the position and normal, both are 16 bit fixed point with
+    /// `frac` fractional bits
+    pub fn decode(&self, frac: u8) -> ([f32; 3], [f32; 3]) {
+        let scale = fixed_scale(frac);
+        (
+            self.position.map(|value| value as f32 * scale),
+            self.normal.map(|value| value as f32 * scale),
+        )
+    }
+
+    /// Packs the position and normal as 16 bit fixed point with `frac`
+    /// fractional bits
+    pub fn encode(position: [f32; 3], normal: [f32; 3], frac: u8) -> Result<Self, PositionError> {
+        let out_of_range = |value| PositionError::OutOfRange {
+            value,
+            pos_type: GCPosType::S16,
+        };
+        Ok(GCSkinPosNorm {
+            position: quantize_s16(position, frac).ok_or_else(|| out_of_range(position))?,
+            normal: quantize_s16(normal, frac).ok_or_else(|| out_of_range(normal))?,
+        })
+    }
+}
+
+impl GCMeshSkin {
+    /// Decoded position of the skinned vertex at the index
+    pub fn get_position(&self, index: usize, frac: u8) -> Option<[f32; 3]> {
+        let vertex = self.skinned_verts.value.as_ref()?.get(index)?;
+        Some(vertex.decode(frac).0)
+    }
+
+    /// Replaces the position of the skinned vertex at the index, the pair is
+    /// re-packed with the normal of the vertex left as is. `frac` is the
+    /// position fractional bits of the vertex buffer the skin belongs to
+    pub fn set_position(
+        &mut self,
+        index: usize,
+        value: [f32; 3],
+        frac: u8,
+    ) -> Result<(), PositionError> {
+        let vertices = self
+            .skinned_verts
+            .value
+            .as_mut()
+            .ok_or(PositionError::MissingPositions)?;
+        let count = vertices.len();
+        let vertex = vertices
+            .get_mut(index)
+            .ok_or(PositionError::OutOfBounds { index, count })?;
+        let (_, normal) = vertex.decode(frac);
+        *vertex = GCSkinPosNorm::encode(value, normal, frac)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::render::mesh::{Mesh, VertexAttributeValues};
+
+    use crate::formats::{
+        mesh::mesh_raw_old::{
+            create_bevy_mesh, GCMeshSkin, GCPosType, GCSkinPosNorm, GCVertBufferPos,
+            GCVertexBuffer, GCVertexBufferFlags, GXAttrType, VertexBufferError,
+        },
+        report::LoadReport,
+        types::NullableFilePtr,
+    };
+
+    use super::{decode_position, encode_position, PositionError};
+
+    fn nullable<T>(value: T) -> NullableFilePtr<T> {
+        NullableFilePtr {
+            ptr: 1,
+            value: Some(value),
+        }
+    }
+
+    fn vertex_buffer(
+        pos_type: GCPosType,
+        pos_frac: u8,
+        positions: Vec<GCVertBufferPos>,
+    ) -> GCVertexBuffer {
+        GCVertexBuffer {
+            flags: GCVertexBufferFlags::NONE,
+            pos_count: positions.len() as u16,
+            pos_type,
+            pos_idx_type: GXAttrType::Index16,
+            pos_stride: pos_type.size() as u8,
+            pos_frac,
+            diffuse_count: 0,
+            color_idx_type: GXAttrType::None,
+            vertex_format: 0,
+            position: nullable(positions),
+            diffuse: NullableFilePtr {
+                ptr: 0,
+                value: None,
+            },
+            st: NullableFilePtr {
+                ptr: 0,
+                value: None,
+            },
+            normals: NullableFilePtr {
+                ptr: 0,
+                value: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_position_round_trip() {
+        // Every fixed point value encodes back to itself
+        for frac in [0, 4, 7] {
+            for x in i8::MIN..=i8::MAX {
+                let raw = GCVertBufferPos::S8 { x, y: x / 2, z: 1 };
+                let decoded = decode_position(&raw, frac);
+                let encoded = encode_position(decoded, GCPosType::S8, frac).unwrap();
+                assert_eq!(decode_position(&encoded, frac), decoded);
+            }
+        }
+
+        let raw = GCVertBufferPos::S16 {
+            x: i16::MIN,
+            y: i16::MAX,
+            z: -3,
+        };
+        let decoded = decode_position(&raw, 12);
+        assert_eq!(decoded[2], -3.0 / 4096.0);
+        let encoded = encode_position(decoded, GCPosType::S16, 12).unwrap();
+        assert!(matches!(
+            encoded,
+            GCVertBufferPos::S16 {
+                x: i16::MIN,
+                y: i16::MAX,
+                z: -3
+            }
+        ));
+    }
+
+    #[test]
+    fn test_bevy_mesh_positions() {
+        // Mesh positions are scaled by the fractional bits like the decoded ones
+        let mut buffer = vertex_buffer(
+            GCPosType::S16,
+            4,
+            vec![
+                GCVertBufferPos::S16 { x: 16, y: -8, z: 0 },
+                GCVertBufferPos::S16 { x: 0, y: 32, z: 1 },
+                GCVertBufferPos::S16 { x: 0, y: 0, z: 0 },
+            ],
+        );
+        let mesh = create_bevy_mesh(&buffer, &mut LoadReport::new("test")).unwrap();
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("mesh has no positions");
+        };
+        assert_eq!(positions, &buffer.positions().unwrap());
+        assert_eq!(positions[0], [1.0, -0.5, 0.0]);
+
+        // Buffers without positions are an error rather than a panic
+        buffer.position = NullableFilePtr {
+            ptr: 0,
+            value: None,
+        };
+        assert!(matches!(
+            create_bevy_mesh(&buffer, &mut LoadReport::new("test")),
+            Err(VertexBufferError::MissingPositions)
+        ));
+    }
+
+    #[test]
+    fn test_set_position() {
+        let mut buffer = vertex_buffer(
+            GCPosType::S16,
+            8,
+            vec![GCVertBufferPos::S16 { x: 0, y: 0, z: 0 }; 2],
+        );
+
+        // Rounded to the nearest 1/256
+        buffer.set_position(1, [1.5, -0.25, 0.001]).unwrap();
+        assert_eq!(buffer.get_position(1), Some([1.5, -0.25, 0.0]));
+        assert_eq!(buffer.get_position(0), Some([0.0, 0.0, 0.0]));
+        assert_eq!(buffer.get_position(2), None);
+
+        assert_eq!(
+            buffer.set_position(2, [0.0; 3]),
+            Err(PositionError::OutOfBounds { index: 2, count: 2 })
+        );
+        // 128 * 256 is past the largest 16 bit value
+        assert_eq!(
+            buffer.set_position(0, [128.0, 0.0, 0.0]),
+            Err(PositionError::OutOfRange {
+                value: [128.0, 0.0, 0.0],
+                pos_type: GCPosType::S16
+            })
+        );
+        assert!(buffer.set_position(0, [f32::NAN, 0.0, 0.0]).is_err());
+
+        let mut buffer = vertex_buffer(
+            GCPosType::F32,
+            8,
+            vec![
+                GCVertBufferPos::F32 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0
+                };
+                1
+            ],
+        );
+        buffer.set_position(0, [128.0, 0.1, 0.0]).unwrap();
+        assert_eq!(buffer.get_position(0), Some([128.0, 0.1, 0.0]));
+    }
+
+    #[test]
+    fn test_set_skinned_position() {
+        let mut skin = GCMeshSkin {
+            trans_desc_count: 0,
+            td1_mtx_count: 0,
+            td2_mtx_count: 0,
+            td3_or_4mtx_count: 0,
+            trans_desc: NullableFilePtr {
+                ptr: 0,
+                value: None,
+            },
+            skinned_verts_count: 1,
+            skinned_verts: nullable(vec![GCSkinPosNorm {
+                position: [16, 32, 48],
+                normal: [0, 16, 0],
+            }]),
+            weights: NullableFilePtr {
+                ptr: 0,
+                value: None,
+            },
+        };
+
+        assert_eq!(skin.get_position(0, 4), Some([1.0, 2.0, 3.0]));
+        skin.set_position(0, [-1.0, 0.5, 2.0], 4).unwrap();
+
+        // The normal is re-packed unchanged alongside the new position
+        let vertex = &skin.skinned_verts.value.as_ref().unwrap()[0];
+        assert_eq!(vertex.position, [-16, 8, 32]);
+        assert_eq!(vertex.normal, [0, 16, 0]);
+        assert_eq!(
+            skin.set_position(1, [0.0; 3], 4),
+            Err(PositionError::OutOfBounds { index: 1, count: 1 })
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Skeleton hierarchy of a mesh built from the at rest bone matrices. Each
+//! bone is placed relative to its parent using the at rest bone to parent
+//! matrix, root bones are placed in model space
+
+use bevy::math::Mat4;
+use openglitch_formats::mesh::{FMeshBone, MeshBoneFlags};
+use thiserror::Error;
+
+/// Parent index of bones without a parent
+const NO_PARENT: u8 = 255;
+
+#[derive(Debug, Error)]
+pub enum SkeletonError {
+    #[error("bone {bone} has parent {parent} outside the {count} bones")]
+    ParentOutOfRange {
+        bone: usize,
+        parent: usize,
+        count: usize,
+    },
+    #[error("bone {0} is its own ancestor")]
+    Cycle(usize),
+}
+
+/// Bone of a [skeleton_bones] hierarchy
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkeletonBone {
+    pub name: String,
+    /// Index of the parent bone
+    pub parent: Option<usize>,
+    /// At rest transform relative to the parent, or model space for roots
+    pub local: Mat4,
+    /// At rest transform of the bone in model space
+    pub bone_to_model: Mat4,
+    /// Bone deforms skinned vertices
+    pub skinned: bool,
+}
+
+/// Builds the skeleton hierarchy of the provided bones, parents are
+/// checked to be in range and to not form cycles
+pub fn skeleton_bones(bones: &[FMeshBone]) -> Result<Vec<SkeletonBone>, SkeletonError> {
+    let count = bones.len();
+    let parents: Vec<Option<usize>> = bones
+        .iter()
+        .enumerate()
+        .map(|(bone, value)| match value.skeleton.parent_bone_index {
+            NO_PARENT => Ok(None),
+            parent if (parent as usize) < count => Ok(Some(parent as usize)),
+            parent => Err(SkeletonError::ParentOutOfRange {
+                bone,
+                parent: parent as usize,
+                count,
+            }),
+        })
+        .collect::<Result<_, _>>()?;
+
+    // Any chain longer than the bone count must revisit a bone
+    for bone in 0..count {
+        let mut current = parents[bone];
+        for _ in 0..count {
+            match current {
+                Some(parent) if parent == bone => return Err(SkeletonError::Cycle(bone)),
+                Some(parent) => current = parents[parent],
+                None => break,
+            }
+        }
+    }
+
+    Ok(bones
+        .iter()
+        .zip(parents)
+        .map(|(bone, parent)| SkeletonBone {
+            name: bone.name.to_string(),
+            parent,
+            local: match parent {
+                Some(_) => Mat4::from(&bone.at_rest_bone_to_parent),
+                None => Mat4::from(&bone.at_rest_bone_to_model),
+            },
+            bone_to_model: Mat4::from(&bone.at_rest_bone_to_model),
+            skinned: bone.flags.contains(MeshBoneFlags::SKINNEDBONE),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use binrw::BinRead;
+    use openglitch_formats::mesh::FMeshBone;
+
+    use super::{skeleton_bones, SkeletonError};
+
+    /// Offset of the skeleton within a bone
+    const SKELETON_OFFSET: usize = 32 + 48 * 4 + 16;
+
+    fn bone(parent: u8) -> FMeshBone {
+        let mut bytes = vec![0u8; FMeshBone::SIZE];
+        bytes[SKELETON_OFFSET] = parent;
+        let mut cursor = Cursor::new(bytes);
+        let bone = FMeshBone::read_be(&mut cursor).unwrap();
+        // The whole bone was read including its padding
+        assert_eq!(cursor.position() as usize, FMeshBone::SIZE);
+        bone
+    }
+
+    #[test]
+    fn test_skeleton_bones() {
+        let bones = [bone(255), bone(0), bone(1)];
+        let skeleton = skeleton_bones(&bones).unwrap();
+        assert_eq!(skeleton[0].parent, None);
+        assert_eq!(skeleton[2].parent, Some(1));
+
+        assert!(matches!(
+            skeleton_bones(&[bone(255), bone(4)]),
+            Err(SkeletonError::ParentOutOfRange { bone: 1, .. })
+        ));
+        assert!(matches!(
+            skeleton_bones(&[bone(1), bone(0)]),
+            Err(SkeletonError::Cycle(0))
+        ));
+    }
+}
@@ -0,0 +1,548 @@
+//! Conversion of skinned geometry into meshes for bevy's GPU skinning,
+//! geometry is split into partitions that each fit within the joint
+//! palette limit of a single skinned mesh
+
+use std::collections::HashMap;
+
+use bevy::{
+    ecs::entity::Entity,
+    math::{Mat3, Mat4, Vec3},
+    render::{
+        mesh::{Indices, Mesh, VertexAttributeValues},
+        render_resource::PrimitiveTopology,
+    },
+};
+use openglitch_formats::mesh::FMeshBone;
+
+use crate::formats::types::RawMatrix4x3f;
+
+use super::{mesh_raw_old::GCMeshSkin, normals::normalize};
+
+/// Maximum number of joints a single bevy skinned mesh can reference
+pub const MAX_JOINTS: usize = 256;
+/// Number of joint influences per vertex
+pub const MAX_INFLUENCES: usize = 4;
+
+/// Vertex influenced by up to [MAX_INFLUENCES] joints
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkinnedVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    /// Indices of the influencing joints within the skeleton
+    pub joints: [u16; MAX_INFLUENCES],
+    /// Weights of each influence, unused influences have zero weight
+    pub weights: [f32; MAX_INFLUENCES],
+}
+
+impl SkinnedVertex {
+    fn used_joints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.joints
+            .iter()
+            .zip(self.weights.iter())
+            .filter(|(_, weight)| **weight > 0.0)
+            .map(|(joint, _)| *joint)
+    }
+}
+
+/// Portion of a skinned mesh whose joints fit within a single palette
+#[derive(Debug, Default)]
+pub struct SkinPartition {
+    pub vertices: Vec<SkinnedVertex>,
+    /// Triangle list indices into the partition vertices
+    pub indices: Vec<u32>,
+    /// Skeleton joint index for each palette slot, the vertex joint
+    /// indices of the partition index into this palette
+    pub palette: Vec<u16>,
+}
+
+impl SkinPartition {
+    /// Creates the bevy mesh for the partition, joint indices refer
+    /// to the partition palette
+    pub fn to_mesh(&self) -> Mesh {
+        let positions: Vec<[f32; 3]> = self.vertices.iter().map(|value| value.position).collect();
+        let normals: Vec<[f32; 3]> = self.vertices.iter().map(|value| value.normal).collect();
+        let joints: Vec<[u16; 4]> = self.vertices.iter().map(|value| value.joints).collect();
+        let weights: Vec<[f32; 4]> = self.vertices.iter().map(|value| value.weights).collect();
+
+        Mesh::new(PrimitiveTopology::TriangleList)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(
+                Mesh::ATTRIBUTE_JOINT_INDEX,
+                VertexAttributeValues::Uint16x4(joints),
+            )
+            .with_inserted_attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT, weights)
+            .with_indices(Some(Indices::U32(self.indices.clone())))
+    }
+
+    /// Maps the palette onto the joint entities of the skeleton for
+    /// use as the joints of the partition's `SkinnedMesh`
+    pub fn joint_entities(&self, skeleton: &[Entity]) -> Vec<Entity> {
+        self.palette
+            .iter()
+            .map(|joint| skeleton[*joint as usize])
+            .collect()
+    }
+
+    /// Maps the palette onto the inverse bind poses of the skeleton for use
+    /// as the partition's `SkinnedMeshInverseBindposes`, joints without a
+    /// bind pose use the identity
+    pub fn palette_bindposes(&self, inverse_bindposes: &[Mat4]) -> Vec<Mat4> {
+        self.palette
+            .iter()
+            .map(|joint| {
+                inverse_bindposes
+                    .get(*joint as usize)
+                    .copied()
+                    .unwrap_or(Mat4::IDENTITY)
+            })
+            .collect()
+    }
+}
+
+/// Inverse bind pose of each bone, the at rest model to bone matrix takes
+/// a vertex from model space into the space of the bone
+pub fn inverse_bindposes(bones: &[FMeshBone]) -> Vec<Mat4> {
+    bones
+        .iter()
+        .map(|bone| Mat4::from(&bone.at_rest_model_to_bone))
+        .collect()
+}
+
+/// Builder for the partition currently being filled
+#[derive(Default)]
+struct PartitionBuilder {
+    partition: SkinPartition,
+    /// Lookup from skeleton joint to palette slot
+    palette_slots: HashMap<u16, u16>,
+    /// Lookup from source vertex to partition vertex
+    vertex_slots: HashMap<u32, u32>,
+}
+
+impl PartitionBuilder {
+    /// Number of joints the triangle would add to the palette
+    fn new_joints(&self, triangle: &[SkinnedVertex; 3]) -> usize {
+        let mut joints: Vec<u16> = triangle
+            .iter()
+            .flat_map(|vertex| vertex.used_joints())
+            .filter(|joint| !self.palette_slots.contains_key(joint))
+            .collect();
+        joints.sort_unstable();
+        joints.dedup();
+        joints.len()
+    }
+
+    fn push_vertex(&mut self, index: u32, vertex: &SkinnedVertex) -> u32 {
+        if let Some(slot) = self.vertex_slots.get(&index) {
+            return *slot;
+        }
+
+        let mut local = *vertex;
+        for (joint, weight) in local.joints.iter_mut().zip(vertex.weights.iter()) {
+            if *weight <= 0.0 {
+                *joint = 0;
+                continue;
+            }
+
+            let source = *joint;
+            let palette = &mut self.partition.palette;
+            *joint = *self.palette_slots.entry(source).or_insert_with(|| {
+                palette.push(source);
+                (palette.len() - 1) as u16
+            });
+        }
+
+        let slot = self.partition.vertices.len() as u32;
+        self.partition.vertices.push(local);
+        self.vertex_slots.insert(index, slot);
+        slot
+    }
+}
+
+/// Splits skinned triangle list geometry into partitions that each
+/// reference at most `max_joints` joints
+pub fn partition_skinned(
+    vertices: &[SkinnedVertex],
+    indices: &[u32],
+    max_joints: usize,
+) -> Vec<SkinPartition> {
+    // A single triangle can reference every influence of its vertices
+    let max_joints = max_joints.max(MAX_INFLUENCES * 3);
+
+    let mut out = Vec::new();
+    let mut current = PartitionBuilder::default();
+
+    for triangle in indices.chunks_exact(3) {
+        let triangle_vertices = [
+            vertices[triangle[0] as usize],
+            vertices[triangle[1] as usize],
+            vertices[triangle[2] as usize],
+        ];
+
+        if current.partition.palette.len() + current.new_joints(&triangle_vertices) > max_joints {
+            out.push(std::mem::take(&mut current).partition);
+        }
+
+        for (index, vertex) in triangle.iter().zip(triangle_vertices.iter()) {
+            let slot = current.push_vertex(*index, vertex);
+            current.partition.indices.push(slot);
+        }
+    }
+
+    if !current.partition.indices.is_empty() {
+        out.push(current.partition);
+    }
+
+    out
+}
+
+/// Creates the skinned vertices from the GameCube skin data, the skin
+/// is made up of runs of vertices sharing the same (up to 4) matrices.
+/// Positions and normals are fixed point with `frac` fractional bits
+pub fn gc_skinned_vertices(skin: &GCMeshSkin, frac: u8) -> Vec<SkinnedVertex> {
+    let (Some(trans_desc), Some(verts), Some(weights)) = (
+        skin.trans_desc.value.as_ref(),
+        skin.skinned_verts.value.as_ref(),
+        skin.weights.value.as_ref(),
+    ) else {
+        return Vec::new();
+    };
+
+    let mut out = Vec::with_capacity(verts.len());
+    let mut vertex_index = 0;
+
+    for desc in trans_desc {
+        let matrix_count = (desc.matrix_count as usize).min(MAX_INFLUENCES);
+
+        for _ in 0..desc.vert_count {
+            let (Some(vert), Some(vert_weights)) =
+                (verts.get(vertex_index), weights.get(vertex_index))
+            else {
+                return out;
+            };
+            vertex_index += 1;
+
+            let joints = desc.mtx_index.map(|value| value as u16);
+            let mut vertex_weights = [0f32; MAX_INFLUENCES];
+            if matrix_count == 1 {
+                vertex_weights[0] = 1.0;
+            } else {
+                vertex_weights
+                    .iter_mut()
+                    .zip(vert_weights.weights.iter())
+                    .take(matrix_count)
+                    .for_each(|(weight, value)| *weight = *value as f32 / 255.0);
+            }
+
+            // Quantized weights don't always sum to one
+            let total: f32 = vertex_weights.iter().sum();
+            if total > 0.0 {
+                vertex_weights
+                    .iter_mut()
+                    .for_each(|weight| *weight /= total);
+            }
+
+            let (position, normal) = vert.decode(frac);
+            out.push(SkinnedVertex {
+                position,
+                normal: normalize(normal),
+                joints,
+                weights: vertex_weights,
+            });
+        }
+    }
+
+    out
+}
+
+/// Matrix moving a vertex from its at rest position in model space to its
+/// posed position, the vertex is taken into bone space by the at rest
+/// model to bone matrix before the posed bone to model matrix applies
+pub fn joint_matrix(
+    posed_bone_to_model: &RawMatrix4x3f,
+    at_rest_model_to_bone: &RawMatrix4x3f,
+) -> Mat4 {
+    Mat4::from(posed_bone_to_model) * Mat4::from(at_rest_model_to_bone)
+}
+
+/// Skins a single vertex on the CPU using the same blending as bevy's
+/// skinning shader, the weighted joint matrices are summed before being
+/// applied and normals use the inverse transpose of the blended matrix.
+/// Returns the posed position and normal
+pub fn skin_vertex(vertex: &SkinnedVertex, joint_matrices: &[Mat4]) -> ([f32; 3], [f32; 3]) {
+    let skin = vertex
+        .joints
+        .iter()
+        .zip(vertex.weights.iter())
+        .filter(|(_, weight)| **weight > 0.0)
+        .fold(Mat4::ZERO, |skin, (joint, weight)| {
+            skin + joint_matrices[*joint as usize] * *weight
+        });
+
+    let position = skin.transform_point3(Vec3::from_array(vertex.position));
+    let normal = (Mat3::from_mat4(skin).inverse().transpose() * Vec3::from_array(vertex.normal))
+        .normalize_or_zero();
+
+    (position.to_array(), normal.to_array())
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::{Mat4, Vec3};
+
+    use crate::formats::types::RawMatrix4x3f;
+
+    use super::{
+        joint_matrix, partition_skinned, skin_vertex, SkinnedVertex, MAX_INFLUENCES, MAX_JOINTS,
+    };
+
+    fn vertex(joint: u16) -> SkinnedVertex {
+        SkinnedVertex {
+            position: [joint as f32, 0.0, 0.0],
+            normal: [0.0, 1.0, 0.0],
+            joints: [joint, 0, 0, 0],
+            weights: [1.0, 0.0, 0.0, 0.0],
+        }
+    }
+
+    #[test]
+    fn test_palette_bindposes() {
+        let vertices: Vec<SkinnedVertex> = [4, 2, 7].into_iter().map(vertex).collect();
+        let partitions = partition_skinned(&vertices, &[0, 1, 2], MAX_JOINTS);
+
+        let bindposes: Vec<Mat4> = (0..6)
+            .map(|joint| Mat4::from_translation(Vec3::X * joint as f32))
+            .collect();
+        let palette = partitions[0].palette_bindposes(&bindposes);
+        assert_eq!(palette[0], bindposes[4]);
+        assert_eq!(palette[1], bindposes[2]);
+        // Joint 7 has no bind pose
+        assert_eq!(palette[2], Mat4::IDENTITY);
+    }
+
+    #[test]
+    fn test_partition_palette_limit() {
+        // Each triangle uses 3 unique joints, palette limit of 12 fits 4 triangles
+        let vertices: Vec<SkinnedVertex> = (0..24).map(vertex).collect();
+        let indices: Vec<u32> = (0..24).collect();
+
+        let partitions = partition_skinned(&vertices, &indices, MAX_INFLUENCES * 3);
+        assert_eq!(partitions.len(), 2);
+
+        for partition in &partitions {
+            assert!(partition.palette.len() <= MAX_INFLUENCES * 3);
+            assert_eq!(partition.indices.len(), 12);
+            for vertex in &partition.vertices {
+                let joint = partition.palette[vertex.joints[0] as usize];
+                assert_eq!(joint as f32, vertex.position[0]);
+            }
+        }
+    }
+
+    /// Identity in the engine layout (right, up, front, position rows)
+    const IDENTITY: [[f32; 3]; 4] = [
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [0.0, 0.0, 1.0],
+        [0.0, 0.0, 0.0],
+    ];
+
+    /// Bone of a golden pose, matrices are in the engine memory layout
+    struct GoldenBone {
+        at_rest_model_to_bone: [[f32; 3]; 4],
+        posed_bone_to_model: [[f32; 3]; 4],
+    }
+
+    /// Vertex of a golden pose along with its expected posed values, the
+    /// expected values follow the engine's row vector convention (v * M)
+    struct GoldenVertex {
+        vertex: SkinnedVertex,
+        position: [f32; 3],
+        normal: [f32; 3],
+    }
+
+    fn skinned(
+        position: [f32; 3],
+        normal: [f32; 3],
+        joints: [u16; MAX_INFLUENCES],
+        weights: [f32; MAX_INFLUENCES],
+    ) -> SkinnedVertex {
+        SkinnedVertex {
+            position,
+            normal,
+            joints,
+            weights,
+        }
+    }
+
+    fn assert_golden(bones: &[GoldenBone], vertices: &[GoldenVertex]) {
+        let joints: Vec<_> = bones
+            .iter()
+            .map(|bone| {
+                joint_matrix(
+                    &RawMatrix4x3f {
+                        matrix: bone.posed_bone_to_model,
+                    },
+                    &RawMatrix4x3f {
+                        matrix: bone.at_rest_model_to_bone,
+                    },
+                )
+            })
+            .collect();
+
+        for (index, golden) in vertices.iter().enumerate() {
+            let (position, normal) = skin_vertex(&golden.vertex, &joints);
+            for axis in 0..3 {
+                assert!(
+                    (position[axis] - golden.position[axis]).abs() < 1e-5,
+                    "vertex {} position {:?} expected {:?}",
+                    index,
+                    position,
+                    golden.position
+                );
+                assert!(
+                    (normal[axis] - golden.normal[axis]).abs() < 1e-5,
+                    "vertex {} normal {:?} expected {:?}",
+                    index,
+                    normal,
+                    golden.normal
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_golden_rotation() {
+        // Bone turned so its right axis points down -z, then moved along z
+        let bones = [GoldenBone {
+            at_rest_model_to_bone: IDENTITY,
+            posed_bone_to_model: [
+                [0.0, 0.0, -1.0],
+                [0.0, 1.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [0.0, 0.0, 5.0],
+            ],
+        }];
+        let vertices = [
+            GoldenVertex {
+                vertex: skinned(
+                    [1.0, 0.0, 0.0],
+                    [1.0, 0.0, 0.0],
+                    [0; 4],
+                    [1.0, 0.0, 0.0, 0.0],
+                ),
+                position: [0.0, 0.0, 4.0],
+                normal: [0.0, 0.0, -1.0],
+            },
+            GoldenVertex {
+                vertex: skinned(
+                    [0.0, 0.0, 1.0],
+                    [0.0, 0.0, 1.0],
+                    [0; 4],
+                    [1.0, 0.0, 0.0, 0.0],
+                ),
+                position: [1.0, 0.0, 5.0],
+                normal: [1.0, 0.0, 0.0],
+            },
+        ];
+
+        assert_golden(&bones, &vertices);
+    }
+
+    #[test]
+    fn test_golden_bind_pose() {
+        // Bone resting at y=1 rotated a quarter turn about z in place, vertices
+        // must be taken into bone space before the pose is applied
+        let bones = [GoldenBone {
+            at_rest_model_to_bone: [
+                [1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [0.0, 0.0, 1.0],
+                [0.0, -1.0, 0.0],
+            ],
+            posed_bone_to_model: [
+                [0.0, 1.0, 0.0],
+                [-1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0],
+                [0.0, 1.0, 0.0],
+            ],
+        }];
+        let vertices = [GoldenVertex {
+            vertex: skinned(
+                [1.0, 1.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [0; 4],
+                [1.0, 0.0, 0.0, 0.0],
+            ),
+            position: [0.0, 2.0, 0.0],
+            normal: [0.0, 1.0, 0.0],
+        }];
+
+        assert_golden(&bones, &vertices);
+    }
+
+    #[test]
+    fn test_golden_blend() {
+        let mut moved = IDENTITY;
+        moved[3] = [2.0, 0.0, 0.0];
+        let bones = [
+            GoldenBone {
+                at_rest_model_to_bone: IDENTITY,
+                posed_bone_to_model: IDENTITY,
+            },
+            GoldenBone {
+                at_rest_model_to_bone: IDENTITY,
+                posed_bone_to_model: moved,
+            },
+        ];
+        let vertices = [
+            GoldenVertex {
+                vertex: skinned(
+                    [0.0, 1.0, 0.0],
+                    [0.0, 1.0, 0.0],
+                    [0, 1, 0, 0],
+                    [0.5, 0.5, 0.0, 0.0],
+                ),
+                position: [1.0, 1.0, 0.0],
+                normal: [0.0, 1.0, 0.0],
+            },
+            GoldenVertex {
+                vertex: skinned(
+                    [0.0, 0.0, 0.0],
+                    [0.0, 1.0, 0.0],
+                    [0, 1, 0, 0],
+                    [0.25, 0.75, 0.0, 0.0],
+                ),
+                position: [1.5, 0.0, 0.0],
+                normal: [0.0, 1.0, 0.0],
+            },
+        ];
+
+        assert_golden(&bones, &vertices);
+    }
+
+    #[test]
+    fn test_golden_scaled_normals() {
+        // Normals use the inverse transpose so they stay perpendicular
+        // to surfaces under non uniform scale
+        let mut scaled = IDENTITY;
+        scaled[0] = [2.0, 0.0, 0.0];
+        let bones = [GoldenBone {
+            at_rest_model_to_bone: IDENTITY,
+            posed_bone_to_model: scaled,
+        }];
+        let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+        let vertices = [GoldenVertex {
+            vertex: skinned(
+                [1.0, 1.0, 0.0],
+                [diagonal, diagonal, 0.0],
+                [0; 4],
+                [1.0, 0.0, 0.0, 0.0],
+            ),
+            position: [2.0, 1.0, 0.0],
+            normal: [1.0 / 5f32.sqrt(), 2.0 / 5f32.sqrt(), 0.0],
+        }];
+
+        assert_golden(&bones, &vertices);
+    }
+}
@@ -0,0 +1,293 @@
+//! Splitting a loaded mesh into the geometry drawn by each of its
+//! materials. Each display list of a material is decoded into a triangle
+//! list against the vertex buffer it indexes, keeping the LOD and part ID of
+//! the display list so the submesh can be shown with them.
+//!
+//! The containers don't record which vertex buffer a display list indexes,
+//! the buffer is the one whose vertex format the draws use out of those
+//! holding every position drawn. Display lists that still match several
+//! buffers are reported
+
+use bevy::render::mesh::Mesh;
+
+use crate::formats::report::LoadReport;
+
+use super::{
+    display_list::{decode_display_list, draw_vertex_formats, indexed_mesh, VertexDescriptor},
+    dl_container::{
+        read_containers, resolve_display_lists, DisplayListSource, MaterialDisplayList,
+    },
+    loader::LoadedMesh,
+    mesh_raw_old::create_bevy_mesh,
+    winding::{normalize_winding, Winding},
+};
+
+/// Vertex buffer decoded into a mesh along with its index in the mesh data
+pub struct DecodedVertexBuffer {
+    pub index: usize,
+    pub descriptor: VertexDescriptor,
+    /// Vertex format index the draws of the buffer use
+    pub vertex_format: u8,
+    pub mesh: Mesh,
+}
+
+/// Display list decoded against the vertex buffer it indexes
+pub struct DecodedDisplayList {
+    /// Index of the vertex buffer in the mesh data
+    pub vertex_buffer: usize,
+    /// Triangle list mesh of the display list
+    pub mesh: Mesh,
+    /// Indices of the other vertex buffers the display list matches
+    pub ambiguous: Vec<usize>,
+}
+
+impl DecodedDisplayList {
+    /// Reports the vertex buffers the display list also matched
+    pub fn report_ambiguous(&self, report: &mut LoadReport, index: usize, material: usize) {
+        if self.ambiguous.is_empty() {
+            return;
+        }
+
+        report.warn(format!(
+            "display list {} of material {} also matches vertex buffers {:?}, using {}",
+            index, material, self.ambiguous, self.vertex_buffer
+        ));
+    }
+}
+
+/// Geometry drawn by a single display list of a material
+#[derive(Debug, Clone)]
+pub struct MaterialSubmesh {
+    /// Index of the material drawing the submesh
+    pub material: usize,
+    /// Index of the vertex buffer the display list indexes
+    pub vertex_buffer: usize,
+    pub lod_id: u8,
+    pub part_id: u8,
+    /// Triangle list mesh of the display list
+    pub mesh: Mesh,
+}
+
+/// Decodes the vertex buffers of the mesh, buffers that fail to decode are
+/// reported and skipped
+pub fn decode_vertex_buffers(
+    loaded: &LoadedMesh,
+    report: &mut LoadReport,
+) -> Vec<DecodedVertexBuffer> {
+    loaded
+        .vertex_buffers()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, buffer)| match create_bevy_mesh(buffer, report) {
+            Ok(mesh) => Some(DecodedVertexBuffer {
+                index,
+                descriptor: VertexDescriptor::from_vertex_buffer(buffer),
+                vertex_format: buffer.vertex_format,
+                mesh,
+            }),
+            Err(err) => {
+                report.error(format!("vertex buffer {}: {}", index, err));
+                None
+            }
+        })
+        .collect()
+}
+
+/// Decodes the display list against the vertex buffer it indexes. Buffers
+/// holding every position drawn are candidates, candidates using the
+/// vertex format of the draws are preferred and the first remaining
+/// candidate is used with the others kept as ambiguous
+pub fn decode_material_mesh(
+    display_list: &MaterialDisplayList,
+    vertex_buffers: &[DecodedVertexBuffer],
+) -> Option<DecodedDisplayList> {
+    let mut candidates: Vec<_> = vertex_buffers
+        .iter()
+        .filter_map(|buffer| {
+            let indices = decode_display_list(&display_list.bytes, &buffer.descriptor).ok()?;
+            let count = buffer.mesh.count_vertices();
+            if indices.is_empty() || indices.iter().any(|index| *index as usize >= count) {
+                return None;
+            }
+
+            let same_format = draw_vertex_formats(&display_list.bytes, &buffer.descriptor)
+                .is_ok_and(|formats| formats == [buffer.vertex_format]);
+            Some((buffer, indices, same_format))
+        })
+        .collect();
+
+    if candidates.iter().any(|(_, _, same_format)| *same_format) {
+        candidates.retain(|(_, _, same_format)| *same_format);
+    }
+
+    let mut candidates = candidates.into_iter();
+    let (buffer, indices, _) = candidates.next()?;
+    let mut mesh = indexed_mesh(&buffer.mesh, indices);
+    normalize_winding(&mut mesh, Winding::GAMECUBE);
+    Some(DecodedDisplayList {
+        vertex_buffer: buffer.index,
+        mesh,
+        ambiguous: candidates.map(|(buffer, _, _)| buffer.index).collect(),
+    })
+}
+
+/// Submeshes of each display list stored in the mesh file, streamed display
+/// lists aren't available without the stream file so they're reported and
+/// skipped along with the display lists that don't decode
+pub fn material_submeshes(
+    loaded: &LoadedMesh,
+    vertex_buffers: &[DecodedVertexBuffer],
+    report: &mut LoadReport,
+) -> Vec<MaterialSubmesh> {
+    let containers = read_containers(loaded).unwrap_or_else(|err| {
+        report.warn(format!("display list containers: {}", err));
+        Vec::new()
+    });
+
+    let (display_lists, failed) = resolve_display_lists(
+        &containers,
+        &DisplayListSource {
+            file: loaded.bytes(),
+            stream: None,
+        },
+    );
+    for (material, err) in failed {
+        report.warn(format!("material {}: {}", material, err));
+    }
+
+    display_lists
+        .iter()
+        .enumerate()
+        .filter_map(|(index, display_list)| {
+            let Some(decoded) = decode_material_mesh(display_list, vertex_buffers) else {
+                report.warn(format!(
+                    "display list {} of material {} doesn't decode against any vertex buffer",
+                    index, display_list.material
+                ));
+                return None;
+            };
+            decoded.report_ambiguous(report, index, display_list.material);
+
+            Some(MaterialSubmesh {
+                material: display_list.material,
+                vertex_buffer: decoded.vertex_buffer,
+                lod_id: display_list.lod_id,
+                part_id: display_list.part_id,
+                mesh: decoded.mesh,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::render::{mesh::Mesh, render_resource::PrimitiveTopology};
+
+    use crate::formats::{
+        mesh::{
+            display_list::{GXAttr, VertexDescriptor},
+            dl_container::MaterialDisplayList,
+            fixture::textured_quad,
+            loader::MeshLoader,
+            mesh_raw_old::GXAttrType,
+        },
+        report::LoadReport,
+    };
+
+    use super::{
+        decode_material_mesh, decode_vertex_buffers, material_submeshes, DecodedVertexBuffer,
+    };
+
+    fn vertex_buffer(index: usize, vertex_format: u8, count: usize) -> DecodedVertexBuffer {
+        let mut descriptor = VertexDescriptor::default();
+        descriptor.push(GXAttr::Position, GXAttrType::Index16, 6);
+        DecodedVertexBuffer {
+            index,
+            descriptor,
+            vertex_format,
+            mesh: Mesh::new(PrimitiveTopology::TriangleStrip)
+                .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0f32; 3]; count]),
+        }
+    }
+
+    fn display_list(vertex_format: u8, positions: &[u16]) -> MaterialDisplayList {
+        // Triangle list draw of 16 bit position indices
+        let mut bytes = vec![0x90 | vertex_format];
+        bytes.extend((positions.len() as u16).to_be_bytes());
+        for position in positions {
+            bytes.extend(position.to_be_bytes());
+        }
+
+        MaterialDisplayList {
+            material: 1,
+            part_id: 2,
+            lod_id: 0,
+            streamed: false,
+            bytes,
+        }
+    }
+
+    #[test]
+    fn test_decode_material_mesh() {
+        let vertex_buffers = [vertex_buffer(0, 0, 2), vertex_buffer(3, 0, 4)];
+
+        // First buffer doesn't hold every position drawn
+        let decoded = decode_material_mesh(&display_list(0, &[1, 2, 3]), &vertex_buffers)
+            .expect("display list decodes");
+        assert_eq!(decoded.vertex_buffer, 3);
+        assert!(decoded.ambiguous.is_empty());
+        assert_eq!(
+            decoded.mesh.primitive_topology(),
+            PrimitiveTopology::TriangleList
+        );
+        assert_eq!(decoded.mesh.indices().map(|indices| indices.len()), Some(3));
+
+        assert!(decode_material_mesh(&display_list(0, &[0, 1, 9]), &vertex_buffers).is_none());
+    }
+
+    #[test]
+    fn test_ambiguous_vertex_buffers() {
+        let vertex_buffers = [
+            vertex_buffer(0, 0, 4),
+            vertex_buffer(1, 1, 4),
+            vertex_buffer(2, 1, 4),
+        ];
+
+        // Buffer using the vertex format of the draws is preferred
+        let decoded = decode_material_mesh(&display_list(0, &[0, 1, 2]), &vertex_buffers).unwrap();
+        assert_eq!(decoded.vertex_buffer, 0);
+        assert!(decoded.ambiguous.is_empty());
+
+        // Buffers sharing a vertex format can't be told apart
+        let decoded = decode_material_mesh(&display_list(1, &[0, 1, 2]), &vertex_buffers).unwrap();
+        assert_eq!(decoded.vertex_buffer, 1);
+        assert_eq!(decoded.ambiguous, [2]);
+
+        let mut report = LoadReport::new("test");
+        decoded.report_ambiguous(&mut report, 0, 1);
+        assert_eq!(report.findings.len(), 1);
+
+        // Without a buffer using the format every buffer is a candidate
+        let decoded = decode_material_mesh(&display_list(5, &[0, 1, 2]), &vertex_buffers).unwrap();
+        assert_eq!(decoded.vertex_buffer, 0);
+        assert_eq!(decoded.ambiguous, [1, 2]);
+    }
+
+    #[test]
+    fn test_material_submeshes() {
+        let loaded = MeshLoader::from_bytes(textured_quad()).load().unwrap();
+        let mut report = LoadReport::new("test");
+        let vertex_buffers = decode_vertex_buffers(&loaded, &mut report);
+        let submeshes = material_submeshes(&loaded, &vertex_buffers, &mut report);
+        assert!(report.findings.is_empty());
+
+        // Strip of the quad is split into two triangles
+        assert_eq!(submeshes.len(), 1);
+        assert_eq!(submeshes[0].material, 0);
+        assert_eq!(
+            submeshes[0].mesh.indices().map(|indices| indices.len()),
+            Some(6)
+        );
+        assert!(submeshes[0].mesh.attribute(Mesh::ATTRIBUTE_UV_0).is_some());
+    }
+}
@@ -0,0 +1,233 @@
+//! Textures sampled by the texture layers of the GameCube meshes. Each flip
+//! page of a layer is an offset to a CFTexInst, the instance points to the
+//! FTexDef of its texture which holds the name the texture is found by.
+//!
+//! Definitions of textures loaded in place are embedded at the start of
+//! their [GCTexData] which points to the GX texels in the mesh file, these
+//! are decoded along with their mip levels. Definitions without texture data
+//! are left to be found by name
+
+use std::io::Cursor;
+
+use bevy::{render::texture::Image, utils::HashMap};
+use binrw::{BinRead, BinResult};
+use openglitch_formats::{
+    mesh::FMeshTexLayerID,
+    texture::{CFTexInst, FTexDef},
+    types::PtrOffset,
+};
+use thiserror::Error;
+
+use crate::formats::texture::{
+    decode_mipmaps, gx::GXTexFmt, mipmapped_image, DecodedTexture, TexelFormat, TextureError,
+};
+
+use super::loader::LoadedMesh;
+
+/// FTexData_t of the GameCube release, laid out the same as the DirectX
+/// texture data with the GX texel format in place of the D3D formats
+#[derive(Debug, BinRead)]
+#[br(big)]
+pub struct GCTexData {
+    /// Definition of the texture, points back to this data
+    pub tex_def: FTexDef,
+    pub link: [PtrOffset; 2],
+    pub flags: u8,
+    pub lod_count: u8,
+    pub width: u16,
+    #[br(pad_after = 2)]
+    pub height: u16,
+    /// GX texel format of the image data (GX_TF_*)
+    pub gx_fmt: u32,
+    /// Bytes of image data covering every LOD
+    pub texture_bytes: u32,
+    pub image_data: PtrOffset,
+}
+
+impl GCTexData {
+    pub const SIZE: usize = 64;
+}
+
+#[derive(Debug, Error)]
+pub enum MeshTextureError {
+    #[error(transparent)]
+    Parse(#[from] binrw::Error),
+    #[error("unsupported GX texel format {0:#x}")]
+    UnknownFormat(u32),
+    #[error(
+        "image data of {size} bytes at {offset:#x} runs past the end of the {length} byte file"
+    )]
+    OutOfBounds {
+        offset: u32,
+        size: u32,
+        length: usize,
+    },
+    #[error(transparent)]
+    Texture(#[from] TextureError),
+}
+
+/// Reads the texture definition of each flip page of the layer, pages
+/// without a texture instance or definition are None
+pub fn layer_textures(
+    mesh: &LoadedMesh,
+    layer: &FMeshTexLayerID,
+) -> BinResult<Vec<Option<FTexDef>>> {
+    let Some(flip_palette) = layer.flip_palette.offset() else {
+        return Ok(Vec::new());
+    };
+
+    let mut cursor = Cursor::new(mesh.bytes());
+    cursor.set_position(flip_palette as u64);
+    let pages = (0..layer.flip_page_count)
+        .map(|_| PtrOffset::read_be(&mut cursor))
+        .collect::<BinResult<Vec<_>>>()?;
+
+    pages
+        .iter()
+        .map(|tex_inst| {
+            let Some(tex_inst) = tex_inst.offset() else {
+                return Ok(None);
+            };
+            cursor.set_position(tex_inst as u64);
+            let tex_inst = CFTexInst::read_be(&mut cursor)?;
+
+            let Some(tex_def) = tex_inst.tex_def.offset() else {
+                return Ok(None);
+            };
+            cursor.set_position(tex_def as u64);
+            FTexDef::read_be(&mut cursor).map(Some)
+        })
+        .collect()
+}
+
+/// Names of the textures of each flip page of the layer, pages without a
+/// texture are skipped
+pub fn layer_texture_names(mesh: &LoadedMesh, layer: &FMeshTexLayerID) -> BinResult<Vec<String>> {
+    Ok(layer_textures(mesh, layer)?
+        .into_iter()
+        .flatten()
+        .map(|tex_def| tex_def.tex_info.name.as_string())
+        .collect())
+}
+
+/// Decodes each mip level of the texture the definition points to, None
+/// when the texels aren't stored in the mesh file. The size and LOD count
+/// of the definition are used as the texture data repeats them
+pub fn decode_tex_data(
+    mesh: &LoadedMesh,
+    tex_def: &FTexDef,
+) -> Result<Option<Vec<DecodedTexture>>, MeshTextureError> {
+    let Some(offset) = tex_def.tex_data.offset() else {
+        return Ok(None);
+    };
+
+    let mut cursor = Cursor::new(mesh.bytes());
+    cursor.set_position(offset as u64);
+    let tex_data = GCTexData::read(&mut cursor)?;
+    let Some(image_data) = tex_data.image_data.offset() else {
+        return Ok(None);
+    };
+
+    let format = u8::try_from(tex_data.gx_fmt)
+        .ok()
+        .and_then(|value| GXTexFmt::try_from(value).ok())
+        .ok_or(MeshTextureError::UnknownFormat(tex_data.gx_fmt))?;
+
+    let start = image_data as usize;
+    let bytes = start
+        .checked_add(tex_data.texture_bytes as usize)
+        .and_then(|end| mesh.bytes().get(start..end))
+        .ok_or(MeshTextureError::OutOfBounds {
+            offset: image_data,
+            size: tex_data.texture_bytes,
+            length: mesh.length(),
+        })?;
+
+    let info = &tex_def.tex_info;
+    let levels = decode_mipmaps(
+        TexelFormat::Gx(format),
+        info.texels_across as u32,
+        info.texels_down as u32,
+        info.lod_count as u32,
+        bytes,
+    )?;
+    Ok(Some(levels))
+}
+
+/// Image of a decoded texture along with whether it has texels that
+/// aren't fully opaque
+pub fn texture_image(levels: &[DecodedTexture]) -> Result<(Image, bool), TextureError> {
+    let image = mipmapped_image(levels)?;
+    let translucent = levels[0].data.chunks_exact(4).any(|texel| texel[3] != 255);
+    Ok((image, translucent))
+}
+
+/// Definitions of every texture used by the texture layers of a mesh by
+/// their lowercase name, layers that can't be read are skipped as they're
+/// reported when the materials are read
+pub fn mesh_texture_defs(mesh: &LoadedMesh) -> HashMap<String, FTexDef> {
+    mesh.tex_layers()
+        .iter()
+        .filter_map(|layer| layer_textures(mesh, layer).ok())
+        .flatten()
+        .flatten()
+        .map(|tex_def| {
+            (
+                tex_def.tex_info.name.as_string().to_ascii_lowercase(),
+                tex_def,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::formats::mesh::{fixture::textured_mesh, loader::MeshLoader};
+
+    use super::{
+        decode_tex_data, layer_texture_names, layer_textures, mesh_texture_defs, texture_image,
+        MeshTextureError,
+    };
+
+    #[test]
+    fn test_layer_textures() {
+        let loaded = MeshLoader::from_bytes(textured_mesh(&["water_00", "water_01"]))
+            .load()
+            .unwrap();
+        let layer = &loaded.tex_layers()[0];
+
+        let textures = layer_textures(&loaded, layer).unwrap();
+        assert_eq!(textures.len(), 2);
+        assert_eq!(textures[0].unwrap().tex_info.texels_across, 4);
+        assert_eq!(
+            layer_texture_names(&loaded, layer).unwrap(),
+            ["water_00", "water_01"]
+        );
+    }
+
+    #[test]
+    fn test_decode_tex_data() {
+        let mut bytes = textured_mesh(&["Rock_00"]);
+        let loaded = MeshLoader::from_bytes(bytes.clone()).load().unwrap();
+        let defs = mesh_texture_defs(&loaded);
+        let tex_def = defs.get("rock_00").unwrap();
+
+        let levels = decode_tex_data(&loaded, tex_def).unwrap().unwrap();
+        assert_eq!(levels.len(), 1);
+        assert_eq!((levels[0].width, levels[0].height), (4, 4));
+        assert_eq!(levels[0].texel(3, 3), [255, 255, 255, 255]);
+
+        let (image, translucent) = texture_image(&levels).unwrap();
+        assert_eq!(image.texture_descriptor.mip_level_count, 1);
+        assert!(!translucent);
+
+        // Palette formats aren't supported
+        let tex_data = tex_def.tex_data.0 as usize;
+        bytes[tex_data + 52..tex_data + 56].copy_from_slice(&0x8u32.to_be_bytes());
+        let loaded = MeshLoader::from_bytes(bytes).load().unwrap();
+        assert!(matches!(
+            decode_tex_data(&loaded, tex_def),
+            Err(MeshTextureError::UnknownFormat(0x8))
+        ));
+    }
+}
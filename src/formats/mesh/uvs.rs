@@ -0,0 +1,43 @@
+//! Decoding of the fixed point GameCube texture coordinates
+
+use super::mesh_raw_old::{GCVertexBuffer, GCST16};
+
+/// Fractional bits of the 16 bit texture coordinates
+pub const ST_S16_FRAC: u32 = 12;
+
+/// Decodes a 16 bit fixed point texture coordinate
+pub fn decode_st16(value: &GCST16) -> [f32; 2] {
+    let scale = 1.0 / (1u32 << ST_S16_FRAC) as f32;
+    [value.s as f32 * scale, value.t as f32 * scale]
+}
+
+/// Decodes the texture coordinates of a vertex buffer
+pub fn decode_uvs(values: &[GCST16]) -> Vec<[f32; 2]> {
+    values.iter().map(decode_st16).collect()
+}
+
+impl GCVertexBuffer {
+    /// Texture coordinates of each position for the layer, none when the
+    /// buffer has no coordinates for the layer. The vertex buffers only
+    /// store a single set of coordinates so only layer 0 is provided
+    pub fn uvs(&self, layer: usize) -> Option<Vec<[f32; 2]>> {
+        if layer != 0 {
+            return None;
+        }
+        self.st.value.as_deref().map(decode_uvs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::formats::mesh::mesh_raw_old::GCST16;
+
+    use super::decode_st16;
+
+    #[test]
+    fn test_decode_st16() {
+        assert_eq!(decode_st16(&GCST16 { s: 4096, t: 2048 }), [1.0, 0.5]);
+        // Coordinates outside 0..1 repeat the texture
+        assert_eq!(decode_st16(&GCST16 { s: -4096, t: 8192 }), [-1.0, 2.0]);
+    }
+}
@@ -0,0 +1,71 @@
+use bevy::{
+    pbr::StandardMaterial,
+    render::{
+        mesh::{Indices, Mesh},
+        render_resource::{Face, PrimitiveTopology},
+    },
+};
+
+/// Order of the vertices of a triangle when seen from the front
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+impl Winding {
+    /// Winding used for front faces by the GameCube assets
+    pub const GAMECUBE: Winding = Winding::CounterClockwise;
+    /// Winding used for front faces by the DirectX (Xbox/PC) assets
+    pub const DIRECTX: Winding = Winding::Clockwise;
+    /// Winding bevy expects for front faces
+    pub const BEVY: Winding = Winding::CounterClockwise;
+}
+
+/// Converts the triangles of the provided mesh from the `source` winding
+/// to the winding bevy expects
+pub fn normalize_winding(mesh: &mut Mesh, source: Winding) {
+    if source != Winding::BEVY {
+        flip_winding(mesh);
+    }
+}
+
+/// Reverses the winding of every triangle in the mesh, meshes without
+/// indices are given sequential indices before flipping
+pub fn flip_winding(mesh: &mut Mesh) {
+    let mut indices: Vec<u32> = match mesh.indices() {
+        Some(indices) => indices.iter().map(|value| value as u32).collect(),
+        None => (0..mesh.count_vertices() as u32).collect(),
+    };
+
+    match mesh.primitive_topology() {
+        PrimitiveTopology::TriangleList => {
+            indices
+                .chunks_exact_mut(3)
+                .for_each(|triangle| triangle.swap(1, 2));
+        }
+        PrimitiveTopology::TriangleStrip => {
+            // Repeating the first index adds a degenerate triangle which
+            // shifts the parity of every following triangle in the strip
+            if let Some(first) = indices.first().copied() {
+                indices.insert(0, first);
+            }
+        }
+        // Points and lines have no winding
+        _ => return,
+    }
+
+    mesh.set_indices(Some(Indices::U32(indices)));
+}
+
+/// Updates the culling of the material for materials that should be
+/// visible from both sides
+pub fn apply_two_sided(material: &mut StandardMaterial, two_sided: bool) {
+    if two_sided {
+        material.double_sided = true;
+        material.cull_mode = None;
+    } else {
+        material.double_sided = false;
+        material.cull_mode = Some(Face::Back);
+    }
+}
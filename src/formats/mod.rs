@@ -1,2 +1,19 @@
+pub mod ambience;
+pub mod bink;
+pub mod decals;
+pub mod gamedata;
+pub mod handler;
+pub mod hex;
+pub mod level;
 pub mod mesh;
+pub mod motif;
+pub mod report;
+pub mod shader_table;
+pub mod skybox;
+pub mod sound_events;
+pub mod spline;
+pub mod stream;
+pub mod texture;
+pub mod timeline;
 pub mod types;
+pub mod validation;
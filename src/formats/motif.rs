@@ -0,0 +1,270 @@
+//! Color motifs referenced by the motif index of lights and materials. A
+//! motif index of zero uses the color as stored, any other index tints the
+//! color by the current color of the motif, letting the game animate many
+//! colors at once. The motif tables are read from a plain text listing with
+//! one motif per line:
+//!
+//! - `index constant r g b`
+//! - `index cycle period r g b r g b ...` blends through each color in turn
+//!   taking period seconds to return to the first
+//! - `index pulse period r g b r g b` swings between the two colors and back
+//!   over the period
+
+use std::{collections::HashMap, str::FromStr};
+
+use bevy::render::color::Color;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MotifError {
+    #[error("line {0}: expected index kind [period] colors")]
+    MalformedLine(usize),
+    #[error("line {0}: unknown motif kind {1:?}")]
+    UnknownKind(usize, String),
+    #[error("line {0}: invalid number {1:?}")]
+    InvalidNumber(usize, String),
+    #[error("line {0}: {1} motifs need {2}")]
+    WrongColorCount(usize, &'static str, &'static str),
+    #[error("line {0}: motif index 0 is reserved for colors without a motif")]
+    ReservedIndex(usize),
+    #[error("line {0}: motif {1} is already defined")]
+    DuplicateIndex(usize, u32),
+}
+
+/// How a motif changes over time
+#[derive(Debug, Clone, PartialEq)]
+pub enum MotifKind {
+    Constant(Color),
+    /// Blends through the colors, looping back to the first
+    Cycle {
+        period: f32,
+        colors: Vec<Color>,
+    },
+    /// Swings smoothly from one color to the other and back
+    Pulse {
+        period: f32,
+        from: Color,
+        to: Color,
+    },
+}
+
+/// Blends between two colors
+fn mix(from: Color, to: Color, amount: f32) -> Color {
+    let [r0, g0, b0, a0] = from.as_rgba_f32();
+    let [r1, g1, b1, a1] = to.as_rgba_f32();
+    let lerp = |a: f32, b: f32| a + (b - a) * amount;
+    Color::rgba(lerp(r0, r1), lerp(g0, g1), lerp(b0, b1), lerp(a0, a1))
+}
+
+impl MotifKind {
+    /// Color of the motif the provided number of seconds in
+    pub fn color_at(&self, seconds: f32) -> Color {
+        match self {
+            MotifKind::Constant(color) => *color,
+            MotifKind::Cycle { period, colors } => {
+                let phase = (seconds / period).rem_euclid(1.0) * colors.len() as f32;
+                let index = (phase.floor() as usize).min(colors.len() - 1);
+                let next = (index + 1) % colors.len();
+                mix(colors[index], colors[next], phase - index as f32)
+            }
+            MotifKind::Pulse { period, from, to } => {
+                let phase = (seconds / period).rem_euclid(1.0);
+                let amount = 0.5 - 0.5 * (phase * std::f32::consts::TAU).cos();
+                mix(*from, *to, amount)
+            }
+        }
+    }
+}
+
+/// Motifs by their index
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MotifTable {
+    pub motifs: HashMap<u32, MotifKind>,
+}
+
+impl MotifTable {
+    /// Color of the motif at the time, none for index zero and for motifs
+    /// that aren't in the table
+    pub fn color(&self, index: u32, seconds: f32) -> Option<Color> {
+        self.motifs.get(&index).map(|motif| motif.color_at(seconds))
+    }
+
+    /// Color stored with a motif index tinted by the current color of the
+    /// motif, colors without a known motif are returned unchanged
+    pub fn apply(&self, base: Color, index: u32, seconds: f32) -> Color {
+        match self.color(index, seconds) {
+            Some(tint) => {
+                let [r0, g0, b0, a0] = base.as_rgba_f32();
+                let [r1, g1, b1, a1] = tint.as_rgba_f32();
+                Color::rgba(r0 * r1, g0 * g1, b0 * b1, a0 * a1)
+            }
+            None => base,
+        }
+    }
+}
+
+fn number<T: FromStr>(line_number: usize, value: &str) -> Result<T, MotifError> {
+    value
+        .parse()
+        .map_err(|_| MotifError::InvalidNumber(line_number, value.to_string()))
+}
+
+/// Parses the seconds a motif takes to repeat, which must be positive
+fn parse_period(line_number: usize, value: &str) -> Result<f32, MotifError> {
+    let period: f32 = number(line_number, value)?;
+    if period.is_nan() || period <= 0.0 {
+        return Err(MotifError::InvalidNumber(line_number, value.to_string()));
+    }
+    Ok(period)
+}
+
+/// Parses the colors listed as groups of three components
+fn colors(line_number: usize, values: &[&str]) -> Result<Vec<Color>, MotifError> {
+    if values.len() % 3 != 0 {
+        return Err(MotifError::MalformedLine(line_number));
+    }
+    values
+        .chunks_exact(3)
+        .map(|rgb| {
+            Ok(Color::rgb(
+                number(line_number, rgb[0])?,
+                number(line_number, rgb[1])?,
+                number(line_number, rgb[2])?,
+            ))
+        })
+        .collect()
+}
+
+/// Parses a motif listing, blank lines and lines starting
+/// with `#` are ignored
+pub fn parse_motifs(value: &str) -> Result<MotifTable, MotifError> {
+    let mut out = MotifTable::default();
+
+    for (index, line) in value.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let motif = match parts.as_slice() {
+            &[_, "constant", ref rest @ ..] => match colors(line_number, rest)?.as_slice() {
+                &[color] => MotifKind::Constant(color),
+                _ => {
+                    return Err(MotifError::WrongColorCount(
+                        line_number,
+                        "constant",
+                        "one color",
+                    ))
+                }
+            },
+            &[_, "cycle", period, ref rest @ ..] => {
+                let colors = colors(line_number, rest)?;
+                if colors.len() < 2 {
+                    return Err(MotifError::WrongColorCount(
+                        line_number,
+                        "cycle",
+                        "at least two colors",
+                    ));
+                }
+                MotifKind::Cycle {
+                    period: parse_period(line_number, period)?,
+                    colors,
+                }
+            }
+            &[_, "pulse", period, ref rest @ ..] => match colors(line_number, rest)?.as_slice() {
+                &[from, to] => MotifKind::Pulse {
+                    period: parse_period(line_number, period)?,
+                    from,
+                    to,
+                },
+                _ => {
+                    return Err(MotifError::WrongColorCount(
+                        line_number,
+                        "pulse",
+                        "two colors",
+                    ))
+                }
+            },
+            &[_, "constant" | "cycle" | "pulse", ..] => {
+                return Err(MotifError::MalformedLine(line_number))
+            }
+            &[_, kind, ..] => return Err(MotifError::UnknownKind(line_number, kind.to_string())),
+            _ => return Err(MotifError::MalformedLine(line_number)),
+        };
+
+        let motif_index: u32 = number(line_number, parts[0])?;
+        if motif_index == 0 {
+            return Err(MotifError::ReservedIndex(line_number));
+        }
+        if out.motifs.insert(motif_index, motif).is_some() {
+            return Err(MotifError::DuplicateIndex(line_number, motif_index));
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::render::color::Color;
+
+    use super::{parse_motifs, MotifError, MotifKind};
+
+    #[test]
+    fn test_parse_motifs() {
+        let table = parse_motifs(
+            "# motifs\n\
+             1 constant 1 0 0\n\
+             2 cycle 3 1 0 0 0 1 0 0 0 1\n\
+             3 pulse 2 0 0 0 1 1 1\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            table.motifs[&1],
+            MotifKind::Constant(Color::rgb(1.0, 0.0, 0.0))
+        );
+        assert!(
+            matches!(table.motifs[&2], MotifKind::Cycle { ref colors, .. } if colors.len() == 3)
+        );
+        assert_eq!(table.color(0, 0.0), None);
+
+        assert!(matches!(
+            parse_motifs("1 flicker 1 1 1"),
+            Err(MotifError::UnknownKind(1, _))
+        ));
+        assert!(matches!(
+            parse_motifs("0 constant 1 1 1"),
+            Err(MotifError::ReservedIndex(1))
+        ));
+        assert!(matches!(
+            parse_motifs("1 pulse 2 1 1 1"),
+            Err(MotifError::WrongColorCount(1, "pulse", _))
+        ));
+        assert!(matches!(
+            parse_motifs("1 constant 1 1 1\n1 constant 0 0 0"),
+            Err(MotifError::DuplicateIndex(2, 1))
+        ));
+        assert!(parse_motifs("1 cycle").is_err());
+        assert!(matches!(
+            parse_motifs("1 pulse 0 1 1 1 0 0 0"),
+            Err(MotifError::InvalidNumber(1, _))
+        ));
+    }
+
+    #[test]
+    fn test_motif_colors() {
+        let table = parse_motifs("2 cycle 2 1 0 0 0 1 0\n3 pulse 2 0 0 0 1 1 1").unwrap();
+
+        assert_eq!(table.color(2, 0.0), Some(Color::rgb(1.0, 0.0, 0.0)));
+        assert_eq!(table.color(2, 0.5), Some(Color::rgb(0.5, 0.5, 0.0)));
+        assert_eq!(table.color(2, 2.0), Some(Color::rgb(1.0, 0.0, 0.0)));
+        assert_eq!(table.color(3, 1.0), Some(Color::rgb(1.0, 1.0, 1.0)));
+
+        let base = Color::rgb(0.5, 0.5, 0.5);
+        assert_eq!(table.apply(base, 0, 1.0), base);
+        assert_eq!(table.apply(base, 2, 0.0), Color::rgb(0.5, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,115 @@
+//! Non-fatal findings collected while loading an asset, these describe
+//! what is missing or incomplete about what is being displayed
+
+use std::fmt::Display;
+
+use bevy::ecs::system::Resource;
+
+/// Severity of a load finding, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Findings collected while loading a single asset
+#[derive(Debug, Clone)]
+pub struct LoadReport {
+    /// Path of the loaded asset
+    pub asset: String,
+    pub findings: Vec<Finding>,
+    /// Only part of the asset could be loaded
+    pub partial: bool,
+}
+
+impl LoadReport {
+    pub fn new(asset: impl Into<String>) -> Self {
+        Self {
+            asset: asset.into(),
+            findings: Vec::new(),
+            partial: false,
+        }
+    }
+
+    pub fn push(&mut self, severity: Severity, message: impl Into<String>) {
+        self.findings.push(Finding {
+            severity,
+            message: message.into(),
+        });
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Severity::Info, message);
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.push(Severity::Warning, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Severity::Error, message);
+    }
+
+    /// Marks the asset as partially loaded with a warning describing
+    /// what couldn't be loaded
+    pub fn partial(&mut self, message: impl Into<String>) {
+        self.partial = true;
+        self.warn(message);
+    }
+
+    /// Findings at or above the provided severity
+    pub fn filtered(&self, min: Severity) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(move |finding| finding.severity >= min)
+    }
+
+    /// Most severe finding in the report
+    pub fn severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|finding| finding.severity).max()
+    }
+}
+
+/// Reports for all the loaded assets
+#[derive(Debug, Default, Resource)]
+pub struct LoadReports {
+    reports: Vec<LoadReport>,
+}
+
+impl LoadReports {
+    /// Adds the report for an asset, findings are also written to the log
+    pub fn add(&mut self, report: LoadReport) {
+        for finding in &report.findings {
+            match finding.severity {
+                Severity::Info => bevy::log::info!("{}: {}", report.asset, finding.message),
+                Severity::Warning => bevy::log::warn!("{}: {}", report.asset, finding.message),
+                Severity::Error => bevy::log::error!("{}: {}", report.asset, finding.message),
+            }
+        }
+
+        // Reloading an asset replaces its previous report
+        self.reports.retain(|value| value.asset != report.asset);
+        self.reports.push(report);
+    }
+
+    pub fn reports(&self) -> &[LoadReport] {
+        &self.reports
+    }
+}
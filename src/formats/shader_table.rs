@@ -0,0 +1,143 @@
+use std::{collections::HashMap, path::Path};
+
+use bevy::{ecs::system::Resource, pbr::StandardMaterial};
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Default location of the shader effect table
+pub const SHADER_TABLE_PATH: &str = "assets/tables/shader_effects.ini";
+
+#[derive(Debug, Error)]
+pub enum ShaderTableError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Parse(#[from] serde_ini::de::Error),
+    #[error("invalid shader table section {0:?}")]
+    InvalidSection(String),
+}
+
+/// Shader table a material shader index refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderKind {
+    Light,
+    Specular,
+    Surface,
+}
+
+impl ShaderKind {
+    fn from_name(name: &str) -> Option<ShaderKind> {
+        match name {
+            "light" => Some(ShaderKind::Light),
+            "specular" => Some(ShaderKind::Specular),
+            "surface" => Some(ShaderKind::Surface),
+            _ => None,
+        }
+    }
+}
+
+/// Renderer effects for a single engine shader
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ShaderEffect {
+    /// Name of the engine shader
+    pub name: String,
+    /// Shader reflects an environment map
+    pub env_map: bool,
+    /// Specular power of the shader
+    pub specular_power: Option<f32>,
+    /// Shader is emissive (self illuminated)
+    pub emissive: bool,
+    /// Shader ignores scene lighting
+    pub unlit: bool,
+}
+
+/// Combined effects of the shaders used by a material
+#[derive(Debug, Clone, Default)]
+pub struct MaterialEffects {
+    pub env_map: bool,
+    pub specular_power: Option<f32>,
+    pub emissive: bool,
+    pub unlit: bool,
+}
+
+impl MaterialEffects {
+    fn merge(&mut self, effect: &ShaderEffect) {
+        self.env_map |= effect.env_map;
+        self.emissive |= effect.emissive;
+        self.unlit |= effect.unlit;
+        if effect.specular_power.is_some() {
+            self.specular_power = effect.specular_power;
+        }
+    }
+
+    /// Applies the effects onto the provided material
+    pub fn apply(&self, material: &mut StandardMaterial) {
+        if let Some(power) = self.specular_power {
+            // Blinn-Phong power to roughness approximation
+            material.perceptual_roughness = (2.0 / (power + 2.0)).sqrt().clamp(0.089, 1.0);
+        }
+
+        if self.env_map {
+            material.reflectance = 1.0;
+            material.metallic = material.metallic.max(0.5);
+        }
+
+        if self.emissive {
+            material.emissive = material.base_color;
+        }
+
+        material.unlit = self.unlit;
+    }
+}
+
+/// Table mapping the light, specular and surface shader indices used by
+/// materials onto renderer effects. Sections in the table file are named
+/// by kind and index (e.g. `[surface.3]`)
+#[derive(Debug, Default, Resource)]
+pub struct ShaderEffectTable {
+    effects: HashMap<(ShaderKind, u16), ShaderEffect>,
+}
+
+impl ShaderEffectTable {
+    pub fn load(path: &Path) -> Result<ShaderEffectTable, ShaderTableError> {
+        let value = std::fs::read_to_string(path)?;
+        Self::from_ini(&value)
+    }
+
+    pub fn from_ini(value: &str) -> Result<ShaderEffectTable, ShaderTableError> {
+        let sections: HashMap<String, ShaderEffect> = serde_ini::from_str(value)?;
+        let mut effects = HashMap::with_capacity(sections.len());
+
+        for (section, effect) in sections {
+            let key = section
+                .split_once('.')
+                .and_then(|(kind, index)| Some((ShaderKind::from_name(kind)?, index.parse().ok()?)))
+                .ok_or(ShaderTableError::InvalidSection(section))?;
+            effects.insert(key, effect);
+        }
+
+        Ok(ShaderEffectTable { effects })
+    }
+
+    pub fn get(&self, kind: ShaderKind, index: u16) -> Option<&ShaderEffect> {
+        self.effects.get(&(kind, index))
+    }
+
+    /// Combines the effects of the shaders used by a material, indices
+    /// missing from the table contribute no effects
+    pub fn material_effects(&self, light: u8, specular: u8, surface: u16) -> MaterialEffects {
+        let mut out = MaterialEffects::default();
+
+        [
+            (ShaderKind::Light, light as u16),
+            (ShaderKind::Specular, specular as u16),
+            (ShaderKind::Surface, surface),
+        ]
+        .into_iter()
+        .filter_map(|(kind, index)| self.get(kind, index))
+        .for_each(|effect| out.merge(effect));
+
+        out
+    }
+}
@@ -0,0 +1,90 @@
+//! Skybox meshes used by each level. The level data referencing the
+//! background meshes hasn't been decoded yet, so the meshes are read from a
+//! plain text listing with one level per line as `level mesh [scale]`
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SkyboxError {
+    #[error("line {0}: expected level mesh [scale]")]
+    MalformedLine(usize),
+    #[error("line {0}: invalid scale {1:?}")]
+    InvalidScale(usize, String),
+}
+
+/// Skybox mesh used by a level
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkyboxEntry {
+    /// Name of the level, matched ignoring case
+    pub level: String,
+    /// Path of the skybox mesh
+    pub mesh: String,
+    /// Scale the mesh is drawn at
+    pub scale: f32,
+}
+
+/// Parses a skybox listing, blank lines and lines starting
+/// with `#` are ignored
+pub fn parse_skyboxes(value: &str) -> Result<Vec<SkyboxEntry>, SkyboxError> {
+    let mut out = Vec::new();
+
+    for (index, line) in value.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let (level, mesh, scale) = match parts.as_slice() {
+            &[level, mesh] => (level, mesh, 1.0),
+            &[level, mesh, scale] => (
+                level,
+                mesh,
+                scale
+                    .parse()
+                    .map_err(|_| SkyboxError::InvalidScale(line_number, scale.to_string()))?,
+            ),
+            _ => return Err(SkyboxError::MalformedLine(line_number)),
+        };
+
+        out.push(SkyboxEntry {
+            level: level.to_string(),
+            mesh: mesh.to_string(),
+            scale,
+        });
+    }
+
+    Ok(out)
+}
+
+/// Finds the skybox of the provided level
+pub fn skybox_for_level<'a>(entries: &'a [SkyboxEntry], level: &str) -> Option<&'a SkyboxEntry> {
+    entries
+        .iter()
+        .find(|entry| entry.level.eq_ignore_ascii_case(level))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_skyboxes, skybox_for_level};
+
+    #[test]
+    fn test_parse_skyboxes() {
+        let entries = parse_skyboxes(
+            "# levels\nWDGlitch01 mesh/gcsky_night.ape\nwdglitch02 mesh/gcsky_day.ape 2.5\n",
+        )
+        .unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            skybox_for_level(&entries, "wdglitch01").unwrap().mesh,
+            "mesh/gcsky_night.ape"
+        );
+        assert_eq!(skybox_for_level(&entries, "WDGLITCH02").unwrap().scale, 2.5);
+        assert!(skybox_for_level(&entries, "missing").is_none());
+
+        assert!(parse_skyboxes("level").is_err());
+        assert!(parse_skyboxes("level mesh.ape big").is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! Mapping of the sound event IDs the game triggers to the entries of its
+//! sound banks. The table is read from a plain text listing with one event
+//! per line as `id name bank sample`, the samples of each bank are expected
+//! to be extracted as wav files into a directory named after the bank
+
+use thiserror::Error;
+
+/// Directory the extracted sound banks are stored in
+pub const SOUND_BANK_DIR: &str = "Sounds";
+/// Extension of the extracted samples
+const SAMPLE_EXTENSION: &str = "wav";
+
+#[derive(Debug, Error)]
+pub enum SoundEventError {
+    #[error("line {0}: expected id name bank sample")]
+    MalformedLine(usize),
+    #[error("line {0}: invalid event id {1:?}")]
+    InvalidId(usize, String),
+    #[error("line {0}: duplicate event id {1}")]
+    DuplicateId(usize, u32),
+}
+
+/// Sound bank entry played by an event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoundEvent {
+    pub id: u32,
+    pub name: String,
+    /// Name of the bank containing the sample
+    pub bank: String,
+    /// Name of the sample within the bank
+    pub sample: String,
+}
+
+impl SoundEvent {
+    /// Path of the extracted sample in the game data
+    pub fn sample_path(&self) -> String {
+        format!(
+            "{}/{}/{}.{}",
+            SOUND_BANK_DIR, self.bank, self.sample, SAMPLE_EXTENSION
+        )
+    }
+}
+
+/// Table of the known sound events
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SoundEventTable {
+    events: Vec<SoundEvent>,
+}
+
+impl SoundEventTable {
+    /// Parses an event listing, blank lines and lines starting
+    /// with `#` are ignored
+    pub fn parse(value: &str) -> Result<Self, SoundEventError> {
+        let mut events: Vec<SoundEvent> = Vec::new();
+
+        for (index, line) in value.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let &[id, name, bank, sample] = parts.as_slice() else {
+                return Err(SoundEventError::MalformedLine(line_number));
+            };
+
+            // IDs are written in hex by the engine's debug output
+            let id = match id.strip_prefix("0x") {
+                Some(hex) => u32::from_str_radix(hex, 16),
+                None => id.parse(),
+            }
+            .map_err(|_| SoundEventError::InvalidId(line_number, id.to_string()))?;
+
+            if events.iter().any(|event| event.id == id) {
+                return Err(SoundEventError::DuplicateId(line_number, id));
+            }
+
+            events.push(SoundEvent {
+                id,
+                name: name.to_string(),
+                bank: bank.to_string(),
+                sample: sample.to_string(),
+            });
+        }
+
+        Ok(Self { events })
+    }
+
+    pub fn events(&self) -> &[SoundEvent] {
+        &self.events
+    }
+
+    pub fn by_id(&self, id: u32) -> Option<&SoundEvent> {
+        self.events.iter().find(|event| event.id == id)
+    }
+
+    /// Finds an event by name, names ignore case
+    pub fn by_name(&self, name: &str) -> Option<&SoundEvent> {
+        self.events
+            .iter()
+            .find(|event| event.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Events using samples from the provided bank
+    pub fn bank_events<'a>(&'a self, bank: &'a str) -> impl Iterator<Item = &'a SoundEvent> {
+        self.events
+            .iter()
+            .filter(move |event| event.bank.eq_ignore_ascii_case(bank))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SoundEventError, SoundEventTable};
+
+    #[test]
+    fn test_parse_sound_events() {
+        let table = SoundEventTable::parse(
+            "# id name bank sample\n12 door_open doors door_open_01\n0x1f glitch_hit glitch hit_03\n",
+        )
+        .unwrap();
+
+        assert_eq!(table.events().len(), 2);
+        assert_eq!(table.by_id(31).unwrap().name, "glitch_hit");
+        assert_eq!(
+            table.by_name("DOOR_OPEN").unwrap().sample_path(),
+            "Sounds/doors/door_open_01.wav"
+        );
+        assert_eq!(table.bank_events("glitch").count(), 1);
+
+        assert!(matches!(
+            SoundEventTable::parse("1 a b c\n1 d e f"),
+            Err(SoundEventError::DuplicateId(2, 1))
+        ));
+        assert!(SoundEventTable::parse("1 a b").is_err());
+    }
+}
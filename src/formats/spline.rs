@@ -0,0 +1,191 @@
+//! Splines placed as shapes in a world. The points of a spline shape are
+//! stored in its user data as a point count and flags followed by the
+//! position of each point, the spline passes through every point and
+//! closed splines join the last point back to the first
+
+use std::io::Cursor;
+
+use bevy::math::Vec3;
+use binrw::{BinRead, Endian};
+use thiserror::Error;
+
+use super::types::RawVec3f;
+
+#[derive(Debug, Error)]
+pub enum SplineError {
+    #[error(transparent)]
+    Parse(#[from] binrw::Error),
+    #[error("spline has {0} points, at least 2 are required")]
+    TooFewPoints(usize),
+}
+
+// PasmSplinePt
+#[derive(Debug, BinRead)]
+pub struct RawSplinePoint {
+    pub position: RawVec3f,
+}
+
+/// Spline user data of a shape
+#[derive(Debug, BinRead)]
+pub struct RawSpline {
+    pub point_count: u32,
+    pub flags: u32,
+    #[br(count = point_count)]
+    pub points: Vec<RawSplinePoint>,
+}
+
+impl RawSpline {
+    pub const FLAG_CLOSED: u32 = 0x1;
+}
+
+/// Catmull-Rom spline through a list of points
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spline {
+    pub points: Vec<Vec3>,
+    /// Whether the last point joins back to the first
+    pub closed: bool,
+}
+
+impl Spline {
+    pub fn new(points: Vec<Vec3>, closed: bool) -> Result<Spline, SplineError> {
+        if points.len() < 2 {
+            return Err(SplineError::TooFewPoints(points.len()));
+        }
+        Ok(Spline { points, closed })
+    }
+
+    /// Decodes the user data of a spline shape in the byte order of the
+    /// world it was read from
+    pub fn from_user_data(bytes: &[u8], endian: Endian) -> Result<Spline, SplineError> {
+        let raw = RawSpline::read_options(&mut Cursor::new(bytes), endian, ())?;
+        Spline::new(
+            raw.points
+                .iter()
+                .map(|point| Vec3::from(&point.position))
+                .collect(),
+            raw.flags & RawSpline::FLAG_CLOSED != 0,
+        )
+    }
+
+    /// Number of segments between points
+    pub fn segment_count(&self) -> usize {
+        if self.closed {
+            self.points.len()
+        } else {
+            self.points.len() - 1
+        }
+    }
+
+    /// Point at the index, wrapping around closed splines and clamping to
+    /// the ends of open ones
+    fn point(&self, index: isize) -> Vec3 {
+        let len = self.points.len() as isize;
+        let index = if self.closed {
+            index.rem_euclid(len)
+        } else {
+            index.clamp(0, len - 1)
+        };
+        self.points[index as usize]
+    }
+
+    /// Position along the segment starting at the point index, t runs
+    /// from 0.0 at the start of the segment to 1.0 at its end
+    pub fn evaluate_segment(&self, segment: usize, t: f32) -> Vec3 {
+        let segment = segment as isize;
+        let [p0, p1, p2, p3] = [-1, 0, 1, 2].map(|offset| self.point(segment + offset));
+
+        let t2 = t * t;
+        let t3 = t2 * t;
+        0.5 * ((2.0 * p1)
+            + (p2 - p0) * t
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+            + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+    }
+
+    /// Position along the whole spline, t runs from 0.0 at the first point
+    /// to 1.0 at the last point, or back at the first point when closed.
+    /// Each segment covers an equal part of t
+    pub fn evaluate(&self, t: f32) -> Vec3 {
+        let count = self.segment_count();
+        let scaled = t.clamp(0.0, 1.0) * count as f32;
+        let segment = (scaled.floor() as usize).min(count - 1);
+        self.evaluate_segment(segment, scaled - segment as f32)
+    }
+
+    /// Positions along the spline with the provided number of steps per
+    /// segment, closed splines end with their first point
+    pub fn polyline(&self, steps: usize) -> Vec<Vec3> {
+        let steps = steps.max(1);
+        let mut out = Vec::with_capacity(self.segment_count() * steps + 1);
+        for segment in 0..self.segment_count() {
+            for step in 0..steps {
+                out.push(self.evaluate_segment(segment, step as f32 / steps as f32));
+            }
+        }
+        out.push(self.point(self.segment_count() as isize));
+        out
+    }
+
+    /// Approximate length of the spline measured along its polyline
+    pub fn length(&self, steps: usize) -> f32 {
+        self.polyline(steps)
+            .windows(2)
+            .map(|pair| pair[0].distance(pair[1]))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::Vec3;
+    use binrw::Endian;
+
+    use super::{Spline, SplineError};
+
+    fn user_data(points: &[[f32; 3]], flags: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&u32::to_be_bytes(points.len() as u32));
+        out.extend_from_slice(&u32::to_be_bytes(flags));
+        for value in points.iter().flatten() {
+            out.extend_from_slice(&f32::to_be_bytes(*value));
+        }
+        out
+    }
+
+    #[test]
+    fn test_spline_user_data() {
+        let bytes = user_data(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 1.0]], 1);
+        let spline = Spline::from_user_data(&bytes, Endian::Big).unwrap();
+        assert!(spline.closed);
+        assert_eq!(spline.points[2], Vec3::new(1.0, 0.0, 1.0));
+        assert_eq!(spline.segment_count(), 3);
+
+        let bytes = user_data(&[[0.0, 0.0, 0.0]], 0);
+        assert!(matches!(
+            Spline::from_user_data(&bytes, Endian::Big),
+            Err(SplineError::TooFewPoints(1))
+        ));
+    }
+
+    #[test]
+    fn test_evaluate_through_points() {
+        let points = vec![Vec3::ZERO, Vec3::X, Vec3::new(2.0, 0.0, 1.0)];
+
+        let open = Spline::new(points.clone(), false).unwrap();
+        assert_eq!(open.evaluate(0.0), Vec3::ZERO);
+        assert!(open.evaluate(0.5).abs_diff_eq(Vec3::X, 1e-5));
+        assert!(open.evaluate(1.0).abs_diff_eq(points[2], 1e-5));
+
+        let closed = Spline::new(points, true).unwrap();
+        assert!(closed.evaluate(1.0).abs_diff_eq(Vec3::ZERO, 1e-5));
+        let polyline = closed.polyline(4);
+        assert_eq!(polyline.len(), 13);
+        assert_eq!(polyline.last(), Some(&Vec3::ZERO));
+    }
+
+    #[test]
+    fn test_straight_length() {
+        let spline = Spline::new(vec![Vec3::ZERO, Vec3::X * 2.0, Vec3::X * 4.0], false).unwrap();
+        assert!((spline.length(8) - 4.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,296 @@
+//! Readers allowing the binrw parsers, which seek around the file to follow
+//! [NullableFilePtr](super::types::NullableFilePtr)s, to read from sources
+//! where seeking is expensive or impossible.
+//!
+//! [RangeReader] reads fixed size blocks on demand from a [RangeSource] such
+//! as ranged HTTP fetches, known ranges can be prefetched up front so they're
+//! requested together. [ForwardReader] wraps a forward only stream such as a
+//! decompressor, keeping everything read so far so seeking backwards is free.
+
+use std::{
+    collections::HashMap,
+    io::{self, Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+/// Default size of the blocks fetched by a [RangeReader]
+pub const DEFAULT_BLOCK_SIZE: usize = 64 * 1024;
+
+/// Source that can read arbitrary byte ranges, each read is
+/// assumed to be expensive
+pub trait RangeSource {
+    /// Total length of the source in bytes
+    fn length(&mut self) -> io::Result<u64>;
+
+    /// Fills the buffer with the bytes starting at the offset, the
+    /// range is always within the length of the source
+    fn read_range(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()>;
+}
+
+impl RangeSource for Vec<u8> {
+    fn length(&mut self) -> io::Result<u64> {
+        Ok(Vec::len(self) as u64)
+    }
+
+    fn read_range(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        let start = offset as usize;
+        buf.copy_from_slice(&self[start..start + buf.len()]);
+        Ok(())
+    }
+}
+
+/// Converts a seek into an absolute position
+fn seek_position(position: u64, length: u64, pos: SeekFrom) -> io::Result<u64> {
+    let (base, offset) = match pos {
+        SeekFrom::Start(value) => return Ok(value),
+        SeekFrom::End(offset) => (length, offset),
+        SeekFrom::Current(offset) => (position, offset),
+    };
+
+    base.checked_add_signed(offset).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        )
+    })
+}
+
+/// Seekable reader over a [RangeSource] caching the fetched blocks
+pub struct RangeReader<S> {
+    source: S,
+    length: u64,
+    position: u64,
+    block_size: usize,
+    blocks: HashMap<u64, Vec<u8>>,
+    /// Number of reads made from the source
+    fetches: usize,
+}
+
+impl<S: RangeSource> RangeReader<S> {
+    pub fn new(source: S) -> io::Result<Self> {
+        Self::with_block_size(source, DEFAULT_BLOCK_SIZE)
+    }
+
+    pub fn with_block_size(mut source: S, block_size: usize) -> io::Result<Self> {
+        let length = source.length()?;
+        Ok(Self {
+            source,
+            length,
+            position: 0,
+            block_size: block_size.max(1),
+            blocks: HashMap::new(),
+            fetches: 0,
+        })
+    }
+
+    /// Number of reads made from the source so far
+    pub fn fetch_count(&self) -> usize {
+        self.fetches
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    /// Range of bytes covered by the block at the provided index
+    fn block_range(&self, index: u64) -> Range<u64> {
+        let start = index * self.block_size as u64;
+        start..(start + self.block_size as u64).min(self.length)
+    }
+
+    /// Fetches the blocks covering the provided ranges, consecutive blocks
+    /// that aren't cached yet are read from the source in a single request
+    pub fn prefetch<I>(&mut self, ranges: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = Range<u64>>,
+    {
+        let block_size = self.block_size as u64;
+        let mut missing: Vec<u64> = ranges
+            .into_iter()
+            .filter(|range| range.start < range.end)
+            .flat_map(|range| {
+                let end = range.end.min(self.length);
+                range.start / block_size..end.div_ceil(block_size)
+            })
+            .filter(|index| !self.blocks.contains_key(index))
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+
+        let mut runs: Vec<Range<u64>> = Vec::new();
+        for index in missing {
+            match runs.last_mut() {
+                Some(run) if run.end == index => run.end += 1,
+                _ => runs.push(index..index + 1),
+            }
+        }
+
+        for run in runs {
+            let start = self.block_range(run.start).start;
+            let end = self.block_range(run.end - 1).end;
+
+            let mut bytes = vec![0u8; (end - start) as usize];
+            self.source.read_range(start, &mut bytes)?;
+            self.fetches += 1;
+
+            for (index, chunk) in run.zip(bytes.chunks(self.block_size)) {
+                self.blocks.insert(index, chunk.to_vec());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn block(&mut self, index: u64) -> io::Result<&[u8]> {
+        if !self.blocks.contains_key(&index) {
+            let range = self.block_range(index);
+            let mut bytes = vec![0u8; (range.end - range.start) as usize];
+            self.source.read_range(range.start, &mut bytes)?;
+            self.fetches += 1;
+            self.blocks.insert(index, bytes);
+        }
+
+        Ok(&self.blocks[&index])
+    }
+}
+
+impl<S: RangeSource> Read for RangeReader<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.length || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let block_size = self.block_size as u64;
+        let within = (self.position % block_size) as usize;
+        let block = self.block(self.position / block_size)?;
+
+        let count = buf.len().min(block.len() - within);
+        buf[..count].copy_from_slice(&block[within..within + count]);
+
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl<S: RangeSource> Seek for RangeReader<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = seek_position(self.position, self.length, pos)?;
+        Ok(self.position)
+    }
+}
+
+/// Size of the chunks read from the inner reader of a [ForwardReader]
+const FORWARD_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Seekable reader over a forward only stream, everything read from the
+/// stream is kept so seeking backwards never touches the stream and seeking
+/// forwards reads ahead. Memory use is bounded by the furthest offset read
+pub struct ForwardReader<R> {
+    inner: R,
+    buffer: Vec<u8>,
+    position: u64,
+    /// Whether the end of the inner stream was reached
+    eof: bool,
+}
+
+impl<R: Read> ForwardReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+            position: 0,
+            eof: false,
+        }
+    }
+
+    /// Reads from the stream until the buffer holds `end` bytes
+    /// or the stream ends
+    fn fill_to(&mut self, end: u64) -> io::Result<()> {
+        let mut chunk = [0u8; FORWARD_CHUNK_SIZE];
+        while !self.eof && (self.buffer.len() as u64) < end {
+            match self.inner.read(&mut chunk) {
+                Ok(0) => self.eof = true,
+                Ok(count) => self.buffer.extend_from_slice(&chunk[..count]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for ForwardReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fill_to(self.position + buf.len() as u64)?;
+
+        let start = (self.position as usize).min(self.buffer.len());
+        let count = buf.len().min(self.buffer.len() - start);
+        buf[..count].copy_from_slice(&self.buffer[start..start + count]);
+
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl<R: Read> Seek for ForwardReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        // The length is only known once the whole stream is read
+        if let SeekFrom::End(_) = pos {
+            self.fill_to(u64::MAX)?;
+        }
+
+        self.position = seek_position(self.position, self.buffer.len() as u64, pos)?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use binrw::BinRead;
+
+    use crate::formats::types::NullableFilePtr;
+
+    use super::{ForwardReader, RangeReader};
+
+    #[derive(BinRead)]
+    #[br(big)]
+    struct Node {
+        value: u32,
+        next: NullableFilePtr<u32>,
+    }
+
+    fn node_bytes() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&1u32.to_be_bytes());
+        bytes.extend_from_slice(&12u32.to_be_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&7u32.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_forward_reader() {
+        // Cursor only used as a Read, the reader has to handle the seeks
+        let mut reader = ForwardReader::new(Cursor::new(node_bytes()));
+        let node = Node::read(&mut reader).unwrap();
+        assert_eq!(node.value, 1);
+        assert_eq!(node.next.value, Some(7));
+    }
+
+    #[test]
+    fn test_range_reader() {
+        let mut reader = RangeReader::with_block_size(node_bytes(), 4).unwrap();
+        let node = Node::read(&mut reader).unwrap();
+        assert_eq!(node.value, 1);
+        assert_eq!(node.next.value, Some(7));
+        // Padding block is never fetched
+        assert_eq!(reader.fetch_count(), 3);
+
+        let mut reader = RangeReader::with_block_size(node_bytes(), 4).unwrap();
+        reader.prefetch([0..8, 12..16]).unwrap();
+        Node::read(&mut reader).unwrap();
+        assert_eq!(reader.fetch_count(), 2);
+    }
+}
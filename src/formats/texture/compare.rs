@@ -0,0 +1,206 @@
+use std::path::Path;
+
+use thiserror::Error;
+
+use super::DecodedTexture;
+
+/// Size of the blocks compared, matches the 4x4 blocks used by the
+/// compressed texel formats
+pub const BLOCK_SIZE: u32 = 4;
+
+#[derive(Debug, Error)]
+pub enum CompareError {
+    #[error("texture size {0}x{1} does not match reference size {2}x{3}")]
+    SizeMismatch(u32, u32, u32, u32),
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+}
+
+/// Result of comparing a decoded texture against a reference
+#[derive(Debug)]
+pub struct TextureComparison {
+    /// Mean squared error across all channels
+    pub mse: f64,
+    /// Peak signal to noise ratio in decibels (infinite when identical)
+    pub psnr: f64,
+    /// Positions (in blocks) of the blocks containing a texel that differs from
+    /// the reference by more than the tolerance
+    pub mismatched_blocks: Vec<(u32, u32)>,
+}
+
+impl TextureComparison {
+    pub fn is_identical(&self) -> bool {
+        self.mse == 0.0
+    }
+}
+
+/// Loads a reference PNG (e.g. a Dolphin texture dump) as RGBA8
+pub fn load_reference(path: &Path) -> Result<DecodedTexture, CompareError> {
+    let image = image::open(path)?.to_rgba8();
+
+    Ok(DecodedTexture {
+        width: image.width(),
+        height: image.height(),
+        data: image.into_raw(),
+    })
+}
+
+/// Saves the texture as a PNG, used to write out highlighted comparisons
+pub fn save_png(texture: &DecodedTexture, path: &Path) -> Result<(), CompareError> {
+    image::save_buffer(
+        path,
+        &texture.data,
+        texture.width,
+        texture.height,
+        image::ColorType::Rgba8,
+    )?;
+    Ok(())
+}
+
+/// Compares the decoded texture against the reference, any channel differing
+/// by more than `tolerance` marks its block as mismatched
+pub fn compare_textures(
+    decoded: &DecodedTexture,
+    reference: &DecodedTexture,
+    tolerance: u8,
+) -> Result<TextureComparison, CompareError> {
+    if decoded.width != reference.width || decoded.height != reference.height {
+        return Err(CompareError::SizeMismatch(
+            decoded.width,
+            decoded.height,
+            reference.width,
+            reference.height,
+        ));
+    }
+
+    let blocks_across = decoded.width.div_ceil(BLOCK_SIZE);
+    let blocks_down = decoded.height.div_ceil(BLOCK_SIZE);
+    let mut mismatched = vec![false; (blocks_across * blocks_down) as usize];
+    let mut squared_error: f64 = 0.0;
+
+    for y in 0..decoded.height {
+        for x in 0..decoded.width {
+            let left = decoded.texel(x, y);
+            let right = reference.texel(x, y);
+
+            let mut block_mismatch = false;
+            for (a, b) in left.iter().zip(right.iter()) {
+                let difference = a.abs_diff(*b);
+                squared_error += (difference as f64).powi(2);
+                block_mismatch |= difference > tolerance;
+            }
+
+            if block_mismatch {
+                let block = (y / BLOCK_SIZE) * blocks_across + (x / BLOCK_SIZE);
+                mismatched[block as usize] = true;
+            }
+        }
+    }
+
+    let samples = decoded.data.len().max(1) as f64;
+    let mse = squared_error / samples;
+    let psnr = if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0f64.powi(2) / mse).log10()
+    };
+
+    let mismatched_blocks = mismatched
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| **value)
+        .map(|(index, _)| {
+            let index = index as u32;
+            (index % blocks_across, index / blocks_across)
+        })
+        .collect();
+
+    Ok(TextureComparison {
+        mse,
+        psnr,
+        mismatched_blocks,
+    })
+}
+
+/// Creates a copy of the decoded texture with the mismatched blocks
+/// tinted red so they stand out
+pub fn highlight_mismatches(
+    decoded: &DecodedTexture,
+    comparison: &TextureComparison,
+) -> DecodedTexture {
+    let mut out = decoded.clone();
+
+    for (block_x, block_y) in &comparison.mismatched_blocks {
+        let start_x = block_x * BLOCK_SIZE;
+        let start_y = block_y * BLOCK_SIZE;
+
+        for y in start_y..(start_y + BLOCK_SIZE).min(out.height) {
+            for x in start_x..(start_x + BLOCK_SIZE).min(out.width) {
+                let [red, green, blue, _] = out.texel(x, y);
+                out.set_texel(x, y, [red / 2 + 128, green / 2, blue / 2, 255]);
+            }
+        }
+    }
+
+    out
+}
+
+/// Diagnostic mode comparing a decoded texture against the reference PNG at
+/// `reference_path`, when blocks mismatch a highlighted copy of the decoded
+/// texture is written to `highlight_path`
+pub fn compare_with_reference(
+    decoded: &DecodedTexture,
+    reference_path: &Path,
+    highlight_path: &Path,
+    tolerance: u8,
+) -> Result<TextureComparison, CompareError> {
+    let reference = load_reference(reference_path)?;
+    let comparison = compare_textures(decoded, &reference, tolerance)?;
+
+    if !comparison.mismatched_blocks.is_empty() {
+        let highlighted = highlight_mismatches(decoded, &comparison);
+        save_png(&highlighted, highlight_path)?;
+    }
+
+    Ok(comparison)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::formats::texture::DecodedTexture;
+
+    use super::{compare_textures, highlight_mismatches};
+
+    fn solid(width: u32, height: u32, value: [u8; 4]) -> DecodedTexture {
+        DecodedTexture {
+            width,
+            height,
+            data: value.repeat((width * height) as usize),
+        }
+    }
+
+    #[test]
+    fn test_compare_identical() {
+        let texture = solid(8, 8, [10, 20, 30, 255]);
+        let comparison = compare_textures(&texture, &texture, 0).unwrap();
+
+        assert!(comparison.is_identical());
+        assert!(comparison.psnr.is_infinite());
+        assert!(comparison.mismatched_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_compare_mismatched_block() {
+        let reference = solid(8, 8, [10, 20, 30, 255]);
+        let mut decoded = reference.clone();
+        decoded.set_texel(5, 6, [200, 20, 30, 255]);
+
+        let comparison = compare_textures(&decoded, &reference, 2).unwrap();
+        assert_eq!(comparison.mismatched_blocks, vec![(1, 1)]);
+        assert!(comparison.psnr.is_finite());
+
+        let highlighted = highlight_mismatches(&decoded, &comparison);
+        assert_ne!(highlighted.texel(4, 4), decoded.texel(4, 4));
+        assert_eq!(highlighted.texel(0, 0), decoded.texel(0, 0));
+    }
+}
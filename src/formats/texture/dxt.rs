@@ -0,0 +1,209 @@
+//! Decoding of the DXT (BC1-3) block compressed texel formats used by the
+//! DirectX assets. The color blocks are shared with the GameCube CMPR
+//! format which stores the same blocks with big endian colors
+
+use super::DecodedTexture;
+
+/// Width and height of the compressed blocks in texels
+pub const DXT_BLOCK_SIZE: u32 = 4;
+
+/// Expands a 5 or 6 bit channel to 8 bits
+fn expand(value: u16, bits: u32) -> u8 {
+    let value = value as u32;
+    ((value << (8 - bits)) | (value >> (2 * bits - 8))) as u8
+}
+
+/// Converts an RGB565 color to RGBA8
+pub fn rgb565(value: u16) -> [u8; 4] {
+    [
+        expand(value >> 11, 5),
+        expand((value >> 5) & 0x3F, 6),
+        expand(value & 0x1F, 5),
+        255,
+    ]
+}
+
+fn blend(a: [u8; 4], b: [u8; 4], a_weight: u16, b_weight: u16) -> [u8; 4] {
+    let total = a_weight + b_weight;
+    std::array::from_fn(|channel| {
+        ((a[channel] as u16 * a_weight + b[channel] as u16 * b_weight) / total) as u8
+    })
+}
+
+/// Builds the four colors of a color block, when `punch_through` is set and
+/// the first color isn't greater than the second the block has three colors
+/// and a transparent texel
+pub fn color_palette(c0: u16, c1: u16, punch_through: bool) -> [[u8; 4]; 4] {
+    let first = rgb565(c0);
+    let second = rgb565(c1);
+
+    if c0 > c1 || !punch_through {
+        [
+            first,
+            second,
+            blend(first, second, 2, 1),
+            blend(first, second, 1, 2),
+        ]
+    } else {
+        let middle = blend(first, second, 1, 1);
+        let [red, green, blue, _] = middle;
+        [first, second, middle, [red, green, blue, 0]]
+    }
+}
+
+/// Decodes the 16 texels of a little endian color block, texel indices
+/// are stored two bits each starting from the lowest bits
+fn decode_color_block(block: &[u8], punch_through: bool) -> [[u8; 4]; 16] {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    let palette = color_palette(c0, c1, punch_through);
+
+    std::array::from_fn(|texel| palette[((indices >> (texel * 2)) & 0b11) as usize])
+}
+
+/// Explicit 4 bit alpha of a DXT3 block
+fn decode_explicit_alpha(block: &[u8]) -> [u8; 16] {
+    let values = u64::from_le_bytes(block[..8].try_into().expect("Alpha block is 8 bytes"));
+    std::array::from_fn(|texel| ((values >> (texel * 4)) & 0xF) as u8 * 17)
+}
+
+/// Interpolated alpha of a DXT5 block
+fn decode_interpolated_alpha(block: &[u8]) -> [u8; 16] {
+    let a0 = block[0] as u16;
+    let a1 = block[1] as u16;
+
+    let palette: [u8; 8] = std::array::from_fn(|index| {
+        let index = index as u16;
+        match index {
+            0 => a0 as u8,
+            1 => a1 as u8,
+            _ if a0 > a1 => ((a0 * (8 - index) + a1 * (index - 1)) / 7) as u8,
+            6 => 0,
+            7 => 255,
+            _ => ((a0 * (6 - index) + a1 * (index - 1)) / 5) as u8,
+        }
+    });
+
+    let mut bits = [0u8; 8];
+    bits[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bits);
+
+    std::array::from_fn(|texel| palette[((indices >> (texel * 3)) & 0b111) as usize])
+}
+
+/// Variant of DXT compression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DxtFormat {
+    /// Color only with optional punch through alpha
+    Dxt1,
+    /// Color with explicit 4 bit alpha
+    Dxt3,
+    /// Color with interpolated alpha
+    Dxt5,
+}
+
+impl DxtFormat {
+    /// Size in bytes of a block
+    pub fn block_bytes(&self) -> usize {
+        match self {
+            DxtFormat::Dxt1 => 8,
+            DxtFormat::Dxt3 | DxtFormat::Dxt5 => 16,
+        }
+    }
+
+    fn decode_block(&self, block: &[u8]) -> [[u8; 4]; 16] {
+        match self {
+            DxtFormat::Dxt1 => decode_color_block(block, true),
+            DxtFormat::Dxt3 => {
+                let alpha = decode_explicit_alpha(&block[..8]);
+                let mut texels = decode_color_block(&block[8..], false);
+                texels
+                    .iter_mut()
+                    .zip(alpha)
+                    .for_each(|(texel, alpha)| texel[3] = alpha);
+                texels
+            }
+            DxtFormat::Dxt5 => {
+                let alpha = decode_interpolated_alpha(&block[..8]);
+                let mut texels = decode_color_block(&block[8..], false);
+                texels
+                    .iter_mut()
+                    .zip(alpha)
+                    .for_each(|(texel, alpha)| texel[3] = alpha);
+                texels
+            }
+        }
+    }
+}
+
+/// Decodes a DXT image, `data` must hold every block of the image
+pub(super) fn decode_dxt(
+    format: DxtFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) -> DecodedTexture {
+    let mut out = DecodedTexture::new(width, height);
+    let blocks_across = width.div_ceil(DXT_BLOCK_SIZE);
+
+    for (index, block) in data.chunks_exact(format.block_bytes()).enumerate() {
+        let block_x = index as u32 % blocks_across * DXT_BLOCK_SIZE;
+        let block_y = index as u32 / blocks_across * DXT_BLOCK_SIZE;
+
+        for (texel, value) in format.decode_block(block).into_iter().enumerate() {
+            let x = block_x + texel as u32 % DXT_BLOCK_SIZE;
+            let y = block_y + texel as u32 / DXT_BLOCK_SIZE;
+            if x < width && y < height {
+                out.set_texel(x, y, value);
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{color_palette, decode_dxt, rgb565, DxtFormat};
+
+    #[test]
+    fn test_rgb565() {
+        assert_eq!(rgb565(0xFFFF), [255, 255, 255, 255]);
+        assert_eq!(rgb565(0xF800), [255, 0, 0, 255]);
+        assert_eq!(rgb565(0x07E0), [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn test_punch_through_palette() {
+        let palette = color_palette(0x0000, 0xFFFF, true);
+        assert_eq!(palette[2], [127, 127, 127, 255]);
+        assert_eq!(palette[3][3], 0);
+
+        // Same colors without punch through interpolate a third of the way
+        let palette = color_palette(0x0000, 0xFFFF, false);
+        assert_eq!(palette[2], [85, 85, 85, 255]);
+    }
+
+    #[test]
+    fn test_decode_dxt() {
+        // Red and blue with the first texel using the second color
+        let block = [0x00, 0xF8, 0x1F, 0x00, 0b01, 0, 0, 0];
+        let texture = decode_dxt(DxtFormat::Dxt1, 4, 4, &block);
+        assert_eq!(texture.texel(0, 0), [0, 0, 255, 255]);
+        assert_eq!(texture.texel(1, 0), [255, 0, 0, 255]);
+
+        // Explicit alpha of the first texel only
+        let mut block = vec![0x0F, 0, 0, 0, 0, 0, 0, 0];
+        block.extend([0xFF, 0xFF, 0, 0, 0, 0, 0, 0]);
+        let texture = decode_dxt(DxtFormat::Dxt3, 4, 4, &block);
+        assert_eq!(texture.texel(0, 0), [255, 255, 255, 255]);
+        assert_eq!(texture.texel(1, 0), [255, 255, 255, 0]);
+
+        // Interpolated alpha with every texel using the first alpha
+        let mut block = vec![200, 100, 0, 0, 0, 0, 0, 0];
+        block.extend([0xFF, 0xFF, 0, 0, 0, 0, 0, 0]);
+        let texture = decode_dxt(DxtFormat::Dxt5, 4, 4, &block);
+        assert_eq!(texture.texel(3, 3)[3], 200);
+    }
+}
@@ -0,0 +1,230 @@
+//! Decoding of the GameCube GX texel formats. GX textures are stored as
+//! tiles of 32 bytes (64 for RGBA8) covering a fixed number of texels,
+//! the tiles are stored row by row and images are padded to whole tiles
+
+use thiserror::Error;
+
+use super::{
+    dxt::{color_palette, rgb565},
+    DecodedTexture,
+};
+
+/// GX texel formats (matches the GX_TF_* values)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum GXTexFmt {
+    I4 = 0x0,
+    I8 = 0x1,
+    IA4 = 0x2,
+    IA8 = 0x3,
+    RGB565 = 0x4,
+    RGB5A3 = 0x5,
+    RGBA8 = 0x6,
+    /// DXT1 style block compression
+    CMPR = 0xE,
+}
+
+/// Texel format value that isn't a supported GX texel format, palette
+/// formats aren't supported
+#[derive(Debug, Error)]
+#[error("unsupported GX texel format {0:#x}")]
+pub struct UnknownTexFmt(pub u8);
+
+impl TryFrom<u8> for GXTexFmt {
+    type Error = UnknownTexFmt;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0x0 => GXTexFmt::I4,
+            0x1 => GXTexFmt::I8,
+            0x2 => GXTexFmt::IA4,
+            0x3 => GXTexFmt::IA8,
+            0x4 => GXTexFmt::RGB565,
+            0x5 => GXTexFmt::RGB5A3,
+            0x6 => GXTexFmt::RGBA8,
+            0xE => GXTexFmt::CMPR,
+            _ => return Err(UnknownTexFmt(value)),
+        })
+    }
+}
+
+impl GXTexFmt {
+    /// Width and height in texels of a single tile
+    pub fn tile_size(&self) -> (u32, u32) {
+        match self {
+            GXTexFmt::I4 | GXTexFmt::CMPR => (8, 8),
+            GXTexFmt::I8 | GXTexFmt::IA4 => (8, 4),
+            GXTexFmt::IA8 | GXTexFmt::RGB565 | GXTexFmt::RGB5A3 | GXTexFmt::RGBA8 => (4, 4),
+        }
+    }
+
+    /// Size in bytes of a single tile
+    pub fn tile_bytes(&self) -> usize {
+        match self {
+            GXTexFmt::RGBA8 => 64,
+            _ => 32,
+        }
+    }
+
+    /// Decodes the texels of a single tile in row major order
+    fn decode_tile(&self, tile: &[u8]) -> Vec<[u8; 4]> {
+        match self {
+            GXTexFmt::I4 => tile
+                .iter()
+                .flat_map(|value| [value >> 4, value & 0xF])
+                .map(|value| intensity(value * 17, 255))
+                .collect(),
+            GXTexFmt::I8 => tile.iter().map(|value| intensity(*value, 255)).collect(),
+            GXTexFmt::IA4 => tile
+                .iter()
+                .map(|value| intensity((value & 0xF) * 17, (value >> 4) * 17))
+                .collect(),
+            GXTexFmt::IA8 => tile
+                .chunks_exact(2)
+                .map(|value| intensity(value[1], value[0]))
+                .collect(),
+            GXTexFmt::RGB565 => tile
+                .chunks_exact(2)
+                .map(|value| rgb565(u16::from_be_bytes([value[0], value[1]])))
+                .collect(),
+            GXTexFmt::RGB5A3 => tile
+                .chunks_exact(2)
+                .map(|value| rgb5a3(u16::from_be_bytes([value[0], value[1]])))
+                .collect(),
+            GXTexFmt::RGBA8 => {
+                // Alpha and red pairs for the tile followed by green and blue pairs
+                let (ar, gb) = tile.split_at(32);
+                ar.chunks_exact(2)
+                    .zip(gb.chunks_exact(2))
+                    .map(|(ar, gb)| [ar[1], gb[0], gb[1], ar[0]])
+                    .collect()
+            }
+            GXTexFmt::CMPR => decode_cmpr_tile(tile),
+        }
+    }
+}
+
+fn intensity(value: u8, alpha: u8) -> [u8; 4] {
+    [value, value, value, alpha]
+}
+
+/// Converts an RGB5A3 texel, the top bit selects between opaque
+/// RGB555 and RGB444 with 3 bits of alpha
+fn rgb5a3(value: u16) -> [u8; 4] {
+    if value & 0x8000 != 0 {
+        let channel = |shift: u16| {
+            let value = (value >> shift) & 0x1F;
+            ((value << 3) | (value >> 2)) as u8
+        };
+        [channel(10), channel(5), channel(0), 255]
+    } else {
+        let channel = |shift: u16| ((value >> shift) & 0xF) as u8 * 17;
+        let alpha = ((value >> 12) & 0x7) as u8;
+        [
+            channel(8),
+            channel(4),
+            channel(0),
+            (alpha << 5) | (alpha << 2) | (alpha >> 1),
+        ]
+    }
+}
+
+/// Decodes an 8x8 CMPR tile made up of four 4x4 DXT1 style blocks with
+/// big endian colors and the texel indices stored from the highest bits
+fn decode_cmpr_tile(tile: &[u8]) -> Vec<[u8; 4]> {
+    let mut out = vec![[0; 4]; 64];
+
+    for (block_index, block) in tile.chunks_exact(8).enumerate() {
+        let c0 = u16::from_be_bytes([block[0], block[1]]);
+        let c1 = u16::from_be_bytes([block[2], block[3]]);
+        let palette = color_palette(c0, c1, true);
+
+        let block_x = (block_index % 2) * 4;
+        let block_y = (block_index / 2) * 4;
+        for (row, indices) in block[4..].iter().enumerate() {
+            for column in 0..4 {
+                let index = (indices >> (6 - column * 2)) & 0b11;
+                out[(block_y + row) * 8 + block_x + column] = palette[index as usize];
+            }
+        }
+    }
+
+    out
+}
+
+/// Number of bytes used by a GX image including the tile padding
+pub fn gx_image_size(format: GXTexFmt, width: u32, height: u32) -> usize {
+    let (tile_width, tile_height) = format.tile_size();
+    (width.div_ceil(tile_width) * height.div_ceil(tile_height)) as usize * format.tile_bytes()
+}
+
+/// Decodes a GX image, `data` must hold every tile of the image
+pub(super) fn decode_gx(format: GXTexFmt, width: u32, height: u32, data: &[u8]) -> DecodedTexture {
+    let mut out = DecodedTexture::new(width, height);
+    let (tile_width, tile_height) = format.tile_size();
+    let tiles_across = width.div_ceil(tile_width);
+
+    for (index, tile) in data.chunks_exact(format.tile_bytes()).enumerate() {
+        let tile_x = index as u32 % tiles_across * tile_width;
+        let tile_y = index as u32 / tiles_across * tile_height;
+
+        for (texel, value) in format.decode_tile(tile).into_iter().enumerate() {
+            let x = tile_x + texel as u32 % tile_width;
+            let y = tile_y + texel as u32 / tile_width;
+            if x < width && y < height {
+                out.set_texel(x, y, value);
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_gx, gx_image_size, rgb5a3, GXTexFmt};
+
+    #[test]
+    fn test_rgb5a3() {
+        assert_eq!(rgb5a3(0xFFFF), [255, 255, 255, 255]);
+        assert_eq!(rgb5a3(0xFC00), [255, 0, 0, 255]);
+        // Translucent blue
+        assert_eq!(rgb5a3(0x400F), [0, 0, 255, 146]);
+    }
+
+    #[test]
+    fn test_decode_tiles() {
+        // 8x4 I8 image is a single tile
+        let data: Vec<u8> = (0..32).collect();
+        let texture = decode_gx(GXTexFmt::I8, 8, 4, &data);
+        assert_eq!(texture.texel(1, 0), [1, 1, 1, 255]);
+        assert_eq!(texture.texel(0, 1), [8, 8, 8, 255]);
+
+        // RGBA8 splits each tile into AR and GB halves
+        let mut data = vec![0u8; 64];
+        data[0..2].copy_from_slice(&[0x80, 0x10]);
+        data[32..34].copy_from_slice(&[0x20, 0x30]);
+        let texture = decode_gx(GXTexFmt::RGBA8, 4, 4, &data);
+        assert_eq!(texture.texel(0, 0), [0x10, 0x20, 0x30, 0x80]);
+
+        // Images smaller than a tile still use a whole tile
+        assert_eq!(gx_image_size(GXTexFmt::CMPR, 4, 4), 32);
+        assert_eq!(gx_image_size(GXTexFmt::I4, 16, 8), 64);
+    }
+
+    #[test]
+    fn test_decode_cmpr() {
+        let mut data = Vec::new();
+        for _ in 0..4 {
+            // Red and blue blocks with the first texel of each row using blue
+            data.extend([0xF8, 0x00, 0x00, 0x1F]);
+            data.extend([0b0100_0000; 4]);
+        }
+
+        let texture = decode_gx(GXTexFmt::CMPR, 8, 8, &data);
+        assert_eq!(texture.texel(0, 0), [0, 0, 255, 255]);
+        assert_eq!(texture.texel(1, 0), [255, 0, 0, 255]);
+        assert_eq!(texture.texel(4, 5), [0, 0, 255, 255]);
+        assert_eq!(texture.texel(7, 7), [255, 0, 0, 255]);
+    }
+}
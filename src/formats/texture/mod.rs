@@ -0,0 +1,253 @@
+use bevy::{
+    prelude::Image,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use thiserror::Error;
+
+use self::{
+    dxt::{decode_dxt, DxtFormat, DXT_BLOCK_SIZE},
+    gx::{decode_gx, gx_image_size, GXTexFmt},
+};
+
+pub mod compare;
+pub mod dxt;
+pub mod gx;
+pub mod sampling;
+
+#[derive(Debug, Error)]
+pub enum TextureError {
+    #[error("texture data is truncated, expected {expected} bytes but got {actual}")]
+    Truncated { expected: usize, actual: usize },
+    #[error("texture has no levels")]
+    NoLevels,
+}
+
+/// Encoding of the texels of a texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TexelFormat {
+    /// GameCube GX texel format
+    Gx(GXTexFmt),
+    /// DirectX block compressed format
+    Dxt(DxtFormat),
+}
+
+impl TexelFormat {
+    /// Number of bytes used by a single level of the provided size
+    pub fn encoded_size(&self, width: u32, height: u32) -> usize {
+        match self {
+            TexelFormat::Gx(format) => gx_image_size(*format, width, height),
+            TexelFormat::Dxt(format) => {
+                let blocks = width.div_ceil(DXT_BLOCK_SIZE) * height.div_ceil(DXT_BLOCK_SIZE);
+                blocks as usize * format.block_bytes()
+            }
+        }
+    }
+}
+
+/// Size of a mip level, levels are halved down to a minimum of one texel
+pub fn mip_size(width: u32, height: u32, level: u32) -> (u32, u32) {
+    ((width >> level).max(1), (height >> level).max(1))
+}
+
+/// Decodes a single level texture into RGBA8 texels
+pub fn decode_texture(
+    format: TexelFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) -> Result<DecodedTexture, TextureError> {
+    let expected = format.encoded_size(width, height);
+    if data.len() < expected {
+        return Err(TextureError::Truncated {
+            expected,
+            actual: data.len(),
+        });
+    }
+
+    let data = &data[..expected];
+    Ok(match format {
+        TexelFormat::Gx(format) => decode_gx(format, width, height, data),
+        TexelFormat::Dxt(format) => decode_dxt(format, width, height, data),
+    })
+}
+
+/// Decodes each of the mip levels of a texture, the levels are stored
+/// one after another starting from the full size level
+pub fn decode_mipmaps(
+    format: TexelFormat,
+    width: u32,
+    height: u32,
+    level_count: u32,
+    data: &[u8],
+) -> Result<Vec<DecodedTexture>, TextureError> {
+    let mut offset = 0;
+    (0..level_count.max(1))
+        .map(|level| {
+            let (width, height) = mip_size(width, height, level);
+            let size = format.encoded_size(width, height);
+            let level = decode_texture(format, width, height, data.get(offset..).unwrap_or(&[]))
+                .map_err(|_| TextureError::Truncated {
+                    expected: offset + size,
+                    actual: data.len(),
+                })?;
+            offset += size;
+            Ok(level)
+        })
+        .collect()
+}
+
+/// Creates an image from decoded mip levels, the first level is
+/// the full size texture
+pub fn mipmapped_image(levels: &[DecodedTexture]) -> Result<Image, TextureError> {
+    let (first, rest) = levels.split_first().ok_or(TextureError::NoLevels)?;
+
+    let mut image = first.to_image();
+    image
+        .data
+        .extend(rest.iter().flat_map(|level| level.data.iter().copied()));
+    image.texture_descriptor.mip_level_count = levels.len() as u32;
+    Ok(image)
+}
+
+/// Builds the full mip chain of a texture by averaging each 2x2 block of
+/// texels, the first level is the provided texture
+pub fn generate_mipmaps(texture: &DecodedTexture) -> Vec<DecodedTexture> {
+    let mut levels = vec![texture.clone()];
+
+    loop {
+        let previous = levels.last().expect("Chain starts with a level");
+        if previous.width == 1 && previous.height == 1 {
+            break;
+        }
+
+        let (width, height) = mip_size(previous.width, previous.height, 1);
+        let mut level = DecodedTexture::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                // Odd sized levels reuse the last row or column
+                let mut sum = [0u32; 4];
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let texel = previous.texel(
+                        (x * 2 + dx).min(previous.width - 1),
+                        (y * 2 + dy).min(previous.height - 1),
+                    );
+                    for (total, value) in sum.iter_mut().zip(texel) {
+                        *total += value as u32;
+                    }
+                }
+                level.set_texel(x, y, sum.map(|value| ((value + 2) / 4) as u8));
+            }
+        }
+
+        levels.push(level);
+    }
+
+    levels
+}
+
+/// Texture decoded into RGBA8 texels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedTexture {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA8 texel data, row major
+    pub data: Vec<u8>,
+}
+
+impl DecodedTexture {
+    /// Creates a fully transparent texture
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    /// Gets the RGBA value of the texel at the provided position
+    pub fn texel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = ((y * self.width + x) * 4) as usize;
+        [
+            self.data[index],
+            self.data[index + 1],
+            self.data[index + 2],
+            self.data[index + 3],
+        ]
+    }
+
+    /// Sets the RGBA value of the texel at the provided position
+    pub fn set_texel(&mut self, x: u32, y: u32, value: [u8; 4]) {
+        let index = ((y * self.width + x) * 4) as usize;
+        self.data[index..index + 4].copy_from_slice(&value);
+    }
+
+    /// Creates a single level image from the texture
+    pub fn to_image(&self) -> Image {
+        Image::new(
+            Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            self.data.clone(),
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        decode_mipmaps, dxt::DxtFormat, generate_mipmaps, gx::GXTexFmt, mipmapped_image,
+        DecodedTexture, TexelFormat, TextureError,
+    };
+
+    #[test]
+    fn test_decode_mipmaps() {
+        // Full size level of an 8x8 DXT1 texture is four blocks
+        let format = TexelFormat::Dxt(DxtFormat::Dxt1);
+        let data = vec![0xFF; 8 * 3];
+        assert!(matches!(
+            decode_mipmaps(format, 8, 8, 4, &data),
+            Err(TextureError::Truncated {
+                expected: 32,
+                actual: 24
+            })
+        ));
+
+        // Remaining levels are a single block each
+        let data = vec![0xFF; 8 * 7];
+        let levels = decode_mipmaps(format, 8, 8, 4, &data).unwrap();
+        let sizes: Vec<_> = levels
+            .iter()
+            .map(|level| (level.width, level.height))
+            .collect();
+        assert_eq!(sizes, [(8, 8), (4, 4), (2, 2), (1, 1)]);
+
+        let image = mipmapped_image(&levels).unwrap();
+        assert_eq!(image.texture_descriptor.mip_level_count, 4);
+        assert_eq!(image.data.len(), (64 + 16 + 4 + 1) * 4);
+
+        // GX levels are padded to whole tiles
+        let format = TexelFormat::Gx(GXTexFmt::RGB5A3);
+        assert_eq!(format.encoded_size(2, 2), 32);
+    }
+
+    #[test]
+    fn test_generate_mipmaps() {
+        let mut texture = DecodedTexture::new(4, 2);
+        texture.set_texel(0, 0, [255, 0, 0, 255]);
+        texture.set_texel(1, 0, [255, 0, 0, 255]);
+
+        let levels = generate_mipmaps(&texture);
+        let sizes: Vec<_> = levels
+            .iter()
+            .map(|level| (level.width, level.height))
+            .collect();
+        assert_eq!(sizes, [(4, 2), (2, 1), (1, 1)]);
+        assert_eq!(levels[1].texel(0, 0), [128, 0, 0, 128]);
+        assert_eq!(levels[1].texel(1, 0), [0, 0, 0, 0]);
+        assert_eq!(levels[2].texel(0, 0), [64, 0, 0, 64]);
+    }
+}
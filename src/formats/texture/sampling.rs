@@ -0,0 +1,148 @@
+//! Sampler settings for decoded textures, shared by the viewer and the
+//! exporters so exported files are filtered the same way they're shown
+
+use bevy::{
+    ecs::system::Resource,
+    render::texture::{ImageAddressMode, ImageFilterMode, ImageSampler, ImageSamplerDescriptor},
+    utils::HashMap,
+};
+
+/// Highest anisotropy level supported by the graphics APIs
+pub const MAX_ANISOTROPY: u16 = 16;
+
+/// glTF sampler filter constants
+pub const GLTF_NEAREST: u32 = 9728;
+pub const GLTF_LINEAR: u32 = 9729;
+pub const GLTF_NEAREST_MIPMAP_NEAREST: u32 = 9984;
+pub const GLTF_LINEAR_MIPMAP_LINEAR: u32 = 9987;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextureFilter {
+    /// Nearest neighbor sampling for the blocky look of the original hardware
+    Nearest,
+    #[default]
+    Linear,
+}
+
+/// How a texture is sampled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SamplerSettings {
+    pub filter: TextureFilter,
+    /// Anisotropic filtering level, 1 disables it. Only used with
+    /// linear filtering
+    pub anisotropy: u16,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self::SMOOTH
+    }
+}
+
+impl SamplerSettings {
+    /// Nearest neighbor sampling without anisotropy
+    pub const RETRO: SamplerSettings = SamplerSettings {
+        filter: TextureFilter::Nearest,
+        anisotropy: 1,
+    };
+
+    /// Linear sampling with anisotropic filtering
+    pub const SMOOTH: SamplerSettings = SamplerSettings {
+        filter: TextureFilter::Linear,
+        anisotropy: 8,
+    };
+
+    /// Anisotropy level actually used, anisotropy requires every
+    /// filter to be linear so nearest sampling never uses it
+    pub fn effective_anisotropy(&self) -> u16 {
+        match self.filter {
+            TextureFilter::Nearest => 1,
+            TextureFilter::Linear => self.anisotropy.clamp(1, MAX_ANISOTROPY),
+        }
+    }
+
+    /// Sampler for the bevy image of the texture, textures repeat
+    /// like they do in game
+    pub fn image_sampler(&self) -> ImageSampler {
+        let filter = match self.filter {
+            TextureFilter::Nearest => ImageFilterMode::Nearest,
+            TextureFilter::Linear => ImageFilterMode::Linear,
+        };
+
+        ImageSampler::Descriptor(ImageSamplerDescriptor {
+            address_mode_u: ImageAddressMode::Repeat,
+            address_mode_v: ImageAddressMode::Repeat,
+            address_mode_w: ImageAddressMode::Repeat,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter: filter,
+            anisotropy_clamp: self.effective_anisotropy(),
+            ..Default::default()
+        })
+    }
+
+    /// glTF magnification and minification filters for the texture, glTF
+    /// has no anisotropy setting so it's left to the importer
+    pub fn gltf_filters(&self) -> (u32, u32) {
+        match self.filter {
+            TextureFilter::Nearest => (GLTF_NEAREST, GLTF_NEAREST_MIPMAP_NEAREST),
+            TextureFilter::Linear => (GLTF_LINEAR, GLTF_LINEAR_MIPMAP_LINEAR),
+        }
+    }
+}
+
+/// Sampler settings applied to all textures, with overrides
+/// for individual textures by name
+#[derive(Debug, Clone, Default, Resource)]
+pub struct TextureSampling {
+    pub global: SamplerSettings,
+    overrides: HashMap<String, SamplerSettings>,
+}
+
+impl TextureSampling {
+    /// Settings used by the texture with the provided name
+    pub fn for_texture(&self, name: &str) -> SamplerSettings {
+        self.overrides
+            .get(&name.to_ascii_lowercase())
+            .copied()
+            .unwrap_or(self.global)
+    }
+
+    /// Overrides the settings of a single texture, names ignore case
+    pub fn set_override(&mut self, name: &str, settings: SamplerSettings) {
+        self.overrides.insert(name.to_ascii_lowercase(), settings);
+    }
+
+    pub fn clear_override(&mut self, name: &str) {
+        self.overrides.remove(&name.to_ascii_lowercase());
+    }
+
+    pub fn overrides(&self) -> impl Iterator<Item = (&str, &SamplerSettings)> {
+        self.overrides
+            .iter()
+            .map(|(name, settings)| (name.as_str(), settings))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SamplerSettings, TextureFilter, TextureSampling, MAX_ANISOTROPY};
+
+    #[test]
+    fn test_texture_overrides() {
+        let mut sampling = TextureSampling::default();
+        sampling.set_override("Arrow_01", SamplerSettings::RETRO);
+
+        assert_eq!(sampling.for_texture("arrow_01"), SamplerSettings::RETRO);
+        assert_eq!(sampling.for_texture("grass"), SamplerSettings::SMOOTH);
+
+        sampling.global.filter = TextureFilter::Nearest;
+        assert_eq!(sampling.for_texture("grass").effective_anisotropy(), 1);
+
+        let settings = SamplerSettings {
+            filter: TextureFilter::Linear,
+            anisotropy: 64,
+        };
+        assert_eq!(settings.effective_anisotropy(), MAX_ANISOTROPY);
+    }
+}
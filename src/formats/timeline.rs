@@ -0,0 +1,138 @@
+//! Event timelines for cutscenes. The layout of the game's own cutscene
+//! data is still unknown, so timelines are read from a plain text listing
+//! of `time,kind,target` lines that reverse engineering notes or other
+//! tools can produce
+
+use std::{fmt::Display, str::FromStr};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TimelineError {
+    #[error("line {0}: expected time,kind,target")]
+    MalformedLine(usize),
+    #[error("line {0}: invalid time {1:?}")]
+    InvalidTime(usize, String),
+    #[error("line {0}: unknown event kind {1:?}")]
+    UnknownKind(usize, String),
+}
+
+/// Kind of timeline event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// Switch to a different camera
+    CameraCut,
+    /// Start an animation on an object
+    Animation,
+    /// Play a sound
+    Sound,
+}
+
+impl FromStr for EventKind {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "camera" => Ok(EventKind::CameraCut),
+            "anim" | "animation" => Ok(EventKind::Animation),
+            "sound" => Ok(EventKind::Sound),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            EventKind::CameraCut => "camera",
+            EventKind::Animation => "animation",
+            EventKind::Sound => "sound",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineEvent {
+    /// Time of the event in seconds from the start of the timeline
+    pub time: f32,
+    pub kind: EventKind,
+    /// Name of the camera, object or sound the event refers to
+    pub target: String,
+}
+
+/// Events of a timeline ordered by time
+#[derive(Debug, Clone, Default)]
+pub struct Timeline {
+    pub events: Vec<TimelineEvent>,
+}
+
+impl Timeline {
+    /// Parses a timeline listing, blank lines and lines starting
+    /// with `#` are ignored
+    pub fn parse(value: &str) -> Result<Timeline, TimelineError> {
+        let mut events = Vec::new();
+
+        for (index, line) in value.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(3, ',').map(str::trim);
+            let (Some(time), Some(kind), Some(target)) = (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(TimelineError::MalformedLine(line_number));
+            };
+
+            let time: f32 = time
+                .parse()
+                .map_err(|_| TimelineError::InvalidTime(line_number, time.to_string()))?;
+            let kind: EventKind = kind
+                .parse()
+                .map_err(|_| TimelineError::UnknownKind(line_number, kind.to_string()))?;
+
+            events.push(TimelineEvent {
+                time,
+                kind,
+                target: target.to_string(),
+            });
+        }
+
+        events.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(Timeline { events })
+    }
+
+    /// Total length of the timeline in seconds
+    pub fn duration(&self) -> f32 {
+        self.events
+            .last()
+            .map(|event| event.time)
+            .unwrap_or_default()
+    }
+
+    /// Index of the last event at or before the provided time
+    pub fn event_at(&self, time: f32) -> Option<usize> {
+        self.events.iter().rposition(|event| event.time <= time)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EventKind, Timeline};
+
+    #[test]
+    fn test_parse_timeline() {
+        let timeline = Timeline::parse(
+            "# intro\n2.5, sound, glitch_hello\n0, camera, cam_01\n1.0,anim,glitch,wave\n",
+        )
+        .unwrap();
+
+        assert_eq!(timeline.events.len(), 3);
+        assert_eq!(timeline.events[0].kind, EventKind::CameraCut);
+        assert_eq!(timeline.events[1].target, "glitch,wave");
+        assert_eq!(timeline.duration(), 2.5);
+        assert_eq!(timeline.event_at(1.5), Some(1));
+        assert!(Timeline::parse("1.0,explode,thing").is_err());
+    }
+}
@@ -1,106 +1,55 @@
 use std::{
-    ffi::CStr,
-    fmt::{Debug, Display},
+    any::type_name,
+    cell::RefCell,
     io::{Read, Seek, SeekFrom},
-    ops::{Deref, DerefMut},
+    ops::Deref,
 };
 
-use binrw::{file_ptr::IntoSeekFrom, BinRead, BinResult, Endian};
+use binrw::{BinRead, BinResult, Endian};
 
-// Offset within the file that something can be found at
-#[derive(Debug, BinRead, Default)]
-pub struct PtrOffset(u32);
-
-// CFMtx43
-#[derive(Debug, BinRead, Default)]
-pub struct RawMatrix4x3f {
-    pub matrix: [[f32; 3]; 4],
-}
-
-// CFMtx44
-#[derive(Debug, BinRead, Default)]
-pub struct RawMatrix4x4f {
-    pub matrix: [[f32; 4]; 4],
-}
-
-#[derive(Debug, BinRead, Default)]
-pub struct RawVec3f {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-}
-
-#[derive(Debug, BinRead, Default)]
-pub struct RawVec2f {
-    pub x: f32,
-    pub y: f32,
-}
-
-#[derive(Debug, BinRead, Default)]
-pub struct RawSphere {
-    pub radius: f32,
-    pub position: RawVec3f,
-}
-
-#[derive(Debug, BinRead, Default)]
-pub struct RawColorRGBA {
-    pub red: f32,
-    pub green: f32,
-    pub blue: f32,
-    pub alpha: f32,
-}
-
-#[derive(Debug, BinRead, Default)]
-pub struct RawColorRGB {
-    pub red: f32,
-    pub green: f32,
-    pub blue: f32,
-}
-
-#[derive(Debug, BinRead, Default)]
-pub struct RawColorMotif {
-    pub color: RawColorRGBA,
-    pub modif_index: u32,
-}
-
-/// Null terminated string created from a fixed length
-/// chunk of bytes
-#[derive(BinRead, Clone, Copy)]
-#[repr(C)]
-pub struct FixedString<const LENGTH: usize> {
-    bytes: [u8; LENGTH],
-}
+pub use openglitch_formats::types::{
+    FixedString, PtrOffset, RawColorMotif, RawColorRGB, RawColorRGBA, RawMatrix4x3f, RawMatrix4x4f,
+    RawSphere, RawVec2f, RawVec3f,
+};
 
-impl<const LENGTH: usize> Default for FixedString<LENGTH> {
-    fn default() -> Self {
-        Self {
-            bytes: [0u8; LENGTH],
+/// Pointer that was read as null because its value ran past the end of
+/// the file while reading with [recover_truncated]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Truncation {
+    /// Offset the value was pointed to at
+    pub ptr: u32,
+    /// Name of the value that was pointed to
+    pub target: &'static str,
+}
+
+thread_local! {
+    /// Truncations recorded while reading with [recover_truncated]
+    static TRUNCATIONS: RefCell<Option<Vec<Truncation>>> = const { RefCell::new(None) };
+}
+
+/// Runs the provided read with truncated [NullableFilePtr] values read as
+/// null instead of failing the whole read, the innermost pointer that ran
+/// past the end of the file is the one nulled so as much as possible of the
+/// file is recovered
+pub fn recover_truncated<T>(read: impl FnOnce() -> T) -> (T, Vec<Truncation>) {
+    let previous = TRUNCATIONS.with(|value| value.replace(Some(Vec::new())));
+    let out = read();
+    let truncations = TRUNCATIONS
+        .with(|value| value.replace(previous))
+        .unwrap_or_default();
+    (out, truncations)
+}
+
+/// Records a truncated pointer, returns false when truncations aren't
+/// being recovered
+fn record_truncation(truncation: Truncation) -> bool {
+    TRUNCATIONS.with(|value| match value.borrow_mut().as_mut() {
+        Some(truncations) => {
+            truncations.push(truncation);
+            true
         }
-    }
-}
-
-impl<const LENGTH: usize> FixedString<LENGTH> {
-    pub fn as_cstr(&self) -> &CStr {
-        CStr::from_bytes_until_nul(&self.bytes).expect("Fixed string missing null byte")
-    }
-
-    pub fn as_string(&self) -> String {
-        self.as_cstr().to_string_lossy().to_string()
-    }
-}
-
-impl<const LENGTH: usize> Debug for FixedString<LENGTH> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let value = self.as_cstr();
-        Debug::fmt(value, f)
-    }
-}
-
-impl<const LENGTH: usize> Display for FixedString<LENGTH> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let value = self.as_string();
-        Display::fmt(&value, f)
-    }
+        None => false,
+    })
 }
 
 #[derive(Debug)]
@@ -132,7 +81,19 @@ where
             let value = T::read_options(reader, endian, args);
             reader.seek(SeekFrom::Start(before))?;
 
-            Some(value?)
+            match value {
+                Ok(value) => Some(value),
+                Err(err)
+                    if err.is_eof()
+                        && record_truncation(Truncation {
+                            ptr,
+                            target: type_name::<T>(),
+                        }) =>
+                {
+                    None
+                }
+                Err(err) => return Err(err),
+            }
         };
 
         Ok(NullableFilePtr { ptr, value })
@@ -146,3 +107,41 @@ impl<T> Deref for NullableFilePtr<T> {
         &self.value
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::math::{Affine3A, Mat4, Vec3};
+
+    use super::{RawMatrix4x3f, RawMatrix4x4f, RawVec3f};
+
+    #[test]
+    fn test_matrix_rows_to_columns() {
+        let matrix = RawMatrix4x3f {
+            matrix: [
+                [0.0, 0.0, -1.0],
+                [0.0, 1.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [5.0, 6.0, 7.0],
+            ],
+        };
+
+        // Row vector transforms: p * M = x * right + y * up + z * front + position
+        let point = Vec3::new(1.0, 2.0, 3.0);
+        let expected = Vec3::new(8.0, 8.0, 6.0);
+        assert_eq!(Mat4::from(&matrix).transform_point3(point), expected);
+        assert_eq!(Affine3A::from(&matrix).transform_point3(point), expected);
+
+        let mut full = RawMatrix4x4f::default();
+        for (row, value) in full.matrix.iter_mut().zip(matrix.matrix) {
+            *row = [value[0], value[1], value[2], 0.0];
+        }
+        full.matrix[3][3] = 1.0;
+        assert_eq!(Mat4::from(&full), Mat4::from(&matrix));
+    }
+
+    #[test]
+    fn test_vec_round_trip() {
+        let value = Vec3::new(1.0, -2.0, 3.5);
+        assert_eq!(Vec3::from(&RawVec3f::from(value)), value);
+    }
+}
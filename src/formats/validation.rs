@@ -0,0 +1,288 @@
+//! Cases for the screenshot validation harness. Each case renders an asset
+//! from a fixed camera pose after converting it with a named coordinate
+//! space transform, the capture is compared against a stored reference
+//! screenshot. Cases are read from a plain text listing with one case per
+//! line as `name asset transform eye_x eye_y eye_z target_x target_y
+//! target_z [tolerance]`. Assets stored alongside the listing are used
+//! before the game data so cases can run without it
+
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    ecs::system::Resource,
+    math::{Mat4, Vec3},
+};
+use thiserror::Error;
+
+use super::texture::compare::TextureComparison;
+
+/// Directory containing the validation listing and reference screenshots
+pub const VALIDATION_DIR: &str = "assets/validation";
+/// Listing of the validation cases within [VALIDATION_DIR]
+pub const VALIDATION_LISTING: &str = "cases.txt";
+/// Tolerance used when a case doesn't provide one, screenshots differ
+/// slightly between drivers
+pub const DEFAULT_TOLERANCE: u8 = 8;
+
+#[derive(Debug, Error)]
+pub enum ValidationError {
+    #[error("line {0}: expected name asset transform eye target [tolerance]")]
+    MalformedLine(usize),
+    #[error("line {0}: invalid number {1:?}")]
+    InvalidNumber(usize, String),
+    #[error("line {0}: duplicate case {1:?}")]
+    DuplicateCase(usize, String),
+}
+
+/// Single asset rendered and compared by the harness
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationCase {
+    /// Name of the case, the reference screenshot is named after it
+    pub name: String,
+    /// Path of the mesh in [VALIDATION_DIR] or the game data
+    pub asset: String,
+    /// Name of the [SpaceTransform] applied to the asset
+    pub transform: String,
+    /// Position of the camera
+    pub eye: Vec3,
+    /// Position the camera looks at
+    pub target: Vec3,
+    /// Largest channel difference allowed before a block mismatches
+    pub tolerance: u8,
+}
+
+impl ValidationCase {
+    /// Path of the asset when it's stored alongside the listing
+    pub fn local_asset_path(&self) -> Option<PathBuf> {
+        Some(Path::new(VALIDATION_DIR).join(&self.asset)).filter(|path| path.is_file())
+    }
+
+    /// Path of the reference screenshot
+    pub fn reference_path(&self) -> String {
+        format!("{}/{}.png", VALIDATION_DIR, self.name)
+    }
+
+    /// Path the capture is written to with the mismatches highlighted
+    pub fn highlight_path(&self) -> String {
+        format!("{}/{}_diff.png", VALIDATION_DIR, self.name)
+    }
+}
+
+/// Parses a validation listing, blank lines and lines starting
+/// with `#` are ignored
+pub fn parse_cases(value: &str) -> Result<Vec<ValidationCase>, ValidationError> {
+    let mut out: Vec<ValidationCase> = Vec::new();
+
+    for (index, line) in value.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let (&[name, asset, transform], rest) = parts.split_at(parts.len().min(3)) else {
+            return Err(ValidationError::MalformedLine(line_number));
+        };
+
+        let number = |value: &str| {
+            value
+                .parse::<f32>()
+                .map_err(|_| ValidationError::InvalidNumber(line_number, value.to_string()))
+        };
+
+        let (eye, target, tolerance) = match rest {
+            &[ex, ey, ez, tx, ty, tz] => (
+                Vec3::new(number(ex)?, number(ey)?, number(ez)?),
+                Vec3::new(number(tx)?, number(ty)?, number(tz)?),
+                DEFAULT_TOLERANCE,
+            ),
+            &[ex, ey, ez, tx, ty, tz, tolerance] => (
+                Vec3::new(number(ex)?, number(ey)?, number(ez)?),
+                Vec3::new(number(tx)?, number(ty)?, number(tz)?),
+                tolerance.parse().map_err(|_| {
+                    ValidationError::InvalidNumber(line_number, tolerance.to_string())
+                })?,
+            ),
+            _ => return Err(ValidationError::MalformedLine(line_number)),
+        };
+
+        if out.iter().any(|case| case.name == name) {
+            return Err(ValidationError::DuplicateCase(
+                line_number,
+                name.to_string(),
+            ));
+        }
+
+        out.push(ValidationCase {
+            name: name.to_string(),
+            asset: asset.to_string(),
+            transform: transform.to_string(),
+            eye,
+            target,
+            tolerance,
+        });
+    }
+
+    Ok(out)
+}
+
+/// Conversion from the coordinate space of an asset into the world
+pub trait SpaceTransform: Send + Sync {
+    /// Name the transform is referred to by in the listing
+    fn name(&self) -> &str;
+
+    /// Matrix converting asset space into world space
+    fn to_world(&self) -> Mat4;
+}
+
+/// Assets already in the world coordinate space
+struct IdentitySpace;
+
+impl SpaceTransform for IdentitySpace {
+    fn name(&self) -> &str {
+        "identity"
+    }
+
+    fn to_world(&self) -> Mat4 {
+        Mat4::IDENTITY
+    }
+}
+
+/// Left handed engine space with Z pointing into the screen
+struct LeftHandedSpace;
+
+impl SpaceTransform for LeftHandedSpace {
+    fn name(&self) -> &str {
+        "left_handed"
+    }
+
+    fn to_world(&self) -> Mat4 {
+        Mat4::from_scale(Vec3::new(1.0, 1.0, -1.0))
+    }
+}
+
+/// Registry of the coordinate space transforms available to the cases,
+/// transforms registered later take priority over the built-in transforms
+#[derive(Default, Resource)]
+pub struct SpaceTransforms {
+    transforms: Vec<Box<dyn SpaceTransform>>,
+}
+
+impl SpaceTransforms {
+    /// Creates a registry containing the built-in transforms
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register(IdentitySpace);
+        registry.register(LeftHandedSpace);
+        registry
+    }
+
+    pub fn register<T: SpaceTransform + 'static>(&mut self, transform: T) {
+        self.transforms.push(Box::new(transform));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn SpaceTransform> {
+        self.transforms
+            .iter()
+            .rev()
+            .map(|transform| transform.as_ref())
+            .find(|transform| transform.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Outcome of a single validation case
+#[derive(Debug)]
+pub enum ValidationOutcome {
+    /// Capture matched the reference within the tolerance
+    Passed(TextureComparison),
+    /// Capture had blocks outside the tolerance
+    Failed(TextureComparison),
+    /// No reference existed so the capture was stored as the reference
+    Recorded,
+    /// Case couldn't be run or compared
+    Error(String),
+}
+
+impl ValidationOutcome {
+    pub fn from_comparison(comparison: TextureComparison) -> Self {
+        if comparison.mismatched_blocks.is_empty() {
+            ValidationOutcome::Passed(comparison)
+        } else {
+            ValidationOutcome::Failed(comparison)
+        }
+    }
+
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            ValidationOutcome::Failed(_) | ValidationOutcome::Error(_)
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use bevy::math::Vec3;
+
+    use crate::formats::mesh::fixture::{textured_quad, VALIDATION_QUAD};
+
+    use super::{
+        parse_cases, SpaceTransforms, ValidationError, DEFAULT_TOLERANCE, VALIDATION_DIR,
+        VALIDATION_LISTING,
+    };
+
+    #[test]
+    fn test_parse_cases() {
+        let cases = parse_cases(
+            "# validation\nglitch mesh/gcdggltch00.ape left_handed 0 1 -4 0 1 0\nsky mesh/gcsky.ape identity 0 0 0 0 0 1 20\n",
+        )
+        .unwrap();
+
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].eye, Vec3::new(0.0, 1.0, -4.0));
+        assert_eq!(cases[0].tolerance, DEFAULT_TOLERANCE);
+        assert_eq!(cases[1].tolerance, 20);
+        assert_eq!(cases[0].reference_path(), "assets/validation/glitch.png");
+
+        assert!(matches!(
+            parse_cases("a b identity 0 0 0 0 0 x"),
+            Err(ValidationError::InvalidNumber(1, _))
+        ));
+        assert!(matches!(
+            parse_cases("a b c\n"),
+            Err(ValidationError::MalformedLine(1))
+        ));
+        assert!(matches!(
+            parse_cases("a b identity 0 0 0 0 0 0\na c identity 0 0 0 0 0 0"),
+            Err(ValidationError::DuplicateCase(2, _))
+        ));
+    }
+
+    #[test]
+    fn test_listed_cases() {
+        let listing = std::fs::read_to_string(Path::new(VALIDATION_DIR).join(VALIDATION_LISTING));
+        let cases = parse_cases(&listing.unwrap()).unwrap();
+
+        // Quad stored with the cases is the one built by the fixtures
+        let quad = cases.iter().find(|case| case.name == "quad").unwrap();
+        let path = quad
+            .local_asset_path()
+            .expect("quad is stored with the cases");
+        assert_eq!(path, Path::new(VALIDATION_QUAD));
+        assert_eq!(std::fs::read(path).unwrap(), textured_quad());
+    }
+
+    #[test]
+    fn test_space_transforms() {
+        let transforms = SpaceTransforms::with_builtin();
+        let left_handed = transforms.get("LEFT_HANDED").unwrap().to_world();
+        assert_eq!(
+            left_handed.transform_point3(Vec3::new(1.0, 2.0, 3.0)),
+            Vec3::new(1.0, 2.0, -3.0)
+        );
+        assert!(transforms.get("missing").is_none());
+    }
+}
@@ -0,0 +1,236 @@
+//! Master archives packing many game files into a single `.dat` file with a
+//! separate `.toc` index. The index is an entry count followed by a fixed
+//! size entry for each file, a null padded path followed by the offset of
+//! the file within the `.dat` and its size. Archives are written in the byte
+//! order of their platform, the order is detected from the entry count
+//! matching the size of the index
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use parking_lot::Mutex;
+
+use super::{
+    disc::{read_at, RegionReader},
+    normalize_path, FileInfo, FsError, GameFile, GameSource,
+};
+
+/// Extension of the archive index
+pub const ARCHIVE_INDEX_EXTENSION: &str = "toc";
+/// Extension of the archive data
+pub const ARCHIVE_DATA_EXTENSION: &str = "dat";
+
+/// Length of the null padded path of each entry
+const ENTRY_NAME_LENGTH: usize = 64;
+/// Length of each entry, the path followed by the offset and size
+const ENTRY_LENGTH: usize = ENTRY_NAME_LENGTH + 8;
+
+/// Location of a file within the archive data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// Reads the entries of an archive index, returning the normalized path
+/// and location of each file
+pub fn parse_index(index: &[u8]) -> Result<Vec<(String, ArchiveEntry)>, FsError> {
+    let count = index
+        .get(..4)
+        .ok_or(FsError::InvalidArchive("index is missing its entry count"))?;
+    let count: [u8; 4] = count.try_into().expect("Entry count length");
+
+    let expected = |count: u32| 4 + count as usize * ENTRY_LENGTH == index.len();
+    let read_u32: fn([u8; 4]) -> u32 = if expected(u32::from_be_bytes(count)) {
+        u32::from_be_bytes
+    } else if expected(u32::from_le_bytes(count)) {
+        u32::from_le_bytes
+    } else {
+        return Err(FsError::InvalidArchive(
+            "entry count doesn't match the index size",
+        ));
+    };
+
+    index[4..]
+        .chunks_exact(ENTRY_LENGTH)
+        .map(|entry| {
+            let (name, location) = entry.split_at(ENTRY_NAME_LENGTH);
+            let name = name.split(|value| *value == 0).next().unwrap_or_default();
+            let name = std::str::from_utf8(name)
+                .map_err(|_| FsError::InvalidArchive("entry path isn't valid text"))?;
+            if name.is_empty() {
+                return Err(FsError::InvalidArchive("entry has an empty path"));
+            }
+
+            let offset = read_u32(location[..4].try_into().expect("Offset length"));
+            let size = read_u32(location[4..].try_into().expect("Size length"));
+            Ok((
+                normalize_path(name),
+                ArchiveEntry {
+                    offset: offset as u64,
+                    size: size as u64,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Master archive mounted as a read-only source
+pub struct ArchiveSource {
+    name: String,
+    file: Mutex<File>,
+    /// Lookup of normalized file paths to their location in the data
+    entries: HashMap<String, ArchiveEntry>,
+}
+
+impl ArchiveSource {
+    /// Opens the archive from either its index or data path, the other
+    /// file is expected next to it with the same name
+    pub fn open(path: &Path) -> Result<ArchiveSource, FsError> {
+        let index_path = path.with_extension(ARCHIVE_INDEX_EXTENSION);
+        let data_path = path.with_extension(ARCHIVE_DATA_EXTENSION);
+
+        let index = std::fs::read(&index_path)?;
+        let file = File::open(&data_path)?;
+        let data_size = file.metadata()?.len();
+
+        let entries = parse_index(&index)?;
+        if entries
+            .iter()
+            .any(|(_, entry)| entry.offset + entry.size > data_size)
+        {
+            return Err(FsError::InvalidArchive(
+                "entry runs past the end of the data",
+            ));
+        }
+
+        Ok(ArchiveSource {
+            name: data_path.display().to_string(),
+            file: Mutex::new(file),
+            entries: entries.into_iter().collect(),
+        })
+    }
+
+    /// Finds the archive indexes at the top of the provided directory
+    pub fn find(dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+
+        let mut out: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|value| value.eq_ignore_ascii_case(ARCHIVE_INDEX_EXTENSION))
+            })
+            .collect();
+        out.sort();
+        out
+    }
+
+    pub fn entry(&self, path: &str) -> Option<ArchiveEntry> {
+        self.entries.get(&normalize_path(path)).copied()
+    }
+}
+
+impl GameSource for ArchiveSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.entry(path).is_some()
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, FsError> {
+        let entry = self
+            .entry(path)
+            .ok_or_else(|| FsError::NotFound(path.to_string()))?;
+
+        let mut file = self.file.lock();
+        read_at(&mut *file, entry.offset, entry.size as usize)
+    }
+
+    fn info(&self, path: &str) -> Option<FileInfo> {
+        self.entry(path).map(|entry| FileInfo {
+            offset: Some(entry.offset),
+            size: entry.size,
+        })
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn GameFile + '_>, FsError> {
+        let entry = self
+            .entry(path)
+            .ok_or_else(|| FsError::NotFound(path.to_string()))?;
+
+        Ok(Box::new(RegionReader::new(
+            &self.file,
+            entry.offset,
+            entry.size,
+        )))
+    }
+
+    fn files(&self) -> Vec<String> {
+        let mut out: Vec<String> = self.entries.keys().cloned().collect();
+        out.sort();
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_index, ArchiveEntry, ENTRY_NAME_LENGTH};
+
+    fn index(entries: &[(&str, u32, u32)], to_bytes: fn(u32) -> [u8; 4]) -> Vec<u8> {
+        let mut out = to_bytes(entries.len() as u32).to_vec();
+        for (name, offset, size) in entries {
+            let mut padded = [0u8; ENTRY_NAME_LENGTH];
+            padded[..name.len()].copy_from_slice(name.as_bytes());
+            out.extend_from_slice(&padded);
+            out.extend_from_slice(&to_bytes(*offset));
+            out.extend_from_slice(&to_bytes(*size));
+        }
+        out
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let entries = [("ape\\GCDGGLTCH00.ape", 0, 16), ("movies/intro.bik", 16, 4)];
+
+        for to_bytes in [u32::to_be_bytes, u32::to_le_bytes] {
+            let parsed = parse_index(&index(&entries, to_bytes)).unwrap();
+            assert_eq!(
+                parsed,
+                vec![
+                    (
+                        "ape/gcdggltch00.ape".to_string(),
+                        ArchiveEntry {
+                            offset: 0,
+                            size: 16
+                        }
+                    ),
+                    (
+                        "movies/intro.bik".to_string(),
+                        ArchiveEntry {
+                            offset: 16,
+                            size: 4
+                        }
+                    ),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_index_truncated() {
+        let mut value = index(&[("ape/a.ape", 0, 1)], u32::to_be_bytes);
+        value.pop();
+        assert!(parse_index(&value).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::{Path, PathBuf};
+
+use super::{FileInfo, FsError, GameFile, GameSource};
+
+/// Source reading files from an extracted directory
+pub struct DirectorySource {
+    root: PathBuf,
+    name: String,
+}
+
+impl DirectorySource {
+    pub fn new(root: PathBuf) -> Self {
+        let name = root.display().to_string();
+        Self { root, name }
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches(['/', '\\']))
+    }
+}
+
+impl GameSource for DirectorySource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.resolve(path).is_file()
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, FsError> {
+        Ok(std::fs::read(self.resolve(path))?)
+    }
+
+    fn info(&self, path: &str) -> Option<FileInfo> {
+        let metadata = std::fs::metadata(self.resolve(path)).ok()?;
+        metadata.is_file().then_some(FileInfo {
+            offset: None,
+            size: metadata.len(),
+        })
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn GameFile + '_>, FsError> {
+        Ok(Box::new(std::fs::File::open(self.resolve(path))?))
+    }
+
+    fn watch_root(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+
+    fn files(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut pending = vec![self.root.clone()];
+
+        while let Some(dir) = pending.pop() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else if let Ok(relative) = path.strip_prefix(&self.root) {
+                    out.push(relative.to_string_lossy().replace('\\', "/"));
+                }
+            }
+        }
+
+        out.sort();
+        out
+    }
+}
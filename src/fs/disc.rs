@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use parking_lot::Mutex;
+
+use super::{gcm, normalize_path, xiso, FileInfo, FsError, GameFile, GameSource};
+
+/// Format of a mounted disc image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscKind {
+    /// GameCube disc image (.iso / .gcm)
+    GameCube,
+    /// Xbox disc image (XDVDFS)
+    Xbox,
+}
+
+/// Location of a file within a disc image
+#[derive(Debug, Clone, Copy)]
+pub struct DiscEntry {
+    /// Absolute offset of the file within the image
+    pub offset: u64,
+    /// Size of the file in bytes
+    pub size: u64,
+}
+
+/// Disc image mounted as a read-only source
+pub struct DiscImage {
+    name: String,
+    kind: DiscKind,
+    file: Mutex<File>,
+    /// Lookup of normalized file paths to their location in the image
+    entries: HashMap<String, DiscEntry>,
+}
+
+impl DiscImage {
+    /// Opens the disc image at the provided path, detecting the image format
+    pub fn open(path: &Path) -> Result<DiscImage, FsError> {
+        let mut file = File::open(path)?;
+
+        let (kind, entries) = if gcm::is_gcm(&mut file)? {
+            (DiscKind::GameCube, gcm::read_entries(&mut file)?)
+        } else if let Some(partition) = xiso::find_partition(&mut file)? {
+            (DiscKind::Xbox, xiso::read_entries(&mut file, partition)?)
+        } else {
+            return Err(FsError::UnknownImage);
+        };
+
+        let entries = entries
+            .into_iter()
+            .map(|(path, entry)| (normalize_path(&path), entry))
+            .collect();
+
+        Ok(DiscImage {
+            name: path.display().to_string(),
+            kind,
+            file: Mutex::new(file),
+            entries,
+        })
+    }
+
+    pub fn kind(&self) -> DiscKind {
+        self.kind
+    }
+
+    pub fn entry(&self, path: &str) -> Option<DiscEntry> {
+        self.entries.get(&normalize_path(path)).copied()
+    }
+
+    /// Iterates the normalized paths and locations of all the files
+    pub fn entries(&self) -> impl Iterator<Item = (&str, DiscEntry)> {
+        self.entries
+            .iter()
+            .map(|(path, entry)| (path.as_str(), *entry))
+    }
+}
+
+/// Random access reader over a single file within a disc image or archive,
+/// the underlying file is only locked for the duration of each read
+pub struct RegionReader<'a> {
+    file: &'a Mutex<File>,
+    offset: u64,
+    size: u64,
+    position: u64,
+}
+
+impl<'a> RegionReader<'a> {
+    pub fn new(file: &'a Mutex<File>, offset: u64, size: u64) -> Self {
+        Self {
+            file,
+            offset,
+            size,
+            position: 0,
+        }
+    }
+}
+
+impl Read for RegionReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.size.saturating_sub(self.position);
+        let length = (buf.len() as u64).min(remaining) as usize;
+        if length == 0 {
+            return Ok(0);
+        }
+
+        let mut file = self.file.lock();
+        file.seek(SeekFrom::Start(self.offset + self.position))?;
+        let count = file.read(&mut buf[..length])?;
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for RegionReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(value) => Some(value),
+            SeekFrom::End(value) => self.size.checked_add_signed(value),
+            SeekFrom::Current(value) => self.position.checked_add_signed(value),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek position"))?;
+
+        self.position = position;
+        Ok(position)
+    }
+}
+
+impl GameSource for DiscImage {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.entry(path).is_some()
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, FsError> {
+        let entry = self
+            .entry(path)
+            .ok_or_else(|| FsError::NotFound(path.to_string()))?;
+
+        let mut file = self.file.lock();
+        read_at(&mut *file, entry.offset, entry.size as usize)
+    }
+
+    fn info(&self, path: &str) -> Option<FileInfo> {
+        self.entry(path).map(|entry| FileInfo {
+            offset: Some(entry.offset),
+            size: entry.size,
+        })
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn GameFile + '_>, FsError> {
+        let entry = self
+            .entry(path)
+            .ok_or_else(|| FsError::NotFound(path.to_string()))?;
+
+        Ok(Box::new(RegionReader::new(
+            &self.file,
+            entry.offset,
+            entry.size,
+        )))
+    }
+
+    fn files(&self) -> Vec<String> {
+        let mut out: Vec<String> = self.entries.keys().cloned().collect();
+        out.sort();
+        out
+    }
+}
+
+/// Reads `length` bytes at the provided offset
+pub(super) fn read_at<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    length: usize,
+) -> Result<Vec<u8>, FsError> {
+    let mut buffer = vec![0u8; length];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
@@ -0,0 +1,147 @@
+//! GameCube disc image filesystem (FST)
+
+use std::io::{Read, Seek};
+
+use super::{
+    disc::{read_at, DiscEntry},
+    FsError,
+};
+
+/// Offset of the GameCube magic word in the disc header
+const MAGIC_OFFSET: u64 = 0x1C;
+const MAGIC: u32 = 0xC2339F3D;
+/// Offset of the FST offset and size in the disc header
+const FST_INFO_OFFSET: u64 = 0x424;
+/// Size of a single FST entry
+const FST_ENTRY_SIZE: usize = 12;
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+pub fn is_gcm<R: Read + Seek>(reader: &mut R) -> Result<bool, FsError> {
+    let Ok(magic) = read_at(reader, MAGIC_OFFSET, 4) else {
+        return Ok(false);
+    };
+    Ok(read_u32(&magic, 0) == MAGIC)
+}
+
+/// Reads all the file entries from the FST
+pub fn read_entries<R: Read + Seek>(reader: &mut R) -> Result<Vec<(String, DiscEntry)>, FsError> {
+    let info = read_at(reader, FST_INFO_OFFSET, 8)?;
+    let fst_offset = read_u32(&info, 0) as u64;
+    let fst_size = read_u32(&info, 4) as usize;
+
+    let fst = read_at(reader, fst_offset, fst_size)?;
+    if fst.len() < FST_ENTRY_SIZE {
+        return Err(FsError::InvalidImage("fst too small"));
+    }
+
+    // Root entry holds the total entry count, names follow the entries
+    let count = read_u32(&fst, 8) as usize;
+    let strings_offset = count
+        .checked_mul(FST_ENTRY_SIZE)
+        .filter(|offset| *offset <= fst.len())
+        .ok_or(FsError::InvalidImage("fst entry count out of bounds"))?;
+    let strings = &fst[strings_offset..];
+
+    let mut out = Vec::new();
+    // Directories being walked as (end index, path)
+    let mut stack: Vec<(usize, String)> = Vec::new();
+
+    for index in 1..count {
+        while stack.last().is_some_and(|(end, _)| index >= *end) {
+            stack.pop();
+        }
+
+        let offset = index * FST_ENTRY_SIZE;
+        let entry = &fst[offset..offset + FST_ENTRY_SIZE];
+        let is_dir = entry[0] != 0;
+        let name_offset = (read_u32(entry, 0) & 0x00FF_FFFF) as usize;
+        let name = read_name(strings, name_offset)?;
+
+        let path = match stack.last() {
+            Some((_, parent)) => format!("{parent}/{name}"),
+            None => name,
+        };
+
+        if is_dir {
+            // Directories store their parent and the index after their last child
+            stack.push((read_u32(entry, 8) as usize, path));
+        } else {
+            out.push((
+                path,
+                DiscEntry {
+                    offset: read_u32(entry, 4) as u64,
+                    size: read_u32(entry, 8) as u64,
+                },
+            ));
+        }
+    }
+
+    Ok(out)
+}
+
+fn read_name(strings: &[u8], offset: usize) -> Result<String, FsError> {
+    let bytes = strings
+        .get(offset..)
+        .ok_or(FsError::InvalidImage("fst name out of bounds"))?;
+    let end = bytes
+        .iter()
+        .position(|value| *value == 0)
+        .unwrap_or(bytes.len());
+    Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::{is_gcm, read_entries, FST_INFO_OFFSET, MAGIC, MAGIC_OFFSET};
+
+    fn entry(is_dir: bool, name_offset: u32, a: u32, b: u32) -> Vec<u8> {
+        let mut out = (((is_dir as u32) << 24) | name_offset)
+            .to_be_bytes()
+            .to_vec();
+        out.extend_from_slice(&a.to_be_bytes());
+        out.extend_from_slice(&b.to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn test_read_entries() {
+        let fst_offset = 0x440u32;
+        let mut fst = Vec::new();
+        fst.extend(entry(true, 0, 0, 4));
+        fst.extend(entry(true, 0, 0, 3));
+        fst.extend(entry(false, 4, 0x1000, 16));
+        fst.extend(entry(false, 10, 0x2000, 32));
+        fst.extend_from_slice(b"ape\0a.ape\0b.txt\0");
+
+        let mut image = vec![0u8; fst_offset as usize];
+        image[MAGIC_OFFSET as usize..MAGIC_OFFSET as usize + 4]
+            .copy_from_slice(&MAGIC.to_be_bytes());
+        let info = FST_INFO_OFFSET as usize;
+        image[info..info + 4].copy_from_slice(&fst_offset.to_be_bytes());
+        image[info + 4..info + 8].copy_from_slice(&(fst.len() as u32).to_be_bytes());
+        image.extend(fst);
+
+        let mut reader = Cursor::new(image);
+        assert!(is_gcm(&mut reader).unwrap());
+
+        let entries = read_entries(&mut reader).unwrap();
+        let paths: Vec<(&str, u64, u64)> = entries
+            .iter()
+            .map(|(path, entry)| (path.as_str(), entry.offset, entry.size))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![("ape/a.ape", 0x1000, 16), ("b.txt", 0x2000, 32)]
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! Read-only filesystem the game data is loaded through, allowing the data
+//! to come from an extracted directory, a master archive or directly from
+//! a disc image
+
+use std::{
+    io::{self, Read, Seek, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use bevy::{
+    app::App,
+    asset::{
+        io::{AssetSource, AssetSourceId},
+        AssetApp, AssetPath,
+    },
+    ecs::system::Resource,
+};
+use thiserror::Error;
+
+use self::{
+    archive::{ArchiveSource, ARCHIVE_DATA_EXTENSION, ARCHIVE_INDEX_EXTENSION},
+    directory::DirectorySource,
+    disc::DiscImage,
+    movies::MovieIndex,
+    vfs::{VfsReader, VfsWatcher},
+};
+
+pub mod archive;
+pub mod directory;
+pub mod disc;
+mod gcm;
+pub mod movies;
+pub mod version;
+pub mod vfs;
+mod xiso;
+
+/// Default directory containing the extracted game data
+pub const DEFAULT_DATA_DIR: &str = "data";
+/// Name of the asset source reading from the extracted game data
+pub const DATA_ASSET_SOURCE: &str = "data";
+/// Name of the asset source reading through every mounted source
+pub const VFS_ASSET_SOURCE: &str = "vfs";
+/// Time to wait for further changes to a watched file before reloading it
+const DATA_WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+/// Directory within the temporary directory files of packed sources are
+/// extracted to when they're needed on disk
+const EXTRACT_DIR: &str = "open_ma";
+
+#[derive(Debug, Error)]
+pub enum FsError {
+    #[error("file not found: {0}")]
+    NotFound(String),
+    #[error("unrecognized disc image")]
+    UnknownImage,
+    #[error("invalid disc image: {0}")]
+    InvalidImage(&'static str),
+    #[error("invalid archive: {0}")]
+    InvalidArchive(&'static str),
+    #[error("hash mismatch for file: {0}")]
+    HashMismatch(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Readable and seekable handle to a game file
+pub trait GameFile: Read + Seek {}
+
+impl<T: Read + Seek> GameFile for T {}
+
+/// Location and size of a file within its source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileInfo {
+    /// Offset of the file within the underlying archive, files that
+    /// aren't stored within an archive have no offset
+    pub offset: Option<u64>,
+    /// Size of the file in bytes
+    pub size: u64,
+}
+
+/// Source of game files
+pub trait GameSource: Send + Sync {
+    /// Name of the source for display
+    fn name(&self) -> &str;
+
+    /// Checks if the source contains the provided file
+    fn contains(&self, path: &str) -> bool;
+
+    /// Reads the entire contents of the provided file
+    fn read(&self, path: &str) -> Result<Vec<u8>, FsError>;
+
+    /// Provides the location and size of the provided file
+    fn info(&self, path: &str) -> Option<FileInfo>;
+
+    /// Opens the provided file for random access without reading
+    /// the whole file up front
+    fn open(&self, path: &str) -> Result<Box<dyn GameFile + '_>, FsError>;
+
+    /// Lists the paths of all the files within the source
+    fn files(&self) -> Vec<String>;
+
+    /// Directory the files of the source are read from when they can be
+    /// watched for changes, none for sources packed into a single file
+    fn watch_root(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// Game filesystem, files are looked up through each of the mounted
+/// sources in the order they were mounted. Clones share the same sources
+#[derive(Default, Clone, Resource)]
+pub struct GameFs {
+    sources: Vec<Arc<dyn GameSource>>,
+}
+
+impl GameFs {
+    /// Mounts the provided source
+    pub fn mount<S: GameSource + 'static>(&mut self, source: S) {
+        self.sources.push(Arc::new(source));
+    }
+
+    /// Mounts a path, archive indexes and data are mounted as archives,
+    /// other files are mounted as disc images and anything else is mounted
+    /// as a directory
+    pub fn mount_path(&mut self, path: &Path) -> Result<(), FsError> {
+        let is_archive = path.extension().is_some_and(|value| {
+            value.eq_ignore_ascii_case(ARCHIVE_INDEX_EXTENSION)
+                || value.eq_ignore_ascii_case(ARCHIVE_DATA_EXTENSION)
+        });
+
+        if path.is_dir() {
+            self.mount(DirectorySource::new(path.to_path_buf()));
+        } else if is_archive {
+            self.mount(ArchiveSource::open(path)?);
+        } else {
+            self.mount(DiscImage::open(path)?);
+        }
+        Ok(())
+    }
+
+    /// Creates the filesystem for the viewer, mounting any image or
+    /// directory provided on the command line ahead of the data directory.
+    /// Archives in the data directory are mounted after it so loose files
+    /// replace the packed ones
+    pub fn from_args() -> Result<GameFs, FsError> {
+        let mut fs = GameFs::default();
+        if let Some(path) = std::env::args().nth(1) {
+            fs.mount_path(&PathBuf::from(path))?;
+        }
+        fs.mount(DirectorySource::new(PathBuf::from(DEFAULT_DATA_DIR)));
+        for path in ArchiveSource::find(Path::new(DEFAULT_DATA_DIR)) {
+            fs.mount(ArchiveSource::open(&path)?);
+        }
+        Ok(fs)
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = &dyn GameSource> {
+        self.sources.iter().map(|source| source.as_ref())
+    }
+
+    /// Directories of the mounted sources that can be watched for changes
+    pub fn watch_roots(&self) -> Vec<PathBuf> {
+        self.sources
+            .iter()
+            .filter_map(|source| source.watch_root())
+            .map(Path::to_path_buf)
+            .collect()
+    }
+
+    /// Lists the files across all the sources, files shadowed by an
+    /// earlier source are only listed once
+    pub fn files(&self) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .sources
+            .iter()
+            .flat_map(|source| source.files())
+            .collect();
+        out.sort_by_key(|path| normalize_path(path));
+        out.dedup_by_key(|path| normalize_path(path));
+        out
+    }
+
+    /// Pairs the movies in the game data with their audio and captions
+    pub fn movies(&self) -> MovieIndex {
+        let files = self.files();
+        MovieIndex::build(files.iter().map(String::as_str))
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.sources.iter().any(|source| source.contains(path))
+    }
+
+    fn source(&self, path: &str) -> Result<&dyn GameSource, FsError> {
+        self.sources
+            .iter()
+            .find(|source| source.contains(path))
+            .map(|source| source.as_ref())
+            .ok_or_else(|| FsError::NotFound(path.to_string()))
+    }
+
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, FsError> {
+        self.source(path)?.read(path)
+    }
+
+    pub fn info(&self, path: &str) -> Option<FileInfo> {
+        self.sources.iter().find_map(|source| source.info(path))
+    }
+
+    pub fn open(&self, path: &str) -> Result<Box<dyn GameFile + '_>, FsError> {
+        self.source(path)?.open(path)
+    }
+
+    /// Extracts a single file into the provided writer, only the
+    /// region of the archive holding the file is read
+    pub fn extract<W: Write>(&self, path: &str, writer: &mut W) -> Result<u64, FsError> {
+        let mut file = self.open(path)?;
+        Ok(io::copy(&mut file, writer)?)
+    }
+
+    /// Hashes the contents of the provided file
+    pub fn hash(&self, path: &str) -> Result<blake3::Hash, FsError> {
+        let mut hasher = blake3::Hasher::new();
+        self.extract(path, &mut hasher)?;
+        Ok(hasher.finalize())
+    }
+
+    /// Extracts a file verifying its contents against the expected hash
+    /// (hex encoded), nothing is written when the hash doesn't match
+    pub fn extract_verified<W: Write>(
+        &self,
+        path: &str,
+        expected: &str,
+        writer: &mut W,
+    ) -> Result<u64, FsError> {
+        let expected = blake3::Hash::from_hex(expected)
+            .map_err(|_| FsError::HashMismatch(path.to_string()))?;
+        let bytes = self.read(path)?;
+        if blake3::hash(&bytes) != expected {
+            return Err(FsError::HashMismatch(path.to_string()));
+        }
+        writer.write_all(&bytes)?;
+        Ok(bytes.len() as u64)
+    }
+
+    /// Path of a file on disk for decoders that can only read from a path,
+    /// files of packed sources are extracted to the temporary directory
+    /// the first time they're needed
+    pub fn local_path(&self, path: &str) -> Result<PathBuf, FsError> {
+        let source = self.source(path)?;
+        if let Some(root) = source.watch_root() {
+            return Ok(root.join(path.trim_start_matches(['/', '\\'])));
+        }
+
+        let target = std::env::temp_dir()
+            .join(EXTRACT_DIR)
+            .join(normalize_path(path));
+        if target.is_file() {
+            return Ok(target);
+        }
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Extracted to a separate file so an interrupted extraction isn't used
+        let partial = target.with_extension("part");
+        self.extract(path, &mut std::fs::File::create(&partial)?)?;
+        std::fs::rename(&partial, &target)?;
+        Ok(target)
+    }
+
+    pub fn read_to_string(&self, path: &str) -> Result<String, FsError> {
+        let bytes = self.read(path)?;
+        String::from_utf8(bytes)
+            .map_err(|err| FsError::Io(io::Error::new(io::ErrorKind::InvalidData, err)))
+    }
+}
+
+/// Registers the extracted game data as an asset source so files in it can
+/// be loaded and watched for changes by the asset server, must be called
+/// before the asset plugin is added
+pub fn register_data_source(app: &mut App) {
+    app.register_asset_source(
+        DATA_ASSET_SOURCE,
+        AssetSource::build()
+            .with_reader(AssetSource::get_default_reader(
+                DEFAULT_DATA_DIR.to_string(),
+            ))
+            .with_watcher(AssetSource::get_default_watcher(
+                DEFAULT_DATA_DIR.to_string(),
+                DATA_WATCH_DEBOUNCE,
+            )),
+    );
+}
+
+/// Registers the game filesystem as an asset source so files from any of
+/// the mounted sources can be loaded by the asset server, must be called
+/// before the asset plugin is added. Files of mounted directories are
+/// watched so assets re-exported into them are reloaded
+pub fn register_vfs_source(app: &mut App, fs: &GameFs) {
+    let roots = fs.watch_roots();
+    let fs = fs.clone();
+    app.register_asset_source(
+        VFS_ASSET_SOURCE,
+        AssetSource::build()
+            .with_reader(move || Box::new(VfsReader::new(fs.clone())))
+            .with_watcher(move |sender| VfsWatcher::new(&roots, sender, DATA_WATCH_DEBOUNCE)),
+    );
+}
+
+/// Path of a file in the extracted game data for loading through the
+/// asset server
+pub fn asset_path(path: &str) -> String {
+    format!("{}://{}", DATA_ASSET_SOURCE, path)
+}
+
+/// Path of a file in the game filesystem for loading through the asset
+/// server
+pub fn vfs_path(path: &str) -> String {
+    format!("{}://{}", VFS_ASSET_SOURCE, path)
+}
+
+/// Path of a file loaded through the same asset source as the asset being
+/// loaded, so dependencies of assets loaded through the filesystem resolve
+/// through it as well
+pub fn source_asset_path(source: &AssetSourceId, path: &str) -> AssetPath<'static> {
+    AssetPath::from(path.to_string()).with_source(source.clone_owned())
+}
+
+/// Normalizes a path for lookup within a disc image, disc filesystems
+/// are case insensitive and use forward slashes
+pub fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/")
+        .trim_start_matches('/')
+        .to_ascii_lowercase()
+}
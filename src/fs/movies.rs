@@ -0,0 +1,157 @@
+//! Pairing of movies with their audio and caption files, movies are stored
+//! without audio and the audio is played from a sibling file of the same name
+
+use std::path::Path;
+
+use bevy::log::warn;
+
+/// Extension of movie files
+const MOVIE_EXTENSION: &str = "bik";
+/// Extension of movie audio files
+const AUDIO_EXTENSION: &str = "wav";
+/// Extensions of movie caption files
+const SUBTITLE_EXTENSIONS: [&str; 2] = ["srt", "txt"];
+/// Platform prefixes that may be present on only one of the paired files
+const PLATFORM_PREFIXES: [&str; 3] = ["gc_", "xb_", "pc_"];
+
+/// Movie along with its paired files, paths are relative to the game data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoviePair {
+    pub movie: String,
+    pub audio: Option<String>,
+    pub subtitles: Option<String>,
+}
+
+/// Index of all the movies within the game data
+#[derive(Debug, Default)]
+pub struct MovieIndex {
+    pub movies: Vec<MoviePair>,
+    /// Audio and caption files that didn't pair with any movie
+    pub unpaired: Vec<String>,
+}
+
+/// Key files are paired by, the lowercase directory and file stem
+/// without any platform prefix
+fn pair_key(path: &str) -> Option<(String, String)> {
+    let path = Path::new(path);
+    let parent = path
+        .parent()
+        .map(|value| value.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let stem = path.file_stem()?.to_string_lossy().to_ascii_lowercase();
+    let stem = PLATFORM_PREFIXES
+        .iter()
+        .find_map(|prefix| stem.strip_prefix(prefix))
+        .map(str::to_string)
+        .unwrap_or(stem);
+    Some((parent, stem))
+}
+
+fn extension(path: &str) -> Option<String> {
+    Path::new(path)
+        .extension()
+        .map(|value| value.to_string_lossy().to_ascii_lowercase())
+}
+
+impl MovieIndex {
+    /// Pairs the movies within the provided file list
+    pub fn build<'a>(files: impl IntoIterator<Item = &'a str>) -> MovieIndex {
+        let mut movies = Vec::new();
+        let mut extras = Vec::new();
+
+        for file in files {
+            match extension(file).as_deref() {
+                Some(MOVIE_EXTENSION) => movies.push(file),
+                Some(ext) if ext == AUDIO_EXTENSION || SUBTITLE_EXTENSIONS.contains(&ext) => {
+                    extras.push(file)
+                }
+                _ => {}
+            }
+        }
+
+        let mut index = MovieIndex::default();
+        let mut paired = vec![false; extras.len()];
+
+        for movie in movies {
+            let key = pair_key(movie);
+            let mut pair = MoviePair {
+                movie: movie.to_string(),
+                audio: None,
+                subtitles: None,
+            };
+
+            for (extra, used) in extras.iter().zip(paired.iter_mut()) {
+                if *used || pair_key(extra) != key {
+                    continue;
+                }
+
+                let slot = if extension(extra).as_deref() == Some(AUDIO_EXTENSION) {
+                    &mut pair.audio
+                } else {
+                    &mut pair.subtitles
+                };
+
+                if slot.is_none() {
+                    *slot = Some(extra.to_string());
+                    *used = true;
+                }
+            }
+
+            if pair.audio.is_none() {
+                warn!("Movie {} has no paired audio", pair.movie);
+            }
+            index.movies.push(pair);
+        }
+
+        for (extra, used) in extras.iter().zip(paired.iter()) {
+            if !used && is_movie_dir(extra) {
+                warn!("File {} did not pair with any movie", extra);
+                index.unpaired.push(extra.to_string());
+            }
+        }
+
+        index
+    }
+
+    /// Finds the pairing for the provided movie path
+    pub fn get(&self, movie: &str) -> Option<&MoviePair> {
+        self.movies
+            .iter()
+            .find(|pair| pair.movie.eq_ignore_ascii_case(movie))
+    }
+}
+
+/// Only audio and captions next to movies are expected to pair,
+/// other sounds and text files are left alone
+fn is_movie_dir(path: &str) -> bool {
+    Path::new(path)
+        .parent()
+        .and_then(|value| value.file_name())
+        .is_some_and(|value| value.eq_ignore_ascii_case("movies"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::MovieIndex;
+
+    #[test]
+    fn test_pair_movies() {
+        let index = MovieIndex::build([
+            "Movies/xb_intro$.bik",
+            "Movies/xb_intro$.wav",
+            "Movies/intro$.srt",
+            "Movies/credits.bik",
+            "Movies/stray.wav",
+            "sounds/explosion.wav",
+        ]);
+
+        let intro = index.get("movies/XB_INTRO$.bik").unwrap();
+        assert_eq!(intro.audio.as_deref(), Some("Movies/xb_intro$.wav"));
+        assert_eq!(intro.subtitles.as_deref(), Some("Movies/intro$.srt"));
+
+        let credits = index.get("Movies/credits.bik").unwrap();
+        assert_eq!(credits.audio, None);
+
+        assert_eq!(index.unpaired, vec!["Movies/stray.wav".to_string()]);
+    }
+}
@@ -0,0 +1,315 @@
+//! Identification of the release of the game the data comes from. A few key
+//! files are hashed into a fingerprint which is looked up in the listing of
+//! known releases, the listing has one release per line as
+//! `fingerprint platform region name`. Data with an unknown fingerprint has
+//! its platform guessed from the system files on the disc
+//!
+//! The detected version is stored in the [Config] along with the
+//! fingerprint, a stored version with a matching fingerprint is used as is
+//! so a wrong guess can be corrected by editing the config
+
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use bevy::{ecs::system::Resource, log::warn};
+use binrw::Endian;
+use openglitch_formats::platform::Platform;
+use thiserror::Error;
+
+use crate::config::{Config, CONFIG_PATH};
+
+use super::GameFs;
+
+/// Listing of the known releases
+pub const VERSIONS_PATH: &str = "assets/tables/versions.txt";
+
+/// Files hashed into the fingerprint, the disc banner and executables
+/// differ between each release and region
+const KEY_FILES: [&str; 3] = ["opening.bnr", "default.xbe", "main.dol"];
+
+#[derive(Debug, Error)]
+pub enum VersionError {
+    #[error("line {0}: expected fingerprint platform region name")]
+    MalformedLine(usize),
+    #[error("line {0}: unknown platform {1:?}")]
+    UnknownPlatform(usize, String),
+    #[error("line {0}: unknown region {1:?}")]
+    UnknownRegion(usize, String),
+}
+
+/// Parses the platform of a listing or config, None for data of an
+/// unknown platform
+fn parse_platform(value: &str) -> Result<Option<Platform>, ()> {
+    match value {
+        "unknown" => Ok(None),
+        value => Platform::from_name(value).map(Some).ok_or(()),
+    }
+}
+
+fn platform_name(platform: Option<Platform>) -> &'static str {
+    platform.map_or("unknown", |platform| platform.name())
+}
+
+/// Region the game data was released in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Region {
+    Ntsc,
+    Pal,
+    Japan,
+    #[default]
+    Unknown,
+}
+
+impl FromStr for Region {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ntsc" => Ok(Region::Ntsc),
+            "pal" => Ok(Region::Pal),
+            "japan" => Ok(Region::Japan),
+            "unknown" => Ok(Region::Unknown),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Region::Ntsc => "ntsc",
+            Region::Pal => "pal",
+            Region::Japan => "japan",
+            Region::Unknown => "unknown",
+        })
+    }
+}
+
+/// Release listed in the known versions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnownVersion {
+    /// Hex encoded fingerprint of the release
+    pub fingerprint: String,
+    /// Platform of the release, None when unknown
+    pub platform: Option<Platform>,
+    pub region: Region,
+    pub name: String,
+}
+
+/// Parses a listing of known releases, blank lines and lines starting
+/// with `#` are ignored
+pub fn parse_versions(value: &str) -> Result<Vec<KnownVersion>, VersionError> {
+    let mut out = Vec::new();
+
+    for (index, line) in value.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.splitn(4, char::is_whitespace);
+        let (Some(fingerprint), Some(platform), Some(region), Some(name)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(VersionError::MalformedLine(line_number));
+        };
+
+        out.push(KnownVersion {
+            fingerprint: fingerprint.to_ascii_lowercase(),
+            platform: parse_platform(platform)
+                .map_err(|_| VersionError::UnknownPlatform(line_number, platform.to_string()))?,
+            region: region
+                .parse()
+                .map_err(|_| VersionError::UnknownRegion(line_number, region.to_string()))?,
+            name: name.trim().to_string(),
+        });
+    }
+
+    Ok(out)
+}
+
+/// Differences in how each release has to be parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseQuirks {
+    /// Meshes use the big endian GameCube layout, the other platforms use
+    /// the DirectX layout which only the repacker reads
+    pub big_endian_meshes: bool,
+}
+
+impl Default for ParseQuirks {
+    fn default() -> Self {
+        Self {
+            big_endian_meshes: true,
+        }
+    }
+}
+
+/// Release of the game the mounted data comes from
+#[derive(Debug, Default, Clone, PartialEq, Eq, Resource)]
+pub struct GameVersion {
+    /// Hex encoded fingerprint of the key files, none when
+    /// the data has none of the key files
+    pub fingerprint: Option<String>,
+    /// Platform of the data, None when it couldn't be identified
+    pub platform: Option<Platform>,
+    pub region: Region,
+    /// Name of the known release the data matched
+    pub release: Option<String>,
+}
+
+impl GameVersion {
+    /// Identifies the version of the provided game data
+    pub fn identify(fs: &GameFs, known: &[KnownVersion]) -> GameVersion {
+        let fingerprint = fingerprint(fs);
+        if let Some(version) = fingerprint.as_deref().and_then(|fingerprint| {
+            known
+                .iter()
+                .find(|version| version.fingerprint == fingerprint)
+        }) {
+            return GameVersion {
+                fingerprint,
+                platform: version.platform,
+                region: version.region,
+                release: Some(version.name.clone()),
+            };
+        }
+
+        GameVersion {
+            fingerprint,
+            platform: guess_platform(fs),
+            region: Region::Unknown,
+            release: None,
+        }
+    }
+
+    /// Identifies the version of the game data, using the version stored in
+    /// the config when the fingerprint still matches and otherwise storing
+    /// the newly identified version
+    pub fn load_or_identify(fs: &GameFs) -> GameVersion {
+        let config_path = Path::new(CONFIG_PATH);
+        let mut config = Config::load(config_path).unwrap_or_else(|err| {
+            warn!("Failed to load config: {}", err);
+            Config::default()
+        });
+
+        let fingerprint = fingerprint(fs);
+        if fingerprint.is_some() && config.data_fingerprint == fingerprint {
+            if let Some(version) = GameVersion::from_config(&config) {
+                return version;
+            }
+        }
+
+        let known = match std::fs::read_to_string(VERSIONS_PATH) {
+            Ok(value) => parse_versions(&value).unwrap_or_else(|err| {
+                warn!("Failed to parse known versions: {}", err);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+
+        let version = GameVersion::identify(fs, &known);
+        version.store(&mut config);
+        if let Err(err) = config.save(config_path) {
+            warn!("Failed to save config: {}", err);
+        }
+        version
+    }
+
+    /// Version stored in the config
+    fn from_config(config: &Config) -> Option<GameVersion> {
+        Some(GameVersion {
+            fingerprint: config.data_fingerprint.clone(),
+            platform: parse_platform(config.data_platform.as_deref()?).ok()?,
+            region: config
+                .data_region
+                .as_deref()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_default(),
+            release: config.data_release.clone(),
+        })
+    }
+
+    fn store(&self, config: &mut Config) {
+        config.data_fingerprint = self.fingerprint.clone();
+        config.data_platform = Some(platform_name(self.platform).to_string());
+        config.data_region = Some(self.region.to_string());
+        config.data_release = self.release.clone();
+    }
+
+    pub fn quirks(&self) -> ParseQuirks {
+        ParseQuirks {
+            // Unknown data is treated as GameCube data since that's the
+            // only layout the viewer reads
+            big_endian_meshes: self
+                .platform
+                .map_or(true, |platform| platform.endian() == Endian::Big),
+        }
+    }
+}
+
+/// Hashes the key files present in the game data, the path of each file is
+/// included so data with the same file under another name differs
+pub fn fingerprint(fs: &GameFs) -> Option<String> {
+    let mut hasher = blake3::Hasher::new();
+    let mut any = false;
+
+    for path in KEY_FILES {
+        let Ok(hash) = fs.hash(path) else {
+            continue;
+        };
+        hasher.update(path.as_bytes());
+        hasher.update(hash.as_bytes());
+        any = true;
+    }
+
+    any.then(|| hasher.finalize().to_hex().to_string())
+}
+
+/// Guesses the platform from the system files in the game data
+fn guess_platform(fs: &GameFs) -> Option<Platform> {
+    if fs.contains("opening.bnr") || fs.contains("main.dol") {
+        Some(Platform::GameCube)
+    } else if fs.contains("default.xbe") {
+        Some(Platform::Xbox)
+    } else if fs
+        .files()
+        .iter()
+        .any(|path| !path.contains('/') && path.to_ascii_lowercase().ends_with(".exe"))
+    {
+        Some(Platform::Pc)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use openglitch_formats::platform::Platform;
+
+    use super::{parse_versions, GameVersion, Region, VersionError};
+
+    #[test]
+    fn test_parse_versions() {
+        let versions = parse_versions("# known\nABCD gamecube pal Release 1.0\n").unwrap();
+        assert_eq!(versions[0].fingerprint, "abcd");
+        assert_eq!(versions[0].platform, Some(Platform::GameCube));
+        assert_eq!(versions[0].region, Region::Pal);
+        assert_eq!(versions[0].name, "Release 1.0");
+
+        assert!(matches!(
+            parse_versions("abcd wii pal Release"),
+            Err(VersionError::UnknownPlatform(1, _))
+        ));
+        assert!(parse_versions("abcd gamecube").is_err());
+    }
+
+    #[test]
+    fn test_quirks() {
+        let version = GameVersion {
+            platform: Some(Platform::Xbox),
+            ..Default::default()
+        };
+        assert!(!version.quirks().big_endian_meshes);
+        assert!(GameVersion::default().quirks().big_endian_meshes);
+    }
+}
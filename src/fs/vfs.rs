@@ -0,0 +1,143 @@
+//! Asset source reading through the [GameFs], so assets can be loaded from
+//! any of the mounted sources with paths such as `vfs://ape/gcdggltch00.ape`
+//! whether the file is loose, on a disc image or packed in an archive.
+//! Loose files are watched for changes so assets re-exported by modding
+//! tools are reloaded while the viewer is running
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{
+    asset::io::{
+        file::FileWatcher, AssetReader, AssetReaderError, AssetSourceEvent, AssetWatcher,
+        PathStream, Reader, VecReader,
+    },
+    log::warn,
+    utils::BoxedFuture,
+};
+use crossbeam_channel::Sender;
+
+use super::{normalize_path, FsError, GameFs};
+
+/// Asset reader over the files of the game filesystem
+pub struct VfsReader {
+    fs: GameFs,
+}
+
+impl VfsReader {
+    pub fn new(fs: GameFs) -> Self {
+        Self { fs }
+    }
+
+    /// Normalized path of the directory with a trailing separator
+    fn directory_prefix(path: &Path) -> String {
+        let mut prefix = normalize_path(&path.to_string_lossy());
+        if !prefix.is_empty() && !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+        prefix
+    }
+
+    /// Files within the directory, including the files of nested directories
+    fn directory_files(&self, path: &Path) -> Vec<PathBuf> {
+        let prefix = Self::directory_prefix(path);
+        self.fs
+            .files()
+            .into_iter()
+            .filter(|file| normalize_path(file).starts_with(&prefix))
+            .map(PathBuf::from)
+            .collect()
+    }
+}
+
+impl AssetReader for VfsReader {
+    fn read<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<Reader<'a>>, AssetReaderError>> {
+        Box::pin(async move {
+            let name = path.to_string_lossy();
+            match self.fs.read(&name) {
+                Ok(bytes) => {
+                    let reader: Box<Reader> = Box::new(VecReader::new(bytes));
+                    Ok(reader)
+                }
+                Err(FsError::NotFound(_)) => Err(AssetReaderError::NotFound(path.to_path_buf())),
+                Err(FsError::Io(err)) => Err(AssetReaderError::Io(err)),
+                Err(err) => Err(AssetReaderError::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    err.to_string(),
+                ))),
+            }
+        })
+    }
+
+    fn read_meta<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<Reader<'a>>, AssetReaderError>> {
+        // The game data has no asset meta files
+        Box::pin(async move { Err(AssetReaderError::NotFound(path.to_path_buf())) })
+    }
+
+    fn read_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<PathStream>, AssetReaderError>> {
+        Box::pin(async move {
+            let files = self.directory_files(path);
+            if files.is_empty() {
+                return Err(AssetReaderError::NotFound(path.to_path_buf()));
+            }
+            let stream: Box<PathStream> = Box::new(futures::stream::iter(files));
+            Ok(stream)
+        })
+    }
+
+    fn is_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<bool, AssetReaderError>> {
+        Box::pin(async move { Ok(!self.directory_files(path).is_empty()) })
+    }
+}
+
+/// Watcher over the directories mounted in the [GameFs], changes are
+/// reported with paths relative to their directory which match the paths
+/// the files are loaded through
+pub struct VfsWatcher {
+    _watchers: Vec<FileWatcher>,
+}
+
+impl VfsWatcher {
+    /// Watches each of the directories, none when none of them can be
+    /// watched
+    pub fn new(
+        roots: &[PathBuf],
+        sender: Sender<AssetSourceEvent>,
+        debounce: Duration,
+    ) -> Option<Box<dyn AssetWatcher>> {
+        let watchers: Vec<FileWatcher> = roots
+            .iter()
+            .filter_map(|root| {
+                // Changes are reported with absolute paths which are made
+                // relative to the root
+                let root = root.canonicalize().ok()?;
+                FileWatcher::new(root.clone(), sender.clone(), debounce)
+                    .map_err(|err| warn!("Failed to watch {}: {}", root.display(), err))
+                    .ok()
+            })
+            .collect();
+
+        if watchers.is_empty() {
+            return None;
+        }
+        Some(Box::new(VfsWatcher {
+            _watchers: watchers,
+        }))
+    }
+}
+
+impl AssetWatcher for VfsWatcher {}
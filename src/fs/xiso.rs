@@ -0,0 +1,135 @@
+//! Xbox disc image filesystem (XDVDFS)
+
+use std::io::{Read, Seek};
+
+use super::{
+    disc::{read_at, DiscEntry},
+    FsError,
+};
+
+const SECTOR_SIZE: u64 = 2048;
+/// Sector of the volume descriptor relative to the game partition
+const VOLUME_SECTOR: u64 = 32;
+const MAGIC: &[u8; 20] = b"MICROSOFT*XBOX*MEDIA";
+/// Offsets of the game partition within the image, extracted images
+/// start at the partition while full disc dumps contain video partitions
+const PARTITION_OFFSETS: [u64; 4] = [0, 0x1830_0000, 0x0FD9_0000, 0x89D8_0000];
+/// Size of a directory entry excluding the name
+const ENTRY_HEADER_SIZE: usize = 14;
+/// Left subtree offset marking padding to the end of the sector
+const ENTRY_PADDING: u16 = 0xFFFF;
+const ATTRIBUTE_DIRECTORY: u8 = 0x10;
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+/// Finds the offset of the game partition within the image
+pub fn find_partition<R: Read + Seek>(reader: &mut R) -> Result<Option<u64>, FsError> {
+    for partition in PARTITION_OFFSETS {
+        let Ok(magic) = read_at(reader, partition + VOLUME_SECTOR * SECTOR_SIZE, MAGIC.len())
+        else {
+            continue;
+        };
+
+        if magic == MAGIC {
+            return Ok(Some(partition));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Reads all the file entries from the directory tables
+pub fn read_entries<R: Read + Seek>(
+    reader: &mut R,
+    partition: u64,
+) -> Result<Vec<(String, DiscEntry)>, FsError> {
+    let volume = read_at(
+        reader,
+        partition + VOLUME_SECTOR * SECTOR_SIZE + MAGIC.len() as u64,
+        8,
+    )?;
+
+    let mut out = Vec::new();
+    // Directory tables waiting to be read as (sector, size, path)
+    let mut directories = vec![(read_u32(&volume, 0), read_u32(&volume, 4), String::new())];
+
+    while let Some((sector, size, parent)) = directories.pop() {
+        if size == 0 {
+            continue;
+        }
+
+        let table = read_at(
+            reader,
+            partition + sector as u64 * SECTOR_SIZE,
+            size as usize,
+        )?;
+
+        // Entries form a binary tree, offsets are in 4 byte units
+        let mut pending = vec![0usize];
+        // Bounds the walk so a corrupt tree can't loop forever
+        let mut remaining = table.len() / ENTRY_HEADER_SIZE + 1;
+
+        while let Some(offset) = pending.pop() {
+            remaining = remaining
+                .checked_sub(1)
+                .ok_or(FsError::InvalidImage("directory tree loops"))?;
+
+            let start = offset * 4;
+            let header = table
+                .get(start..start + ENTRY_HEADER_SIZE)
+                .ok_or(FsError::InvalidImage("directory entry out of bounds"))?;
+
+            let left = read_u16(header, 0);
+            if left == ENTRY_PADDING {
+                continue;
+            }
+            let right = read_u16(header, 2);
+            let entry_sector = read_u32(header, 4);
+            let entry_size = read_u32(header, 8);
+            let attributes = header[12];
+            let name_length = header[13] as usize;
+
+            let name = table
+                .get(start + ENTRY_HEADER_SIZE..start + ENTRY_HEADER_SIZE + name_length)
+                .ok_or(FsError::InvalidImage("directory entry name out of bounds"))?;
+            let name = String::from_utf8_lossy(name);
+            let path = if parent.is_empty() {
+                name.into_owned()
+            } else {
+                format!("{parent}/{name}")
+            };
+
+            if left != 0 {
+                pending.push(left as usize);
+            }
+            if right != 0 {
+                pending.push(right as usize);
+            }
+
+            if attributes & ATTRIBUTE_DIRECTORY != 0 {
+                directories.push((entry_sector, entry_size, path));
+            } else {
+                out.push((
+                    path,
+                    DiscEntry {
+                        offset: partition + entry_sector as u64 * SECTOR_SIZE,
+                        size: entry_size as u64,
+                    },
+                ));
+            }
+        }
+    }
+
+    Ok(out)
+}
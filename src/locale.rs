@@ -0,0 +1,132 @@
+//! Translatable strings for the viewer UI, format and debug output is
+//! intentionally left untranslated
+
+use std::{collections::HashMap, fmt::Display, path::Path};
+
+use bevy::{ecs::system::Resource, log::warn};
+
+/// Directory containing the translation tables
+pub const LOCALE_DIR: &str = "assets/locale";
+/// Language used when no language is selected or a key is missing
+pub const DEFAULT_LANGUAGE: &str = "en";
+/// Environment variable overriding the selected language
+const LANGUAGE_ENV: &str = "OPENMA_LANG";
+
+/// Built-in English table used as the fallback for missing keys
+const DEFAULT_TABLE: &str = include_str!("../assets/locale/en.ini");
+
+/// Table of UI strings for the selected language
+#[derive(Debug, Resource)]
+pub struct Locale {
+    language: String,
+    strings: HashMap<String, String>,
+    fallback: HashMap<String, String>,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self {
+            language: DEFAULT_LANGUAGE.to_string(),
+            strings: HashMap::new(),
+            fallback: parse_table(DEFAULT_TABLE).expect("Invalid default locale table"),
+        }
+    }
+}
+
+impl Locale {
+    /// Loads the table for the language selected through the environment,
+    /// falling back to English when the table is missing
+    pub fn from_env() -> Locale {
+        let language = std::env::var(LANGUAGE_ENV)
+            .or_else(|_| std::env::var("LANG"))
+            .ok()
+            .and_then(|value| language_code(&value))
+            .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string());
+
+        Self::load(Path::new(LOCALE_DIR), &language)
+    }
+
+    /// Loads the table for the provided language from the locale directory
+    pub fn load(dir: &Path, language: &str) -> Locale {
+        let mut locale = Locale::default();
+        if language == DEFAULT_LANGUAGE {
+            return locale;
+        }
+
+        let path = dir.join(format!("{language}.ini"));
+        let strings = match std::fs::read_to_string(&path) {
+            Ok(value) => parse_table(&value).map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+
+        match strings {
+            Ok(strings) => {
+                locale.language = language.to_string();
+                locale.strings = strings;
+            }
+            Err(err) => {
+                warn!("Failed to load translation table {:?}: {}", path, err);
+            }
+        }
+
+        locale
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Gets the string for the provided key, missing keys are returned as is
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings
+            .get(key)
+            .or_else(|| self.fallback.get(key))
+            .map(|value| value.as_str())
+            .unwrap_or(key)
+    }
+
+    /// Gets the string for the provided key replacing the `{n}` placeholders
+    /// with the provided arguments
+    pub fn format(&self, key: &str, args: &[&dyn Display]) -> String {
+        let mut out = self.get(key).to_string();
+        for (index, arg) in args.iter().enumerate() {
+            out = out.replace(&format!("{{{index}}}"), &arg.to_string());
+        }
+        out
+    }
+}
+
+/// Extracts the language code from a locale name (e.g. "fr_FR.UTF-8" -> "fr")
+fn language_code(value: &str) -> Option<String> {
+    let code = value
+        .split(['_', '.', '-'])
+        .next()
+        .filter(|code| !code.is_empty() && *code != "C" && *code != "POSIX")?;
+    Some(code.to_ascii_lowercase())
+}
+
+/// Parses a table of `key=value` entries
+fn parse_table(value: &str) -> Result<HashMap<String, String>, serde_ini::de::Error> {
+    serde_ini::from_str(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{language_code, parse_table, Locale};
+
+    #[test]
+    fn test_format_with_fallback() {
+        let mut locale = Locale::default();
+        locale.strings = parse_table("lod.unnamed=maillage").unwrap();
+
+        assert_eq!(locale.get("lod.unnamed"), "maillage");
+        assert_eq!(locale.format("window.title", &[&"0.1.0"]), "OpenMA v0.1.0");
+        assert_eq!(locale.get("missing.key"), "missing.key");
+    }
+
+    #[test]
+    fn test_language_code() {
+        assert_eq!(language_code("fr_FR.UTF-8").as_deref(), Some("fr"));
+        assert_eq!(language_code("C"), None);
+    }
+}
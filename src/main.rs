@@ -1,126 +1,204 @@
-use std::{
-    fs::{read_to_string, File},
-    os::windows::fs::MetadataExt,
-};
-
 use crate::constants::{WINDOW_DEFAULT_HEIGHT, WINDOW_DEFAULT_WIDTH};
 use bevy::{
     log::{Level, LogPlugin},
     prelude::*,
-    render::render_resource::PrimitiveTopology,
     window::{WindowResolution, WindowTheme},
 };
 use bevy_flycam::prelude::*;
 use bevy_framepace::{FramepacePlugin, FramepaceSettings};
 use binrw::BinRead;
-use components::video::{VideoPlayer, VideoPlugin, VideoResource};
+#[cfg(feature = "ffmpeg")]
+use components::audio::{FAudioPlugin, FAudioSource, VideoSoundtrack};
+use components::{
+    ambience::AmbiencePlugin,
+    ape::{ApeInstance, ApePlugin},
+    asset_tracking::AssetTrackingPlugin,
+    backfaces::BackfacePlugin,
+    cull_distance::CullDistancePlugin,
+    decals::{BlobShadow, DecalPlugin},
+    heatmap::HeatmapPlugin,
+    hex_view::HexViewPlugin,
+    inspector::InspectorPlugin,
+    instancing::InstancingPlugin,
+    level::LevelPlugin,
+    load_log::LoadLogPlugin,
+    lod::LodPlugin,
+    lod_rings::LodRingsPlugin,
+    material_culling::MaterialCullingPlugin,
+    mesh_lights::MeshLightPlugin,
+    motif::MotifPlugin,
+    parts::PartVisibilityPlugin,
+    perf_hud::PerfHudPlugin,
+    play_mode::PlayModePlugin,
+    replay::ReplayPlugin,
+    selection::{Selectable, SelectionPlugin},
+    skeleton::SkeletonPlugin,
+    skybox::SkyboxPlugin,
+    sound_events::SoundEventPlugin,
+    splines::SplineDebugPlugin,
+    texture_animation::TextureAnimationPlugin,
+    texture_filtering::TextureFilteringPlugin,
+    texture_streaming::TextureStreamingPlugin,
+    timeline::TimelinePlugin,
+    validation::ValidationPlugin,
+    video::{VideoPlayer, VideoPlugin, VideoResource},
+};
 use constants::VERSION;
+use formats::{
+    handler::FormatsPlugin,
+    report::{LoadReport, LoadReports},
+};
+use fs::{register_data_source, register_vfs_source, version::GameVersion, vfs_path, GameFs};
+use locale::Locale;
 
+pub mod cancel;
 pub mod components;
+pub mod config;
 pub mod constants;
+pub mod export;
 pub mod formats;
+pub mod fs;
+pub mod locale;
 
 fn main() {
-    App::new()
-        .add_plugins(
-            DefaultPlugins
-                .build()
-                // Custom window settings
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        title: format!("OpenMA v{}", VERSION),
-                        resolution: WindowResolution::new(
-                            WINDOW_DEFAULT_WIDTH,
-                            WINDOW_DEFAULT_HEIGHT,
-                        ),
-                        window_theme: Some(WindowTheme::Dark),
-                        ..Default::default()
-                    }),
+    let game_fs = GameFs::from_args().expect("Failed to mount game data");
+    let version = GameVersion::load_or_identify(&game_fs);
+    let locale = Locale::from_env();
+
+    let mut app = App::new();
+    register_data_source(&mut app);
+    register_vfs_source(&mut app, &game_fs);
+
+    app.add_plugins(
+        DefaultPlugins
+            .build()
+            // Custom window settings
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: locale.format("window.title", &[&VERSION]),
+                    resolution: WindowResolution::new(WINDOW_DEFAULT_WIDTH, WINDOW_DEFAULT_HEIGHT),
+                    window_theme: Some(WindowTheme::Dark),
                     ..Default::default()
-                })
-                // Update logging
-                .set(LogPlugin {
-                    level: Level::DEBUG,
-                    filter: "wgpu=error,naga=warn,open_ma=debug,bevy_app=warn,bevy_render=warn"
-                        .to_string(),
                 }),
-        )
-        .add_plugins(VideoPlugin)
+                ..Default::default()
+            })
+            // Update logging
+            .set(LogPlugin {
+                level: Level::DEBUG,
+                filter: "wgpu=error,naga=warn,open_ma=debug,bevy_app=warn,bevy_render=warn"
+                    .to_string(),
+            }),
+    )
+    .insert_resource(game_fs)
+    .insert_resource(locale)
+    .insert_resource(version)
+    .add_plugins(FormatsPlugin)
+    .add_plugins(VideoPlugin);
+
+    // Soundtracks are decoded through ffmpeg
+    #[cfg(feature = "ffmpeg")]
+    app.add_plugins(FAudioPlugin);
+
+    app.add_plugins(BackfacePlugin)
+        .add_plugins(SelectionPlugin)
+        .add_plugins(LodRingsPlugin)
+        .add_plugins(LodPlugin)
+        .add_plugins(LoadLogPlugin)
+        .add_plugins(TimelinePlugin)
+        .add_plugins(AssetTrackingPlugin)
+        .add_plugins(HexViewPlugin)
+        .add_plugins(DecalPlugin)
+        .add_plugins(PartVisibilityPlugin)
+        .add_plugins(MaterialCullingPlugin)
+        .add_plugins(PerfHudPlugin)
+        .add_plugins(TextureFilteringPlugin)
+        .add_plugins(TextureStreamingPlugin)
+        .add_plugins(SkyboxPlugin)
+        .add_plugins(SoundEventPlugin)
+        .add_plugins(AmbiencePlugin)
+        .add_plugins(ValidationPlugin)
+        .add_plugins(SkeletonPlugin)
+        .add_plugins(ApePlugin)
+        .add_plugins(LevelPlugin)
+        .add_plugins(InstancingPlugin)
+        .add_plugins(CullDistancePlugin)
+        .add_plugins(PlayModePlugin)
+        .add_plugins(HeatmapPlugin)
+        .add_plugins(ReplayPlugin)
+        .add_plugins(SplineDebugPlugin)
+        .add_plugins(MotifPlugin)
+        .add_plugins(TextureAnimationPlugin)
+        .add_plugins(MeshLightPlugin)
+        .add_plugins(InspectorPlugin)
         // .add_systems(Startup, init_startup_movie)
         .add_systems(Startup, init_startup_mesh_test)
         .add_plugins(PlayerPlugin)
         .run();
 }
 
-fn init_startup_mesh_test(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
-    let mut buffer = read_to_string("data/buffer_dump.txt").unwrap();
-    let values: Vec<[f32; 3]> = buffer
-        .lines()
-        .filter_map(|line| {
-            let mut split = line.splitn(3, ',');
-            let a = split.next()?;
-            let b = split.next()?;
-            let c = split.next()?;
-
-            let a: f32 = a.parse().ok()?;
-            let b: f32 = b.parse().ok()?;
-            let c: f32 = c.parse().ok()?;
-
-            Some((a * 2.0, b * 2.0, c * 2.0))
-        })
-        .map(|(a, b, c)| [a, b, c])
-        .collect();
-
-    let mut buffer = read_to_string("data/buffer_dump_index.txt").unwrap();
-    let indicies: Vec<u16> = buffer
-        .lines()
-        .filter_map(|line| {
-            let a: u16 = line.trim().parse().ok()?;
-
-            Some(a)
-        })
-        .collect();
-
-    let mut mesh = Mesh::new(PrimitiveTopology::TriangleStrip)
-        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, values)
-        .with_indices(Some(bevy::render::mesh::Indices::U16(indicies)));
-
-    // let mut file = File::open("data/ape/gcdggltch00.ape").unwrap();
-    // let mut header: FMesh = FMesh::read(&mut file).unwrap();
-    // println!("Length: {}", file.metadata().unwrap().file_size());
-    // // dbg!(&header);
-    // let mut mesh_data = (header.mesh_data.value.take()).unwrap();
-    // let vb = mesh_data
-    //     .vertex_buffers
-    //     .value
-    //     .take()
-    //     .unwrap()
-    //     .pop()
-    //     .unwrap();
-    // let mesh = create_bevy_mesh(vb);
-    let handle = meshes.add(mesh);
+/// Mesh spawned at startup for testing the mesh loaders
+const MESH_TEST_FILE: &str = "ape/gcdggltch00.ape";
 
-    // Render the mesh with the custom texture using a PbrBundle, add the marker.
-    commands.spawn((PbrBundle {
-        mesh: handle,
-
-        ..default()
-    },));
+fn init_startup_mesh_test(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game_fs: Res<GameFs>,
+    mut reports: ResMut<LoadReports>,
+) {
+    if !game_fs.contains(MESH_TEST_FILE) {
+        let mut report = LoadReport::new(MESH_TEST_FILE);
+        report.error("mesh is missing from the game data");
+        reports.add(report);
+        return;
+    }
+
+    commands.spawn((
+        SpatialBundle::default(),
+        ApeInstance(asset_server.load(vfs_path(MESH_TEST_FILE))),
+        Selectable,
+        BlobShadow { radius: 1.0 },
+    ));
 }
 
-/// Plays the startup movie
+/// Plays the startup movie along with its paired audio, movies without
+/// paired audio play the audio stored in the movie
 fn init_startup_movie(
     mut commands: Commands,
     images: ResMut<Assets<Image>>,
     mut video_resource: NonSendMut<VideoResource>,
-    asset_server: Res<AssetServer>,
+    game_fs: Res<GameFs>,
+    mut reports: ResMut<LoadReports>,
+    #[cfg(feature = "ffmpeg")] mut audio_sources: ResMut<Assets<FAudioSource>>,
 ) {
-    const INTRO_MOVIE_FILE: &str = "data/Movies/xb_intro$.bik";
-
-    let (video_player, video_player_non_send) =
-        VideoPlayer::new(INTRO_MOVIE_FILE, true, images).unwrap();
+    const INTRO_MOVIE_FILE: &str = "Movies/xb_intro$.bik";
+
+    let mut report = LoadReport::new(INTRO_MOVIE_FILE);
+    let movies = game_fs.movies();
+    let Some(movie) = movies.get(INTRO_MOVIE_FILE) else {
+        report.error("movie is missing from the game data");
+        reports.add(report);
+        return;
+    };
+
+    let movie_path = match game_fs.local_path(&movie.movie) {
+        Ok(value) => value,
+        Err(err) => {
+            report.error(err.to_string());
+            reports.add(report);
+            return;
+        }
+    };
+    let (video_player, video_player_non_send) = match VideoPlayer::new(&movie_path, true, images) {
+        Ok(value) => value,
+        Err(err) => {
+            report.error(err.to_string());
+            reports.add(report);
+            return;
+        }
+    };
 
     commands.spawn(Camera2dBundle::default());
+    let mut player = Entity::PLACEHOLDER;
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -147,10 +225,30 @@ fn init_startup_movie(
                 .insert(video_player)
                 .id();
             video_resource.data.insert(entity, video_player_non_send);
+            player = entity;
         });
 
-    commands.spawn(AudioBundle {
-        source: asset_server.load("../data/Movies/xb_intro$.wav"),
-        ..Default::default()
-    });
+    #[cfg(feature = "ffmpeg")]
+    {
+        let audio_path = match movie
+            .audio
+            .as_deref()
+            .map(|audio| game_fs.local_path(audio))
+        {
+            Some(Ok(value)) => value,
+            Some(Err(err)) => {
+                report.warn(format!("paired audio: {}", err));
+                movie_path
+            }
+            None => movie_path,
+        };
+        commands.spawn((
+            AudioSourceBundle {
+                source: audio_sources.add(FAudioSource::new(audio_path)),
+                settings: PlaybackSettings::ONCE,
+            },
+            VideoSoundtrack::new(player),
+        ));
+    }
+    reports.add(report);
 }